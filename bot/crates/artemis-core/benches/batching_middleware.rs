@@ -0,0 +1,37 @@
+//! Latency of a burst of concurrent state reads on a slow provider, sent one by one and through
+//! the batching middleware in a few round trips.
+//!
+//! Run with `cargo bench -p artemis-core`, criterion compares each run with the last one saved
+//! under `target/criterion` and flags regressions.
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::time::Duration;
+
+use artemis_core::utilities::batching_middleware::BatchingMiddleware;
+use criterion::{criterion_group, criterion_main, Criterion};
+use tokio::runtime::Runtime;
+
+use common::SlowState;
+
+fn batching_middleware(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let state = rt.block_on(SlowState::spawn());
+    let direct = BatchingMiddleware::new(state.provider.clone());
+    let batching =
+        BatchingMiddleware::new(state.provider.clone()).with_window(Duration::from_millis(5));
+
+    // every read waits on the provider's latency, a few samples are enough
+    let mut group = c.benchmark_group("batching_middleware");
+    group.sample_size(10);
+    group.bench_function("one_by_one", |b| {
+        b.to_async(&rt).iter(|| state.read(&direct))
+    });
+    group.bench_function("batched", |b| {
+        b.to_async(&rt).iter(|| state.read(&batching))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, batching_middleware);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio_stream::StreamExt;
+
+use crate::types::{Collector, CollectorStream};
+use anyhow::Result;
+
+/// A collector that wraps another collector and groups the events it emits within a short
+/// window into a single [batch](Vec) event, so bursts of events can be handled together.
+pub struct BatchingCollector<E> {
+    collector: Box<dyn Collector<E>>,
+    window: Duration,
+    max_batch_size: usize,
+}
+
+impl<E> BatchingCollector<E> {
+    /// A batch is emitted once `max_batch_size` events are collected, or `window` after its
+    /// first event arrived.
+    pub fn new(collector: Box<dyn Collector<E>>, window: Duration, max_batch_size: usize) -> Self {
+        Self {
+            collector,
+            window,
+            max_batch_size: max_batch_size.max(1),
+        }
+    }
+}
+
+/// Implementation of the [Collector](Collector) trait for the
+/// [BatchingCollector](BatchingCollector).
+#[async_trait]
+impl<E> Collector<Vec<E>> for BatchingCollector<E>
+where
+    E: Send + Sync + 'static,
+{
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Vec<E>>> {
+        let stream = self.collector.get_event_stream().await?;
+        let stream = stream.chunks_timeout(self.max_batch_size, self.window);
+        Ok(Box::pin(stream))
+    }
+}
@@ -4,8 +4,9 @@ use async_trait::async_trait;
 use ethers::{
     prelude::Middleware,
     providers::PubsubClient,
-    types::{U256, U64},
+    types::{H256, U256, U64},
 };
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio_stream::StreamExt;
 
@@ -16,9 +17,11 @@ pub struct BlockCollector<M> {
 }
 
 /// A new block event, containing the block number and hash.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewBlock {
     pub number: U64,
+    pub hash: H256,
+    pub parent_hash: H256,
     pub gas_used: U256,
     pub gas_limit: U256,
     pub base_fee_per_gas: U256,
@@ -42,15 +45,17 @@ where
 {
     async fn get_event_stream(&self) -> Result<CollectorStream<'_, NewBlock>> {
         let stream = self.provider.subscribe_blocks().await?;
-        let stream = stream.filter_map(|block| match block.number {
-            Some(number) => Some(NewBlock {
+        let stream = stream.filter_map(|block| match (block.number, block.hash) {
+            (Some(number), Some(hash)) => Some(NewBlock {
                 number,
+                hash,
+                parent_hash: block.parent_hash,
                 gas_limit: block.gas_limit,
                 gas_used: block.gas_used,
                 base_fee_per_gas: block.base_fee_per_gas.unwrap_or_default(),
                 timestamp: block.timestamp,
             }),
-            None => None,
+            _ => None,
         });
         Ok(Box::pin(stream))
     }
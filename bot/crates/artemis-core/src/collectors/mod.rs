@@ -2,6 +2,9 @@
 //! turning them into internal events. For example, a collector might listen to
 //! a stream of new blocks, and turn them into a stream of `NewBlock` events.
 
+/// This collector groups the events of another collector into batches.
+pub mod batching_collector;
+
 /// This collector listens to a stream of new blocks.
 pub mod block_collector;
 
@@ -1,7 +1,9 @@
+use std::time::Duration;
+
 use tokio::sync::broadcast::{self, Sender};
 use tokio::task::JoinSet;
 use tokio_stream::StreamExt;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::types::{Collector, Executor, Strategy};
 
@@ -16,16 +18,33 @@ pub struct Engine<E, A> {
 
     /// The set of executors that the engine will use to execute actions.
     executors: Vec<Box<dyn Executor<A>>>,
+
+    /// How many times in a row a collector may try to resubscribe before giving up.
+    max_collector_retries: u32,
+
+    /// Stops every collector, strategy, and executor once a value is sent.
+    shutdown_sender: Sender<()>,
 }
 
+/// Delay before a collector's first resubscribe attempt (doubles after every failed attempt).
+const COLLECTOR_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
 impl<E, A> Engine<E, A> {
     pub fn new() -> Self {
         Self {
             collectors: vec![],
             strategies: vec![],
             executors: vec![],
+            max_collector_retries: 5,
+            shutdown_sender: broadcast::channel(1).0,
         }
     }
+
+    /// Sets how many times in a row a collector may try to resubscribe after its stream drops.
+    pub fn with_max_collector_retries(mut self, max_collector_retries: u32) -> Self {
+        self.max_collector_retries = max_collector_retries;
+        self
+    }
 }
 
 impl<E, A> Default for Engine<E, A> {
@@ -54,71 +73,148 @@ where
         self.executors.push(executor);
     }
 
+    /// Returns a sender that shuts the engine down: collectors stop, strategies run their
+    /// `shutdown` hook, and every task spawned by `run` finishes.
+    pub fn shutdown_sender(&self) -> Sender<()> {
+        self.shutdown_sender.clone()
+    }
+
     /// The core run loop of the engine. This function will spawn a thread for
     /// each collector, strategy, and executor. It will then orchestrate the
     /// data flow between them.
+    ///
+    /// If a collector's stream drops it is resubscribed (with bounded retries), and every
+    /// strategy resyncs its state once the collector is back.
     pub async fn run(self) -> Result<JoinSet<()>, Box<dyn std::error::Error>> {
         let (event_sender, _): (Sender<E>, _) = broadcast::channel(512);
         let (action_sender, _): (Sender<A>, _) = broadcast::channel(512);
+        let (resync_sender, _): (Sender<()>, _) = broadcast::channel(16);
 
         let mut set = JoinSet::new();
 
         // Spawn executors in separate threads.
         for executor in self.executors {
             let mut receiver = action_sender.subscribe();
+            let mut shutdown_receiver = self.shutdown_sender.subscribe();
             set.spawn(async move {
                 info!("starting executor... ");
                 loop {
-                    match receiver.recv().await {
-                        Ok(action) => match executor.execute(action).await {
-                            Ok(_) => {}
-                            Err(e) => error!("error executing action: {}", e),
+                    tokio::select! {
+                        action = receiver.recv() => match action {
+                            Ok(action) => match executor.execute(action).await {
+                                Ok(_) => {}
+                                Err(e) => error!("error executing action: {}", e),
+                            },
+                            Err(e) => error!("error receiving action: {}", e),
                         },
-                        Err(e) => error!("error receiving action: {}", e),
+                        Ok(()) = shutdown_receiver.recv() => break,
                     }
                 }
+                info!("executor stopped");
             });
         }
 
         // Spawn strategies in separate threads.
         for mut strategy in self.strategies {
             let mut event_receiver = event_sender.subscribe();
+            let mut resync_receiver = resync_sender.subscribe();
+            let mut shutdown_receiver = self.shutdown_sender.subscribe();
             let action_sender = action_sender.clone();
             strategy.sync_state().await?;
 
             set.spawn(async move {
                 info!("starting strategy... ");
                 loop {
-                    match event_receiver.recv().await {
-                        Ok(event) => {
-                            if let Some(action) = strategy.process_event(event).await {
-                                match action_sender.send(action) {
-                                    Ok(_) => {}
-                                    Err(e) => error!("error sending action: {}", e),
+                    tokio::select! {
+                        event = event_receiver.recv() => match event {
+                            Ok(event) => {
+                                if let Some(action) = strategy.process_event(event).await {
+                                    match action_sender.send(action) {
+                                        Ok(_) => {}
+                                        Err(e) => error!("error sending action: {}", e),
+                                    }
                                 }
                             }
+                            Err(e) => error!("error receiving event: {}", e),
+                        },
+                        Ok(()) = resync_receiver.recv() => {
+                            warn!("collector reconnected, resyncing strategy state...");
+                            if let Err(e) = strategy.sync_state().await {
+                                error!("error resyncing strategy: {}", e);
+                            }
+                        }
+                        Ok(()) = shutdown_receiver.recv() => {
+                            if let Err(e) = strategy.shutdown().await {
+                                error!("error shutting down strategy: {}", e);
+                            }
+                            break;
                         }
-                        Err(e) => error!("error receiving event: {}", e),
                     }
                 }
+                info!("strategy stopped");
             });
         }
 
         // Spawn collectors in separate threads.
         for collector in self.collectors {
             let event_sender = event_sender.clone();
+            let resync_sender = resync_sender.clone();
+            let max_retries = self.max_collector_retries;
+            let mut shutdown_receiver = self.shutdown_sender.subscribe();
             set.spawn(async move {
                 info!("starting collector... ");
-                let mut event_stream = collector.get_event_stream().await.unwrap();
-                while let Some(event) = event_stream.next().await {
-                    match event_sender.send(event) {
-                        Ok(_) => {}
-                        Err(e) => error!("error sending event: {}", e),
-                    }
+                tokio::select! {
+                    _ = Self::run_collector(collector, event_sender, resync_sender, max_retries) => {}
+                    Ok(()) = shutdown_receiver.recv() => info!("collector stopped"),
                 }
             });
         }
 
         Ok(set)
     }
+
+    /// Forward a collector's events, resubscribing (with bounded retries) when its stream drops.
+    async fn run_collector(
+        collector: Box<dyn Collector<E>>,
+        event_sender: Sender<E>,
+        resync_sender: Sender<()>,
+        max_retries: u32,
+    ) {
+        let mut retries = 0;
+        let mut reconnecting = false;
+        loop {
+            match collector.get_event_stream().await {
+                Ok(mut event_stream) => {
+                    if reconnecting {
+                        // events may have been missed while we were disconnected
+                        info!("collector resubscribed");
+                        let _ = resync_sender.send(());
+                    }
+                    while let Some(event) = event_stream.next().await {
+                        retries = 0;
+                        match event_sender.send(event) {
+                            Ok(_) => {}
+                            Err(e) => error!("error sending event: {}", e),
+                        }
+                    }
+                    warn!("collector stream ended");
+                }
+                Err(e) => error!("error creating event stream: {}", e),
+            }
+
+            if retries >= max_retries {
+                error!("collector failed to resubscribe after {} attempts", retries);
+                break;
+            }
+
+            let backoff = COLLECTOR_RETRY_BACKOFF * 2u32.pow(retries);
+            retries += 1;
+            reconnecting = true;
+            warn!(
+                "resubscribing collector in {:?} (attempt {}/{})",
+                backoff, retries, max_retries
+            );
+            tokio::time::sleep(backoff).await;
+        }
+    }
 }
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    abi::{encode, Token},
+    providers::Middleware,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, BlockTrace, Diff, TraceType,
+        H256, I256, U256,
+    },
+    utils::{keccak256, rlp::Rlp},
+};
+use ethers_flashbots::{BundleRequest, BundleTransaction};
+
+/// Storage slot of WETH9's `balanceOf` mapping.
+const WETH_BALANCE_SLOT: u64 = 3;
+
+/// Simulates what a bundle earns the searcher, so bundles are only sent while they still pay.
+#[async_trait]
+pub trait BundleProfit: Send + Sync {
+    /// Net weth and eth the searcher gains if the bundle lands on top of its simulation block
+    /// (negative if it loses).
+    async fn simulate_profit(&self, bundle: &BundleRequest) -> Result<I256>;
+}
+
+/// Returns the signer, hash and decoded tx of a bundle tx.
+pub(crate) fn decode_bundle_tx(
+    tx: &BundleTransaction,
+) -> Result<(Address, H256, TypedTransaction)> {
+    let raw = match tx {
+        BundleTransaction::Signed(tx) => tx.rlp(),
+        BundleTransaction::Raw(raw) => raw.clone(),
+    };
+    let (decoded, signature) = TypedTransaction::decode_signed(&Rlp::new(&raw))
+        .map_err(|e| anyhow!("Failed to decode bundle tx: {:?}", e))?;
+    let from = signature
+        .recover(decoded.sighash())
+        .map_err(|e| anyhow!("Failed to recover bundle tx signer: {:?}", e))?;
+    Ok((from, H256::from(keccak256(&raw)), decoded))
+}
+
+/// Net change of the eth and weth balances of `accounts` over the traces of a bundle's txs
+/// (traced in order, each on top of the ones before it).
+pub fn searcher_profit(traces: &[BlockTrace], weth: Address, accounts: &[Address]) -> I256 {
+    fn change<T: Copy>(diff: &Diff<T>, value: impl Fn(T) -> U256) -> I256 {
+        match diff {
+            Diff::Same => I256::zero(),
+            Diff::Born(to) => I256::from_raw(value(*to)),
+            Diff::Died(from) => -I256::from_raw(value(*from)),
+            Diff::Changed(changed) => {
+                I256::from_raw(value(changed.to)) - I256::from_raw(value(changed.from))
+            }
+        }
+    }
+    let word = |word: H256| U256::from(word.to_fixed_bytes());
+
+    let balance_slots: Vec<H256> = accounts
+        .iter()
+        .map(|account| {
+            H256::from(keccak256(encode(&[
+                Token::Address(*account),
+                Token::Uint(U256::from(WETH_BALANCE_SLOT)),
+            ])))
+        })
+        .collect();
+
+    let mut profit = I256::zero();
+    for diffs in traces.iter().filter_map(|trace| trace.state_diff.as_ref()) {
+        for account in accounts {
+            if let Some(diff) = diffs.0.get(account) {
+                profit += change(&diff.balance, |balance| balance);
+            }
+        }
+
+        let weth_storage = match diffs.0.get(&weth) {
+            Some(diff) => &diff.storage,
+            None => continue,
+        };
+        for diff in balance_slots
+            .iter()
+            .filter_map(|slot| weth_storage.get(slot))
+        {
+            profit += change(diff, word);
+        }
+    }
+
+    profit
+}
+
+/// Traces a bundle's txs in order with `trace_callMany` on a node and sums the searcher's weth
+/// and eth balance changes.
+pub struct TracedBundleProfit<M> {
+    client: Arc<M>,
+    weth: Address,
+    /// Searcher EOAs (which pay the gas) and sando contracts (which hold the weth).
+    accounts: Vec<Address>,
+}
+
+impl<M: Middleware> TracedBundleProfit<M> {
+    pub fn new(client: Arc<M>, weth: Address, accounts: Vec<Address>) -> Self {
+        Self {
+            client,
+            weth,
+            accounts,
+        }
+    }
+}
+
+#[async_trait]
+impl<M> BundleProfit for TracedBundleProfit<M>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+{
+    async fn simulate_profit(&self, bundle: &BundleRequest) -> Result<I256> {
+        let calls = bundle
+            .transactions()
+            .iter()
+            .map(|tx| {
+                let (from, _, mut tx) = decode_bundle_tx(tx)?;
+                tx.set_from(from);
+                Ok((tx, vec![TraceType::StateDiff]))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let traces = self
+            .client
+            .trace_call_many(calls, bundle.simulation_block().map(BlockNumber::Number))
+            .await
+            .map_err(|e| anyhow!("Failed to trace bundle: {:?}", e))?;
+        Ok(searcher_profit(&traces, self.weth, &self.accounts))
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{providers::Middleware, signers::Signer, utils::hex};
+use ethers_flashbots::{
+    BundleHash, BundleRequest, BundleTransaction, FlashbotsMiddleware, SimulatedBundle,
+};
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Client, Url,
+};
+use serde_json::{json, Value};
+
+/// A builder endpoint that accepts bundles. Each implementation takes care of its builder's
+/// request format and authentication.
+#[async_trait]
+pub trait BundleRelay: Send + Sync {
+    /// Name used in logs (usually the endpoint url).
+    fn name(&self) -> String;
+
+    /// Send a bundle for inclusion in its target block, returns the bundle hash if the relay
+    /// reports one.
+    async fn send_bundle(&self, bundle: &BundleRequest) -> Result<Option<BundleHash>>;
+
+    /// Simulate a bundle on top of its simulation block without sending it.
+    async fn call_bundle(&self, bundle: &BundleRequest) -> Result<SimulatedBundle>;
+}
+
+/// A relay speaking the Flashbots bundle api (`eth_sendBundle` / `eth_callBundle`) with
+/// requests signed in the `X-Flashbots-Signature` header. Besides Flashbots this covers
+/// builders accepting the same api such as Titan, beaverbuild and rsync.
+pub struct FlashbotsRelay<M, S> {
+    url: Url,
+    client: FlashbotsMiddleware<Arc<M>, S>,
+}
+
+impl<M: Middleware, S: Signer> FlashbotsRelay<M, S> {
+    /// `relay_signer` is the searcher identity builders use to score our bundles, it should be
+    /// separate from the key that signs bundle transactions.
+    pub fn new(client: Arc<M>, url: Url, relay_signer: S) -> Self {
+        Self {
+            client: FlashbotsMiddleware::new(client, url.clone(), relay_signer),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl<M, S> BundleRelay for FlashbotsRelay<M, S>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    fn name(&self) -> String {
+        self.url.to_string()
+    }
+
+    async fn send_bundle(&self, bundle: &BundleRequest) -> Result<Option<BundleHash>> {
+        let pending_bundle = self
+            .client
+            .send_bundle(bundle)
+            .await
+            .map_err(|e| anyhow!("{:?}", e))?;
+        Ok(pending_bundle.bundle_hash)
+    }
+
+    async fn call_bundle(&self, bundle: &BundleRequest) -> Result<SimulatedBundle> {
+        self.client
+            .simulate_bundle(bundle)
+            .await
+            .map_err(|e| anyhow!("{:?}", e))
+    }
+}
+
+/// The bloXroute bundle api (`blxr_submit_bundle` / `blxr_simulate_bundle`), authenticated with
+/// the account's auth header. Bundles are forwarded to every builder bloXroute works with.
+pub struct BloxrouteRelay {
+    client: Client,
+    url: Url,
+    /// Value of the `Authorization` header from the bloXroute account portal.
+    auth_header: String,
+}
+
+impl BloxrouteRelay {
+    pub fn new(url: Url, auth_header: impl Into<String>) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            auth_header: auth_header.into(),
+        }
+    }
+
+    /// Send a json rpc request, returns its `result`.
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .header(AUTHORIZATION, &self.auth_header)
+            .body(body.to_string())
+            .send()
+            .await?
+            .text()
+            .await?;
+
+        let response: Value = serde_json::from_str(&response)?;
+        match response.get("error") {
+            Some(rpc_error) => Err(anyhow!("Relay returned error: {}", rpc_error)),
+            None => Ok(response["result"].clone()),
+        }
+    }
+}
+
+/// bloXroute takes raw txs as hex without the `0x` prefix.
+fn bloxroute_bundle_params(bundle: &BundleRequest) -> Result<Value> {
+    let block = bundle
+        .block()
+        .ok_or_else(|| anyhow!("Bundle has no target block"))?;
+    let transactions: Vec<String> = bundle
+        .transactions()
+        .iter()
+        .map(|tx| match tx {
+            BundleTransaction::Signed(tx) => hex::encode(tx.rlp()),
+            BundleTransaction::Raw(raw) => hex::encode(raw),
+        })
+        .collect();
+
+    Ok(json!({
+        "transaction": transactions,
+        "block_number": format!("{:#x}", block.as_u64()),
+        "mev_builders": { "all": "" },
+    }))
+}
+
+#[async_trait]
+impl BundleRelay for BloxrouteRelay {
+    fn name(&self) -> String {
+        self.url.to_string()
+    }
+
+    async fn send_bundle(&self, bundle: &BundleRequest) -> Result<Option<BundleHash>> {
+        let result = self
+            .request("blxr_submit_bundle", bloxroute_bundle_params(bundle)?)
+            .await?;
+        Ok(serde_json::from_value(result["bundleHash"].clone()).ok())
+    }
+
+    async fn call_bundle(&self, bundle: &BundleRequest) -> Result<SimulatedBundle> {
+        let mut params = bloxroute_bundle_params(bundle)?;
+        params["state_block_number"] = match bundle.simulation_block() {
+            Some(block) => json!(format!("{:#x}", block.as_u64())),
+            None => json!("latest"),
+        };
+
+        let result = self.request("blxr_simulate_bundle", params).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+}
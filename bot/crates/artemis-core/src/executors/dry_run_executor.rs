@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::Mutex};
+use tracing::info;
+
+use crate::types::Executor;
+
+/// An executor that never submits anything, it appends every action as a json line to a file.
+/// Useful for paper trading a strategy before running it with real funds.
+pub struct DryRunExecutor {
+    /// File that actions are appended to.
+    output_path: PathBuf,
+    /// Serializes writes so concurrent actions don't interleave lines.
+    write_lock: Mutex<()>,
+}
+
+impl DryRunExecutor {
+    /// Creates an executor that appends actions to `output_path` (created if missing).
+    pub fn new(output_path: impl Into<PathBuf>) -> Self {
+        Self {
+            output_path: output_path.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<A> Executor<A> for DryRunExecutor
+where
+    A: Serialize + Send + Sync + 'static,
+{
+    /// Record the action instead of executing it.
+    async fn execute(&self, action: A) -> Result<()> {
+        let mut line = serde_json::to_string(&action)?;
+        line.push('\n');
+
+        let _guard = self.write_lock.lock().await;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.output_path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+
+        info!("Dry run: recorded action to {}", self.output_path.display());
+        Ok(())
+    }
+}
@@ -1,18 +1,42 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use async_trait::async_trait;
-use ethers::{providers::Middleware, signers::Signer};
-use ethers_flashbots::{BundleRequest, FlashbotsMiddleware};
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{I256, U256},
+};
+use ethers_flashbots::BundleRequest;
+use futures::future::join_all;
 use reqwest::Url;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::types::Executor;
+use crate::{
+    executors::{
+        bundle_profit::BundleProfit,
+        bundle_relay::{BundleRelay, FlashbotsRelay},
+        public_mempool::PublicBroadcaster,
+        relay_stats::RelayStats,
+    },
+    types::Executor,
+};
 
-/// A Flashbots executor that sends transactions to the Flashbots relay.
-pub struct FlashbotsExecutor<M, S> {
-    /// The Flashbots middleware.
-    fb_client: FlashbotsMiddleware<Arc<M>, S>,
+/// A bundle executor that sends bundles to one or more relays (Flashbots style relays and any
+/// other [BundleRelay](BundleRelay)).
+pub struct FlashbotsExecutor {
+    /// Every bundle is sent to all of these.
+    relays: Vec<Box<dyn BundleRelay>>,
+    /// If set, bundles are simulated with `eth_callBundle` on the first relay and only sent if
+    /// every tx succeeds and the simulated profit beats the minimum (in wei).
+    simulation: Option<(U256, Box<dyn BundleProfit>)>,
+    /// How each relay answered our submissions.
+    relay_stats: Arc<RelayStats>,
+    /// If set, bundles that no relay accepted are broadcast as public txs through it.
+    public_fallback: Option<Box<dyn PublicBroadcaster>>,
 }
 
 /// A bundle of transactions to send to the Flashbots relay.
@@ -20,37 +44,186 @@ pub struct FlashbotsExecutor<M, S> {
 /// See issue: https://github.com/paradigmxyz/artemis/issues/34
 pub type FlashbotsBundle = Vec<BundleRequest>;
 
-impl<M: Middleware, S: Signer> FlashbotsExecutor<M, S> {
-    pub fn new(client: Arc<M>, relay_signer: S, relay_url: impl Into<Url>) -> Self {
-        let fb_client = FlashbotsMiddleware::new(client, relay_url, relay_signer);
-        Self { fb_client }
+impl FlashbotsExecutor {
+    /// Creates an executor that fans out every bundle to all `relay_urls` (Flashbots style
+    /// relays). `relay_signer` is the searcher identity used to authenticate with relays and
+    /// should be separate from the key that signs bundle transactions.
+    pub fn new<M, S>(client: Arc<M>, relay_signer: S, relay_urls: Vec<Url>) -> Self
+    where
+        M: Middleware + 'static,
+        M::Error: 'static,
+        S: Signer + Clone + 'static,
+    {
+        let relays = relay_urls
+            .into_iter()
+            .map(|relay_url| {
+                Box::new(FlashbotsRelay::new(
+                    client.clone(),
+                    relay_url,
+                    relay_signer.clone(),
+                )) as Box<dyn BundleRelay>
+            })
+            .collect();
+        Self {
+            relays,
+            simulation: None,
+            relay_stats: Arc::new(RelayStats::new()),
+            public_fallback: None,
+        }
+    }
+
+    /// Also send every bundle to `relay` (e.g. a builder with its own bundle api).
+    pub fn with_relay(mut self, relay: impl BundleRelay + 'static) -> Self {
+        self.relays.push(Box::new(relay));
+        self
+    }
+
+    /// Simulate every bundle before sending it, dropping bundles with a failing tx or that
+    /// `profit` simulates earning the searcher no more than `min_profit` (in wei).
+    pub fn with_simulation(
+        mut self,
+        min_profit: U256,
+        profit: impl BundleProfit + 'static,
+    ) -> Self {
+        self.simulation = Some((min_profit, Box::new(profit)));
+        self
+    }
+
+    /// Broadcast the txs of bundles that no relay accepted through `broadcaster` (see
+    /// [PublicFallback](crate::executors::public_mempool::PublicFallback) for the risks).
+    pub fn with_public_fallback(mut self, broadcaster: impl PublicBroadcaster + 'static) -> Self {
+        warn!("Public mempool fallback enabled, bundles no relay accepts are sent as public txs");
+        self.public_fallback = Some(Box::new(broadcaster));
+        self
+    }
+
+    /// Log a summary line per relay (submissions, accepted, errors, median latency) every
+    /// `interval`.
+    pub fn with_stats_summary(self, interval: Duration) -> Self {
+        self.relay_stats.spawn_summary_logger(interval);
+        self
+    }
+
+    /// Submission stats of every relay since startup.
+    pub fn relay_stats(&self) -> Arc<RelayStats> {
+        self.relay_stats.clone()
+    }
+
+    /// Returns true if the bundle should be sent (always true when simulation is disabled).
+    async fn passes_simulation(&self, bundle: &BundleRequest) -> bool {
+        let ((min_profit, profit), relay) = match (&self.simulation, self.relays.first()) {
+            (Some(simulation), Some(relay)) => (simulation, relay),
+            _ => return true,
+        };
+
+        let simulated_bundle = match relay.call_bundle(bundle).await {
+            Ok(simulated_bundle) => simulated_bundle,
+            Err(simulate_error) => {
+                error!(
+                    "Error simulating bundle on {}: {:?}",
+                    relay.name(),
+                    simulate_error
+                );
+                return false;
+            }
+        };
+
+        info!(
+            "Bundle simulated on {} (block: {:?}, coinbase diff: {})",
+            relay.name(),
+            simulated_bundle.simulation_block,
+            simulated_bundle.coinbase_diff
+        );
+
+        // state may have changed since the strategy simulated locally
+        if let Some(failed_tx) = simulated_bundle
+            .transactions
+            .iter()
+            .find(|tx| tx.error.is_some() || tx.revert.is_some())
+        {
+            warn!(
+                "Dropping bundle, tx {:?} failed in simulation (error: {:?}, revert: {:?})",
+                failed_tx.hash, failed_tx.error, failed_tx.revert
+            );
+            return false;
+        }
+
+        // the coinbase diff includes the victims' fees, what we keep is our balance change
+        let profit = match profit.simulate_profit(bundle).await {
+            Ok(profit) => profit,
+            Err(e) => {
+                error!("Error simulating bundle profit: {:?}", e);
+                return false;
+            }
+        };
+        if profit <= I256::from_raw(*min_profit) {
+            warn!(
+                "Dropping bundle, simulated profit {} is not above {} (coinbase diff: {})",
+                profit, min_profit, simulated_bundle.coinbase_diff
+            );
+            return false;
+        }
+
+        true
+    }
+
+    /// Fall back to broadcasting a bundle that no relay accepted (no-op unless enabled).
+    async fn broadcast_publicly(&self, bundle: &BundleRequest) {
+        let broadcaster = match &self.public_fallback {
+            Some(broadcaster) => broadcaster,
+            None => return,
+        };
+
+        match broadcaster.broadcast(bundle).await {
+            Ok(hashes) if hashes.is_empty() => {}
+            Ok(hashes) => warn!(
+                "No relay accepted bundle for block {:?}, broadcast {:?} publicly",
+                bundle.block(),
+                hashes
+            ),
+            Err(e) => error!("Error broadcasting bundle publicly: {:?}", e),
+        }
     }
 }
 
 #[async_trait]
-impl<M, S> Executor<FlashbotsBundle> for FlashbotsExecutor<M, S>
-where
-    M: Middleware + 'static,
-    M::Error: 'static,
-    S: Signer + 'static,
-{
-    /// Send a bundle to transactions to the Flashbots relay.
+impl Executor<FlashbotsBundle> for FlashbotsExecutor {
+    /// Send bundles of transactions to all relays concurrently.
     async fn execute(&self, action: FlashbotsBundle) -> Result<()> {
         for bundle in action {
-            //// Simulate bundle.
-            //let simulated_bundle = self.fb_client.simulate_bundle(&bundle).await;
+            // Simulate bundle against the target block before sending it.
+            if !self.passes_simulation(&bundle).await {
+                continue;
+            }
 
-            //match simulated_bundle {
-            //    Ok(res) => info!("Simulation Result: {:?}", res),
-            //    Err(simulate_error) => error!("Error simulating bundle: {:?}", simulate_error),
-            //}
+            // Send bundle to every relay, a failing relay should not stop the others.
+            let bundle = &bundle;
+            let sends = self.relays.iter().map(|relay| async move {
+                let sent_at = Instant::now();
+                let bundle_hash = relay.send_bundle(bundle).await;
+                (relay.name(), sent_at.elapsed(), bundle_hash)
+            });
 
-            // Send bundle.
-            let pending_bundle = self.fb_client.send_bundle(&bundle).await;
+            let mut accepted = false;
+            for (relay, latency, bundle_hash) in join_all(sends).await {
+                self.relay_stats
+                    .record(&relay, latency, bundle_hash.is_ok());
+                accepted |= bundle_hash.is_ok();
+                match bundle_hash {
+                    Ok(bundle_hash) => info!(
+                        "Bundle sent to {} (block: {:?}, hash: {:?})",
+                        relay,
+                        bundle.block(),
+                        bundle_hash
+                    ),
+                    Err(send_error) => {
+                        error!("Error sending bundle to {}: {:?}", relay, send_error)
+                    }
+                }
+            }
 
-            match pending_bundle {
-                Ok(res) => info!("Simulation Result: {:?}", res.await),
-                Err(send_error) => error!("Error sending bundle: {:?}", send_error),
+            if !accepted {
+                self.broadcast_publicly(bundle).await;
             }
         }
 
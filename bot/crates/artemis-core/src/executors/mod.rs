@@ -2,5 +2,23 @@
 //! executing them in different domains. For example, an executor might take a
 //! `SubmitTx` action and submit it to the mempool.
 
-/// This executor submits transactions to the flashbots relay.
+/// Simulates what a bundle earns the searcher before it is sent.
+pub mod bundle_profit;
+
+/// Relays (builder endpoints) that bundles are sent to.
+pub mod bundle_relay;
+
+/// This executor records actions to a file instead of executing them.
+pub mod dry_run_executor;
+
+/// This executor submits bundles to the flashbots relay and other builders.
 pub mod flashbots_executor;
+
+/// Broadcasts bundles no relay accepted as public txs (opt-in, frontrunnable).
+pub mod public_mempool;
+
+/// Per relay submission counts and response latencies.
+pub mod relay_stats;
+
+/// This executor sends private transactions (`eth_sendPrivateTransaction`) to relays.
+pub mod private_tx_executor;
@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    signers::Signer,
+    types::{Bytes, H256, U64},
+    utils::keccak256,
+};
+use futures::future::join_all;
+use reqwest::{header::CONTENT_TYPE, Client, Url};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::types::Executor;
+
+/// Signed transactions to send privately with `eth_sendPrivateTransaction`.
+/// Relays forward them straight to builders, they are never gossiped in the public mempool.
+#[derive(Debug, Clone)]
+pub struct PrivateTx {
+    /// Raw signed transactions, sent one after the other in this order (e.g. by nonce). A tx is
+    /// only sent once a relay accepted the one before it.
+    pub txs: Vec<Bytes>,
+    /// Relays stop trying to include the txs after this block.
+    pub max_block_number: U64,
+}
+
+/// An executor that sends private transactions to one or more Flashbots style relays.
+pub struct PrivateTxExecutor<S> {
+    client: Client,
+    /// Searcher identity used to sign requests (`X-Flashbots-Signature` header).
+    relay_signer: S,
+    relay_urls: Vec<Url>,
+}
+
+impl<S: Signer> PrivateTxExecutor<S> {
+    /// Creates an executor that fans out every private tx to all `relay_urls`.
+    /// `relay_signer` should be the same identity used for bundles so relays can score it.
+    pub fn new(relay_signer: S, relay_urls: Vec<Url>) -> Self {
+        Self {
+            client: Client::new(),
+            relay_signer,
+            relay_urls,
+        }
+    }
+
+    /// Send a signed json rpc request body to a single relay.
+    async fn send(&self, relay: &Url, body: &str) -> Result<Value> {
+        // relays authenticate the keccak of the body signed as a hex string message
+        let body_hash = format!("{:?}", H256::from(keccak256(body.as_bytes())));
+        let signature = self
+            .relay_signer
+            .sign_message(body_hash)
+            .await
+            .map_err(|e| anyhow!("Failed to sign request: {:?}", e))?;
+
+        let response = self
+            .client
+            .post(relay.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .header(
+                "X-Flashbots-Signature",
+                format!("{:?}:0x{}", self.relay_signer.address(), signature),
+            )
+            .body(body.to_string())
+            .send()
+            .await?
+            .text()
+            .await?;
+
+        let response: Value = serde_json::from_str(&response)?;
+        match response.get("error") {
+            Some(rpc_error) => Err(anyhow!("Relay returned error: {}", rpc_error)),
+            None => Ok(response["result"].clone()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Executor<PrivateTx> for PrivateTxExecutor<S>
+where
+    S: Signer + 'static,
+{
+    /// Send each private tx to all relays concurrently, one tx after the other. Stops once no
+    /// relay accepted a tx, the txs after it depend on it.
+    async fn execute(&self, action: PrivateTx) -> Result<()> {
+        let txs = action.txs.len();
+        for (index, tx) in action.txs.into_iter().enumerate() {
+            let body = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_sendPrivateTransaction",
+                "params": [{
+                    "tx": tx,
+                    "maxBlockNumber": action.max_block_number,
+                }],
+            })
+            .to_string();
+
+            // a failing relay should not stop the others
+            let body = &body;
+            let sends = self
+                .relay_urls
+                .iter()
+                .map(|relay| async move { (relay, self.send(relay, body).await) });
+
+            let mut accepted = false;
+            for (relay, result) in join_all(sends).await {
+                match result {
+                    Ok(tx_hash) => {
+                        accepted = true;
+                        info!(
+                            "Private tx sent to {} (max block: {}, hash: {})",
+                            relay, action.max_block_number, tx_hash
+                        )
+                    }
+                    Err(send_error) => {
+                        error!("Error sending private tx to {}: {:?}", relay, send_error)
+                    }
+                }
+            }
+
+            if !accepted && index + 1 < txs {
+                error!(
+                    "No relay accepted private tx {} of {}, not sending the {} after it",
+                    index + 1,
+                    txs,
+                    txs - index - 1
+                );
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{transaction::eip2718::TypedTransaction, Address, BlockNumber, Bytes, H256, U256},
+    utils::keccak256,
+};
+use ethers_flashbots::BundleRequest;
+use tracing::{error, warn};
+
+use crate::executors::bundle_profit::decode_bundle_tx;
+
+/// Which bundles are broadcast as plain public txs when no relay accepted them. Public txs can
+/// be frontrun and are ordered by priority fee alone, so either mode risks losing to others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublicFallback {
+    /// Only bundles whose searcher txs all come after the other txs (backruns and arbs).
+    Backrun,
+    /// Every bundle, frontruns included.
+    Sandwich,
+}
+
+impl FromStr for PublicFallback {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "backrun" => Ok(PublicFallback::Backrun),
+            "sandwich" => Ok(PublicFallback::Sandwich),
+            _ => Err(anyhow!("Unknown public fallback {}", s)),
+        }
+    }
+}
+
+/// Picks which of a bundle's txs to broadcast publicly, given which of them the searcher sent
+/// (in bundle order).
+///
+/// Returns the indexes of the searcher's txs, `None` if there are none or `fallback` doesn't
+/// allow broadcasting this bundle.
+pub fn select_public_fallback_txs(fallback: PublicFallback, ours: &[bool]) -> Option<Vec<usize>> {
+    let indexes: Vec<usize> = (0..ours.len()).filter(|index| ours[*index]).collect();
+    if indexes.is_empty() {
+        return None;
+    }
+
+    // a searcher tx before another tx frontruns it
+    let last_foreign = ours.iter().rposition(|ours| !ours);
+    let frontruns = match last_foreign {
+        Some(last_foreign) => indexes[0] < last_foreign,
+        None => false,
+    };
+    if frontruns && fallback == PublicFallback::Backrun {
+        return None;
+    }
+
+    Some(indexes)
+}
+
+/// Priority fee of one of the searcher's txs broadcast publicly, where txs are ordered by
+/// priority fee instead of their bundle order.
+///
+/// `bid` is the highest priority fee of the searcher's txs in the bundle (what the strategy bid
+/// for it) and `victim_tip` the highest priority fee the other txs pay. A frontrun outbids the
+/// victims by `bid` (at least 1 wei), a tx after them keeps the bid but never pays more than they
+/// do so it can't overtake them.
+pub fn public_fallback_tip(bid: U256, victim_tip: U256, frontruns: bool) -> U256 {
+    match frontruns {
+        true => victim_tip + bid.max(U256::one()),
+        false => bid.min(victim_tip),
+    }
+}
+
+/// Priority fee a tx pays at `base_fee`.
+fn effective_tip(tx: &TypedTransaction, base_fee: U256) -> U256 {
+    match tx {
+        TypedTransaction::Eip1559(tx) => {
+            let max_fee = tx.max_fee_per_gas.unwrap_or_default();
+            let max_tip = tx.max_priority_fee_per_gas.unwrap_or_default();
+            max_tip.min(max_fee.saturating_sub(base_fee))
+        }
+        _ => tx.gas_price().unwrap_or_default().saturating_sub(base_fee),
+    }
+}
+
+/// Broadcasts the searcher's txs of a bundle that no relay accepted.
+#[async_trait]
+pub trait PublicBroadcaster: Send + Sync {
+    /// Send the bundle's txs allowed by the fallback mode to the public mempool, returns the
+    /// hashes sent (empty if the bundle was skipped).
+    async fn broadcast(&self, bundle: &BundleRequest) -> Result<Vec<H256>>;
+}
+
+/// Sends fallback txs with `eth_sendRawTransaction` through a node.
+///
+/// Our txs are re-signed with their public priority fee (see
+/// [public_fallback_tip](public_fallback_tip)) and keep the nonces the strategy gave them, which
+/// no relay used as no relay accepted the bundle.
+pub struct PublicMempool<M, S> {
+    client: Arc<M>,
+    fallback: PublicFallback,
+    /// Signers of our txs by address, every other tx in a bundle is someone else's (e.g. a
+    /// victim).
+    searchers: HashMap<Address, S>,
+}
+
+impl<M: Middleware, S: Signer> PublicMempool<M, S> {
+    pub fn new(client: Arc<M>, fallback: PublicFallback, searchers: Vec<S>) -> Self {
+        Self {
+            client,
+            fallback,
+            searchers: searchers
+                .into_iter()
+                .map(|signer| (signer.address(), signer))
+                .collect(),
+        }
+    }
+
+    /// Re-sign one of our eip1559 txs paying `tip`, its fee cap keeps the same headroom over
+    /// the base fee.
+    ///
+    /// Returns the new hash and raw bytes.
+    async fn retip(
+        &self,
+        from: Address,
+        tx: &TypedTransaction,
+        tip: U256,
+    ) -> Result<(H256, Bytes)> {
+        let signer = self
+            .searchers
+            .get(&from)
+            .ok_or_else(|| anyhow!("No signer for {:?}", from))?;
+        let mut tx = match tx {
+            TypedTransaction::Eip1559(tx) => tx.clone(),
+            _ => return Err(anyhow!("Only eip1559 txs can be re-tipped")),
+        };
+        let max_fee = tx.max_fee_per_gas.unwrap_or_default();
+        let old_tip = tx.max_priority_fee_per_gas.unwrap_or_default();
+        tx.max_fee_per_gas = Some(max_fee.saturating_sub(old_tip) + tip);
+        tx.max_priority_fee_per_gas = Some(tip);
+
+        let tx = TypedTransaction::Eip1559(tx);
+        let signature = signer
+            .sign_transaction(&tx)
+            .await
+            .map_err(|e| anyhow!("Failed to sign public tx: {:?}", e))?;
+        let raw = tx.rlp_signed(&signature);
+        Ok((H256::from(keccak256(&raw)), raw))
+    }
+
+    /// True if the bundle could still land as it was built: its target block isn't mined yet
+    /// (`tip` is the latest block) and none of the other txs (victims) is.
+    async fn still_valid(&self, bundle: &BundleRequest, tip: u64, others: &[H256]) -> Result<bool> {
+        if bundle.block().map_or(true, |block| block.as_u64() <= tip) {
+            return Ok(false);
+        }
+
+        for hash in others {
+            let tx = self
+                .client
+                .get_transaction(*hash)
+                .await
+                .map_err(|e| anyhow!("Failed to get tx {:?}: {:?}", hash, e))?;
+            if tx.and_then(|tx| tx.block_number).is_some() {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl<M, S> PublicBroadcaster for PublicMempool<M, S>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    async fn broadcast(&self, bundle: &BundleRequest) -> Result<Vec<H256>> {
+        let txs = bundle
+            .transactions()
+            .iter()
+            .map(decode_bundle_tx)
+            .collect::<Result<Vec<_>>>()?;
+        let ours: Vec<bool> = txs
+            .iter()
+            .map(|(from, _, _)| self.searchers.contains_key(from))
+            .collect();
+
+        let indexes = match select_public_fallback_txs(self.fallback, &ours) {
+            Some(indexes) => indexes,
+            None => return Ok(vec![]),
+        };
+        let others: Vec<H256> = txs
+            .iter()
+            .zip(&ours)
+            .filter(|(_, ours)| !**ours)
+            .map(|((_, hash, _), _)| *hash)
+            .collect();
+        let latest = self
+            .client
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|e| anyhow!("Failed to get latest block: {:?}", e))?
+            .ok_or_else(|| anyhow!("Failed to get latest block"))?;
+        let tip = latest.number.unwrap_or_default().as_u64();
+        if !self.still_valid(bundle, tip, &others).await? {
+            warn!(
+                "Not broadcasting bundle for block {:?} publicly, it can no longer land",
+                bundle.block()
+            );
+            return Ok(vec![]);
+        }
+
+        // public txs are ordered by priority fee, so ours are re-tipped around the victims'
+        let base_fee = latest.base_fee_per_gas.unwrap_or_default();
+        let bid = indexes
+            .iter()
+            .filter_map(|index| match &txs[*index].2 {
+                TypedTransaction::Eip1559(tx) => tx.max_priority_fee_per_gas,
+                _ => None,
+            })
+            .max()
+            .unwrap_or_default();
+        let victim_tip = txs
+            .iter()
+            .zip(&ours)
+            .filter(|(_, ours)| !**ours)
+            .map(|((_, _, tx), _)| effective_tip(tx, base_fee))
+            .max()
+            .unwrap_or_default();
+        let last_foreign = ours.iter().rposition(|ours| !ours);
+
+        // sent in bundle order, a failing tx stops the ones after it (they'd revert)
+        let mut sent = vec![];
+        for index in indexes {
+            let (from, _, tx) = &txs[index];
+            let frontruns = last_foreign.map_or(false, |last_foreign| index < last_foreign);
+            let tip = public_fallback_tip(bid, victim_tip, frontruns);
+            let (hash, raw) = match self.retip(*from, tx, tip).await {
+                Ok(retipped) => retipped,
+                Err(e) => {
+                    error!(
+                        "Error re-tipping bundle tx {} for the public mempool: {:?}",
+                        index, e
+                    );
+                    break;
+                }
+            };
+            if let Err(e) = self.client.send_raw_transaction(raw).await {
+                error!("Error broadcasting tx {:?} publicly: {:?}", hash, e);
+                break;
+            }
+            sent.push(hash);
+        }
+
+        Ok(sent)
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use ethers::prelude::Lazy;
+use prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
+use tracing::info;
+
+/// Latencies kept per relay to compute its median response time.
+const LATENCY_WINDOW: usize = 256;
+
+static RELAY_SUBMISSIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "relay_submissions",
+        "Bundles submitted to each relay",
+        &["relay"]
+    )
+    .unwrap()
+});
+
+static RELAY_ACCEPTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "relay_accepted",
+        "Bundle submissions each relay accepted",
+        &["relay"]
+    )
+    .unwrap()
+});
+
+static RELAY_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "relay_errors",
+        "Bundle submissions that errored or that each relay rejected",
+        &["relay"]
+    )
+    .unwrap()
+});
+
+static RELAY_RESPONSE_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "relay_response_seconds",
+        "Time each relay took to answer a bundle submission",
+        &["relay"],
+        vec![0.01, 0.025, 0.05, 0.1, 0.2, 0.3, 0.5, 1.0, 2.0]
+    )
+    .unwrap()
+});
+
+/// Submission stats of a single relay since startup.
+#[derive(Debug, Clone, Default)]
+pub struct RelaySummary {
+    pub submissions: u64,
+    pub accepted: u64,
+    pub errors: u64,
+    /// Median response time over the last submissions, `None` before the first one.
+    pub median_latency: Option<Duration>,
+}
+
+#[derive(Default)]
+struct RelayRecord {
+    submissions: u64,
+    accepted: u64,
+    errors: u64,
+    /// Most recent response times, oldest first.
+    latencies: VecDeque<Duration>,
+}
+
+/// Tracks how each relay answers bundle submissions (also exported as prometheus metrics
+/// labeled by relay), so slow relays and relays that keep rejecting bundles can be dropped.
+#[derive(Default)]
+pub struct RelayStats {
+    relays: Mutex<BTreeMap<String, RelayRecord>>,
+}
+
+impl RelayStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a submission to `relay` that was answered after `latency`.
+    pub fn record(&self, relay: &str, latency: Duration, accepted: bool) {
+        RELAY_SUBMISSIONS.with_label_values(&[relay]).inc();
+        RELAY_RESPONSE_SECONDS
+            .with_label_values(&[relay])
+            .observe(latency.as_secs_f64());
+        match accepted {
+            true => RELAY_ACCEPTED.with_label_values(&[relay]).inc(),
+            false => RELAY_ERRORS.with_label_values(&[relay]).inc(),
+        }
+
+        let mut relays = self.relays.lock().unwrap();
+        let record = relays.entry(relay.to_string()).or_default();
+        record.submissions += 1;
+        match accepted {
+            true => record.accepted += 1,
+            false => record.errors += 1,
+        }
+        if record.latencies.len() == LATENCY_WINDOW {
+            record.latencies.pop_front();
+        }
+        record.latencies.push_back(latency);
+    }
+
+    /// Stats of every relay that was sent a bundle, by relay name.
+    pub fn summaries(&self) -> BTreeMap<String, RelaySummary> {
+        let relays = self.relays.lock().unwrap();
+        relays
+            .iter()
+            .map(|(relay, record)| {
+                let mut latencies: Vec<Duration> = record.latencies.iter().copied().collect();
+                latencies.sort();
+                let summary = RelaySummary {
+                    submissions: record.submissions,
+                    accepted: record.accepted,
+                    errors: record.errors,
+                    median_latency: latencies.get(latencies.len() / 2).copied(),
+                };
+                (relay.clone(), summary)
+            })
+            .collect()
+    }
+
+    /// Log a summary line per relay.
+    pub fn log_summaries(&self) {
+        for (relay, summary) in self.summaries() {
+            info!(
+                "Relay {}: {} submitted, {} accepted, {} errors, median latency {:?}",
+                relay,
+                summary.submissions,
+                summary.accepted,
+                summary.errors,
+                summary.median_latency.unwrap_or_default()
+            );
+        }
+    }
+
+    /// Log a summary line per relay every `interval` in the background.
+    pub fn spawn_summary_logger(self: &Arc<Self>, interval: Duration) {
+        let stats = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // first tick completes immediately, nothing has been sent yet
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                stats.log_summaries();
+            }
+        });
+    }
+}
@@ -27,6 +27,11 @@ pub trait Strategy<E, A>: Send + Sync {
 
     /// Process an event, and return an action if needed.
     async fn process_event(&mut self, event: E) -> Option<A>;
+
+    /// Persist state before the engine stops, called once no more events will be processed.
+    async fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Executor trait, responsible for executing actions returned by strategies.
@@ -0,0 +1,468 @@
+use std::{
+    future::Future,
+    mem,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use ethers::{
+    core::types::{transaction::eip2718::TypedTransaction, BlockId},
+    prelude::Lazy,
+    providers::{spoof, CallBuilder, Middleware, MiddlewareError, RawCall},
+    types::{Address, Bytes, NameOrAddress, TransactionRequest, H256, U256},
+};
+use prometheus::{register_int_counter, IntCounter};
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tracing::warn;
+
+/// Most reads sent in one batch, later ones start the next batch.
+const DEFAULT_MAX_BATCH_SIZE: usize = 100;
+
+/// Address the aggregator code is put at for a batch (it holds nothing onchain).
+const AGGREGATOR_ADDRESS: Address = Address::repeat_byte(0xba);
+
+/// Calls every `(target, calldata)` record in its calldata (target left padded to 32 bytes,
+/// calldata length as a 32 byte word, then the calldata) and returns a `(success, returndata
+/// length, returndata)` record for each, in the same layout.
+const AGGREGATOR_CODE: &str = concat!(
+    "0x600060005b8136111561004d5781358260200135808460400184604001376000600082856040016000865af1",
+    "83523d83602001523d6000846040013e83016040019250503d01604001610004565b6000f3",
+);
+
+/// Returns the storage slot of the running contract named by each 32 byte word of its calldata,
+/// put at every contract a storage batch reads from (state overrides keep their storage).
+const STORAGE_READER_CODE: &str = "0x60005b803611600d57366000f35b8035548152602001600256";
+
+static RPC_BATCHES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "rpc_batches",
+        "Batches of coalesced reads sent as a single eth_call"
+    )
+    .unwrap()
+});
+
+static RPC_BATCHED_REQUESTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "rpc_batched_requests",
+        "Reads answered from a batch instead of their own request"
+    )
+    .unwrap()
+});
+
+/// This middleware coalesces `eth_call`s and `eth_getStorageAt` reads at the same block that
+/// arrive within a short window into a single `eth_call`, saving a round trip per read on high
+/// latency providers. Calls are made from an aggregator contract put in place with a state
+/// override, storage reads run a reader put in place of each contract read from.
+///
+/// Calls with a sender or value are sent as they are, and so is every read while no window is
+/// set. A read whose batch failed (e.g. a node without state overrides) or whose call reverted
+/// inside it is sent on its own, so callers see the same results and errors as without batching.
+#[derive(Debug)]
+pub struct BatchingMiddleware<M> {
+    /// The inner middleware.
+    inner: M,
+    /// How long a batch waits for more reads after its first one, `None` = no batching.
+    window: Option<Duration>,
+    /// Pending calls as `(target, calldata)`, each answered with its returndata (`None` if it
+    /// reverted).
+    calls: Batcher<(Address, Bytes), Option<Bytes>>,
+    /// Pending storage reads as `(contract, slot)`.
+    storage_reads: Batcher<(Address, H256), H256>,
+}
+
+impl<M> BatchingMiddleware<M>
+where
+    M: Middleware,
+{
+    /// Creates an instance of BatchingMiddleware that batches nothing until a window is set.
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            window: None,
+            calls: Batcher::new(DEFAULT_MAX_BATCH_SIZE),
+            storage_reads: Batcher::new(DEFAULT_MAX_BATCH_SIZE),
+        }
+    }
+
+    /// Wait `window` after the first read of a batch for more to send with it.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Send at most `max_batch_size` reads per batch.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.calls = Batcher::new(max_batch_size);
+        self.storage_reads = Batcher::new(max_batch_size);
+        self
+    }
+
+    /// Make `calls` from the aggregator in one `eth_call`.
+    ///
+    /// Returns each call's returndata (`None` if it reverted), `None` if the batch failed.
+    async fn aggregate_calls(
+        &self,
+        block: Option<BlockId>,
+        calls: Vec<(Address, Bytes)>,
+    ) -> Option<Vec<Option<Bytes>>> {
+        // a lone call is cheaper sent as is
+        if calls.len() < 2 {
+            return None;
+        }
+
+        let mut state = spoof::state();
+        state
+            .account(AGGREGATOR_ADDRESS)
+            .code(AGGREGATOR_CODE.parse().unwrap());
+        self.aggregate(block, &calls, &state).await
+    }
+
+    /// Read every `(contract, slot)` in `reads` in one `eth_call`.
+    ///
+    /// Returns the value of each slot, `None` if the batch failed.
+    async fn aggregate_storage_reads(
+        &self,
+        block: Option<BlockId>,
+        reads: Vec<(Address, H256)>,
+    ) -> Option<Vec<H256>> {
+        if reads.len() < 2 {
+            return None;
+        }
+
+        // one call per contract, its calldata is every slot read from it
+        let mut contracts: Vec<(Address, Vec<u8>)> = vec![];
+        for (contract, slot) in &reads {
+            match contracts
+                .iter_mut()
+                .find(|(address, _)| address == contract)
+            {
+                Some((_, slots)) => slots.extend_from_slice(slot.as_bytes()),
+                None => contracts.push((*contract, slot.as_bytes().to_vec())),
+            }
+        }
+
+        let mut state = spoof::state();
+        state
+            .account(AGGREGATOR_ADDRESS)
+            .code(AGGREGATOR_CODE.parse().unwrap());
+        let reader: Bytes = STORAGE_READER_CODE.parse().unwrap();
+        for (contract, _) in &contracts {
+            state.account(*contract).code(reader.clone());
+        }
+
+        let calls: Vec<(Address, Bytes)> = contracts
+            .iter()
+            .map(|(contract, slots)| (*contract, Bytes::from(slots.clone())))
+            .collect();
+        let outputs = self.aggregate(block, &calls, &state).await?;
+
+        // slots come back in the order they were asked for, per contract
+        let mut values: Vec<(Address, Vec<H256>)> = vec![];
+        for ((contract, _), output) in contracts.iter().zip(outputs) {
+            let output = output?;
+            if output.len() % 32 != 0 {
+                return None;
+            }
+            let words = output.chunks(32).map(H256::from_slice).collect();
+            values.push((*contract, words));
+        }
+        let mut read_counts: Vec<(Address, usize)> = vec![];
+        let mut results = Vec::with_capacity(reads.len());
+        for (contract, _) in &reads {
+            let index = match read_counts
+                .iter_mut()
+                .find(|(address, _)| address == contract)
+            {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count - 1
+                }
+                None => {
+                    read_counts.push((*contract, 1));
+                    0
+                }
+            };
+            let (_, words) = values.iter().find(|(address, _)| address == contract)?;
+            results.push(*words.get(index)?);
+        }
+        Some(results)
+    }
+
+    /// Run `calls` through the aggregator with `state` overridden.
+    async fn aggregate(
+        &self,
+        block: Option<BlockId>,
+        calls: &[(Address, Bytes)],
+        state: &spoof::State,
+    ) -> Option<Vec<Option<Bytes>>> {
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(AGGREGATOR_ADDRESS)
+            .data(encode_calls(calls))
+            .into();
+        let call_builder = CallBuilder::new(self.inner.provider(), &tx);
+        let call_builder = match block {
+            Some(block) => call_builder.block(block),
+            None => call_builder,
+        };
+
+        let output = match call_builder.state(state).await {
+            Ok(output) => output,
+            Err(e) => {
+                warn!(
+                    "Batch of {} reads failed, sending them one by one: {}",
+                    calls.len(),
+                    e
+                );
+                return None;
+            }
+        };
+
+        let results = decode_results(&output, calls.len());
+        if results.is_some() {
+            RPC_BATCHES.inc();
+            RPC_BATCHED_REQUESTS.inc_by(calls.len() as u64);
+        }
+        results
+    }
+}
+
+#[async_trait]
+impl<M> Middleware for BatchingMiddleware<M>
+where
+    M: Middleware,
+{
+    type Error = BatchingMiddlewareError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// Performs a call, batched with the other calls at `block` if it has no sender or value.
+    async fn call(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<Bytes, Self::Error> {
+        let batchable = tx.from().is_none() && tx.value().map_or(true, |value| value.is_zero());
+        if let (Some(window), Some(to), true) = (self.window, tx.to_addr(), batchable) {
+            let data = tx.data().cloned().unwrap_or_default();
+            let output = self
+                .calls
+                .submit(window, block, (*to, data), |block, calls| {
+                    self.aggregate_calls(block, calls)
+                })
+                .await;
+            if let Some(Some(output)) = output {
+                return Ok(output);
+            }
+        }
+
+        self.inner
+            .call(tx, block)
+            .await
+            .map_err(BatchingMiddlewareError::from_err)
+    }
+
+    /// Reads a storage slot, batched with the other reads at `block`.
+    async fn get_storage_at<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        from: T,
+        location: H256,
+        block: Option<BlockId>,
+    ) -> Result<H256, Self::Error> {
+        let from = from.into();
+        if let (Some(window), NameOrAddress::Address(contract)) = (self.window, &from) {
+            let value = self
+                .storage_reads
+                .submit(window, block, (*contract, location), |block, reads| {
+                    self.aggregate_storage_reads(block, reads)
+                })
+                .await;
+            if let Some(value) = value {
+                return Ok(value);
+            }
+        }
+
+        self.inner
+            .get_storage_at(from, location, block)
+            .await
+            .map_err(BatchingMiddlewareError::from_err)
+    }
+}
+
+/// Reads waiting to be sent together.
+#[derive(Debug)]
+struct Batch<R, O> {
+    block: Option<BlockId>,
+    requests: Vec<R>,
+    /// Where each request's output goes, dropped if the batch fails.
+    senders: Vec<oneshot::Sender<O>>,
+}
+
+/// Groups requests at the same block into batches. The first request of a batch waits out the
+/// window, then sends the whole batch and hands every request its output.
+#[derive(Debug)]
+struct Batcher<R, O> {
+    max_batch_size: usize,
+    /// Batches still taking requests.
+    open: Mutex<Vec<Arc<Mutex<Batch<R, O>>>>>,
+}
+
+impl<R, O> Batcher<R, O> {
+    fn new(max_batch_size: usize) -> Self {
+        Self {
+            max_batch_size: max_batch_size.max(1),
+            open: Mutex::new(vec![]),
+        }
+    }
+
+    /// Add `request` to the open batch at `block` (or start one and send it with `send` after
+    /// `window`).
+    ///
+    /// Returns the request's output, `None` if its batch failed.
+    async fn submit<F, Fut>(
+        &self,
+        window: Duration,
+        block: Option<BlockId>,
+        request: R,
+        send: F,
+    ) -> Option<O>
+    where
+        F: FnOnce(Option<BlockId>, Vec<R>) -> Fut,
+        Fut: Future<Output = Option<Vec<O>>>,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let leading = {
+            let mut open = self.open.lock().unwrap();
+            match open
+                .iter()
+                .position(|batch| batch.lock().unwrap().block == block)
+            {
+                Some(index) => {
+                    let full = {
+                        let mut batch = open[index].lock().unwrap();
+                        batch.requests.push(request);
+                        batch.senders.push(sender);
+                        batch.requests.len() >= self.max_batch_size
+                    };
+                    if full {
+                        open.remove(index);
+                    }
+                    None
+                }
+                None => {
+                    let batch = Arc::new(Mutex::new(Batch {
+                        block,
+                        requests: vec![request],
+                        senders: vec![sender],
+                    }));
+                    open.push(batch.clone());
+                    Some(batch)
+                }
+            }
+        };
+
+        if let Some(batch) = leading {
+            // closed even if this future is dropped while waiting, the other requests then
+            // see their batch fail instead of waiting forever
+            let guard = CloseOnDrop {
+                open: &self.open,
+                batch,
+            };
+            tokio::time::sleep(window).await;
+            let batch = guard.batch.clone();
+            drop(guard);
+            let (requests, senders) = {
+                let mut batch = batch.lock().unwrap();
+                (
+                    mem::take(&mut batch.requests),
+                    mem::take(&mut batch.senders),
+                )
+            };
+
+            if let Some(outputs) = send(block, requests).await {
+                if outputs.len() == senders.len() {
+                    for (sender, output) in senders.into_iter().zip(outputs) {
+                        let _ = sender.send(output);
+                    }
+                }
+            }
+        }
+
+        receiver.await.ok()
+    }
+}
+
+/// Stops a batch from taking requests once its first request is done waiting (or dropped).
+struct CloseOnDrop<'a, R, O> {
+    open: &'a Mutex<Vec<Arc<Mutex<Batch<R, O>>>>>,
+    batch: Arc<Mutex<Batch<R, O>>>,
+}
+
+impl<R, O> Drop for CloseOnDrop<'_, R, O> {
+    fn drop(&mut self) {
+        self.open
+            .lock()
+            .unwrap()
+            .retain(|open| !Arc::ptr_eq(open, &self.batch));
+    }
+}
+
+/// Aggregator calldata for `calls`.
+fn encode_calls(calls: &[(Address, Bytes)]) -> Bytes {
+    let mut input = vec![];
+    for (target, data) in calls {
+        let mut word = [0u8; 32];
+        word[12..].copy_from_slice(target.as_bytes());
+        input.extend_from_slice(&word);
+        U256::from(data.len()).to_big_endian(&mut word);
+        input.extend_from_slice(&word);
+        input.extend_from_slice(data);
+    }
+    input.into()
+}
+
+/// Parse the aggregator's output for `count` calls.
+///
+/// Returns each call's returndata (`None` if it reverted), `None` if the output is malformed.
+fn decode_results(output: &[u8], count: usize) -> Option<Vec<Option<Bytes>>> {
+    let mut results = Vec::with_capacity(count);
+    let mut cursor = 0;
+    while results.len() < count {
+        let header = output.get(cursor..cursor + 64)?;
+        let success = !U256::from_big_endian(&header[..32]).is_zero();
+        let len = U256::from_big_endian(&header[32..]);
+        if len > U256::from(output.len()) {
+            return None;
+        }
+        let data = output.get(cursor + 64..cursor + 64 + len.as_usize())?;
+        results.push(success.then(|| Bytes::from(data.to_vec())));
+        cursor += 64 + len.as_usize();
+    }
+    Some(results)
+}
+
+#[derive(Error, Debug)]
+pub enum BatchingMiddlewareError<M: Middleware> {
+    /// Thrown when the internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+}
+
+impl<M: Middleware> MiddlewareError for BatchingMiddlewareError<M> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        BatchingMiddlewareError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            BatchingMiddlewareError::MiddlewareError(e) => Some(e),
+        }
+    }
+}
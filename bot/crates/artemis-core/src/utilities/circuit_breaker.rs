@@ -0,0 +1,164 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::Lazy;
+use prometheus::{register_int_counter, register_int_gauge, IntCounter, IntGauge};
+use tracing::{error, info};
+
+/// Requests in the window before its error rate can trip the breaker (a couple of failures
+/// right after startup shouldn't).
+const DEFAULT_MIN_REQUESTS: usize = 20;
+
+static RPC_BREAKER_OPEN: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "rpc_breaker_open",
+        "1 while the rpc circuit breaker is open and processing is paused"
+    )
+    .unwrap()
+});
+
+static RPC_BREAKER_TRIPS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "rpc_breaker_trips",
+        "Times the rpc error rate tripped the circuit breaker"
+    )
+    .unwrap()
+});
+
+/// Whether requests should go to the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Error rate is below the threshold, requests go through.
+    Closed,
+    /// Error rate went over the threshold, callers should hold off for `remaining`.
+    Open { remaining: Duration },
+}
+
+#[derive(Debug)]
+struct BreakerWindow {
+    /// When each request in the window finished and whether it succeeded, oldest first.
+    outcomes: VecDeque<(Instant, bool)>,
+    /// End of the cooldown while the breaker is open.
+    open_until: Option<Instant>,
+}
+
+/// Trips once the share of failed rpc requests over a sliding window goes over a threshold,
+/// then stays open for a cooldown so callers stop hammering a provider that is down or rate
+/// limiting us. The window starts over when it closes again.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// Requests finished within this long count towards the error rate.
+    window: Duration,
+    /// Share of requests (0.0 to 1.0) that may fail before the breaker trips.
+    max_error_rate: f64,
+    /// How long the breaker stays open after tripping.
+    cooldown: Duration,
+    /// Requests needed in the window before it can trip.
+    min_requests: usize,
+    state: Mutex<BreakerWindow>,
+}
+
+impl CircuitBreaker {
+    pub fn new(window: Duration, max_error_rate: f64, cooldown: Duration) -> Self {
+        Self {
+            window,
+            max_error_rate,
+            cooldown,
+            min_requests: DEFAULT_MIN_REQUESTS,
+            state: Mutex::new(BreakerWindow {
+                outcomes: VecDeque::new(),
+                open_until: None,
+            }),
+        }
+    }
+
+    /// Only trip once the window holds at least `min_requests` requests.
+    pub fn with_min_requests(mut self, min_requests: usize) -> Self {
+        self.min_requests = min_requests.max(1);
+        self
+    }
+
+    /// Record that a request finished now.
+    ///
+    /// Returns true if this tripped the breaker.
+    pub fn record(&self, success: bool) -> bool {
+        self.record_at(Instant::now(), success)
+    }
+
+    /// Record that a request finished at `now`.
+    ///
+    /// Returns true if this tripped the breaker.
+    pub fn record_at(&self, now: Instant, success: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            // requests still in flight when it tripped don't count towards the next window
+            Some(open_until) if now < open_until => return false,
+            Some(_) => close(&mut state),
+            None => {}
+        }
+
+        state.outcomes.push_back((now, success));
+        while let Some((at, _)) = state.outcomes.front() {
+            if now.duration_since(*at) <= self.window {
+                break;
+            }
+            state.outcomes.pop_front();
+        }
+
+        let requests = state.outcomes.len();
+        let errors = state
+            .outcomes
+            .iter()
+            .filter(|(_, success)| !success)
+            .count();
+        let error_rate = errors as f64 / requests as f64;
+        if requests < self.min_requests || error_rate <= self.max_error_rate {
+            return false;
+        }
+
+        state.outcomes.clear();
+        state.open_until = Some(now + self.cooldown);
+        RPC_BREAKER_TRIPS.inc();
+        RPC_BREAKER_OPEN.set(1);
+        error!(
+            "RPC CIRCUIT BREAKER TRIPPED: {}/{} requests failed in {:?}, pausing for {:?}",
+            errors, requests, self.window, self.cooldown
+        );
+        true
+    }
+
+    /// Current state of the breaker.
+    pub fn state(&self) -> BreakerState {
+        self.state_at(Instant::now())
+    }
+
+    /// State of the breaker at `now`, closes it once the cooldown is over.
+    pub fn state_at(&self, now: Instant) -> BreakerState {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(open_until) if now < open_until => BreakerState::Open {
+                remaining: open_until - now,
+            },
+            Some(_) => {
+                close(&mut state);
+                BreakerState::Closed
+            }
+            None => BreakerState::Closed,
+        }
+    }
+
+    /// True while callers should hold off.
+    pub fn is_open(&self) -> bool {
+        matches!(self.state(), BreakerState::Open { .. })
+    }
+}
+
+/// Close the breaker once its cooldown is over.
+fn close(state: &mut BreakerWindow) {
+    state.open_until = None;
+    RPC_BREAKER_OPEN.set(0);
+    info!("Rpc circuit breaker closed, resuming requests");
+}
@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use ethers::{
+    providers::{
+        JsonRpcClient, JsonRpcError, ProviderError, PubsubClient, RpcError, Ws, WsClientError,
+    },
+    types::U256,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use tracing::{error, warn};
+
+use super::circuit_breaker::CircuitBreaker;
+
+/// Json-rpc error codes providers answer with when rate limiting us.
+const RATE_LIMIT_CODES: [i64; 2] = [-32005, 429];
+
+/// A websocket transport over several rpc endpoints. Requests go to the active endpoint (the
+/// first one to start with), if it errors or times out the request is retried against the
+/// following endpoints in order and the first one to answer becomes the active endpoint.
+#[derive(Debug, Clone)]
+pub struct FailoverWs {
+    /// Connected endpoints in failover order.
+    transports: Vec<Ws>,
+    /// Index of the endpoint that requests are sent to first.
+    active: Arc<AtomicUsize>,
+    /// Requests taking longer than this fail over to the next endpoint.
+    request_timeout: Duration,
+    /// Endpoint that created each subscription, notifications only arrive on that connection.
+    subscriptions: Arc<Mutex<HashMap<U256, usize>>>,
+    /// Told how each request ended, `None` if no breaker watches the error rate.
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+/// Errors returned by [FailoverWs](FailoverWs).
+#[derive(Debug, Error)]
+pub enum FailoverWsError {
+    /// Error from the last endpoint that was tried.
+    #[error(transparent)]
+    Ws(#[from] WsClientError),
+    /// The last endpoint that was tried did not answer in time.
+    #[error("request timed out after {0:?}")]
+    Timeout(Duration),
+    /// Params or response could not be (de)serialized.
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    /// None of the endpoints could be connected to.
+    #[error("failed to connect to any rpc endpoint")]
+    NoEndpoints,
+}
+
+impl RpcError for FailoverWsError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            FailoverWsError::Ws(e) => e.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            FailoverWsError::Ws(e) => e.as_serde_error(),
+            FailoverWsError::Serde(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FailoverWsError> for ProviderError {
+    fn from(e: FailoverWsError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+impl FailoverWs {
+    /// Connects to every endpoint in `urls` (each transport reconnects up to `reconnects` times
+    /// on its own). Endpoints that can't be reached are skipped, errors if none can.
+    pub async fn connect(
+        urls: &[impl AsRef<str>],
+        reconnects: usize,
+        request_timeout: Duration,
+    ) -> Result<Self, FailoverWsError> {
+        let mut transports = vec![];
+        for url in urls {
+            match Ws::connect_with_reconnects(url.as_ref(), reconnects).await {
+                Ok(ws) => transports.push(ws),
+                Err(e) => error!(
+                    "Failed to connect to rpc endpoint {}: {:?}",
+                    url.as_ref(),
+                    e
+                ),
+            }
+        }
+
+        if transports.is_empty() {
+            return Err(FailoverWsError::NoEndpoints);
+        }
+
+        Ok(Self {
+            transports,
+            active: Arc::new(AtomicUsize::new(0)),
+            request_timeout,
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+            breaker: None,
+        })
+    }
+
+    /// Record the outcome of every request in `breaker` (after failing over, so a request one
+    /// of the endpoints answered counts as a success).
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = Some(breaker);
+        self
+    }
+
+    /// Tell the breaker how a request ended.
+    fn record_outcome(&self, success: bool) {
+        if let Some(breaker) = &self.breaker {
+            breaker.record(success);
+        }
+    }
+
+    /// Send a request to a single endpoint.
+    async fn request_from(
+        &self,
+        index: usize,
+        method: &str,
+        params: &Value,
+    ) -> Result<Value, FailoverWsError> {
+        let request = self.transports[index].request(method, params);
+        match tokio::time::timeout(self.request_timeout, request).await {
+            Ok(response) => Ok(response?),
+            Err(_) => Err(FailoverWsError::Timeout(self.request_timeout)),
+        }
+    }
+
+    /// Endpoint that created subscription `id` (falls back to the active endpoint).
+    fn subscription_transport(&self, id: U256) -> &Ws {
+        let index = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .get(&id)
+            .copied()
+            .unwrap_or_else(|| self.active.load(Ordering::SeqCst));
+        &self.transports[index]
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for FailoverWs {
+    type Error = FailoverWsError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        // params are sent again on failover, so serialize them once up front
+        let params = serde_json::to_value(params)?;
+        let active = self.active.load(Ordering::SeqCst);
+
+        let mut last_error = FailoverWsError::NoEndpoints;
+        for offset in 0..self.transports.len() {
+            let index = (active + offset) % self.transports.len();
+
+            match self.request_from(index, method, &params).await {
+                Ok(response) => {
+                    if index != active {
+                        warn!("Failing over to rpc endpoint {} for {}", index, method);
+                        self.active.store(index, Ordering::SeqCst);
+                    }
+                    if method == "eth_subscribe" {
+                        if let Ok(id) = serde_json::from_value::<U256>(response.clone()) {
+                            self.subscriptions.lock().unwrap().insert(id, index);
+                        }
+                    }
+                    self.record_outcome(true);
+                    return Ok(serde_json::from_value(response)?);
+                }
+                // node answered (e.g. a reverted call), another endpoint won't do better
+                Err(e) if e.is_error_response() => {
+                    self.record_outcome(!is_rate_limited(&e));
+                    return Err(e);
+                }
+                Err(e) => {
+                    warn!("Rpc endpoint {} failed {}: {}", index, method, e);
+                    last_error = e;
+                }
+            }
+        }
+
+        self.record_outcome(false);
+        Err(last_error)
+    }
+}
+
+/// True if the node answered by rate limiting us rather than with a result or a revert.
+fn is_rate_limited(e: &FailoverWsError) -> bool {
+    match e.as_error_response() {
+        Some(response) => {
+            let message = response.message.to_lowercase();
+            RATE_LIMIT_CODES.contains(&response.code)
+                || message.contains("rate limit")
+                || message.contains("too many requests")
+        }
+        None => false,
+    }
+}
+
+impl PubsubClient for FailoverWs {
+    type NotificationStream = <Ws as PubsubClient>::NotificationStream;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        let id = id.into();
+        Ok(self.subscription_transport(id).subscribe(id)?)
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        let id = id.into();
+        let result = self.subscription_transport(id).unsubscribe(id);
+        self.subscriptions.lock().unwrap().remove(&id);
+        Ok(result?)
+    }
+}
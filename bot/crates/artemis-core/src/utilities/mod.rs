@@ -2,3 +2,12 @@
 
 /// This module implements state overriding middleware.
 pub mod state_override_middleware;
+
+/// This module implements a websocket transport that fails over between rpc endpoints.
+pub mod failover_ws;
+
+/// This module implements a circuit breaker on the rpc error rate.
+pub mod circuit_breaker;
+
+/// This module implements a middleware that batches state reads.
+pub mod batching_middleware;
@@ -0,0 +1,131 @@
+//! Slow provider shared by the batching middleware test (round trips) and bench (latency).
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use ethers::{
+    core::types::transaction::eip2718::TypedTransaction,
+    providers::{JsonRpcClient, Middleware, Provider, Ws, WsClientError},
+    types::{Address, Bytes, TransactionRequest, H256},
+    utils::{Anvil, AnvilInstance},
+};
+use futures::future::join_all;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tokio::sync::Semaphore;
+
+/// Ws transport that answers every request `latency` late and only a few at a time, the way a
+/// remote provider limiting requests in flight does.
+#[derive(Debug, Clone)]
+pub struct SlowWs {
+    inner: Ws,
+    latency: Duration,
+    in_flight: Arc<Semaphore>,
+    requests: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl JsonRpcClient for SlowWs {
+    type Error = WsClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, WsClientError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let _permit = self.in_flight.acquire().await.unwrap();
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        tokio::time::sleep(self.latency).await;
+        self.inner.request(method, params).await
+    }
+}
+
+/// Anvil behind a `SlowWs`, with a contract that reverts whatever it's called with and a few
+/// of its slots set.
+pub struct SlowState {
+    pub provider: Provider<SlowWs>,
+    contract: Address,
+    slots: Vec<H256>,
+    inputs: Vec<Bytes>,
+    requests: Arc<AtomicUsize>,
+    _anvil: AnvilInstance,
+}
+
+impl SlowState {
+    /// Spawns Anvil answering 20ms late, 4 requests at a time.
+    pub async fn spawn() -> Self {
+        let anvil = Anvil::new().spawn();
+        let ws = Ws::connect(anvil.ws_endpoint()).await.unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let provider = Provider::new(SlowWs {
+            inner: ws,
+            latency: Duration::from_millis(20),
+            in_flight: Arc::new(Semaphore::new(4)),
+            requests: requests.clone(),
+        });
+
+        let contract = Address::repeat_byte(0x11);
+        provider
+            .request::<_, Value>("anvil_setCode", (contract, Bytes::from(vec![0xfe])))
+            .await
+            .unwrap();
+        let slots: Vec<H256> = (0..16).map(H256::from_low_u64_be).collect();
+        for (i, slot) in slots.iter().enumerate() {
+            let value = H256::from_low_u64_be(i as u64 * 7 + 1);
+            provider
+                .request::<_, Value>("anvil_setStorageAt", (contract, *slot, value))
+                .await
+                .unwrap();
+        }
+        let inputs: Vec<Bytes> = (0..16u8)
+            .map(|i| Bytes::from(vec![i; i as usize + 1]))
+            .collect();
+
+        Self {
+            provider,
+            contract,
+            slots,
+            inputs,
+            requests,
+            _anvil: anvil,
+        }
+    }
+
+    /// Requests sent to Anvil so far.
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Reads every set slot of the contract and echoes each input through the identity
+    /// precompile, all at once, plus a call that reverts.
+    pub async fn read<M: Middleware>(&self, client: &M) -> (Vec<H256>, Vec<Bytes>, bool) {
+        let identity = Address::from_low_u64_be(4);
+        let storage = join_all(
+            self.slots
+                .iter()
+                .map(|slot| client.get_storage_at(self.contract, *slot, None)),
+        );
+        let calls = self
+            .inputs
+            .iter()
+            .map(|input| {
+                TypedTransaction::from(TransactionRequest::new().to(identity).data(input.clone()))
+            })
+            .collect::<Vec<_>>();
+        let calls = join_all(calls.iter().map(|tx| client.call(tx, None)));
+        let revert = TypedTransaction::from(TransactionRequest::new().to(self.contract));
+        let (storage, calls, revert) = tokio::join!(storage, calls, client.call(&revert, None));
+
+        (
+            storage.into_iter().map(|value| value.unwrap()).collect(),
+            calls.into_iter().map(|output| output.unwrap()).collect(),
+            revert.is_err(),
+        )
+    }
+}
@@ -1,14 +1,45 @@
+mod common;
+
 use artemis_core::{
-    collectors::{block_collector::BlockCollector, mempool_collector::MempoolCollector},
-    types::Collector,
+    collectors::{
+        batching_collector::BatchingCollector, block_collector::BlockCollector,
+        mempool_collector::MempoolCollector,
+    },
+    executors::{
+        bundle_profit::searcher_profit,
+        bundle_relay::{BloxrouteRelay, BundleRelay},
+        private_tx_executor::{PrivateTx, PrivateTxExecutor},
+        public_mempool::{public_fallback_tip, select_public_fallback_txs, PublicFallback},
+        relay_stats::RelayStats,
+    },
+    types::{Collector, CollectorStream, Executor},
+    utilities::{
+        batching_middleware::BatchingMiddleware,
+        circuit_breaker::{BreakerState, CircuitBreaker},
+        failover_ws::FailoverWs,
+    },
 };
+use async_trait::async_trait;
 use ethers::providers::StreamExt;
 use ethers::{
+    abi::{encode, Token},
     providers::{Middleware, Provider, Ws},
-    types::{BlockNumber, TransactionRequest, U256},
-    utils::{Anvil, AnvilInstance},
+    signers::LocalWallet,
+    types::{Address, BlockNumber, BlockTrace, Bytes, TransactionRequest, H256, I256, U256, U64},
+    utils::{keccak256, Anvil, AnvilInstance},
+};
+use ethers_flashbots::BundleRequest;
+use serde_json::{json, Value};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::{sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use common::SlowState;
 
 /// Spawns Anvil and instantiates an Http provider.
 pub async fn spawn_anvil() -> (Provider<Ws>, AnvilInstance) {
@@ -57,3 +88,356 @@ async fn test_mempool_collector_sends_txs() {
     let tx = mempool_stream.into_future().await.0.unwrap();
     assert_eq!(tx.value, value.into());
 }
+
+/// Emits `0..count` as fast as it can.
+struct CountingCollector {
+    count: u64,
+}
+
+#[async_trait]
+impl Collector<u64> for CountingCollector {
+    async fn get_event_stream(&self) -> anyhow::Result<CollectorStream<'_, u64>> {
+        Ok(Box::pin(futures::stream::iter(0..self.count)))
+    }
+}
+
+/// Test that batching collector groups events up to its max batch size.
+#[tokio::test]
+async fn test_batching_collector_caps_batch_size() {
+    let collector = BatchingCollector::new(
+        Box::new(CountingCollector { count: 5 }),
+        Duration::from_secs(1),
+        2,
+    );
+    let batches: Vec<Vec<u64>> = collector.get_event_stream().await.unwrap().collect().await;
+    assert_eq!(batches, vec![vec![0, 1], vec![2, 3], vec![4]]);
+}
+
+/// Test that requests fail over to the backup endpoint once the primary goes down.
+#[tokio::test]
+async fn test_failover_ws_uses_backup_when_primary_is_down() {
+    let primary = Anvil::new().chain_id(1u64).spawn();
+    let backup = Anvil::new().chain_id(2u64).spawn();
+    let ws = FailoverWs::connect(
+        &[primary.ws_endpoint(), backup.ws_endpoint()],
+        0,
+        Duration::from_secs(1),
+    )
+    .await
+    .unwrap();
+    let provider = Provider::new(ws);
+
+    assert_eq!(provider.get_chainid().await.unwrap(), 1.into());
+
+    drop(primary);
+    assert_eq!(provider.get_chainid().await.unwrap(), 2.into());
+}
+
+/// Serves a single http request with `response` as the body, returns the raw request.
+async fn serve_once(listener: &TcpListener, response: &'static str) -> String {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let mut request = vec![];
+    let mut buf = [0u8; 4096];
+    // read until the whole body (sized by content-length) has arrived
+    loop {
+        let n = stream.read(&mut buf).await.unwrap();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request).to_string();
+        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+            let content_length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|l| l.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or_default();
+            if body.len() >= content_length {
+                break;
+            }
+        }
+    }
+
+    let reply = format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+        response.len(),
+        response
+    );
+    stream.write_all(reply.as_bytes()).await.unwrap();
+    String::from_utf8(request).unwrap()
+}
+
+/// Test that bloXroute bundles use its own method, tx encoding and auth header.
+#[tokio::test]
+async fn test_bloxroute_relay_submits_bundle_with_auth_header() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        serve_once(
+            &listener,
+            concat!(
+                r#"{"jsonrpc":"2.0","id":1,"result":{"bundleHash":"#,
+                r#""0x1111111111111111111111111111111111111111111111111111111111111111"}}"#
+            ),
+        )
+        .await
+    });
+
+    let relay = BloxrouteRelay::new(url.parse().unwrap(), "secret-auth");
+    let bundle = BundleRequest::new()
+        .push_transaction(Bytes::from(vec![0xab, 0xcd]))
+        .set_block(U64::from(17_000_000));
+    let bundle_hash = relay.send_bundle(&bundle).await.unwrap();
+
+    let request = server.await.unwrap();
+    let (head, body) = request.split_once("\r\n\r\n").unwrap();
+    assert!(head.to_lowercase().contains("authorization: secret-auth"));
+
+    let body: Value = serde_json::from_str(body).unwrap();
+    assert_eq!(body["method"], "blxr_submit_bundle");
+    assert_eq!(body["params"]["transaction"][0], "abcd");
+    assert_eq!(body["params"]["block_number"], "0x1036640");
+    assert_eq!(bundle_hash, Some([0x11; 32].into()));
+}
+
+/// Test that private txs after one no relay accepted aren't sent (they depend on its nonce).
+#[tokio::test]
+async fn test_private_tx_executor_stops_after_a_rejected_tx() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let request = serve_once(
+            &listener,
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"nonce too low"}}"#,
+        )
+        .await;
+        let resent = tokio::time::timeout(Duration::from_millis(200), listener.accept())
+            .await
+            .is_ok();
+        (request, resent)
+    });
+
+    let relay_signer: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+    let executor = PrivateTxExecutor::new(relay_signer, vec![url.parse().unwrap()]);
+    executor
+        .execute(PrivateTx {
+            txs: vec![Bytes::from(vec![0xab]), Bytes::from(vec![0xcd])],
+            max_block_number: U64::from(17_000_000),
+        })
+        .await
+        .unwrap();
+
+    let (request, resent) = server.await.unwrap();
+    let (_, body) = request.split_once("\r\n\r\n").unwrap();
+    let body: Value = serde_json::from_str(body).unwrap();
+    assert_eq!(body["method"], "eth_sendPrivateTransaction");
+    assert_eq!(body["params"][0]["tx"], "0xab");
+    assert!(!resent);
+}
+
+/// Test that relay stats count responses per relay and report their median latency.
+#[test]
+fn test_relay_stats_summarize_each_relay() {
+    let stats = RelayStats::new();
+    for (latency_ms, accepted) in [(120, true), (80, true), (300, false)] {
+        stats.record(
+            "https://relay.flashbots.net/",
+            Duration::from_millis(latency_ms),
+            accepted,
+        );
+    }
+    stats.record(
+        "https://rsync-builder.xyz/",
+        Duration::from_millis(40),
+        false,
+    );
+
+    let summaries = stats.summaries();
+    let flashbots = &summaries["https://relay.flashbots.net/"];
+    assert_eq!(flashbots.submissions, 3);
+    assert_eq!(flashbots.accepted, 2);
+    assert_eq!(flashbots.errors, 1);
+    assert_eq!(flashbots.median_latency, Some(Duration::from_millis(120)));
+
+    let rsync = &summaries["https://rsync-builder.xyz/"];
+    assert_eq!((rsync.submissions, rsync.accepted, rsync.errors), (1, 0, 1));
+    assert_eq!(rsync.median_latency, Some(Duration::from_millis(40)));
+}
+
+/// Test that only bundles without a frontrun are broadcast publicly in backrun mode.
+#[test]
+fn test_public_fallback_skips_frontruns_unless_allowed() {
+    // victim, then our backrun (or an arb's buy and sell)
+    let backrun = [false, true, true];
+    assert_eq!(
+        select_public_fallback_txs(PublicFallback::Backrun, &backrun),
+        Some(vec![1, 2])
+    );
+
+    // our frontrun, victim, our backrun
+    let sandwich = [true, false, true];
+    assert_eq!(
+        select_public_fallback_txs(PublicFallback::Backrun, &sandwich),
+        None
+    );
+    assert_eq!(
+        select_public_fallback_txs(PublicFallback::Sandwich, &sandwich),
+        Some(vec![0, 2])
+    );
+
+    // nothing of ours to send
+    assert_eq!(
+        select_public_fallback_txs(PublicFallback::Sandwich, &[false]),
+        None
+    );
+}
+
+/// Test that publicly broadcast txs are tipped to stay on their side of the victim.
+#[test]
+fn test_public_fallback_tips_around_the_victim() {
+    let gwei = U256::exp10(9);
+    let victim_tip = gwei * 2;
+
+    // frontrun outbids the victim by the bundle's bid, even one that bids nothing
+    assert_eq!(public_fallback_tip(gwei * 5, victim_tip, true), gwei * 7);
+    assert_eq!(
+        public_fallback_tip(U256::zero(), victim_tip, true),
+        victim_tip + U256::one()
+    );
+
+    // backrun keeps a bid below the victim's tip, a bigger one would land ahead of the victim
+    assert_eq!(public_fallback_tip(gwei, victim_tip, false), gwei);
+    assert_eq!(public_fallback_tip(gwei * 5, victim_tip, false), victim_tip);
+}
+
+/// Test that a bundle's simulated profit is the searcher's eth and weth change over its txs.
+#[test]
+fn test_searcher_profit_sums_eth_and_weth_changes() {
+    let weth = Address::repeat_byte(0xee);
+    let searcher = Address::repeat_byte(0x01);
+    let sando = Address::repeat_byte(0x02);
+    let victim = Address::repeat_byte(0x03);
+
+    let weth_slot = |owner: Address| {
+        let slot = keccak256(encode(&[Token::Address(owner), Token::Uint(U256::from(3))]));
+        format!("{:?}", H256::from(slot))
+    };
+    let changed =
+        |from: u64, to: u64| json!({ "*": { "from": U256::from(from), "to": U256::from(to) } });
+    let changed_word = |from: u64, to: u64| {
+        let (from, to) = (H256::from_low_u64_be(from), H256::from_low_u64_be(to));
+        json!({ "*": { "from": from, "to": to } })
+    };
+    let account = |balance: Value, storage: Value| {
+        json!({
+            "balance": balance,
+            "nonce": "=",
+            "code": "=",
+            "storage": storage,
+        })
+    };
+    let trace = |state_diff: Value| -> BlockTrace {
+        serde_json::from_value(json!({
+            "output": "0x",
+            "trace": null,
+            "vmTrace": null,
+            "stateDiff": state_diff,
+        }))
+        .unwrap()
+    };
+
+    // frontrun: searcher pays 2 wei of gas, sando contract spends 100 weth
+    let frontrun = trace(json!({
+        format!("{:?}", searcher): account(changed(1_000, 998), json!({})),
+        format!("{:?}", weth): account(
+            json!("="),
+            json!({ weth_slot(sando): changed_word(1_000, 900) })
+        ),
+    }));
+    // victim's weth and eth aren't ours
+    let victim_tx = trace(json!({
+        format!("{:?}", victim): account(changed(500, 400), json!({})),
+        format!("{:?}", weth): account(
+            json!("="),
+            json!({ weth_slot(victim): changed_word(0, 90) })
+        ),
+    }));
+    // backrun: searcher pays 3 wei of gas, sando contract gets 110 weth back
+    let backrun = trace(json!({
+        format!("{:?}", searcher): account(changed(998, 995), json!({})),
+        format!("{:?}", weth): account(
+            json!("="),
+            json!({ weth_slot(sando): changed_word(900, 1_010) })
+        ),
+    }));
+
+    let profit = searcher_profit(&[frontrun, victim_tx, backrun], weth, &[searcher, sando]);
+    assert_eq!(profit, I256::from(5));
+}
+
+/// Test that the circuit breaker trips on the error rate over its window and closes after the
+/// cooldown.
+#[test]
+fn test_circuit_breaker_trips_on_error_rate() {
+    let breaker = CircuitBreaker::new(Duration::from_secs(10), 0.5, Duration::from_secs(30))
+        .with_min_requests(4);
+    let start = Instant::now();
+    let at = |secs| start + Duration::from_secs(secs);
+
+    // too few requests to judge, then half failing is still within the threshold
+    assert!(!breaker.record_at(at(0), false));
+    assert!(!breaker.record_at(at(1), false));
+    assert!(!breaker.record_at(at(2), true));
+    assert!(!breaker.record_at(at(3), true));
+
+    // failures that fell out of the window don't count
+    assert!(!breaker.record_at(at(12), false));
+    assert_eq!(breaker.state_at(at(12)), BreakerState::Closed);
+
+    // 3 of 4 failed within the window
+    assert!(!breaker.record_at(at(13), false));
+    assert!(breaker.record_at(at(13), false));
+    assert_eq!(
+        breaker.state_at(at(20)),
+        BreakerState::Open {
+            remaining: Duration::from_secs(23)
+        }
+    );
+    // requests that finish while open don't trip it again
+    assert!(!breaker.record_at(at(20), false));
+
+    // closes after the cooldown with an empty window
+    assert_eq!(breaker.state_at(at(43)), BreakerState::Closed);
+    assert!(!breaker.record_at(at(44), false));
+    assert!(!breaker.record_at(at(44), false));
+    assert!(!breaker.record_at(at(44), false));
+}
+
+/// Test that concurrent reads batched into a few calls come back the same as sent one by one,
+/// in fewer round trips (see `benches/batching_middleware.rs` for the latency).
+#[tokio::test]
+async fn test_batching_middleware_coalesces_reads() {
+    let state = SlowState::spawn().await;
+
+    let direct = BatchingMiddleware::new(state.provider.clone());
+    let sent = state.requests();
+    let unbatched = state.read(&direct).await;
+    let unbatched_requests = state.requests() - sent;
+
+    let batching =
+        BatchingMiddleware::new(state.provider.clone()).with_window(Duration::from_millis(5));
+    let sent = state.requests();
+    let batched = state.read(&batching).await;
+    let batched_requests = state.requests() - sent;
+
+    assert_eq!(batched, unbatched);
+    assert_eq!(unbatched.0[3], H256::from_low_u64_be(22));
+    assert_eq!(unbatched.1[2], Bytes::from(vec![2; 3]));
+    assert!(unbatched.2);
+    // a batch of storage reads, one of calls, and the reverted call sent again on its own
+    assert_eq!(batched_requests, 3);
+    assert_eq!(unbatched_requests, 33);
+}
@@ -0,0 +1,246 @@
+//! Latency of the hot path a pending tx goes through before its bundle is built, every victim has
+//! to get through it before its target block's deadline
+//!
+//! Run with `cargo bench -p strategy`, criterion compares each run with the last one saved under
+//! `target/criterion` and flags regressions
+#[path = "../tests/common/mod.rs"]
+#[allow(dead_code)]
+mod common;
+
+use std::time::{Duration, Instant};
+
+use artemis_core::types::Strategy;
+use cfmms::pool::{Pool, UniswapV2Pool, UniswapV3Pool};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ethers::{
+    providers::{Middleware, Provider},
+    types::{Address, BlockNumber, BlockTrace, U256, U64},
+};
+use strategy::{
+    bot::SandoBot,
+    math::{
+        optimizer::{optimize_v3_sandwich, V3PoolState, VictimSwap},
+        v2_sandwich::get_amount_out,
+        v3_sandwich::{simulate_round_trip, TickMap},
+    },
+    types::{BlockInfo, Event, StratConfig, SwapDirection, VictimInfo},
+};
+use tokio::runtime::Runtime;
+
+use common::{FixtureClient, Scenario};
+
+/// Router02 `swapExactETHForTokens`, the victim of the `v2_backrun_arb` scenario
+const SWAP_EXACT_ETH_FOR_TOKENS: &str = "0x7ff36ab5";
+
+fn config() -> StratConfig {
+    StratConfig::new(
+        "0x5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a"
+            .parse()
+            .unwrap(),
+        U64::from(18_000_000),
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap(),
+    )
+}
+
+/// Bot on the scenario's fixture rpc, synced and pinned to the fixture's latest block
+async fn bot(scenario: &Scenario, config: StratConfig) -> SandoBot<Provider<FixtureClient>> {
+    let client = scenario.client();
+    let mut bot = SandoBot::new(client.provider(), config).with_pools(scenario.pools());
+    bot.sync_state().await.unwrap();
+
+    let block: BlockInfo = client
+        .provider()
+        .get_block(BlockNumber::Latest)
+        .await
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    bot.pin_to_block(block);
+    bot
+}
+
+/// The scenario's victim pool
+fn victim_pool(scenario: &Scenario) -> UniswapV2Pool {
+    let pool: Address = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+        .parse()
+        .unwrap();
+    scenario
+        .pools()
+        .into_iter()
+        .find_map(|p| match p {
+            Pool::UniswapV2(p) if p.address == pool => Some(p),
+            _ => None,
+        })
+        .unwrap()
+}
+
+/// The scenario's victim with the state diffs its fixture traces, its floor lowered to
+/// `slippage_bps` under what it gets on the untouched pool to leave room for a frontrun
+fn victim(scenario: &Scenario, slippage_bps: u64) -> VictimInfo {
+    let trace = scenario
+        .responses
+        .iter()
+        .find(|response| {
+            response.method == "trace_call"
+                && response
+                    .params_contain
+                    .iter()
+                    .any(|needle| needle == SWAP_EXACT_ETH_FOR_TOKENS)
+        })
+        .unwrap();
+    let trace: BlockTrace = serde_json::from_value(trace.result.clone()).unwrap();
+
+    // weth is the pool's token0
+    let pool = victim_pool(scenario);
+    let mut tx = scenario.victim.clone();
+    let fair_out = get_amount_out(
+        tx.value,
+        U256::from(pool.reserve_0),
+        U256::from(pool.reserve_1),
+        pool.fee / 10,
+    );
+    let mut input = tx.input.to_vec();
+    (fair_out * (10_000 - slippage_bps) / 10_000).to_big_endian(&mut input[4..36]);
+    tx.input = input.into();
+
+    VictimInfo::with_state_diffs(tx, trace.state_diff.unwrap().0)
+}
+
+/// 1:1 v3 pool with liquidity added every tick spacing above its price, sandwiches buying the
+/// token cross a tick every few million wei
+fn v3_pool() -> V3PoolState {
+    let weth: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap();
+    let token = Address::repeat_byte(0x11);
+
+    let pool = UniswapV3Pool {
+        token_a: weth.min(token),
+        token_b: weth.max(token),
+        fee: 3_000,
+        liquidity: 10u128.pow(9),
+        sqrt_price: U256::one() << 96,
+        tick: 0,
+        tick_spacing: 60,
+        ..Default::default()
+    };
+    let tick_map = TickMap {
+        ticks: (1..=20).map(|i| (i * 60, 10i128.pow(8))).collect(),
+        lowest_tick: -887_220,
+        highest_tick: 887_220,
+    };
+
+    V3PoolState::new(pool, tick_map, weth < token)
+}
+
+fn decode_swap(c: &mut Criterion) {
+    let victim = victim(&Scenario::load("v2_backrun_arb"), 500);
+    c.bench_function("decode_swap", |b| {
+        b.iter(|| black_box(&victim).decode_swap())
+    });
+}
+
+fn touched_pools(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let scenario = Scenario::load("v2_backrun_arb");
+    let bot = rt.block_on(bot(&scenario, config()));
+    let victim = victim(&scenario, 500);
+
+    // the pool's token is checked for transfer taxes once, later matches hit the cache
+    c.bench_function("touched_pools", |b| {
+        b.to_async(&rt)
+            .iter(|| async { bot.touched_pools(black_box(&victim)).await.unwrap() })
+    });
+}
+
+fn find_optimal_v2_input(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let scenario = Scenario::load("v2_backrun_arb");
+    let bot = rt.block_on(bot(&scenario, config()));
+    let pool = victim_pool(&scenario);
+    let victim = victim(&scenario, 500);
+    let sando_address = config().sando_addresses[0];
+
+    // warm started from the optimum of the previous iteration, as for back to back victims on
+    // a pool
+    c.bench_function("find_optimal_v2_input", |b| {
+        b.iter(|| {
+            bot.find_optimal_v2_input(
+                &pool,
+                black_box(&victim),
+                SwapDirection::WethToToken,
+                U256::zero(),
+                sando_address,
+            )
+        })
+    });
+}
+
+fn v3_tick_walk(c: &mut Criterion) {
+    let pool = v3_pool();
+    let victim = VictimSwap::Unchecked {
+        amount_in: U256::from(10_000_000),
+    };
+
+    let mut group = c.benchmark_group("v3_tick_walk");
+    group.bench_function("round_trip", |b| {
+        b.iter(|| {
+            simulate_round_trip(
+                black_box(U256::from(30_000_000)),
+                U256::from(10_000_000),
+                &pool.pool,
+                &pool.tick_map,
+                pool.weth_is_token0,
+            )
+        })
+    });
+    group.bench_function("optimize", |b| {
+        b.iter(|| {
+            optimize_v3_sandwich(
+                &pool,
+                black_box(&victim),
+                U256::from(200_000_000),
+                U256::one(),
+            )
+        })
+    });
+    group.finish();
+}
+
+fn process_new_tx(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let scenario = Scenario::load("v2_backrun_arb");
+    let mut config = config();
+    config.enable_backrun_arb = true;
+
+    // every iteration gets a fresh bot, a victim is only evaluated once
+    c.bench_function("process_new_tx", |b| {
+        b.to_async(&rt).iter_custom(|iters| {
+            let (scenario, config) = (&scenario, &config);
+            async move {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iters {
+                    let mut bot = bot(scenario, config.clone()).await;
+                    let event = Event::NewTransaction(scenario.victim.clone());
+                    let start = Instant::now();
+                    black_box(bot.process_event(event).await);
+                    elapsed += start.elapsed();
+                }
+                elapsed
+            }
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    decode_swap,
+    touched_pools,
+    find_optimal_v2_input,
+    v3_tick_walk,
+    process_new_tx
+);
+criterion_main!(benches);
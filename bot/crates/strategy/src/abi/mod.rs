@@ -30,3 +30,8 @@ abigen!(
     "src/abi/IERC20.abi",
     event_derives(serde::Deserialize, serde::Serialize)
 );
+abigen!(
+    UniswapV3Router,
+    "src/abi/ISwapRouter.abi",
+    event_derives(serde::Deserialize, serde::Serialize)
+);
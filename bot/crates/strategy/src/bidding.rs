@@ -0,0 +1,113 @@
+use ethers::types::{TxHash, U256, U64};
+use std::collections::HashMap;
+
+use crate::types::StratConfig;
+
+/// Competition seen around a victim, bids on it escalate until `until_block`
+#[derive(Debug, Clone, Copy)]
+struct Escalation {
+    /// Competition signals seen so far, each raises the bid by `escalation_step`
+    steps: u32,
+    /// Last block that bids around the victim are escalated for
+    until_block: U64,
+}
+
+/// Decides how much of a sandwich's profit to give away as priority fee
+#[derive(Debug, Clone)]
+pub struct Bidder {
+    /// Fraction of net profit bid when there is no competition
+    bid_fraction: f64,
+    /// Smallest tip we are willing to bid (in wei)
+    min_tip: U256,
+    /// Largest tip we are willing to bid (in wei)
+    max_tip: U256,
+    /// Added to the bid fraction for every competition signal around a victim (0.0 = off)
+    escalation_step: f64,
+    /// Escalation never raises the bid fraction above this
+    max_bid_fraction: f64,
+    /// Victims that other searchers compete for
+    escalations: HashMap<TxHash, Escalation>,
+}
+
+impl Bidder {
+    pub fn new(config: &StratConfig) -> Self {
+        Self {
+            bid_fraction: config.bid_fraction.clamp(0.0, 1.0),
+            min_tip: config.min_tip,
+            max_tip: config.max_tip,
+            escalation_step: config.bid_escalation_step.clamp(0.0, 1.0),
+            max_bid_fraction: config.max_bid_fraction.clamp(0.0, 1.0),
+            escalations: HashMap::new(),
+        }
+    }
+
+    /// True if competition signals raise our bids (`bid_escalation_step` is set)
+    pub fn escalates(&self) -> bool {
+        self.escalation_step > 0.0
+    }
+
+    /// Record that another searcher competes for `victim`, bids around it are escalated one
+    /// more step up to `until_block`
+    ///
+    /// Returns the victim's escalation steps (always 0 if escalation is off)
+    pub fn record_competition(&mut self, victim: TxHash, until_block: U64) -> u32 {
+        if !self.escalates() {
+            return 0;
+        }
+
+        let escalation = self.escalations.entry(victim).or_insert(Escalation {
+            steps: 0,
+            until_block,
+        });
+        escalation.steps += 1;
+        escalation.until_block = escalation.until_block.max(until_block);
+        escalation.steps
+    }
+
+    /// Escalation steps of the most contested of `victims`
+    pub fn escalation_steps<'a>(&self, victims: impl IntoIterator<Item = &'a TxHash>) -> u32 {
+        victims
+            .into_iter()
+            .filter_map(|victim| self.escalations.get(victim))
+            .map(|escalation| escalation.steps)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Forget competition around victims once their bundles can't target `next_block`
+    pub fn expire_escalations(&mut self, next_block: U64) {
+        self.escalations
+            .retain(|_, escalation| escalation.until_block >= next_block);
+    }
+
+    // Find the total priority fee to pay the builder for landing our bundle
+    // note: higher competition moves our bid from `bid_fraction` towards all of the profit,
+    // every escalation step adds `escalation_step` on top (capped at `max_bid_fraction`)
+    //
+    // Arguments:
+    // * `net_profit`: profit after paying base fee on frontrun and backrun
+    // * `competition_level`: how contested the opportunity is (0.0 = none, 1.0 = very)
+    // * `escalation_steps`: competition signals seen around the victims (see `escalation_steps`)
+    //
+    // Returns:
+    // U256: tip in wei, clamped between `min_tip` and `max_tip` (never more than `net_profit`)
+    pub fn compute_priority_fee(
+        &self,
+        net_profit: U256,
+        competition_level: f64,
+        escalation_steps: u32,
+    ) -> U256 {
+        let competition_level = competition_level.clamp(0.0, 1.0);
+        let mut fraction = self.bid_fraction + (1.0 - self.bid_fraction) * competition_level;
+        if escalation_steps > 0 {
+            // escalating never lowers the bid, even if it already sits above the cap
+            let escalated = fraction + self.escalation_step * escalation_steps as f64;
+            fraction = escalated.min(self.max_bid_fraction).max(fraction);
+        }
+
+        // scale fraction to integer math (1e9 precision)
+        let bid = net_profit * U256::from((fraction * 1e9) as u64) / U256::from(1_000_000_000u64);
+
+        bid.max(self.min_tip).min(self.max_tip).min(net_profit)
+    }
+}
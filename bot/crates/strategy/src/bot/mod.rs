@@ -1,48 +1,582 @@
-use anyhow::Result;
-use artemis_core::{collectors::block_collector::NewBlock, types::Strategy};
+use anyhow::{anyhow, Result};
+use artemis_core::{
+    collectors::block_collector::NewBlock,
+    types::Strategy,
+    utilities::circuit_breaker::{BreakerState, CircuitBreaker},
+};
 use async_trait::async_trait;
-use cfmms::pool::Pool::{UniswapV2, UniswapV3};
+use cfmms::pool::{
+    Pool::{self, UniswapV2, UniswapV3},
+    UniswapV2Pool, UniswapV3Pool,
+};
 use colored::Colorize;
-use ethers::{providers::Middleware, types::Transaction};
+use dashmap::DashMap;
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{
+        Address, BlockNumber, Eip1559TransactionRequest, Transaction, TransactionReceipt, TxHash,
+        H256, I256, U256, U64,
+    },
+    utils::keccak256,
+};
+use ethers_flashbots::BundleRequest;
 use foundry_evm::executor::fork::{BlockchainDb, BlockchainDbMeta, SharedBackend};
 use log::{error, info};
-use std::{collections::BTreeSet, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use crate::{
-    constants::WETH_ADDRESS,
-    log_error, log_info_cyan, log_new_block_info, log_not_sandwichable, log_opportunity,
+    abi::Erc20,
+    bidding::Bidder,
+    constants::{
+        BALANCE_CHECK_INTERVAL_BLOCKS, COINBASE_PAYMENT_GAS_ESTIMATE,
+        FUNNEL_SUMMARY_INTERVAL_BLOCKS, REORG_BUFFER_SIZE, V3_TICK_CROSS_GAS_ESTIMATE,
+    },
+    helpers::{
+        format_signed_token_amount, ms_until, padded_gas_limit, set_log_format, sign_eip1559,
+    },
+    log_deadline_missed, log_error, log_info_cyan, log_new_block_info, log_not_sandwichable,
+    log_opportunity, log_stale_state_abort,
     managers::{
-        block_manager::BlockManager, pool_manager::PoolManager,
+        alert_notifier::{Alert, AlertNotifier, LandedAlert, LowBalanceAlert},
+        balance_monitor::BalanceMonitor,
+        block_manager::{max_base_fee_after, with_base_fee_margin, BlockManager},
+        bundle_audit::{
+            balance_delta, BlockReconciliation, BundleAudit, BundleStatus, LandedBundle,
+        },
+        bundle_ranking::{select_top_opportunities, BlockBudget, BundleRanking, HeldOpportunity},
+        funnel::{FunnelStage, FunnelTracker},
+        loss_guard::{realized_profit, LossGuard},
+        mempool_manager::{
+            can_include_in_target_block, max_fee_per_gas, preceding_txs, priority_fee_per_gas,
+            tip_exceeds_swap_share, MempoolManager,
+        },
+        nonce_manager::NonceManager,
+        opportunity_log::{OpportunityLog, OpportunityOutcome, OpportunityRecord},
+        pool_manager::PoolManager,
+        rebase_detector::{standalone_sync_pairs, RebaseDetector},
         sando_state_manager::SandoStateManager,
+        simulation_cache::{SimulationCache, SimulationKey},
+        victim_cooldown::VictimCooldown,
+    },
+    math::{
+        is_within_tolerance,
+        optimizer::{self, PendingV2Swap, V2PoolState, V3PoolState, VictimSwap},
+        ordering::{best_ordering, get_frontrun_only_revenue, OrderingStrategy},
+        profit::{rank_by_net_profit, SandwichProfit},
+        v2_sandwich,
+    },
+    metrics::{self, spawn_metrics_server},
+    rng::DecisionRng,
+    simulator::{
+        code_cache::{CachedCodeBackend, CodeCache},
+        estimate_gas,
+        huff_sando::create_recipe,
+        lil_router::find_optimal_input,
+    },
+    startup_info_log,
+    tx_utils::{
+        huff_sando_interface::common::{
+            five_byte_encoder::FiveByteMetaData, weth_encoder::WethEncoder,
+        },
+        sando_encoder::{
+            v2_direct_backrun, v2_direct_frontrun, DirectSwap, HuffSandoEncoder, SandoEncoder,
+        },
+    },
+    types::{
+        Action, BlockInfo, DexConfig, Event, OpposingVictims, PaymentStrategy, PendingOpportunity,
+        PendingStage, PoolActivity, PoolManagerMetrics, PoolState, PoolUpdate, RawIngredients,
+        RejectReason, SandoError, SandoGasProfile, SandoRecipe, SimGasCapExceeded, SimStateSource,
+        StateDiffError, StratConfig, SwapDirection, SyncError, VictimInfo, VictimPosition,
     },
-    simulator::{huff_sando::create_recipe, lil_router::find_optimal_input},
-    types::{Action, BlockInfo, Event, RawIngredients, SandoRecipe, StratConfig},
 };
 
 pub struct SandoBot<M> {
     /// Ethers client
     provider: Arc<M>,
-    /// Keeps track of onchain pools
-    pool_manager: PoolManager<M>,
+    /// Keeps track of onchain pools (possibly shared with other bots, see `sharing_pools_of`)
+    pool_manager: Arc<PoolManager<M>>,
+    /// False if another bot syncs and updates `pool_manager`
+    owns_pools: bool,
     /// Block manager
     block_manager: BlockManager,
     /// Keeps track of weth inventory & token dust
     sando_state_manager: SandoStateManager,
+    /// Hands out searcher nonces for frontrun/backrun txs
+    nonce_manager: NonceManager,
+    /// Sizes the priority fee we bid per bundle
+    bidder: Bidder,
+    /// Pending txs already evaluated (so rebroadcasts are skipped)
+    mempool_manager: MempoolManager,
+    /// Bundles waiting for their target block (to track which ones landed)
+    submitted_bundles: Vec<SubmittedBundle>,
+    /// Txs to retry after a transient rpc failure or a contested miss (with the block they
+    /// were targeting)
+    requeued_txs: Vec<(U64, Transaction)>,
+    /// Bounds how many rpc heavy traces and simulations run at once (extra calls wait)
+    sim_permits: Arc<Semaphore>,
+    /// Simulation results for the target block, cleared on every new block
+    sim_cache: SimulationCache,
+    /// Bytecode of contracts simulations loaded, kept across blocks
+    code_cache: Arc<CodeCache>,
+    /// Every random choice is drawn from here (seeded by `rng_seed`)
+    rng: Arc<DecisionRng>,
+    /// Pending txs traced for the target block by the pools they touch, a builder may run them
+    /// before a later victim on the pool (only kept with `preceding_txs_min_profit` or when
+    /// victims are assumed to land after pending txs)
+    pool_pending_txs: HashMap<Address, Vec<VictimInfo>>,
+    /// Last optimal v2 frontrun input per pool and direction, seeds the next search on the pool
+    warm_starts: DashMap<(Address, SwapDirection), U256>,
+    /// Tracing failed and victims are being rebuilt from storage reads (see
+    /// `state_diff_storage_fallback`), only logged when it changes
+    storage_fallback_active: bool,
+    /// Failed resyncs in a row (reset once a block processes or a resync succeeds)
+    consecutive_resync_failures: u32,
+    /// Trips on the provider's error rate, events are skipped while it's open
+    rpc_breaker: Option<Arc<CircuitBreaker>>,
+    /// Events are being skipped for `rpc_breaker`, only logged when it changes
+    rpc_paused: bool,
+    /// When the bot was created (reported on shutdown)
+    started_at: Instant,
+    /// Called for every pool that moves more than `pool_update_threshold_bps` in a block
+    pool_update_hooks: Vec<PoolUpdateHook>,
+    /// Called for every bundle that lands in its target block
+    bundle_landed_hooks: Vec<BundleLandedHook>,
+    /// Posts to `alert_webhook` (if set)
+    alert_notifier: Option<Arc<AlertNotifier>>,
+    /// Which of the sando contracts' weth and the searcher's eth are below their floor
+    balance_monitor: BalanceMonitor,
+    /// Called for every opportunity record (whether or not `opportunity_log_path` is set)
+    opportunity_hooks: Vec<OpportunityHook>,
+    /// Replaying a historical block (see `pin_to_block`), wall clock deadlines don't apply
+    replaying: bool,
+    /// Where sized opportunities and their outcomes are recorded (if enabled)
+    opportunity_log: Option<OpportunityLog>,
+    /// Halts submissions after too large a realized loss (if enabled)
+    loss_guard: Option<LossGuard>,
+    /// Skips senders whose txs our bundles keep missing (if enabled)
+    victim_cooldown: Option<VictimCooldown>,
+    /// Recently landed bundles, their profit is taken back if a reorg drops their block
+    bundle_audit: BundleAudit,
+    /// Counts pools syncing reserves without swaps to catch rebasing tokens
+    rebase_detector: RebaseDetector,
+    /// Sandwiches held for the next block's ranking (if `rank_bundles_ms_before_block` is set)
+    bundle_ranking: BundleRanking<HeldSandwich>,
+    /// Counts how far pending txs make it through the pipeline, per block
+    funnel: FunnelTracker,
+    /// Builds calldata in the layout our sando contract decodes
+    sando_encoder: Arc<dyn SandoEncoder>,
+    /// Tunable strategy parameters
+    config: StratConfig,
+}
+
+/// Callback for monitored pools that moved significantly (e.g. to feed a dashboard)
+pub type PoolUpdateHook = Box<dyn Fn(&PoolUpdate) + Send + Sync>;
+
+/// Callback registered with `SandoBot::on_bundle_landed`
+pub type BundleLandedHook = Box<dyn Fn(&LandedBundle) + Send + Sync>;
+
+/// Callback registered with `SandoBot::on_opportunity`
+pub type OpportunityHook = Box<dyn Fn(&OpportunityRecord) + Send + Sync>;
+
+/// Run one victim's `evaluation`, abandoning it after `timeout` (never with `None`) so a slow rpc
+/// or simulation can't hold up the events behind it
+///
+/// Returns `None` if the evaluation timed out
+pub async fn evaluate_within<F: Future>(
+    timeout: Option<Duration>,
+    evaluation: F,
+) -> Option<F::Output> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, evaluation).await.ok(),
+        None => Some(evaluation.await),
+    }
+}
+
+/// Log and count a simulation that was aborted at `max_sim_gas` (no-op for other errors)
+fn log_sim_gas_abort(e: &anyhow::Error) {
+    if let Some(abort) = e.downcast_ref::<SimGasCapExceeded>() {
+        metrics::SIM_GAS_CAP_ABORTS.inc();
+        log_error!(tx_hash = abort.tx_hash; "Aborted simulation: {}", abort);
+    }
+}
+
+/// A bundle we sent out, used to record profit if it lands
+struct SubmittedBundle {
+    target_block: U64,
+    /// Furthest block the bundle was also sent for (see `target_block_offsets`)
+    last_target_block: U64,
+    victim_hash: TxHash,
+    /// Pool that the bundle sandwiches (a later bundle on the same pool conflicts with it)
+    pool: Address,
+    /// Paid to the builder, a replacement has to pay more to be picked over this bundle
+    priority_fee: U256,
+    /// `None` for private backruns and frontrun only bundles
+    frontrun_hash: Option<TxHash>,
+    /// Our last tx in the bundle (the frontrun of a frontrun only bundle), it landing means the
+    /// bundle did
+    backrun_hash: TxHash,
+    frontrun_nonce: U256,
+    revenue: U256,
+    net_profit: I256,
+    /// Private txs can't be withdrawn and frontrun only bundles only hold `frontrun_nonce`, so a
+    /// bundle can't reuse their nonces to replace them
+    private: bool,
+    /// Logged again with the outcome once the target block is mined
+    record: OpportunityRecord,
+    /// Victims of a sandwich (several once merged or netted), empty for private backruns
+    victims: Vec<VictimInfo>,
+    direction: SwapDirection,
+    submitted_at: Instant,
+}
+
+/// A simulated sandwich waiting for its block's ranking, it only gets nonces and is signed if
+/// it makes the block's top bundles
+struct HeldSandwich {
+    recipe: SandoRecipe,
+    sando_address: Address,
+    victim: VictimInfo,
+    priority_fee: U256,
+    revenue: U256,
+    record: OpportunityRecord,
+    direction: SwapDirection,
+}
+
+/// What sizing a sandwich on one touched pool found
+enum PoolEvaluation {
+    /// Frontrun input and expected profit of a sandwich
+    Sandwich(RawIngredients, U256, SandwichProfit),
+    /// Selling the contract's inventory into the victim beats sandwiching it
+    Backrun(BackrunOpportunity),
+    /// Keeping what a frontrun buys beats paying for a backrun
+    Frontrun(FrontrunOpportunity),
+    Unprofitable,
+}
+
+/// Rejections and optimizer warm starts from sizing a victim, held back until we know which sando
+/// contract it is sized with so that trying every contract doesn't count the victim once per
+/// contract (or leave the last contract's warm start behind)
+#[derive(Debug, Default)]
+struct SizingEffects {
+    rejections: Vec<RejectReason>,
+    /// Pools dropped by `passes_profit_screen`
+    screened_out: u64,
+    warm_starts: Vec<((Address, SwapDirection), U256)>,
+}
+
+/// A backrun (no frontrun) that sells the sando contract's token inventory into a victim's
+/// price impact
+struct BackrunOpportunity {
+    pool: UniswapV2Pool,
+    /// Token that the contract holds and sells for weth
+    token: Address,
+    amount_in: U256,
+    amount_out: U256,
+    profit: SandwichProfit,
+}
+
+/// A frontrun (no backrun) that buys a token ahead of a `weth->token` victim and keeps it as
+/// inventory, for victims whose sandwich backrun would cost more gas than it adds
+struct FrontrunOpportunity {
+    pool: UniswapV2Pool,
+    /// Token that the contract buys and keeps
+    token: Address,
+    /// Weth in and token out, encoded like a sandwich frontrun
+    buy: DirectSwap,
+    profit: SandwichProfit,
+}
+
+/// Two v2 pools of a token left at different prices by a victim, we buy the token on
+/// `buy_pool` and sell it on `sell_pool` (one of them is the pool the victim moved)
+struct ArbOpportunity {
+    buy_pool: UniswapV2Pool,
+    sell_pool: UniswapV2Pool,
+    token: Address,
+    /// Weth in and token out on `buy_pool`, encoded like a frontrun
+    buy: DirectSwap,
+    /// Token in and weth out on `sell_pool`, encoded like a backrun
+    sell: DirectSwap,
+    profit: SandwichProfit,
+}
+
+/// Round a frontrun input down to what the sando contract can be told to swap (weth in multiples
+/// of the encoding constant, token amounts five byte encoded)
+fn encodable_frontrun_input(direction: SwapDirection, input: U256) -> U256 {
+    match direction {
+        SwapDirection::WethToToken => WethEncoder::decode(WethEncoder::encode(input)),
+        SwapDirection::TokenToWeth => FiveByteMetaData::encode(input, 1).decode(),
+    }
 }
 
 impl<M: Middleware + 'static> SandoBot<M> {
     /// Create a new instance
     pub fn new(client: Arc<M>, config: StratConfig) -> Self {
+        set_log_format(config.log_format);
+        let rng = Arc::new(DecisionRng::new(config.rng_seed));
+        let pool_manager = PoolManager::new(
+            client.clone(),
+            config.dexes.clone(),
+            config.blacklist.clone(),
+            config.stable_tokens.clone(),
+            config.min_pool_liquidity,
+            config.weth_address,
+        );
+        let pool_manager = match &config.pool_whitelist {
+            Some(pool_whitelist) => pool_manager.with_pool_whitelist(pool_whitelist.clone()),
+            None => pool_manager,
+        };
+        let pool_manager = match config.sync_chunk_blocks {
+            Some(sync_chunk_blocks) => pool_manager.with_sync_chunk_blocks(sync_chunk_blocks),
+            None => pool_manager,
+        }
+        .with_state_diff_method(config.state_diff_method)
+        .with_rng(rng.clone())
+        .with_rebasing_tokens(config.rebasing_tokens.clone())
+        .with_sync_concurrency(config.sync_concurrency)
+        .with_activity_thresholds(config.hot_pool_change_bps, config.cold_pool_after_blocks)
+        .with_max_divergence_rate(config.max_pool_divergence_rate)
+        .with_fee_switch_exclusion(config.exclude_fee_switch_pools)
+        .with_aggregator_routers(config.aggregator_routers.clone());
+
+        // landed sandwiches are pushed to the alert webhook like any other landed bundle hook
+        let alert_notifier = config
+            .alert_webhook
+            .as_ref()
+            .map(|alert_webhook| Arc::new(AlertNotifier::new(alert_webhook.clone())));
+        let mut alert_hooks: Vec<BundleLandedHook> = vec![];
+        if let Some(notifier) = alert_notifier.clone() {
+            alert_hooks.push(Box::new(move |bundle| {
+                notifier.notify(LandedAlert::new(bundle))
+            }));
+        }
         Self {
-            pool_manager: PoolManager::new(client.clone()),
+            pool_manager: Arc::new(pool_manager),
+            owns_pools: true,
             provider: client,
             block_manager: BlockManager::new(),
             sando_state_manager: SandoStateManager::new(
-                config.sando_address,
-                config.searcher_signer,
+                config.sando_addresses.clone(),
+                config.searcher_signer.clone(),
                 config.sando_inception_block,
-            ),
+            )
+            .with_rotation(config.sando_rotation, rng.clone()),
+            nonce_manager: NonceManager::new(config.searcher_signer.address())
+                .with_max_bundles_per_block(config.max_bundles_per_block),
+            bidder: Bidder::new(&config),
+            mempool_manager: MempoolManager::default(),
+            submitted_bundles: vec![],
+            requeued_txs: vec![],
+            storage_fallback_active: false,
+            consecutive_resync_failures: 0,
+            rpc_breaker: None,
+            rpc_paused: false,
+            sim_permits: Arc::new(Semaphore::new(config.max_concurrent_sims)),
+            sim_cache: SimulationCache::default(),
+            code_cache: Arc::new(CodeCache::default()),
+            rng,
+            pool_pending_txs: HashMap::new(),
+            warm_starts: DashMap::new(),
+            started_at: Instant::now(),
+            pool_update_hooks: vec![],
+            bundle_landed_hooks: alert_hooks,
+            alert_notifier,
+            balance_monitor: BalanceMonitor::default(),
+            opportunity_hooks: vec![],
+            replaying: false,
+            opportunity_log: config
+                .opportunity_log_path
+                .as_ref()
+                .map(OpportunityLog::new),
+            loss_guard: config.max_window_loss.map(|max_loss| {
+                let loss_guard = LossGuard::new(max_loss, config.loss_window_blocks);
+                match config.loss_cooldown_blocks {
+                    Some(cooldown_blocks) => loss_guard.with_cooldown_blocks(cooldown_blocks),
+                    None => loss_guard,
+                }
+            }),
+            victim_cooldown: config
+                .victim_max_misses
+                .map(|max_misses| VictimCooldown::new(max_misses, config.victim_cooldown_blocks)),
+            bundle_audit: BundleAudit::new(REORG_BUFFER_SIZE as u64),
+            rebase_detector: RebaseDetector::new(config.rebase_sync_strikes),
+            bundle_ranking: BundleRanking::new(),
+            funnel: FunnelTracker::new(FUNNEL_SUMMARY_INTERVAL_BLOCKS),
+            sando_encoder: Arc::new(HuffSandoEncoder),
+            config,
+        }
+    }
+
+    /// Call `hook` for every monitored pool that moves more than `pool_update_threshold_bps` in
+    /// a block (hooks run on the strategy's task, so they should return quickly)
+    pub fn on_pool_update(mut self, hook: impl Fn(&PoolUpdate) + Send + Sync + 'static) -> Self {
+        self.pool_update_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` for every bundle that lands in its target block, failed ones too (see
+    /// `LandedBundle::status`), hooks run on the strategy's task so they should return quickly
+    pub fn on_bundle_landed(
+        mut self,
+        hook: impl Fn(&LandedBundle) + Send + Sync + 'static,
+    ) -> Self {
+        self.bundle_landed_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` for every sized opportunity and its outcome, the same records that go to the
+    /// opportunity log
+    pub fn on_opportunity(
+        mut self,
+        hook: impl Fn(&OpportunityRecord) + Send + Sync + 'static,
+    ) -> Self {
+        self.opportunity_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Evaluate victims against `owner`'s pools instead of syncing our own, so strategy variants
+    /// with different thresholds can run side by side without repeating the pool sync
+    /// note: only `owner` syncs and updates the shared pools (add it to the engine first), so
+    /// which pools are monitored follows its config, and the metrics server is left to it too
+    pub fn sharing_pools_of(mut self, owner: &SandoBot<M>) -> Self {
+        self.pool_manager = owner.pool_manager.clone();
+        self.owns_pools = false;
+        self
+    }
+
+    /// Pause processing while `breaker` is open (it should watch the provider's transport)
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.rpc_breaker = Some(breaker);
+        self
+    }
+
+    /// Encode frontruns and backruns with `encoder` instead of the huff sando contract's layout,
+    /// for contracts that decode their calldata differently
+    pub fn with_sando_encoder(mut self, encoder: impl SandoEncoder + 'static) -> Self {
+        self.sando_encoder = Arc::new(encoder);
+        self
+    }
+
+    /// Evaluate victims against a fixed set of `pools` instead of discovering them from the
+    /// dexes' factories (e.g. pools loaded from test fixtures)
+    /// note: like shared pools nothing syncs them, they keep the given state as blocks are mined
+    pub fn with_pools(mut self, pools: Vec<Pool>) -> Self {
+        for pool in pools {
+            self.pool_manager.add_pool(pool);
         }
+        self.owns_pools = false;
+        self
+    }
+
+    /// Evaluate the txs that follow as if `block` had just been mined, victims are traced and
+    /// simulated on its state (the provider must be an archive node for old blocks)
+    /// note: meant for replaying history, deadlines measured against the wall clock are skipped
+    pub fn pin_to_block(&mut self, block: BlockInfo) {
+        self.block_manager = BlockManager::new();
+        self.block_manager.update_block_info(block);
+        self.replaying = true;
+    }
+
+    /// Block that victims are currently evaluated for (the one after the latest mined block)
+    pub fn current_next_block(&self) -> BlockInfo {
+        self.block_manager.get_next_block()
+    }
+
+    /// Number of pools currently monitored (shared ones included)
+    pub fn synced_pool_count(&self) -> usize {
+        self.pool_manager.pool_count()
+    }
+
+    /// Pools monitored per dex and by activity, with their estimated memory footprint
+    pub fn pool_metrics(&self) -> PoolManagerMetrics {
+        self.pool_manager.metrics_snapshot()
+    }
+
+    /// Cached spot price of a monitored pool (token1 per token0 in raw units)
+    pub fn pool_price(&self, pool: Address) -> Option<f64> {
+        self.pool_manager.pool_price(pool)
+    }
+
+    /// Weth per raw unit of `token`, routed through an intermediate token if no weth pool trades
+    /// it
+    pub fn weth_price(&self, token: Address) -> Option<f64> {
+        self.pool_manager.weth_price(token)
+    }
+
+    /// Decimals of a monitored pool's token, `None` if they couldn't be read
+    pub fn token_decimals(&self, token: Address) -> Option<u8> {
+        self.pool_manager.token_decimals(token)
+    }
+
+    /// `amount` of `token` in whole tokens if its decimals are known, raw units otherwise
+    pub fn display_amount(&self, token: Address, amount: U256) -> String {
+        self.pool_manager.display_amount(token, amount)
+    }
+
+    /// Re-read `pools`' state at `block` with multicalls, overwriting cached state that drifted
+    /// from the chain (what `refresh_hot_pools` does with the hot pools after every block)
+    ///
+    /// Returns the pools that had drifted, with their cached and refreshed state
+    pub async fn refresh_pools(
+        &self,
+        pools: &[Address],
+        block: U64,
+    ) -> Result<Vec<(Address, PoolState, PoolState)>> {
+        self.pool_manager.refresh_pools(pools, block).await
+    }
+
+    /// Monitored pools that `victim` swaps through and that can be sandwiched (its state diffs
+    /// must be traced), what the bot sizes sandwiches on
+    pub async fn touched_pools(&self, victim: &VictimInfo) -> Result<Vec<Pool>> {
+        self.pool_manager
+            .get_touched_sandwichable_pools(victim)
+            .await
+    }
+
+    /// How active `pool` has been as of the latest block (see `hot_pool_change_bps` and
+    /// `cold_pool_after_blocks`)
+    pub fn pool_activity(&self, pool: Address) -> PoolActivity {
+        self.pool_manager
+            .pool_activity(pool, self.block_manager.get_latest_block().number)
+    }
+
+    /// Snapshot of the sandwiches held for ranking and the bundles waiting for their target
+    /// block, held ones first
+    pub fn pending_opportunities(&self) -> Vec<PendingOpportunity> {
+        let held = self
+            .bundle_ranking
+            .held()
+            .iter()
+            .map(|held| PendingOpportunity {
+                stage: PendingStage::Held,
+                pool: held.pool,
+                target_block: held.opportunity.record.target_block,
+                net_profit: held.net_profit,
+                priority_fee: held.opportunity.priority_fee,
+                record: held.opportunity.record.clone(),
+            });
+        let submitted = self
+            .submitted_bundles
+            .iter()
+            .map(|bundle| PendingOpportunity {
+                stage: PendingStage::Submitted,
+                pool: bundle.pool,
+                target_block: bundle.target_block,
+                net_profit: bundle.net_profit,
+                priority_fee: bundle.priority_fee,
+                record: bundle.record.clone(),
+            });
+        held.chain(submitted).collect()
+    }
+
+    /// Victims held back to be evaluated again on the next event for the same target block
+    pub fn requeued_victims(&self) -> Vec<TxHash> {
+        self.requeued_txs.iter().map(|(_, tx)| tx.hash).collect()
     }
 
     /// Main logic for the strategy
@@ -51,184 +585,4343 @@ impl<M: Middleware + 'static> SandoBot<M> {
         &self,
         ingredients: RawIngredients,
         target_block: BlockInfo,
-    ) -> Result<SandoRecipe> {
-        // setup shared backend
-        let shared_backend = SharedBackend::spawn_backend_thread(
-            self.provider.clone(),
-            BlockchainDb::new(
-                BlockchainDbMeta {
-                    cfg_env: Default::default(),
-                    block_env: Default::default(),
-                    hosts: BTreeSet::from(["".to_string()]),
-                },
-                None,
-            ), /* default because not accounting for this atm */
-            Some((target_block.number - 1).into()),
-        );
-
-        let weth_inventory = if cfg!(feature = "debug") {
-            // spoof weth balance when the debug feature is active
-            (*crate::constants::WETH_FUND_AMT).into()
-        } else {
-            self.sando_state_manager.get_weth_inventory()
-        };
+    ) -> std::result::Result<SandoRecipe, SandoError> {
+        let shared_backend = self.setup_shared_backend(&target_block);
+        let sando_address = self.sando_state_manager.get_sando_address();
 
         let optimal_input = find_optimal_input(
             &ingredients,
             &target_block,
-            weth_inventory,
+            self.get_position_budget(sando_address),
             shared_backend.clone(),
+            self.config.max_sim_gas,
         )
-        .await?;
+        .await
+        .map_err(|e| {
+            log_sim_gas_abort(&e);
+            SandoError::SimulationFailed(format!("{:#}", e))
+        })?;
 
-        let recipe = create_recipe(
-            &ingredients,
-            &target_block,
+        self.create_sando_recipe(
+            ingredients,
+            target_block,
             optimal_input,
-            weth_inventory,
-            self.sando_state_manager.get_searcher_address(),
-            self.sando_state_manager.get_sando_address(),
+            sando_address,
             shared_backend,
-        )?;
+            &[],
+        )
+        .map_err(|e| SandoError::SimulationFailed(format!("{:#}", e)))
+    }
 
-        log_opportunity!(
-            ingredients.print_meats(),
-            optimal_input.as_u128() as f64 / 1e18,
-            recipe.get_revenue().as_u128() as f64 / 1e18
+    /// Checks if the passed `RawIngredients` is sandwichable using an already known optimal input
+    /// and the sando contract at `sando_address`
+    pub fn is_sandwichable_with_input(
+        &self,
+        ingredients: RawIngredients,
+        target_block: BlockInfo,
+        optimal_input: U256,
+        sando_address: Address,
+    ) -> std::result::Result<SandoRecipe, SandoError> {
+        let shared_backend = self.setup_shared_backend(&target_block);
+        self.create_sando_recipe(
+            ingredients,
+            target_block,
+            optimal_input,
+            sando_address,
+            shared_backend,
+            &[],
+        )
+        .map_err(|e| SandoError::SimulationFailed(format!("{:#}", e)))
+    }
+
+    /// Simulate a sandwich after `preceding_txs`, reusing the result of an earlier simulation
+    /// for this target block if the victim left the pool in the same state
+    async fn simulate_sandwich(
+        &mut self,
+        victim: &VictimInfo,
+        ingredients: RawIngredients,
+        target_block: BlockInfo,
+        optimal_input: U256,
+        sando_address: Address,
+        preceding_txs: &[Transaction],
+    ) -> Result<SandoRecipe> {
+        // a merged sandwich's result depends on every victim (and any sandwich's on the txs
+        // before it), only single victims simulated on the block state alone are cached
+        let key = match ingredients.get_meats_ref().len() {
+            1 if preceding_txs.is_empty() => SimulationKey::new(
+                target_block.number,
+                ingredients.get_target_pool().address(),
+                sando_address,
+                optimal_input,
+                victim,
+            ),
+            _ => None,
+        };
+
+        if let Some(cached) = key.as_ref().and_then(|key| self.sim_cache.get(key)) {
+            metrics::SIM_CACHE_HITS.inc();
+            let meats = ingredients.get_meats_ref().clone();
+            return match cached {
+                Ok(recipe) => Ok(recipe.clone().with_meats(meats)),
+                Err(e) => Err(anyhow!("{} (cached)", e)),
+            };
+        }
+
+        // forked state is fetched over rpc, so it shares the trace permits
+        let permit = self.acquire_sim_permit().await;
+        let shared_backend = self.setup_shared_backend(&target_block);
+        let recipe = self.create_sando_recipe(
+            ingredients,
+            target_block,
+            optimal_input,
+            sando_address,
+            shared_backend,
+            preceding_txs,
         );
+        drop(permit);
 
-        Ok(recipe)
-    }
-}
+        if let Some(key) = key {
+            let result = match &recipe {
+                Ok(recipe) => Ok(recipe.clone()),
+                Err(e) => Err(e.to_string()),
+            };
+            self.sim_cache.insert(key, result);
+        }
 
-#[async_trait]
-impl<M: Middleware + 'static> Strategy<Event, Action> for SandoBot<M> {
-    /// Setup by getting all pools to monitor for swaps
-    async fn sync_state(&mut self) -> Result<()> {
-        self.pool_manager.setup().await?;
-        self.sando_state_manager
-            .setup(self.provider.clone())
-            .await?;
-        self.block_manager.setup(self.provider.clone()).await?;
-        Ok(())
+        recipe
     }
 
-    /// Process incoming events
-    async fn process_event(&mut self, event: Event) -> Option<Action> {
-        match event {
-            Event::NewBlock(block) => match self.process_new_block(block).await {
-                Ok(_) => None,
-                Err(e) => {
-                    panic!("strategy is out of sync {}", e);
-                }
-            },
-            Event::NewTransaction(tx) => self.process_new_tx(tx).await,
-        }
+    /// Discover every pool of the configured dexes from scratch and write them to `path` in the
+    /// pool cache format (copy it to `.pool-cache.json` to pre-warm the bot's cache)
+    ///
+    /// Returns each dex with how many pools it monitors
+    pub async fn sync_pools_to(
+        &mut self,
+        path: &str,
+    ) -> std::result::Result<Vec<(DexConfig, usize)>, SandoError> {
+        let latest_block = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(|e| SandoError::ProviderError(format!("Failed to get latest block: {}", e)))?
+            .as_u64();
+
+        let synced = self.pool_manager.sync_all_pools(latest_block).await?;
+        self.pool_manager.save_to_cache(path)?;
+
+        Ok(synced)
     }
-}
 
-impl<M: Middleware + 'static> SandoBot<M> {
-    /// Process new blocks as they come in
-    async fn process_new_block(&mut self, event: NewBlock) -> Result<()> {
-        log_new_block_info!(event);
-        self.block_manager.update_block_info(event);
-        Ok(())
+    /// Binary search for the v2 frontrun input that maximizes profit using the pool's reserves
+    /// found in the victim's state diffs (no evm calls needed)
+    ///
+    /// Frontrun trades in the victim's `direction`: weth for the token if they buy it, the sando
+    /// contract's `token_inventory` for weth if they sell it
+    ///
+    /// Returns `(optimal input, expected profit)`, or `None` if no input nets more than
+    /// `min_profit_threshold` after gas (or victim is already at their slippage limit)
+    pub fn find_optimal_v2_input(
+        &self,
+        pool: &UniswapV2Pool,
+        victim: &VictimInfo,
+        direction: SwapDirection,
+        token_inventory: U256,
+        sando_address: Address,
+    ) -> Option<(U256, SandwichProfit)> {
+        let mut effects = SizingEffects::default();
+        let optimal = self.size_v2_sandwich(
+            pool,
+            victim,
+            direction,
+            token_inventory,
+            sando_address,
+            &mut effects,
+        );
+        self.apply_sizing_effects(victim.tx.hash, effects);
+        optimal
     }
 
-    /// Process new txs as they come in
-    #[allow(unused_mut)]
-    async fn process_new_tx(&mut self, victim_tx: Transaction) -> Option<Action> {
-        // setup variables for processing tx
-        let next_block = self.block_manager.get_next_block();
-        let latest_block = self.block_manager.get_latest_block();
+    /// `find_optimal_v2_input` that leaves its rejections and warm start in `effects`
+    fn size_v2_sandwich(
+        &self,
+        pool: &UniswapV2Pool,
+        victim: &VictimInfo,
+        direction: SwapDirection,
+        token_inventory: U256,
+        sando_address: Address,
+        effects: &mut SizingEffects,
+    ) -> Option<(U256, SandwichProfit)> {
+        let weth = self.config.weth_address;
+        let other_token = if pool.token_a == weth {
+            pool.token_b
+        } else {
+            pool.token_a
+        };
 
-        // ignore txs that we can't include in next block
-        // enhancement: simulate all txs regardless, store result, and use result when tx can included
-        if victim_tx.max_fee_per_gas.unwrap_or_default() < next_block.base_fee_per_gas {
-            log_info_cyan!("{:?} mf<nbf", victim_tx.hash);
+        // reserves of the frontrun's input token first, for sells that is the token
+        let (victim_in, reserve_in, reserve_out) =
+            victim.get_v2_sandwich_reserves(pool.address, other_token, weth, direction)?;
+
+        // pool fee is tagged from its dex config (cfmms stores 0.3% as 300), victims assumed to
+        // land after pending txs swap on the reserves those leave
+        let pool_state = V2PoolState::new(reserve_in, reserve_out, pool.fee / 10);
+        let pending = self.pending_v2_swaps(victim, pool.address, other_token, direction);
+        let pool_state = optimizer::v2_state_after_pending(&pool_state, &pending);
+        let (reserve_in, reserve_out) = (pool_state.reserve_in, pool_state.reserve_out);
+        let victim_swap = self.get_v2_victim_swap(victim, direction, victim_in, other_token)?;
+        let inventory = match direction {
+            SwapDirection::WethToToken => self.get_position_budget(sando_address),
+            SwapDirection::TokenToWeth => token_inventory,
+        };
+        let inventory = optimizer::v2_price_impact_budget(
+            &pool_state,
+            inventory,
+            self.config.max_frontrun_price_impact_bps,
+        );
+
+        let ceiling = optimizer::sandwich_revenue_ceiling(&victim_swap, reserve_in, reserve_out);
+        let ceiling = match direction {
+            SwapDirection::WethToToken => ceiling,
+            SwapDirection::TokenToWeth => self.pool_manager.quote_in_weth(other_token, ceiling)?,
+        };
+        let gas = self.gas_profile(sando_address);
+        if !self.passes_profit_screen(ceiling, gas.v2_frontrun, gas.v2_backrun, effects) {
             return None;
         }
 
-        // check if tx is a swap
-        let touched_pools = self
-            .pool_manager
-            .get_touched_sandwichable_pools(
-                &victim_tx,
-                latest_block.number.into(),
-                self.provider.clone(),
-            )
-            .await
-            .map_err(|e| {
-                log_error!("Failed to get touched sandwichable pools: {}", e);
-                e
-            })
-            .ok()?;
+        // frontrun must leave the victim's swap above their slippage floor
+        let warm_start_key = (pool.address, direction);
+        let hint = self.warm_starts.get(&warm_start_key).map(|hint| *hint);
+        let optimal = match optimizer::optimize_v2_sandwich_from(
+            &pool_state,
+            &victim_swap,
+            inventory,
+            self.config.optimizer_epsilon,
+            hint,
+        ) {
+            Some(optimal) => optimal,
+            None => {
+                effects.rejections.push(RejectReason::SlippageBound);
+                return None;
+            }
+        };
+        effects
+            .warm_starts
+            .push((warm_start_key, optimal.frontrun_in));
 
-        // no touched pools = no sandwich opps
-        if touched_pools.is_empty() {
-            info!("{:?}", victim_tx.hash);
+        // contract only takes weth in multiples of the encoding constant (and token amounts five
+        // byte encoded), reprice the full round trip (frontrun, victim on the moved reserves,
+        // backrun) for the input it actually swaps
+        let optimal_input = encodable_frontrun_input(direction, optimal.frontrun_in);
+        let revenue = optimizer::v2_sandwich_revenue(&pool_state, &victim_swap, optimal_input);
+
+        // gas is paid in eth, a sell sandwich's revenue is in the token
+        let revenue = match direction {
+            SwapDirection::WethToToken => revenue,
+            SwapDirection::TokenToWeth => self.pool_manager.quote_in_weth(other_token, revenue)?,
+        };
+
+        // frontrun pays no tip, backrun bribe is taken out of profit when building the bundle
+        let profit = SandwichProfit::new(
+            revenue,
+            U256::from(gas.v2_frontrun),
+            U256::from(gas.v2_backrun),
+            self.block_manager.get_next_block().base_fee_per_gas,
+            U256::zero(),
+            self.config.payment_strategy,
+        );
+
+        if !profit.exceeds(self.config.min_profit_threshold) {
+            effects.rejections.push(RejectReason::BelowMinProfit);
             return None;
         }
 
-        let mut sando_bundles = vec![];
+        Some((optimal_input, profit))
+    }
 
-        for pool in touched_pools {
-            let (token_a, token_b) = match pool {
-                UniswapV2(p) => (p.token_a, p.token_b),
-                UniswapV3(p) => (p.token_a, p.token_b),
-            };
+    /// Size one sandwich around the victims of our outstanding bundle on `pool` followed by
+    /// `victim`, when they all swap on it in the same direction for the same block
+    ///
+    /// Returns the victim txs in order with the combined frontrun input and profit, `None` if
+    /// there's no bundle to merge with, a victim isn't a single hop exact input swap or the
+    /// merged sandwich doesn't beat the bundle
+    fn find_merged_v2_sandwich(
+        &self,
+        pool: &UniswapV2Pool,
+        victim: &VictimInfo,
+        direction: SwapDirection,
+        token_inventory: U256,
+        sando_address: Address,
+    ) -> Option<(Vec<Transaction>, U256, SandwichProfit)> {
+        let target_block = self.block_manager.get_next_block().number;
+        let outstanding = self.submitted_bundles.iter().find(|b| {
+            b.target_block == target_block
+                && b.pool == pool.address
+                && !b.private
+                && b.direction == direction
+        })?;
+        if outstanding
+            .victims
+            .iter()
+            .any(|merged| merged.tx.hash == victim.tx.hash)
+        {
+            return None;
+        }
 
-            if token_a != *WETH_ADDRESS && token_b != *WETH_ADDRESS {
-                // contract can only sandwich weth pools
-                continue;
+        let weth = self.config.weth_address;
+        let token = if pool.token_a == weth {
+            pool.token_b
+        } else {
+            pool.token_a
+        };
+        let path = match direction {
+            SwapDirection::WethToToken => vec![weth, token],
+            SwapDirection::TokenToWeth => vec![token, weth],
+        };
+
+        let merged = || outstanding.victims.iter().chain(std::iter::once(victim));
+        let mut reserves = None;
+        let mut victims = vec![];
+        for merged_victim in merged() {
+            let (victim_in, reserve_in, reserve_out) =
+                merged_victim.get_v2_sandwich_reserves(pool.address, token, weth, direction)?;
+
+            // every victim must have been traced on the same pool state
+            if *reserves.get_or_insert((reserve_in, reserve_out)) != (reserve_in, reserve_out) {
+                return None;
             }
 
-            // token that we use as frontrun input and backrun output
-            let start_end_token = *WETH_ADDRESS;
+            let swap = merged_victim
+                .decode_swap()
+                .filter(|swap| !swap.exact_output && swap.path == path)?;
+            victims.push((victim_in, swap.amount_out_min));
+        }
+        let (reserve_in, reserve_out) = reserves?;
+        let fee_bps = pool.fee / 10;
 
-            // token that we use as frontrun output and backrun input
-            let intermediary_token = if token_a == start_end_token {
-                token_b
-            } else {
-                token_a
-            };
+        // every victim, each on the reserves the previous one left, must clear its floor
+        let inventory = match direction {
+            SwapDirection::WethToToken => self.get_position_budget(sando_address),
+            SwapDirection::TokenToWeth => token_inventory,
+        };
+        let inventory = optimizer::v2_price_impact_budget(
+            &V2PoolState::new(reserve_in, reserve_out, fee_bps),
+            inventory,
+            self.config.max_frontrun_price_impact_bps,
+        );
+        let upper_bound = v2_sandwich::max_frontrun_for_merged_slippage(
+            &victims,
+            reserve_in,
+            reserve_out,
+            fee_bps,
+            inventory,
+        )?;
 
-            let ingredients = RawIngredients::new(
-                vec![victim_tx.clone()],
-                start_end_token,
-                intermediary_token,
-                pool,
-            );
+        let victims_in: Vec<U256> = victims.iter().map(|(victim_in, _)| *victim_in).collect();
+        let (optimal_input, _) = v2_sandwich::find_optimal_merged_input(
+            &victims_in,
+            reserve_in,
+            reserve_out,
+            fee_bps,
+            upper_bound,
+            self.config.optimizer_epsilon,
+        );
+        let optimal_input = encodable_frontrun_input(direction, optimal_input);
+        let revenue = v2_sandwich::get_merged_sandwich_revenue(
+            optimal_input,
+            &victims_in,
+            reserve_in,
+            reserve_out,
+            fee_bps,
+        );
+        let revenue = match direction {
+            SwapDirection::WethToToken => revenue,
+            SwapDirection::TokenToWeth => self.pool_manager.quote_in_weth(token, revenue)?,
+        };
 
-            match self.is_sandwichable(ingredients, next_block.clone()).await {
-                Ok(s) => {
-                    let _bundle = match s
-                        .to_fb_bundle(
-                            self.sando_state_manager.get_sando_address(),
-                            self.sando_state_manager.get_searcher_signer(),
-                            false,
-                            self.provider.clone(),
-                        )
-                        .await
-                    {
-                        Ok(b) => b,
-                        Err(e) => {
-                            log_not_sandwichable!("{:?}", e);
-                            continue;
-                        }
-                    };
+        let gas = self.gas_profile(sando_address);
+        let profit = SandwichProfit::new(
+            revenue,
+            U256::from(gas.v2_frontrun),
+            U256::from(gas.v2_backrun),
+            self.block_manager.get_next_block().base_fee_per_gas,
+            U256::zero(),
+            self.config.payment_strategy,
+        );
 
-                    #[cfg(not(feature = "debug"))]
-                    {
-                        sando_bundles.push(_bundle);
-                    }
-                }
-                Err(e) => {
-                    log_not_sandwichable!("{:?} {:?}", victim_tx.hash, e)
+        // merged bundle replaces the outstanding one (`should_replace` checks it after bidding)
+        if !profit.exceeds(self.config.min_profit_threshold)
+            || profit.net_profit <= outstanding.net_profit
+        {
+            return None;
+        }
+
+        let meats = merged()
+            .map(|merged_victim| merged_victim.tx.clone())
+            .collect();
+        Some((meats, optimal_input, profit))
+    }
+
+    /// Sandwich already out on the v2 `pool` for the next block with a victim that swaps
+    /// against `direction`
+    fn opposing_v2_bundle(
+        &self,
+        pool: &UniswapV2Pool,
+        direction: SwapDirection,
+    ) -> Option<&SubmittedBundle> {
+        let target_block = self.block_manager.get_next_block().number;
+        let weth = self.config.weth_address;
+        let token = if pool.token_a == weth {
+            pool.token_b
+        } else {
+            pool.token_a
+        };
+
+        self.submitted_bundles.iter().find(|b| {
+            b.target_block == target_block
+                && b.pool == pool.address
+                && !b.private
+                && b.victims.iter().any(|victim| {
+                    victim.get_swap_direction(pool.address, token, weth)
+                        == Some(direction.opposite())
+                })
+        })
+    }
+
+    /// Size one sandwich around `victim` and the victims of the `outstanding` bundle on the v2
+    /// `pool`, frontrunning whichever way their swaps move the pool once netted
+    ///
+    /// Victims swapping against the frontrun run first, which leaves the ones swapping along it
+    /// more room above their slippage floors
+    ///
+    /// Returns the victims in the order they run, the frontrun's direction, input and profit, or
+    /// `None` if nothing beating `min_profit_threshold` and the outstanding bundle is left
+    async fn find_netted_v2_sandwich(
+        &self,
+        pool: &UniswapV2Pool,
+        victim: &VictimInfo,
+        outstanding: &SubmittedBundle,
+        sim_block: BlockNumber,
+        sando_address: Address,
+    ) -> Option<(Vec<Transaction>, SwapDirection, U256, SandwichProfit)> {
+        if outstanding
+            .victims
+            .iter()
+            .any(|netted| netted.tx.hash == victim.tx.hash)
+        {
+            return None;
+        }
+
+        let weth = self.config.weth_address;
+        let token = if pool.token_a == weth {
+            pool.token_b
+        } else {
+            pool.token_a
+        };
+
+        // every victim oriented along a frontrun buying the token, with its slippage floor
+        let mut reserves = None;
+        let mut victims = vec![];
+        for netted in outstanding.victims.iter().chain(std::iter::once(victim)) {
+            let (swap, path, reserve_weth, reserve_token) = match netted.get_v2_sandwich_reserves(
+                pool.address,
+                token,
+                weth,
+                SwapDirection::WethToToken,
+            ) {
+                Some((weth_in, reserve_weth, reserve_token)) => (
+                    PendingV2Swap::SameDirection(weth_in),
+                    vec![weth, token],
+                    reserve_weth,
+                    reserve_token,
+                ),
+                None => {
+                    let (token_in, reserve_token, reserve_weth) = netted.get_v2_sandwich_reserves(
+                        pool.address,
+                        token,
+                        weth,
+                        SwapDirection::TokenToWeth,
+                    )?;
+                    (
+                        PendingV2Swap::OppositeDirection(token_in),
+                        vec![token, weth],
+                        reserve_weth,
+                        reserve_token,
+                    )
                 }
             };
-        }
 
-        Some(Action::SubmitToFlashbots(sando_bundles))
+            // every victim must have been traced on the same pool state
+            if *reserves.get_or_insert((reserve_weth, reserve_token))
+                != (reserve_weth, reserve_token)
+            {
+                return None;
+            }
+
+            let decoded = netted
+                .decode_swap()
+                .filter(|decoded| !decoded.exact_output && decoded.path == path)?;
+            victims.push((swap, decoded.amount_out_min, netted.tx.clone()));
+        }
+        let (reserve_weth, reserve_token) = reserves?;
+        let fee_bps = pool.fee / 10;
+
+        // frontrun the way the victims move the pool together
+        let buys = V2PoolState::new(reserve_weth, reserve_token, fee_bps);
+        let swaps: Vec<PendingV2Swap> = victims.iter().map(|(swap, _, _)| *swap).collect();
+        let (direction, pool_state) = if optimizer::v2_victims_net_along(&buys, &swaps) {
+            (SwapDirection::WethToToken, buys)
+        } else {
+            for (swap, _, _) in victims.iter_mut() {
+                *swap = swap.reversed();
+            }
+            (
+                SwapDirection::TokenToWeth,
+                V2PoolState::new(reserve_token, reserve_weth, fee_bps),
+            )
+        };
+        victims.sort_by_key(|(swap, _, _)| matches!(swap, PendingV2Swap::SameDirection(_)));
+
+        let inventory = match direction {
+            SwapDirection::WethToToken => self.get_position_budget(sando_address),
+            SwapDirection::TokenToWeth => {
+                self.get_token_inventory(direction, token, sando_address, sim_block)
+                    .await
+            }
+        };
+        let inventory = optimizer::v2_price_impact_budget(
+            &pool_state,
+            inventory,
+            self.config.max_frontrun_price_impact_bps,
+        );
+        let netted: Vec<(PendingV2Swap, U256)> = victims
+            .iter()
+            .map(|(swap, amount_out_min, _)| (*swap, *amount_out_min))
+            .collect();
+        let optimal = optimizer::optimize_v2_netted_sandwich(
+            &pool_state,
+            &netted,
+            inventory,
+            self.config.optimizer_epsilon,
+        )?;
+        let optimal_input = encodable_frontrun_input(direction, optimal.frontrun_in);
+        let revenue = optimizer::v2_netted_sandwich_revenue(&pool_state, &netted, optimal_input);
+        let revenue = match direction {
+            SwapDirection::WethToToken => revenue,
+            SwapDirection::TokenToWeth => self.pool_manager.quote_in_weth(token, revenue)?,
+        };
+
+        let gas = self.gas_profile(sando_address);
+        let profit = SandwichProfit::new(
+            revenue,
+            U256::from(gas.v2_frontrun),
+            U256::from(gas.v2_backrun),
+            self.block_manager.get_next_block().base_fee_per_gas,
+            U256::zero(),
+            self.config.payment_strategy,
+        );
+
+        // netted bundle replaces the outstanding one (`should_replace` checks it after bidding)
+        if !profit.exceeds(self.config.min_profit_threshold)
+            || profit.net_profit <= outstanding.net_profit
+        {
+            return None;
+        }
+
+        let meats = victims.into_iter().map(|(_, _, tx)| tx).collect();
+        Some((meats, direction, optimal_input, profit))
+    }
+
+    /// Size a backrun that sells the sando contract's `token` inventory right after a
+    /// `weth->token` victim swap
+    ///
+    /// Returns `None` if the contract holds no `token` or the backrun isn't profitable
+    async fn find_v2_backrun(
+        &self,
+        pool: &UniswapV2Pool,
+        victim: &VictimInfo,
+        token: Address,
+        sim_block: BlockNumber,
+        sando_address: Address,
+    ) -> Option<BackrunOpportunity> {
+        let (reserves_before, reserves_after) = victim.get_v2_reserves(pool.address)?;
+
+        // orient reserves around weth (token0 is the lower address)
+        let orient = |(reserve_0, reserve_1)| {
+            if self.config.weth_address < token {
+                (reserve_0, reserve_1)
+            } else {
+                (reserve_1, reserve_0)
+            }
+        };
+        let (reserve_weth, reserve_token) = orient(reserves_before);
+        let (weth_after, token_after) = orient(reserves_after);
+
+        // only a `weth->token` victim pushes the token's price up
+        let victim_in = weth_after.checked_sub(reserve_weth)?;
+
+        let inventory = Erc20::new(token, self.provider.clone())
+            .balance_of(sando_address)
+            .block(sim_block)
+            .call()
+            .await
+            .ok()?;
+        // swapped on the pool directly, same as a sandwich backrun dust stays on the contract
+        let fee_bps = pool.fee / 10;
+        let DirectSwap {
+            amount_in,
+            amount_out,
+        } = v2_direct_backrun(inventory, token_after, weth_after, fee_bps)?;
+        let revenue = v2_sandwich::get_backrun_revenue(
+            amount_in,
+            victim_in,
+            reserve_weth,
+            reserve_token,
+            fee_bps,
+        );
+
+        let gas = self.gas_profile(sando_address);
+        let profit = OrderingStrategy::BackrunOnly.profit(
+            revenue,
+            U256::from(gas.v2_frontrun),
+            U256::from(gas.v2_backrun),
+            self.with_safety_margin(self.block_manager.get_next_block().base_fee_per_gas),
+            U256::zero(),
+            self.config.payment_strategy,
+        );
+
+        if !profit.exceeds(self.config.min_profit_threshold) {
+            return None;
+        }
+
+        Some(BackrunOpportunity {
+            pool: *pool,
+            token,
+            amount_in,
+            amount_out,
+            profit,
+        })
+    }
+
+    /// Size a frontrun that buys `token` ahead of a `weth->token` victim and keeps it, at the
+    /// input a sandwich would frontrun with (the kept tokens are priced with
+    /// `frontrun_only_haircut_bps`)
+    ///
+    /// Returns `None` if the victim's slippage leaves no room to frontrun or it isn't profitable
+    fn find_v2_frontrun_only(
+        &self,
+        pool: &UniswapV2Pool,
+        victim: &VictimInfo,
+        token: Address,
+        sando_address: Address,
+    ) -> Option<FrontrunOpportunity> {
+        let direction = SwapDirection::WethToToken;
+        let (victim_in, reserve_weth, reserve_token) = victim.get_v2_sandwich_reserves(
+            pool.address,
+            token,
+            self.config.weth_address,
+            direction,
+        )?;
+
+        let fee_bps = pool.fee / 10;
+        let pool_state = V2PoolState::new(reserve_weth, reserve_token, fee_bps);
+        let victim_swap = self.get_v2_victim_swap(victim, direction, victim_in, token)?;
+        let inventory = optimizer::v2_price_impact_budget(
+            &pool_state,
+            self.get_position_budget(sando_address),
+            self.config.max_frontrun_price_impact_bps,
+        );
+        let optimal = optimizer::optimize_v2_sandwich(
+            &pool_state,
+            &victim_swap,
+            inventory,
+            self.config.optimizer_epsilon,
+        )?;
+
+        // swapped on the pool directly, the bought tokens stay on the contract
+        let buy = v2_direct_frontrun(optimal.frontrun_in, reserve_weth, reserve_token, fee_bps);
+        let revenue = get_frontrun_only_revenue(
+            buy.amount_in,
+            victim_in,
+            reserve_weth,
+            reserve_token,
+            fee_bps,
+            self.config.frontrun_only_haircut_bps,
+        );
+
+        let gas = self.gas_profile(sando_address);
+        let profit = OrderingStrategy::FrontrunOnly.profit(
+            revenue,
+            U256::from(gas.v2_frontrun),
+            U256::from(gas.v2_backrun),
+            self.with_safety_margin(self.block_manager.get_next_block().base_fee_per_gas),
+            U256::zero(),
+            self.config.payment_strategy,
+        );
+
+        if !profit.exceeds(self.config.min_profit_threshold) {
+            return None;
+        }
+
+        Some(FrontrunOpportunity {
+            pool: *pool,
+            token,
+            buy,
+            profit,
+        })
+    }
+
+    /// Size the most profitable arb between a v2 pool the victim moved and the token's other
+    /// monitored weth pools, in whichever direction the victim left the price
+    /// note: pools the victim didn't touch are priced from their cached reserves (synced every
+    /// block)
+    ///
+    /// Returns `None` if no pair of pools can be arbed profitably after gas
+    fn find_v2_backrun_arb(
+        &self,
+        victim: &VictimInfo,
+        moved_pools: &[UniswapV2Pool],
+        sando_address: Address,
+    ) -> Option<ArbOpportunity> {
+        let weth = self.config.weth_address;
+        let inventory = self.get_position_budget(sando_address);
+        let base_fee =
+            self.with_safety_margin(self.block_manager.get_next_block().base_fee_per_gas);
+        let gas = self.gas_profile(sando_address);
+
+        // (weth, token) reserves of a pool once the victim swapped
+        let reserves_after = |pool: &UniswapV2Pool, token: Address| {
+            let (reserve_0, reserve_1) = match victim.get_v2_reserves(pool.address) {
+                Some((_, after)) => after,
+                None => (U256::from(pool.reserve_0), U256::from(pool.reserve_1)),
+            };
+            if weth < token {
+                (reserve_0, reserve_1)
+            } else {
+                (reserve_1, reserve_0)
+            }
+        };
+
+        let mut best: Option<ArbOpportunity> = None;
+        for moved in moved_pools {
+            let token = match (moved.token_a == weth, moved.token_b == weth) {
+                (true, _) => moved.token_b,
+                (_, true) => moved.token_a,
+                _ => continue,
+            };
+
+            let others = self
+                .pool_manager
+                .get_pools_for_token(token)
+                .into_iter()
+                .filter_map(|pool| match pool {
+                    UniswapV2(p) if p.address != moved.address => Some(p),
+                    _ => None,
+                })
+                .filter(|p| p.token_a == weth || p.token_b == weth);
+
+            for other in others {
+                for (buy_pool, sell_pool) in [(other, *moved), (*moved, other)] {
+                    let (buy_weth, buy_token) = reserves_after(&buy_pool, token);
+                    let (sell_weth, sell_token) = reserves_after(&sell_pool, token);
+                    let (buy_fee_bps, sell_fee_bps) = (buy_pool.fee / 10, sell_pool.fee / 10);
+
+                    let optimal = match optimizer::optimize_v2_arb(
+                        &V2PoolState::new(buy_weth, buy_token, buy_fee_bps),
+                        &V2PoolState::new(sell_token, sell_weth, sell_fee_bps),
+                        inventory,
+                        self.config.optimizer_epsilon,
+                    ) {
+                        Some(optimal) => optimal,
+                        None => continue,
+                    };
+
+                    // both legs swap on the pools directly, size them as the contract encodes
+                    // them (dust of the bought token stays on the contract)
+                    let buy =
+                        v2_direct_frontrun(optimal.frontrun_in, buy_weth, buy_token, buy_fee_bps);
+                    let sell = match v2_direct_backrun(
+                        buy.amount_out,
+                        sell_token,
+                        sell_weth,
+                        sell_fee_bps,
+                    ) {
+                        Some(sell) => sell,
+                        None => continue,
+                    };
+
+                    let profit = SandwichProfit::new(
+                        sell.amount_out.saturating_sub(buy.amount_in),
+                        U256::from(gas.v2_frontrun),
+                        U256::from(gas.v2_backrun),
+                        base_fee,
+                        U256::zero(),
+                        self.config.payment_strategy,
+                    );
+                    let beats_best = best
+                        .as_ref()
+                        .map_or(true, |best| profit.net_profit > best.profit.net_profit);
+                    if profit.exceeds(self.config.min_profit_threshold) && beats_best {
+                        best = Some(ArbOpportunity {
+                            buy_pool,
+                            sell_pool,
+                            token,
+                            buy,
+                            sell,
+                            profit,
+                        });
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Describe the victim's swap through a v2 pool for the optimizer, in the frontrun's
+    /// `direction` (`victim_in` is what the pool received from them)
+    /// note: two hop paths are only checked when `enable_multihop` is set, sells must be single
+    /// hop
+    ///
+    /// Returns `None` if the victim's swap can't be sandwiched
+    fn get_v2_victim_swap(
+        &self,
+        victim: &VictimInfo,
+        direction: SwapDirection,
+        victim_in: U256,
+        other_token: Address,
+    ) -> Option<VictimSwap> {
+        let weth = self.config.weth_address;
+
+        let swap = match victim.decode_swap() {
+            Some(swap) => swap,
+            None => {
+                return Some(VictimSwap::Unchecked {
+                    amount_in: victim_in,
+                })
+            }
+        };
+
+        let (token_in, token_out) = match direction {
+            SwapDirection::WethToToken => (weth, other_token),
+            SwapDirection::TokenToWeth => (other_token, weth),
+        };
+
+        match swap.path.as_slice() {
+            &[first, last] if first == token_in && last == token_out => match swap.exact_output {
+                true => Some(VictimSwap::ExactOutput {
+                    amount_out: swap.amount_out_min,
+                    amount_in_max: swap.amount_in,
+                }),
+                false => Some(VictimSwap::ExactInput {
+                    amount_in: victim_in,
+                    amount_out_min: swap.amount_out_min,
+                }),
+            },
+            // multihop sells aren't sized, the victim's floor depends on the other hop
+            _ if direction == SwapDirection::TokenToWeth => None,
+            &[_, token_mid, last]
+                if self.config.enable_multihop && token_mid == weth && last == other_token =>
+            {
+                // we sandwich the last hop, victim's weth input was already set by the first hop
+                Some(VictimSwap::ExactInput {
+                    amount_in: victim_in,
+                    amount_out_min: swap.amount_out_min,
+                })
+            }
+            &[first, token_mid, last]
+                if self.config.enable_multihop && first == weth && token_mid == other_token =>
+            {
+                // we sandwich the first hop, victim's floor applies to the second hop's output
+                let next_pool = match self
+                    .pool_manager
+                    .get_touched_v2_pool(victim, token_mid, last)
+                {
+                    Some(pool) => pool,
+                    None => {
+                        return Some(VictimSwap::Unchecked {
+                            amount_in: victim_in,
+                        })
+                    }
+                };
+
+                let (next_reserves, _) = victim.get_v2_reserves(next_pool.address)?;
+                let (next_reserve_in, next_reserve_out) = if token_mid < last {
+                    next_reserves
+                } else {
+                    (next_reserves.1, next_reserves.0)
+                };
+
+                Some(VictimSwap::ExactInputTwoHop {
+                    amount_in: victim_in,
+                    amount_out_min: swap.amount_out_min,
+                    next_hop: V2PoolState::new(
+                        next_reserve_in,
+                        next_reserve_out,
+                        next_pool.fee / 10,
+                    ),
+                })
+            }
+            _ => Some(VictimSwap::Unchecked {
+                amount_in: victim_in,
+            }),
+        }
+    }
+
+    /// Search for the v3 frontrun input that maximizes profit by walking the pool's initialized
+    /// ticks (synced at `block`), sizing is left to `optimizer::optimize_v3_sandwich`
+    ///
+    /// Returns `(optimal input, expected profit)`, or `None` if no input nets more than
+    /// `min_profit_threshold` after gas (or victim is already at their slippage limit)
+    pub async fn find_optimal_v3_input(
+        &self,
+        pool: &UniswapV3Pool,
+        victim: &VictimInfo,
+        block: BlockNumber,
+        sando_address: Address,
+    ) -> Option<(U256, SandwichProfit)> {
+        let mut effects = SizingEffects::default();
+        let optimal = self
+            .size_v3_sandwich(pool, victim, block, sando_address, &mut effects)
+            .await;
+        self.apply_sizing_effects(victim.tx.hash, effects);
+        optimal
+    }
+
+    /// `find_optimal_v3_input` that leaves its rejections in `effects`
+    async fn size_v3_sandwich(
+        &self,
+        pool: &UniswapV3Pool,
+        victim: &VictimInfo,
+        block: BlockNumber,
+        sando_address: Address,
+        effects: &mut SizingEffects,
+    ) -> Option<(U256, SandwichProfit)> {
+        // right now bot can only sandwich `weth->token` trades
+        let (weth_before, weth_after) = victim.get_weth_balance_change(pool.address)?;
+        let victim_in = weth_after.checked_sub(weth_before)?;
+
+        let other_token = if pool.token_a == self.config.weth_address {
+            pool.token_b
+        } else {
+            pool.token_a
+        };
+        let weth_is_token0 = self.config.weth_address < other_token;
+
+        // victim's slippage floor only applies if they swapped through this fee tier, other tiers
+        // of the pair are separate pools
+        let victim_swap = match (
+            victim.get_swap_limits(other_token, Some(pool.fee)),
+            victim.get_exact_output_limits(other_token, Some(pool.fee)),
+        ) {
+            (_, Some((amount_in_max, amount_out))) => VictimSwap::ExactOutput {
+                amount_out,
+                amount_in_max,
+            },
+            (Some((_, amount_out_min)), None) => VictimSwap::ExactInput {
+                amount_in: victim_in,
+                amount_out_min,
+            },
+            (None, None) => VictimSwap::Unchecked {
+                amount_in: victim_in,
+            },
+        };
+
+        // pool's last known price is enough to screen out victims that can't pay for gas before
+        // syncing its ticks (ticks crossed only add gas)
+        let (reserve_weth, reserve_token) = optimizer::v3_virtual_reserves(pool, weth_is_token0);
+        let ceiling =
+            optimizer::sandwich_revenue_ceiling(&victim_swap, reserve_weth, reserve_token);
+        let gas = self.gas_profile(sando_address);
+        if !self.passes_profit_screen(ceiling, gas.v3_frontrun, gas.v3_backrun, effects) {
+            return None;
+        }
+
+        let permit = self.acquire_sim_permit().await;
+        let synced_state = self.pool_manager.get_v3_pool_state(*pool, block).await;
+        drop(permit);
+
+        let (pool, tick_map) = synced_state
+            .map_err(|e| {
+                log_error!(pool = pool.address; "Failed to sync v3 pool state: {}", e);
+                e
+            })
+            .ok()?;
+        let pool_state = V3PoolState::new(pool, tick_map, weth_is_token0);
+        let inventory = optimizer::v3_price_impact_budget(
+            &pool_state,
+            self.get_position_budget(sando_address),
+            self.config.max_frontrun_price_impact_bps,
+        );
+
+        let optimal = match optimizer::optimize_v3_sandwich(
+            &pool_state,
+            &victim_swap,
+            inventory,
+            self.config.optimizer_epsilon,
+        ) {
+            Some(optimal) => optimal,
+            None => {
+                effects.rejections.push(RejectReason::SlippageBound);
+                return None;
+            }
+        };
+
+        // every initialized tick crossed costs extra gas, the backrun walks back from where the
+        // victim left the price so it also pays for the ticks the victim crossed
+        let (frontrun_ticks, backrun_ticks) =
+            optimizer::v3_round_trip_ticks(&pool_state, &victim_swap, optimal.frontrun_in);
+        let tick_gas = |ticks: u32| U256::from(V3_TICK_CROSS_GAS_ESTIMATE) * ticks;
+
+        // frontrun pays no tip, backrun bribe is taken out of profit when building the bundle
+        let profit = SandwichProfit::new(
+            optimal.revenue,
+            U256::from(gas.v3_frontrun) + tick_gas(frontrun_ticks),
+            U256::from(gas.v3_backrun) + tick_gas(backrun_ticks),
+            self.block_manager.get_next_block().base_fee_per_gas,
+            U256::zero(),
+            self.config.payment_strategy,
+        );
+
+        if !profit.exceeds(self.config.min_profit_threshold) {
+            effects.rejections.push(RejectReason::BelowMinProfit);
+            return None;
+        }
+
+        Some((optimal.frontrun_in, profit))
+    }
+
+    /// Record the rejections and warm starts of the sizing we went with
+    fn apply_sizing_effects(&self, victim: TxHash, effects: SizingEffects) {
+        for reason in effects.rejections {
+            metrics::record_rejection(victim, reason);
+        }
+        metrics::POOLS_SCREENED_OUT.inc_by(effects.screened_out);
+        for (key, hint) in effects.warm_starts {
+            self.warm_starts.insert(key, hint);
+        }
+    }
+
+    /// Cheap check that a sandwich taking `ceiling` (weth, see
+    /// `optimizer::sandwich_revenue_ceiling`) could clear `min_profit_threshold` after gas, so
+    /// the optimizer and simulation only run on pools that can be profitable
+    fn passes_profit_screen(
+        &self,
+        ceiling: U256,
+        frontrun_gas: u64,
+        backrun_gas: u64,
+        effects: &mut SizingEffects,
+    ) -> bool {
+        let profit = SandwichProfit::new(
+            ceiling,
+            U256::from(frontrun_gas),
+            U256::from(backrun_gas),
+            self.block_manager.get_next_block().base_fee_per_gas,
+            U256::zero(),
+            self.config.payment_strategy,
+        );
+        if profit.exceeds(self.config.min_profit_threshold) {
+            return true;
+        }
+
+        effects.screened_out += 1;
+        effects.rejections.push(RejectReason::BelowProfitScreen);
+        false
+    }
+
+    /// Simulate sandwich with the huff contract (after `preceding_txs`) and check that it is
+    /// salmonella free
+    fn create_sando_recipe(
+        &self,
+        ingredients: RawIngredients,
+        target_block: BlockInfo,
+        optimal_input: U256,
+        sando_address: Address,
+        shared_backend: CachedCodeBackend,
+        preceding_txs: &[Transaction],
+    ) -> Result<SandoRecipe> {
+        let recipe = create_recipe(
+            self.sando_encoder.as_ref(),
+            &ingredients,
+            &target_block,
+            optimal_input,
+            self.get_weth_inventory(sando_address),
+            self.sando_state_manager.get_searcher_address(),
+            sando_address,
+            shared_backend,
+            self.config.backrun_min_out_buffer_bps,
+            preceding_txs,
+            self.config.max_sim_gas,
+            self.config.access_list_mode,
+        )
+        .map_err(|e| {
+            log_sim_gas_abort(&e);
+            e
+        })?;
+        metrics::ACCESS_LIST_GAS_SAVED.inc_by(recipe.get_access_list_gas_saved());
+
+        // input and revenue are in the token the sandwich starts and ends with
+        let start_end_token = ingredients.get_start_end_token();
+        log_opportunity!(
+            ingredients.print_meats(),
+            self.pool_manager
+                .display_amount(start_end_token, optimal_input),
+            self.pool_manager
+                .display_amount(start_end_token, recipe.get_revenue()),
+            self.pool_manager.token_label(start_end_token)
+        );
+
+        Ok(recipe)
+    }
+
+    /// Setup shared backend forked at the block before `target_block`, contracts already in the
+    /// code cache aren't fetched again
+    fn setup_shared_backend(&self, target_block: &BlockInfo) -> CachedCodeBackend {
+        let backend = SharedBackend::spawn_backend_thread(
+            self.provider.clone(),
+            BlockchainDb::new(
+                BlockchainDbMeta {
+                    cfg_env: Default::default(),
+                    block_env: Default::default(),
+                    hosts: BTreeSet::from(["".to_string()]),
+                },
+                None,
+            ), /* default because not accounting for this atm */
+            Some(
+                self.config
+                    .sim_state_source
+                    .block_for(target_block.number)
+                    .into(),
+            ),
+        );
+        CachedCodeBackend::new(backend, self.code_cache.clone())
+    }
+
+    /// Gas the sando contract at `sando_address` uses per swap, the default estimates unless
+    /// `sando_gas_profiles` has its own
+    fn gas_profile(&self, sando_address: Address) -> SandoGasProfile {
+        self.config
+            .sando_gas_profiles
+            .get(&sando_address)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Weth available to the sando contract at `sando_address`
+    fn get_weth_inventory(&self, sando_address: Address) -> U256 {
+        if cfg!(feature = "debug") {
+            // spoof weth balance when the debug feature is active
+            (*crate::constants::WETH_FUND_AMT).into()
+        } else {
+            self.sando_state_manager.get_weth_inventory(sando_address)
+        }
+    }
+
+    /// Weth a frontrun from `sando_address` may spend, its inventory capped by
+    /// `max_position_weth`
+    fn get_position_budget(&self, sando_address: Address) -> U256 {
+        optimizer::position_budget(
+            self.get_weth_inventory(sando_address),
+            self.config.max_position_weth,
+        )
+    }
+
+    /// Token balance of the sando contract at `sim_block` if a sandwich in `direction` spends it
+    /// (selling the token), zero otherwise or if it can't be fetched
+    async fn get_token_inventory(
+        &self,
+        direction: SwapDirection,
+        token: Address,
+        sando_address: Address,
+        sim_block: BlockNumber,
+    ) -> U256 {
+        if direction == SwapDirection::WethToToken {
+            return U256::zero();
+        }
+
+        Erc20::new(token, self.provider.clone())
+            .balance_of(sando_address)
+            .block(sim_block)
+            .call()
+            .await
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> Strategy<Event, Action> for SandoBot<M> {
+    /// Setup by getting all pools to monitor for swaps
+    async fn sync_state(&mut self) -> Result<()> {
+        Ok(self.setup_state().await?)
+    }
+
+    /// Persist pools and metrics so a restarted bot picks up where this one stopped
+    async fn shutdown(&mut self) -> Result<()> {
+        if self.owns_pools {
+            self.pool_manager.save_cache()?;
+        }
+
+        if let Some(path) = &self.config.metrics_snapshot_path {
+            metrics::write_snapshot(path)?;
+        }
+
+        startup_info_log!("uptime           : {:?}", self.started_at.elapsed());
+        startup_info_log!("bundles submitted: {}", metrics::BUNDLES_SUBMITTED.get());
+        startup_info_log!("bundles landed   : {}", metrics::BUNDLES_LANDED.get());
+        startup_info_log!("net profit (eth) : {}", metrics::NET_PROFIT.get());
+        Ok(())
+    }
+
+    /// Process incoming events
+    async fn process_event(&mut self, event: Event) -> Option<Action> {
+        if self.is_rpc_paused() {
+            // keep targeting the right block once the breaker closes, without any rpc calls
+            if let Event::NewBlock(block) = event {
+                self.block_manager.update_block_info(block);
+            }
+            return None;
+        }
+
+        match event {
+            Event::NewBlock(block) => {
+                let block_number = block.number;
+                let result = match self.process_new_block(block).await {
+                    Ok(_) => {
+                        metrics::record_block_processed();
+                        self.consecutive_resync_failures = 0;
+                        Ok(())
+                    }
+                    Err(e) => {
+                        self.recover_from_sync_error(SyncError::BlockProcessing {
+                            block: block_number,
+                            reason: e.to_string(),
+                        })
+                        .await
+                    }
+                };
+
+                // only crash once resyncing keeps failing
+                if let Err(e) = result {
+                    panic!("{}", e);
+                }
+                None
+            }
+            Event::NewTransaction(tx) => self.process_new_txs_with_requeued(vec![tx]).await,
+            Event::NewTransactions(txs) => self.process_new_txs_with_requeued(txs).await,
+        }
+    }
+}
+
+impl<M: Middleware + 'static> SandoBot<M> {
+    /// Sync pools, sando contract, nonce and block state from the chain (see `sync_state`)
+    async fn setup_state(&mut self) -> std::result::Result<(), SandoError> {
+        startup_info_log!("bot mode         : {:?}", self.config.mode);
+        startup_info_log!("rng seed         : {}", self.rng.seed());
+        // a bot sharing another's pools picks them up as its owner syncs them
+        if self.owns_pools {
+            metrics::set_sync_complete(false);
+            spawn_metrics_server(
+                self.config.metrics_port,
+                self.config.health_stale_after_secs,
+            );
+            self.pool_manager.setup().await?;
+            metrics::record_pool_manager(&self.pool_manager.metrics_snapshot());
+        }
+        startup_info_log!(
+            "routers tracked  : {}",
+            self.pool_manager.get_routers().len()
+        );
+        // these only read the chain, any failure is the node's
+        let provider_error = |e: anyhow::Error| SandoError::ProviderError(format!("{:#}", e));
+        self.sando_state_manager
+            .setup(self.provider.clone())
+            .await
+            .map_err(provider_error)?;
+        self.nonce_manager
+            .setup(self.provider.clone())
+            .await
+            .map_err(provider_error)?;
+        self.block_manager
+            .setup(self.provider.clone())
+            .await
+            .map_err(provider_error)?;
+        if self.owns_pools {
+            metrics::set_sync_complete(true);
+        }
+        Ok(())
+    }
+
+    /// Try to rebuild pool, block, and sando state after `error`
+    ///
+    /// Returns `SyncError::Fatal` after `max_resync_failures` consecutive failed resyncs, or the
+    /// first one that retrying can't fix
+    async fn recover_from_sync_error(
+        &mut self,
+        error: SyncError,
+    ) -> std::result::Result<(), SyncError> {
+        log_error!("{}, resyncing state", error);
+
+        match self.setup_state().await {
+            Ok(_) => {
+                self.consecutive_resync_failures = 0;
+                log_info_cyan!("resynced state");
+                Ok(())
+            }
+            Err(e) => {
+                self.consecutive_resync_failures += 1;
+                log_error!(
+                    "Resync failed ({}/{}): {}",
+                    self.consecutive_resync_failures,
+                    self.config.max_resync_failures,
+                    e
+                );
+
+                // e.g. a corrupt cache fails the same way on every resync
+                if !e.is_retryable()
+                    || self.consecutive_resync_failures >= self.config.max_resync_failures
+                {
+                    return Err(SyncError::Fatal {
+                        attempts: self.consecutive_resync_failures,
+                        reason: e.to_string(),
+                    });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Process new blocks as they come in
+    async fn process_new_block(&mut self, event: NewBlock) -> Result<()> {
+        log_new_block_info!(event);
+        let block_number = event.number;
+        if let Some(next_block) = self.block_manager.update_block_info(event) {
+            log_info_cyan!(
+                block = next_block.number;
+                "targeting block {:?} after reorg", next_block.number
+            );
+        }
+        self.reconcile_reorged_bundles();
+        let next_block_number = self.block_manager.get_next_block().number;
+        self.nonce_manager.reset_block_budget(next_block_number);
+        self.expire_held_sandwiches(next_block_number);
+        self.bidder.expire_escalations(next_block_number);
+        self.sim_cache.clear();
+        self.report_code_cache(block_number);
+        self.pool_pending_txs.clear();
+        self.mempool_manager.evict_stale(next_block_number);
+        self.unpark_includable_txs(block_number).await;
+        self.record_landed_bundles(block_number).await;
+        self.close_funnel(block_number);
+        self.sync_searcher_nonce(block_number).await;
+        self.monitor_balances(block_number).await;
+        if self.owns_pools {
+            self.sync_new_pools(block_number).await;
+            self.report_pool_updates(block_number).await;
+            self.refresh_hot_pools(block_number).await;
+            self.detect_rebasing_tokens(block_number).await;
+            // refreshed every block, pools turn hot or cold without new ones being added
+            metrics::record_pool_manager(&self.pool_manager.metrics_snapshot());
+        }
+        Ok(())
+    }
+
+    /// Log how many of the forked accounts loaded during `block_number` came from the code cache
+    fn report_code_cache(&self, block_number: U64) {
+        let (hits, misses) = self.code_cache.take_stats();
+        metrics::record_code_cache(hits, misses, self.code_cache.len());
+        if hits + misses == 0 {
+            return;
+        }
+
+        log_info_cyan!(
+            block = block_number;
+            "code cache: {:.1}% of {} account loads hit, {} contracts cached",
+            hits as f64 * 100.0 / (hits + misses) as f64,
+            hits + misses,
+            self.code_cache.len()
+        );
+    }
+
+    /// Exclude tokens whose v2 pools keep syncing reserves without a swap (no-op when
+    /// `rebase_sync_strikes` is 0)
+    async fn detect_rebasing_tokens(&mut self, block_number: U64) {
+        if self.config.rebase_sync_strikes == 0 {
+            return;
+        }
+
+        let logs = match self.pool_manager.get_v2_pair_logs(block_number).await {
+            Ok(logs) => logs,
+            Err(e) => {
+                log_error!(block = block_number; "Failed to get pair logs: {}", e);
+                return;
+            }
+        };
+
+        for pair in standalone_sync_pairs(&logs) {
+            let pool = match self.pool_manager.get_pool(pair) {
+                Some(UniswapV2(pool)) => pool,
+                _ => continue,
+            };
+            // only weth pairs are sandwiched, and weth never rebases so the other token does
+            let token = if pool.token_a == self.config.weth_address {
+                pool.token_b
+            } else if pool.token_b == self.config.weth_address {
+                pool.token_a
+            } else {
+                continue;
+            };
+
+            if self.rebase_detector.record_standalone_sync(token)
+                && self.pool_manager.flag_rebasing_token(token)
+            {
+                metrics::REBASING_TOKENS_FLAGGED.inc();
+                log_info_cyan!(
+                    block = block_number,
+                    pool = pair;
+                    "{:?} treated as rebasing, its pools sync reserves without swaps", token
+                );
+            }
+        }
+    }
+
+    /// Apply the block's pool logs and report pools that moved more than
+    /// `pool_update_threshold_bps` (no-op when it is unset and neither pool activity nor pool
+    /// prices are tracked)
+    async fn report_pool_updates(&self, block_number: U64) {
+        let threshold_bps = self.config.pool_update_threshold_bps;
+        if threshold_bps.is_none()
+            && !self.pool_manager.tracks_activity()
+            && !self.config.track_pool_prices
+        {
+            return;
+        }
+
+        let updates = match self.pool_manager.apply_block_updates(block_number).await {
+            Ok(updates) => updates,
+            Err(e) => {
+                log_error!(block = block_number; "Failed to apply pool updates: {}", e);
+                return;
+            }
+        };
+
+        let threshold_bps = match threshold_bps {
+            Some(threshold_bps) => threshold_bps,
+            None => return,
+        };
+        for (pool, before, after) in updates {
+            let change_bps = match after.change_bps(&before) {
+                Some(change_bps) if change_bps >= threshold_bps => change_bps,
+                _ => continue,
+            };
+
+            metrics::POOL_UPDATES.inc();
+            log_info_cyan!(
+                block = block_number,
+                pool = pool;
+                "pool {:?} moved {} bps", pool, change_bps
+            );
+
+            let update = PoolUpdate {
+                block: block_number,
+                pool,
+                before,
+                after,
+                change_bps,
+            };
+            for hook in &self.pool_update_hooks {
+                hook(&update);
+            }
+        }
+    }
+
+    /// Re-read the hot pools' state at the end of `block_number` and log the ones whose cached
+    /// state had drifted (no-op unless `refresh_hot_pools` is set)
+    async fn refresh_hot_pools(&self, block_number: U64) {
+        if !self.config.refresh_hot_pools {
+            return;
+        }
+
+        let hot_pools = self.pool_manager.hot_pools(block_number);
+        if hot_pools.is_empty() {
+            return;
+        }
+        let drifted = match self.refresh_pools(&hot_pools, block_number).await {
+            Ok(drifted) => drifted,
+            Err(e) => {
+                log_error!(block = block_number; "Failed to refresh hot pools: {}", e);
+                return;
+            }
+        };
+
+        metrics::POOLS_REFRESHED.inc_by(hot_pools.len() as u64);
+        metrics::POOL_STATE_DRIFTS.inc_by(drifted.len() as u64);
+        for (pool, cached, refreshed) in drifted {
+            log_info_cyan!(
+                block = block_number,
+                pool = pool;
+                "pool {:?} drifted from chain, cached {:?} refreshed to {:?}",
+                pool,
+                cached,
+                refreshed
+            );
+        }
+    }
+
+    /// Requeue parked txs that can pay the next block's base fee, dropping mined and expired ones
+    async fn unpark_includable_txs(&mut self, block_number: U64) {
+        if self.mempool_manager.parked_count() == 0 {
+            return;
+        }
+
+        let mined: HashSet<TxHash> = match self.provider.get_block(block_number).await {
+            Ok(Some(block)) => block.transactions.into_iter().collect(),
+            Ok(None) => HashSet::new(),
+            Err(e) => {
+                log_error!(block = block_number; "Failed to get mined txs: {}", e);
+                HashSet::new()
+            }
+        };
+
+        let next_block = self.block_manager.get_next_block();
+        self.mempool_manager.prune_parked(
+            &mined,
+            next_block.number,
+            self.config.parked_tx_ttl_blocks,
+        );
+
+        for tx in self
+            .mempool_manager
+            .take_includable(next_block.base_fee_per_gas)
+        {
+            // evaluated along with the next pending tx, like txs requeued after rpc errors
+            if self.mempool_manager.mark_seen(tx.hash, next_block.number) {
+                self.requeued_txs.push((next_block.number, tx));
+            }
+        }
+    }
+
+    /// Start monitoring pools created by our factories in `block_number` (new token launches)
+    async fn sync_new_pools(&mut self, block_number: U64) {
+        match self.pool_manager.sync_to_block(block_number.as_u64()).await {
+            Ok(0) => {}
+            Ok(new_pools) => {
+                log_info_cyan!(
+                    block = block_number;
+                    "added {} new pools in block {:?}", new_pools, block_number
+                );
+            }
+            Err(e) => log_error!(block = block_number; "Failed to sync new pools: {}", e),
+        }
+    }
+
+    /// Publish the funnel of `block_number` (just mined), its totals are logged every
+    /// `FUNNEL_SUMMARY_INTERVAL_BLOCKS`
+    fn close_funnel(&mut self, block_number: U64) {
+        metrics::record_block_funnel(&self.funnel.close_block());
+        if let Some(window) = self.funnel.poll_summary() {
+            log_info_cyan!(
+                block = block_number;
+                "funnel over the last {} blocks: {}", window.blocks, window.summary()
+            );
+        }
+    }
+
+    /// Check which of the bundles targeting `block_number` or earlier blocks landed and record
+    /// their profit, receipts are looked up until `inclusion_check_blocks` after a bundle's last
+    /// target block before it counts as not landed
+    async fn record_landed_bundles(&mut self, block_number: U64) {
+        let (targeted, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.submitted_bundles)
+            .into_iter()
+            .filter(|b| b.last_target_block + self.config.inclusion_check_blocks >= block_number)
+            .partition(|b| b.target_block <= block_number);
+        self.submitted_bundles = pending;
+
+        if let Some(loss_guard) = &mut self.loss_guard {
+            if loss_guard.poll_cooldown(block_number) {
+                log_info_cyan!(
+                    block = block_number;
+                    "loss guard cooldown over, submitting bundles again"
+                );
+            }
+        }
+        if let Some(victim_cooldown) = &mut self.victim_cooldown {
+            for sender in victim_cooldown.poll(block_number) {
+                log_info_cyan!(
+                    block = block_number;
+                    "victim cooldown of {:?} over, evaluating its txs again", sender
+                );
+            }
+        }
+
+        // bundles found onchain and their summed estimate, by the block they landed in
+        let mut landed_blocks: BTreeMap<U64, (usize, I256)> = BTreeMap::new();
+        for bundle in targeted {
+            let backrun_receipt = match self
+                .provider
+                .get_transaction_receipt(bundle.backrun_hash)
+                .await
+            {
+                // a receipt can show up a few blocks late, the bundle can only land in its targets
+                Ok(Some(receipt))
+                    if receipt.block_number.map_or(false, |landed_in| {
+                        bundle.target_block <= landed_in && landed_in <= bundle.last_target_block
+                    }) =>
+                {
+                    Some(receipt)
+                }
+                Ok(_) => None,
+                Err(e) => {
+                    log_error!(
+                        tx_hash = bundle.backrun_hash;
+                        "Failed to get backrun receipt: {}", e
+                    );
+                    None
+                }
+            };
+
+            let outcome = if let Some(backrun_receipt) = backrun_receipt {
+                let landed_in = backrun_receipt.block_number.unwrap_or(block_number);
+                let (outcome, net_profit) = self
+                    .record_landed_bundle(&bundle, landed_in, backrun_receipt)
+                    .await;
+                let (bundles, estimate) =
+                    landed_blocks.entry(landed_in).or_insert((0, I256::zero()));
+                *bundles += 1;
+                *estimate += net_profit;
+                outcome
+            } else {
+                // later bundles reuse the nonces from the first block it misses, it can still
+                // land in a later target block (then those fail instead)
+                if bundle.target_block == block_number {
+                    self.nonce_manager
+                        .release_bundle_nonces(bundle.frontrun_nonce);
+                }
+                if block_number <= bundle.last_target_block {
+                    self.escalate_missed_bundle(&bundle, block_number).await;
+                }
+                // target blocks left, or its receipt may still show up
+                if bundle.last_target_block + self.config.inclusion_check_blocks > block_number {
+                    self.submitted_bundles.push(bundle);
+                    continue;
+                }
+                self.record_missed_victims(&bundle, block_number);
+                OpportunityOutcome::NotLanded
+            };
+            self.record_opportunity(bundle.record.with_outcome(outcome));
+        }
+
+        for (landed_in, (bundles, estimate)) in landed_blocks {
+            self.reconcile_block_profit(landed_in, bundles, estimate)
+                .await;
+        }
+    }
+
+    /// Count a bundle whose backrun landed in `landed_in`, as failed if one of our txs reverted
+    ///
+    /// Returns its outcome and the net profit it counts with (the gas it lost if it failed)
+    async fn record_landed_bundle(
+        &mut self,
+        bundle: &SubmittedBundle,
+        landed_in: U64,
+        backrun_receipt: TransactionReceipt,
+    ) -> (OpportunityOutcome, I256) {
+        let failed = self.landed_reverted(bundle, &backrun_receipt).await;
+        if failed {
+            metrics::BUNDLES_LANDED_FAILED.inc();
+        } else {
+            metrics::BUNDLES_LANDED.inc();
+            self.funnel.record(FunnelStage::Landed, 1);
+        }
+        metrics::INCLUSION_LATENCY.observe(bundle.submitted_at.elapsed().as_secs_f64());
+
+        // a failed bundle made no revenue, what it lost is only known from its receipts
+        let realized = if failed || self.loss_guard.is_some() {
+            Some(self.realized_profit(bundle, backrun_receipt).await)
+        } else {
+            None
+        };
+        let (revenue, net_profit) = match (failed, realized) {
+            (true, Some(realized)) => (U256::zero(), realized),
+            _ => (bundle.revenue, bundle.net_profit),
+        };
+
+        metrics::GROSS_PROFIT.add(revenue.as_u128() as f64 / 1e18);
+        metrics::NET_PROFIT.add(net_profit.as_i128() as f64 / 1e18);
+        self.pool_manager
+            .record_pool_landed(bundle.pool, net_profit);
+        let mut landed = LandedBundle::new(
+            landed_in,
+            bundle.backrun_hash,
+            bundle.pool,
+            revenue,
+            net_profit,
+            bundle.record.clone(),
+        );
+        if let Some(realized) = realized {
+            self.record_realized_profit(landed_in, bundle, realized);
+            landed = landed.with_realized_profit(realized);
+        }
+        if failed {
+            landed = landed.with_status(BundleStatus::Failed);
+            log_error!(
+                block = landed_in,
+                tx_hash = bundle.backrun_hash;
+                "bundle landed in block {:?} but reverted, lost {} wei", landed_in, net_profit
+            );
+        }
+        for hook in &self.bundle_landed_hooks {
+            hook(&landed);
+        }
+        self.bundle_audit.record_landed(landed);
+
+        if failed {
+            return (OpportunityOutcome::LandedFailed, net_profit);
+        }
+        if let Some(victim_cooldown) = &mut self.victim_cooldown {
+            for victim in &bundle.victims {
+                victim_cooldown.record_landed(victim.tx.from);
+            }
+        }
+        (OpportunityOutcome::Landed, net_profit)
+    }
+
+    /// True if the landed bundle's backrun, or its frontrun if it has one, reverted onchain
+    async fn landed_reverted(
+        &self,
+        bundle: &SubmittedBundle,
+        backrun: &TransactionReceipt,
+    ) -> bool {
+        let reverted = |receipt: &TransactionReceipt| receipt.status == Some(U64::zero());
+        if reverted(backrun) {
+            return true;
+        }
+
+        let frontrun_hash = match bundle.frontrun_hash {
+            Some(frontrun_hash) => frontrun_hash,
+            None => return false,
+        };
+        match self.provider.get_transaction_receipt(frontrun_hash).await {
+            Ok(Some(frontrun)) => reverted(&frontrun),
+            Ok(None) => false,
+            Err(e) => {
+                log_error!(tx_hash = frontrun_hash; "Failed to get frontrun receipt: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Compare what the bundles that landed in `block_number` were estimated to make with how
+    /// much our balances (sando contracts' weth and eth, searcher's eth) changed across the block
+    /// note: anything else moving those balances in the block (a top up, a withdrawal) shows up
+    /// as profit or loss too
+    async fn reconcile_block_profit(&mut self, block_number: U64, bundles: usize, estimate: I256) {
+        let before = self.our_balances(block_number - 1).await;
+        let after = self.our_balances(block_number).await;
+        let realized = match (before, after) {
+            (Ok(before), Ok(after)) => before
+                .into_iter()
+                .zip(after)
+                .fold(I256::zero(), |total, (before, after)| {
+                    total + balance_delta(before, after)
+                }),
+            (Err(e), _) | (_, Err(e)) => {
+                log_error!(block = block_number; "Failed to reconcile landed profit: {}", e);
+                return;
+            }
+        };
+
+        let reconciliation = BlockReconciliation::new(block_number, bundles, estimate, realized);
+        log_info_cyan!(
+            block = block_number;
+            "{} bundles in block {:?} made {} wei onchain, estimated {} ({} off)",
+            bundles,
+            block_number,
+            realized,
+            estimate,
+            reconciliation.error()
+        );
+        self.bundle_audit.record_reconciliation(reconciliation);
+        self.publish_onchain_profit();
+    }
+
+    /// Weth and eth of every sando contract then the searcher's eth, as of `block_number`
+    async fn our_balances(&self, block_number: U64) -> Result<Vec<U256>> {
+        let block = BlockNumber::Number(block_number);
+        let weth = Erc20::new(self.config.weth_address, self.provider.clone());
+        let mut balances = vec![];
+        for sando_address in self.sando_state_manager.sando_addresses() {
+            let weth_balance = weth
+                .balance_of(sando_address)
+                .block(block)
+                .call()
+                .await
+                .map_err(|e| anyhow!("Failed to get weth of {:?}: {}", sando_address, e))?;
+            let eth_balance = self
+                .provider
+                .get_balance(sando_address, Some(block.into()))
+                .await
+                .map_err(|e| anyhow!("Failed to get eth of {:?}: {}", sando_address, e))?;
+            balances.extend([weth_balance, eth_balance]);
+        }
+
+        let searcher = self.sando_state_manager.get_searcher_address();
+        let balance = self
+            .provider
+            .get_balance(searcher, Some(block.into()))
+            .await
+            .map_err(|e| anyhow!("Failed to get searcher balance: {}", e))?;
+        balances.push(balance);
+        Ok(balances)
+    }
+
+    /// Export reconciled onchain profit and its estimate (taking back reorged blocks)
+    fn publish_onchain_profit(&self) {
+        let (realized, estimated) = self.bundle_audit.reconciled_profit();
+        metrics::ONCHAIN_PROFIT.set(realized.as_i128() as f64 / 1e18);
+        metrics::ONCHAIN_PROFIT_ESTIMATE.set(estimated.as_i128() as f64 / 1e18);
+    }
+
+    /// A bundle that missed `block_number` while its victim is still pending was likely outbid,
+    /// escalate bids around the victim (no-op unless `bid_escalation_step` is set)
+    /// note: once the bundle has no target block left its victims are retried for the next block
+    async fn escalate_missed_bundle(&mut self, bundle: &SubmittedBundle, block_number: U64) {
+        if !self.bidder.escalates() {
+            return;
+        }
+
+        match self
+            .provider
+            .get_transaction_receipt(bundle.victim_hash)
+            .await
+        {
+            Ok(None) => {}
+            // victim landed (or we can't tell), there's nothing left to retry
+            Ok(Some(_)) => return,
+            Err(e) => {
+                log_error!(tx_hash = bundle.victim_hash; "Failed to get victim receipt: {}", e);
+                return;
+            }
+        }
+
+        let next_block = block_number + 1;
+        let victims = match bundle.victims.is_empty() {
+            true => vec![bundle.victim_hash],
+            false => bundle.victims.iter().map(|victim| victim.tx.hash).collect(),
+        };
+        self.record_competition(victims, next_block, "bundle missed its block");
+
+        if bundle.last_target_block <= block_number {
+            self.requeued_txs.extend(
+                bundle
+                    .victims
+                    .iter()
+                    .map(|victim| (next_block, victim.tx.clone())),
+            );
+        }
+    }
+
+    /// Count a miss against the senders of a bundle that missed its last target block
+    /// `block_number`, too many in a row cool them down (no-op unless `victim_max_misses` is set)
+    fn record_missed_victims(&mut self, bundle: &SubmittedBundle, block_number: U64) {
+        let victim_cooldown = match &mut self.victim_cooldown {
+            Some(victim_cooldown) => victim_cooldown,
+            None => return,
+        };
+
+        for victim in &bundle.victims {
+            if victim_cooldown.record_missed(victim.tx.from, block_number) {
+                log_info_cyan!(
+                    block = block_number;
+                    "bundles around {:?} keep missing, skipping its txs for {} blocks",
+                    victim.tx.from,
+                    self.config.victim_cooldown_blocks
+                );
+            }
+        }
+    }
+
+    /// Take back profit counted for landed bundles whose block was reorged out, so metrics, pool
+    /// stats and the loss guard only count bundles that are still onchain
+    fn reconcile_reorged_bundles(&mut self) {
+        let reorged_blocks = self.block_manager.take_reorged_blocks();
+        if reorged_blocks.is_empty() {
+            return;
+        }
+
+        for bundle in self.bundle_audit.revert_blocks(&reorged_blocks) {
+            metrics::BUNDLES_REORGED.inc();
+            metrics::GROSS_PROFIT.sub(bundle.revenue.as_u128() as f64 / 1e18);
+            metrics::NET_PROFIT.sub(bundle.net_profit.as_i128() as f64 / 1e18);
+            self.pool_manager
+                .record_pool_reverted(bundle.pool, bundle.net_profit);
+            if let (Some(loss_guard), Some(realized)) =
+                (&mut self.loss_guard, bundle.realized_profit)
+            {
+                loss_guard.forget(bundle.block, realized);
+            }
+
+            log_info_cyan!(
+                block = bundle.block,
+                tx_hash = bundle.backrun_hash;
+                "bundle in block {:?} reorged out, took back {} wei profit",
+                bundle.block,
+                bundle.net_profit
+            );
+            self.record_opportunity(bundle.record.with_outcome(OpportunityOutcome::Reverted));
+        }
+        self.publish_onchain_profit();
+    }
+
+    /// Net profit that a landed bundle actually made, from its receipts (falls back to the
+    /// estimate if the frontrun's receipt can't be fetched)
+    async fn realized_profit(&self, bundle: &SubmittedBundle, backrun: TransactionReceipt) -> I256 {
+        let mut receipts = vec![backrun];
+        if let Some(frontrun_hash) = bundle.frontrun_hash {
+            match self.provider.get_transaction_receipt(frontrun_hash).await {
+                Ok(Some(frontrun)) => receipts.push(frontrun),
+                Ok(None) => return bundle.net_profit,
+                Err(e) => {
+                    log_error!(tx_hash = frontrun_hash; "Failed to get frontrun receipt: {}", e);
+                    return bundle.net_profit;
+                }
+            }
+        }
+
+        let coinbase_payment = match self.config.payment_strategy {
+            PaymentStrategy::CoinbaseTransfer => bundle.priority_fee,
+            PaymentStrategy::PriorityFee => U256::zero(),
+        };
+        realized_profit(&receipts, self.config.weth_address, coinbase_payment)
+    }
+
+    /// Feed a landed bundle's realized profit to the loss guard, tripping it loudly if the
+    /// window's loss got too large
+    fn record_realized_profit(
+        &mut self,
+        block_number: U64,
+        bundle: &SubmittedBundle,
+        realized: I256,
+    ) {
+        let loss_guard = match &mut self.loss_guard {
+            Some(loss_guard) => loss_guard,
+            None => return,
+        };
+
+        if !loss_guard.record(block_number, realized) {
+            return;
+        }
+
+        let until = match self.config.loss_cooldown_blocks {
+            Some(cooldown_blocks) => format!("{} blocks from now", cooldown_blocks),
+            None => "the bot is restarted".to_string(),
+        };
+        metrics::LOSS_GUARD_TRIPS.inc();
+        log_error!(
+            block = block_number,
+            tx_hash = bundle.victim_hash;
+            "LOSS GUARD TRIPPED: realized {} wei over the last {} blocks ({} on this bundle, \
+             estimated {}), not submitting bundles until {}",
+            loss_guard.window_profit(),
+            self.config.loss_window_blocks,
+            realized,
+            bundle.net_profit,
+            until
+        );
+    }
+
+    /// Pass `record` to opportunity hooks and append it to the opportunity log (if
+    /// `opportunity_log_path` is set)
+    fn record_opportunity(&self, record: OpportunityRecord) {
+        for hook in &self.opportunity_hooks {
+            hook(&record);
+        }
+        if let Some(opportunity_log) = &self.opportunity_log {
+            if let Err(e) = opportunity_log.record_opportunity(&record) {
+                log_error!(tx_hash = record.victim_hash; "Failed to record opportunity: {}", e);
+            }
+        }
+    }
+
+    /// Make sure our local nonce is not behind the searcher's onchain nonce
+    async fn sync_searcher_nonce(&self, block_number: U64) {
+        match self
+            .provider
+            .get_transaction_count(
+                self.sando_state_manager.get_searcher_address(),
+                Some(BlockNumber::Number(block_number).into()),
+            )
+            .await
+        {
+            Ok(nonce) => self.nonce_manager.sync_onchain_nonce(nonce),
+            Err(e) => log_error!("Failed to get searcher nonce: {}", e),
+        }
+    }
+
+    /// Refresh the sando contracts' weth inventory and the searcher's eth for gas every
+    /// `BALANCE_CHECK_INTERVAL_BLOCKS` blocks, alerting once when one drops below its floor
+    async fn monitor_balances(&mut self, block_number: U64) {
+        if block_number.as_u64() % BALANCE_CHECK_INTERVAL_BLOCKS != 0 {
+            return;
+        }
+
+        let weth = Erc20::new(self.config.weth_address, self.provider.clone());
+        for sando_address in self.sando_state_manager.sando_addresses() {
+            let balance = match weth
+                .balance_of(sando_address)
+                .block(BlockNumber::Number(block_number))
+                .call()
+                .await
+            {
+                Ok(balance) => balance,
+                Err(e) => {
+                    log_error!("Failed to get weth inventory of {:?}: {}", sando_address, e);
+                    continue;
+                }
+            };
+            // sizing spends what the contract holds now, not what it held at startup
+            self.sando_state_manager
+                .set_weth_inventory(sando_address, balance);
+            metrics::SANDO_WETH_INVENTORY
+                .with_label_values(&[&format!("{:?}", sando_address)])
+                .set(balance.as_u128() as f64 / 1e18);
+            if let Some(floor) = self.config.min_weth_inventory {
+                self.check_balance(block_number, sando_address, "weth", balance, floor);
+            }
+        }
+
+        let searcher = self.sando_state_manager.get_searcher_address();
+        match self
+            .provider
+            .get_balance(searcher, Some(BlockNumber::Number(block_number).into()))
+            .await
+        {
+            Ok(balance) => {
+                metrics::SEARCHER_ETH_BALANCE.set(balance.as_u128() as f64 / 1e18);
+                if let Some(floor) = self.config.min_searcher_balance {
+                    self.check_balance(block_number, searcher, "eth", balance, floor);
+                }
+            }
+            Err(e) => log_error!("Failed to get searcher balance: {}", e),
+        }
+    }
+
+    /// Log and alert if `account`'s `asset` balance just dropped below `floor`
+    fn check_balance(
+        &mut self,
+        block_number: U64,
+        account: Address,
+        asset: &str,
+        balance: U256,
+        floor: U256,
+    ) {
+        if !self.balance_monitor.check(account, balance, floor) {
+            return;
+        }
+
+        let alert = LowBalanceAlert::new(block_number, account, asset, balance, floor);
+        log_error!("{}", alert.message());
+        if let Some(notifier) = &self.alert_notifier {
+            notifier.notify(alert);
+        }
+    }
+
+    /// Forget sandwiches around a victim tx that was replaced or mined (`why` it is gone),
+    /// freeing their nonces and block budget so the replacement can be sandwiched instead
+    /// note: bundles already sent can't be recalled, they just fail to land
+    fn invalidate_sandwiches_for(&mut self, replaced_hash: TxHash, target_block: U64, why: &str) {
+        self.requeued_txs.retain(|(_, tx)| tx.hash != replaced_hash);
+
+        let (invalidated, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.submitted_bundles)
+            .into_iter()
+            .partition(|b| {
+                b.target_block == target_block
+                    && (b.victim_hash == replaced_hash
+                        || b.victims
+                            .iter()
+                            .any(|victim| victim.tx.hash == replaced_hash))
+            });
+        self.submitted_bundles = kept;
+
+        for bundle in invalidated {
+            log_info_cyan!(
+                tx_hash = replaced_hash;
+                "{:?} {}, dropping its bundle", replaced_hash, why
+            );
+            self.nonce_manager.cancel_bundle(bundle.frontrun_nonce);
+            self.record_opportunity(bundle.record.rejected(format!("victim tx {}", why)));
+        }
+    }
+
+    /// Priority fee to bid for a sandwich or backrun around `victim_tx`, escalated if other
+    /// searchers were seen competing for it (see `bid_escalation_step`)
+    fn bid_for(&self, victim_tx: &Transaction, net_profit: U256) -> U256 {
+        let escalation_steps = self.bidder.escalation_steps([&victim_tx.hash]);
+        let priority_fee = self.bidder.compute_priority_fee(
+            net_profit,
+            self.config.competition_level,
+            escalation_steps,
+        );
+        if escalation_steps > 0 {
+            log_info_cyan!(
+                tx_hash = victim_tx.hash;
+                "{:?} bid escalated to {} wei after {} competition signals",
+                victim_tx.hash,
+                priority_fee,
+                escalation_steps
+            );
+        }
+        priority_fee
+    }
+
+    /// Record that another searcher competes for `victims`, bids around them escalate for as
+    /// long as bundles built now could target
+    fn record_competition(&mut self, victims: Vec<TxHash>, next_block: U64, signal: &str) {
+        let until_block = next_block + self.max_target_block_offset();
+        for victim in victims {
+            let steps = self.bidder.record_competition(victim, until_block);
+            if steps > 0 {
+                log_info_cyan!(
+                    tx_hash = victim;
+                    "{:?} competition signal ({}), escalating bids to step {}",
+                    victim,
+                    signal,
+                    steps
+                );
+            }
+        }
+    }
+
+    /// Sign the most profitable backrun as a private tx
+    /// note: without a frontrun there is nothing to bundle, the backrun only has to land
+    /// after the victim so it is sent with `eth_sendPrivateTransaction`
+    async fn build_private_backrun(
+        &mut self,
+        victim_tx: &Transaction,
+        backruns: Vec<BackrunOpportunity>,
+        sando_address: Address,
+        next_block: BlockInfo,
+    ) -> Option<Action> {
+        let backrun = backruns
+            .into_iter()
+            .max_by_key(|backrun| backrun.profit.net_profit)?;
+        let pool_address = backrun.pool.address;
+
+        // bid part of our profit to outbid competing searchers
+        let net_profit = backrun.profit.net_profit.into_raw();
+        let priority_fee = self.bid_for(victim_tx, net_profit);
+        let backrun_profit = I256::from_raw(net_profit) - I256::from_raw(priority_fee);
+        let record = OpportunityRecord::new(
+            next_block.number,
+            victim_tx.hash,
+            pool_address,
+            U256::zero(),
+            backrun.profit.net_profit,
+        )
+        .with_bid(priority_fee);
+
+        let nonce = match self
+            .nonce_manager
+            .reserve_nonce_for_block(next_block.number)
+        {
+            Some(nonce) => nonce,
+            None => {
+                log_not_sandwichable!(
+                    tx_hash = victim_tx.hash,
+                    block = next_block.number;
+                    "{:?} bundle budget for block {:?} used up",
+                    victim_tx.hash,
+                    next_block.number
+                );
+                metrics::record_rejection(victim_tx.hash, RejectReason::BundleBudget);
+                self.record_opportunity(record.rejected("bundle budget used up"));
+                return None;
+            }
+        };
+
+        let backrun_call = self.sando_encoder.encode_backrun(
+            UniswapV2(backrun.pool),
+            backrun.token,
+            backrun.amount_in,
+            backrun.amount_out,
+        );
+        let gas = self.gas_profile(sando_address);
+        let (backrun_call, backrun_gas, max_priority_fee) = match self.config.payment_strategy {
+            PaymentStrategy::PriorityFee => {
+                (backrun_call, gas.v2_backrun, priority_fee / gas.v2_backrun)
+            }
+            PaymentStrategy::CoinbaseTransfer => (
+                backrun_call.with_coinbase_payment(priority_fee),
+                gas.v2_backrun + COINBASE_PAYMENT_GAS_ESTIMATE,
+                U256::zero(),
+            ),
+        };
+
+        let backrun_tx = Eip1559TransactionRequest {
+            to: Some(sando_address.into()),
+            gas: Some(padded_gas_limit(
+                backrun_gas,
+                self.config.gas_limit_multiplier,
+            )),
+            value: Some(backrun_call.value),
+            data: Some(backrun_call.calldata),
+            nonce: Some(nonce),
+            max_priority_fee_per_gas: Some(max_priority_fee),
+            max_fee_per_gas: Some(
+                self.with_safety_margin(next_block.base_fee_per_gas) + max_priority_fee,
+            ),
+            ..Default::default()
+        };
+        let signed_backrun =
+            match sign_eip1559(backrun_tx, self.sando_state_manager.get_searcher_signer()).await {
+                Ok(signed_backrun) => signed_backrun,
+                Err(e) => {
+                    self.nonce_manager.cancel_bundle(nonce);
+                    log_not_sandwichable!(
+                        tx_hash = victim_tx.hash,
+                        pool = pool_address;
+                        "{:?}", e
+                    );
+                    metrics::record_rejection(victim_tx.hash, RejectReason::BuildFailed);
+                    self.record_opportunity(record.rejected(format!("failed to sign: {}", e)));
+                    return None;
+                }
+            };
+
+        log_info_cyan!(
+            tx_hash = victim_tx.hash,
+            pool = pool_address,
+            profit = backrun_profit;
+            "{:?} private backrun on {:?}", victim_tx.hash, pool_address
+        );
+
+        self.record_opportunity(record.clone());
+
+        // tracked like a bundle so its profit is recorded (or nonce released) once mined
+        #[cfg(not(feature = "debug"))]
+        self.submitted_bundles.push(SubmittedBundle {
+            target_block: next_block.number,
+            last_target_block: next_block.number,
+            victim_hash: victim_tx.hash,
+            pool: pool_address,
+            priority_fee,
+            frontrun_hash: None,
+            backrun_hash: H256::from(keccak256(&signed_backrun)),
+            frontrun_nonce: nonce,
+            revenue: backrun.profit.gross_revenue,
+            net_profit: backrun_profit,
+            private: true,
+            record,
+            victims: vec![],
+            direction: SwapDirection::WethToToken,
+            submitted_at: Instant::now(),
+        });
+
+        // private txs are never sent in debug mode, free up the nonce
+        #[cfg(feature = "debug")]
+        self.nonce_manager.cancel_bundle(nonce);
+
+        Some(Action::SubmitPrivateTx {
+            txs: vec![signed_backrun],
+            target_block: next_block.number,
+            net_profit: backrun_profit,
+        })
+    }
+
+    /// Sign a frontrun only bundle, our buy right before the victim
+    /// note: the contract only pays a coinbase transfer from a backrun, so the builder is always
+    /// tipped through the frontrun's priority fee
+    async fn build_frontrun_only(
+        &mut self,
+        victim_info: &VictimInfo,
+        frontrun: FrontrunOpportunity,
+        sando_address: Address,
+        next_block: BlockInfo,
+    ) -> Option<Action> {
+        let victim_tx = &victim_info.tx;
+        let pool_address = frontrun.pool.address;
+
+        // another bundle's math already priced the pool without our buy
+        let conflicting = self
+            .submitted_bundles
+            .iter()
+            .any(|b| b.target_block == next_block.number && b.pool == pool_address);
+        if conflicting {
+            log_not_sandwichable!(
+                tx_hash = victim_tx.hash,
+                pool = pool_address;
+                "{:?} frontrun only bundle trades a pool with an outstanding bundle",
+                victim_tx.hash
+            );
+            metrics::record_rejection(victim_tx.hash, RejectReason::ConflictingBundle);
+            return None;
+        }
+
+        let net_profit = frontrun.profit.net_profit.into_raw();
+        let priority_fee = self.bid_for(victim_tx, net_profit);
+        let frontrun_profit = I256::from_raw(net_profit) - I256::from_raw(priority_fee);
+        let record = OpportunityRecord::new(
+            next_block.number,
+            victim_tx.hash,
+            pool_address,
+            frontrun.buy.amount_in,
+            frontrun.profit.net_profit,
+        )
+        .with_input_token(self.config.weth_address, Some(18))
+        .with_bid(priority_fee);
+
+        let nonce = match self
+            .nonce_manager
+            .reserve_nonce_for_block(next_block.number)
+        {
+            Some(nonce) => nonce,
+            None => {
+                log_not_sandwichable!(
+                    tx_hash = victim_tx.hash,
+                    block = next_block.number;
+                    "{:?} bundle budget for block {:?} used up",
+                    victim_tx.hash,
+                    next_block.number
+                );
+                metrics::record_rejection(victim_tx.hash, RejectReason::BundleBudget);
+                self.record_opportunity(record.rejected("bundle budget used up"));
+                return None;
+            }
+        };
+
+        let frontrun_call = self.sando_encoder.encode_frontrun(
+            UniswapV2(frontrun.pool),
+            frontrun.token,
+            frontrun.buy.amount_in,
+            frontrun.buy.amount_out,
+        );
+        let frontrun_gas = self.gas_profile(sando_address).v2_frontrun;
+        let max_priority_fee = priority_fee / frontrun_gas;
+        let frontrun_tx = Eip1559TransactionRequest {
+            to: Some(sando_address.into()),
+            gas: Some(padded_gas_limit(
+                frontrun_gas,
+                self.config.gas_limit_multiplier,
+            )),
+            value: Some(frontrun_call.value),
+            data: Some(frontrun_call.calldata),
+            nonce: Some(nonce),
+            max_priority_fee_per_gas: Some(max_priority_fee),
+            max_fee_per_gas: Some(
+                self.with_safety_margin(next_block.base_fee_per_gas) + max_priority_fee,
+            ),
+            ..Default::default()
+        };
+        let signed_frontrun =
+            match sign_eip1559(frontrun_tx, self.sando_state_manager.get_searcher_signer()).await {
+                Ok(signed_frontrun) => signed_frontrun,
+                Err(e) => {
+                    self.nonce_manager.cancel_bundle(nonce);
+                    log_not_sandwichable!(
+                        tx_hash = victim_tx.hash,
+                        pool = pool_address;
+                        "{:?}", e
+                    );
+                    metrics::record_rejection(victim_tx.hash, RejectReason::BuildFailed);
+                    self.record_opportunity(record.rejected(format!("failed to sign: {}", e)));
+                    return None;
+                }
+            };
+
+        let bundle = BundleRequest::new()
+            .push_transaction(signed_frontrun.clone())
+            .push_transaction(victim_tx.rlp())
+            .set_block(next_block.number)
+            .set_simulation_block(next_block.number - 1)
+            .set_simulation_timestamp(next_block.timestamp.as_u64());
+
+        log_info_cyan!(
+            tx_hash = victim_tx.hash,
+            pool = pool_address,
+            profit = frontrun_profit;
+            "{:?} frontrun only bundle on {:?}", victim_tx.hash, pool_address
+        );
+
+        self.record_opportunity(record.clone());
+
+        // tracked like a sandwich so its profit is recorded (or nonce released) once mined
+        #[cfg(not(feature = "debug"))]
+        self.submitted_bundles.push(SubmittedBundle {
+            target_block: next_block.number,
+            last_target_block: next_block.number,
+            victim_hash: victim_tx.hash,
+            pool: pool_address,
+            priority_fee,
+            frontrun_hash: None,
+            backrun_hash: H256::from(keccak256(&signed_frontrun)),
+            frontrun_nonce: nonce,
+            revenue: frontrun.profit.gross_revenue,
+            net_profit: frontrun_profit,
+            private: true,
+            record,
+            victims: vec![victim_info.clone()],
+            direction: SwapDirection::WethToToken,
+            submitted_at: Instant::now(),
+        });
+
+        // bundles are never sent in debug mode, free up the nonce
+        #[cfg(feature = "debug")]
+        self.nonce_manager.cancel_bundle(nonce);
+
+        Some(Action::SubmitBundle {
+            bundles: vec![bundle],
+            target_block: next_block.number,
+            net_profits: vec![frontrun_profit],
+            target_blocks: vec![vec![next_block.number]],
+        })
+    }
+
+    /// Sign an arb's two legs as private txs behind the victim
+    /// note: the contract swaps once per call, so the arb is two txs with consecutive nonces,
+    /// the sell can't land without the buy before it
+    async fn build_backrun_arb(
+        &mut self,
+        victim_info: &VictimInfo,
+        arb: ArbOpportunity,
+        sando_address: Address,
+        next_block: BlockInfo,
+    ) -> Option<Action> {
+        let victim_tx = &victim_info.tx;
+        let pool_address = arb.sell_pool.address;
+
+        // either leg trades a pool another bundle's math already priced
+        let conflicting = self.submitted_bundles.iter().any(|b| {
+            b.target_block == next_block.number
+                && (b.pool == arb.buy_pool.address || b.pool == arb.sell_pool.address)
+        });
+        if conflicting {
+            log_not_sandwichable!(
+                tx_hash = victim_tx.hash,
+                pool = pool_address;
+                "{:?} arb trades a pool with an outstanding bundle",
+                victim_tx.hash
+            );
+            metrics::record_rejection(victim_tx.hash, RejectReason::ConflictingBundle);
+            return None;
+        }
+
+        let net_profit = arb.profit.net_profit.into_raw();
+        let priority_fee = self.bid_for(victim_tx, net_profit);
+        let arb_profit = I256::from_raw(net_profit) - I256::from_raw(priority_fee);
+        let record = OpportunityRecord::new(
+            next_block.number,
+            victim_tx.hash,
+            pool_address,
+            arb.buy.amount_in,
+            arb.profit.net_profit,
+        )
+        .with_input_token(self.config.weth_address, Some(18))
+        .with_bid(priority_fee);
+
+        let (buy_nonce, sell_nonce) = match self
+            .nonce_manager
+            .reserve_nonces_for_block(next_block.number)
+        {
+            Some(nonces) => nonces,
+            None => {
+                log_not_sandwichable!(
+                    tx_hash = victim_tx.hash,
+                    block = next_block.number;
+                    "{:?} bundle budget for block {:?} used up",
+                    victim_tx.hash,
+                    next_block.number
+                );
+                metrics::record_rejection(victim_tx.hash, RejectReason::BundleBudget);
+                self.record_opportunity(record.rejected("bundle budget used up"));
+                return None;
+            }
+        };
+
+        let buy_call = self.sando_encoder.encode_frontrun(
+            UniswapV2(arb.buy_pool),
+            arb.token,
+            arb.buy.amount_in,
+            arb.buy.amount_out,
+        );
+        let sell_call = self.sando_encoder.encode_backrun(
+            UniswapV2(arb.sell_pool),
+            arb.token,
+            arb.sell.amount_in,
+            arb.sell.amount_out,
+        );
+        // the sell leg pays the builder, same as a sandwich backrun
+        let gas = self.gas_profile(sando_address);
+        let (sell_call, sell_gas, max_priority_fee) = match self.config.payment_strategy {
+            PaymentStrategy::PriorityFee => {
+                (sell_call, gas.v2_backrun, priority_fee / gas.v2_backrun)
+            }
+            PaymentStrategy::CoinbaseTransfer => (
+                sell_call.with_coinbase_payment(priority_fee),
+                gas.v2_backrun + COINBASE_PAYMENT_GAS_ESTIMATE,
+                U256::zero(),
+            ),
+        };
+
+        let buy_tx = Eip1559TransactionRequest {
+            to: Some(sando_address.into()),
+            gas: Some(padded_gas_limit(
+                gas.v2_frontrun,
+                self.config.gas_limit_multiplier,
+            )),
+            value: Some(buy_call.value),
+            data: Some(buy_call.calldata),
+            nonce: Some(buy_nonce),
+            max_priority_fee_per_gas: Some(U256::zero()),
+            // a buy the base fee outgrows would leave the sell stuck behind its nonce
+            max_fee_per_gas: Some(self.with_safety_margin(next_block.base_fee_per_gas)),
+            ..Default::default()
+        };
+        let sell_tx = Eip1559TransactionRequest {
+            to: Some(sando_address.into()),
+            gas: Some(padded_gas_limit(sell_gas, self.config.gas_limit_multiplier)),
+            value: Some(sell_call.value),
+            data: Some(sell_call.calldata),
+            nonce: Some(sell_nonce),
+            max_priority_fee_per_gas: Some(max_priority_fee),
+            max_fee_per_gas: Some(
+                self.with_safety_margin(next_block.base_fee_per_gas) + max_priority_fee,
+            ),
+            ..Default::default()
+        };
+
+        let searcher = self.sando_state_manager.get_searcher_signer();
+        let signed = async {
+            let signed_buy = sign_eip1559(buy_tx, searcher).await?;
+            let signed_sell = sign_eip1559(sell_tx, searcher).await?;
+            Ok::<_, anyhow::Error>((signed_buy, signed_sell))
+        }
+        .await;
+        let (signed_buy, signed_sell) = match signed {
+            Ok(signed) => signed,
+            Err(e) => {
+                self.nonce_manager.cancel_bundle(buy_nonce);
+                log_not_sandwichable!(
+                    tx_hash = victim_tx.hash,
+                    pool = pool_address;
+                    "{:?}", e
+                );
+                metrics::record_rejection(victim_tx.hash, RejectReason::BuildFailed);
+                self.record_opportunity(record.rejected(format!("failed to sign: {}", e)));
+                return None;
+            }
+        };
+
+        log_info_cyan!(
+            tx_hash = victim_tx.hash,
+            pool = pool_address,
+            profit = arb_profit;
+            "{:?} backrun arb buying on {:?} and selling on {:?}",
+            victim_tx.hash,
+            arb.buy_pool.address,
+            arb.sell_pool.address
+        );
+
+        self.record_opportunity(record.clone());
+
+        // tracked like a bundle so its profit is recorded (or nonces released) once mined
+        #[cfg(not(feature = "debug"))]
+        self.submitted_bundles.push(SubmittedBundle {
+            target_block: next_block.number,
+            last_target_block: next_block.number,
+            victim_hash: victim_tx.hash,
+            pool: pool_address,
+            priority_fee,
+            frontrun_hash: Some(H256::from(keccak256(&signed_buy))),
+            backrun_hash: H256::from(keccak256(&signed_sell)),
+            frontrun_nonce: buy_nonce,
+            revenue: arb.profit.gross_revenue,
+            net_profit: arb_profit,
+            private: true,
+            record,
+            victims: vec![victim_info.clone()],
+            direction: SwapDirection::WethToToken,
+            submitted_at: Instant::now(),
+        });
+
+        // private txs are never sent in debug mode, free up their nonces
+        #[cfg(feature = "debug")]
+        self.nonce_manager.cancel_bundle(buy_nonce);
+
+        Some(Action::SubmitPrivateTx {
+            txs: vec![signed_buy, signed_sell],
+            target_block: next_block.number,
+            net_profit: arb_profit,
+        })
+    }
+
+    /// True if a conflicting bundle is profitable enough to supersede `outstanding`
+    /// note: builders pick the higher paying of two bundles with the same nonces, so the
+    /// replacement also has to pay the builder more
+    fn should_replace(
+        &self,
+        outstanding: &SubmittedBundle,
+        net_profit: I256,
+        priority_fee: U256,
+    ) -> bool {
+        let margin_bps = I256::from(self.config.min_replacement_gain_bps);
+        let min_profit =
+            outstanding.net_profit + outstanding.net_profit.abs() * margin_bps / I256::from(10_000);
+
+        net_profit > min_profit && priority_fee > outstanding.priority_fee
+    }
+
+    /// Process a new tx along with requeued txs that still target the next block
+    async fn process_new_txs_with_requeued(
+        &mut self,
+        pending_txs: Vec<Transaction>,
+    ) -> Option<Action> {
+        metrics::TXS_SEEN.inc_by(pending_txs.len() as u64);
+        self.funnel
+            .record(FunnelStage::Seen, pending_txs.len() as u64);
+
+        // loss guard tripped, don't risk more until it is re-enabled
+        if self.loss_guard.as_ref().map_or(false, LossGuard::is_halted) {
+            return None;
+        }
+
+        // requeued txs can't have been mined if the next block hasn't changed
+        let next_block = self.block_manager.get_next_block();
+
+        let mut fresh_txs = vec![];
+        for victim_tx in pending_txs {
+            // victim sped up or cancelled their tx, anything built around the old version is dead
+            if let Some(replaced_hash) = self.mempool_manager.track_replacement(&victim_tx) {
+                self.invalidate_sandwiches_for(replaced_hash, next_block.number, "was replaced");
+            }
+
+            // same tx often arrives from several peers, only trace it once per target block
+            if self
+                .mempool_manager
+                .mark_seen(victim_tx.hash, next_block.number)
+            {
+                fresh_txs.push(victim_tx);
+            }
+        }
+        let requeued_txs: Vec<Transaction> = std::mem::take(&mut self.requeued_txs)
+            .into_iter()
+            .filter(|(target_block, _)| *target_block == next_block.number)
+            .map(|(_, tx)| tx)
+            .collect();
+
+        let screened: Vec<VictimInfo> = requeued_txs
+            .into_iter()
+            .chain(fresh_txs)
+            .filter_map(|tx| self.screen_victim(tx))
+            .collect();
+        self.funnel
+            .record(FunnelStage::Prefiltered, screened.len() as u64);
+        let mut victims = self.trace_victims(screened).await;
+        // victims on hot pools get the block's budget first, those only on cold pools last
+        if self.pool_manager.tracks_activity() {
+            victims.sort_by_key(|victim| self.victim_activity(victim));
+        }
+
+        // held sandwiches are sent before this event's victims take any of the block's budget
+        let (mut sando_bundles, mut net_profits, mut target_blocks) =
+            self.submit_ranked_sandwiches(next_block).await;
+        let mut victims = victims.into_iter();
+        while let Some(victim_info) = victims.next() {
+            // private backruns and arbs are sent as their own action, so only fall back to one
+            // while no bundle has been collected for this event
+            let allow_private_backrun = sando_bundles.is_empty();
+            let victim_hash = victim_info.tx.hash;
+            let nonce_before = self.nonce_manager.get_next_nonce();
+            let action = match evaluate_within(
+                self.victim_timeout(),
+                self.process_traced_victim(victim_info, allow_private_backrun),
+            )
+            .await
+            {
+                Some(action) => action,
+                None => {
+                    metrics::VICTIM_TIMEOUTS.inc();
+                    metrics::record_rejection(victim_hash, RejectReason::Timeout);
+                    log_error!(
+                        tx_hash = victim_hash;
+                        "{:?} evaluation timed out after {:?}, moving on",
+                        victim_hash,
+                        self.victim_timeout()
+                    );
+                    // nothing it reserved was sent, later bundles reuse its nonces
+                    self.nonce_manager.release_bundle_nonces(nonce_before);
+                    None
+                }
+            };
+            // a victim mined while we evaluated it already moved its pools, a frontrun built on
+            // the state before it would only lose
+            if action.is_some()
+                && self
+                    .victim_already_mined(victim_hash, next_block.number)
+                    .await
+            {
+                metrics::record_rejection(victim_hash, RejectReason::AlreadyMined);
+                self.invalidate_sandwiches_for(victim_hash, next_block.number, "was already mined");
+                continue;
+            }
+            match action {
+                Some(Action::SubmitBundle {
+                    bundles,
+                    net_profits: bundle_profits,
+                    target_blocks: bundle_target_blocks,
+                    ..
+                }) => {
+                    sando_bundles.extend(bundles);
+                    net_profits.extend(bundle_profits);
+                    target_blocks.extend(bundle_target_blocks);
+                }
+                Some(action @ Action::SubmitPrivateTx { .. }) => {
+                    // multiple actions per event not supported, pick up the rest next event
+                    self.requeued_txs
+                        .extend(victims.map(|victim| (next_block.number, victim.tx)));
+                    if self.state_went_stale(next_block.number, "private tx").await {
+                        return None;
+                    }
+                    metrics::PRIVATE_TXS_SUBMITTED.inc();
+                    self.funnel.record(FunnelStage::Submitted, 1);
+                    return Some(action);
+                }
+                None => {}
+            }
+        }
+
+        if sando_bundles.is_empty() {
+            return None;
+        }
+        let bundles = format!("{} bundles", sando_bundles.len());
+        if self.state_went_stale(next_block.number, &bundles).await {
+            return None;
+        }
+
+        metrics::BUNDLES_SUBMITTED.inc_by(sando_bundles.len() as u64);
+        self.funnel
+            .record(FunnelStage::Submitted, sando_bundles.len() as u64);
+
+        Some(Action::SubmitBundle {
+            bundles: sando_bundles,
+            target_block: next_block.number,
+            net_profits,
+            target_blocks,
+        })
+    }
+
+    /// Longest a single victim may be traced or evaluated for (see `victim_timeout_ms`)
+    fn victim_timeout(&self) -> Option<Duration> {
+        self.config.victim_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Furthest of `target_block_offsets`, our txs are priced to stay valid that many blocks
+    /// past the next one
+    fn max_target_block_offset(&self) -> u64 {
+        self.config
+            .target_block_offsets
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Pending txs on `pool` a builder would run before `victim_tx`, empty unless victims are
+    /// assumed to land after pending txs or a sandwich netting `net_profit` clears
+    /// `preceding_txs_min_profit`
+    fn preceding_txs_for(
+        &self,
+        victim_tx: &Transaction,
+        pool: Address,
+        net_profit: I256,
+    ) -> Vec<Transaction> {
+        let high_value = self
+            .config
+            .preceding_txs_min_profit
+            .map_or(false, |min_profit| net_profit >= I256::from_raw(min_profit));
+        let after_pending = self.config.victim_position_assumption == VictimPosition::AfterPending;
+        if !high_value && !after_pending {
+            return vec![];
+        }
+        self.pending_before(victim_tx, pool)
+            .into_iter()
+            .map(|pending| pending.tx.clone())
+            .collect()
+    }
+
+    /// Traced pending txs on `pool` that a builder would run before `victim_tx`, in order
+    fn pending_before(&self, victim_tx: &Transaction, pool: Address) -> Vec<&VictimInfo> {
+        let pending = match self.pool_pending_txs.get(&pool) {
+            Some(pending) => pending,
+            None => return vec![],
+        };
+        let txs: Vec<Transaction> = pending.iter().map(|pending| pending.tx.clone()).collect();
+        let base_fee = self.block_manager.get_next_block().base_fee_per_gas;
+        preceding_txs(victim_tx, &txs, base_fee)
+            .iter()
+            .filter_map(|tx| pending.iter().find(|pending| pending.tx.hash == tx.hash))
+            .collect()
+    }
+
+    /// Swaps that the pending txs a builder runs before `victim` make through the v2 `pool`,
+    /// oriented along a frontrun in `direction` (none unless victims are assumed to land after
+    /// pending txs)
+    fn pending_v2_swaps(
+        &self,
+        victim: &VictimInfo,
+        pool: Address,
+        token: Address,
+        direction: SwapDirection,
+    ) -> Vec<PendingV2Swap> {
+        if self.config.victim_position_assumption != VictimPosition::AfterPending {
+            return vec![];
+        }
+
+        let weth = self.config.weth_address;
+        self.pending_before(&victim.tx, pool)
+            .into_iter()
+            .filter_map(|pending| {
+                match pending.get_v2_sandwich_reserves(pool, token, weth, direction) {
+                    Some((amount_in, _, _)) => Some(PendingV2Swap::SameDirection(amount_in)),
+                    None => pending
+                        .get_v2_sandwich_reserves(pool, token, weth, direction.opposite())
+                        .map(|(amount_in, _, _)| PendingV2Swap::OppositeDirection(amount_in)),
+                }
+            })
+            .collect()
+    }
+
+    /// `base_fee` raised by `base_fee_safety_margin_bps`
+    fn with_safety_margin(&self, base_fee: U256) -> U256 {
+        with_base_fee_margin(base_fee, self.config.base_fee_safety_margin_bps)
+    }
+
+    /// Blocks from `target_block_offsets` to send a bundle around `victim_tx` for, skipping blocks
+    /// whose base fee could outgrow what the victim pays (our txs cover the furthest one)
+    fn bundle_target_blocks(&self, victim_tx: &Transaction, next_block: U64) -> Vec<U64> {
+        let predicted_base_fee = self.block_manager.predict_next_base_fee();
+        self.config
+            .target_block_offsets
+            .iter()
+            .filter(|offset| {
+                max_fee_per_gas(victim_tx) >= max_base_fee_after(predicted_base_fee, **offset)
+            })
+            .map(|offset| next_block + *offset)
+            .collect()
+    }
+
+    /// Sandwiches for `next_block` are held while it is further away than
+    /// `rank_bundles_ms_before_block`
+    fn holds_for_ranking(&self, next_block: BlockInfo) -> bool {
+        if self.replaying {
+            return false;
+        }
+        self.config
+            .rank_bundles_ms_before_block
+            .map_or(false, |window_ms| {
+                ms_until(next_block.timestamp) > window_ms as i64
+            })
+    }
+
+    /// Drop sandwiches held for blocks before `target_block`, they missed their block
+    fn expire_held_sandwiches(&mut self, target_block: U64) {
+        for expired in self.bundle_ranking.expire_before(target_block) {
+            metrics::record_rejection(
+                expired.opportunity.record.victim_hash,
+                RejectReason::HeldPastBlock,
+            );
+            self.record_opportunity(
+                expired
+                    .opportunity
+                    .record
+                    .rejected("held past its target block"),
+            );
+        }
+    }
+
+    /// Once `next_block` is within `rank_bundles_ms_before_block`, sign and send the most
+    /// profitable held sandwiches that fit the block's bundle and gas budget
+    ///
+    /// Returns the bundles with their net profits and target blocks (empty while still holding)
+    async fn submit_ranked_sandwiches(
+        &mut self,
+        next_block: BlockInfo,
+    ) -> (Vec<BundleRequest>, Vec<I256>, Vec<Vec<U64>>) {
+        let mut sando_bundles = vec![];
+        let mut net_profits = vec![];
+        let mut bundle_target_blocks = vec![];
+        if self.holds_for_ranking(next_block) {
+            return (sando_bundles, net_profits, bundle_target_blocks);
+        }
+
+        self.expire_held_sandwiches(next_block.number);
+        let held = self.bundle_ranking.take(next_block.number);
+        if held.is_empty() {
+            return (sando_bundles, net_profits, bundle_target_blocks);
+        }
+
+        let mut budget = BlockBudget {
+            max_bundles: self.config.max_bundles_per_block,
+            max_gas: self.config.max_bundle_gas_per_block.unwrap_or(u64::MAX),
+        };
+        // next block's gas limit isn't known yet, it moves little from the latest one's
+        let gas_limit = self.block_manager.get_latest_block().gas_limit;
+        if let (Some(fraction), Some(gas_limit)) = (self.config.max_block_gas_fraction, gas_limit) {
+            budget = budget.with_block_gas_fraction(gas_limit.as_u64(), fraction);
+        }
+        let held_count = held.len();
+        let (selected, dropped) = select_top_opportunities(held, budget);
+        log_info_cyan!(
+            block = next_block.number;
+            "ranked {} held sandwiches for block {:?}, sending the top {}",
+            held_count,
+            next_block.number,
+            selected.len()
+        );
+
+        for outranked in dropped {
+            metrics::SANDWICHES_OUTRANKED.inc();
+            metrics::record_rejection(
+                outranked.opportunity.record.victim_hash,
+                RejectReason::Outranked,
+            );
+            self.record_opportunity(
+                outranked
+                    .opportunity
+                    .record
+                    .rejected("outranked by the block's other sandwiches"),
+            );
+        }
+
+        for selected in selected {
+            let HeldOpportunity {
+                pool,
+                net_profit,
+                opportunity: held,
+                ..
+            } = selected;
+            let victim_hash = held.victim.tx.hash;
+
+            // base fee may have moved since the sandwich was held
+            let target_blocks = self.bundle_target_blocks(&held.victim.tx, next_block.number);
+            if target_blocks.is_empty() {
+                metrics::record_rejection(held.record.victim_hash, RejectReason::BaseFeeTooHigh);
+                self.record_opportunity(
+                    held.record
+                        .rejected("victim can't pay the base fee of any target block"),
+                );
+                continue;
+            }
+
+            let (frontrun_nonce, backrun_nonce) = match self
+                .nonce_manager
+                .reserve_nonces_for_block(next_block.number)
+            {
+                Some(nonces) => nonces,
+                None => {
+                    metrics::record_rejection(held.record.victim_hash, RejectReason::BundleBudget);
+                    self.record_opportunity(held.record.rejected("bundle budget used up"));
+                    continue;
+                }
+            };
+
+            let _bundle = match held
+                .recipe
+                .to_fb_bundle(
+                    held.sando_address,
+                    self.sando_state_manager.get_searcher_signer(),
+                    false,
+                    held.priority_fee,
+                    self.config.payment_strategy,
+                    self.config.gas_limit_multiplier,
+                    frontrun_nonce,
+                    backrun_nonce,
+                )
+                .await
+            {
+                Ok(b) => b,
+                Err(e) => {
+                    self.nonce_manager.cancel_bundle(frontrun_nonce);
+                    log_not_sandwichable!(
+                        tx_hash = victim_hash,
+                        pool = pool;
+                        "{:?}", e
+                    );
+                    metrics::record_rejection(held.record.victim_hash, RejectReason::BuildFailed);
+                    self.record_opportunity(
+                        held.record
+                            .rejected(format!("failed to build bundle: {}", e)),
+                    );
+                    continue;
+                }
+            };
+
+            metrics::SANDWICHES_FOUND.inc();
+            self.record_opportunity(held.record.clone());
+
+            #[cfg(not(feature = "debug"))]
+            {
+                if let Some(&backrun_hash) = _bundle.transaction_hashes().last() {
+                    self.submitted_bundles.push(SubmittedBundle {
+                        target_block: next_block.number,
+                        last_target_block: target_blocks.last().copied().unwrap_or_default(),
+                        victim_hash,
+                        pool,
+                        priority_fee: held.priority_fee,
+                        frontrun_hash: _bundle.transaction_hashes().first().copied(),
+                        backrun_hash,
+                        frontrun_nonce,
+                        revenue: held.revenue,
+                        net_profit,
+                        private: false,
+                        record: held.record,
+                        victims: vec![held.victim],
+                        direction: held.direction,
+                        submitted_at: Instant::now(),
+                    });
+                }
+                sando_bundles.push(_bundle);
+                net_profits.push(net_profit);
+                bundle_target_blocks.push(target_blocks);
+            }
+
+            // bundles are never sent in debug mode, free up their nonces
+            #[cfg(feature = "debug")]
+            self.nonce_manager.cancel_bundle(frontrun_nonce);
+        }
+
+        (sando_bundles, net_profits, bundle_target_blocks)
+    }
+
+    /// Wait for a free slot to trace or simulate (queues rather than dropping the tx)
+    async fn acquire_sim_permit(&self) -> Option<OwnedSemaphorePermit> {
+        // semaphore is never closed so this only waits
+        self.sim_permits.clone().acquire_owned().await.ok()
+    }
+
+    /// Fill a victim's state diffs, retrying transient rpc errors with exponential backoff
+    async fn fill_state_diffs_with_retry(
+        &self,
+        victim_info: &mut VictimInfo,
+        block: BlockNumber,
+    ) -> std::result::Result<(), StateDiffError> {
+        let mut backoff = Duration::from_millis(self.config.state_diff_backoff_ms);
+        let mut attempt = 1;
+
+        loop {
+            // permit is released while backing off so other txs can trace
+            let permit = self.acquire_sim_permit().await;
+            let result = victim_info
+                .fill_state_diffs(block, self.provider.clone(), self.config.state_diff_method)
+                .await;
+            drop(permit);
+
+            match result {
+                Err(StateDiffError::Transient(e)) if attempt < self.config.state_diff_attempts => {
+                    log_info_cyan!("trace attempt {} failed, retrying: {}", attempt, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// True if less than `min_time_budget_ms` is left before `next_block` is expected, work on
+    /// the victim should stop since its bundle can't reach builders in time
+    fn missed_deadline(&self, victim_tx: &Transaction, next_block: BlockInfo, stage: &str) -> bool {
+        let ms_left = ms_until(next_block.timestamp);
+        if self.replaying || ms_left >= self.config.min_time_budget_ms as i64 {
+            return false;
+        }
+
+        metrics::DEADLINES_MISSED.inc();
+        metrics::record_rejection(victim_tx.hash, RejectReason::DeadlineMissed);
+        log_deadline_missed!(
+            tx_hash = victim_tx.hash,
+            block = next_block.number;
+            "{:?} deadline missed {}, {} ms left before block {:?}",
+            victim_tx.hash,
+            stage,
+            ms_left,
+            next_block.number
+        );
+        true
+    }
+
+    /// Activity of the most active monitored pool in a traced victim's state diffs
+    fn victim_activity(&self, victim: &VictimInfo) -> PoolActivity {
+        let latest_block = self.block_manager.get_latest_block().number;
+        victim
+            .get_state_diffs()
+            .into_iter()
+            .flat_map(|state_diffs| state_diffs.keys())
+            .filter(|address| self.pool_manager.is_pool(**address))
+            .map(|pool| self.pool_manager.pool_activity(*pool, latest_block))
+            .min()
+            .unwrap_or(PoolActivity::Active)
+    }
+
+    /// True if `victim` already landed in a block, so the pools our bundle for `target_block`
+    /// trades on have absorbed its swap
+    /// note: replays evaluate txs mined in the block they target, only earlier blocks count then
+    async fn victim_already_mined(&self, victim: TxHash, target_block: U64) -> bool {
+        let mined_in = match self.provider.get_transaction(victim).await {
+            Ok(tx) => tx.and_then(|tx| tx.block_number),
+            Err(e) => {
+                // can't tell, submitting is no worse than without the check
+                log_error!(tx_hash = victim; "Failed to check if {:?} is pending: {}", victim, e);
+                return false;
+            }
+        };
+        match mined_in {
+            Some(block) => !self.replaying || block < target_block,
+            None => false,
+        }
+    }
+
+    /// True if the chain's tip already passed the block that `target_block`'s state was sourced
+    /// from (we fell behind while evaluating), logs that `what` built on it is dropped
+    /// note: only checked with `stale_state_guard`, pinned state and replays are behind on purpose
+    async fn state_went_stale(&self, target_block: U64, what: &str) -> bool {
+        if !self.config.stale_state_guard || self.replaying {
+            return false;
+        }
+        // pending state is the node's view on top of the target block's parent as well
+        let state_block = match self.config.sim_state_source {
+            SimStateSource::Latest | SimStateSource::Pending => target_block - 1,
+            SimStateSource::Pinned(_) => return false,
+        };
+
+        let tip = match self.provider.get_block_number().await {
+            Ok(tip) => tip,
+            Err(e) => {
+                // can't tell, submitting is no worse than without the guard
+                log_error!(block = target_block; "Failed to get the chain's tip: {}", e);
+                return false;
+            }
+        };
+        if tip <= state_block {
+            return false;
+        }
+
+        metrics::STALE_STATE_ABORTS.inc();
+        log_stale_state_abort!(
+            block = target_block;
+            "dropping {} for block {:?}, built on block {:?} state but the tip is at {:?}",
+            what,
+            target_block,
+            state_block,
+            tip
+        );
+        true
+    }
+
+    /// Flag txs that look like they come from another searcher
+    /// Returns why the tx was flagged, or None if it looks like a regular swap
+    fn searcher_tx_reason(&self, tx: &Transaction, base_fee: U256) -> Option<&'static str> {
+        if self.config.known_searchers.contains(&tx.from) {
+            return Some("sent by known searcher");
+        }
+
+        if let Some(to) = tx.to {
+            if self.config.known_searchers.contains(&to) {
+                return Some("calls known searcher contract");
+            }
+
+            // users swap through routers, bots call pairs directly to save gas
+            if self.pool_manager.is_pool(to) {
+                return Some("swaps on pool directly");
+            }
+        }
+
+        if priority_fee_per_gas(tx, base_fee) > self.config.max_victim_priority_fee {
+            return Some("priority fee too high");
+        }
+
+        None
+    }
+
+    /// Sando contract that a sandwich of `victim` on `pool` is sized with (the one netting the most
+    /// when their gas differs) and its expected profit, `None` if no contract can sandwich it
+    pub async fn cheapest_sando(
+        &self,
+        pool: Pool,
+        victim: &VictimInfo,
+        sim_block: BlockNumber,
+        sando_address: Address,
+    ) -> Option<(Address, SandwichProfit)> {
+        match self
+            .evaluate_with_cheapest_sando(pool, victim, sim_block, sando_address, false)
+            .await
+        {
+            (sando_address, PoolEvaluation::Sandwich(_, _, profit)) => {
+                Some((sando_address, profit))
+            }
+            _ => None,
+        }
+    }
+
+    /// Size a sandwich on one pool that the victim touches with every sando contract when their
+    /// gas differs (`sando_gas_profiles`) and keep the one netting the most, otherwise (and for
+    /// private backruns) with `sando_address`
+    ///
+    /// Returns the sando contract that the evaluation is for, only its sizing is counted in the
+    /// rejection metrics and warm starts the optimizer
+    async fn evaluate_with_cheapest_sando(
+        &self,
+        pool: Pool,
+        victim_info: &VictimInfo,
+        sim_block: BlockNumber,
+        sando_address: Address,
+        allow_private_backrun: bool,
+    ) -> (Address, PoolEvaluation) {
+        let mut effects = SizingEffects::default();
+        let evaluation = self
+            .evaluate_touched_pool(
+                pool,
+                victim_info,
+                sim_block,
+                sando_address,
+                allow_private_backrun,
+                &mut effects,
+            )
+            .await;
+        if self.config.sando_gas_profiles.is_empty() {
+            self.apply_sizing_effects(victim_info.tx.hash, effects);
+            return (sando_address, evaluation);
+        }
+
+        let mut best = (sando_address, evaluation, effects);
+        for other in self.sando_state_manager.sando_addresses() {
+            if other == sando_address {
+                continue;
+            }
+            // a backrun only needs the inventory of the contract we rotated to
+            let mut effects = SizingEffects::default();
+            let evaluation = self
+                .evaluate_touched_pool(pool, victim_info, sim_block, other, false, &mut effects)
+                .await;
+            let beats_best = match (&evaluation, &best.1) {
+                (PoolEvaluation::Sandwich(_, _, profit), PoolEvaluation::Sandwich(_, _, best)) => {
+                    profit.net_profit > best.net_profit
+                }
+                (PoolEvaluation::Sandwich(..), _) => true,
+                _ => false,
+            };
+            if beats_best {
+                best = (other, evaluation, effects);
+            }
+        }
+
+        if let (PoolEvaluation::Sandwich(_, _, profit), true) = (&best.1, best.0 != sando_address) {
+            log_info_cyan!(
+                tx_hash = victim_info.tx.hash,
+                pool = pool.address();
+                "{:?} cheapest sando contract on {:?} is {:?}, est. profit {}",
+                victim_info.tx.hash,
+                pool.address(),
+                best.0,
+                profit.net_profit
+            );
+        }
+        let (best_sando, evaluation, effects) = best;
+        self.apply_sizing_effects(victim_info.tx.hash, effects);
+        (best_sando, evaluation)
+    }
+
+    /// Size a sandwich on one pool that the victim touches, or a private backrun if there's no
+    /// room to frontrun, leaving its rejections and warm starts in `effects`
+    async fn evaluate_touched_pool(
+        &self,
+        pool: Pool,
+        victim_info: &VictimInfo,
+        sim_block: BlockNumber,
+        sando_address: Address,
+        allow_private_backrun: bool,
+        effects: &mut SizingEffects,
+    ) -> PoolEvaluation {
+        let victim_hash = victim_info.tx.hash;
+        let (token_a, token_b) = match pool {
+            UniswapV2(p) => (p.token_a, p.token_b),
+            UniswapV3(p) => (p.token_a, p.token_b),
+        };
+
+        if token_a != self.config.weth_address && token_b != self.config.weth_address {
+            // contract can only sandwich weth pools
+            return PoolEvaluation::Unprofitable;
+        }
+
+        let weth = self.config.weth_address;
+        let token = if token_a == weth { token_b } else { token_a };
+
+        // v3 sandwiches only buy the token
+        let direction = match pool {
+            UniswapV2(p) => match victim_info.get_swap_direction(p.address, token, weth) {
+                Some(direction) => direction,
+                None => {
+                    log_not_sandwichable!(
+                        tx_hash = victim_hash,
+                        pool = p.address;
+                        "{:?} can't tell which way the victim swaps", victim_hash
+                    );
+                    effects.rejections.push(RejectReason::UnknownDirection);
+                    return PoolEvaluation::Unprofitable;
+                }
+            },
+            UniswapV3(_) => SwapDirection::WethToToken,
+        };
+
+        // frontrun input and backrun output, we hold the other one for the sandwich
+        let (start_end_token, intermediary_token) = match direction {
+            SwapDirection::WethToToken => (weth, token),
+            SwapDirection::TokenToWeth => (token, weth),
+        };
+
+        // a victim swapping against one we already sandwich on the pool for this block would
+        // undercut its bundle (and be undercut by it)
+        let opposing = match (self.config.opposing_victims, pool) {
+            (OpposingVictims::Independent, _) | (_, UniswapV3(_)) => None,
+            (_, UniswapV2(p)) => self.opposing_v2_bundle(&p, direction).map(|b| (p, b)),
+        };
+        if let Some((p, outstanding)) = opposing {
+            let netted = match self.config.opposing_victims {
+                OpposingVictims::Net => {
+                    self.find_netted_v2_sandwich(
+                        &p,
+                        victim_info,
+                        outstanding,
+                        sim_block,
+                        sando_address,
+                    )
+                    .await
+                }
+                OpposingVictims::Independent | OpposingVictims::Skip => None,
+            };
+            let (meats, net_direction, optimal_input, profit) = match netted {
+                Some(netted) => netted,
+                None => {
+                    log_not_sandwichable!(
+                        tx_hash = victim_hash,
+                        pool = p.address;
+                        "{:?} swaps against bundle around {:?} on {:?} ({:?} opposing victims)",
+                        victim_hash,
+                        outstanding.victim_hash,
+                        p.address,
+                        self.config.opposing_victims
+                    );
+                    effects.rejections.push(RejectReason::OpposingVictims);
+                    return PoolEvaluation::Unprofitable;
+                }
+            };
+
+            let (start_end_token, intermediary_token) = match net_direction {
+                SwapDirection::WethToToken => (weth, token),
+                SwapDirection::TokenToWeth => (token, weth),
+            };
+            log_info_cyan!(
+                tx_hash = victim_hash,
+                pool = p.address,
+                direction = net_direction,
+                input = optimal_input,
+                profit = profit.net_profit;
+                "{:?} netted with {} earlier victims on {:?} ({:?}), input {} {} \
+                 est. profit {} eth",
+                victim_hash,
+                meats.len() - 1,
+                p.address,
+                net_direction,
+                self.pool_manager.display_amount(start_end_token, optimal_input),
+                self.pool_manager.token_label(start_end_token),
+                format_signed_token_amount(profit.net_profit, 18)
+            );
+            let ingredients = RawIngredients::new(meats, start_end_token, intermediary_token, pool);
+            return PoolEvaluation::Sandwich(ingredients, optimal_input, profit);
+        }
+
+        let token_inventory = self
+            .get_token_inventory(direction, token, sando_address, sim_block)
+            .await;
+
+        // a victim swapping the same way as one we already sandwich on the pool for this block
+        // shares its frontrun and backrun
+        if let (true, UniswapV2(p)) = (self.config.enable_bundle_merging, pool) {
+            if let Some((meats, optimal_input, profit)) = self.find_merged_v2_sandwich(
+                &p,
+                victim_info,
+                direction,
+                token_inventory,
+                sando_address,
+            ) {
+                log_info_cyan!(
+                    tx_hash = victim_hash,
+                    pool = p.address,
+                    direction = direction,
+                    input = optimal_input,
+                    profit = profit.net_profit;
+                    "{:?} merged with {} earlier victims on {:?} ({:?}), input {} {} \
+                     est. profit {} eth",
+                    victim_hash,
+                    meats.len() - 1,
+                    p.address,
+                    direction,
+                    self.pool_manager.display_amount(start_end_token, optimal_input),
+                    self.pool_manager.token_label(start_end_token),
+                    format_signed_token_amount(profit.net_profit, 18)
+                );
+                let ingredients =
+                    RawIngredients::new(meats, start_end_token, intermediary_token, pool);
+                return PoolEvaluation::Sandwich(ingredients, optimal_input, profit);
+            }
+        }
+
+        let (optimal_input, profit) = match pool {
+            UniswapV2(p) => {
+                let orderings = &self.config.ordering_strategies;
+                let sandwich = if orderings.contains(&OrderingStrategy::Sandwich) {
+                    self.size_v2_sandwich(
+                        &p,
+                        victim_info,
+                        direction,
+                        token_inventory,
+                        sando_address,
+                        effects,
+                    )
+                } else {
+                    None
+                };
+                if sandwich.is_none() {
+                    log_not_sandwichable!(
+                        tx_hash = victim_hash,
+                        pool = p.address,
+                        direction = direction;
+                        "{:?} no profitable v2 input on {:?} ({:?})",
+                        victim_hash,
+                        p.address,
+                        direction
+                    );
+                }
+
+                // only a victim buying the token leaves room to buy ahead of it and keep the
+                // tokens, or to sell our inventory into it
+                let buys_token = direction == SwapDirection::WethToToken;
+                let frontrun = if buys_token && orderings.contains(&OrderingStrategy::FrontrunOnly)
+                {
+                    self.find_v2_frontrun_only(&p, victim_info, token, sando_address)
+                } else {
+                    None
+                };
+                let backrun = if allow_private_backrun
+                    && orderings.contains(&OrderingStrategy::BackrunOnly)
+                {
+                    self.find_v2_backrun(&p, victim_info, token, sim_block, sando_address)
+                        .await
+                } else {
+                    None
+                };
+
+                let candidates: Vec<(OrderingStrategy, SandwichProfit)> = [
+                    sandwich.map(|(_, profit)| (OrderingStrategy::Sandwich, profit)),
+                    frontrun
+                        .as_ref()
+                        .map(|frontrun| (OrderingStrategy::FrontrunOnly, frontrun.profit)),
+                    backrun
+                        .as_ref()
+                        .map(|backrun| (OrderingStrategy::BackrunOnly, backrun.profit)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                let best = best_ordering(&candidates, self.config.min_profit_threshold);
+                if let Some((ordering, profit)) = best {
+                    if ordering != OrderingStrategy::Sandwich {
+                        log_info_cyan!(
+                            tx_hash = victim_hash,
+                            pool = p.address,
+                            profit = profit.net_profit;
+                            "{:?} {} ordering on {:?}, est. profit {}",
+                            victim_hash,
+                            ordering.label(),
+                            p.address,
+                            profit.net_profit
+                        );
+                    }
+                }
+
+                match (
+                    best.map(|(ordering, _)| ordering),
+                    sandwich,
+                    frontrun,
+                    backrun,
+                ) {
+                    (Some(OrderingStrategy::Sandwich), Some(sandwich), _, _) => sandwich,
+                    (Some(OrderingStrategy::FrontrunOnly), _, Some(frontrun), _) => {
+                        return PoolEvaluation::Frontrun(frontrun)
+                    }
+                    (Some(OrderingStrategy::BackrunOnly), _, _, Some(backrun)) => {
+                        return PoolEvaluation::Backrun(backrun)
+                    }
+                    _ => return PoolEvaluation::Unprofitable,
+                }
+            }
+            UniswapV3(p) => match self
+                .size_v3_sandwich(&p, victim_info, sim_block, sando_address, effects)
+                .await
+            {
+                Some(optimal) => optimal,
+                None => {
+                    log_not_sandwichable!(
+                        tx_hash = victim_hash,
+                        pool = p.address,
+                        direction = direction;
+                        "{:?} no profitable v3 input on {:?} ({:?})",
+                        victim_hash,
+                        p.address,
+                        direction
+                    );
+                    return PoolEvaluation::Unprofitable;
+                }
+            },
+        };
+
+        log_info_cyan!(
+            tx_hash = victim_hash,
+            pool = pool.address(),
+            direction = direction,
+            input = optimal_input,
+            profit = profit.net_profit;
+            "{:?} sandwich on {:?} ({:?}), input {} {} est. profit {} eth",
+            victim_hash,
+            pool.address(),
+            direction,
+            self.pool_manager.display_amount(start_end_token, optimal_input),
+            self.pool_manager.token_label(start_end_token),
+            format_signed_token_amount(profit.net_profit, 18)
+        );
+
+        let ingredients = RawIngredients::new(
+            vec![victim_info.tx.clone()],
+            start_end_token,
+            intermediary_token,
+            pool,
+        );
+        PoolEvaluation::Sandwich(ingredients, optimal_input, profit)
+    }
+
+    /// Cheap checks that a pending tx could be a victim for the next block, before spending rpc
+    /// calls tracing it
+    fn screen_victim(&mut self, victim_tx: Transaction) -> Option<VictimInfo> {
+        let next_block = self.block_manager.get_next_block();
+
+        // txs that we can't include in next block are evaluated again once base fee drops
+        if max_fee_per_gas(&victim_tx) < next_block.base_fee_per_gas {
+            log_info_cyan!(tx_hash = victim_tx.hash; "{:?} mf<nbf, parking", victim_tx.hash);
+            metrics::record_rejection(victim_tx.hash, RejectReason::CantInclude);
+            self.mempool_manager.park(victim_tx, next_block.number);
+            return None;
+        }
+
+        // most mempool traffic never touches a dex, skip it before tracing
+        if !self.pool_manager.is_dex_tx(&victim_tx) {
+            return None;
+        }
+
+        // aggregators' calldata is opaque, their swaps are read from the pools' state diffs
+        let victim_info = match victim_tx.to {
+            Some(to) if self.pool_manager.is_aggregator(to) => {
+                VictimInfo::new(victim_tx.clone()).via_aggregator()
+            }
+            _ => VictimInfo::new(victim_tx.clone()),
+        };
+
+        // victim's swap reverts once the deadline passes, and later blocks only get later
+        if victim_info.is_expired_at(next_block.timestamp) {
+            log_not_sandwichable!(
+                tx_hash = victim_tx.hash;
+                "{:?} deadline passed before block {:?}", victim_tx.hash, next_block.number
+            );
+            metrics::record_rejection(victim_tx.hash, RejectReason::SwapExpired);
+            return None;
+        }
+
+        // sandwiching another searcher's frontrun only starts a bidding war
+        if let Some(reason) = self.searcher_tx_reason(&victim_tx, next_block.base_fee_per_gas) {
+            // a searcher trading a pool that one of our bundles sandwiches competes with it
+            if let Some(to) = victim_tx.to {
+                let contested: Vec<TxHash> = self
+                    .submitted_bundles
+                    .iter()
+                    .filter(|b| b.pool == to && b.last_target_block >= next_block.number)
+                    .map(|b| b.victim_hash)
+                    .collect();
+                if !contested.is_empty() {
+                    self.record_competition(contested, next_block.number, reason);
+                }
+            }
+            log_not_sandwichable!(
+                tx_hash = victim_tx.hash;
+                "{:?} likely searcher tx, {}", victim_tx.hash, reason
+            );
+            metrics::record_rejection(victim_tx.hash, RejectReason::SearcherTx);
+            return None;
+        }
+
+        // our bundles around this sender keep missing, don't spend a trace on it for now
+        if let Some(victim_cooldown) = &self.victim_cooldown {
+            if victim_cooldown.is_cooling(victim_tx.from) {
+                log_not_sandwichable!(
+                    tx_hash = victim_tx.hash;
+                    "{:?} sender {:?} in cooldown", victim_tx.hash, victim_tx.from
+                );
+                metrics::record_rejection(victim_tx.hash, RejectReason::VictimCooldown);
+                return None;
+            }
+        }
+
+        // skip dust swaps before spending an rpc call tracing them
+        if let Some(swap_value) = victim_info.get_swap_value() {
+            if swap_value < self.config.min_victim_swap_value {
+                log_info_cyan!(
+                    tx_hash = victim_tx.hash;
+                    "{:?} swap value too small", victim_tx.hash
+                );
+                metrics::record_rejection(victim_tx.hash, RejectReason::SwapTooSmall);
+                return None;
+            }
+
+            // bait txs tip more than sandwiching their swap could ever earn us
+            if let Some(max_tip_bps) = self.config.max_victim_tip_bps {
+                if tip_exceeds_swap_share(
+                    &victim_tx,
+                    next_block.base_fee_per_gas,
+                    swap_value,
+                    max_tip_bps,
+                ) {
+                    log_not_sandwichable!(
+                        tx_hash = victim_tx.hash;
+                        "{:?} tip too high for swap of {} wei", victim_tx.hash, swap_value
+                    );
+                    metrics::record_rejection(victim_tx.hash, RejectReason::TipTooHigh);
+                    return None;
+                }
+            }
+        }
+
+        // tracing and simulating take a good part of the slot, don't start late
+        if self.missed_deadline(&victim_tx, next_block, "before tracing") {
+            return None;
+        }
+
+        Some(victim_info)
+    }
+
+    /// Fill the state diffs of screened victims, tracing them concurrently (bounded by
+    /// `max_concurrent_sims`)
+    ///
+    /// Returns the victims that were traced, txs that failed on a transient rpc error are
+    /// requeued for the next event
+    async fn trace_victims(&mut self, victims: Vec<VictimInfo>) -> Vec<VictimInfo> {
+        let next_block = self.block_manager.get_next_block();
+        // trace victims and fork for simulations on the configured state
+        let sim_block = self.config.sim_state_source.block_for(next_block.number);
+
+        let timeout = self.victim_timeout();
+        let this = &*self;
+        let traced =
+            futures::future::join_all(victims.into_iter().map(|mut victim_info| async move {
+                let result = evaluate_within(
+                    timeout,
+                    this.fill_state_diffs_with_retry(&mut victim_info, sim_block),
+                )
+                .await;
+                (victim_info, result)
+            }))
+            .await;
+
+        let mut victims = vec![];
+        for (victim_info, result) in traced {
+            let result = match result {
+                Some(result) => result,
+                None => {
+                    metrics::VICTIM_TIMEOUTS.inc();
+                    metrics::record_rejection(victim_info.tx.hash, RejectReason::Timeout);
+                    log_error!(
+                        tx_hash = victim_info.tx.hash;
+                        "{:?} tracing timed out after {:?}, skipping", victim_info.tx.hash, timeout
+                    );
+                    continue;
+                }
+            };
+            match result {
+                Ok(()) => {
+                    self.set_storage_fallback_active(false);
+                    victims.push(victim_info);
+                }
+                Err(StateDiffError::Transient(e)) if self.config.state_diff_storage_fallback => {
+                    match self
+                        .pool_manager
+                        .v2_state_diffs_from_storage(&victim_info, sim_block)
+                        .await
+                    {
+                        Ok(state_diffs) => {
+                            self.set_storage_fallback_active(true);
+                            metrics::TRACE_FALLBACKS.inc();
+                            victims.push(VictimInfo::with_state_diffs(victim_info.tx, state_diffs));
+                        }
+                        Err(fallback_error) => {
+                            let victim_tx = victim_info.tx;
+                            log_error!(
+                                tx_hash = victim_tx.hash;
+                                "Failed to fill state diffs: {} (storage fallback: {})",
+                                e,
+                                fallback_error
+                            );
+                            self.requeued_txs.push((next_block.number, victim_tx));
+                        }
+                    }
+                }
+                Err(e) => {
+                    let victim_tx = victim_info.tx;
+                    log_error!(tx_hash = victim_tx.hash; "Failed to fill state diffs: {}", e);
+
+                    // rpc is flaky, try again on the next event if tx can still land in the same
+                    // block
+                    match e {
+                        StateDiffError::Transient(_) => {
+                            self.requeued_txs.push((next_block.number, victim_tx))
+                        }
+                        StateDiffError::Permanent(_) => {
+                            metrics::record_rejection(victim_tx.hash, RejectReason::TraceFailed)
+                        }
+                    }
+                }
+            }
+        }
+        victims
+    }
+
+    /// True while the rpc circuit breaker is open, logs when events start or stop being skipped
+    fn is_rpc_paused(&mut self) -> bool {
+        let state = match &self.rpc_breaker {
+            Some(breaker) => breaker.state(),
+            None => return false,
+        };
+        let paused = matches!(state, BreakerState::Open { .. });
+        if paused != self.rpc_paused {
+            self.rpc_paused = paused;
+            metrics::set_rpc_breaker_open(paused);
+            match state {
+                BreakerState::Open { remaining } => log_error!(
+                    "Provider is failing, skipping events for {:?} until the rpc breaker closes",
+                    remaining
+                ),
+                BreakerState::Closed => log_info_cyan!("Rpc breaker closed, processing events"),
+            }
+        }
+        paused
+    }
+
+    /// Log when victims start or stop being rebuilt from storage reads instead of traces
+    fn set_storage_fallback_active(&mut self, active: bool) {
+        if self.storage_fallback_active == active {
+            return;
+        }
+        self.storage_fallback_active = active;
+        match active {
+            true => log_error!(
+                "Tracing unavailable, rebuilding v2 swaps from pair reserve reads until it recovers"
+            ),
+            false => log_info_cyan!("Tracing recovered, storage fallback off"),
+        }
+    }
+
+    /// Size, simulate and bid sandwiches around a traced victim
+    async fn process_traced_victim(
+        &mut self,
+        victim_info: VictimInfo,
+        allow_private_backrun: bool,
+    ) -> Option<Action> {
+        // setup variables for processing tx
+        let victim_tx = victim_info.tx.clone();
+        let next_block = self.block_manager.get_next_block();
+        // fork for simulations on the configured state
+        let sim_block = self.config.sim_state_source.block_for(next_block.number);
+
+        // check if tx is a swap
+        let mut touched_pools = self
+            .pool_manager
+            .get_touched_sandwichable_pools(&victim_info)
+            .await
+            .map_err(|e| {
+                log_error!(
+                    tx_hash = victim_tx.hash;
+                    "Failed to get touched sandwichable pools: {}", e
+                );
+                e
+            })
+            .ok()?;
+
+        // no touched pools = no sandwich opps
+        if touched_pools.is_empty() {
+            info!("{:?}", victim_tx.hash);
+            metrics::record_rejection(victim_tx.hash, RejectReason::NoTouchedPools);
+            return None;
+        }
+
+        metrics::TXS_WITH_TOUCHED_POOLS.inc();
+        self.funnel.record(FunnelStage::TouchedPools, 1);
+        if self.config.preceding_txs_min_profit.is_some()
+            || self.config.victim_position_assumption == VictimPosition::AfterPending
+        {
+            for pool in &touched_pools {
+                self.pool_pending_txs
+                    .entry(pool.address())
+                    .or_default()
+                    .push(victim_info.clone());
+            }
+        }
+        log_info_cyan!(
+            tx_hash = victim_tx.hash;
+            "{:?} touches pools {:?}",
+            victim_tx.hash,
+            touched_pools.iter().map(|pool| pool.address()).collect::<Vec<_>>()
+        );
+
+        // pools that paid off before get the first simulation permits
+        if self.config.prioritize_profitable_pools && touched_pools.len() > 1 {
+            touched_pools.sort_by_key(|pool| {
+                std::cmp::Reverse(
+                    self.pool_manager
+                        .pool_stats(pool.address())
+                        .profit_per_opportunity(),
+                )
+            });
+        }
+
+        // rotate sando contracts between bundles so no single contract is easy to track
+        let sando_address = self.sando_state_manager.select_sando_address();
+
+        // size a sandwich on every touched pool at once (sequentially a victim touching several
+        // pools can miss the block), victim can only be sandwiched once so we only bundle the
+        // most profitable pool
+        let allow_inventory_backrun = allow_private_backrun && self.config.enable_private_backruns;
+        let moved_v2_pools: Vec<UniswapV2Pool> = touched_pools
+            .iter()
+            .filter_map(|pool| match pool {
+                UniswapV2(p) => Some(*p),
+                UniswapV3(_) => None,
+            })
+            .collect();
+        let evaluations = futures::future::join_all(touched_pools.into_iter().map(|pool| {
+            self.evaluate_with_cheapest_sando(
+                pool,
+                &victim_info,
+                sim_block,
+                sando_address,
+                allow_inventory_backrun,
+            )
+        }))
+        .await;
+
+        let mut candidates = vec![];
+        let mut backruns = vec![];
+        let mut frontruns = vec![];
+        for (sandwich_sando, evaluation) in evaluations {
+            match evaluation {
+                PoolEvaluation::Sandwich(ingredients, optimal_input, profit) => {
+                    self.pool_manager
+                        .record_pool_opportunity(ingredients.get_target_pool().address());
+                    candidates.push(((ingredients, optimal_input, sandwich_sando), profit))
+                }
+                PoolEvaluation::Backrun(backrun) => backruns.push(backrun),
+                PoolEvaluation::Frontrun(frontrun) => frontruns.push(frontrun),
+                PoolEvaluation::Unprofitable => {}
+            }
+        }
+        if !candidates.is_empty() || !backruns.is_empty() || !frontruns.is_empty() {
+            self.funnel.record(FunnelStage::Profitable, 1);
+        }
+        log_info_cyan!(
+            tx_hash = victim_tx.hash,
+            block = next_block.number;
+            "{:?} sized {} candidates, {} ms before block {:?}",
+            victim_tx.hash,
+            candidates.len(),
+            ms_until(next_block.timestamp),
+            next_block.number
+        );
+
+        // sizing may have taken long enough that simulating would finish after the block
+        if !candidates.is_empty()
+            && self.missed_deadline(&victim_tx, next_block, "before simulation")
+        {
+            return None;
+        }
+
+        let mut sando_bundles = vec![];
+        let mut net_profits = vec![];
+        let mut bundle_target_blocks = vec![];
+
+        // fall back to the next best pool if a sandwich fails simulation
+        for ((ingredients, optimal_input, sando_address), profit) in
+            rank_by_net_profit(candidates, self.config.min_profit_threshold)
+        {
+            let analytic_revenue = profit.gross_revenue;
+            let merged = ingredients.get_meats_ref().len() > 1;
+            let start_end_token = ingredients.get_start_end_token();
+            let pool_address = ingredients.get_target_pool().address();
+            let record = OpportunityRecord::new(
+                next_block.number,
+                victim_tx.hash,
+                pool_address,
+                optimal_input,
+                profit.net_profit,
+            )
+            .with_input_token(
+                start_end_token,
+                self.pool_manager.token_decimals(start_end_token),
+            );
+
+            // simulate frontrun, victim, backrun in revm to catch where our math is wrong
+            let preceding = self.preceding_txs_for(&victim_tx, pool_address, profit.net_profit);
+            if !preceding.is_empty() {
+                log_info_cyan!(
+                    tx_hash = victim_tx.hash,
+                    pool = pool_address;
+                    "{:?} simulating after {} pending txs that outbid it",
+                    victim_tx.hash,
+                    preceding.len()
+                );
+            }
+            let recipe = self
+                .simulate_sandwich(
+                    &victim_info,
+                    ingredients,
+                    next_block,
+                    optimal_input,
+                    sando_address,
+                    &preceding,
+                )
+                .await;
+
+            // our math only sees the victim's state, txs before it move the pool on their own
+            if let (Ok(recipe), true) = (&recipe, preceding.is_empty()) {
+                // analytic revenue is valued in weth, sell sandwiches make theirs in the token
+                let simulated_revenue = self
+                    .pool_manager
+                    .quote_in_weth(start_end_token, recipe.get_revenue())
+                    .unwrap_or_default();
+                let diverged = !is_within_tolerance(
+                    analytic_revenue,
+                    simulated_revenue,
+                    self.config.simulation_tolerance_bps,
+                );
+                metrics::record_pool_simulation(pool_address, diverged);
+                if self
+                    .pool_manager
+                    .record_pool_simulation(pool_address, diverged)
+                {
+                    let stats = self.pool_manager.pool_stats(pool_address);
+                    metrics::POOLS_DISABLED.inc();
+                    log_error!(
+                        pool = pool_address;
+                        "Disabling pool {:?} for review, {}/{} simulations disagreed",
+                        pool_address,
+                        stats.diverged,
+                        stats.simulated
+                    );
+                }
+                if diverged {
+                    log_not_sandwichable!(
+                        tx_hash = victim_tx.hash,
+                        pool = pool_address;
+                        "{:?} simulated revenue {} disagrees with analytic revenue {}",
+                        victim_tx.hash,
+                        simulated_revenue,
+                        analytic_revenue
+                    );
+                    metrics::record_rejection(victim_tx.hash, RejectReason::SimulationMismatch);
+                    self.record_opportunity(
+                        record.rejected("simulated revenue disagrees with analytic revenue"),
+                    );
+
+                    // our copy of the pool may have drifted from chain, refresh it for the next
+                    // victim
+                    if let Err(e) = self.pool_manager.resync_pool(pool_address).await {
+                        log_error!(pool = pool_address; "Failed to resync pool: {}", e);
+                    }
+                    continue;
+                }
+            }
+
+            match recipe {
+                Ok(s) => {
+                    let s = s
+                        .covering_later_blocks(self.max_target_block_offset())
+                        .covering_base_fee_margin(self.config.base_fee_safety_margin_bps);
+
+                    // gas is paid in eth, so revenue has to be valued in weth before netting it
+                    let revenue_in_weth = match self
+                        .pool_manager
+                        .quote_in_weth(start_end_token, s.get_revenue())
+                    {
+                        Some(revenue) => revenue,
+                        None => {
+                            log_not_sandwichable!(
+                                tx_hash = victim_tx.hash,
+                                pool = pool_address;
+                                "{:?} no weth pool to value {:?} revenue",
+                                victim_tx.hash,
+                                start_end_token
+                            );
+                            metrics::record_rejection(victim_tx.hash, RejectReason::NoWethQuote);
+                            self.record_opportunity(
+                                record.rejected("no weth pool to value revenue"),
+                            );
+                            continue;
+                        }
+                    };
+
+                    // recheck profit using the gas our txs used in the fork, not the estimates
+                    let (frontrun_gas, backrun_gas) = estimate_gas(&s);
+                    // priced at the margined base fee, so the bid leaves enough to pay for a spike
+                    let simulated_profit = SandwichProfit::new(
+                        revenue_in_weth,
+                        frontrun_gas,
+                        backrun_gas,
+                        self.with_safety_margin(next_block.base_fee_per_gas),
+                        U256::zero(),
+                        self.config.payment_strategy,
+                    );
+                    let record = record.with_simulation(
+                        simulated_profit.net_profit,
+                        (frontrun_gas + backrun_gas).as_u64(),
+                    );
+                    if !simulated_profit.exceeds(self.config.min_profit_threshold) {
+                        log_not_sandwichable!(
+                            tx_hash = victim_tx.hash,
+                            pool = pool_address,
+                            profit = simulated_profit.net_profit;
+                            "{:?} not profitable after simulated gas",
+                            victim_tx.hash
+                        );
+                        metrics::record_rejection(
+                            victim_tx.hash,
+                            RejectReason::UnprofitableAfterGas,
+                        );
+                        self.record_opportunity(
+                            record.rejected("not profitable after simulated gas"),
+                        );
+                        continue;
+                    }
+
+                    // bid part of our profit to outbid competing searchers
+                    let net_profit = simulated_profit.net_profit.into_raw();
+                    let priority_fee = self.bid_for(&victim_tx, net_profit);
+                    let record = record.with_bid(priority_fee);
+
+                    // recipes can be priced for a stale base fee (cached across a reorg), our
+                    // txs have to clear the base fee the next block will actually have (plus the
+                    // safety margin)
+                    let predicted_base_fee =
+                        self.with_safety_margin(self.block_manager.predict_next_base_fee());
+                    let bundle_max_fee =
+                        s.max_fee_per_gas(priority_fee, self.config.payment_strategy);
+                    if !can_include_in_target_block(&victim_tx, bundle_max_fee, predicted_base_fee)
+                    {
+                        log_not_sandwichable!(
+                            tx_hash = victim_tx.hash,
+                            pool = pool_address;
+                            "{:?} our max fee {} below predicted base fee {}",
+                            victim_tx.hash,
+                            bundle_max_fee,
+                            predicted_base_fee
+                        );
+                        metrics::record_rejection(victim_tx.hash, RejectReason::BaseFeeTooHigh);
+                        self.record_opportunity(record.rejected("bundle max fee below base fee"));
+                        continue;
+                    }
+                    let target_blocks = self.bundle_target_blocks(&victim_tx, next_block.number);
+                    if target_blocks.is_empty() {
+                        log_not_sandwichable!(
+                            tx_hash = victim_tx.hash,
+                            pool = pool_address;
+                            "{:?} victim can't pay the base fee of any target block",
+                            victim_tx.hash
+                        );
+                        metrics::record_rejection(victim_tx.hash, RejectReason::BaseFeeTooHigh);
+                        self.record_opportunity(
+                            record.rejected("victim can't pay the base fee of any target block"),
+                        );
+                        continue;
+                    }
+
+                    // a bundle on this pool is already out for the block, only supersede it if
+                    // this victim pays enough more
+                    let bundle_profit = I256::from_raw(net_profit) - I256::from_raw(priority_fee);
+
+                    // hold the sandwich until the block is close, only the block's most
+                    // profitable sandwiches get nonces and are sent
+                    if self.holds_for_ranking(next_block) {
+                        let held = HeldOpportunity {
+                            pool: pool_address,
+                            net_profit: bundle_profit,
+                            gas_used: (frontrun_gas + backrun_gas).as_u64(),
+                            opportunity: HeldSandwich {
+                                recipe: s,
+                                sando_address,
+                                victim: victim_info.clone(),
+                                priority_fee,
+                                revenue: revenue_in_weth,
+                                record,
+                                direction: if start_end_token == self.config.weth_address {
+                                    SwapDirection::WethToToken
+                                } else {
+                                    SwapDirection::TokenToWeth
+                                },
+                            },
+                        };
+                        self.expire_held_sandwiches(next_block.number);
+                        self.bundle_ranking.hold(held);
+                        log_info_cyan!(
+                            tx_hash = victim_tx.hash,
+                            pool = pool_address,
+                            profit = bundle_profit;
+                            "{:?} held for block {:?} ranking ({} held)",
+                            victim_tx.hash,
+                            next_block.number,
+                            self.bundle_ranking.len()
+                        );
+                        return None;
+                    }
+
+                    let superseded = self.submitted_bundles.iter().position(|b| {
+                        b.target_block == next_block.number && b.pool == pool_address && !b.private
+                    });
+                    if let Some(index) = superseded {
+                        let outstanding = &self.submitted_bundles[index];
+                        if !self.should_replace(outstanding, bundle_profit, priority_fee) {
+                            log_not_sandwichable!(
+                                tx_hash = victim_tx.hash,
+                                pool = pool_address,
+                                profit = bundle_profit;
+                                "{:?} conflicts with bundle around {:?} (profit {} vs {})",
+                                victim_tx.hash,
+                                outstanding.victim_hash,
+                                bundle_profit,
+                                outstanding.net_profit
+                            );
+                            metrics::record_rejection(
+                                victim_tx.hash,
+                                RejectReason::ConflictingBundle,
+                            );
+                            self.record_opportunity(record.rejected(format!(
+                                "conflicts with bundle around {:?}",
+                                outstanding.victim_hash
+                            )));
+                            continue;
+                        }
+                    }
+
+                    // replacement reuses the superseded bundle's nonces so only one can land,
+                    // later bundles for the same block only land if every earlier one does
+                    let nonces = match superseded {
+                        Some(index) => {
+                            let frontrun_nonce = self.submitted_bundles[index].frontrun_nonce;
+                            Some((frontrun_nonce, frontrun_nonce + 1))
+                        }
+                        None => self
+                            .nonce_manager
+                            .reserve_nonces_for_block(next_block.number),
+                    };
+                    let (frontrun_nonce, backrun_nonce) = match nonces {
+                        Some(nonces) => nonces,
+                        None => {
+                            log_not_sandwichable!(
+                                tx_hash = victim_tx.hash,
+                                block = next_block.number;
+                                "{:?} bundle budget for block {:?} used up",
+                                victim_tx.hash,
+                                next_block.number
+                            );
+                            metrics::record_rejection(victim_tx.hash, RejectReason::BundleBudget);
+                            self.record_opportunity(record.rejected("bundle budget used up"));
+                            break;
+                        }
+                    };
+
+                    let _bundle = match s
+                        .to_fb_bundle(
+                            sando_address,
+                            self.sando_state_manager.get_searcher_signer(),
+                            false,
+                            priority_fee,
+                            self.config.payment_strategy,
+                            self.config.gas_limit_multiplier,
+                            frontrun_nonce,
+                            backrun_nonce,
+                        )
+                        .await
+                    {
+                        Ok(b) => b,
+                        Err(e) => {
+                            // superseded bundle is still out and keeps its nonces
+                            if superseded.is_none() {
+                                self.nonce_manager.cancel_bundle(frontrun_nonce);
+                            }
+                            log_not_sandwichable!(
+                                tx_hash = victim_tx.hash,
+                                pool = pool_address;
+                                "{:?}", e
+                            );
+                            metrics::record_rejection(victim_tx.hash, RejectReason::BuildFailed);
+                            self.record_opportunity(
+                                record.rejected(format!("failed to build bundle: {}", e)),
+                            );
+                            continue;
+                        }
+                    };
+
+                    metrics::SANDWICHES_FOUND.inc();
+                    self.record_opportunity(record.clone());
+
+                    #[cfg(not(feature = "debug"))]
+                    {
+                        let mut victims = vec![];
+                        if let Some(index) = superseded {
+                            let replaced = self.submitted_bundles.remove(index);
+                            metrics::BUNDLES_REPLACED.inc();
+                            log_info_cyan!(
+                                tx_hash = victim_tx.hash,
+                                pool = pool_address,
+                                profit = bundle_profit;
+                                "{:?} replaces bundle around {:?} (profit delta {})",
+                                victim_tx.hash,
+                                replaced.victim_hash,
+                                bundle_profit - replaced.net_profit
+                            );
+                            self.record_opportunity(replaced.record.rejected(format!(
+                                "replaced by bundle around {:?}",
+                                victim_tx.hash
+                            )));
+
+                            // merged bundle still sandwiches the replaced bundle's victims
+                            if merged {
+                                metrics::BUNDLES_MERGED.inc();
+                                victims = replaced.victims;
+                            }
+                        }
+                        victims.push(victim_info.clone());
+
+                        if let Some(&backrun_hash) = _bundle.transaction_hashes().last() {
+                            self.submitted_bundles.push(SubmittedBundle {
+                                target_block: next_block.number,
+                                last_target_block: target_blocks
+                                    .last()
+                                    .copied()
+                                    .unwrap_or_default(),
+                                victim_hash: victim_tx.hash,
+                                pool: pool_address,
+                                priority_fee,
+                                frontrun_hash: _bundle.transaction_hashes().first().copied(),
+                                backrun_hash,
+                                frontrun_nonce,
+                                revenue: revenue_in_weth,
+                                net_profit: bundle_profit,
+                                private: false,
+                                record,
+                                victims,
+                                direction: if start_end_token == self.config.weth_address {
+                                    SwapDirection::WethToToken
+                                } else {
+                                    SwapDirection::TokenToWeth
+                                },
+                                submitted_at: Instant::now(),
+                            });
+                        }
+                        sando_bundles.push(_bundle);
+                        net_profits.push(bundle_profit);
+                        bundle_target_blocks.push(target_blocks);
+                    }
+
+                    // bundles are never sent in debug mode, free up their nonces
+                    #[cfg(feature = "debug")]
+                    self.nonce_manager.cancel_bundle(frontrun_nonce);
+
+                    break;
+                }
+                Err(e) => {
+                    log_not_sandwichable!(
+                        tx_hash = victim_tx.hash,
+                        pool = pool_address;
+                        "{:?} {:?}", victim_tx.hash, e
+                    );
+                    metrics::record_rejection(victim_tx.hash, RejectReason::SimulationFailed);
+                    self.record_opportunity(record.rejected(format!("simulation failed: {}", e)));
+                }
+            };
+        }
+
+        // nothing profitable found for this tx
+        if sando_bundles.is_empty() {
+            // a frontrun only bundle goes out instead of a backrun that makes less
+            let frontrun = frontruns
+                .into_iter()
+                .max_by_key(|frontrun| frontrun.profit.net_profit);
+            if let Some(frontrun) = frontrun {
+                let beats_backruns = backruns
+                    .iter()
+                    .all(|backrun| frontrun.profit.net_profit > backrun.profit.net_profit);
+                if beats_backruns {
+                    return self
+                        .build_frontrun_only(&victim_info, frontrun, sando_address, next_block)
+                        .await;
+                }
+            }
+
+            let backrun = self
+                .build_private_backrun(&victim_tx, backruns, sando_address, next_block)
+                .await;
+            if backrun.is_some() || !self.config.enable_backrun_arb {
+                return backrun;
+            }
+
+            // the victim may still leave its pool's price away from the token's other pools
+            let arb = self.find_v2_backrun_arb(&victim_info, &moved_v2_pools, sando_address)?;
+
+            // the arb is sent as its own private txs, which would drop the bundles collected for
+            // this event, so try it again on the next event
+            if !allow_private_backrun {
+                log_info_cyan!(
+                    tx_hash = victim_tx.hash;
+                    "{:?} arb held for the next event, bundles already collected for this one",
+                    victim_tx.hash
+                );
+                self.requeued_txs.push((next_block.number, victim_tx));
+                return None;
+            }
+            self.funnel.record(FunnelStage::Profitable, 1);
+            return self
+                .build_backrun_arb(&victim_info, arb, sando_address, next_block)
+                .await;
+        }
+
+        // bundles decided too close to the block boundary may not reach builders in time
+        let ms_before_block = ms_until(next_block.timestamp);
+        metrics::DECISION_MS_BEFORE_BLOCK.set(ms_before_block);
+        log_info_cyan!(
+            tx_hash = victim_tx.hash,
+            block = next_block.number;
+            "{:?} bundle decided {} ms before block {:?}",
+            victim_tx.hash,
+            ms_before_block,
+            next_block.number
+        );
+
+        Some(Action::SubmitBundle {
+            bundles: sando_bundles,
+            target_block: next_block.number,
+            net_profits,
+            target_blocks: bundle_target_blocks,
+        })
     }
 }
@@ -0,0 +1,599 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::Path,
+};
+
+use anyhow::{anyhow, ensure, Result};
+use cfmms::dex::DexVariant;
+use ethers::types::{Address, U256, U64};
+use serde::Deserialize;
+
+use crate::{
+    managers::block_manager::MAX_BASE_FEE_SAFETY_MARGIN_BPS,
+    signer::SearcherSigner,
+    types::{DexConfig, SandoGasProfile, StratConfig},
+};
+
+/// Env var that overrides `searcher_private_key` (keeps the key out of the config file)
+const SEARCHER_PRIVATE_KEY_ENV: &str = "SEARCHER_PRIVATE_KEY";
+
+/// Largest backrun min out buffer (in bps), a wider one gives away too much of the sandwich
+const MAX_BACKRUN_MIN_OUT_BUFFER_BPS: u64 = 1_000;
+
+/// Wei amount, toml integers can't hold most of them so decimal or `0x` strings also work
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Wei {
+    Int(u64),
+    Str(String),
+}
+
+impl Wei {
+    fn parse(self, field: &str) -> Result<U256> {
+        match self {
+            Wei::Int(value) => Ok(U256::from(value)),
+            Wei::Str(value) => {
+                let value = value.trim();
+                match value.strip_prefix("0x") {
+                    Some(hex) => U256::from_str_radix(hex, 16).ok(),
+                    None => U256::from_dec_str(value).ok(),
+                }
+                .ok_or_else(|| anyhow!("Failed to parse \"{}\" amount {}", field, value))
+            }
+        }
+    }
+}
+
+/// A `[[sando_gas_profiles]]` entry, unset fields keep the default estimates
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SandoGasProfileFile {
+    address: Address,
+    v2_frontrun_gas: Option<u64>,
+    v2_backrun_gas: Option<u64>,
+    v3_frontrun_gas: Option<u64>,
+    v3_backrun_gas: Option<u64>,
+}
+
+impl SandoGasProfileFile {
+    fn into_gas_profile(self) -> SandoGasProfile {
+        let default = SandoGasProfile::default();
+        SandoGasProfile {
+            v2_frontrun: self.v2_frontrun_gas.unwrap_or(default.v2_frontrun),
+            v2_backrun: self.v2_backrun_gas.unwrap_or(default.v2_backrun),
+            v3_frontrun: self.v3_frontrun_gas.unwrap_or(default.v3_frontrun),
+            v3_backrun: self.v3_backrun_gas.unwrap_or(default.v3_backrun),
+        }
+    }
+}
+
+/// A `[[dexes]]` entry
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DexFile {
+    factory: Address,
+    fee_bps: u32,
+    /// `v2` or `v3`
+    kind: String,
+    creation_block: u64,
+    #[serde(default)]
+    routers: Vec<Address>,
+    #[serde(default)]
+    fee_tiers: Vec<u32>,
+}
+
+impl DexFile {
+    fn into_dex_config(self) -> Result<DexConfig> {
+        let kind = match self.kind.to_lowercase().as_str() {
+            "v2" => DexVariant::UniswapV2,
+            "v3" => DexVariant::UniswapV3,
+            _ => {
+                return Err(anyhow!(
+                    "Unknown dex kind {} (expected v2 or v3)",
+                    self.kind
+                ))
+            }
+        };
+        // sandwich math prices swaps with `10_000 - fee_bps`
+        ensure!(
+            self.fee_bps < 10_000,
+            "Dex {:?} \"fee_bps\" must be below 10000",
+            self.factory
+        );
+
+        Ok(DexConfig::new(
+            self.factory,
+            self.fee_bps,
+            kind,
+            self.creation_block,
+            self.routers,
+        )
+        .with_fee_tiers(self.fee_tiers))
+    }
+}
+
+/// Layout of a strategy config file, unset fields keep `StratConfig::new`'s defaults
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StratConfigFile {
+    sando_addresses: Vec<Address>,
+    sando_rotation: Option<String>,
+    rng_seed: Option<u64>,
+    sando_inception_block: u64,
+    searcher_private_key: Option<String>,
+    optimizer_epsilon: Option<Wei>,
+    min_profit_threshold: Option<Wei>,
+    simulation_tolerance_bps: Option<u64>,
+    max_pool_divergence_rate: Option<f64>,
+    exclude_fee_switch_pools: Option<bool>,
+    preceding_txs_min_profit: Option<Wei>,
+    victim_position_assumption: Option<String>,
+    blacklist: Option<HashSet<Address>>,
+    dexes: Option<Vec<DexFile>>,
+    sando_gas_profiles: Option<Vec<SandoGasProfileFile>>,
+    bid_fraction: Option<f64>,
+    competition_level: Option<f64>,
+    min_tip: Option<Wei>,
+    max_tip: Option<Wei>,
+    bid_escalation_step: Option<f64>,
+    max_bid_fraction: Option<f64>,
+    min_victim_swap_value: Option<Wei>,
+    state_diff_attempts: Option<u32>,
+    state_diff_backoff_ms: Option<u64>,
+    state_diff_storage_fallback: Option<bool>,
+    metrics_port: Option<u16>,
+    metrics_snapshot_path: Option<String>,
+    opportunity_log_path: Option<String>,
+    alert_webhook: Option<String>,
+    max_window_loss: Option<Wei>,
+    min_weth_inventory: Option<Wei>,
+    min_searcher_balance: Option<Wei>,
+    loss_window_blocks: Option<u64>,
+    loss_cooldown_blocks: Option<u64>,
+    victim_max_misses: Option<u32>,
+    victim_cooldown_blocks: Option<u64>,
+    health_stale_after_secs: Option<u64>,
+    enable_multihop: Option<bool>,
+    enable_private_backruns: Option<bool>,
+    enable_backrun_arb: Option<bool>,
+    ordering_strategies: Option<Vec<String>>,
+    frontrun_only_haircut_bps: Option<u64>,
+    prioritize_profitable_pools: Option<bool>,
+    enable_bundle_merging: Option<bool>,
+    opposing_victims: Option<String>,
+    min_time_budget_ms: Option<u64>,
+    gas_limit_multiplier: Option<f64>,
+    backrun_min_out_buffer_bps: Option<u64>,
+    base_fee_safety_margin_bps: Option<u64>,
+    pool_update_threshold_bps: Option<u64>,
+    track_pool_prices: Option<bool>,
+    hot_pool_change_bps: Option<u64>,
+    cold_pool_after_blocks: Option<u64>,
+    refresh_hot_pools: Option<bool>,
+    mode: Option<String>,
+    max_resync_failures: Option<u32>,
+    min_pool_liquidity: Option<Wei>,
+    stable_tokens: Option<HashSet<Address>>,
+    rebasing_tokens: Option<HashSet<Address>>,
+    rebase_sync_strikes: Option<u32>,
+    known_searchers: Option<HashSet<Address>>,
+    aggregator_routers: Option<HashSet<Address>>,
+    pool_whitelist: Option<HashSet<Address>>,
+    max_victim_priority_fee: Option<Wei>,
+    max_victim_tip_bps: Option<u64>,
+    max_position_weth: Option<Wei>,
+    max_frontrun_price_impact_bps: Option<u64>,
+    parked_tx_ttl_blocks: Option<u64>,
+    weth_address: Option<Address>,
+    max_concurrent_sims: Option<usize>,
+    min_replacement_gain_bps: Option<u64>,
+    max_bundles_per_block: Option<u32>,
+    rank_bundles_ms_before_block: Option<u64>,
+    max_bundle_gas_per_block: Option<u64>,
+    max_block_gas_fraction: Option<f64>,
+    victim_timeout_ms: Option<u64>,
+    max_sim_gas: Option<u64>,
+    sync_chunk_blocks: Option<u64>,
+    sync_concurrency: Option<usize>,
+    target_block_offsets: Option<Vec<u64>>,
+    inclusion_check_blocks: Option<u64>,
+    log_format: Option<String>,
+    sim_state_source: Option<String>,
+    stale_state_guard: Option<bool>,
+    state_diff_method: Option<String>,
+    access_list_mode: Option<String>,
+    payment_strategy: Option<String>,
+}
+
+impl StratConfig {
+    /// Load config from a toml file, fields it leaves out keep their defaults
+    /// note: `SEARCHER_PRIVATE_KEY` in the environment overrides `searcher_private_key`
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read_file(path.as_ref(), None)
+    }
+
+    /// Load config from a toml file, signing with `searcher_signer` (e.g. a ledger or kms
+    /// signer) instead of a searcher private key
+    pub fn from_file_with_signer(
+        path: impl AsRef<Path>,
+        searcher_signer: SearcherSigner,
+    ) -> Result<Self> {
+        Self::read_file(path.as_ref(), Some(searcher_signer))
+    }
+
+    /// Parse config from the contents of a toml config file (see `from_file`)
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        Self::parse_toml(contents, None)
+    }
+
+    fn read_file(path: &Path, searcher_signer: Option<SearcherSigner>) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read config file {}: {}", path.display(), e))?;
+
+        Self::parse_toml(&contents, searcher_signer)
+            .map_err(|e| anyhow!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    fn parse_toml(contents: &str, searcher_signer: Option<SearcherSigner>) -> Result<Self> {
+        let file: StratConfigFile = toml::from_str(contents)?;
+
+        ensure!(
+            !file.sando_addresses.is_empty(),
+            "\"sando_addresses\" needs at least one address"
+        );
+
+        let searcher_signer = match searcher_signer {
+            Some(searcher_signer) => searcher_signer,
+            None => env::var(SEARCHER_PRIVATE_KEY_ENV)
+                .ok()
+                .or(file.searcher_private_key)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Set \"searcher_private_key\" or the {} env var",
+                        SEARCHER_PRIVATE_KEY_ENV
+                    )
+                })?
+                .trim()
+                .parse::<SearcherSigner>()
+                .map_err(|_| anyhow!("Failed to parse searcher private key"))?,
+        };
+
+        let mut config = StratConfig::new(
+            file.sando_addresses[0],
+            U64::from(file.sando_inception_block),
+            searcher_signer,
+        );
+        config.sando_addresses = file.sando_addresses;
+        if let Some(profiles) = file.sando_gas_profiles {
+            let mut sando_gas_profiles = HashMap::new();
+            for profile in profiles {
+                ensure!(
+                    config.sando_addresses.contains(&profile.address),
+                    "Gas profile for {:?} which isn't in \"sando_addresses\"",
+                    profile.address
+                );
+                sando_gas_profiles.insert(profile.address, profile.into_gas_profile());
+            }
+            config.sando_gas_profiles = sando_gas_profiles;
+        }
+        if let Some(sando_rotation) = file.sando_rotation {
+            config.sando_rotation = sando_rotation.parse()?;
+        }
+        config.rng_seed = file.rng_seed;
+
+        if let Some(optimizer_epsilon) = file.optimizer_epsilon {
+            config.optimizer_epsilon = optimizer_epsilon.parse("optimizer_epsilon")?;
+        }
+        if let Some(min_profit_threshold) = file.min_profit_threshold {
+            config.min_profit_threshold = min_profit_threshold.parse("min_profit_threshold")?;
+        }
+        if let Some(simulation_tolerance_bps) = file.simulation_tolerance_bps {
+            config.simulation_tolerance_bps = simulation_tolerance_bps;
+        }
+        if let Some(max_pool_divergence_rate) = file.max_pool_divergence_rate {
+            ensure!(
+                (0.0..=1.0).contains(&max_pool_divergence_rate),
+                "\"max_pool_divergence_rate\" must be between 0 and 1"
+            );
+            config.max_pool_divergence_rate = Some(max_pool_divergence_rate);
+        }
+        if let Some(exclude_fee_switch_pools) = file.exclude_fee_switch_pools {
+            config.exclude_fee_switch_pools = exclude_fee_switch_pools;
+        }
+        config.preceding_txs_min_profit = file
+            .preceding_txs_min_profit
+            .map(|profit| profit.parse("preceding_txs_min_profit"))
+            .transpose()?;
+        if let Some(victim_position_assumption) = file.victim_position_assumption {
+            config.victim_position_assumption = victim_position_assumption.parse()?;
+        }
+        if let Some(blacklist) = file.blacklist {
+            config.blacklist = blacklist;
+        }
+        if let Some(dexes) = file.dexes {
+            config.dexes = dexes
+                .into_iter()
+                .map(DexFile::into_dex_config)
+                .collect::<Result<_>>()?;
+        }
+        if let Some(bid_fraction) = file.bid_fraction {
+            ensure!(
+                (0.0..=1.0).contains(&bid_fraction),
+                "\"bid_fraction\" must be between 0 and 1"
+            );
+            config.bid_fraction = bid_fraction;
+        }
+        if let Some(competition_level) = file.competition_level {
+            ensure!(
+                (0.0..=1.0).contains(&competition_level),
+                "\"competition_level\" must be between 0 and 1"
+            );
+            config.competition_level = competition_level;
+        }
+        if let Some(bid_escalation_step) = file.bid_escalation_step {
+            ensure!(
+                (0.0..=1.0).contains(&bid_escalation_step),
+                "\"bid_escalation_step\" must be between 0 and 1"
+            );
+            config.bid_escalation_step = bid_escalation_step;
+        }
+        if let Some(max_bid_fraction) = file.max_bid_fraction {
+            ensure!(
+                (0.0..=1.0).contains(&max_bid_fraction),
+                "\"max_bid_fraction\" must be between 0 and 1"
+            );
+            config.max_bid_fraction = max_bid_fraction;
+        }
+        if let Some(min_tip) = file.min_tip {
+            config.min_tip = min_tip.parse("min_tip")?;
+        }
+        if let Some(max_tip) = file.max_tip {
+            config.max_tip = max_tip.parse("max_tip")?;
+        }
+        ensure!(
+            config.min_tip <= config.max_tip,
+            "\"min_tip\" can't be above \"max_tip\""
+        );
+        if let Some(min_victim_swap_value) = file.min_victim_swap_value {
+            config.min_victim_swap_value = min_victim_swap_value.parse("min_victim_swap_value")?;
+        }
+        if let Some(state_diff_attempts) = file.state_diff_attempts {
+            config.state_diff_attempts = state_diff_attempts;
+        }
+        if let Some(state_diff_backoff_ms) = file.state_diff_backoff_ms {
+            config.state_diff_backoff_ms = state_diff_backoff_ms;
+        }
+        if let Some(state_diff_storage_fallback) = file.state_diff_storage_fallback {
+            config.state_diff_storage_fallback = state_diff_storage_fallback;
+        }
+        if let Some(metrics_port) = file.metrics_port {
+            config.metrics_port = metrics_port;
+        }
+        config.metrics_snapshot_path = file.metrics_snapshot_path;
+        config.opportunity_log_path = file.opportunity_log_path;
+        config.alert_webhook = file.alert_webhook;
+        config.max_window_loss = file
+            .max_window_loss
+            .map(|loss| loss.parse("max_window_loss"))
+            .transpose()?;
+        config.min_weth_inventory = file
+            .min_weth_inventory
+            .map(|floor| floor.parse("min_weth_inventory"))
+            .transpose()?;
+        config.min_searcher_balance = file
+            .min_searcher_balance
+            .map(|floor| floor.parse("min_searcher_balance"))
+            .transpose()?;
+        if let Some(loss_window_blocks) = file.loss_window_blocks {
+            config.loss_window_blocks = loss_window_blocks;
+        }
+        config.loss_cooldown_blocks = file.loss_cooldown_blocks;
+        config.victim_max_misses = file.victim_max_misses;
+        if let Some(victim_cooldown_blocks) = file.victim_cooldown_blocks {
+            config.victim_cooldown_blocks = victim_cooldown_blocks;
+        }
+        if let Some(health_stale_after_secs) = file.health_stale_after_secs {
+            config.health_stale_after_secs = health_stale_after_secs;
+        }
+        if let Some(enable_multihop) = file.enable_multihop {
+            config.enable_multihop = enable_multihop;
+        }
+        if let Some(enable_private_backruns) = file.enable_private_backruns {
+            config.enable_private_backruns = enable_private_backruns;
+        }
+        if let Some(enable_backrun_arb) = file.enable_backrun_arb {
+            config.enable_backrun_arb = enable_backrun_arb;
+        }
+        if let Some(ordering_strategies) = file.ordering_strategies {
+            ensure!(
+                !ordering_strategies.is_empty(),
+                "\"ordering_strategies\" needs at least one ordering"
+            );
+            config.ordering_strategies = ordering_strategies
+                .iter()
+                .map(|ordering| ordering.parse())
+                .collect::<Result<Vec<_>>>()?;
+        }
+        if let Some(frontrun_only_haircut_bps) = file.frontrun_only_haircut_bps {
+            ensure!(
+                frontrun_only_haircut_bps <= 10_000,
+                "\"frontrun_only_haircut_bps\" must be at most 10000"
+            );
+            config.frontrun_only_haircut_bps = frontrun_only_haircut_bps;
+        }
+        if let Some(prioritize_profitable_pools) = file.prioritize_profitable_pools {
+            config.prioritize_profitable_pools = prioritize_profitable_pools;
+        }
+        if let Some(enable_bundle_merging) = file.enable_bundle_merging {
+            config.enable_bundle_merging = enable_bundle_merging;
+        }
+        if let Some(opposing_victims) = file.opposing_victims {
+            config.opposing_victims = opposing_victims.parse()?;
+        }
+        if let Some(min_time_budget_ms) = file.min_time_budget_ms {
+            config.min_time_budget_ms = min_time_budget_ms;
+        }
+        if let Some(gas_limit_multiplier) = file.gas_limit_multiplier {
+            ensure!(
+                gas_limit_multiplier >= 1.0,
+                "\"gas_limit_multiplier\" can't be below 1"
+            );
+            config.gas_limit_multiplier = gas_limit_multiplier;
+        }
+        if let Some(backrun_min_out_buffer_bps) = file.backrun_min_out_buffer_bps {
+            ensure!(
+                backrun_min_out_buffer_bps <= MAX_BACKRUN_MIN_OUT_BUFFER_BPS,
+                "\"backrun_min_out_buffer_bps\" can't be above {}",
+                MAX_BACKRUN_MIN_OUT_BUFFER_BPS
+            );
+            config.backrun_min_out_buffer_bps = backrun_min_out_buffer_bps;
+        }
+        if let Some(base_fee_safety_margin_bps) = file.base_fee_safety_margin_bps {
+            ensure!(
+                base_fee_safety_margin_bps <= MAX_BASE_FEE_SAFETY_MARGIN_BPS,
+                "\"base_fee_safety_margin_bps\" can't be above {}",
+                MAX_BASE_FEE_SAFETY_MARGIN_BPS
+            );
+            config.base_fee_safety_margin_bps = base_fee_safety_margin_bps;
+        }
+        config.pool_update_threshold_bps = file.pool_update_threshold_bps;
+        if let Some(track_pool_prices) = file.track_pool_prices {
+            config.track_pool_prices = track_pool_prices;
+        }
+        config.hot_pool_change_bps = file.hot_pool_change_bps;
+        if let Some(cold_pool_after_blocks) = file.cold_pool_after_blocks {
+            ensure!(
+                cold_pool_after_blocks > 0,
+                "\"cold_pool_after_blocks\" must be above 0"
+            );
+            config.cold_pool_after_blocks = Some(cold_pool_after_blocks);
+        }
+        if let Some(refresh_hot_pools) = file.refresh_hot_pools {
+            config.refresh_hot_pools = refresh_hot_pools;
+        }
+        if let Some(mode) = file.mode {
+            config.mode = mode.parse()?;
+        }
+        if let Some(max_resync_failures) = file.max_resync_failures {
+            config.max_resync_failures = max_resync_failures;
+        }
+        if let Some(min_pool_liquidity) = file.min_pool_liquidity {
+            config.min_pool_liquidity = min_pool_liquidity.parse("min_pool_liquidity")?;
+        }
+        if let Some(stable_tokens) = file.stable_tokens {
+            config.stable_tokens = stable_tokens;
+        }
+        if let Some(rebasing_tokens) = file.rebasing_tokens {
+            config.rebasing_tokens = rebasing_tokens;
+        }
+        if let Some(rebase_sync_strikes) = file.rebase_sync_strikes {
+            config.rebase_sync_strikes = rebase_sync_strikes;
+        }
+        if let Some(known_searchers) = file.known_searchers {
+            config.known_searchers = known_searchers;
+        }
+        if let Some(aggregator_routers) = file.aggregator_routers {
+            config.aggregator_routers = aggregator_routers;
+        }
+        config.pool_whitelist = file.pool_whitelist;
+        if let Some(max_victim_priority_fee) = file.max_victim_priority_fee {
+            config.max_victim_priority_fee =
+                max_victim_priority_fee.parse("max_victim_priority_fee")?;
+        }
+        config.max_victim_tip_bps = file.max_victim_tip_bps;
+        if let Some(max_position_weth) = file.max_position_weth {
+            config.max_position_weth = Some(max_position_weth.parse("max_position_weth")?);
+        }
+        config.max_frontrun_price_impact_bps = file.max_frontrun_price_impact_bps;
+        if let Some(parked_tx_ttl_blocks) = file.parked_tx_ttl_blocks {
+            config.parked_tx_ttl_blocks = parked_tx_ttl_blocks;
+        }
+        if let Some(weth_address) = file.weth_address {
+            config.weth_address = weth_address;
+        }
+        if let Some(max_concurrent_sims) = file.max_concurrent_sims {
+            ensure!(
+                max_concurrent_sims > 0,
+                "\"max_concurrent_sims\" must be at least 1"
+            );
+            config.max_concurrent_sims = max_concurrent_sims;
+        }
+        if let Some(min_replacement_gain_bps) = file.min_replacement_gain_bps {
+            config.min_replacement_gain_bps = min_replacement_gain_bps;
+        }
+        if let Some(max_bundles_per_block) = file.max_bundles_per_block {
+            config.max_bundles_per_block = max_bundles_per_block;
+        }
+        if let Some(rank_bundles_ms_before_block) = file.rank_bundles_ms_before_block {
+            config.rank_bundles_ms_before_block = Some(rank_bundles_ms_before_block);
+        }
+        if let Some(max_bundle_gas_per_block) = file.max_bundle_gas_per_block {
+            config.max_bundle_gas_per_block = Some(max_bundle_gas_per_block);
+        }
+        if let Some(max_block_gas_fraction) = file.max_block_gas_fraction {
+            ensure!(
+                (0.0..=1.0).contains(&max_block_gas_fraction),
+                "\"max_block_gas_fraction\" must be between 0 and 1"
+            );
+            config.max_block_gas_fraction = Some(max_block_gas_fraction);
+        }
+        if let Some(victim_timeout_ms) = file.victim_timeout_ms {
+            ensure!(
+                victim_timeout_ms > 0,
+                "\"victim_timeout_ms\" must be above 0"
+            );
+            config.victim_timeout_ms = Some(victim_timeout_ms);
+        }
+        if let Some(max_sim_gas) = file.max_sim_gas {
+            ensure!(max_sim_gas > 0, "\"max_sim_gas\" must be above 0");
+            config.max_sim_gas = Some(max_sim_gas);
+        }
+        if let Some(sync_chunk_blocks) = file.sync_chunk_blocks {
+            ensure!(
+                sync_chunk_blocks > 0,
+                "\"sync_chunk_blocks\" must be above 0"
+            );
+            config.sync_chunk_blocks = Some(sync_chunk_blocks);
+        }
+        if let Some(sync_concurrency) = file.sync_concurrency {
+            ensure!(
+                sync_concurrency > 0,
+                "\"sync_concurrency\" must be at least 1"
+            );
+            config.sync_concurrency = sync_concurrency;
+        }
+        if let Some(mut target_block_offsets) = file.target_block_offsets {
+            ensure!(
+                !target_block_offsets.is_empty(),
+                "\"target_block_offsets\" needs at least one offset"
+            );
+            target_block_offsets.sort();
+            target_block_offsets.dedup();
+            config.target_block_offsets = target_block_offsets;
+        }
+        if let Some(inclusion_check_blocks) = file.inclusion_check_blocks {
+            config.inclusion_check_blocks = inclusion_check_blocks;
+        }
+        if let Some(log_format) = file.log_format {
+            config.log_format = log_format.parse()?;
+        }
+        if let Some(sim_state_source) = file.sim_state_source {
+            config.sim_state_source = sim_state_source.parse()?;
+        }
+        if let Some(stale_state_guard) = file.stale_state_guard {
+            config.stale_state_guard = stale_state_guard;
+        }
+        if let Some(state_diff_method) = file.state_diff_method {
+            config.state_diff_method = state_diff_method.parse()?;
+        }
+        if let Some(access_list_mode) = file.access_list_mode {
+            config.access_list_mode = access_list_mode.parse()?;
+        }
+        if let Some(payment_strategy) = file.payment_strategy {
+            config.payment_strategy = payment_strategy.parse()?;
+        }
+
+        Ok(config)
+    }
+}
@@ -43,6 +43,54 @@ pub static WETH_ADDRESS: Lazy<Address> = Lazy::new(|| {
         .unwrap()
 });
 
+// placeholders that routers and aggregators put in swap paths for native eth, the swap itself
+// always trades through weth pools
+pub static NATIVE_ETH_ADDRESSES: Lazy<Vec<Address>> = Lazy::new(|| {
+    vec![
+        Address::zero(),
+        "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE"
+            .parse()
+            .unwrap(),
+    ]
+});
+
+// mainnet USDC, USDT and DAI, pools between two stables barely move on a swap
+pub static STABLE_TOKENS: Lazy<Vec<Address>> = Lazy::new(|| {
+    [
+        "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+        "0xdAC17F958D2ee523a2206206994597C13D831ec7",
+        "0x6B175474E89094C44Da98b954EedeAC495271d0F",
+    ]
+    .iter()
+    .map(|address| address.parse().unwrap())
+    .collect()
+});
+
+// mainnet AMPL and stETH, their balances rebase so pool reserves drift from what our math sees
+pub static REBASING_TOKENS: Lazy<Vec<Address>> = Lazy::new(|| {
+    [
+        "0xD46bA6D942050d489DBd938a2C909A5d5039A161",
+        "0xae7ab96520DE3A18E5e111B5EaAb095312D7fE84",
+    ]
+    .iter()
+    .map(|address| address.parse().unwrap())
+    .collect()
+});
+
+// mainnet 1inch v5 and v6, 0x exchange proxy and CoW settlement, their calldata doesn't decode as
+// a router swap but the pools they call show up in the state diffs
+pub static AGGREGATOR_ROUTERS: Lazy<Vec<Address>> = Lazy::new(|| {
+    [
+        "0x1111111254EEB25477B68fb85Ed929f73A960582",
+        "0x111111125421cA6dc452d289314280a0f8842A65",
+        "0xDef1C0ded9bec7F1a1670819833240f027b25EfF",
+        "0x9008D19f58AAbD9eD0D60971565AA8510560ab41",
+    ]
+    .iter()
+    .map(|address| address.parse().unwrap())
+    .collect()
+});
+
 // when we need an address with a lot of eth
 pub static SUGAR_DADDY: Lazy<Address> = Lazy::new(|| {
     "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
@@ -58,3 +106,76 @@ pub static COINBASE: Lazy<rAddress> = Lazy::new(|| {
 });
 
 pub static DUST_OVERPAY: Lazy<U256> = Lazy::new(|| ethers::utils::parse_ether("0.00015").unwrap());
+
+// rough gas used by huff sando for a v2 frontrun/backrun (used for sizing before simulating)
+pub const V2_FRONTRUN_GAS_ESTIMATE: u64 = 100_000;
+pub const V2_BACKRUN_GAS_ESTIMATE: u64 = 100_000;
+
+// rough gas used by huff sando for a v3 frontrun/backrun (used for sizing before simulating)
+pub const V3_FRONTRUN_GAS_ESTIMATE: u64 = 150_000;
+pub const V3_BACKRUN_GAS_ESTIMATE: u64 = 150_000;
+
+// rough extra gas paid by a v3 swap for every initialized tick it crosses
+pub const V3_TICK_CROSS_GAS_ESTIMATE: u64 = 25_000;
+
+// rough extra gas paid by a backrun that transfers its bribe to `block.coinbase`
+pub const COINBASE_PAYMENT_GAS_ESTIMATE: u64 = 10_000;
+
+// uniswap v3 fee tiers in hundredths of a bip (0.01%, 0.05%, 0.3%, 1%)
+pub const V3_FEE_TIERS: [u32; 4] = [100, 500, 3_000, 10_000];
+
+// how many tick bitmap words either side of the current tick to sync for v3 sandwich math
+pub const V3_TICK_BITMAP_WORD_RADIUS: i16 = 2;
+
+// how many recent blocks the block manager remembers for detecting reorgs
+pub const REORG_BUFFER_SIZE: usize = 64;
+
+// eip-1559 parameters used to predict the next block's base fee
+pub const ELASTICITY_MULTIPLIER: u64 = 2;
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+
+// how many pending tx hashes the mempool manager remembers for skipping rebroadcasts
+pub const SEEN_TX_CACHE_SIZE: usize = 20_000;
+
+// blocks of factory logs queried at once when catching pools up from the cache
+// (`sync_chunk_blocks` overrides it)
+pub const POOL_SYNC_CHUNK_BLOCKS: u64 = 10_000;
+
+// pools whose tokens and reserves are fetched at once while discovering pools
+// (`sync_concurrency` overrides it)
+pub const POOL_SYNC_CONCURRENCY: usize = 10;
+
+// attempts at each chunk of pool creation logs before pool discovery gives up, waiting
+// `POOL_SYNC_RETRY_BACKOFF_MS` (doubling every attempt) in between
+pub const POOL_SYNC_CHUNK_ATTEMPTS: u32 = 3;
+pub const POOL_SYNC_RETRY_BACKOFF_MS: u64 = 500;
+
+// storage slot uniswap v2 pairs pack their reserves in, forks with another layout have theirs
+// detected among the first `V2_RESERVE_SLOT_CANDIDATES` slots
+pub const V2_RESERVE_SLOT: u64 = 8;
+pub const V2_RESERVE_SLOT_CANDIDATES: u64 = 16;
+
+// contracts whose code simulations keep between blocks, later ones are fetched every time
+pub const CODE_CACHE_MAX_CONTRACTS: usize = 20_000;
+
+// blocks a pool stays hot after moving at least `hot_pool_change_bps` in one block
+pub const HOT_POOL_WINDOW_BLOCKS: u64 = 10;
+
+// multicall3, deployed at the same address on every chain we run on
+pub static MULTICALL_ADDRESS: Lazy<Address> = Lazy::new(|| {
+    "0xcA11bde05977b3631167028862bE2a173976CA11"
+        .parse()
+        .unwrap()
+});
+
+// pools whose state is re-read per multicall when refreshing hot pools
+pub const MULTICALL_REFRESH_POOLS: usize = 100;
+
+// simulations of a pool before its divergence rate can disable it (a single mismatch shouldn't)
+pub const MIN_POOL_DIVERGENCE_SAMPLES: u64 = 5;
+
+// blocks between refreshing the sando contracts' weth and the searcher's eth balance
+pub const BALANCE_CHECK_INTERVAL_BLOCKS: u64 = 10;
+
+// blocks of pipeline funnels summed into each periodic funnel summary log
+pub const FUNNEL_SUMMARY_INTERVAL_BLOCKS: u64 = 25;
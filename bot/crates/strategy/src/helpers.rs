@@ -1,12 +1,17 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use anyhow::{anyhow, Result};
 use ethers::{
-    signers::{LocalWallet, Signer},
+    signers::Signer,
     types::{
         transaction::{
             eip2718::TypedTransaction,
             eip2930::{AccessList, AccessListItem},
         },
-        BigEndianHash, Bytes, Eip1559TransactionRequest, H256,
+        BigEndianHash, Bytes, Eip1559TransactionRequest, H256, I256, U256,
     },
 };
 use foundry_evm::{
@@ -14,10 +19,12 @@ use foundry_evm::{
     utils::{b160_to_h160, h160_to_b160, ru256_to_u256, u256_to_ru256},
 };
 
+use crate::{signer::SearcherSigner, types::LogFormat};
+
 /// Sign eip1559 transactions
 pub async fn sign_eip1559(
     tx: Eip1559TransactionRequest,
-    signer_wallet: &LocalWallet,
+    signer_wallet: &SearcherSigner,
 ) -> Result<Bytes> {
     let tx_typed = TypedTransaction::Eip1559(tx);
     let signed_frontrun_tx_sig = signer_wallet
@@ -61,75 +68,260 @@ pub fn access_list_to_revm(access_list: AccessList) -> Vec<(B160, Vec<rU256>)> {
         .collect()
 }
 
+/// Gas limit for a tx estimated to use `gas_used`, padded by `multiplier` so state shifting
+/// between simulation and inclusion doesn't run it out of gas
+pub fn padded_gas_limit(gas_used: u64, multiplier: f64) -> U256 {
+    U256::from((gas_used as f64 * multiplier).ceil() as u64)
+}
+
+/// Nearest f64 to `value` (loses precision past 2^53, fine for prices and ratios)
+pub fn u256_to_f64(value: U256) -> f64 {
+    value
+        .0
+        .iter()
+        .rev()
+        .fold(0.0, |acc, limb| acc * 2f64.powi(64) + *limb as f64)
+}
+
+/// `amount` (in raw units) of a token with `decimals` in whole tokens, e.g. 1500000 of a 6
+/// decimal token is "1.5" (trailing zeros are trimmed)
+pub fn format_token_amount(amount: U256, decimals: u8) -> String {
+    // no real token has this many, U256 can't hold the unit either
+    if decimals > 77 {
+        return amount.to_string();
+    }
+    let unit = U256::exp10(decimals as usize);
+    let (whole, fraction) = amount.div_mod(unit);
+    if fraction.is_zero() {
+        return whole.to_string();
+    }
+    let fraction = format!("{:0>width$}", fraction, width = decimals as usize);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Same as `format_token_amount` for a signed amount (e.g. a loss)
+pub fn format_signed_token_amount(amount: I256, decimals: u8) -> String {
+    let formatted = format_token_amount(amount.unsigned_abs(), decimals);
+    if amount.is_negative() {
+        format!("-{}", formatted)
+    } else {
+        formatted
+    }
+}
+
+/// Milliseconds from now until unix `timestamp` (negative once it has passed)
+pub fn ms_until(timestamp: U256) -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    timestamp.low_u64() as i64 * 1000 - now
+}
+
 //
 // -- Logging Macros --
 //
+// Text output is colored for terminals, json output (see `LogFormat`) is one object per line.
+// Macros optionally take `key = value` fields before the message, e.g.
+// `log_not_sandwichable!(tx_hash = tx.hash, pool = pool; "no profitable input")`,
+// fields are only emitted in json mode.
+//
+static JSON_LOGS: AtomicBool = AtomicBool::new(false);
+
+/// Switch the logging macros between colored text and json output
+pub fn set_log_format(log_format: LogFormat) {
+    JSON_LOGS.store(log_format == LogFormat::Json, Ordering::Relaxed);
+}
+
+/// True if the logging macros should emit json
+pub fn json_logs() -> bool {
+    JSON_LOGS.load(Ordering::Relaxed)
+}
+
+/// Build a json log line, field values are `Debug` formatted so hashes and addresses keep their
+/// full hex
+pub fn format_json_log(event: &str, message: String, fields: &[(&str, String)]) -> String {
+    let mut line = serde_json::Map::new();
+    line.insert("event".to_string(), event.into());
+    line.insert("message".to_string(), message.into());
+    for (key, value) in fields {
+        line.insert(key.to_string(), value.clone().into());
+    }
+    serde_json::Value::Object(line).to_string()
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_formatted {
+    ($level:ident, $event:expr, [$($key:ident = $value:expr),*], $style:expr, $($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        if $crate::helpers::json_logs() {
+            $level!(
+                "{}",
+                $crate::helpers::format_json_log(
+                    $event,
+                    message,
+                    &[$((stringify!($key), format!("{:?}", $value))),*]
+                )
+            );
+        } else {
+            $level!("{}", ($style)(message));
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! log_info_cyan {
+    ($($key:ident = $value:expr),*; $($arg:tt)*) => {
+        $crate::log_formatted!(info, "info", [$($key = $value),*], |m: String| m.cyan(), $($arg)*)
+    };
     ($($arg:tt)*) => {
-        info!("{}", format_args!($($arg)*).to_string().cyan());
+        $crate::log_info_cyan!(; $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! log_not_sandwichable {
+    ($($key:ident = $value:expr),*; $($arg:tt)*) => {
+        $crate::log_formatted!(
+            info,
+            "not_sandwichable",
+            [$($key = $value),*],
+            |m: String| m.yellow(),
+            $($arg)*
+        )
+    };
+    ($($arg:tt)*) => {
+        $crate::log_not_sandwichable!(; $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! log_deadline_missed {
+    ($($key:ident = $value:expr),*; $($arg:tt)*) => {
+        $crate::log_formatted!(
+            info,
+            "deadline_missed",
+            [$($key = $value),*],
+            |m: String| m.yellow(),
+            $($arg)*
+        )
+    };
     ($($arg:tt)*) => {
-        info!("{}", format_args!($($arg)*).to_string().yellow())
+        $crate::log_deadline_missed!(; $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! log_stale_state_abort {
+    ($($key:ident = $value:expr),*; $($arg:tt)*) => {
+        $crate::log_formatted!(
+            info,
+            "stale_state_abort",
+            [$($key = $value),*],
+            |m: String| m.yellow(),
+            $($arg)*
+        )
+    };
+    ($($arg:tt)*) => {
+        $crate::log_stale_state_abort!(; $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! log_opportunity {
-    ($meats:expr, $optimal_input:expr, $revenue:expr) => {{
-        info!("\n{}", "[OPPORTUNITY DETECTED]".green().on_black().bold());
-        info!(
-            "{}",
-            format!("meats: {}", $meats.to_string().green().on_black()).bold()
-        );
-        info!(
-            "{}",
-            format!(
-                "optimal_input: {} wETH",
-                $optimal_input.to_string().green().on_black()
-            )
-            .bold()
-        );
-        info!(
-            "{}",
-            format!(
-                "revenue      : {} wETH",
-                $revenue.to_string().green().on_black()
-            )
-            .bold()
-        );
+    ($meats:expr, $optimal_input:expr, $revenue:expr, $token:expr) => {{
+        if $crate::helpers::json_logs() {
+            info!(
+                "{}",
+                $crate::helpers::format_json_log(
+                    "opportunity",
+                    "opportunity detected".to_string(),
+                    &[
+                        ("meats", $meats.to_string()),
+                        ("optimal_input", $optimal_input.to_string()),
+                        ("profit", $revenue.to_string()),
+                        ("token", $token.to_string()),
+                    ]
+                )
+            );
+        } else {
+            info!("\n{}", "[OPPORTUNITY DETECTED]".green().on_black().bold());
+            info!(
+                "{}",
+                format!("meats: {}", $meats.to_string().green().on_black()).bold()
+            );
+            info!(
+                "{}",
+                format!(
+                    "optimal_input: {} {}",
+                    $optimal_input.to_string().green().on_black(),
+                    $token
+                )
+                .bold()
+            );
+            info!(
+                "{}",
+                format!(
+                    "revenue      : {} {}",
+                    $revenue.to_string().green().on_black(),
+                    $token
+                )
+                .bold()
+            );
+        }
     }};
 }
 
 #[macro_export]
 macro_rules! startup_info_log {
     ($($arg:tt)*) => {
-        info!("{}", format_args!($($arg)*).to_string().on_black().yellow().bold());
+        $crate::log_formatted!(
+            info,
+            "startup",
+            [],
+            |m: String| m.on_black().yellow().bold(),
+            $($arg)*
+        )
     };
 }
 
 #[macro_export]
 macro_rules! log_error {
+    ($($key:ident = $value:expr),*; $($arg:tt)*) => {
+        $crate::log_formatted!(error, "error", [$($key = $value),*], |m: String| m.red(), $($arg)*)
+    };
     ($($arg:tt)*) => {
-        error!("{}", format_args!($($arg)*).to_string().red());
+        $crate::log_error!(; $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! log_new_block_info {
     ($new_block:expr) => {
-        log::info!(
-            "{}",
-            format!(
-                "\nFound New Block\nLatest Block: (number:{:?}, timestamp:{:?}, basefee:{:?})",
-                $new_block.number, $new_block.timestamp, $new_block.base_fee_per_gas,
-            )
-            .bright_purple()
-            .on_black()
-        );
+        if $crate::helpers::json_logs() {
+            log::info!(
+                "{}",
+                $crate::helpers::format_json_log(
+                    "new_block",
+                    "found new block".to_string(),
+                    &[
+                        ("block", format!("{:?}", $new_block.number)),
+                        ("timestamp", format!("{:?}", $new_block.timestamp)),
+                        ("base_fee", format!("{:?}", $new_block.base_fee_per_gas)),
+                    ]
+                )
+            );
+        } else {
+            log::info!(
+                "{}",
+                format!(
+                    "\nFound New Block\nLatest Block: (number:{:?}, timestamp:{:?}, basefee:{:?})",
+                    $new_block.number, $new_block.timestamp, $new_block.base_fee_per_gas,
+                )
+                .bright_purple()
+                .on_black()
+            );
+        }
     };
 }
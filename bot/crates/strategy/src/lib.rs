@@ -3,14 +3,35 @@ mod constants;
 mod helpers;
 mod simulator;
 
+/// Module contains closed form math used to size sandwiches
+pub mod math;
+
+/// Module contains logic for valuing tokens in weth
+pub mod pricing;
+
+/// Module contains logic for sizing priority fee bids
+mod bidding;
+
 /// Module contains logic to manage info on onchain pools
-mod managers;
+pub mod managers;
+
+/// Module contains prometheus metrics and the endpoints serving them (`/metrics`, `/health`)
+mod metrics;
 
 /// Module contains logic related to transaction building
-mod tx_utils;
+pub mod tx_utils;
 
 /// Module contains core strategy implementation
 pub mod bot;
 
 /// Module contains the core type defenitions for sandwiching
 pub mod types;
+
+/// Module contains the seeded rng behind every random choice the bot makes
+pub mod rng;
+
+/// Module contains the searcher's tx signer (local key, keystore, ledger or kms)
+pub mod signer;
+
+/// Module contains loading `StratConfig` from a toml file
+mod config_file;
@@ -0,0 +1,150 @@
+use colored::Colorize;
+use ethers::types::{Address, TxHash, U256, U64};
+use log::error;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::bundle_audit::{BundleStatus, LandedBundle};
+use crate::log_error;
+
+/// Something the operator should hear about, posted to the alert webhook as json (or as its
+/// `message` to discord)
+pub trait Alert: Serialize + Send + 'static {
+    /// One line summary for chat channels
+    fn message(&self) -> String;
+}
+
+/// A landed sandwich as posted to the alert webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct LandedAlert {
+    pub block: U64,
+    pub victim_hash: TxHash,
+    pub backrun_hash: TxHash,
+    pub pool: Address,
+    /// Token the frontrun input is in, `None` for private backruns
+    pub input_token: Option<Address>,
+    /// Frontrun input in whole tokens, `None` if the token's decimals aren't known
+    pub input_amount: Option<String>,
+    /// Estimated net profit (in wei)
+    pub net_profit: String,
+    /// Same as `net_profit` but in eth, for humans
+    pub net_profit_eth: f64,
+    /// One of our txs reverted, `net_profit` is the gas it cost
+    pub failed: bool,
+}
+
+impl LandedAlert {
+    pub fn new(bundle: &LandedBundle) -> Self {
+        Self {
+            block: bundle.block,
+            victim_hash: bundle.record.victim_hash,
+            backrun_hash: bundle.backrun_hash,
+            pool: bundle.pool,
+            input_token: bundle.record.input_token,
+            input_amount: bundle.record.input_amount.clone(),
+            net_profit: bundle.net_profit.to_string(),
+            net_profit_eth: bundle.net_profit.as_i128() as f64 / 1e18,
+            failed: bundle.status == BundleStatus::Failed,
+        }
+    }
+}
+
+impl Alert for LandedAlert {
+    fn message(&self) -> String {
+        if self.failed {
+            return format!(
+                "Sandwich landed but reverted in block {}: {:.6} eth on pool {:?} (victim {:?})",
+                self.block, self.net_profit_eth, self.pool, self.victim_hash
+            );
+        }
+        format!(
+            "Sandwich landed in block {}: {:.6} eth on pool {:?} (victim {:?})",
+            self.block, self.net_profit_eth, self.pool, self.victim_hash
+        )
+    }
+}
+
+/// A balance the bot spends from dropped below its configured floor, e.g. a sando contract's
+/// weth inventory or the searcher's eth for gas
+#[derive(Debug, Clone, Serialize)]
+pub struct LowBalanceAlert {
+    pub block: U64,
+    pub account: Address,
+    /// What ran low, `weth` or `eth`
+    pub asset: String,
+    /// Balance (in wei)
+    pub balance: String,
+    /// Same as `balance` but in eth, for humans
+    pub balance_eth: f64,
+    /// Floor the balance dropped below (in eth)
+    pub floor_eth: f64,
+}
+
+impl LowBalanceAlert {
+    pub fn new(block: U64, account: Address, asset: &str, balance: U256, floor: U256) -> Self {
+        Self {
+            block,
+            account,
+            asset: asset.to_string(),
+            balance: balance.to_string(),
+            balance_eth: balance.as_u128() as f64 / 1e18,
+            floor_eth: floor.as_u128() as f64 / 1e18,
+        }
+    }
+}
+
+impl Alert for LowBalanceAlert {
+    fn message(&self) -> String {
+        format!(
+            "Low {} balance on {:?} in block {}: {:.6} eth (floor {:.6} eth), top up before the \
+             bot goes idle",
+            self.asset, self.account, self.block, self.balance_eth, self.floor_eth
+        )
+    }
+}
+
+/// Posts alerts (every landed sandwich, low balances) to a webhook, failed posts are only logged
+pub struct AlertNotifier {
+    client: reqwest::Client,
+    url: String,
+    /// Discord webhooks only show a `content` message
+    discord: bool,
+}
+
+impl AlertNotifier {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            discord: url.contains("discord.com/api/webhooks")
+                || url.contains("discordapp.com/api/webhooks"),
+            url,
+        }
+    }
+
+    /// Json body posted for `alert`
+    pub fn payload(&self, alert: &impl Alert) -> Value {
+        match self.discord {
+            true => json!({ "content": alert.message() }),
+            false => json!(alert),
+        }
+    }
+
+    /// Post `alert` in the background so a slow webhook never holds up the bot
+    pub fn notify(&self, alert: impl Alert) {
+        let request = self.client.post(&self.url).json(&self.payload(&alert));
+        let message = alert.message();
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    log_error!(
+                        "Alert webhook answered {} to \"{}\"",
+                        response.status(),
+                        message
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => log_error!("Failed to post alert \"{}\": {}", message, e),
+            }
+        });
+    }
+}
@@ -0,0 +1,29 @@
+use ethers::types::{Address, U256};
+use std::collections::HashSet;
+
+/// Remembers which balances are below their floor, so each drop is alerted once instead of on
+/// every check until it gets topped up
+#[derive(Debug, Default)]
+pub struct BalanceMonitor {
+    /// Accounts last seen below their floor
+    low: HashSet<Address>,
+}
+
+impl BalanceMonitor {
+    /// Record the latest `balance` of `account` against its `floor`
+    ///
+    /// Returns true if the balance just dropped below the floor (it was above it, or unchecked,
+    /// before), topping it back up re-arms the alert
+    pub fn check(&mut self, account: Address, balance: U256, floor: U256) -> bool {
+        if balance >= floor {
+            self.low.remove(&account);
+            return false;
+        }
+        self.low.insert(account)
+    }
+
+    /// True if `account` was below its floor when last checked
+    pub fn is_low(&self, account: Address) -> bool {
+        self.low.contains(&account)
+    }
+}
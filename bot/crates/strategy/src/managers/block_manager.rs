@@ -1,15 +1,27 @@
 use anyhow::{anyhow, Result};
-use ethers::{providers::Middleware, types::BlockNumber};
+use ethers::{
+    providers::Middleware,
+    types::{BlockNumber, U256, U64},
+};
 use log::info;
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
 use colored::Colorize;
 
-use crate::{startup_info_log, types::BlockInfo};
+use crate::{
+    constants::{BASE_FEE_MAX_CHANGE_DENOMINATOR, ELASTICITY_MULTIPLIER, REORG_BUFFER_SIZE},
+    log_info_cyan, startup_info_log,
+    types::BlockInfo,
+};
 
+#[derive(Default)]
 pub struct BlockManager {
     latest_block: BlockInfo,
     next_block: BlockInfo,
+    /// Most recent blocks on the canonical chain (oldest first), used to detect reorgs
+    recent_blocks: VecDeque<BlockInfo>,
+    /// Numbers of blocks dropped from the canonical chain by reorgs, until taken
+    reorged_blocks: Vec<U64>,
 }
 
 impl BlockManager {
@@ -17,6 +29,8 @@ impl BlockManager {
         Self {
             latest_block: BlockInfo::default(),
             next_block: BlockInfo::default(),
+            recent_blocks: VecDeque::with_capacity(REORG_BUFFER_SIZE),
+            reorged_blocks: vec![],
         }
     }
 
@@ -39,15 +53,137 @@ impl BlockManager {
         self.next_block
     }
 
-    /// Return info for the next block
+    /// Return info for the latest block
     pub fn get_latest_block(&self) -> BlockInfo {
         self.latest_block
     }
 
+    /// Exact base fee of the next block (derived from the latest block's gas used vs gas target)
+    pub fn predict_next_base_fee(&self) -> U256 {
+        calculate_next_block_base_fee(&self.latest_block)
+    }
+
+    /// Numbers of blocks reorged out since the last call (anything that landed in them didn't)
+    pub fn take_reorged_blocks(&mut self) -> Vec<U64> {
+        std::mem::take(&mut self.reorged_blocks)
+    }
+
     /// Updates internal state with the latest mined block and next block
-    pub fn update_block_info<T: Into<BlockInfo>>(&mut self, latest_block: T) {
+    /// note: if the block's parent is not our tip, we roll back to the common ancestor before
+    /// applying it (or start fresh if the ancestor is older than our buffer)
+    ///
+    /// Returns `Some(next_block)` if a reorg was detected, `None` otherwise
+    pub fn update_block_info<T: Into<BlockInfo>>(&mut self, latest_block: T) -> Option<BlockInfo> {
         let latest_block: BlockInfo = latest_block.into();
+
+        // block already part of our chain (duplicate notification)
+        if latest_block.hash.is_some()
+            && self
+                .recent_blocks
+                .iter()
+                .any(|b| b.hash == latest_block.hash)
+        {
+            return None;
+        }
+
+        let is_reorg = match self.recent_blocks.back() {
+            Some(tip) => tip.hash != latest_block.parent_hash,
+            None => false,
+        };
+
+        if is_reorg {
+            // the new block replaces every block from its height up
+            for dropped in self
+                .recent_blocks
+                .iter()
+                .filter(|b| b.number >= latest_block.number)
+            {
+                if !self.reorged_blocks.contains(&dropped.number) {
+                    self.reorged_blocks.push(dropped.number);
+                }
+            }
+
+            match self
+                .recent_blocks
+                .iter()
+                .position(|b| b.hash.is_some() && b.hash == latest_block.parent_hash)
+            {
+                // drop blocks that are no longer on the canonical chain
+                Some(ancestor) => self.recent_blocks.truncate(ancestor + 1),
+                // reorg deeper than our buffer (or a gap in blocks)
+                None => self.recent_blocks.clear(),
+            }
+
+            log_info_cyan!(
+                "reorg detected, new tip {:?} ({:?})",
+                latest_block.number,
+                latest_block.hash
+            );
+        }
+
+        if self.recent_blocks.len() == REORG_BUFFER_SIZE {
+            self.recent_blocks.pop_front();
+        }
+        self.recent_blocks.push_back(latest_block);
+
         self.latest_block = latest_block;
         self.next_block = latest_block.get_next_block();
+
+        if is_reorg {
+            Some(self.next_block)
+        } else {
+            None
+        }
     }
 }
+
+/// Calculate the next block base fee
+// follows the spec exactly: https://eips.ethereum.org/EIPS/eip-1559
+pub fn calculate_next_block_base_fee(block: &BlockInfo) -> U256 {
+    let parent_base_fee = block.base_fee_per_gas;
+
+    let parent_gas_used = block
+        .gas_used
+        .expect("can't calculate base fee from unmined block \"next_block\"");
+
+    let parent_gas_target = block
+        .gas_limit
+        .expect("can't calculate base fee from unmined block \"next_block\"")
+        / ELASTICITY_MULTIPLIER;
+
+    if parent_gas_target.is_zero() || parent_gas_used == parent_gas_target {
+        parent_base_fee
+    } else if parent_gas_used > parent_gas_target {
+        let gas_used_delta = parent_gas_used - parent_gas_target;
+        // base fee must increase by at least 1 wei when block is over target
+        let base_fee_delta = (parent_base_fee * gas_used_delta
+            / parent_gas_target
+            / BASE_FEE_MAX_CHANGE_DENOMINATOR)
+            .max(U256::one());
+
+        parent_base_fee + base_fee_delta
+    } else {
+        let gas_used_delta = parent_gas_target - parent_gas_used;
+        let base_fee_delta =
+            parent_base_fee * gas_used_delta / parent_gas_target / BASE_FEE_MAX_CHANGE_DENOMINATOR;
+
+        parent_base_fee.saturating_sub(base_fee_delta)
+    }
+}
+
+/// Largest base fee safety margin (in bps), one block's max base fee rise
+pub const MAX_BASE_FEE_SAFETY_MARGIN_BPS: u64 = 1_250;
+
+/// `base_fee` raised by `margin_bps`, fees checked against it still clear a base fee that comes
+/// in that much above the prediction
+pub fn with_base_fee_margin(base_fee: U256, margin_bps: u64) -> U256 {
+    base_fee + base_fee * U256::from(margin_bps) / U256::from(10_000)
+}
+
+/// Highest base fee a block `blocks` after one with `base_fee` can have (every block in between
+/// full, each raising the base fee by the max 1/8)
+pub fn max_base_fee_after(base_fee: U256, blocks: u64) -> U256 {
+    (0..blocks).fold(base_fee, |base_fee, _| {
+        base_fee + (base_fee / BASE_FEE_MAX_CHANGE_DENOMINATOR).max(U256::one())
+    })
+}
@@ -0,0 +1,209 @@
+use ethers::types::{Address, TxHash, I256, U256, U64};
+use std::collections::VecDeque;
+
+use super::opportunity_log::OpportunityRecord;
+
+/// Whether a landed bundle's block is still on the canonical chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleStatus {
+    Landed,
+    /// Landed but one of our txs reverted onchain, it only paid gas
+    Failed,
+    /// Its block was reorged out, the bundle's profit no longer counts
+    Reverted,
+}
+
+/// A bundle that landed onchain, kept while a reorg can still drop its block
+#[derive(Debug, Clone)]
+pub struct LandedBundle {
+    pub block: U64,
+    pub backrun_hash: TxHash,
+    pub pool: Address,
+    pub revenue: U256,
+    /// Estimated net profit (what metrics and pool stats count), the gas lost for failed
+    /// bundles
+    pub net_profit: I256,
+    /// Realized net profit from receipts, `None` if it wasn't fed to the loss guard
+    pub realized_profit: Option<I256>,
+    pub status: BundleStatus,
+    /// Logged again if the bundle gets reverted
+    pub record: OpportunityRecord,
+}
+
+impl LandedBundle {
+    pub fn new(
+        block: U64,
+        backrun_hash: TxHash,
+        pool: Address,
+        revenue: U256,
+        net_profit: I256,
+        record: OpportunityRecord,
+    ) -> Self {
+        Self {
+            block,
+            backrun_hash,
+            pool,
+            revenue,
+            net_profit,
+            realized_profit: None,
+            status: BundleStatus::Landed,
+            record,
+        }
+    }
+
+    pub fn with_realized_profit(mut self, realized_profit: I256) -> Self {
+        self.realized_profit = Some(realized_profit);
+        self
+    }
+
+    /// Record the bundle as `status` instead of `Landed` (e.g. `Failed`)
+    pub fn with_status(mut self, status: BundleStatus) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+/// Onchain profit of a block that our bundles landed in, from the sando contracts' weth and the
+/// searcher's eth balances before and after the block
+/// note: covers every bundle of ours in the block, balances can't tell them apart
+#[derive(Debug, Clone)]
+pub struct BlockReconciliation {
+    pub block: U64,
+    /// Bundles of ours that landed in the block
+    pub bundles: usize,
+    /// Summed estimated net profit of those bundles
+    pub estimated_profit: I256,
+    /// Change of our balances across the block
+    pub realized_profit: I256,
+    pub status: BundleStatus,
+}
+
+impl BlockReconciliation {
+    pub fn new(block: U64, bundles: usize, estimated_profit: I256, realized_profit: I256) -> Self {
+        Self {
+            block,
+            bundles,
+            estimated_profit,
+            realized_profit,
+            status: BundleStatus::Landed,
+        }
+    }
+
+    /// How much more the block made onchain than estimated (negative if our model overshot)
+    pub fn error(&self) -> I256 {
+        self.realized_profit - self.estimated_profit
+    }
+}
+
+/// Change of a balance from `before` to `after`
+pub fn balance_delta(before: U256, after: U256) -> I256 {
+    I256::from_raw(after) - I256::from_raw(before)
+}
+
+/// Bundles that landed in recent blocks, so profit counted for them can be taken back when a
+/// reorg drops their block
+pub struct BundleAudit {
+    /// Blocks that landed bundles are kept for (reorgs deeper than this aren't reconciled)
+    depth: u64,
+    /// Oldest first
+    bundles: VecDeque<LandedBundle>,
+    /// Estimated net profit of bundles that are still landed, since startup
+    landed_profit: I256,
+    /// Balance reconciliations of blocks that bundles landed in (oldest first)
+    reconciliations: VecDeque<BlockReconciliation>,
+    /// Onchain profit of reconciled blocks that weren't reorged out, since startup
+    reconciled_profit: I256,
+    /// Estimated net profit of the same blocks, since startup
+    reconciled_estimate: I256,
+}
+
+impl BundleAudit {
+    pub fn new(depth: u64) -> Self {
+        Self {
+            depth,
+            bundles: VecDeque::new(),
+            landed_profit: I256::zero(),
+            reconciliations: VecDeque::new(),
+            reconciled_profit: I256::zero(),
+            reconciled_estimate: I256::zero(),
+        }
+    }
+
+    /// Record a bundle that landed in `bundle.block`
+    pub fn record_landed(&mut self, bundle: LandedBundle) {
+        self.landed_profit += bundle.net_profit;
+        let oldest = bundle
+            .block
+            .saturating_sub(U64::from(self.depth.saturating_sub(1)));
+        self.bundles.push_back(bundle);
+
+        while matches!(self.bundles.front(), Some(b) if b.block < oldest) {
+            self.bundles.pop_front();
+        }
+    }
+
+    /// Record the onchain profit of a block that bundles landed in
+    pub fn record_reconciliation(&mut self, reconciliation: BlockReconciliation) {
+        self.reconciled_profit += reconciliation.realized_profit;
+        self.reconciled_estimate += reconciliation.estimated_profit;
+        let oldest = reconciliation
+            .block
+            .saturating_sub(U64::from(self.depth.saturating_sub(1)));
+        self.reconciliations.push_back(reconciliation);
+
+        while matches!(self.reconciliations.front(), Some(r) if r.block < oldest) {
+            self.reconciliations.pop_front();
+        }
+    }
+
+    /// Mark bundles that landed in any of `blocks` as reverted and take their profit out of the
+    /// totals
+    ///
+    /// Returns the bundles reverted by this call
+    pub fn revert_blocks(&mut self, blocks: &[U64]) -> Vec<LandedBundle> {
+        let mut reverted = vec![];
+        for bundle in self.bundles.iter_mut() {
+            if bundle.status == BundleStatus::Reverted || !blocks.contains(&bundle.block) {
+                continue;
+            }
+
+            bundle.status = BundleStatus::Reverted;
+            self.landed_profit -= bundle.net_profit;
+            reverted.push(bundle.clone());
+        }
+
+        for reconciliation in self.reconciliations.iter_mut() {
+            if reconciliation.status == BundleStatus::Reverted
+                || !blocks.contains(&reconciliation.block)
+            {
+                continue;
+            }
+
+            reconciliation.status = BundleStatus::Reverted;
+            self.reconciled_profit -= reconciliation.realized_profit;
+            self.reconciled_estimate -= reconciliation.estimated_profit;
+        }
+        reverted
+    }
+
+    /// Estimated net profit of landed bundles that weren't reorged out
+    pub fn landed_profit(&self) -> I256 {
+        self.landed_profit
+    }
+
+    /// Onchain profit of reconciled blocks that weren't reorged out, with what we estimated for
+    /// the same blocks (blocks whose balances couldn't be read are in neither)
+    pub fn reconciled_profit(&self) -> (I256, I256) {
+        (self.reconciled_profit, self.reconciled_estimate)
+    }
+
+    /// Recent balance reconciliations (oldest first)
+    pub fn reconciliations(&self) -> impl Iterator<Item = &BlockReconciliation> {
+        self.reconciliations.iter()
+    }
+
+    /// Recent landed and reverted bundles (oldest first)
+    pub fn bundles(&self) -> impl Iterator<Item = &LandedBundle> {
+        self.bundles.iter()
+    }
+}
@@ -0,0 +1,124 @@
+use ethers::types::{Address, I256, U64};
+use std::collections::HashSet;
+
+/// A sandwich found for a target block, held until the block's other sandwiches are in
+pub struct HeldOpportunity<T> {
+    /// Pool that the sandwich trades against (two bundles on one pool conflict)
+    pub pool: Address,
+    /// Net profit after gas and the builder's bid
+    pub net_profit: I256,
+    /// Gas the frontrun and backrun used in simulation
+    pub gas_used: u64,
+    pub opportunity: T,
+}
+
+/// What the bundles sent for a single block may use between them
+#[derive(Debug, Clone, Copy)]
+pub struct BlockBudget {
+    /// Each bundle takes the next pair of searcher nonces
+    pub max_bundles: u32,
+    /// Gas of our frontruns and backruns summed over the block's bundles
+    pub max_gas: u64,
+}
+
+impl BlockBudget {
+    /// Also cap `max_gas` at `fraction` of the block's `gas_limit`, builders only give one
+    /// searcher's bundles part of a block
+    pub fn with_block_gas_fraction(mut self, gas_limit: u64, fraction: f64) -> Self {
+        let share = (gas_limit as f64 * fraction.clamp(0.0, 1.0)) as u64;
+        self.max_gas = self.max_gas.min(share);
+        self
+    }
+}
+
+/// Holds the sandwiches found for the next block so only the most profitable ones are sent
+/// once the block is close (see `select_top_opportunities`)
+pub struct BundleRanking<T> {
+    target_block: U64,
+    held: Vec<HeldOpportunity<T>>,
+}
+
+impl<T> Default for BundleRanking<T> {
+    fn default() -> Self {
+        Self {
+            target_block: U64::zero(),
+            held: vec![],
+        }
+    }
+}
+
+impl<T> BundleRanking<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold `opportunity` for the current target block (see `expire_before`)
+    pub fn hold(&mut self, opportunity: HeldOpportunity<T>) {
+        self.held.push(opportunity);
+    }
+
+    /// Take everything held for `target_block`
+    pub fn take(&mut self, target_block: U64) -> Vec<HeldOpportunity<T>> {
+        match self.target_block == target_block {
+            true => std::mem::take(&mut self.held),
+            false => vec![],
+        }
+    }
+
+    /// Start holding for `target_block` (no-op if already holding for it)
+    ///
+    /// Returns opportunities held for an earlier block, those can no longer land
+    pub fn expire_before(&mut self, target_block: U64) -> Vec<HeldOpportunity<T>> {
+        if self.target_block == target_block {
+            return vec![];
+        }
+        self.target_block = target_block;
+        std::mem::take(&mut self.held)
+    }
+
+    /// Number of opportunities held for the current target block
+    pub fn len(&self) -> usize {
+        self.held.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    /// Opportunities held for the current target block
+    pub fn held(&self) -> &[HeldOpportunity<T>] {
+        &self.held
+    }
+}
+
+/// Pick the most profitable opportunities that fit the block's budget (at most one per pool)
+///
+/// Returns `(selected, dropped)`, selected in descending net profit
+pub fn select_top_opportunities<T>(
+    mut held: Vec<HeldOpportunity<T>>,
+    budget: BlockBudget,
+) -> (Vec<HeldOpportunity<T>>, Vec<HeldOpportunity<T>>) {
+    held.sort_by(|a, b| b.net_profit.cmp(&a.net_profit));
+
+    let mut selected = vec![];
+    let mut dropped = vec![];
+    let mut pools = HashSet::new();
+    let mut gas_used = 0u64;
+    for opportunity in held {
+        // builders run bundles one after another, a second bundle on the same pool would
+        // frontrun a price our math never saw
+        let fits = selected.len() < budget.max_bundles as usize
+            && !pools.contains(&opportunity.pool)
+            && gas_used.saturating_add(opportunity.gas_used) <= budget.max_gas;
+
+        if fits {
+            pools.insert(opportunity.pool);
+            gas_used += opportunity.gas_used;
+            selected.push(opportunity);
+        } else {
+            dropped.push(opportunity);
+        }
+    }
+
+    (selected, dropped)
+}
@@ -0,0 +1,136 @@
+/// Stages of the pipeline from a pending tx to a landed bundle, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunnelStage {
+    /// Pending txs received
+    Seen,
+    /// Passed the pre-filter our heuristics run before tracing
+    Prefiltered,
+    /// Touched a sandwichable pool once traced
+    TouchedPools,
+    /// Sized into at least one opportunity with positive profit
+    Profitable,
+    /// Bundles and private txs sent to the executor
+    Submitted,
+    /// Bundles included onchain
+    Landed,
+}
+
+impl FunnelStage {
+    pub const ALL: [FunnelStage; 6] = [
+        FunnelStage::Seen,
+        FunnelStage::Prefiltered,
+        FunnelStage::TouchedPools,
+        FunnelStage::Profitable,
+        FunnelStage::Submitted,
+        FunnelStage::Landed,
+    ];
+
+    /// Metric label and name in log summaries
+    pub fn label(&self) -> &'static str {
+        match self {
+            FunnelStage::Seen => "seen",
+            FunnelStage::Prefiltered => "prefiltered",
+            FunnelStage::TouchedPools => "touched_pools",
+            FunnelStage::Profitable => "profitable",
+            FunnelStage::Submitted => "submitted",
+            FunnelStage::Landed => "landed",
+        }
+    }
+}
+
+/// How many txs (or bundles, see `FunnelStage`) reached each stage over one or more blocks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockFunnel {
+    /// Blocks counted
+    pub blocks: u64,
+    /// Counts indexed like `FunnelStage::ALL`
+    counts: [u64; 6],
+}
+
+impl BlockFunnel {
+    pub fn get(&self, stage: FunnelStage) -> u64 {
+        self.counts[stage as usize]
+    }
+
+    /// Share of the previous stage that made it to `stage` (in percent), `None` for the first
+    /// stage or when nothing reached the previous one
+    pub fn conversion(&self, stage: FunnelStage) -> Option<f64> {
+        let index = stage as usize;
+        if index == 0 || self.counts[index - 1] == 0 {
+            return None;
+        }
+        Some(self.counts[index] as f64 * 100.0 / self.counts[index - 1] as f64)
+    }
+
+    /// One line per funnel, e.g. `seen 200 > prefiltered 40 (20.0%) > ...`
+    pub fn summary(&self) -> String {
+        FunnelStage::ALL
+            .iter()
+            .map(|stage| match self.conversion(*stage) {
+                Some(conversion) => {
+                    format!(
+                        "{} {} ({:.1}%)",
+                        stage.label(),
+                        self.get(*stage),
+                        conversion
+                    )
+                }
+                None => format!("{} {}", stage.label(), self.get(*stage)),
+            })
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+
+    fn add(&mut self, other: &BlockFunnel) {
+        self.blocks += other.blocks;
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+    }
+}
+
+/// Counts the funnel of the block being targeted, closed out when the next block arrives, and
+/// sums closed funnels for a periodic summary
+pub struct FunnelTracker {
+    /// Blocks summed before a summary is due
+    summary_interval_blocks: u64,
+    /// Funnel of the block being targeted
+    current: BlockFunnel,
+    /// Closed funnels since the last summary
+    window: BlockFunnel,
+}
+
+impl FunnelTracker {
+    pub fn new(summary_interval_blocks: u64) -> Self {
+        Self {
+            summary_interval_blocks,
+            current: BlockFunnel::default(),
+            window: BlockFunnel::default(),
+        }
+    }
+
+    /// Count `count` more txs (or bundles) reaching `stage` for the block being targeted
+    pub fn record(&mut self, stage: FunnelStage, count: u64) {
+        self.current.counts[stage as usize] += count;
+    }
+
+    /// Close out the funnel of the block that was just mined (record its landed bundles
+    /// first), counters start over for the next block
+    ///
+    /// Returns the closed block's funnel
+    pub fn close_block(&mut self) -> BlockFunnel {
+        let mut closed = std::mem::take(&mut self.current);
+        closed.blocks = 1;
+        self.window.add(&closed);
+        closed
+    }
+
+    /// Returns the funnel summed over the last `summary_interval_blocks` closed blocks once they
+    /// have all closed (the window starts over), `None` until then
+    pub fn poll_summary(&mut self) -> Option<BlockFunnel> {
+        if self.window.blocks < self.summary_interval_blocks.max(1) {
+            return None;
+        }
+        Some(std::mem::take(&mut self.window))
+    }
+}
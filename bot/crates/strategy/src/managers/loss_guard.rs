@@ -0,0 +1,138 @@
+use ethers::{
+    types::{Address, TransactionReceipt, H256, I256, U256, U64},
+    utils::keccak256,
+};
+use std::collections::VecDeque;
+
+/// Stops submissions once landed bundles lose more than `max_loss` over a rolling window of
+/// blocks (bad math or someone baiting us), until re-enabled after a cooldown or a restart
+pub struct LossGuard {
+    /// Largest realized loss allowed over the window (in wei)
+    max_loss: U256,
+    /// Blocks that realized profit is summed over
+    window_blocks: u64,
+    /// Blocks to stay halted before submitting again, `None` = only a restart re-enables
+    cooldown_blocks: Option<u64>,
+    /// Realized net profit of landed bundles (oldest first)
+    realized: VecDeque<(U64, I256)>,
+    /// Block that the guard tripped in, `None` while submitting
+    tripped_at: Option<U64>,
+}
+
+impl LossGuard {
+    pub fn new(max_loss: U256, window_blocks: u64) -> Self {
+        Self {
+            max_loss,
+            window_blocks,
+            cooldown_blocks: None,
+            realized: VecDeque::new(),
+            tripped_at: None,
+        }
+    }
+
+    /// Submit again `cooldown_blocks` after tripping (instead of waiting for a restart)
+    pub fn with_cooldown_blocks(mut self, cooldown_blocks: u64) -> Self {
+        self.cooldown_blocks = Some(cooldown_blocks);
+        self
+    }
+
+    /// Record the realized profit of a bundle that landed in `block`
+    ///
+    /// Returns true if this pushed the window's loss past `max_loss` (guard just tripped)
+    pub fn record(&mut self, block: U64, net_profit: I256) -> bool {
+        self.realized.push_back((block, net_profit));
+        self.prune(block);
+
+        if self.is_halted() || self.window_profit() >= -I256::from_raw(self.max_loss) {
+            return false;
+        }
+
+        self.tripped_at = Some(block);
+        true
+    }
+
+    /// Take back profit recorded for a bundle in `block` that got reorged out
+    /// note: a tripped guard stays tripped, losses that just caused it aren't trusted either way
+    pub fn forget(&mut self, block: U64, net_profit: I256) {
+        if let Some(index) = self
+            .realized
+            .iter()
+            .position(|(b, profit)| *b == block && *profit == net_profit)
+        {
+            self.realized.remove(index);
+        }
+    }
+
+    /// Realized net profit of landed bundles in the window
+    pub fn window_profit(&self) -> I256 {
+        self.realized
+            .iter()
+            .fold(I256::zero(), |total, (_, profit)| total + *profit)
+    }
+
+    /// True while tripped (nothing should be submitted)
+    pub fn is_halted(&self) -> bool {
+        self.tripped_at.is_some()
+    }
+
+    /// Re-enable once the cooldown has passed (window starts over so old losses don't trip the
+    /// guard again straight away)
+    ///
+    /// Returns true if the guard was re-enabled by this call
+    pub fn poll_cooldown(&mut self, block: U64) -> bool {
+        match (self.tripped_at, self.cooldown_blocks) {
+            (Some(tripped_at), Some(cooldown)) if block >= tripped_at + cooldown => {
+                self.tripped_at = None;
+                self.realized.clear();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forget profit from blocks that fell out of the window
+    fn prune(&mut self, block: U64) {
+        let oldest = block.saturating_sub(U64::from(self.window_blocks.saturating_sub(1)));
+        while matches!(self.realized.front(), Some((b, _)) if *b < oldest) {
+            self.realized.pop_front();
+        }
+    }
+}
+
+/// Realized net profit of a landed bundle from its receipts: weth that our txs moved into the
+/// sando contract (the txs' `to`) minus weth they moved out, minus gas paid and `coinbase_payment`
+pub fn realized_profit(
+    receipts: &[TransactionReceipt],
+    weth: Address,
+    coinbase_payment: U256,
+) -> I256 {
+    let transfer_topic = H256::from(keccak256("Transfer(address,address,uint256)"));
+
+    let mut profit = -I256::from_raw(coinbase_payment);
+    for receipt in receipts {
+        let sando = match receipt.to {
+            Some(sando) => H256::from(sando),
+            None => continue,
+        };
+
+        for log in &receipt.logs {
+            if log.address != weth || log.topics.len() != 3 || log.topics[0] != transfer_topic {
+                continue;
+            }
+
+            let amount = I256::from_raw(U256::from_big_endian(&log.data));
+            if log.topics[2] == sando {
+                profit += amount;
+            }
+            if log.topics[1] == sando {
+                profit -= amount;
+            }
+        }
+
+        let gas_cost =
+            receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or_default();
+        profit -= I256::from_raw(gas_cost);
+    }
+
+    profit
+}
@@ -0,0 +1,177 @@
+use ethers::types::{Address, Transaction, TxHash, U256, U64};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::constants::SEEN_TX_CACHE_SIZE;
+
+/// Remembers which pending txs were already evaluated so rebroadcasts aren't traced again,
+/// spots victims replacing their tx (same sender and nonce, usually with a higher gas price), and
+/// parks txs whose max fee is below the next base fee until they can be included
+pub struct MempoolManager {
+    /// Tx hash -> block the tx was evaluated for
+    seen: HashMap<TxHash, U64>,
+    /// Seen hashes (oldest first), used to evict once the cache is full
+    order: VecDeque<TxHash>,
+    /// Most tx hashes to remember
+    capacity: usize,
+    /// (sender, nonce) -> latest pending tx using that nonce
+    by_sender_nonce: HashMap<(Address, U256), TxHash>,
+    /// Tx hash -> (block the tx was parked at, tx) for txs that can't pay the base fee yet
+    parked: HashMap<TxHash, (U64, Transaction)>,
+}
+
+impl Default for MempoolManager {
+    fn default() -> Self {
+        Self::new(SEEN_TX_CACHE_SIZE)
+    }
+}
+
+impl MempoolManager {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+            by_sender_nonce: HashMap::new(),
+            parked: HashMap::new(),
+        }
+    }
+
+    /// Hold on to a tx that can't pay `next_block`'s base fee (keeps the original park block if
+    /// the tx is already parked)
+    pub fn park(&mut self, tx: Transaction, next_block: U64) {
+        self.parked.entry(tx.hash).or_insert((next_block, tx));
+    }
+
+    /// Remove and return parked txs that can pay `base_fee`
+    pub fn take_includable(&mut self, base_fee: U256) -> Vec<Transaction> {
+        let includable: Vec<TxHash> = self
+            .parked
+            .iter()
+            .filter(|(_, (_, tx))| max_fee_per_gas(tx) >= base_fee)
+            .map(|(tx_hash, _)| *tx_hash)
+            .collect();
+
+        includable
+            .into_iter()
+            .filter_map(|tx_hash| self.parked.remove(&tx_hash).map(|(_, tx)| tx))
+            .collect()
+    }
+
+    /// Drop parked txs that were mined or have been parked for more than `ttl_blocks`
+    pub fn prune_parked(&mut self, mined: &HashSet<TxHash>, next_block: U64, ttl_blocks: u64) {
+        self.parked.retain(|tx_hash, (parked_at, _)| {
+            !mined.contains(tx_hash) && next_block <= *parked_at + ttl_blocks
+        });
+    }
+
+    /// Number of txs waiting for the base fee to drop
+    pub fn parked_count(&self) -> usize {
+        self.parked.len()
+    }
+
+    /// Record `tx` as the latest pending tx for its sender and nonce
+    ///
+    /// Returns the hash of the tx it replaces, if we saw one
+    pub fn track_replacement(&mut self, tx: &Transaction) -> Option<TxHash> {
+        match self.by_sender_nonce.insert((tx.from, tx.nonce), tx.hash) {
+            Some(replaced) if replaced != tx.hash => {
+                // old version can never be mined now
+                self.seen.remove(&replaced);
+                self.parked.remove(&replaced);
+                Some(replaced)
+            }
+            _ => None,
+        }
+    }
+
+    /// Record that `tx_hash` is being evaluated for `target_block`
+    ///
+    /// Returns false if it was already evaluated for this block (a rebroadcast)
+    pub fn mark_seen(&mut self, tx_hash: TxHash, target_block: U64) -> bool {
+        if let Some(seen_for) = self.seen.get_mut(&tx_hash) {
+            if *seen_for == target_block {
+                return false;
+            }
+
+            // still pending after a new block, pool state has changed so evaluate again
+            *seen_for = target_block;
+            return true;
+        }
+
+        if self.seen.len() >= self.capacity {
+            while let Some(oldest) = self.order.pop_front() {
+                if self.seen.remove(&oldest).is_some() {
+                    break;
+                }
+            }
+        }
+
+        self.seen.insert(tx_hash, target_block);
+        self.order.push_back(tx_hash);
+        true
+    }
+
+    /// Forget txs evaluated for blocks before `next_block` (they were mined or are stale)
+    pub fn evict_stale(&mut self, next_block: U64) {
+        self.seen.retain(|_, seen_for| *seen_for >= next_block);
+        self.order.retain(|tx_hash| self.seen.contains_key(tx_hash));
+        self.by_sender_nonce.retain(|_, tx_hash| {
+            self.seen.contains_key(tx_hash) || self.parked.contains_key(tx_hash)
+        });
+    }
+}
+
+/// Most a tx pays per gas (legacy txs pay their gas price)
+pub fn max_fee_per_gas(tx: &Transaction) -> U256 {
+    tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default()
+}
+
+/// Priority fee per gas a tx pays on top of `base_fee` (legacy txs pay everything above it)
+pub fn priority_fee_per_gas(tx: &Transaction, base_fee: U256) -> U256 {
+    let fee_headroom = max_fee_per_gas(tx).saturating_sub(base_fee);
+    match tx.max_priority_fee_per_gas {
+        Some(max_priority_fee) => max_priority_fee.min(fee_headroom),
+        None => fee_headroom,
+    }
+}
+
+/// Pending txs from `pending` that a builder would run before `victim`, in the order it would
+/// run them: they outbid the victim's priority fee at `base_fee` (highest first), the victim
+/// and its sender's other txs are left out since their nonce fixes their order
+pub fn preceding_txs(
+    victim: &Transaction,
+    pending: &[Transaction],
+    base_fee: U256,
+) -> Vec<Transaction> {
+    let victim_fee = priority_fee_per_gas(victim, base_fee);
+    let mut preceding: Vec<Transaction> = pending
+        .iter()
+        .filter(|tx| tx.hash != victim.hash && tx.from != victim.from)
+        .filter(|tx| priority_fee_per_gas(tx, base_fee) > victim_fee)
+        .cloned()
+        .collect();
+    preceding.sort_by_key(|tx| std::cmp::Reverse(priority_fee_per_gas(tx, base_fee)));
+    preceding
+}
+
+/// True if a tx could tip builders more than `max_tip_bps` of the `swap_value` it swaps
+/// note: the tip is counted over the tx's whole gas limit, gas used isn't known before tracing
+pub fn tip_exceeds_swap_share(
+    tx: &Transaction,
+    base_fee: U256,
+    swap_value: U256,
+    max_tip_bps: u64,
+) -> bool {
+    let max_tip = priority_fee_per_gas(tx, base_fee).saturating_mul(tx.gas);
+    max_tip.saturating_mul(U256::from(10_000)) > swap_value.saturating_mul(U256::from(max_tip_bps))
+}
+
+/// True if both the victim and our bundle (paying at most `bundle_max_fee_per_gas` on its
+/// cheapest tx) clear `base_fee`, builders drop the whole bundle if any of its txs can't pay it
+pub fn can_include_in_target_block(
+    victim_tx: &Transaction,
+    bundle_max_fee_per_gas: U256,
+    base_fee: U256,
+) -> bool {
+    max_fee_per_gas(victim_tx) >= base_fee && bundle_max_fee_per_gas >= base_fee
+}
@@ -1,3 +1,15 @@
-pub(crate) mod block_manager;
+pub mod alert_notifier;
+pub mod balance_monitor;
+pub mod block_manager;
+pub mod bundle_audit;
+pub mod bundle_ranking;
+pub mod funnel;
+pub mod loss_guard;
+pub mod mempool_manager;
+pub mod nonce_manager;
+pub mod opportunity_log;
 pub(crate) mod pool_manager;
+pub mod rebase_detector;
 pub(crate) mod sando_state_manager;
+pub mod simulation_cache;
+pub mod victim_cooldown;
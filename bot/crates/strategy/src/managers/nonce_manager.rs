@@ -0,0 +1,124 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, U256, U64},
+};
+use log::info;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::startup_info_log;
+
+/// Hands out nonces for the searcher's frontrun and backrun txs without asking the node each time
+pub struct NonceManager {
+    searcher_address: Address,
+    /// Nonce that the next bundle's frontrun will use
+    next_nonce: AtomicU64,
+    /// Most bundles that reserve nonces for the same target block
+    max_bundles_per_block: u32,
+    /// Target block and how many bundles have reserved nonces for it
+    block_budget: (U64, u32),
+}
+
+impl NonceManager {
+    pub fn new(searcher_address: Address) -> Self {
+        Self {
+            searcher_address,
+            next_nonce: AtomicU64::new(0),
+            max_bundles_per_block: u32::MAX,
+            block_budget: (U64::zero(), 0),
+        }
+    }
+
+    /// Cap how many bundles can target the same block
+    /// note: a later bundle's nonces are only valid if every earlier bundle lands before it
+    pub fn with_max_bundles_per_block(mut self, max_bundles_per_block: u32) -> Self {
+        self.max_bundles_per_block = max_bundles_per_block;
+        self
+    }
+
+    /// Seed the local nonce from the searcher's onchain tx count
+    pub async fn setup<M: Middleware + 'static>(&mut self, provider: Arc<M>) -> Result<()> {
+        let nonce = provider
+            .get_transaction_count(self.searcher_address, Some(BlockNumber::Latest.into()))
+            .await
+            .map_err(|_| anyhow!("Failed to get searcher nonce"))?;
+
+        self.next_nonce = AtomicU64::new(nonce.as_u64());
+
+        startup_info_log!("searcher nonce   : {}", nonce);
+        Ok(())
+    }
+
+    /// Reserve nonces for a bundle
+    ///
+    /// Returns `(frontrun_nonce, backrun_nonce)` where `backrun_nonce = frontrun_nonce + 1`
+    pub fn reserve_bundle_nonces(&self) -> (U256, U256) {
+        let frontrun_nonce = self.next_nonce.fetch_add(2, Ordering::SeqCst);
+        (frontrun_nonce.into(), (frontrun_nonce + 1).into())
+    }
+
+    /// Reserve nonces for a bundle targeting `target_block`
+    ///
+    /// Returns None if `max_bundles_per_block` bundles already target this block
+    pub fn reserve_nonces_for_block(&mut self, target_block: U64) -> Option<(U256, U256)> {
+        self.reset_block_budget(target_block);
+
+        if self.block_budget.1 >= self.max_bundles_per_block {
+            return None;
+        }
+        self.block_budget.1 += 1;
+
+        Some(self.reserve_bundle_nonces())
+    }
+
+    /// Reserve a single nonce for a private backrun targeting `target_block` (counts against
+    /// the same block budget as bundles, give it back with `cancel_bundle`)
+    ///
+    /// Returns None if `max_bundles_per_block` bundles already target this block
+    pub fn reserve_nonce_for_block(&mut self, target_block: U64) -> Option<U256> {
+        self.reset_block_budget(target_block);
+
+        if self.block_budget.1 >= self.max_bundles_per_block {
+            return None;
+        }
+        self.block_budget.1 += 1;
+
+        Some(self.next_nonce.fetch_add(1, Ordering::SeqCst).into())
+    }
+
+    /// Start counting bundles for a new target block (no-op if already counting `target_block`)
+    pub fn reset_block_budget(&mut self, target_block: U64) {
+        if self.block_budget.0 != target_block {
+            self.block_budget = (target_block, 0);
+        }
+    }
+
+    /// Give back nonces and the block budget of a bundle that was never sent
+    pub fn cancel_bundle(&mut self, frontrun_nonce: U256) {
+        self.block_budget.1 = self.block_budget.1.saturating_sub(1);
+        self.release_bundle_nonces(frontrun_nonce);
+    }
+
+    /// Roll back after a bundle did not land (or was never sent)
+    /// note: bundles reserved after this one can't land either (their nonces leave a gap), so
+    /// the next bundle starts again from the failed bundle's frontrun nonce
+    pub fn release_bundle_nonces(&self, frontrun_nonce: U256) {
+        self.next_nonce
+            .fetch_min(frontrun_nonce.as_u64(), Ordering::SeqCst);
+    }
+
+    /// Catch up with the searcher's onchain tx count (e.g. txs sent from outside the bot)
+    pub fn sync_onchain_nonce(&self, onchain_nonce: U256) {
+        self.next_nonce
+            .fetch_max(onchain_nonce.as_u64(), Ordering::SeqCst);
+    }
+
+    /// Nonce that the next bundle's frontrun will use
+    pub fn get_next_nonce(&self) -> U256 {
+        self.next_nonce.load(Ordering::SeqCst).into()
+    }
+}
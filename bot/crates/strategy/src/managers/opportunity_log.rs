@@ -0,0 +1,187 @@
+use anyhow::Result;
+use ethers::types::{Address, TxHash, I256, U256, U64};
+use serde::{Deserialize, Serialize};
+use std::{fs::OpenOptions, io::Write, path::PathBuf};
+
+use crate::helpers::format_token_amount;
+
+/// What happened to a sandwich opportunity once it was sized
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "reason")]
+pub enum OpportunityOutcome {
+    /// Dropped after sizing (failed simulation, unprofitable after gas, lost to another bundle..)
+    Rejected(String),
+    /// Sent to relays (or recorded in dry run mode)
+    Submitted,
+    /// Backrun was included in the target block
+    Landed,
+    /// Included in a target block, but one of our txs reverted (only gas was spent)
+    LandedFailed,
+    /// Target block was mined without our backrun
+    NotLanded,
+    /// Landed, then its block was reorged out
+    Reverted,
+}
+
+/// One line of the opportunity log, everything needed to compare our estimates with what
+/// actually happened onchain
+#[derive(Debug, Clone, Serialize)]
+pub struct OpportunityRecord {
+    pub target_block: U64,
+    pub victim_hash: TxHash,
+    pub pool: Address,
+    /// Frontrun input (zero for private backruns)
+    pub input: U256,
+    /// Token the frontrun input is in, `None` for private backruns
+    pub input_token: Option<Address>,
+    /// `input` in whole tokens, `None` if the input token's decimals aren't known
+    pub input_amount: Option<String>,
+    /// Net profit from our closed form math (in wei)
+    pub analytic_profit: String,
+    /// Net profit after simulating the sandwich in revm (in wei), `None` if never simulated
+    pub simulated_profit: Option<String>,
+    /// Gas used by our txs in the simulation
+    pub gas: Option<u64>,
+    /// Paid to the builder
+    pub bid: Option<U256>,
+    pub outcome: OpportunityOutcome,
+}
+
+impl OpportunityRecord {
+    pub fn new(
+        target_block: U64,
+        victim_hash: TxHash,
+        pool: Address,
+        input: U256,
+        analytic_profit: I256,
+    ) -> Self {
+        Self {
+            target_block,
+            victim_hash,
+            pool,
+            input,
+            input_token: None,
+            input_amount: None,
+            analytic_profit: analytic_profit.to_string(),
+            simulated_profit: None,
+            gas: None,
+            bid: None,
+            outcome: OpportunityOutcome::Submitted,
+        }
+    }
+
+    /// Profit and gas found when simulating the sandwich
+    pub fn with_simulation(mut self, simulated_profit: I256, gas: u64) -> Self {
+        self.simulated_profit = Some(simulated_profit.to_string());
+        self.gas = Some(gas);
+        self
+    }
+
+    /// Token the input is in, with its decimals if they're known
+    pub fn with_input_token(mut self, token: Address, decimals: Option<u8>) -> Self {
+        self.input_token = Some(token);
+        self.input_amount = decimals.map(|decimals| format_token_amount(self.input, decimals));
+        self
+    }
+
+    pub fn with_bid(mut self, bid: U256) -> Self {
+        self.bid = Some(bid);
+        self
+    }
+
+    pub fn with_outcome(mut self, outcome: OpportunityOutcome) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    pub fn rejected(self, reason: impl Into<String>) -> Self {
+        self.with_outcome(OpportunityOutcome::Rejected(reason.into()))
+    }
+}
+
+/// Running totals of how a pool's sandwiches worked out, used to look at pools that paid off
+/// before others
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStats {
+    /// Profitable sandwiches sized on the pool
+    pub opportunities: u64,
+    /// Bundles on the pool that landed
+    pub landed: u64,
+    /// Estimated net profit of landed bundles (in wei)
+    pub cumulative_profit: I256,
+    /// Sandwiches on the pool simulated in revm
+    #[serde(default)]
+    pub simulated: u64,
+    /// Simulations whose revenue disagreed with our math beyond `simulation_tolerance_bps`
+    #[serde(default)]
+    pub diverged: u64,
+}
+
+impl PoolStats {
+    pub fn record_opportunity(&mut self) {
+        self.opportunities += 1;
+    }
+
+    pub fn record_landed(&mut self, net_profit: I256) {
+        self.landed += 1;
+        self.cumulative_profit += net_profit;
+    }
+
+    /// Undo `record_landed` for a bundle whose block was reorged out
+    pub fn record_reverted(&mut self, net_profit: I256) {
+        self.landed = self.landed.saturating_sub(1);
+        self.cumulative_profit -= net_profit;
+    }
+
+    /// Cumulative profit spread over every opportunity sized on the pool (zero if none were),
+    /// pools that get sized often but rarely land rank below ones that land consistently
+    pub fn profit_per_opportunity(&self) -> I256 {
+        if self.opportunities == 0 {
+            return I256::zero();
+        }
+        self.cumulative_profit / I256::from(self.opportunities)
+    }
+
+    /// Count a simulated sandwich, `diverged` if its revenue disagreed with our math
+    pub fn record_simulation(&mut self, diverged: bool) {
+        self.simulated += 1;
+        if diverged {
+            self.diverged += 1;
+        }
+    }
+
+    /// Share of simulations that disagreed with our math (0.0 to 1.0), `None` if none ran
+    pub fn divergence_rate(&self) -> Option<f64> {
+        if self.simulated == 0 {
+            return None;
+        }
+        Some(self.diverged as f64 / self.simulated as f64)
+    }
+}
+
+/// Append only json lines file of every sized opportunity, for tuning parameters against real
+/// outcomes
+pub struct OpportunityLog {
+    path: PathBuf,
+}
+
+impl OpportunityLog {
+    /// Log to `path` (created on the first record)
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Append `record` as a single json line
+    pub fn record_opportunity(&self, record: &OpportunityRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+
+        Ok(())
+    }
+}
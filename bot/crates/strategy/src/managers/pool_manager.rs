@@ -1,83 +1,1177 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use cfmms::{
-    checkpoint::sync_pools_from_checkpoint,
     dex::{Dex, DexVariant},
-    pool::Pool,
+    pool::{Pool, UniswapV2Pool, UniswapV3Pool},
     sync::sync_pairs,
 };
 use colored::Colorize;
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use ethers::{
-    abi,
+    abi::{Token, Tokenizable},
+    contract::{parse_log, EthEvent, Multicall},
     providers::Middleware,
-    types::{Address, BlockNumber, Diff, TraceType, Transaction, H160, H256, U256},
+    types::{
+        AccountDiff, Address, BlockNumber, Bytes, Diff, Filter, Log, Transaction, H256, I256, U256,
+        U64,
+    },
+};
+use futures::{stream, StreamExt};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs, mem,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::{
+    abi::{
+        uniswap_v2_pair::{BurnFilter, MintFilter, SwapFilter as V2SwapFilter, SyncFilter},
+        uniswap_v3_pool::SwapFilter,
+        Erc20, PairCreatedFilter, PoolCreatedFilter, UniswapV2Pair,
+        UniswapV3Pool as UniswapV3PoolContract,
+    },
+    constants::{
+        MIN_POOL_DIVERGENCE_SAMPLES, MULTICALL_ADDRESS, MULTICALL_REFRESH_POOLS,
+        POOL_SYNC_CHUNK_ATTEMPTS, POOL_SYNC_CHUNK_BLOCKS, POOL_SYNC_CONCURRENCY,
+        POOL_SYNC_RETRY_BACKOFF_MS, V2_RESERVE_SLOT, V2_RESERVE_SLOT_CANDIDATES,
+        V3_TICK_BITMAP_WORD_RADIUS,
+    },
+    helpers::{format_token_amount, u256_to_f64},
+    log_error, log_info_cyan,
+    managers::opportunity_log::PoolStats,
+    math::v3_sandwich::{self, TickMap},
+    metrics,
+    pricing::{self, WethLiquidity},
+    rng::DecisionRng,
+    startup_info_log,
+    types::{
+        packs_v2_reserves, v2_swap_state_diffs, DexConfig, PoolActivity, PoolManagerMetrics,
+        PoolState, RejectReason, SandoError, StateDiffMethod, VictimInfo,
+    },
 };
-use log::info;
-use std::{path::Path, str::FromStr, sync::Arc};
 
-use crate::{constants::WETH_ADDRESS, startup_info_log};
+/// Where discovered pools are persisted between runs
+const POOL_CACHE_PATH: &str = ".pool-cache.json";
 
+/// Discovers and tracks the monitored pools, every method takes `&self` so one synced manager can
+/// be shared by several bots
 pub(crate) struct PoolManager<M> {
     /// Provider
     provider: Arc<M>,
     /// Sandwichable pools
     pools: DashMap<Address, Pool>,
+    /// Token -> addresses of every pool that trades it
+    token_pools: DashMap<Address, Vec<Address>>,
+    /// Spot price of each pool (token1 per token0), refreshed whenever its state changes
+    spot_prices: DashMap<Address, f64>,
+    /// Decimals of the monitored pools' tokens, from their synced pool data or `decimals()`
+    token_decimals: DashMap<Address, u8>,
     /// Which dexes to monitor
     dexes: Vec<Dex>,
+    /// Factory, fee, and variant for each monitored dex (used to find newly created pools)
+    dex_configs: Vec<DexConfig>,
+    /// Block that pools have been discovered up to
+    last_synced_block: AtomicU64,
+    /// Routers of every monitored dex
+    routers: HashSet<Address>,
+    /// Aggregator routers, their swaps are inferred from the pools they touch
+    aggregator_routers: HashSet<Address>,
+    /// Tokens that should never be sandwiched
+    blacklist: HashSet<Address>,
+    /// Pools between two of these tokens are skipped
+    stable_tokens: HashSet<Address>,
+    /// Pools with less weth liquidity than this are never sandwiched
+    min_pool_liquidity: U256,
+    /// Weth (or the chain's wrapped native token), only pools pairing it are sandwiched
+    weth_address: Address,
+    /// Cached result of probing if a token transfers without tax
+    sandwichable_tokens: DashMap<Address, bool>,
+    /// How sandwiches on each pool worked out so far (persisted with the pool cache)
+    pool_stats: DashMap<Address, PoolStats>,
+    /// Pools whose simulations disagree with our math at least this often aren't sandwiched
+    /// (`None` = every pool is)
+    max_divergence_rate: Option<f64>,
+    /// Skip v3 pools whose fee settings may charge swaps something else than their tier
+    exclude_fee_switch_pools: bool,
+    /// Cached result of reading a v3 pool's fee settings
+    fee_switch_pools: DashMap<Address, bool>,
+    /// Only these pools are loaded and sandwiched (factories aren't scanned), `None` = every pool
+    /// of the monitored dexes
+    pool_whitelist: Option<HashSet<Address>>,
+    /// Rpc method token transfer probes are traced with
+    state_diff_method: StateDiffMethod,
+    /// Picks the fresh recipients of token transfer probes
+    rng: Arc<DecisionRng>,
+    /// Known and detected rebasing tokens, never sandwiched
+    rebasing_tokens: DashSet<Address>,
+    /// Blocks of factory logs queried at once, full discovery also scans factories in chunks
+    /// of this size when set (`None` = cfmms syncs every pool, catching up uses the default)
+    sync_chunk_blocks: Option<u64>,
+    /// Pools loaded at once while discovering pools
+    sync_concurrency: usize,
+    /// Caps rpc calls for pool metadata across every sync running at once (e.g. a catch up
+    /// while the initial discovery is still going)
+    sync_permits: Semaphore,
+    /// Factory of each v2 pair whose reserves were read from storage
+    pair_factories: DashMap<Address, Address>,
+    /// Factory of each pool discovered from a monitored dex (persisted with the pool cache)
+    pool_factories: DashMap<Address, Address>,
+    /// Storage slot each v2 factory's pairs pack their reserves in (detected on first read)
+    reserve_slots: DashMap<Address, u64>,
+    /// Pools moving at least this many bps in a block are hot (`None` = none are)
+    hot_pool_change_bps: Option<u64>,
+    /// Pools without logs for this many blocks are cold (`None` = none are)
+    cold_pool_after_blocks: Option<u64>,
+    /// Last blocks each pool was active and hot in (see `pool_activity`)
+    activity: DashMap<Address, ActivityRecord>,
+    /// First block that activity was tracked for (0 = not tracking yet), pools quiet since
+    /// count as last active then
+    activity_since: AtomicU64,
+}
+
+/// When a pool last emitted a `Sync`/`Swap` log, and last moved enough to be hot
+#[derive(Debug, Clone, Copy)]
+struct ActivityRecord {
+    last_active: U64,
+    last_hot: Option<U64>,
+}
+
+/// Discovered pool set that is persisted to disk so startup doesn't rescan every factory
+#[derive(Serialize, Deserialize)]
+struct PoolCache {
+    last_synced_block: u64,
+    pools: Vec<Pool>,
+    #[serde(default)]
+    pool_stats: HashMap<Address, PoolStats>,
+    #[serde(default)]
+    pool_factories: HashMap<Address, Address>,
 }
 
 impl<M: Middleware + 'static> PoolManager<M> {
     /// Gets state of all pools
-    pub async fn setup(&mut self) -> Result<()> {
-        let checkpoint_path = ".cfmms-checkpoint.json";
+    pub async fn setup(&self) -> Result<()> {
+        let latest_block = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(|_| anyhow!("Failed to get latest block"))?
+            .as_u64();
+
+        // a handful of whitelisted pools loads faster than the cache
+        if self.pool_whitelist.is_none() && self.load_from_cache(POOL_CACHE_PATH)? {
+            // only need to find pools created since last run
+            startup_info_log!("pools loaded from cache: {}", self.pools.len());
+            self.sync_new_pools(
+                &self.dex_configs,
+                self.last_synced_block() + 1,
+                latest_block,
+            )
+            .await?;
+        } else {
+            self.sync_all_pools(latest_block).await?;
+        }
+
+        self.last_synced_block
+            .store(latest_block, Ordering::Relaxed);
+        self.save_to_cache(POOL_CACHE_PATH)?;
+
+        startup_info_log!("pools synced: {}", self.pools.len());
+        startup_info_log!("token decimals read: {}", self.sync_token_decimals().await);
 
-        let checkpoint_exists = Path::new(checkpoint_path).exists();
+        Ok(())
+    }
 
-        let pools = if checkpoint_exists {
-            let (_, pools) =
-                sync_pools_from_checkpoint(checkpoint_path, 100000, self.provider.clone()).await?;
-            pools
+    /// Discover every pool of the monitored dexes from their factory's creation block, ignoring
+    /// any pool cache, and mark them synced up to `block`
+    ///
+    /// Returns each dex with how many pools it added
+    pub async fn sync_all_pools(&self, block: u64) -> Result<Vec<(DexConfig, usize)>> {
+        if self.pool_whitelist.is_some() {
+            let synced = self.sync_whitelisted_pools().await?;
+            self.last_synced_block.store(block, Ordering::Relaxed);
+            return Ok(synced);
+        }
+
+        let mut synced = vec![];
+
+        // synced per dex so every pool is checked against its own dex's fee tiers
+        for (dex, dex_config) in self.dexes.iter().zip(&self.dex_configs) {
+            let pool_count = self.pools.len();
+            match self.sync_chunk_blocks {
+                // bounded log ranges for rpcs that time out or cap results on a full scan
+                Some(_) => {
+                    self.sync_new_pools(
+                        std::slice::from_ref(dex_config),
+                        dex_config.creation_block,
+                        block,
+                    )
+                    .await?
+                }
+                None => {
+                    let pools = sync_pairs(vec![dex.clone()], self.provider.clone(), None).await?;
+                    for pool in pools {
+                        if dex_config.monitors_pool(&pool) {
+                            self.add_dex_pool(pool, dex_config.factory);
+                        }
+                    }
+                }
+            }
+            synced.push((dex_config.clone(), self.pools.len() - pool_count));
+        }
+
+        self.last_synced_block.store(block, Ordering::Relaxed);
+
+        Ok(synced)
+    }
+
+    /// Load every whitelisted pool by address, matching it to the monitored dex that created it
+    ///
+    /// Returns each dex with how many pools it added
+    async fn sync_whitelisted_pools(&self) -> Result<Vec<(DexConfig, usize)>> {
+        let whitelist = self.pool_whitelist.clone().unwrap_or_default();
+        let mut synced: Vec<(DexConfig, usize)> = self
+            .dex_configs
+            .iter()
+            .map(|dex| (dex.clone(), 0))
+            .collect();
+
+        let started = Instant::now();
+        let pool_count = whitelist.len();
+        let loaded: Vec<_> = stream::iter(whitelist)
+            .map(|address| async move { (address, self.load_whitelisted_pool(address).await) })
+            .buffer_unordered(self.sync_concurrency)
+            .collect()
+            .await;
+        startup_info_log!(
+            "loaded {} whitelisted pools ({:.0} pools/sec)",
+            pool_count,
+            pools_per_sec(pool_count, started)
+        );
+
+        for (address, loaded) in loaded {
+            // a pool we can't load shouldn't stop the others from loading
+            let (index, pool) = match loaded {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    log_error!(pool = address; "Failed to load whitelisted pool: {}", e);
+                    continue;
+                }
+            };
+
+            if !self.pools.contains_key(&address) {
+                synced[index].1 += 1;
+            }
+            self.add_dex_pool(pool, synced[index].0.factory);
+        }
+
+        Ok(synced)
+    }
+
+    /// Load a whitelisted pool, returns the index of its dex in `dex_configs` with the pool
+    async fn load_whitelisted_pool(&self, address: Address) -> Result<(usize, Pool)> {
+        let _permit = self.acquire_sync_permit().await?;
+        // v2 pairs and v3 pools both expose the factory that deployed them
+        let factory = UniswapV2Pair::new(address, self.provider.clone())
+            .factory()
+            .call()
+            .await?;
+        let index = self
+            .dex_configs
+            .iter()
+            .position(|dex| dex.factory == factory)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Pool {:?} is from unmonitored factory {:?}",
+                    address,
+                    factory
+                )
+            })?;
+        let dex = &self.dex_configs[index];
+
+        let pool = match dex.kind {
+            DexVariant::UniswapV2 => {
+                let mut pool =
+                    UniswapV2Pool::new_from_address(address, self.provider.clone()).await?;
+                pool.fee = (dex.fee_bps * 10).into();
+                Pool::UniswapV2(pool)
+            }
+            DexVariant::UniswapV3 => {
+                let mut pool =
+                    UniswapV3Pool::new_from_address(address, self.provider.clone()).await?;
+                pool.fee = UniswapV3PoolContract::new(address, self.provider.clone())
+                    .fee()
+                    .call()
+                    .await?;
+                Pool::UniswapV3(pool)
+            }
+        };
+
+        if !dex.monitors_pool(&pool) {
+            return Err(anyhow!(
+                "Pool {:?} is on a fee tier that isn't monitored",
+                address
+            ));
+        }
+
+        Ok((index, pool))
+    }
+
+    /// Number of pools currently monitored
+    pub fn pool_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// True if `address` is one of our monitored pools
+    pub fn is_pool(&self, address: Address) -> bool {
+        self.pools.contains_key(&address)
+    }
+
+    /// Routers of every monitored dex
+    pub fn get_routers(&self) -> &HashSet<Address> {
+        &self.routers
+    }
+
+    /// Cheap check (no rpc calls) for if a tx could swap on one of our pools
+    /// note: filtering is disabled when no routers are configured
+    pub fn is_dex_tx(&self, tx: &Transaction) -> bool {
+        if self.routers.is_empty() {
+            return true;
+        }
+
+        match tx.to {
+            Some(to) => self.routers.contains(&to) || self.is_aggregator(to) || self.is_pool(to),
+            None => false,
+        }
+    }
+
+    /// True if `address` is an aggregator router (see `with_aggregator_routers`)
+    pub fn is_aggregator(&self, address: Address) -> bool {
+        self.aggregator_routers.contains(&address)
+    }
+
+    /// How sandwiches on `address` worked out so far (all zero for pools never sized)
+    pub fn pool_stats(&self, address: Address) -> PoolStats {
+        self.pool_stats
+            .get(&address)
+            .map(|stats| *stats)
+            .unwrap_or_default()
+    }
+
+    /// Count a profitable sandwich sized on `pool`
+    pub fn record_pool_opportunity(&self, pool: Address) {
+        self.pool_stats
+            .entry(pool)
+            .or_default()
+            .record_opportunity();
+    }
+
+    /// Count a bundle on `pool` that landed
+    pub fn record_pool_landed(&self, pool: Address, net_profit: I256) {
+        self.pool_stats
+            .entry(pool)
+            .or_default()
+            .record_landed(net_profit);
+    }
+
+    /// Count a sandwich on `pool` simulated in revm, `diverged` if its revenue disagreed with our
+    /// math
+    ///
+    /// Returns true if this simulation pushed the pool over `max_divergence_rate`
+    pub fn record_pool_simulation(&self, pool: Address, diverged: bool) -> bool {
+        let was_divergent = self.is_divergent(pool);
+        self.pool_stats
+            .entry(pool)
+            .or_default()
+            .record_simulation(diverged);
+        !was_divergent && self.is_divergent(pool)
+    }
+
+    /// True if `pool` simulated often enough and disagreed with our math at least
+    /// `max_divergence_rate` of the time, the stats are persisted so it stays disabled across
+    /// restarts until they're reset
+    pub fn is_divergent(&self, pool: Address) -> bool {
+        let max_divergence_rate = match self.max_divergence_rate {
+            Some(max_divergence_rate) => max_divergence_rate,
+            None => return false,
+        };
+        let stats = self.pool_stats(pool);
+        stats.simulated >= MIN_POOL_DIVERGENCE_SAMPLES
+            && stats
+                .divergence_rate()
+                .map_or(false, |rate| rate >= max_divergence_rate)
+    }
+
+    /// Take back a landed bundle on `pool` whose block was reorged out
+    pub fn record_pool_reverted(&self, pool: Address, net_profit: I256) {
+        self.pool_stats
+            .entry(pool)
+            .or_default()
+            .record_reverted(net_profit);
+    }
+
+    /// Write discovered pools to the default pool cache
+    pub fn save_cache(&self) -> Result<()> {
+        self.save_to_cache(POOL_CACHE_PATH)
+    }
+
+    /// Load previously discovered pools, returns false if no cache exists at `path`
+    pub fn load_from_cache(&self, path: &str) -> Result<bool> {
+        if !Path::new(path).exists() {
+            return Ok(false);
+        }
+
+        let cache: PoolCache = serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| {
+            SandoError::DecodeError(format!("Failed to parse pool cache {}: {:?}", path, e))
+        })?;
+
+        for pool in cache.pools {
+            self.add_pool(pool);
+        }
+        self.last_synced_block
+            .store(cache.last_synced_block, Ordering::Relaxed);
+        self.pool_stats.extend(cache.pool_stats);
+        self.pool_factories.extend(cache.pool_factories);
+
+        Ok(true)
+    }
+
+    /// Persist discovered pools along with the block they were synced up to
+    pub fn save_to_cache(&self, path: &str) -> Result<()> {
+        let cache = PoolCache {
+            last_synced_block: self.last_synced_block(),
+            pools: self.pools.iter().map(|p| *p.value()).collect(),
+            pool_stats: self
+                .pool_stats
+                .iter()
+                .map(|stats| (*stats.key(), *stats.value()))
+                .collect(),
+            pool_factories: self
+                .pool_factories
+                .iter()
+                .map(|factory| (*factory.key(), *factory.value()))
+                .collect(),
+        };
+
+        fs::write(path, serde_json::to_string(&cache)?)?;
+
+        Ok(())
+    }
+
+    /// Block that pools have been discovered up to
+    pub fn last_synced_block(&self) -> u64 {
+        self.last_synced_block.load(Ordering::Relaxed)
+    }
+
+    /// Add pools created by monitored factories since the last synced block (up to `block`)
+    /// Returns how many new pools were found
+    pub async fn sync_to_block(&self, block: u64) -> Result<usize> {
+        if block <= self.last_synced_block() {
+            return Ok(0);
+        }
+
+        // whitelisted pools are all loaded up front, new pools are never picked up
+        if self.pool_whitelist.is_some() {
+            self.last_synced_block.store(block, Ordering::Relaxed);
+            return Ok(0);
+        }
+
+        let pool_count = self.pools.len();
+        self.sync_new_pools(&self.dex_configs, self.last_synced_block() + 1, block)
+            .await?;
+        self.last_synced_block.store(block, Ordering::Relaxed);
+
+        let new_pools = self.pools.len() - pool_count;
+        if new_pools > 0 {
+            self.sync_token_decimals().await;
+        }
+        Ok(new_pools)
+    }
+
+    /// Start monitoring a pool
+    pub fn add_pool(&self, pool: Pool) {
+        let address = pool.address();
+        self.update_spot_price(&pool);
+        self.record_token_decimals(&pool);
+        if self.pools.insert(address, pool).is_some() {
+            // already indexed
+            return;
+        }
+
+        for token in get_pool_tokens(&pool) {
+            self.token_pools.entry(token).or_default().push(address);
+        }
+    }
+
+    /// Cache the decimals of a pool's tokens if it was synced with them (cfmms leaves them 0
+    /// otherwise)
+    fn record_token_decimals(&self, pool: &Pool) {
+        let decimals = match pool {
+            Pool::UniswapV2(p) => [
+                (p.token_a, p.token_a_decimals),
+                (p.token_b, p.token_b_decimals),
+            ],
+            Pool::UniswapV3(p) => [
+                (p.token_a, p.token_a_decimals),
+                (p.token_b, p.token_b_decimals),
+            ],
+        };
+        for (token, decimals) in decimals {
+            if decimals > 0 {
+                self.token_decimals.entry(token).or_insert(decimals);
+            }
+        }
+    }
+
+    /// Read `decimals()` of every pool token whose decimals didn't come with its pool's data,
+    /// tokens that can't be read are left out (their amounts are shown in raw units)
+    ///
+    /// Returns how many tokens were read
+    async fn sync_token_decimals(&self) -> usize {
+        let missing: Vec<Address> = self
+            .token_pools
+            .iter()
+            .map(|entry| *entry.key())
+            .filter(|token| !self.token_decimals.contains_key(token))
+            .collect();
+
+        let read: Vec<_> = stream::iter(missing)
+            .map(|token| async move {
+                let _permit = self.acquire_sync_permit().await.ok()?;
+                let decimals = Erc20::new(token, self.provider.clone())
+                    .decimals()
+                    .call()
+                    .await
+                    .ok()?;
+                Some((token, decimals))
+            })
+            .buffer_unordered(self.sync_concurrency)
+            .collect()
+            .await;
+
+        let mut count = 0;
+        for (token, decimals) in read.into_iter().flatten() {
+            self.token_decimals.insert(token, decimals);
+            count += 1;
+        }
+        count
+    }
+
+    /// Decimals of `token`, `None` if it isn't a monitored pool's token or they couldn't be read
+    pub fn token_decimals(&self, token: Address) -> Option<u8> {
+        self.token_decimals.get(&token).map(|decimals| *decimals)
+    }
+
+    /// `amount` of `token` for logs: in whole tokens if its decimals are known, raw otherwise
+    pub fn display_amount(&self, token: Address, amount: U256) -> String {
+        match self.token_decimals(token) {
+            Some(decimals) => format_token_amount(amount, decimals),
+            None => format!("{} (raw)", amount),
+        }
+    }
+
+    /// Name of `token` for logs
+    pub fn token_label(&self, token: Address) -> String {
+        if token == self.weth_address {
+            return "wETH".to_string();
+        }
+        format!("{:?}", token)
+    }
+
+    /// Start monitoring a pool discovered from the monitored dex `factory` deployed
+    fn add_dex_pool(&self, pool: Pool, factory: Address) {
+        self.pool_factories.insert(pool.address(), factory);
+        self.add_pool(pool);
+    }
+
+    /// Size of the monitored set: pools per dex, how active they are as of the last synced
+    /// block and an estimate of the memory the pools and their per pool indexes hold
+    /// note: walks every pool, meant for a gauge refresh once a block rather than hot paths
+    pub fn metrics_snapshot(&self) -> PoolManagerMetrics {
+        let mut pools_by_dex: Vec<(DexConfig, usize)> = self
+            .dex_configs
+            .iter()
+            .map(|dex| (dex.clone(), 0))
+            .collect();
+        let mut unattributed_pools = 0;
+        for pool in self.pools.iter() {
+            let factory = self.pool_factories.get(pool.key()).map(|factory| *factory);
+            match factory.and_then(|factory| {
+                pools_by_dex
+                    .iter()
+                    .position(|(dex, _)| dex.factory == factory)
+            }) {
+                Some(index) => pools_by_dex[index].1 += 1,
+                None => unattributed_pools += 1,
+            }
+        }
+
+        let pools_by_activity = if self.activity_since.load(Ordering::Relaxed) == 0 {
+            None
         } else {
-            sync_pairs(
-                self.dexes.clone(),
-                self.provider.clone(),
-                Some(checkpoint_path),
-            )
-            .await?
+            let block = U64::from(self.last_synced_block());
+            let mut counts = [
+                (PoolActivity::Hot, 0),
+                (PoolActivity::Active, 0),
+                (PoolActivity::Cold, 0),
+            ];
+            for pool in self.pools.iter() {
+                let activity = self.pool_activity(*pool.key(), block);
+                if let Some((_, count)) = counts.iter_mut().find(|(a, _)| *a == activity) {
+                    *count += 1;
+                }
+            }
+            Some(counts)
         };
 
-        for pool in pools {
-            self.pools.insert(pool.address(), pool);
+        // payload of every map keyed by pool (and token), allocator overhead isn't counted
+        let entry = |value: usize| mem::size_of::<Address>() + value;
+        let token_pools: usize = self
+            .token_pools
+            .iter()
+            .map(|pools| {
+                entry(mem::size_of::<Vec<Address>>()) + pools.capacity() * mem::size_of::<Address>()
+            })
+            .sum();
+        let estimated_bytes = self.pools.len() * entry(mem::size_of::<Pool>())
+            + token_pools
+            + self.pool_stats.len() * entry(mem::size_of::<PoolStats>())
+            + self.pool_factories.len() * entry(mem::size_of::<Address>())
+            + self.activity.len() * entry(mem::size_of::<ActivityRecord>());
+
+        PoolManagerMetrics {
+            pools_by_dex,
+            unattributed_pools,
+            pools_by_activity,
+            estimated_bytes,
         }
+    }
 
-        startup_info_log!("pools synced: {}", self.pools.len());
+    /// Look up a monitored pool by address
+    /// note: returns a copy, pools live in a concurrent map that can't hand out references
+    pub fn get_pool(&self, address: Address) -> Option<Pool> {
+        self.pools.get(&address).map(|p| *p.value())
+    }
+
+    /// Re-fetch a monitored pool's reserves (v2) or price, tick and liquidity (v3) from chain,
+    /// for when our copy drifted (missed a log, rebasing token) and a full resync is overkill
+    /// note: v3 ticks aren't kept, they are fetched around the current tick on every evaluation
+    pub async fn resync_pool(&self, address: Address) -> Result<()> {
+        let pool = self
+            .get_pool(address)
+            .ok_or_else(|| anyhow!("Pool {:?} isn't monitored", address))?;
+
+        let pool = match pool {
+            Pool::UniswapV2(mut p) => {
+                let pair = UniswapV2Pair::new(address, self.provider.clone());
+                let (reserve_0, reserve_1, _) = pair.get_reserves().call().await?;
+                p.reserve_0 = reserve_0;
+                p.reserve_1 = reserve_1;
+                Pool::UniswapV2(p)
+            }
+            Pool::UniswapV3(mut p) => {
+                let contract = UniswapV3PoolContract::new(address, self.provider.clone());
+                let (sqrt_price, tick, ..) = contract.slot_0().call().await?;
+                p.sqrt_price = sqrt_price;
+                p.tick = tick;
+                p.liquidity = contract.liquidity().call().await?;
+                Pool::UniswapV3(p)
+            }
+        };
+
+        self.update_spot_price(&pool);
+        self.pools.insert(address, pool);
+        Ok(())
+    }
+
+    /// Spot price of a monitored pool (token1 per token0 in raw units) as of its latest state
+    ///
+    /// Returns `None` if the pool isn't monitored or is empty
+    pub fn pool_price(&self, address: Address) -> Option<f64> {
+        self.spot_prices.get(&address).map(|price| *price)
+    }
+
+    /// Weth per raw unit of `token` from cached spot prices, through the most liquid weth pool
+    /// trading it or, without one, through the pool whose other token has a weth pool and
+    /// that is deepest in weth terms (e.g. token -> usdc -> weth)
+    ///
+    /// Returns `None` if no such route is monitored
+    pub fn weth_price(&self, token: Address) -> Option<f64> {
+        if token == self.weth_address {
+            return Some(1.0);
+        }
+        if let Some(price) = self.direct_weth_price(token) {
+            return Some(price);
+        }
+
+        self.get_pools_for_token(token)
+            .into_iter()
+            .filter_map(|pool| {
+                let [token_a, token_b] = get_pool_tokens(&pool);
+                let via = if token_a == token { token_b } else { token_a };
+                // a direct weth pool that didn't price is empty, not a route
+                if via == self.weth_address {
+                    return None;
+                }
+                let via_price = self.direct_weth_price(via)?;
+                let price = self.price_of(&pool, token)? * via_price;
+                let depth = u256_to_f64(pool.weth_liquidity(via)) * via_price;
+                Some((price, depth))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(price, _)| price)
+    }
+
+    /// Weth per raw unit of `token` on the most liquid monitored pool pairing it with weth
+    fn direct_weth_price(&self, token: Address) -> Option<f64> {
+        self.get_pools_for_token(token)
+            .into_iter()
+            .filter(|pool| get_pool_tokens(pool).contains(&self.weth_address))
+            .filter_map(|pool| {
+                let price = self.price_of(&pool, token)?;
+                Some((price, pool.weth_liquidity(self.weth_address)))
+            })
+            .max_by_key(|(_, liquidity)| *liquidity)
+            .map(|(price, _)| price)
+    }
+
+    /// Cached price of `token` in the pool's other token
+    fn price_of(&self, pool: &Pool, token: Address) -> Option<f64> {
+        let price = self.pool_price(pool.address())?;
+        if get_pool_tokens(pool)[0] == token {
+            Some(price)
+        } else {
+            Some(1.0 / price)
+        }
+    }
+
+    /// Recompute a pool's cached spot price from its state
+    fn update_spot_price(&self, pool: &Pool) {
+        match pricing::spot_price(pool) {
+            Some(price) => self.spot_prices.insert(pool.address(), price),
+            None => self
+                .spot_prices
+                .remove(&pool.address())
+                .map(|(_, price)| price),
+        };
+    }
+
+    /// Every monitored pool that trades `token`
+    pub fn get_pools_for_token(&self, token: Address) -> Vec<Pool> {
+        self.token_pools
+            .get(&token)
+            .map(|addresses| {
+                addresses
+                    .iter()
+                    .filter_map(|address| self.get_pool(*address))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Synced v3 pool trading `token_a` against `token_b` (in either order) on the `fee` tier
+    pub fn get_v3_pool(&self, token_a: Address, token_b: Address, fee: u32) -> Option<Pool> {
+        self.get_pools_for_token(token_a)
+            .into_iter()
+            .find(|pool| match pool {
+                Pool::UniswapV3(p) => p.fee == fee && get_pool_tokens(pool).contains(&token_b),
+                Pool::UniswapV2(_) => false,
+            })
+    }
+
+    /// Find and add all pools created by the factories of `dex_configs` between `from_block` and
+    /// `to_block`, `sync_chunk_blocks` blocks at a time
+    async fn sync_new_pools(
+        &self,
+        dex_configs: &[DexConfig],
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<()> {
+        let step = self
+            .sync_chunk_blocks
+            .unwrap_or(POOL_SYNC_CHUNK_BLOCKS)
+            .max(1);
+
+        // one filter covers every factory, logs are matched back to their dex by address
+        let dexes: HashMap<Address, DexConfig> = dex_configs
+            .iter()
+            .map(|dex| (dex.factory, dex.clone()))
+            .collect();
+        let factories: Vec<Address> = dexes.keys().copied().collect();
+        let event_signatures = vec![
+            PairCreatedFilter::signature(),
+            PoolCreatedFilter::signature(),
+        ];
+
+        let started = Instant::now();
+        let mut pools_loaded = 0;
+        for chunk_start in (from_block..=to_block).step_by(step as usize) {
+            let chunk_end = (chunk_start + step - 1).min(to_block);
+
+            let logs = self
+                .get_pool_creation_logs(
+                    &Filter::new()
+                        .address(factories.clone())
+                        .topic0(event_signatures.clone())
+                        .from_block(BlockNumber::Number(U64::from(chunk_start)))
+                        .to_block(BlockNumber::Number(U64::from(chunk_end))),
+                )
+                .await?;
+
+            // the chunk's pools are loaded concurrently, bounded by `sync_permits`
+            let loaded: Vec<_> = stream::iter(
+                logs.into_iter()
+                    .filter_map(|log| dexes.get(&log.address).map(|dex| (dex, log))),
+            )
+            .map(|(dex, log)| async move { (dex, self.pool_from_log(dex, log).await) })
+            .buffer_unordered(self.sync_concurrency)
+            .collect()
+            .await;
+            pools_loaded += loaded.len();
+
+            for (dex, loaded) in loaded {
+                // a pool we can't load shouldn't stop discovery of the others
+                match loaded {
+                    Ok(pool) if dex.monitors_pool(&pool) => self.add_dex_pool(pool, dex.factory),
+                    Ok(_) => {}
+                    Err(e) => log_error!(
+                        factory = dex.factory;
+                        "Failed to load pool created by {:?}: {}", dex.factory, e
+                    ),
+                }
+            }
+
+            // catching up on a new block is a single chunk, only long scans report progress
+            if to_block - from_block >= step {
+                startup_info_log!(
+                    "synced up to block {} ({} pools, {:.0} pools/sec)",
+                    chunk_end,
+                    self.pools.len(),
+                    pools_per_sec(pools_loaded, started)
+                );
+            }
+        }
 
         Ok(())
     }
 
+    /// Wait for one of the `sync_concurrency` slots for loading a pool
+    async fn acquire_sync_permit(&self) -> Result<SemaphorePermit<'_>> {
+        self.sync_permits
+            .acquire()
+            .await
+            .map_err(|_| anyhow!("Pool sync permits were closed"))
+    }
+
+    /// Get the pool creation logs matching `filter`, retrying a failed query with backoff
+    /// (`POOL_SYNC_CHUNK_ATTEMPTS` attempts) so one flaky rpc call doesn't restart discovery
+    async fn get_pool_creation_logs(&self, filter: &Filter) -> Result<Vec<Log>> {
+        let mut backoff = Duration::from_millis(POOL_SYNC_RETRY_BACKOFF_MS);
+        let mut attempt = 1;
+        loop {
+            match self.provider.get_logs(filter).await {
+                Ok(logs) => return Ok(logs),
+                Err(e) if attempt < POOL_SYNC_CHUNK_ATTEMPTS => {
+                    log_error!(
+                        "Failed to get pool creation logs (attempt {}/{}), retrying: {:?}",
+                        attempt,
+                        POOL_SYNC_CHUNK_ATTEMPTS,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(anyhow!("Failed to get pool creation logs {:?}", e)),
+            }
+        }
+    }
+
+    /// Apply a block's v2 `Sync` and v3 `Swap` logs to the monitored pools they came from
+    ///
+    /// Returns `(pool, state before the block, state after)` for every pool the block moved
+    pub async fn apply_block_updates(
+        &self,
+        block: U64,
+    ) -> Result<Vec<(Address, PoolState, PoolState)>> {
+        let logs = self
+            .provider
+            .get_logs(
+                &Filter::new()
+                    .topic0(vec![SyncFilter::signature(), SwapFilter::signature()])
+                    .from_block(block)
+                    .to_block(block),
+            )
+            .await
+            .map_err(|e| anyhow!("Failed to get pool update logs {:?}", e))?;
+
+        // a pool can emit several logs in a block, keep its state from before the first one
+        let mut states_before: HashMap<Address, PoolState> = HashMap::new();
+        for log in logs {
+            let address = log.address;
+            let mut pool = match self.pools.get_mut(&address) {
+                Some(pool) => pool,
+                None => continue,
+            };
+            let previous = PoolState::from(pool.value());
+
+            match pool.value_mut() {
+                Pool::UniswapV2(p) => match parse_log::<SyncFilter>(log) {
+                    Ok(event) => {
+                        p.reserve_0 = event.reserve_0;
+                        p.reserve_1 = event.reserve_1;
+                    }
+                    Err(_) => continue,
+                },
+                Pool::UniswapV3(p) => match parse_log::<SwapFilter>(log) {
+                    Ok(event) => {
+                        p.sqrt_price = event.sqrt_price_x96;
+                        p.liquidity = event.liquidity;
+                        p.tick = event.tick;
+                    }
+                    Err(_) => continue,
+                },
+            }
+
+            states_before.entry(address).or_insert(previous);
+        }
+
+        let updates: Vec<_> = states_before
+            .into_iter()
+            .filter_map(|(address, before)| {
+                let pool = self.get_pool(address)?;
+                self.update_spot_price(&pool);
+                let after = PoolState::from(&pool);
+                Some((address, before, after))
+            })
+            .collect();
+        self.record_activity(block, &updates);
+
+        Ok(updates)
+    }
+
+    /// True if pools can turn hot or cold, their activity is then tracked from every block's
+    /// pool logs
+    pub fn tracks_activity(&self) -> bool {
+        self.hot_pool_change_bps.is_some() || self.cold_pool_after_blocks.is_some()
+    }
+
+    /// Record which pools a block's logs moved (and which moved enough to be hot)
+    fn record_activity(&self, block: U64, updates: &[(Address, PoolState, PoolState)]) {
+        // only the first block starts tracking
+        let _ = self.activity_since.compare_exchange(
+            0,
+            block.as_u64(),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+
+        for (address, before, after) in updates {
+            let hot = match (self.hot_pool_change_bps, after.change_bps(before)) {
+                (Some(hot_pool_change_bps), Some(change_bps)) => change_bps >= hot_pool_change_bps,
+                _ => false,
+            };
+            let mut record = self.activity.entry(*address).or_insert(ActivityRecord {
+                last_active: block,
+                last_hot: None,
+            });
+            record.last_active = block;
+            if hot {
+                record.last_hot = Some(block);
+            }
+        }
+    }
+
+    /// How active `pool` has been as of `block` (always `Active` until activity is tracked)
+    pub fn pool_activity(&self, pool: Address, block: U64) -> PoolActivity {
+        let since = self.activity_since.load(Ordering::Relaxed);
+        if since == 0 {
+            return PoolActivity::Active;
+        }
+        match self.activity.get(&pool) {
+            Some(record) => PoolActivity::classify(
+                block,
+                record.last_active,
+                record.last_hot,
+                self.cold_pool_after_blocks,
+            ),
+            None => {
+                PoolActivity::classify(block, U64::from(since), None, self.cold_pool_after_blocks)
+            }
+        }
+    }
+
+    /// Pools that are hot as of `block` (none unless `hot_pool_change_bps` is set)
+    pub fn hot_pools(&self, block: U64) -> Vec<Address> {
+        let candidates: Vec<Address> = self
+            .activity
+            .iter()
+            .filter(|record| record.last_hot.is_some())
+            .map(|record| *record.key())
+            .collect();
+        candidates
+            .into_iter()
+            .filter(|pool| self.pool_activity(*pool, block) == PoolActivity::Hot)
+            .collect()
+    }
+
+    /// Re-read the state of `pools` at `block` with multicalls (`getReserves` for v2, `slot0`
+    /// and `liquidity` for v3) and overwrite cached state that drifted from it, e.g. after a
+    /// missed log
+    ///
+    /// Returns the pools that had drifted, with their cached and refreshed state
+    pub async fn refresh_pools(
+        &self,
+        pools: &[Address],
+        block: U64,
+    ) -> Result<Vec<(Address, PoolState, PoolState)>> {
+        let mut drifted = vec![];
+        for addresses in pools.chunks(MULTICALL_REFRESH_POOLS) {
+            let cached: Vec<Pool> = addresses
+                .iter()
+                .filter_map(|address| self.get_pool(*address))
+                .collect();
+
+            let mut multicall = Multicall::new(self.provider.clone(), Some(*MULTICALL_ADDRESS))
+                .await
+                .map_err(|e| anyhow!("Failed to set up multicall: {}", e))?
+                .block(block);
+            // a pool that can't be read is skipped rather than failing the others
+            for pool in &cached {
+                match pool {
+                    Pool::UniswapV2(p) => {
+                        let pair = UniswapV2Pair::new(p.address, self.provider.clone());
+                        multicall.add_call(pair.get_reserves(), true);
+                    }
+                    Pool::UniswapV3(p) => {
+                        let contract = UniswapV3PoolContract::new(p.address, self.provider.clone());
+                        multicall.add_call(contract.slot_0(), true);
+                        multicall.add_call(contract.liquidity(), true);
+                    }
+                }
+            }
+            let mut results = multicall
+                .call_raw()
+                .await
+                .map_err(|e| anyhow!("Failed to refresh pools: {}", e))?
+                .into_iter();
+
+            for pool in cached {
+                let address = pool.address();
+                let before = PoolState::from(&pool);
+                let refreshed = match refreshed_pool(pool, &mut results) {
+                    Some(refreshed) => refreshed,
+                    None => continue,
+                };
+                let after = PoolState::from(&refreshed);
+                if after == before {
+                    continue;
+                }
+
+                self.update_spot_price(&refreshed);
+                self.pools.insert(address, refreshed);
+                drifted.push((address, before, after));
+            }
+        }
+
+        Ok(drifted)
+    }
+
+    /// A block's v2 pair `Sync`, `Swap`, `Mint` and `Burn` logs (from every pair, monitored or
+    /// not)
+    pub async fn get_v2_pair_logs(&self, block: U64) -> Result<Vec<Log>> {
+        self.provider
+            .get_logs(
+                &Filter::new()
+                    .topic0(vec![
+                        SyncFilter::signature(),
+                        V2SwapFilter::signature(),
+                        MintFilter::signature(),
+                        BurnFilter::signature(),
+                    ])
+                    .from_block(block)
+                    .to_block(block),
+            )
+            .await
+            .map_err(|e| anyhow!("Failed to get pair logs {:?}", e))
+    }
+
+    /// Load the pool announced by a factory's `PairCreated`/`PoolCreated` log
+    async fn pool_from_log(&self, dex: &DexConfig, log: Log) -> Result<Pool> {
+        let _permit = self.acquire_sync_permit().await?;
+        let pool = match dex.kind {
+            DexVariant::UniswapV2 => {
+                let event: PairCreatedFilter = parse_log(log)?;
+                let mut pool =
+                    UniswapV2Pool::new_from_address(event.pair, self.provider.clone()).await?;
+                pool.fee = (dex.fee_bps * 10).into();
+                Pool::UniswapV2(pool)
+            }
+            DexVariant::UniswapV3 => {
+                let event: PoolCreatedFilter = parse_log(log)?;
+                let mut pool =
+                    UniswapV3Pool::new_from_address(event.pool, self.provider.clone()).await?;
+                // each fee tier of a pair is its own pool, the factory tells us which this is
+                pool.fee = event.fee;
+                Pool::UniswapV3(pool)
+            }
+        };
+
+        Ok(pool)
+    }
+
     /// Return a tx's touched pools
     // enhancement: record stable coin pairs to sandwich as well here
     pub async fn get_touched_sandwichable_pools(
         &self,
-        victim_tx: &Transaction,
-        latest_block: BlockNumber,
-        provider: Arc<M>,
+        victim_info: &VictimInfo,
     ) -> Result<Vec<Pool>> {
         // get victim tx state diffs
-        let state_diffs = provider
-            .trace_call(victim_tx, vec![TraceType::StateDiff], Some(latest_block))
-            .await?
-            .state_diff
-            .ok_or(anyhow!("not sandwichable, no state diffs produced"))?
-            .0;
+        let state_diffs = victim_info
+            .get_state_diffs()
+            .ok_or(anyhow!("victim state diffs have not been filled"))?;
+
+        // v3 pools the victim's decoded path crosses, matched by (pair, fee)
+        // note: eth placeholders in the path were already mapped to weth by `decode_swap`
+        // note: only the first swap of a batch is decoded, `None` falls back to every touched pool
+        let v3_hop_pools: Option<HashSet<Address>> = victim_info
+            .decode_swap()
+            .map(|swap| swap.v3_hops())
+            .filter(|hops| !hops.is_empty())
+            .map(|hops| {
+                hops.into_iter()
+                    .filter_map(|(token_in, fee, token_out)| {
+                        self.get_v3_pool(token_in, token_out, fee)
+                    })
+                    .map(|pool| pool.address())
+                    .collect()
+            });
 
         // capture all addresses that have a state change and are also a `WETH` pool
+        // note: pools are keyed by address, so only the fee tier the victim swapped on is matched
+        // (not every v3 pool for the pair)
         let touched_pools: Vec<Pool> = state_diffs
             .keys()
-            .filter_map(|e| self.pools.get(e).map(|p| (*p.value()).clone()))
-            .filter(|e| match e {
-                Pool::UniswapV2(p) => vec![p.token_a, p.token_b].contains(&WETH_ADDRESS),
-                Pool::UniswapV3(p) => vec![p.token_a, p.token_b].contains(&WETH_ADDRESS),
+            .filter(|address| self.is_whitelisted(**address))
+            .filter_map(|e| self.get_pool(*e))
+            .filter(|e| match (e, &v3_hop_pools) {
+                // pools touched by other calls of the tx (eg. flashloans) aren't the victim's swap
+                (Pool::UniswapV3(p), Some(hop_pools)) => hop_pools.contains(&p.address),
+                _ => true,
+            })
+            .filter(|e| get_pool_tokens(e).contains(&self.weth_address))
+            .filter(|e| match self.pool_rejection(e, victim_info) {
+                Some(reason) => {
+                    metrics::record_rejection(victim_info.tx.hash, reason);
+                    false
+                }
+                None => true,
             })
             .collect();
 
@@ -86,30 +1180,41 @@ impl<M: Middleware + 'static> PoolManager<M> {
             return Ok(vec![]);
         }
 
-        // find trade direction
-        let weth_state_diff = &state_diffs
-            .get(&WETH_ADDRESS)
-            .ok_or(anyhow!("Missing WETH state diffs"))?
-            .storage;
-
         let mut sandwichable_pools = vec![];
 
         for pool in touched_pools {
-            // find pool mapping location on WETH contract
-            let storage_key = H256::from(ethers::utils::keccak256(abi::encode(&[
-                abi::Token::Address(pool.address()),
-                abi::Token::Uint(U256::from(3)), // WETH balanceOf mapping is at index 3
-            ])));
+            // skip tokens that tax or block transfers, they break sandwich math
+            let [token_a, token_b] = get_pool_tokens(&pool);
+            let other_token = if token_a == self.weth_address {
+                token_b
+            } else {
+                token_a
+            };
+            if !self.is_token_sandwichable(other_token).await {
+                metrics::record_rejection(victim_info.tx.hash, RejectReason::TransferTax);
+                continue;
+            }
 
-            // in reality we also want to check stable coin pools
-            if let Some(Diff::Changed(c)) = weth_state_diff.get(&storage_key) {
-                let from = U256::from(c.from.to_fixed_bytes());
-                let to = U256::from(c.to.to_fixed_bytes());
+            // our math prices swaps at the pool's tier, fee settings that charge more lose money
+            if self.exclude_fee_switch_pools && self.has_fee_switch(&pool).await {
+                metrics::record_rejection(victim_info.tx.hash, RejectReason::FeeSwitch);
+                continue;
+            }
 
+            // thin pools give negligible profit for a lot of price impact risk
+            if self.get_weth_liquidity(&pool, victim_info) < self.min_pool_liquidity {
+                metrics::record_rejection(victim_info.tx.hash, RejectReason::LowLiquidity);
+                continue;
+            }
+
+            // in reality we also want to check stable coin pools
+            if let Some((from, to)) = victim_info.get_weth_balance_change(pool.address()) {
                 // right now bot can only sandwich `weth->token` trades
                 // enhancement: add support for `token->weth` trades (using longtail or flashswaps sandos)
                 if to > from {
                     sandwichable_pools.push(pool);
+                } else {
+                    metrics::record_rejection(victim_info.tx.hash, RejectReason::WrongDirection);
                 }
             }
         }
@@ -117,69 +1222,566 @@ impl<M: Middleware + 'static> PoolManager<M> {
         Ok(sandwichable_pools)
     }
 
-    pub fn new(provider: Arc<M>) -> Self {
-        let dexes_data = [
-            (
-                // Uniswap v2
-                "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f",
-                DexVariant::UniswapV2,
-                10000835u64,
-            ),
-            (
-                // Sushiswap
-                "0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac",
-                DexVariant::UniswapV2,
-                10794229u64,
-            ),
-            (
-                // Crypto.com swap
-                "0x9DEB29c9a4c7A88a3C0257393b7f3335338D9A9D",
-                DexVariant::UniswapV2,
-                10828414u64,
-            ),
-            (
-                // Convergence swap
-                "0x4eef5746ED22A2fD368629C1852365bf5dcb79f1",
-                DexVariant::UniswapV2,
-                12385067u64,
-            ),
-            (
-                // Pancakeswap
-                "0x1097053Fd2ea711dad45caCcc45EfF7548fCB362",
-                DexVariant::UniswapV2,
-                15614590u64,
-            ),
-            (
-                // ShibaSwap
-                "0x115934131916C8b277DD010Ee02de363c09d037c",
-                DexVariant::UniswapV2,
-                12771526u64,
-            ),
-            (
-                // Saitaswap
-                "0x35113a300ca0D7621374890ABFEAC30E88f214b1",
-                DexVariant::UniswapV2,
-                15210780u64,
-            ),
-            (
-                // Uniswap v3
-                "0x1F98431c8aD98523631AE4a59f267346ea31F984",
-                DexVariant::UniswapV3,
-                12369621u64,
-            ),
-        ];
+    /// Why a weth pool that the victim touches can't be sandwiched without looking up any
+    /// state, `None` if it may be
+    fn pool_rejection(&self, pool: &Pool, victim_info: &VictimInfo) -> Option<RejectReason> {
+        let tokens = get_pool_tokens(pool);
+        if tokens
+            .iter()
+            .any(|token| self.blacklist.contains(token) || self.is_rebasing(*token))
+        {
+            return Some(RejectReason::Blacklisted);
+        }
+        if self.is_stable_pair(pool) {
+            return Some(RejectReason::StablePair);
+        }
+        if self.is_divergent(pool.address()) {
+            return Some(RejectReason::DivergentPool);
+        }
 
-        let dexes = dexes_data
-            .into_iter()
-            .map(|(address, variant, number)| {
-                Dex::new(H160::from_str(address).unwrap(), variant, number, Some(300))
+        // a victim moving a deep pool less than our round trip fees leaves nothing to capture
+        match pool {
+            Pool::UniswapV3(p) => {
+                let moves_past_fee = victim_info
+                    .get_v3_ticks(p.address)
+                    .map_or(false, |(before, after)| {
+                        v3_sandwich::moves_past_round_trip_fee(before, after, p.fee)
+                    });
+                (!moves_past_fee).then_some(RejectReason::SmallPriceMove)
+            }
+            Pool::UniswapV2(_) => None,
+        }
+    }
+
+    /// True if `address` can be sandwiched under the pool whitelist (always without one)
+    pub fn is_whitelisted(&self, address: Address) -> bool {
+        self.pool_whitelist
+            .as_ref()
+            .map_or(true, |whitelist| whitelist.contains(&address))
+    }
+
+    /// True if both of the pool's tokens are configured stables
+    pub fn is_stable_pair(&self, pool: &Pool) -> bool {
+        get_pool_tokens(pool)
+            .iter()
+            .all(|token| self.stable_tokens.contains(token))
+    }
+
+    /// Value `amount` of `token` in weth using the most liquid weth pool we monitor for it
+    ///
+    /// Tokens without a weth pool are valued through an intermediate token (see `weth_price`)
+    ///
+    /// Returns `None` if we don't monitor a weth route for `token`
+    pub fn quote_in_weth(&self, token: Address, amount: U256) -> Option<U256> {
+        if let Some(value) = pricing::quote_in_weth(
+            token,
+            amount,
+            self.get_pools_for_token(token),
+            self.weth_address,
+        ) {
+            return Some(value);
+        }
+
+        let value = u256_to_f64(amount) * self.weth_price(token)?;
+        Some(U256::from(value as u128))
+    }
+
+    /// Weth liquidity of a pool right before the victim's tx
+    /// note: v2 reserves are read from the victim's state diffs, v3 uses state from our last sync
+    fn get_weth_liquidity(&self, pool: &Pool, victim_info: &VictimInfo) -> U256 {
+        match *pool {
+            Pool::UniswapV2(mut p) => {
+                if let Some(((reserve_0, reserve_1), _)) = victim_info.get_v2_reserves(p.address) {
+                    p.reserve_0 = reserve_0.as_u128();
+                    p.reserve_1 = reserve_1.as_u128();
+                }
+                Pool::UniswapV2(p).weth_liquidity(self.weth_address)
+            }
+            Pool::UniswapV3(_) => pool.weth_liquidity(self.weth_address),
+        }
+    }
+
+    /// Find a monitored v2 pool between `token_a` and `token_b` that the victim's swap touched
+    pub fn get_touched_v2_pool(
+        &self,
+        victim_info: &VictimInfo,
+        token_a: Address,
+        token_b: Address,
+    ) -> Option<UniswapV2Pool> {
+        victim_info
+            .get_state_diffs()?
+            .keys()
+            .filter_map(|address| match self.get_pool(*address) {
+                Some(Pool::UniswapV2(p)) => Some(p),
+                _ => None,
+            })
+            .find(|p| {
+                (p.token_a == token_a && p.token_b == token_b)
+                    || (p.token_a == token_b && p.token_b == token_a)
+            })
+    }
+
+    /// Rebuild the state diffs of a victim's v2 router swap from `eth_getStorageAt` reads of its
+    /// pairs' reserves on `block`, for when the provider can't trace (see `v2_swap_state_diffs`)
+    ///
+    /// Fails if the victim isn't a v2 router swap through monitored pairs we can tell apart
+    pub async fn v2_state_diffs_from_storage(
+        &self,
+        victim_info: &VictimInfo,
+        block: BlockNumber,
+    ) -> Result<BTreeMap<Address, AccountDiff>> {
+        let swap = victim_info
+            .decode_swap()
+            .ok_or_else(|| anyhow!("not a router swap we can decode"))?;
+        ensure!(swap.fees.is_empty(), "not a v2 router swap");
+
+        // pairs of the same tokens on several dexes are told apart by the router's fee
+        let router_fees: Vec<u32> = self
+            .dex_configs
+            .iter()
+            .filter(|dex| matches!(dex.kind, DexVariant::UniswapV2))
+            .filter(|dex| {
+                victim_info
+                    .tx
+                    .to
+                    .map_or(false, |router| dex.routers.contains(&router))
+            })
+            .map(|dex| dex.fee_bps * 10)
+            .collect();
+
+        let mut pairs = vec![];
+        for hop in swap.path.windows(2) {
+            let candidates: Vec<UniswapV2Pool> = self
+                .get_pools_for_token(hop[0])
+                .into_iter()
+                .filter_map(|pool| match pool {
+                    Pool::UniswapV2(p)
+                        if (p.token_a == hop[1] || p.token_b == hop[1])
+                            && (router_fees.is_empty() || router_fees.contains(&p.fee)) =>
+                    {
+                        Some(p)
+                    }
+                    _ => None,
+                })
+                .collect();
+            let pair = match candidates.as_slice() {
+                [pair] => pair.clone(),
+                [] => return Err(anyhow!("no monitored pair for {:?}/{:?}", hop[0], hop[1])),
+                _ => {
+                    return Err(anyhow!(
+                        "several monitored pairs for {:?}/{:?}",
+                        hop[0],
+                        hop[1]
+                    ))
+                }
+            };
+
+            let slot = self.get_v2_reserve_slot(pair.address, block).await?;
+            let packed = self.read_storage(pair.address, slot, block).await?;
+            pairs.push((pair, packed));
+        }
+
+        v2_swap_state_diffs(&swap, &pairs)
+            .ok_or_else(|| anyhow!("swap would revert on the pairs' current reserves"))
+    }
+
+    /// Storage slot `pair` packs its reserves in, detected once per factory by matching the
+    /// pair's storage on `block` against its `getReserves()` (uniswap v2 and most forks use slot
+    /// 8, customized forks may not)
+    async fn get_v2_reserve_slot(&self, pair: Address, block: BlockNumber) -> Result<u64> {
+        let contract = UniswapV2Pair::new(pair, self.provider.clone());
+        let factory = match self.pair_factories.get(&pair).map(|factory| *factory) {
+            Some(factory) => factory,
+            None => {
+                let factory = contract
+                    .factory()
+                    .call()
+                    .await
+                    .map_err(|e| anyhow!("Failed to get factory of {:?}: {:?}", pair, e))?;
+                self.pair_factories.insert(pair, factory);
+                factory
+            }
+        };
+        if let Some(slot) = self.reserve_slots.get(&factory) {
+            return Ok(*slot);
+        }
+
+        let (reserve_0, reserve_1, _) = contract
+            .get_reserves()
+            .block(block)
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to get reserves of {:?}: {:?}", pair, e))?;
+
+        // most forks keep uniswap's layout, so its slot is tried first
+        let candidates = std::iter::once(V2_RESERVE_SLOT)
+            .chain((0..V2_RESERVE_SLOT_CANDIDATES).filter(|slot| *slot != V2_RESERVE_SLOT));
+        for slot in candidates {
+            let value = self.read_storage(pair, slot, block).await?;
+            if !packs_v2_reserves(value, reserve_0.into(), reserve_1.into()) {
+                continue;
+            }
+
+            if slot != V2_RESERVE_SLOT {
+                log_info_cyan!(
+                    pool = pair;
+                    "pairs of factory {:?} keep their reserves in slot {}", factory, slot
+                );
+            }
+            self.reserve_slots.insert(factory, slot);
+            return Ok(slot);
+        }
+
+        // nothing is cached, another of the factory's pairs may still match (e.g. this one is empty)
+        Err(anyhow!(
+            "no storage slot of {:?} holds its reserves (factory {:?})",
+            pair,
+            factory
+        ))
+    }
+
+    /// Read storage `slot` of `address` on `block`
+    async fn read_storage(&self, address: Address, slot: u64, block: BlockNumber) -> Result<U256> {
+        let value = self
+            .provider
+            .get_storage_at(address, H256::from_low_u64_be(slot), Some(block.into()))
+            .await
+            .map_err(|e| anyhow!("Failed to read storage of {:?}: {:?}", address, e))?;
+        Ok(U256::from(value.to_fixed_bytes()))
+    }
+
+    /// True if `token` is a known or detected rebasing token (its pools' reserves drift from
+    /// their balances)
+    pub fn is_rebasing(&self, token: Address) -> bool {
+        self.rebasing_tokens.contains(&token)
+    }
+
+    /// Stop sandwiching `token`, its pools were caught syncing reserves without swaps
+    ///
+    /// Returns false if it was already excluded
+    pub fn flag_rebasing_token(&self, token: Address) -> bool {
+        self.rebasing_tokens.insert(token)
+    }
+
+    /// Check if a token transfers without tax or blocking logic (result is cached per token)
+    pub async fn is_token_sandwichable(&self, token: Address) -> bool {
+        if self.blacklist.contains(&token) || self.is_rebasing(token) {
+            return false;
+        }
+
+        if let Some(is_sandwichable) = self.sandwichable_tokens.get(&token) {
+            return *is_sandwichable;
+        }
+
+        let is_sandwichable = match self.probe_token_transfer(token).await {
+            Ok(is_sandwichable) => is_sandwichable,
+            Err(e) => {
+                log_error!("Failed to probe token {:?}: {}", token, e);
+                false
+            }
+        };
+
+        self.sandwichable_tokens.insert(token, is_sandwichable);
+        is_sandwichable
+    }
+
+    /// Check if a pool's fee settings may make swaps pay something else than its fee tier: a v3
+    /// protocol fee switched on or an onchain fee that differs from the synced one (cached per
+    /// pool, unreadable pools count as switched)
+    ///
+    /// V2 protocol fees are minted to the factory's `feeTo` as lp shares and never change swap
+    /// amounts, so v2 pools always pass
+    pub async fn has_fee_switch(&self, pool: &Pool) -> bool {
+        let pool = match pool {
+            Pool::UniswapV3(pool) => pool,
+            Pool::UniswapV2(_) => return false,
+        };
+
+        if let Some(has_fee_switch) = self.fee_switch_pools.get(&pool.address) {
+            return *has_fee_switch;
+        }
+
+        let has_fee_switch = match self.probe_fee_switch(pool).await {
+            Ok(has_fee_switch) => has_fee_switch,
+            Err(e) => {
+                log_error!(pool = pool.address; "Failed to read pool fee settings: {}", e);
+                true
+            }
+        };
+
+        self.fee_switch_pools.insert(pool.address, has_fee_switch);
+        has_fee_switch
+    }
+
+    /// Read a v3 pool's protocol fee and fee tier from chain
+    async fn probe_fee_switch(&self, pool: &UniswapV3Pool) -> Result<bool> {
+        let contract = UniswapV3PoolContract::new(pool.address, self.provider.clone());
+        let (_, _, _, _, _, fee_protocol, _) = contract.slot_0().call().await?;
+        let fee = contract.fee().call().await?;
+
+        let (token0_cut, token1_cut) = v3_sandwich::protocol_fee_denominators(fee_protocol);
+        Ok(token0_cut.is_some() || token1_cut.is_some() || fee != pool.fee)
+    }
+
+    /// Simulate a transfer out of a pool holding `token` and check that the recipient receives
+    /// the full amount (fee-on-transfer, rebasing, and honeypot tokens fail this check)
+    async fn probe_token_transfer(&self, token: Address) -> Result<bool> {
+        // any pool holding the token can act as the sender
+        let holder = self
+            .pools
+            .iter()
+            .find(|p| get_pool_tokens(p.value()).contains(&token))
+            .map(|p| *p.key())
+            .ok_or(anyhow!("no pool holds token"))?;
+
+        let erc20 = Erc20::new(token, self.provider.clone());
+        let amount = erc20.balance_of(holder).call().await? / 100;
+
+        if amount.is_zero() {
+            return Ok(false);
+        }
+
+        // fresh recipient so its balance slot goes from zero to the received amount
+        let recipient = self.rng.address();
+        let transfer_tx = erc20.transfer(recipient, amount).from(holder).tx;
+
+        let state_diffs = self
+            .state_diff_method
+            .trace_state_diffs(self.provider.as_ref(), transfer_tx, None)
+            .await
+            .map_err(|e| anyhow!("Failed to trace transfer: {}", e))?;
+
+        let received_full_amount = match state_diffs.get(&token) {
+            Some(token_diff) => token_diff.storage.values().any(|diff| match diff {
+                Diff::Born(to) => U256::from(to.to_fixed_bytes()) == amount,
+                Diff::Changed(c) => c.from.is_zero() && U256::from(c.to.to_fixed_bytes()) == amount,
+                _ => false,
+            }),
+            None => false,
+        };
+
+        Ok(received_full_amount)
+    }
+
+    /// Sync a v3 pool's price, liquidity, and initialized ticks around its current tick
+    pub async fn get_v3_pool_state(
+        &self,
+        pool: UniswapV3Pool,
+        block: BlockNumber,
+    ) -> Result<(UniswapV3Pool, TickMap)> {
+        let contract = UniswapV3PoolContract::new(pool.address, self.provider.clone());
+
+        let (sqrt_price, tick, ..) = contract.slot_0().block(block).call().await?;
+        let liquidity = contract.liquidity().block(block).call().await?;
+
+        let mut pool = pool;
+        pool.sqrt_price = sqrt_price;
+        pool.tick = tick;
+        pool.liquidity = liquidity;
+
+        // find bitmap word holding current tick (floor division as ticks can be negative)
+        let tick_spacing = pool.tick_spacing;
+        let current_word = (pool.tick.div_euclid(tick_spacing) >> 8) as i16;
+        let first_word = current_word - V3_TICK_BITMAP_WORD_RADIUS;
+        let last_word = current_word + V3_TICK_BITMAP_WORD_RADIUS;
+
+        let mut tick_map = TickMap {
+            lowest_tick: (first_word as i32 * 256) * tick_spacing,
+            highest_tick: (last_word as i32 * 256 + 255) * tick_spacing,
+            ..Default::default()
+        };
+
+        for word in first_word..=last_word {
+            let bitmap = contract.tick_bitmap(word).block(block).call().await?;
+
+            for bit in 0..256 {
+                if !bitmap.bit(bit) {
+                    continue;
+                }
+
+                let tick = (word as i32 * 256 + bit as i32) * tick_spacing;
+                let (_, liquidity_net, ..) = contract.ticks(tick).block(block).call().await?;
+                tick_map.ticks.insert(tick, liquidity_net);
+            }
+        }
+
+        Ok((pool, tick_map))
+    }
+
+    pub fn new(
+        provider: Arc<M>,
+        dex_configs: Vec<DexConfig>,
+        blacklist: HashSet<Address>,
+        stable_tokens: HashSet<Address>,
+        min_pool_liquidity: U256,
+        weth_address: Address,
+    ) -> Self {
+        let dexes = dex_configs
+            .iter()
+            .map(|dex| {
+                // cfmms expresses fees in hundredths of a bip (300 = 0.3%)
+                Dex::new(
+                    dex.factory,
+                    dex.kind,
+                    dex.creation_block,
+                    Some((dex.fee_bps * 10).into()),
+                )
             })
             .collect();
 
+        let routers = dex_configs
+            .iter()
+            .flat_map(|dex| dex.routers.iter().copied())
+            .collect();
+
         Self {
             pools: DashMap::new(),
+            token_pools: DashMap::new(),
+            spot_prices: DashMap::new(),
+            token_decimals: DashMap::from_iter([(weth_address, 18)]),
             provider,
             dexes,
+            dex_configs,
+            last_synced_block: AtomicU64::new(0),
+            routers,
+            aggregator_routers: HashSet::new(),
+            blacklist,
+            stable_tokens,
+            min_pool_liquidity,
+            weth_address,
+            sandwichable_tokens: DashMap::new(),
+            pool_stats: DashMap::new(),
+            max_divergence_rate: None,
+            exclude_fee_switch_pools: false,
+            fee_switch_pools: DashMap::new(),
+            pool_whitelist: None,
+            state_diff_method: StateDiffMethod::default(),
+            rng: Arc::new(DecisionRng::new(None)),
+            rebasing_tokens: DashSet::new(),
+            sync_chunk_blocks: None,
+            sync_concurrency: POOL_SYNC_CONCURRENCY,
+            sync_permits: Semaphore::new(POOL_SYNC_CONCURRENCY),
+            pair_factories: DashMap::new(),
+            pool_factories: DashMap::new(),
+            reserve_slots: DashMap::new(),
+            hot_pool_change_bps: None,
+            cold_pool_after_blocks: None,
+            activity: DashMap::new(),
+            activity_since: AtomicU64::new(0),
+        }
+    }
+
+    /// Only load and sandwich `pool_whitelist` instead of every pool of the monitored dexes
+    pub fn with_pool_whitelist(mut self, pool_whitelist: HashSet<Address>) -> Self {
+        self.pool_whitelist = Some(pool_whitelist);
+        self
+    }
+
+    /// Never sandwich pools of `rebasing_tokens`
+    pub fn with_rebasing_tokens(self, rebasing_tokens: HashSet<Address>) -> Self {
+        rebasing_tokens.into_iter().for_each(|token| {
+            self.rebasing_tokens.insert(token);
+        });
+        self
+    }
+
+    /// Trace token transfer probes with `state_diff_method` instead of `trace_call`
+    pub fn with_state_diff_method(mut self, state_diff_method: StateDiffMethod) -> Self {
+        self.state_diff_method = state_diff_method;
+        self
+    }
+
+    /// Draw token transfer probe recipients from `rng` (so a seeded run probes the same way)
+    pub fn with_rng(mut self, rng: Arc<DecisionRng>) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Query factory logs `sync_chunk_blocks` blocks at a time, for catching up and for full
+    /// discovery (which then scans every dex's factory instead of using cfmms)
+    pub fn with_sync_chunk_blocks(mut self, sync_chunk_blocks: u64) -> Self {
+        self.sync_chunk_blocks = Some(sync_chunk_blocks);
+        self
+    }
+
+    /// Load up to `sync_concurrency` pools' tokens and reserves at once while discovering pools
+    pub fn with_sync_concurrency(mut self, sync_concurrency: usize) -> Self {
+        self.sync_concurrency = sync_concurrency.max(1);
+        self.sync_permits = Semaphore::new(self.sync_concurrency);
+        self
+    }
+
+    /// Classify pools moving at least `hot_pool_change_bps` in a block as hot, and pools
+    /// without logs for `cold_pool_after_blocks` as cold (see `pool_activity`)
+    pub fn with_activity_thresholds(
+        mut self,
+        hot_pool_change_bps: Option<u64>,
+        cold_pool_after_blocks: Option<u64>,
+    ) -> Self {
+        self.hot_pool_change_bps = hot_pool_change_bps;
+        self.cold_pool_after_blocks = cold_pool_after_blocks;
+        self
+    }
+
+    /// Stop sandwiching pools whose simulations disagree with our math at least
+    /// `max_divergence_rate` of the time (see `is_divergent`)
+    pub fn with_max_divergence_rate(mut self, max_divergence_rate: Option<f64>) -> Self {
+        self.max_divergence_rate = max_divergence_rate;
+        self
+    }
+
+    /// Stop sandwiching v3 pools with a protocol fee switched on or an onchain fee that isn't
+    /// their tier (see `has_fee_switch`)
+    pub fn with_fee_switch_exclusion(mut self, exclude_fee_switch_pools: bool) -> Self {
+        self.exclude_fee_switch_pools = exclude_fee_switch_pools;
+        self
+    }
+
+    /// Let txs to `aggregator_routers` through `is_dex_tx`, their swaps are read from the state
+    /// diffs of the pools they touch since their calldata can't be decoded
+    pub fn with_aggregator_routers(mut self, aggregator_routers: HashSet<Address>) -> Self {
+        self.aggregator_routers = aggregator_routers;
+        self
+    }
+}
+
+/// Pools loaded per second since `started`
+/// `pool` with the state read by its refresh calls (one for v2, two for v3, taken from `results`
+/// in the order they were added)
+///
+/// Returns `None` if one of them failed
+fn refreshed_pool(
+    mut pool: Pool,
+    results: &mut impl Iterator<Item = std::result::Result<Token, Bytes>>,
+) -> Option<Pool> {
+    match &mut pool {
+        Pool::UniswapV2(p) => {
+            let (reserve_0, reserve_1, _) =
+                <(u128, u128, u32)>::from_token(results.next()?.ok()?).ok()?;
+            p.reserve_0 = reserve_0;
+            p.reserve_1 = reserve_1;
         }
+        Pool::UniswapV3(p) => {
+            // both are taken even if the first failed, the next pool's results follow them
+            let (slot_0, liquidity) = (results.next()?, results.next()?);
+            let (sqrt_price, tick, ..) =
+                <(U256, i32, u16, u16, u16, u8, bool)>::from_token(slot_0.ok()?).ok()?;
+            p.sqrt_price = sqrt_price;
+            p.tick = tick;
+            p.liquidity = u128::from_token(liquidity.ok()?).ok()?;
+        }
+    }
+    Some(pool)
+}
+
+fn pools_per_sec(pools: usize, started: Instant) -> f64 {
+    pools as f64 / started.elapsed().as_secs_f64().max(f64::EPSILON)
+}
+
+/// Returns a pool's `[token_a, token_b]`
+fn get_pool_tokens(pool: &Pool) -> [Address; 2] {
+    match pool {
+        Pool::UniswapV2(p) => [p.token_a, p.token_b],
+        Pool::UniswapV3(p) => [p.token_a, p.token_b],
     }
 }
@@ -0,0 +1,72 @@
+use ethers::{
+    contract::EthEvent,
+    types::{Address, Log, TxHash},
+};
+use std::collections::{HashMap, HashSet};
+
+use crate::abi::uniswap_v2_pair::{BurnFilter, MintFilter, SwapFilter, SyncFilter};
+
+/// Flags tokens whose balances change out from under their v2 pools (rebasing or elastic
+/// supply tokens), constant product math doesn't hold for them
+///
+/// A pair only emits `Sync` without a `Swap`, `Mint` or `Burn` in the same tx when someone calls
+/// `sync()` to catch its reserves up with its balances, a token that keeps needing that is
+/// treated as rebasing
+pub struct RebaseDetector {
+    /// Standalone syncs before a token is flagged
+    min_strikes: u32,
+    /// Standalone syncs seen per token
+    strikes: HashMap<Address, u32>,
+}
+
+impl RebaseDetector {
+    pub fn new(min_strikes: u32) -> Self {
+        Self {
+            min_strikes,
+            strikes: HashMap::new(),
+        }
+    }
+
+    /// Record a standalone sync on a pool of `token`
+    ///
+    /// Returns true once `token` reaches `min_strikes` (only reported once)
+    pub fn record_standalone_sync(&mut self, token: Address) -> bool {
+        let strikes = self.strikes.entry(token).or_default();
+        *strikes += 1;
+        *strikes == self.min_strikes
+    }
+}
+
+/// Pairs that emitted a `Sync` in a tx without swapping, minting or burning
+pub fn standalone_sync_pairs(logs: &[Log]) -> Vec<Address> {
+    let mut moved: HashSet<(Option<TxHash>, Address)> = HashSet::new();
+    let mut synced = vec![];
+    for log in logs {
+        let topic = match log.topics.first() {
+            Some(topic) => *topic,
+            None => continue,
+        };
+        let key = (log.transaction_hash, log.address);
+
+        if topic == SyncFilter::signature() {
+            synced.push(key);
+        } else if [
+            SwapFilter::signature(),
+            MintFilter::signature(),
+            BurnFilter::signature(),
+        ]
+        .contains(&topic)
+        {
+            moved.insert(key);
+        }
+    }
+
+    let mut pairs: Vec<Address> = synced
+        .into_iter()
+        .filter(|key| !moved.contains(key))
+        .map(|(_, pair)| pair)
+        .collect();
+    pairs.sort();
+    pairs.dedup();
+    pairs
+}
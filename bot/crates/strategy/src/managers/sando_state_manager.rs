@@ -2,51 +2,74 @@ use anyhow::{anyhow, Result};
 use colored::Colorize;
 use ethers::{
     providers::Middleware,
-    signers::{LocalWallet, Signer},
+    signers::Signer,
     types::{Address, BlockNumber, Filter, U256, U64},
 };
 use log::info;
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
 use crate::{
     abi::Erc20,
     constants::{ERC20_TRANSFER_EVENT_SIG, WETH_ADDRESS},
+    rng::DecisionRng,
+    signer::SearcherSigner,
     startup_info_log,
+    types::SandoRotation,
 };
 
-pub struct SandoStateManager {
-    sando_contract: Address,
-    sando_inception_block: U64,
-    searcher_signer: LocalWallet,
+/// Onchain state of a single sando contract
+struct SandoContract {
+    address: Address,
     weth_inventory: U256,
     token_dust: Vec<Address>,
 }
 
+pub struct SandoStateManager {
+    sando_contracts: Vec<SandoContract>,
+    sando_inception_block: U64,
+    searcher_signer: SearcherSigner,
+    /// Round robin index of the contract used for the next bundle
+    next_sando: AtomicUsize,
+    /// How the contract for each bundle is picked
+    rotation: SandoRotation,
+    /// Draws contracts under `SandoRotation::Random`
+    rng: Arc<DecisionRng>,
+}
+
 impl SandoStateManager {
     pub fn new(
-        sando_contract: Address,
-        searcher_signer: LocalWallet,
+        sando_contracts: Vec<Address>,
+        searcher_signer: SearcherSigner,
         sando_inception_block: U64,
     ) -> Self {
         Self {
-            sando_contract,
+            sando_contracts: sando_contracts
+                .into_iter()
+                .map(|address| SandoContract {
+                    address,
+                    weth_inventory: Default::default(),
+                    token_dust: Default::default(),
+                })
+                .collect(),
             sando_inception_block,
             searcher_signer,
-            weth_inventory: Default::default(),
-            token_dust: Default::default(),
+            next_sando: AtomicUsize::new(0),
+            rotation: SandoRotation::default(),
+            rng: Arc::new(DecisionRng::new(None)),
         }
     }
 
-    pub async fn setup<M: Middleware + 'static>(&mut self, provider: Arc<M>) -> Result<()> {
-        // find weth inventory
-        let weth = Erc20::new(*WETH_ADDRESS, provider.clone());
-        let weth_balance = weth.balance_of(self.sando_contract).call().await?;
-        startup_info_log!("weth inventory   : {}", weth_balance);
-        self.weth_inventory = weth_balance;
-
-        // find weth dust
-        let step = 10000;
+    /// Pick each bundle's contract by `rotation`, drawing from `rng` when random
+    pub fn with_rotation(mut self, rotation: SandoRotation, rng: Arc<DecisionRng>) -> Self {
+        self.rotation = rotation;
+        self.rng = rng;
+        self
+    }
 
+    pub async fn setup<M: Middleware + 'static>(&mut self, provider: Arc<M>) -> Result<()> {
         let latest_block = provider
             .get_block(BlockNumber::Latest)
             .await
@@ -56,49 +79,99 @@ impl SandoStateManager {
             .ok_or(anyhow!("Field block number does not exist on latest block"))?
             .as_u64();
 
-        let mut token_dust = vec![];
-
         let start_block = self.sando_inception_block.as_u64();
 
-        // for each block within the range, get all transfer events asynchronously
-        for from_block in (start_block..=latest_block).step_by(step) {
-            let to_block = from_block + step as u64;
-
-            // check for all incoming and outgoing txs within step range
-            let transfer_logs = provider
-                .get_logs(
-                    &Filter::new()
-                        .topic0(*ERC20_TRANSFER_EVENT_SIG)
-                        .topic1(self.sando_contract)
-                        .from_block(BlockNumber::Number(U64([from_block])))
-                        .to_block(BlockNumber::Number(U64([to_block]))),
-                )
-                .await?;
-
-            for log in transfer_logs {
-                token_dust.push(log.address);
+        for sando in self.sando_contracts.iter_mut() {
+            // find weth inventory
+            let weth = Erc20::new(*WETH_ADDRESS, provider.clone());
+            let weth_balance = weth.balance_of(sando.address).call().await?;
+            startup_info_log!("weth inventory   : {} ({:?})", weth_balance, sando.address);
+            sando.weth_inventory = weth_balance;
+
+            // find weth dust
+            let step = 10000;
+
+            let mut token_dust = vec![];
+
+            // for each block within the range, get all transfer events asynchronously
+            for from_block in (start_block..=latest_block).step_by(step) {
+                let to_block = from_block + step as u64;
+
+                // check for all incoming and outgoing txs within step range
+                let transfer_logs = provider
+                    .get_logs(
+                        &Filter::new()
+                            .topic0(*ERC20_TRANSFER_EVENT_SIG)
+                            .topic1(sando.address)
+                            .from_block(BlockNumber::Number(U64([from_block])))
+                            .to_block(BlockNumber::Number(U64([to_block]))),
+                    )
+                    .await?;
+
+                for log in transfer_logs {
+                    token_dust.push(log.address);
+                }
             }
-        }
 
-        startup_info_log!("token dust found : {}", token_dust.len());
-        self.token_dust = token_dust;
+            startup_info_log!(
+                "token dust found : {} ({:?})",
+                token_dust.len(),
+                sando.address
+            );
+            sando.token_dust = token_dust;
+        }
 
         Ok(())
     }
 
+    /// Pick the sando contract to use for the next bundle
+    pub fn select_sando_address(&self) -> Address {
+        let index = match self.rotation {
+            SandoRotation::RoundRobin => {
+                self.next_sando.fetch_add(1, Ordering::Relaxed) % self.sando_contracts.len()
+            }
+            SandoRotation::Random => self.rng.pick(self.sando_contracts.len()),
+        };
+        self.sando_contracts[index].address
+    }
+
+    /// Returns the first sando contract
     pub fn get_sando_address(&self) -> Address {
-        self.sando_contract
+        self.sando_contracts[0].address
     }
 
     pub fn get_searcher_address(&self) -> Address {
         self.searcher_signer.address()
     }
 
-    pub fn get_searcher_signer(&self) -> &LocalWallet {
+    pub fn get_searcher_signer(&self) -> &SearcherSigner {
         &self.searcher_signer
     }
 
-    pub fn get_weth_inventory(&self) -> U256 {
-        self.weth_inventory
+    /// Addresses of every sando contract
+    pub fn sando_addresses(&self) -> Vec<Address> {
+        self.sando_contracts
+            .iter()
+            .map(|sando| sando.address)
+            .collect()
+    }
+
+    /// Record a fresh read of `sando_address`'s weth inventory (no-op for unknown contracts)
+    pub fn set_weth_inventory(&mut self, sando_address: Address, weth_inventory: U256) {
+        if let Some(sando) = self
+            .sando_contracts
+            .iter_mut()
+            .find(|sando| sando.address == sando_address)
+        {
+            sando.weth_inventory = weth_inventory;
+        }
+    }
+
+    pub fn get_weth_inventory(&self, sando_address: Address) -> U256 {
+        self.sando_contracts
+            .iter()
+            .find(|sando| sando.address == sando_address)
+            .map(|sando| sando.weth_inventory)
+            .unwrap_or_default()
     }
 }
@@ -0,0 +1,73 @@
+use ethers::{
+    types::{Address, H256, U256, U64},
+    utils::keccak256,
+};
+use std::collections::HashMap;
+
+use crate::types::{SandoRecipe, VictimInfo};
+
+/// Everything a sandwich simulation depends on (besides state that only changes between blocks)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimulationKey {
+    pub target_block: U64,
+    pub pool: Address,
+    pub sando_address: Address,
+    pub frontrun_input: U256,
+    /// Hash of the pool's state diff from the victim's trace
+    pub pool_diff_hash: H256,
+}
+
+impl SimulationKey {
+    /// Returns `None` if the victim's trace doesn't touch `pool`
+    pub fn new(
+        target_block: U64,
+        pool: Address,
+        sando_address: Address,
+        frontrun_input: U256,
+        victim: &VictimInfo,
+    ) -> Option<Self> {
+        let pool_diff = victim.get_state_diffs()?.get(&pool)?;
+        let pool_diff_hash = H256::from(keccak256(serde_json::to_vec(pool_diff).ok()?));
+
+        Some(Self {
+            target_block,
+            pool,
+            sando_address,
+            frontrun_input,
+            pool_diff_hash,
+        })
+    }
+}
+
+/// Results of sandwich simulations for the current target block, so victims that leave a pool in
+/// the same state (rebroadcasts, or distinct txs making the same swap) are only simulated once
+#[derive(Default)]
+pub struct SimulationCache {
+    /// Simulated recipe, or why the simulation failed
+    results: HashMap<SimulationKey, Result<SandoRecipe, String>>,
+}
+
+impl SimulationCache {
+    /// Result of an earlier simulation with the same inputs
+    pub fn get(&self, key: &SimulationKey) -> Option<&Result<SandoRecipe, String>> {
+        self.results.get(key)
+    }
+
+    pub fn insert(&mut self, key: SimulationKey, result: Result<SandoRecipe, String>) {
+        self.results.insert(key, result);
+    }
+
+    /// Forget every result (state changes once a new block is mined)
+    pub fn clear(&mut self) {
+        self.results.clear();
+    }
+
+    /// Number of cached simulations
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
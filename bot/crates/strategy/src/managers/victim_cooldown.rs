@@ -0,0 +1,72 @@
+use ethers::types::{Address, U64};
+use std::collections::HashMap;
+
+/// Stops evaluating a victim sender's txs for a while once `max_misses` of our bundles around
+/// them in a row didn't land (e.g. a sender that cancels or a private flow we always lose)
+pub struct VictimCooldown {
+    /// Bundles around a sender that may miss in a row before it cools down
+    max_misses: u32,
+    /// Blocks that a sender stays cooled down for
+    cooldown_blocks: u64,
+    /// Bundles in a row around each sender that didn't land
+    misses: HashMap<Address, u32>,
+    /// Block that each cooled down sender is evaluated again from
+    cooling: HashMap<Address, U64>,
+}
+
+impl VictimCooldown {
+    pub fn new(max_misses: u32, cooldown_blocks: u64) -> Self {
+        Self {
+            max_misses: max_misses.max(1),
+            cooldown_blocks,
+            misses: HashMap::new(),
+            cooling: HashMap::new(),
+        }
+    }
+
+    /// Record that a bundle around `sender`'s tx missed its last target block `block`
+    ///
+    /// Returns true if this put the sender in cooldown
+    pub fn record_missed(&mut self, sender: Address, block: U64) -> bool {
+        if self.is_cooling(sender) {
+            return false;
+        }
+
+        let misses = self.misses.entry(sender).or_default();
+        *misses += 1;
+        if *misses < self.max_misses {
+            return false;
+        }
+
+        self.misses.remove(&sender);
+        self.cooling
+            .insert(sender, block + U64::from(self.cooldown_blocks));
+        true
+    }
+
+    /// Record that a bundle around `sender`'s tx landed, its misses start over
+    pub fn record_landed(&mut self, sender: Address) {
+        self.misses.remove(&sender);
+    }
+
+    /// True while `sender`'s txs shouldn't be evaluated
+    pub fn is_cooling(&self, sender: Address) -> bool {
+        self.cooling.contains_key(&sender)
+    }
+
+    /// Lift cooldowns that are over by `block`
+    ///
+    /// Returns the senders that are evaluated again
+    pub fn poll(&mut self, block: U64) -> Vec<Address> {
+        let lifted: Vec<Address> = self
+            .cooling
+            .iter()
+            .filter(|(_, until)| block >= **until)
+            .map(|(sender, _)| *sender)
+            .collect();
+        for sender in &lifted {
+            self.cooling.remove(sender);
+        }
+        lifted
+    }
+}
@@ -0,0 +1,191 @@
+use ethers::types::U256;
+
+/// Pure sandwich sizing over pool state and the victim's swap (no provider or async needed)
+pub mod optimizer;
+
+/// Revenue and profit of each way to order our txs around a victim
+pub mod ordering;
+
+/// Net profit accounting for sandwiches
+pub mod profit;
+
+/// Constant product (xy=k) math used to size uniswap v2 sandwiches
+pub mod v2_sandwich;
+
+/// Tick aware concentrated liquidity math used to size uniswap v3 sandwiches
+pub mod v3_sandwich;
+
+// Binary search for the input that produces the highest sandwich revenue
+// note: revenue is unimodal in frontrun input, so we keep the third that contains the peak
+//
+// Arguments:
+// * `upper_bound`: max weth we can use for frontrun
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+// * `revenue_at`: returns sandwich revenue for a frontrun input
+//
+// Returns:
+// (U256, U256): (optimal frontrun input, revenue at optimal input)
+pub fn search_optimal_input<F: Fn(U256) -> U256>(
+    upper_bound: U256,
+    epsilon: U256,
+    revenue_at: F,
+) -> (U256, U256) {
+    search_optimal_input_between(
+        U256::zero(),
+        upper_bound,
+        (U256::zero(), U256::zero()),
+        epsilon,
+        revenue_at,
+    )
+}
+
+// Same search as `search_optimal_input` but seeded with a previous optimum (e.g. the last
+// sandwich on the same pool), the search only runs over the window around `hint` that contains
+// the peak
+// note: the window starts at `[hint / 2, hint * 2]` and doubles outwards until revenue at both
+// edges falls below revenue at `hint`, by unimodality the peak lies within it
+//
+// Arguments:
+// * `hint`: frontrun input expected to be near the optimum (clamped to `upper_bound`)
+// * `upper_bound`: max weth we can use for frontrun
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+// * `revenue_at`: returns sandwich revenue for a frontrun input
+//
+// Returns:
+// (U256, U256): (optimal frontrun input, revenue at optimal input)
+pub fn search_optimal_input_from<F: Fn(U256) -> U256>(
+    hint: U256,
+    upper_bound: U256,
+    epsilon: U256,
+    revenue_at: F,
+) -> (U256, U256) {
+    let hint = hint.min(upper_bound);
+    let hint_revenue = revenue_at(hint);
+
+    // a hint off the revenue curve says nothing about where the peak is
+    if hint.is_zero() || hint_revenue.is_zero() {
+        return search_optimal_input(upper_bound, epsilon, revenue_at);
+    }
+
+    let mut lower_bound = hint / 2;
+    while !lower_bound.is_zero() && revenue_at(lower_bound) >= hint_revenue {
+        lower_bound /= 2;
+    }
+
+    let mut window_upper = hint.saturating_mul(U256::from(2)).min(upper_bound);
+    while window_upper < upper_bound && revenue_at(window_upper) >= hint_revenue {
+        window_upper = window_upper.saturating_mul(U256::from(2)).min(upper_bound);
+    }
+
+    search_optimal_input_between(
+        lower_bound,
+        window_upper,
+        (hint, hint_revenue),
+        epsilon,
+        revenue_at,
+    )
+}
+
+// Ternary search over `[lower_bound, upper_bound]` starting from a known best `(input, revenue)`
+fn search_optimal_input_between<F: Fn(U256) -> U256>(
+    lower_bound: U256,
+    upper_bound: U256,
+    best: (U256, U256),
+    epsilon: U256,
+    revenue_at: F,
+) -> (U256, U256) {
+    let mut lower_bound = lower_bound;
+    let mut upper_bound = upper_bound;
+
+    let (mut best_input, mut best_revenue) = best;
+
+    while upper_bound - lower_bound > U256::from(2) {
+        let third = (upper_bound - lower_bound) / 3;
+        let left = lower_bound + third;
+        let right = upper_bound - third;
+
+        let left_revenue = revenue_at(left);
+        let right_revenue = revenue_at(right);
+
+        let (input, revenue) = if left_revenue < right_revenue {
+            lower_bound = left;
+            (right, right_revenue)
+        } else {
+            upper_bound = right;
+            (left, left_revenue)
+        };
+
+        let delta = if revenue > best_revenue {
+            revenue - best_revenue
+        } else {
+            best_revenue - revenue
+        };
+
+        if revenue > best_revenue {
+            best_input = input;
+            best_revenue = revenue;
+        }
+
+        // only terminate early once we are climbing a non zero revenue curve
+        if !best_revenue.is_zero() && delta < epsilon {
+            break;
+        }
+    }
+
+    (best_input, best_revenue)
+}
+
+// Binary search for the largest input that still satisfies `is_valid`
+// note: assumes `is_valid` is monotonic (true up to some input and false after)
+//
+// Arguments:
+// * `upper_bound`: largest input to consider
+// * `is_valid`: returns true if an input is acceptable
+//
+// Returns:
+// Some(U256): largest valid input in `[0, upper_bound]`
+// None: if even a zero input is invalid
+pub fn search_max_valid_input<F: Fn(U256) -> bool>(upper_bound: U256, is_valid: F) -> Option<U256> {
+    if !is_valid(U256::zero()) {
+        return None;
+    }
+
+    if is_valid(upper_bound) {
+        return Some(upper_bound);
+    }
+
+    // invariant: `lower_bound` is valid and `upper_bound` is not
+    let mut lower_bound = U256::zero();
+    let mut upper_bound = upper_bound;
+
+    while upper_bound - lower_bound > U256::one() {
+        let mid = lower_bound + (upper_bound - lower_bound) / 2;
+
+        if is_valid(mid) {
+            lower_bound = mid;
+        } else {
+            upper_bound = mid;
+        }
+    }
+
+    Some(lower_bound)
+}
+
+// Check if a simulated value agrees with its analytic estimate
+//
+// Arguments:
+// * `analytic`: value predicted by our math
+// * `simulated`: value found by simulating in an evm
+// * `tolerance_bps`: max allowed difference in basis points of the analytic value
+//
+// Returns:
+// bool: true if values differ by at most `tolerance_bps`
+pub fn is_within_tolerance(analytic: U256, simulated: U256, tolerance_bps: u64) -> bool {
+    let difference = if analytic > simulated {
+        analytic - simulated
+    } else {
+        simulated - analytic
+    };
+
+    difference * 10_000 <= analytic * tolerance_bps
+}
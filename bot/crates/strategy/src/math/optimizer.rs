@@ -0,0 +1,758 @@
+use cfmms::pool::UniswapV3Pool;
+use ethers::types::{U256, U512};
+
+use super::{
+    search_max_valid_input, search_optimal_input, search_optimal_input_from, v2_sandwich,
+    v3_sandwich::{self, TickMap},
+};
+
+/// Reserves of a v2 pool around a sandwich, oriented along the frontrun (its input token first)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct V2PoolState {
+    /// Reserves of the token our frontrun and the victim swap in
+    pub reserve_in: U256,
+    /// Reserves of the token our frontrun and the victim receive
+    pub reserve_out: U256,
+    /// Pool swap fee in basis points
+    pub fee_bps: u32,
+}
+
+impl V2PoolState {
+    pub fn new(reserve_in: U256, reserve_out: U256, fee_bps: u32) -> Self {
+        Self {
+            reserve_in,
+            reserve_out,
+            fee_bps,
+        }
+    }
+}
+
+/// A pending swap through the sandwiched v2 pool that a builder runs before the victim
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingV2Swap {
+    /// Swaps `amount_in` of the frontrun's input token in, moving the pool the way we would
+    SameDirection(U256),
+    /// Swaps `amount_in` of the frontrun's output token in, moving the pool back
+    OppositeDirection(U256),
+}
+
+impl PendingV2Swap {
+    /// Same swap oriented along a frontrun the other way
+    pub fn reversed(self) -> Self {
+        match self {
+            PendingV2Swap::SameDirection(amount_in) => PendingV2Swap::OppositeDirection(amount_in),
+            PendingV2Swap::OppositeDirection(amount_in) => PendingV2Swap::SameDirection(amount_in),
+        }
+    }
+}
+
+/// A v3 pool synced around its current tick, our frontrun always swaps weth for its token
+#[derive(Debug, Clone)]
+pub struct V3PoolState {
+    /// Pool with current sqrtPriceX96, tick, and liquidity
+    pub pool: UniswapV3Pool,
+    /// Initialized ticks around the pool's current tick
+    pub tick_map: TickMap,
+    /// True if weth is the pool's token0
+    pub weth_is_token0: bool,
+}
+
+impl V3PoolState {
+    pub fn new(pool: UniswapV3Pool, tick_map: TickMap, weth_is_token0: bool) -> Self {
+        Self {
+            pool,
+            tick_map,
+            weth_is_token0,
+        }
+    }
+}
+
+/// What the victim swaps through the sandwiched pool, in the same direction as our frontrun
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VictimSwap {
+    /// A swap whose slippage floor we can't check (calldata not decoded), the frontrun is only
+    /// capped by our inventory
+    Unchecked { amount_in: U256 },
+    /// Swaps exactly `amount_in` and accepts no less than `amount_out_min`
+    ExactInput {
+        amount_in: U256,
+        amount_out_min: U256,
+    },
+    /// First hop of a two hop swap, `amount_out_min` is the floor for the output of swapping
+    /// through `next_hop` (v2 only, v3 treats it as `Unchecked`)
+    ExactInputTwoHop {
+        amount_in: U256,
+        amount_out_min: U256,
+        next_hop: V2PoolState,
+    },
+    /// Buys exactly `amount_out` paying no more than `amount_in_max`
+    ExactOutput {
+        amount_out: U256,
+        amount_in_max: U256,
+    },
+}
+
+/// Frontrun size found by an optimizer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptimalSandwich {
+    /// Amount of the pool's input token to frontrun with
+    pub frontrun_in: U256,
+    /// Amount of that token the backrun returns on top of `frontrun_in`
+    pub revenue: U256,
+}
+
+// Find the largest v2 frontrun that keeps the victim's swap above their slippage floor
+//
+// Arguments:
+// * `pool`: reserves before the frontrun
+// * `victim`: victim's swap through the pool
+// * `inventory`: amount of the input token we hold for the frontrun
+//
+// Returns:
+// Some(U256): max frontrun input (capped at `inventory`)
+// None: if we hold no inventory or the victim's swap fails slippage even without a frontrun
+pub fn v2_frontrun_bound(pool: &V2PoolState, victim: &VictimSwap, inventory: U256) -> Option<U256> {
+    if inventory.is_zero() {
+        return None;
+    }
+
+    match *victim {
+        VictimSwap::Unchecked { .. } => Some(inventory),
+        VictimSwap::ExactInput {
+            amount_in,
+            amount_out_min,
+        } => v2_sandwich::max_frontrun_for_slippage(
+            amount_in,
+            amount_out_min,
+            pool.reserve_in,
+            pool.reserve_out,
+            pool.fee_bps,
+            inventory,
+        ),
+        VictimSwap::ExactInputTwoHop {
+            amount_in,
+            amount_out_min,
+            next_hop,
+        } => v2_sandwich::max_frontrun_for_slippage_two_hop(
+            amount_in,
+            amount_out_min,
+            pool.reserve_in,
+            pool.reserve_out,
+            pool.fee_bps,
+            next_hop.reserve_in,
+            next_hop.reserve_out,
+            next_hop.fee_bps,
+            inventory,
+        ),
+        // victim's input floats up with our frontrun, it can't pass their max input
+        VictimSwap::ExactOutput {
+            amount_out,
+            amount_in_max,
+        } => v2_sandwich::max_frontrun_for_exact_output(
+            amount_out,
+            amount_in_max,
+            pool.reserve_in,
+            pool.reserve_out,
+            pool.fee_bps,
+            inventory,
+        ),
+    }
+}
+
+// Revenue of a v2 sandwich around the victim's swap (frontrun, victim, backrun)
+//
+// Arguments:
+// * `pool`: reserves before the frontrun
+// * `victim`: victim's swap through the pool
+// * `frontrun_in`: amount of the input token to frontrun with
+//
+// Returns:
+// U256: amount of the input token the backrun returns on top of `frontrun_in`
+pub fn v2_sandwich_revenue(pool: &V2PoolState, victim: &VictimSwap, frontrun_in: U256) -> U256 {
+    match *victim {
+        VictimSwap::ExactOutput { amount_out, .. } => {
+            v2_sandwich::get_exact_output_sandwich_revenue(
+                frontrun_in,
+                amount_out,
+                pool.reserve_in,
+                pool.reserve_out,
+                pool.fee_bps,
+            )
+        }
+        VictimSwap::Unchecked { amount_in }
+        | VictimSwap::ExactInput { amount_in, .. }
+        | VictimSwap::ExactInputTwoHop { amount_in, .. } => v2_sandwich::get_sandwich_revenue(
+            frontrun_in,
+            amount_in,
+            pool.reserve_in,
+            pool.reserve_out,
+            pool.fee_bps,
+        ),
+    }
+}
+
+// Size the v2 frontrun that maximizes sandwich revenue without breaking the victim's slippage
+//
+// Arguments:
+// * `pool`: reserves before the frontrun
+// * `victim`: victim's swap through the pool
+// * `inventory`: amount of the input token we hold for the frontrun
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+//
+// Returns:
+// Some(OptimalSandwich): optimal frontrun input and its revenue
+// None: if the frontrun can't be sized (see `v2_frontrun_bound`)
+pub fn optimize_v2_sandwich(
+    pool: &V2PoolState,
+    victim: &VictimSwap,
+    inventory: U256,
+    epsilon: U256,
+) -> Option<OptimalSandwich> {
+    let upper_bound = v2_frontrun_bound(pool, victim, inventory)?;
+
+    let (frontrun_in, revenue) = match *victim {
+        // exact output victims get a fixed amount out, our frontrun raises what they pay
+        VictimSwap::ExactOutput { amount_out, .. } => v2_sandwich::find_optimal_exact_output_input(
+            amount_out,
+            pool.reserve_in,
+            pool.reserve_out,
+            pool.fee_bps,
+            upper_bound,
+            epsilon,
+        ),
+        VictimSwap::Unchecked { amount_in }
+        | VictimSwap::ExactInput { amount_in, .. }
+        | VictimSwap::ExactInputTwoHop { amount_in, .. } => v2_sandwich::find_optimal_input(
+            amount_in,
+            pool.reserve_in,
+            pool.reserve_out,
+            pool.fee_bps,
+            upper_bound,
+            epsilon,
+        ),
+    };
+
+    Some(OptimalSandwich {
+        frontrun_in,
+        revenue,
+    })
+}
+
+// Same as `optimize_v2_sandwich` but warm started from the last optimum found on the pool, which
+// usually sits close to the new one (see `search_optimal_input_from`)
+//
+// Arguments:
+// * `pool`: reserves before the frontrun
+// * `victim`: victim's swap through the pool
+// * `inventory`: amount of the input token we hold for the frontrun
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+// * `hint`: previous optimal frontrun input on the pool, searches cold if `None`
+//
+// Returns:
+// Some(OptimalSandwich): optimal frontrun input and its revenue
+// None: if the frontrun can't be sized (see `v2_frontrun_bound`)
+pub fn optimize_v2_sandwich_from(
+    pool: &V2PoolState,
+    victim: &VictimSwap,
+    inventory: U256,
+    epsilon: U256,
+    hint: Option<U256>,
+) -> Option<OptimalSandwich> {
+    let hint = match hint {
+        Some(hint) => hint,
+        None => return optimize_v2_sandwich(pool, victim, inventory, epsilon),
+    };
+    let upper_bound = v2_frontrun_bound(pool, victim, inventory)?;
+
+    let (frontrun_in, revenue) = search_optimal_input_from(hint, upper_bound, epsilon, |input| {
+        v2_sandwich_revenue(pool, victim, input)
+    });
+
+    Some(OptimalSandwich {
+        frontrun_in,
+        revenue,
+    })
+}
+
+// Reserves a v2 pool is left with once the pending swaps that run before the victim went
+// through, each replayed on the reserves the one before it left
+//
+// Arguments:
+// * `pool`: reserves on the block state
+// * `pending`: swaps through the pool ahead of the victim, in the order they run
+//
+// Returns:
+// V2PoolState: reserves the victim (and our frontrun) would swap on
+pub fn v2_state_after_pending(pool: &V2PoolState, pending: &[PendingV2Swap]) -> V2PoolState {
+    let mut state = *pool;
+    for swap in pending {
+        v2_swap_through(&mut state, *swap);
+    }
+    state
+}
+
+// Run one swap through a v2 pool, moving its reserves
+//
+// Arguments:
+// * `state`: reserves before the swap, updated to the ones it leaves
+// * `swap`: swap through the pool, oriented along the frontrun
+//
+// Returns:
+// U256: amount the swap receives (of the frontrun's output token for `SameDirection`)
+fn v2_swap_through(state: &mut V2PoolState, swap: PendingV2Swap) -> U256 {
+    match swap {
+        PendingV2Swap::SameDirection(amount_in) => {
+            let amount_out = v2_sandwich::get_amount_out(
+                amount_in,
+                state.reserve_in,
+                state.reserve_out,
+                state.fee_bps,
+            );
+            state.reserve_in += amount_in;
+            state.reserve_out -= amount_out;
+            amount_out
+        }
+        PendingV2Swap::OppositeDirection(amount_in) => {
+            let amount_out = v2_sandwich::get_amount_out(
+                amount_in,
+                state.reserve_out,
+                state.reserve_in,
+                state.fee_bps,
+            );
+            state.reserve_out += amount_in;
+            state.reserve_in -= amount_out;
+            amount_out
+        }
+    }
+}
+
+// Tell whether victims trading a v2 pool both ways still move its price along the frontrun once
+// their swaps net out, each victim swapping on the reserves the one before it left
+//
+// Arguments:
+// * `pool`: reserves before the victims
+// * `victims`: victims' swaps through the pool, oriented along the frontrun
+//
+// Returns:
+// bool: true if the frontrun's input token ends up cheaper in the output token than it started
+pub fn v2_victims_net_along(pool: &V2PoolState, victims: &[PendingV2Swap]) -> bool {
+    let after = v2_state_after_pending(pool, victims);
+    after.reserve_in.full_mul(pool.reserve_out) > pool.reserve_in.full_mul(after.reserve_out)
+}
+
+// Revenue of one v2 sandwich around victims that trade the pool both ways (frontrun, every
+// victim in order, then one backrun)
+//
+// Arguments:
+// * `pool`: reserves before the frontrun
+// * `victims`: `(swap, amount_out_min)` of each victim in the order they run, oriented along the
+//   frontrun
+// * `frontrun_in`: amount of the input token to frontrun with
+//
+// Returns:
+// U256: amount of the input token the backrun returns on top of `frontrun_in` (zero if the
+// sandwich makes a loss)
+pub fn v2_netted_sandwich_revenue(
+    pool: &V2PoolState,
+    victims: &[(PendingV2Swap, U256)],
+    frontrun_in: U256,
+) -> U256 {
+    let mut state = *pool;
+    let frontrun_out = v2_swap_through(&mut state, PendingV2Swap::SameDirection(frontrun_in));
+    for (swap, _) in victims {
+        v2_swap_through(&mut state, *swap);
+    }
+    let backrun_out = v2_swap_through(&mut state, PendingV2Swap::OppositeDirection(frontrun_out));
+
+    backrun_out.saturating_sub(frontrun_in)
+}
+
+// Size the frontrun that maximizes revenue around victims that trade a v2 pool both ways, without
+// breaking any victim's slippage (a larger frontrun only hurts the victims trading along it)
+//
+// Arguments:
+// * `pool`: reserves before the frontrun
+// * `victims`: `(swap, amount_out_min)` of each victim in the order they run, oriented along the
+//   frontrun
+// * `inventory`: amount of the input token we hold for the frontrun
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+//
+// Returns:
+// Some(OptimalSandwich): optimal frontrun input and its revenue
+// None: if we hold no inventory, the victims' swaps net against the frontrun, or a victim's swap
+// fails slippage even without a frontrun
+pub fn optimize_v2_netted_sandwich(
+    pool: &V2PoolState,
+    victims: &[(PendingV2Swap, U256)],
+    inventory: U256,
+    epsilon: U256,
+) -> Option<OptimalSandwich> {
+    let swaps: Vec<PendingV2Swap> = victims.iter().map(|(swap, _)| *swap).collect();
+    if inventory.is_zero() || !v2_victims_net_along(pool, &swaps) {
+        return None;
+    }
+
+    let upper_bound = search_max_valid_input(inventory, |frontrun_in| {
+        let mut state = *pool;
+        v2_swap_through(&mut state, PendingV2Swap::SameDirection(frontrun_in));
+        victims
+            .iter()
+            .all(|(swap, amount_out_min)| v2_swap_through(&mut state, *swap) >= *amount_out_min)
+    })?;
+    let (frontrun_in, revenue) = search_optimal_input(upper_bound, epsilon, |frontrun_in| {
+        v2_netted_sandwich_revenue(pool, victims, frontrun_in)
+    });
+
+    Some(OptimalSandwich {
+        frontrun_in,
+        revenue,
+    })
+}
+
+// Find the weth revenue of buying a token on one v2 pool and selling it on another
+//
+// Arguments:
+// * `buy`: reserves of the pool we buy on, weth first
+// * `sell`: reserves of the pool we sell on, token first
+// * `weth_in`: amount of weth spent on the buy
+//
+// Returns:
+// U256: weth out of the sell minus `weth_in` (zero if the arb makes a loss)
+pub fn v2_arb_revenue(buy: &V2PoolState, sell: &V2PoolState, weth_in: U256) -> U256 {
+    let token_out =
+        v2_sandwich::get_amount_out(weth_in, buy.reserve_in, buy.reserve_out, buy.fee_bps);
+    let weth_out =
+        v2_sandwich::get_amount_out(token_out, sell.reserve_in, sell.reserve_out, sell.fee_bps);
+    weth_out.saturating_sub(weth_in)
+}
+
+// Size the weth input of a two pool v2 arb (see `v2_arb_revenue`)
+// note: revenue is concave in the input, so the same ternary search as sandwiches finds its peak
+//
+// Arguments:
+// * `buy`: reserves of the pool we buy on, weth first
+// * `sell`: reserves of the pool we sell on, token first
+// * `inventory`: amount of weth we hold for the buy
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+//
+// Returns:
+// Some(OptimalSandwich): optimal buy input (as `frontrun_in`) and its revenue
+// None: if we hold no inventory or the pools' prices leave nothing to arb
+pub fn optimize_v2_arb(
+    buy: &V2PoolState,
+    sell: &V2PoolState,
+    inventory: U256,
+    epsilon: U256,
+) -> Option<OptimalSandwich> {
+    if inventory.is_zero() {
+        return None;
+    }
+
+    let (frontrun_in, revenue) =
+        search_optimal_input(inventory, epsilon, |input| v2_arb_revenue(buy, sell, input));
+    if revenue.is_zero() {
+        return None;
+    }
+
+    Some(OptimalSandwich {
+        frontrun_in,
+        revenue,
+    })
+}
+
+// Find the largest v3 frontrun that keeps the victim's swap above their slippage floor
+// note: for exact output victims `amount_in_max` buys at least `amount_out` iff buying
+// `amount_out` costs at most `amount_in_max`, so the exact input bound holds for them too
+//
+// Arguments:
+// * `pool`: pool state before the frontrun
+// * `victim`: victim's `weth->token` swap through the pool
+// * `inventory`: amount of weth we hold for the frontrun
+//
+// Returns:
+// Some(U256): max frontrun input (capped at `inventory`)
+// None: if we hold no inventory or the victim's swap fails slippage even without a frontrun
+pub fn v3_frontrun_bound(pool: &V3PoolState, victim: &VictimSwap, inventory: U256) -> Option<U256> {
+    if inventory.is_zero() {
+        return None;
+    }
+
+    let (amount_in, amount_out_min) = match *victim {
+        VictimSwap::Unchecked { .. } | VictimSwap::ExactInputTwoHop { .. } => {
+            return Some(inventory)
+        }
+        VictimSwap::ExactInput {
+            amount_in,
+            amount_out_min,
+        } => (amount_in, amount_out_min),
+        VictimSwap::ExactOutput {
+            amount_out,
+            amount_in_max,
+        } => (amount_in_max, amount_out),
+    };
+
+    v3_sandwich::max_frontrun_for_slippage(
+        amount_in,
+        amount_out_min,
+        &pool.pool,
+        &pool.tick_map,
+        pool.weth_is_token0,
+        inventory,
+    )
+}
+
+// Size the v3 frontrun that maximizes sandwich revenue without breaking the victim's slippage
+//
+// Arguments:
+// * `pool`: pool state before the frontrun
+// * `victim`: victim's `weth->token` swap through the pool
+// * `inventory`: amount of weth we hold for the frontrun
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+//
+// Returns:
+// Some(OptimalSandwich): optimal frontrun input and its revenue
+// None: if the frontrun can't be sized (see `v3_frontrun_bound`)
+pub fn optimize_v3_sandwich(
+    pool: &V3PoolState,
+    victim: &VictimSwap,
+    inventory: U256,
+    epsilon: U256,
+) -> Option<OptimalSandwich> {
+    let upper_bound = v3_frontrun_bound(pool, victim, inventory)?;
+
+    let (frontrun_in, revenue) = match *victim {
+        // exact output victims buy a fixed amount of token, our frontrun raises what they pay
+        VictimSwap::ExactOutput {
+            amount_out,
+            amount_in_max,
+        } => v3_sandwich::find_optimal_exact_output_input(
+            amount_out,
+            amount_in_max,
+            &pool.pool,
+            &pool.tick_map,
+            pool.weth_is_token0,
+            upper_bound,
+            epsilon,
+        ),
+        VictimSwap::Unchecked { amount_in }
+        | VictimSwap::ExactInput { amount_in, .. }
+        | VictimSwap::ExactInputTwoHop { amount_in, .. } => v3_sandwich::find_optimal_input(
+            amount_in,
+            &pool.pool,
+            &pool.tick_map,
+            pool.weth_is_token0,
+            upper_bound,
+            epsilon,
+        ),
+    };
+
+    Some(OptimalSandwich {
+        frontrun_in,
+        revenue,
+    })
+}
+
+// Upper bound on the revenue of a sandwich of any size around the victim's swap, a cheap screen
+// before running the optimizers
+// note: values the victim's slippage allowance at the pool's spot price (ignoring fees and price
+// impact), so it is never below the revenue `optimize_v2_sandwich` or `optimize_v3_sandwich` find
+//
+// Arguments:
+// * `victim`: victim's swap through the pool
+// * `spot_in`: pool's input token side of its spot price (reserves, or virtual reserves for v3)
+// * `spot_out`: pool's output token side of its spot price
+//
+// Returns:
+// U256: most of the input token a sandwich could take from the victim (zero if they left no
+// slippage to take)
+pub fn sandwich_revenue_ceiling(victim: &VictimSwap, spot_in: U256, spot_out: U256) -> U256 {
+    if spot_out.is_zero() {
+        return U256::zero();
+    }
+
+    // victim's output valued in the input token at the spot price
+    let in_at_spot = |amount_out: U256| {
+        U256::try_from(amount_out.full_mul(spot_in) / U512::from(spot_out)).unwrap_or(U256::MAX)
+    };
+
+    match *victim {
+        // nothing caps what the victim gives up but what they swap
+        VictimSwap::Unchecked { amount_in } | VictimSwap::ExactInputTwoHop { amount_in, .. } => {
+            amount_in
+        }
+        VictimSwap::ExactInput {
+            amount_in,
+            amount_out_min,
+        } => amount_in.saturating_sub(in_at_spot(amount_out_min)),
+        VictimSwap::ExactOutput {
+            amount_out,
+            amount_in_max,
+        } => amount_in_max.saturating_sub(in_at_spot(amount_out)),
+    }
+}
+
+// Virtual reserves matching a v3 pool's spot price within its current tick
+//
+// Arguments:
+// * `pool`: pool with its current price and liquidity
+// * `weth_is_token0`: pool's token0 is weth
+//
+// Returns:
+// (U256, U256): (virtual weth reserves, virtual token reserves)
+pub fn v3_virtual_reserves(pool: &UniswapV3Pool, weth_is_token0: bool) -> (U256, U256) {
+    if pool.sqrt_price.is_zero() {
+        return (U256::zero(), U256::zero());
+    }
+
+    // x = L / sqrt(P) and y = L * sqrt(P), with sqrt(P) in q64.96
+    let liquidity = U256::from(pool.liquidity);
+    let reserve_0 = (liquidity << 96) / pool.sqrt_price;
+    let reserve_1 = U256::try_from(liquidity.full_mul(pool.sqrt_price) >> 96).unwrap_or(U256::MAX);
+
+    match weth_is_token0 {
+        true => (reserve_0, reserve_1),
+        false => (reserve_1, reserve_0),
+    }
+}
+
+// Weth a single frontrun may spend
+//
+// Arguments:
+// * `inventory`: weth held by the sando contract
+// * `max_position`: cap on a single sandwich's frontrun (`None` = uncapped)
+//
+// Returns:
+// U256: the inventory, clamped to `max_position`
+pub fn position_budget(inventory: U256, max_position: Option<U256>) -> U256 {
+    match max_position {
+        Some(max_position) => inventory.min(max_position),
+        None => inventory,
+    }
+}
+
+// Largest v2 frontrun within `inventory` that moves the price of its input token by at most
+// `max_impact_bps`
+// note: the pool's price falls with `(reserve_in / (reserve_in + input))^2` before fees, fees only
+// shrink the move so the bound is conservative
+//
+// Arguments:
+// * `pool`: reserves before the frontrun
+// * `inventory`: amount of the input token we may frontrun with
+// * `max_impact_bps`: cap on the frontrun's price impact (`None` = uncapped)
+//
+// Returns:
+// U256: the inventory, clamped to the largest input within the cap
+pub fn v2_price_impact_budget(
+    pool: &V2PoolState,
+    inventory: U256,
+    max_impact_bps: Option<u64>,
+) -> U256 {
+    let max_impact_bps = match max_impact_bps {
+        Some(max_impact_bps) if max_impact_bps < 10_000 => max_impact_bps,
+        _ => return inventory,
+    };
+
+    // cap is hit once reserves reach `reserve_in / sqrt(1 - impact)`, sqrt scaled by 1e18
+    let sqrt_remaining = (U256::from(10_000 - max_impact_bps) * U256::exp10(32)).integer_sqrt();
+    let max_reserve_in =
+        U256::try_from(pool.reserve_in.full_mul(U256::exp10(18)) / U512::from(sqrt_remaining))
+            .unwrap_or(U256::MAX);
+
+    inventory.min(max_reserve_in.saturating_sub(pool.reserve_in))
+}
+
+// Largest v3 frontrun within `inventory` that moves the price of weth by at most
+// `max_impact_bps`, found by simulating the frontrun across the pool's synced ticks
+//
+// Arguments:
+// * `pool`: pool state before the frontrun
+// * `inventory`: amount of weth we may frontrun with
+// * `max_impact_bps`: cap on the frontrun's price impact (`None` = uncapped)
+//
+// Returns:
+// U256: the inventory, clamped to the largest input within the cap
+pub fn v3_price_impact_budget(
+    pool: &V3PoolState,
+    inventory: U256,
+    max_impact_bps: Option<u64>,
+) -> U256 {
+    let max_impact_bps = match max_impact_bps {
+        Some(max_impact_bps) if max_impact_bps < 10_000 => max_impact_bps,
+        _ => return inventory,
+    };
+
+    // weth's price is `P` if it is token0 and `1 / P` if not, with `P = sqrt_price^2`
+    let price_before = pool.pool.sqrt_price.full_mul(pool.pool.sqrt_price);
+    let within_cap = |input: U256| {
+        if input.is_zero() {
+            return true;
+        }
+        let frontrun = match v3_sandwich::simulate_swap(
+            &pool.pool,
+            &pool.tick_map,
+            pool.weth_is_token0,
+            input,
+        ) {
+            Ok(frontrun) => frontrun,
+            Err(_) => return false,
+        };
+        let price_after = frontrun.sqrt_price_x96.full_mul(frontrun.sqrt_price_x96);
+        let (remaining, initial) = match pool.weth_is_token0 {
+            true => (price_after, price_before),
+            false => (price_before, price_after),
+        };
+        remaining * U512::from(10_000) >= initial * U512::from(10_000 - max_impact_bps)
+    };
+
+    search_max_valid_input(inventory, within_cap).unwrap_or_default()
+}
+
+// Count the initialized ticks the frontrun and backrun of a v3 sandwich cross
+// note: the backrun sells from the price the victim left, so it also crosses back over the
+// ticks the victim crossed (and can end up on a different tick than the frontrun started from)
+//
+// Arguments:
+// * `pool`: pool state before the frontrun
+// * `victim`: victim's swap through the pool
+// * `frontrun_in`: amount of weth to frontrun with
+//
+// Returns:
+// (u32, u32): initialized ticks crossed by the frontrun and the backrun, zeros if the round trip
+// can't be simulated
+pub fn v3_round_trip_ticks(
+    pool: &V3PoolState,
+    victim: &VictimSwap,
+    frontrun_in: U256,
+) -> (u32, u32) {
+    let round_trip = match *victim {
+        VictimSwap::ExactOutput {
+            amount_out,
+            amount_in_max,
+        } => v3_sandwich::simulate_exact_output_round_trip(
+            frontrun_in,
+            amount_out,
+            amount_in_max,
+            &pool.pool,
+            &pool.tick_map,
+            pool.weth_is_token0,
+        ),
+        VictimSwap::Unchecked { amount_in }
+        | VictimSwap::ExactInput { amount_in, .. }
+        | VictimSwap::ExactInputTwoHop { amount_in, .. } => v3_sandwich::simulate_round_trip(
+            frontrun_in,
+            amount_in,
+            &pool.pool,
+            &pool.tick_map,
+            pool.weth_is_token0,
+        ),
+    };
+
+    round_trip
+        .map(|round_trip| {
+            (
+                round_trip.frontrun.ticks_crossed,
+                round_trip.backrun.ticks_crossed,
+            )
+        })
+        .unwrap_or_default()
+}
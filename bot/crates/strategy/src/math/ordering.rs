@@ -0,0 +1,211 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use ethers::types::{I256, U256};
+
+use super::{
+    profit::SandwichProfit,
+    v2_sandwich::{get_amount_out, get_backrun_revenue, get_sandwich_revenue},
+};
+use crate::types::PaymentStrategy;
+
+/// Where our txs go relative to the victims in a bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderingStrategy {
+    /// Frontrun, victims, backrun
+    Sandwich,
+    /// Frontrun, victims: the bought tokens stay on the contract as inventory (saves the
+    /// backrun's gas when it would eat most of the revenue)
+    FrontrunOnly,
+    /// Victims, backrun: sells inventory the contract already holds into the victims' impact
+    BackrunOnly,
+}
+
+impl OrderingStrategy {
+    pub const ALL: [OrderingStrategy; 3] = [
+        OrderingStrategy::Sandwich,
+        OrderingStrategy::FrontrunOnly,
+        OrderingStrategy::BackrunOnly,
+    ];
+
+    /// Name in configs and logs
+    pub fn label(&self) -> &'static str {
+        match self {
+            OrderingStrategy::Sandwich => "sandwich",
+            OrderingStrategy::FrontrunOnly => "frontrun_only",
+            OrderingStrategy::BackrunOnly => "backrun_only",
+        }
+    }
+
+    /// True if the bundle has our tx before the victims
+    pub fn has_frontrun(&self) -> bool {
+        matches!(
+            self,
+            OrderingStrategy::Sandwich | OrderingStrategy::FrontrunOnly
+        )
+    }
+
+    /// True if the bundle has our tx after the victims
+    pub fn has_backrun(&self) -> bool {
+        matches!(
+            self,
+            OrderingStrategy::Sandwich | OrderingStrategy::BackrunOnly
+        )
+    }
+
+    /// Net out gas of only the txs this ordering sends from `gross_revenue`
+    pub fn profit(
+        &self,
+        gross_revenue: U256,
+        frontrun_gas: U256,
+        backrun_gas: U256,
+        base_fee: U256,
+        priority_fee: U256,
+        payment: PaymentStrategy,
+    ) -> SandwichProfit {
+        let frontrun_gas = if self.has_frontrun() {
+            frontrun_gas
+        } else {
+            U256::zero()
+        };
+        let backrun_gas = if self.has_backrun() {
+            backrun_gas
+        } else {
+            U256::zero()
+        };
+        // a coinbase transfer is only paid from a backrun, without one the builder gets a tip
+        let payment = if self.has_backrun() {
+            payment
+        } else {
+            PaymentStrategy::PriorityFee
+        };
+
+        SandwichProfit::new(
+            gross_revenue,
+            frontrun_gas,
+            backrun_gas,
+            base_fee,
+            priority_fee,
+            payment,
+        )
+    }
+}
+
+impl FromStr for OrderingStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "sandwich" => Ok(OrderingStrategy::Sandwich),
+            "frontrun_only" | "frontrun" => Ok(OrderingStrategy::FrontrunOnly),
+            "backrun_only" | "backrun" => Ok(OrderingStrategy::BackrunOnly),
+            _ => Err(anyhow!("Unknown ordering strategy {}", s)),
+        }
+    }
+}
+
+// Find the weth revenue of a frontrun that keeps what it buys ahead of a `weth->token` victim
+// note: the tokens aren't sold, they're marked at what selling them right after the victim
+// would return less `haircut_bps` for the price moving back before we sell them
+//
+// Arguments:
+// * `frontrun_in`: amount of weth used in frontrun
+// * `victim_in`: amount of weth the victim swaps
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+// * `haircut_bps`: discount on the marked value of the kept tokens, in basis points
+//
+// Returns:
+// U256: marked weth value of the bought tokens minus frontrun weth input (zero on a loss)
+pub fn get_frontrun_only_revenue(
+    frontrun_in: U256,
+    victim_in: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+    haircut_bps: u64,
+) -> U256 {
+    // frontrun: weth -> token
+    let frontrun_out = get_amount_out(frontrun_in, reserve_weth, reserve_token, fee_bps);
+    let reserve_weth = reserve_weth + frontrun_in;
+    let reserve_token = reserve_token - frontrun_out;
+
+    // victim: weth -> token
+    let victim_out = get_amount_out(victim_in, reserve_weth, reserve_token, fee_bps);
+    let reserve_weth = reserve_weth + victim_in;
+    let reserve_token = reserve_token - victim_out;
+
+    // what a backrun would get for the tokens, we keep them instead
+    let marked = get_amount_out(frontrun_out, reserve_token, reserve_weth, fee_bps);
+    let marked = marked * U256::from(10_000u64.saturating_sub(haircut_bps)) / 10_000;
+
+    marked.saturating_sub(frontrun_in)
+}
+
+// Find the weth revenue of a `weth->token` victim's bundle under an ordering
+//
+// Arguments:
+// * `ordering`: where our txs go relative to the victim
+// * `amount_in`: weth frontrun input, or token inventory sold for `BackrunOnly`
+// * `victim_in`: amount of weth the victim swaps
+// * `reserve_weth`: weth reserves of pool before our txs and the victim
+// * `reserve_token`: token reserves of pool before our txs and the victim
+// * `fee_bps`: pool swap fee in basis points
+// * `haircut_bps`: discount on tokens a `FrontrunOnly` bundle keeps, in basis points
+//
+// Returns:
+// U256: gross weth revenue of the bundle (zero on a loss)
+pub fn get_v2_ordering_revenue(
+    ordering: OrderingStrategy,
+    amount_in: U256,
+    victim_in: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+    haircut_bps: u64,
+) -> U256 {
+    match ordering {
+        OrderingStrategy::Sandwich => {
+            get_sandwich_revenue(amount_in, victim_in, reserve_weth, reserve_token, fee_bps)
+        }
+        OrderingStrategy::FrontrunOnly => get_frontrun_only_revenue(
+            amount_in,
+            victim_in,
+            reserve_weth,
+            reserve_token,
+            fee_bps,
+            haircut_bps,
+        ),
+        OrderingStrategy::BackrunOnly => {
+            get_backrun_revenue(amount_in, victim_in, reserve_weth, reserve_token, fee_bps)
+        }
+    }
+}
+
+// Pick the most profitable ordering for one opportunity
+// note: ties go to the earlier candidate, so list the preferred ordering first
+//
+// Arguments:
+// * `candidates`: each ordering we could build paired with its expected profit
+// * `threshold`: net profit (in wei) that the picked ordering must exceed
+//
+// Returns:
+// Some((OrderingStrategy, SandwichProfit)): the ordering with the highest net profit
+// None: if no candidate exceeds `threshold`
+pub fn best_ordering(
+    candidates: &[(OrderingStrategy, SandwichProfit)],
+    threshold: U256,
+) -> Option<(OrderingStrategy, SandwichProfit)> {
+    let mut best: Option<(OrderingStrategy, SandwichProfit)> = None;
+    for (ordering, profit) in candidates {
+        if !profit.exceeds(threshold) {
+            continue;
+        }
+        let best_profit = best.map_or(I256::MIN, |(_, best)| best.net_profit);
+        if profit.net_profit > best_profit {
+            best = Some((*ordering, *profit));
+        }
+    }
+    best
+}
@@ -0,0 +1,95 @@
+use ethers::types::{I256, U256};
+
+use crate::{constants::COINBASE_PAYMENT_GAS_ESTIMATE, types::PaymentStrategy};
+
+/// Profit and loss of a sandwich after paying for gas
+#[derive(Debug, Clone, Copy)]
+pub struct SandwichProfit {
+    /// Weth gained before paying for gas
+    pub gross_revenue: U256,
+    /// Total paid for frontrun and backrun gas (base fee + priority fee)
+    pub gas_cost: U256,
+    /// Gross revenue minus gas cost (negative if sandwich loses money)
+    pub net_profit: I256,
+}
+
+impl SandwichProfit {
+    pub fn new(
+        gross_revenue: U256,
+        frontrun_gas: U256,
+        backrun_gas: U256,
+        base_fee: U256,
+        priority_fee: U256,
+        payment: PaymentStrategy,
+    ) -> Self {
+        let net_profit = compute_net_profit(
+            gross_revenue,
+            frontrun_gas,
+            backrun_gas,
+            base_fee,
+            priority_fee,
+            payment,
+        );
+
+        Self {
+            gross_revenue,
+            gas_cost: (I256::from_raw(gross_revenue) - net_profit).into_raw(),
+            net_profit,
+        }
+    }
+
+    /// True if net profit is strictly greater than `threshold`
+    pub fn exceeds(&self, threshold: U256) -> bool {
+        self.net_profit > I256::from_raw(threshold)
+    }
+}
+
+// Net out gas spent on the frontrun and backrun from a sandwich's revenue
+// note: gas is paid in eth so revenue must already be valued in weth
+//
+// Arguments:
+// * `gross_revenue`: weth gained before paying for gas
+// * `frontrun_gas`: gas used by frontrun tx
+// * `backrun_gas`: gas used by backrun tx (without a coinbase transfer)
+// * `base_fee`: base fee of the target block
+// * `priority_fee`: builder payment per unit of gas
+// * `payment`: how the builder is paid, a coinbase transfer pays the same amount as the
+//   priority fee would but costs the backrun extra gas at base fee
+//
+// Returns:
+// I256: signed net profit (negative if sandwich loses money after gas)
+pub fn compute_net_profit(
+    gross_revenue: U256,
+    frontrun_gas: U256,
+    backrun_gas: U256,
+    base_fee: U256,
+    priority_fee: U256,
+    payment: PaymentStrategy,
+) -> I256 {
+    let mut gas_cost = (frontrun_gas + backrun_gas) * (base_fee + priority_fee);
+    if payment == PaymentStrategy::CoinbaseTransfer {
+        gas_cost += U256::from(COINBASE_PAYMENT_GAS_ESTIMATE) * base_fee;
+    }
+    I256::from_raw(gross_revenue) - I256::from_raw(gas_cost)
+}
+
+// Order sandwich candidates (e.g. the pools touched by one victim) from most to least profitable
+//
+// Arguments:
+// * `candidates`: each candidate paired with its expected profit
+// * `threshold`: net profit (in wei) that a candidate must exceed to be kept
+//
+// Returns:
+// Vec<(T, SandwichProfit)>: candidates that beat `threshold`, most profitable first
+pub fn rank_by_net_profit<T>(
+    candidates: Vec<(T, SandwichProfit)>,
+    threshold: U256,
+) -> Vec<(T, SandwichProfit)> {
+    let mut ranked: Vec<(T, SandwichProfit)> = candidates
+        .into_iter()
+        .filter(|(_, profit)| profit.exceeds(threshold))
+        .collect();
+
+    ranked.sort_by(|(_, a), (_, b)| b.net_profit.cmp(&a.net_profit));
+    ranked
+}
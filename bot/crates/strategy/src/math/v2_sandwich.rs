@@ -0,0 +1,438 @@
+use ethers::types::U256;
+
+use super::{search_max_valid_input, search_optimal_input};
+
+// Find amount out from an amount in using the k=xy formula
+// note: mirrors `UniswapV2Library.getAmountOut` (with a configurable fee for v2 forks)
+//
+// Arguments:
+// * `amount_in`: amount of token in
+// * `reserve_in`: pool reserves of token in
+// * `reserve_out`: pool reserves of token out
+// * `fee_bps`: pool swap fee in basis points (30 for uniswap v2)
+//
+// Returns:
+// U256: amount out (zero if pool or input is empty)
+pub fn get_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, fee_bps: u32) -> U256 {
+    if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+        return U256::zero();
+    }
+
+    let amount_in_with_fee = amount_in * (10_000 - fee_bps);
+    let numerator = amount_in_with_fee * reserve_out;
+    let denominator = reserve_in * 10_000 + amount_in_with_fee;
+
+    numerator / denominator
+}
+
+// Find amount in needed for an amount out using the k=xy formula
+// note: mirrors `UniswapV2Library.getAmountIn` (rounds up)
+//
+// Arguments:
+// * `amount_out`: amount of token out
+// * `reserve_in`: pool reserves of token in
+// * `reserve_out`: pool reserves of token out
+// * `fee_bps`: pool swap fee in basis points (30 for uniswap v2)
+//
+// Returns:
+// Some(U256): amount in
+// None: if pool is empty or can't pay out `amount_out`
+pub fn get_amount_in(
+    amount_out: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee_bps: u32,
+) -> Option<U256> {
+    if reserve_in.is_zero() || amount_out >= reserve_out {
+        return None;
+    }
+
+    let numerator = reserve_in * amount_out * 10_000;
+    let denominator = (reserve_out - amount_out) * (10_000 - fee_bps);
+
+    Some(numerator / denominator + 1)
+}
+
+// Find the weth revenue of a `weth->token` sandwich
+// note: the math is symmetric, passing the token's reserves first sizes a `token->weth`
+// sandwich (revenue is then in the token)
+//
+// Arguments:
+// * `frontrun_in`: amount of weth used in frontrun
+// * `victim_in`: amount of weth the victim swaps
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+//
+// Returns:
+// U256: backrun weth output minus frontrun weth input (zero if sandwich makes a loss)
+pub fn get_sandwich_revenue(
+    frontrun_in: U256,
+    victim_in: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+) -> U256 {
+    // frontrun: weth -> token
+    let frontrun_out = get_amount_out(frontrun_in, reserve_weth, reserve_token, fee_bps);
+    let reserve_weth = reserve_weth + frontrun_in;
+    let reserve_token = reserve_token - frontrun_out;
+
+    // victim: weth -> token
+    let victim_out = get_amount_out(victim_in, reserve_weth, reserve_token, fee_bps);
+    let reserve_weth = reserve_weth + victim_in;
+    let reserve_token = reserve_token - victim_out;
+
+    // backrun: token -> weth
+    let backrun_out = get_amount_out(frontrun_out, reserve_token, reserve_weth, fee_bps);
+
+    backrun_out.saturating_sub(frontrun_in)
+}
+
+// Find the weth revenue of a sandwich around an exact output `weth->token` victim swap
+// note: victim buys a fixed amount of token, our frontrun raises the weth they pay for it
+//
+// Arguments:
+// * `frontrun_in`: amount of weth used in frontrun
+// * `victim_out`: exact amount of token the victim buys
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+//
+// Returns:
+// U256: backrun weth output minus frontrun weth input (zero if sandwich makes a loss or the
+// pool can't pay out the victim after our frontrun)
+pub fn get_exact_output_sandwich_revenue(
+    frontrun_in: U256,
+    victim_out: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+) -> U256 {
+    // frontrun: weth -> token
+    let frontrun_out = get_amount_out(frontrun_in, reserve_weth, reserve_token, fee_bps);
+    let reserve_weth = reserve_weth + frontrun_in;
+    let reserve_token = reserve_token - frontrun_out;
+
+    // victim: weth -> exact token
+    let victim_in = match get_amount_in(victim_out, reserve_weth, reserve_token, fee_bps) {
+        Some(victim_in) => victim_in,
+        None => return U256::zero(),
+    };
+    let reserve_weth = reserve_weth + victim_in;
+    let reserve_token = reserve_token - victim_out;
+
+    // backrun: token -> weth
+    let backrun_out = get_amount_out(frontrun_out, reserve_token, reserve_weth, fee_bps);
+
+    backrun_out.saturating_sub(frontrun_in)
+}
+
+// Find the weth revenue of one sandwich around several `weth->token` victims that swap on the
+// pool back to back (frontrun, every victim in order, then one backrun)
+//
+// Arguments:
+// * `frontrun_in`: amount of weth used in frontrun
+// * `victims_in`: amount of weth each victim swaps, in the order they swap
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+//
+// Returns:
+// U256: backrun weth output minus frontrun weth input (zero if sandwich makes a loss)
+pub fn get_merged_sandwich_revenue(
+    frontrun_in: U256,
+    victims_in: &[U256],
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+) -> U256 {
+    // frontrun: weth -> token
+    let frontrun_out = get_amount_out(frontrun_in, reserve_weth, reserve_token, fee_bps);
+    let mut reserve_weth = reserve_weth + frontrun_in;
+    let mut reserve_token = reserve_token - frontrun_out;
+
+    // victims: weth -> token, each on the reserves the previous one left
+    for &victim_in in victims_in {
+        let victim_out = get_amount_out(victim_in, reserve_weth, reserve_token, fee_bps);
+        reserve_weth += victim_in;
+        reserve_token -= victim_out;
+    }
+
+    // backrun: token -> weth
+    let backrun_out = get_amount_out(frontrun_out, reserve_token, reserve_weth, fee_bps);
+
+    backrun_out.saturating_sub(frontrun_in)
+}
+
+// Find the extra weth from selling token inventory right after a `weth->token` victim swap
+// instead of before it (the victim pushes the token's price up and a backrun sells into it)
+//
+// Arguments:
+// * `backrun_in`: amount of token we sell in the backrun
+// * `victim_in`: amount of weth the victim swaps
+// * `reserve_weth`: weth reserves of pool before victim
+// * `reserve_token`: token reserves of pool before victim
+// * `fee_bps`: pool swap fee in basis points
+//
+// Returns:
+// U256: backrun weth output minus the weth the same sale gets without the victim
+pub fn get_backrun_revenue(
+    backrun_in: U256,
+    victim_in: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+) -> U256 {
+    let without_victim = get_amount_out(backrun_in, reserve_token, reserve_weth, fee_bps);
+
+    // victim: weth -> token
+    let victim_out = get_amount_out(victim_in, reserve_weth, reserve_token, fee_bps);
+    let reserve_weth = reserve_weth + victim_in;
+    let reserve_token = reserve_token - victim_out;
+
+    // backrun: token -> weth
+    let backrun_out = get_amount_out(backrun_in, reserve_token, reserve_weth, fee_bps);
+
+    backrun_out.saturating_sub(without_victim)
+}
+
+// Binary search for the frontrun input that produces the highest sandwich revenue
+//
+// Arguments:
+// * `victim_in`: amount of weth the victim swaps
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+// * `upper_bound`: max weth we can use for frontrun
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+//
+// Returns:
+// (U256, U256): (optimal frontrun input, revenue at optimal input)
+pub fn find_optimal_input(
+    victim_in: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+    upper_bound: U256,
+    epsilon: U256,
+) -> (U256, U256) {
+    search_optimal_input(upper_bound, epsilon, |frontrun_in| {
+        get_sandwich_revenue(frontrun_in, victim_in, reserve_weth, reserve_token, fee_bps)
+    })
+}
+
+// Binary search for the frontrun input that produces the highest revenue around several victims
+// swapping back to back (see `get_merged_sandwich_revenue`)
+//
+// Arguments:
+// * `victims_in`: amount of weth each victim swaps, in the order they swap
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+// * `upper_bound`: max weth we can use for frontrun (see `max_frontrun_for_merged_slippage`)
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+//
+// Returns:
+// (U256, U256): (optimal frontrun input, revenue at optimal input)
+pub fn find_optimal_merged_input(
+    victims_in: &[U256],
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+    upper_bound: U256,
+    epsilon: U256,
+) -> (U256, U256) {
+    search_optimal_input(upper_bound, epsilon, |frontrun_in| {
+        get_merged_sandwich_revenue(
+            frontrun_in,
+            victims_in,
+            reserve_weth,
+            reserve_token,
+            fee_bps,
+        )
+    })
+}
+
+// Binary search for the frontrun input that produces the highest revenue around an exact output
+// victim swap
+//
+// Arguments:
+// * `victim_out`: exact amount of token the victim buys
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+// * `upper_bound`: max weth we can use for frontrun (see `max_frontrun_for_exact_output`)
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+//
+// Returns:
+// (U256, U256): (optimal frontrun input, revenue at optimal input)
+pub fn find_optimal_exact_output_input(
+    victim_out: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+    upper_bound: U256,
+    epsilon: U256,
+) -> (U256, U256) {
+    search_optimal_input(upper_bound, epsilon, |frontrun_in| {
+        get_exact_output_sandwich_revenue(
+            frontrun_in,
+            victim_out,
+            reserve_weth,
+            reserve_token,
+            fee_bps,
+        )
+    })
+}
+
+// Find the largest frontrun that still lets the victim's swap clear their slippage floor
+//
+// Arguments:
+// * `victim_in`: amount of weth the victim swaps
+// * `victim_out_min`: minimum amount of token the victim accepts
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+// * `upper_bound`: max weth we can use for frontrun
+//
+// Returns:
+// Some(U256): max frontrun input (capped at `upper_bound`)
+// None: if the victim's swap fails slippage even without a frontrun
+pub fn max_frontrun_for_slippage(
+    victim_in: U256,
+    victim_out_min: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+    upper_bound: U256,
+) -> Option<U256> {
+    search_max_valid_input(upper_bound, |frontrun_in| {
+        let frontrun_out = get_amount_out(frontrun_in, reserve_weth, reserve_token, fee_bps);
+        let victim_out = get_amount_out(
+            victim_in,
+            reserve_weth + frontrun_in,
+            reserve_token - frontrun_out,
+            fee_bps,
+        );
+
+        victim_out >= victim_out_min
+    })
+}
+
+// Find the largest frontrun that still lets every victim of a merged sandwich clear their
+// slippage floor (each victim swaps on the reserves the previous one left)
+//
+// Arguments:
+// * `victims`: `(amount_in, amount_out_min)` of each victim, in the order they swap
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+// * `upper_bound`: max weth we can use for frontrun
+//
+// Returns:
+// Some(U256): max frontrun input (capped at `upper_bound`)
+// None: if a victim's swap fails slippage even without a frontrun
+pub fn max_frontrun_for_merged_slippage(
+    victims: &[(U256, U256)],
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+    upper_bound: U256,
+) -> Option<U256> {
+    search_max_valid_input(upper_bound, |frontrun_in| {
+        let frontrun_out = get_amount_out(frontrun_in, reserve_weth, reserve_token, fee_bps);
+        let mut reserve_weth = reserve_weth + frontrun_in;
+        let mut reserve_token = reserve_token - frontrun_out;
+
+        victims.iter().all(|&(victim_in, victim_out_min)| {
+            let victim_out = get_amount_out(victim_in, reserve_weth, reserve_token, fee_bps);
+            reserve_weth += victim_in;
+            reserve_token -= victim_out;
+            victim_out >= victim_out_min
+        })
+    })
+}
+
+// Find the largest frontrun that still lets an exact output victim pay no more than their cap
+//
+// Arguments:
+// * `victim_out`: exact amount of token the victim buys
+// * `victim_in_max`: most weth the victim pays
+// * `reserve_weth`: weth reserves of pool before frontrun
+// * `reserve_token`: token reserves of pool before frontrun
+// * `fee_bps`: pool swap fee in basis points
+// * `upper_bound`: max weth we can use for frontrun
+//
+// Returns:
+// Some(U256): max frontrun input (capped at `upper_bound`)
+// None: if the victim's swap exceeds their cap even without a frontrun
+pub fn max_frontrun_for_exact_output(
+    victim_out: U256,
+    victim_in_max: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+    upper_bound: U256,
+) -> Option<U256> {
+    search_max_valid_input(upper_bound, |frontrun_in| {
+        let frontrun_out = get_amount_out(frontrun_in, reserve_weth, reserve_token, fee_bps);
+        let victim_in = get_amount_in(
+            victim_out,
+            reserve_weth + frontrun_in,
+            reserve_token - frontrun_out,
+            fee_bps,
+        );
+
+        matches!(victim_in, Some(victim_in) if victim_in <= victim_in_max)
+    })
+}
+
+// Find the largest frontrun on the first hop of a two hop `weth->token->token_out` victim swap
+// that still lets the victim's final output clear their slippage floor
+// note: our frontrun only moves the first pool, the second pool is chained using its reserves
+//
+// Arguments:
+// * `victim_in`: amount of weth the victim swaps
+// * `victim_out_min`: minimum amount of `token_out` the victim accepts
+// * `reserve_weth`: weth reserves of first pool before frontrun
+// * `reserve_token`: token reserves of first pool before frontrun
+// * `fee_bps`: first pool swap fee in basis points
+// * `next_reserve_in`: token reserves of second pool
+// * `next_reserve_out`: `token_out` reserves of second pool
+// * `next_fee_bps`: second pool swap fee in basis points
+// * `upper_bound`: max weth we can use for frontrun
+//
+// Returns:
+// Some(U256): max frontrun input (capped at `upper_bound`)
+// None: if the victim's swap fails slippage even without a frontrun
+#[allow(clippy::too_many_arguments)]
+pub fn max_frontrun_for_slippage_two_hop(
+    victim_in: U256,
+    victim_out_min: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+    next_reserve_in: U256,
+    next_reserve_out: U256,
+    next_fee_bps: u32,
+    upper_bound: U256,
+) -> Option<U256> {
+    search_max_valid_input(upper_bound, |frontrun_in| {
+        let frontrun_out = get_amount_out(frontrun_in, reserve_weth, reserve_token, fee_bps);
+        let first_hop_out = get_amount_out(
+            victim_in,
+            reserve_weth + frontrun_in,
+            reserve_token - frontrun_out,
+            fee_bps,
+        );
+        let victim_out = get_amount_out(
+            first_hop_out,
+            next_reserve_in,
+            next_reserve_out,
+            next_fee_bps,
+        );
+
+        victim_out >= victim_out_min
+    })
+}
@@ -0,0 +1,493 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use cfmms::pool::UniswapV3Pool;
+use ethers::types::{I256, U256};
+use uniswap_v3_math::{liquidity_math, swap_math, tick_math};
+
+use super::{search_max_valid_input, search_optimal_input};
+
+/// Initialized ticks of a v3 pool for the tick bitmap words that we have synced
+#[derive(Debug, Clone, Default)]
+pub struct TickMap {
+    /// Tick index -> liquidity net
+    pub ticks: BTreeMap<i32, i128>,
+    /// Lowest tick covered by the synced bitmap words
+    pub lowest_tick: i32,
+    /// Highest tick covered by the synced bitmap words
+    pub highest_tick: i32,
+}
+
+/// Result of simulating a swap against a v3 pool
+#[derive(Debug, Clone, Copy)]
+pub struct SwapResult {
+    /// Amount of input token consumed (including fees)
+    pub amount_in: U256,
+    /// Amount of output token received
+    pub amount_out: U256,
+    /// Pool price after swap
+    pub sqrt_price_x96: U256,
+    /// Pool tick after swap
+    pub tick: i32,
+    /// Pool's active liquidity after swap
+    pub liquidity: u128,
+    /// How many initialized ticks were crossed during the swap
+    pub ticks_crossed: u32,
+}
+
+impl SwapResult {
+    /// Update a pool's state to reflect the swap
+    pub fn apply(&self, pool: &mut UniswapV3Pool) {
+        pool.sqrt_price = self.sqrt_price_x96;
+        pool.tick = self.tick;
+        pool.liquidity = self.liquidity;
+    }
+}
+
+/// Frontrun, victim and backrun of a v3 sandwich, each simulated on the pool state that the swap
+/// before it left
+#[derive(Debug, Clone, Copy)]
+pub struct RoundTrip {
+    pub frontrun: SwapResult,
+    pub victim: SwapResult,
+    /// Sells the frontrun's output from the price the victim left, walking back down over the
+    /// ticks the victim crossed as well as the frontrun's
+    pub backrun: SwapResult,
+}
+
+// Simulate an exact input swap (see `simulate`)
+//
+// Arguments:
+// * `pool`: pool with current sqrtPriceX96, tick, and liquidity
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `zero_for_one`: true if swapping token0 for token1
+// * `amount_in`: amount of input token
+//
+// Returns:
+// Ok(SwapResult): amounts and pool state after the swap
+// Err: if math overflowed or swap moved price outside of the synced tick range
+pub fn simulate_swap(
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    zero_for_one: bool,
+    amount_in: U256,
+) -> Result<SwapResult> {
+    simulate(pool, tick_map, zero_for_one, I256::from_raw(amount_in))
+}
+
+// Simulate an exact output swap (see `simulate`)
+//
+// Arguments:
+// * `pool`: pool with current sqrtPriceX96, tick, and liquidity
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `zero_for_one`: true if swapping token0 for token1
+// * `amount_out`: exact amount of output token
+//
+// Returns:
+// Ok(SwapResult): amounts and pool state after the swap
+// Err: if math overflowed, swap moved price outside of the synced tick range or the pool can't
+// pay out `amount_out`
+pub fn simulate_exact_output_swap(
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    zero_for_one: bool,
+    amount_out: U256,
+) -> Result<SwapResult> {
+    simulate(pool, tick_map, zero_for_one, -I256::from_raw(amount_out))
+}
+
+// Simulate a swap, walking initialized ticks and updating active liquidity each time a tick
+// boundary is crossed (mirrors `UniswapV3Pool.swap`)
+//
+// Arguments:
+// * `pool`: pool with current sqrtPriceX96, tick, and liquidity
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `zero_for_one`: true if swapping token0 for token1
+// * `amount_specified`: exact input if positive, exact output if negative
+//
+// Returns:
+// Ok(SwapResult): amounts and pool state after the swap
+// Err: if math overflowed or swap moved price outside of the synced tick range
+fn simulate(
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    zero_for_one: bool,
+    amount_specified: I256,
+) -> Result<SwapResult> {
+    let exact_input = amount_specified > I256::zero();
+    let sqrt_price_limit = if zero_for_one {
+        tick_math::MIN_SQRT_RATIO + 1
+    } else {
+        tick_math::MAX_SQRT_RATIO - 1
+    };
+
+    let mut amount_remaining = amount_specified;
+    let mut amount_in = U256::zero();
+    let mut amount_out = U256::zero();
+    let mut sqrt_price_x96 = pool.sqrt_price;
+    let mut tick = pool.tick;
+    let mut liquidity = pool.liquidity;
+    let mut ticks_crossed = 0;
+
+    while amount_remaining != I256::zero() && sqrt_price_x96 != sqrt_price_limit {
+        // find next initialized tick in swap direction (stop at edge of synced range)
+        let next_initialized = if zero_for_one {
+            tick_map.ticks.range(..=tick).next_back()
+        } else {
+            tick_map.ticks.range(tick + 1..).next()
+        };
+
+        let (tick_next, liquidity_net) = match next_initialized {
+            Some((&tick_next, &liquidity_net)) => (tick_next, Some(liquidity_net)),
+            None if zero_for_one => (tick_map.lowest_tick, None),
+            None => (tick_map.highest_tick, None),
+        };
+        let tick_next = tick_next.clamp(tick_math::MIN_TICK, tick_math::MAX_TICK);
+
+        let sqrt_price_next = tick_math::get_sqrt_ratio_at_tick(tick_next)
+            .map_err(|e| anyhow!("[v3_sandwich: TickMath] {:?}", e))?;
+
+        let sqrt_price_target = if zero_for_one {
+            sqrt_price_next.max(sqrt_price_limit)
+        } else {
+            sqrt_price_next.min(sqrt_price_limit)
+        };
+
+        let (sqrt_price_after, step_in, step_out, step_fee) = swap_math::compute_swap_step(
+            sqrt_price_x96,
+            sqrt_price_target,
+            liquidity,
+            amount_remaining,
+            pool.fee,
+        )
+        .map_err(|e| anyhow!("[v3_sandwich: SwapMath] {:?}", e))?;
+
+        if exact_input {
+            amount_remaining -= I256::from_raw(step_in + step_fee);
+        } else {
+            amount_remaining += I256::from_raw(step_out);
+        }
+        amount_in += step_in + step_fee;
+        amount_out += step_out;
+        sqrt_price_x96 = sqrt_price_after;
+
+        if sqrt_price_x96 == sqrt_price_next {
+            // crossed a tick boundary mid swap, update active liquidity
+            let liquidity_net = match liquidity_net {
+                Some(net) => net,
+                None if amount_remaining != I256::zero() => {
+                    return Err(anyhow!(
+                        "[v3_sandwich: OutOfRange] swap moves price outside of synced ticks"
+                    ))
+                }
+                None => 0,
+            };
+
+            let liquidity_delta = if zero_for_one {
+                -liquidity_net
+            } else {
+                liquidity_net
+            };
+            liquidity = liquidity_math::add_delta(liquidity, liquidity_delta)
+                .map_err(|e| anyhow!("[v3_sandwich: LiquidityMath] {:?}", e))?;
+
+            tick = if zero_for_one {
+                tick_next - 1
+            } else {
+                tick_next
+            };
+            ticks_crossed += 1;
+        } else {
+            tick = tick_math::get_tick_at_sqrt_ratio(sqrt_price_x96)
+                .map_err(|e| anyhow!("[v3_sandwich: TickMath] {:?}", e))?;
+        }
+    }
+
+    // router reverts unless an exact output swap pays out in full
+    if !exact_input && amount_remaining != I256::zero() {
+        return Err(anyhow!(
+            "[v3_sandwich: InsufficientLiquidity] pool can't pay out exact output"
+        ));
+    }
+
+    Ok(SwapResult {
+        amount_in,
+        amount_out,
+        sqrt_price_x96,
+        tick,
+        liquidity,
+        ticks_crossed,
+    })
+}
+
+// Simulate a `weth->token` sandwich on a v3 pool swap by swap
+//
+// Arguments:
+// * `frontrun_in`: amount of weth used in frontrun
+// * `victim_in`: amount of weth the victim swaps
+// * `pool`: pool state before frontrun
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `weth_is_token0`: true if weth is the pool's token0
+//
+// Returns:
+// Ok(RoundTrip): each swap's amounts, pool state after it and ticks crossed
+// Err: if any of the swaps can't be simulated
+pub fn simulate_round_trip(
+    frontrun_in: U256,
+    victim_in: U256,
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    weth_is_token0: bool,
+) -> Result<RoundTrip> {
+    let mut pool = *pool;
+
+    // frontrun: weth -> token
+    let frontrun = simulate_swap(&pool, tick_map, weth_is_token0, frontrun_in)?;
+    frontrun.apply(&mut pool);
+
+    // victim: weth -> token
+    let victim = simulate_swap(&pool, tick_map, weth_is_token0, victim_in)?;
+    victim.apply(&mut pool);
+
+    // backrun: token -> weth
+    let backrun = simulate_swap(&pool, tick_map, !weth_is_token0, frontrun.amount_out)?;
+
+    Ok(RoundTrip {
+        frontrun,
+        victim,
+        backrun,
+    })
+}
+
+// Simulate a sandwich around an exact output `weth->token` victim swap on a v3 pool swap by swap
+//
+// Arguments:
+// * `frontrun_in`: amount of weth used in frontrun
+// * `victim_out`: exact amount of token the victim buys
+// * `victim_in_max`: most weth the victim pays
+// * `pool`: pool state before frontrun
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `weth_is_token0`: true if weth is the pool's token0
+//
+// Returns:
+// Ok(RoundTrip): each swap's amounts, pool state after it and ticks crossed
+// Err: if any of the swaps can't be simulated or the victim would pay more than `victim_in_max`
+// (their swap reverts)
+pub fn simulate_exact_output_round_trip(
+    frontrun_in: U256,
+    victim_out: U256,
+    victim_in_max: U256,
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    weth_is_token0: bool,
+) -> Result<RoundTrip> {
+    let mut pool = *pool;
+
+    // frontrun: weth -> token
+    let frontrun = simulate_swap(&pool, tick_map, weth_is_token0, frontrun_in)?;
+    frontrun.apply(&mut pool);
+
+    // victim: weth -> exact token
+    let victim = simulate_exact_output_swap(&pool, tick_map, weth_is_token0, victim_out)?;
+    if victim.amount_in > victim_in_max {
+        return Err(anyhow!(
+            "[v3_sandwich: Slippage] victim pays more than their max input"
+        ));
+    }
+    victim.apply(&mut pool);
+
+    // backrun: token -> weth
+    let backrun = simulate_swap(&pool, tick_map, !weth_is_token0, frontrun.amount_out)?;
+
+    Ok(RoundTrip {
+        frontrun,
+        victim,
+        backrun,
+    })
+}
+
+// Find the weth revenue of a `weth->token` sandwich on a v3 pool
+//
+// Arguments:
+// * `frontrun_in`: amount of weth used in frontrun
+// * `victim_in`: amount of weth the victim swaps
+// * `pool`: pool state before frontrun
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `weth_is_token0`: true if weth is the pool's token0
+//
+// Returns:
+// Ok(U256): backrun weth output minus frontrun weth input (zero if sandwich makes a loss)
+pub fn get_sandwich_revenue(
+    frontrun_in: U256,
+    victim_in: U256,
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    weth_is_token0: bool,
+) -> Result<U256> {
+    let round_trip = simulate_round_trip(frontrun_in, victim_in, pool, tick_map, weth_is_token0)?;
+    Ok(round_trip.backrun.amount_out.saturating_sub(frontrun_in))
+}
+
+// Find the weth revenue of a sandwich around an exact output `weth->token` victim swap on a v3
+// pool
+// note: victim buys a fixed amount of token, our frontrun raises the weth they pay for it
+//
+// Arguments:
+// * `frontrun_in`: amount of weth used in frontrun
+// * `victim_out`: exact amount of token the victim buys
+// * `victim_in_max`: most weth the victim pays
+// * `pool`: pool state before frontrun
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `weth_is_token0`: true if weth is the pool's token0
+//
+// Returns:
+// Ok(U256): backrun weth output minus frontrun weth input (zero if sandwich makes a loss)
+// Err: if the victim would pay more than `victim_in_max` (their swap reverts)
+pub fn get_exact_output_sandwich_revenue(
+    frontrun_in: U256,
+    victim_out: U256,
+    victim_in_max: U256,
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    weth_is_token0: bool,
+) -> Result<U256> {
+    let round_trip = simulate_exact_output_round_trip(
+        frontrun_in,
+        victim_out,
+        victim_in_max,
+        pool,
+        tick_map,
+        weth_is_token0,
+    )?;
+    Ok(round_trip.backrun.amount_out.saturating_sub(frontrun_in))
+}
+
+// Binary search for the frontrun input that produces the highest v3 sandwich revenue
+//
+// Arguments:
+// * `victim_in`: amount of weth the victim swaps
+// * `pool`: pool state before frontrun
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `weth_is_token0`: true if weth is the pool's token0
+// * `upper_bound`: max weth we can use for frontrun
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+//
+// Returns:
+// (U256, U256): (optimal frontrun input, revenue at optimal input)
+pub fn find_optimal_input(
+    victim_in: U256,
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    weth_is_token0: bool,
+    upper_bound: U256,
+    epsilon: U256,
+) -> (U256, U256) {
+    search_optimal_input(upper_bound, epsilon, |frontrun_in| {
+        // inputs that push price outside of synced ticks are treated as unprofitable
+        get_sandwich_revenue(frontrun_in, victim_in, pool, tick_map, weth_is_token0)
+            .unwrap_or_default()
+    })
+}
+
+// Binary search for the frontrun input that produces the highest v3 sandwich revenue around an
+// exact output victim swap
+//
+// Arguments:
+// * `victim_out`: exact amount of token the victim buys
+// * `victim_in_max`: most weth the victim pays
+// * `pool`: pool state before frontrun
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `weth_is_token0`: true if weth is the pool's token0
+// * `upper_bound`: max weth we can use for frontrun
+// * `epsilon`: stop searching once revenue improves by less than this between iterations
+//
+// Returns:
+// (U256, U256): (optimal frontrun input, revenue at optimal input)
+pub fn find_optimal_exact_output_input(
+    victim_out: U256,
+    victim_in_max: U256,
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    weth_is_token0: bool,
+    upper_bound: U256,
+    epsilon: U256,
+) -> (U256, U256) {
+    search_optimal_input(upper_bound, epsilon, |frontrun_in| {
+        // frontruns that push the victim past their max input are treated as unprofitable
+        get_exact_output_sandwich_revenue(
+            frontrun_in,
+            victim_out,
+            victim_in_max,
+            pool,
+            tick_map,
+            weth_is_token0,
+        )
+        .unwrap_or_default()
+    })
+}
+
+// Find the largest frontrun that still lets the victim's swap clear their slippage floor
+//
+// Arguments:
+// * `victim_in`: amount of weth the victim swaps
+// * `victim_out_min`: minimum amount of token the victim accepts
+// * `pool`: pool state before frontrun
+// * `tick_map`: initialized ticks around the pool's current tick
+// * `weth_is_token0`: true if weth is the pool's token0
+// * `upper_bound`: max weth we can use for frontrun
+//
+// Returns:
+// Some(U256): max frontrun input (capped at `upper_bound`)
+// None: if the victim's swap fails slippage even without a frontrun
+pub fn max_frontrun_for_slippage(
+    victim_in: U256,
+    victim_out_min: U256,
+    pool: &UniswapV3Pool,
+    tick_map: &TickMap,
+    weth_is_token0: bool,
+    upper_bound: U256,
+) -> Option<U256> {
+    search_max_valid_input(upper_bound, |frontrun_in| {
+        let mut pool = *pool;
+
+        let victim_out = simulate_swap(&pool, tick_map, weth_is_token0, frontrun_in)
+            .and_then(|frontrun| {
+                frontrun.apply(&mut pool);
+                simulate_swap(&pool, tick_map, weth_is_token0, victim_in)
+            })
+            .map(|victim| victim.amount_out);
+
+        // inputs that push price outside of synced ticks are treated as invalid
+        matches!(victim_out, Ok(out) if out >= victim_out_min)
+    })
+}
+
+// Check if a victim's swap moves a v3 pool's price further than our frontrun and backrun pay
+// in fees between them, a sandwich only captures the victim's move on our position
+// note: every tick is a bip of price, and `fee` is in hundredths of a bip (3000 = 0.3%)
+//
+// Arguments:
+// * `tick_before`: pool's tick before the victim's swap
+// * `tick_after`: pool's tick after the victim's swap
+// * `fee`: pool's fee tier
+//
+// Returns:
+// bool: true if the victim moves price by at least twice the pool's fee
+pub fn moves_past_round_trip_fee(tick_before: i32, tick_after: i32, fee: u32) -> bool {
+    let ticks_moved = tick_after.abs_diff(tick_before) as u64;
+    ticks_moved * 100 >= 2 * fee as u64
+}
+
+// Split a v3 pool's `slot0.feeProtocol` into the protocol's cut of the swap fee on each side
+// note: the cut comes out of the lp fee on uniswap's own pools, swaps pay the tier either way
+//
+// Arguments:
+// * `fee_protocol`: packed setting, the token0 side in the low 4 bits and token1 in the high 4
+//
+// Returns:
+// (Option<u8>, Option<u8>): protocol takes 1/N of the fee on swaps paying token0 and token1 in,
+// `None` if it is off for that side
+pub fn protocol_fee_denominators(fee_protocol: u8) -> (Option<u8>, Option<u8>) {
+    let side = |denominator: u8| (denominator != 0).then_some(denominator);
+    (side(fee_protocol & 0x0f), side(fee_protocol >> 4))
+}
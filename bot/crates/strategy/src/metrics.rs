@@ -0,0 +1,549 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Once,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use colored::Colorize;
+use ethers::{
+    prelude::Lazy,
+    types::{Address, TxHash},
+};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use log::{debug, error, info};
+use prometheus::{
+    register_gauge, register_gauge_vec, register_histogram, register_int_counter,
+    register_int_counter_vec, register_int_gauge, register_int_gauge_vec, Encoder, Gauge, GaugeVec,
+    Histogram, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+
+use crate::{
+    log_error,
+    managers::funnel::{BlockFunnel, FunnelStage},
+    startup_info_log,
+    types::{PoolManagerMetrics, RejectReason},
+};
+
+pub static TXS_SEEN: Lazy<IntCounter> =
+    Lazy::new(|| register_int_counter!("sando_txs_seen", "Pending txs processed").unwrap());
+
+pub static TXS_WITH_TOUCHED_POOLS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_txs_with_touched_pools",
+        "Pending txs that touched a sandwichable pool"
+    )
+    .unwrap()
+});
+
+pub static SANDWICHES_FOUND: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("sando_sandwiches_found", "Profitable sandwiches found").unwrap()
+});
+
+pub static ACCESS_LIST_GAS_SAVED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_access_list_gas_saved",
+        "Gas the access lists on our simulated frontruns and backruns saved them"
+    )
+    .unwrap()
+});
+
+pub static POOLS_SCREENED_OUT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_pools_screened_out",
+        "Touched pools skipped before sizing because no sandwich could pay for its gas"
+    )
+    .unwrap()
+});
+
+pub static REBASING_TOKENS_FLAGGED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_rebasing_tokens_flagged",
+        "Tokens excluded after their pools synced reserves without swaps"
+    )
+    .unwrap()
+});
+
+pub static VICTIM_TIMEOUTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_victim_timeouts",
+        "Victims abandoned after tracing or evaluating them took longer than victim_timeout_ms"
+    )
+    .unwrap()
+});
+
+pub static SIM_GAS_CAP_ABORTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_sim_gas_cap_aborts",
+        "Simulations aborted after a pending tx burned all of max_sim_gas"
+    )
+    .unwrap()
+});
+
+pub static REJECTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "sando_rejections",
+        "Pending txs or touched pools that weren't sandwiched, by reason",
+        &["reason"]
+    )
+    .unwrap()
+});
+
+pub static POOL_SIMULATIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "sando_pool_simulations",
+        "Sandwiches simulated in revm, by pool",
+        &["pool"]
+    )
+    .unwrap()
+});
+
+pub static POOL_DIVERGENCES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "sando_pool_divergences",
+        "Simulations whose revenue disagreed with analytic revenue beyond tolerance, by pool",
+        &["pool"]
+    )
+    .unwrap()
+});
+
+pub static POOLS_DISABLED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_pools_disabled",
+        "Pools no longer sandwiched after crossing max_pool_divergence_rate"
+    )
+    .unwrap()
+});
+
+pub static TRACE_FALLBACKS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_trace_fallbacks",
+        "Victims whose state diffs were rebuilt from storage reads after tracing failed"
+    )
+    .unwrap()
+});
+
+pub static SANDWICHES_OUTRANKED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_sandwiches_outranked",
+        "Held sandwiches left out of their block's top bundles"
+    )
+    .unwrap()
+});
+
+pub static BUNDLES_SUBMITTED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("sando_bundles_submitted", "Bundles sent to the executor").unwrap()
+});
+
+pub static PRIVATE_TXS_SUBMITTED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_private_txs_submitted",
+        "Backrun only private txs sent to the executor"
+    )
+    .unwrap()
+});
+
+pub static SIM_CACHE_HITS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_sim_cache_hits",
+        "Sandwich simulations reused from an earlier victim that left the pool in the same state"
+    )
+    .unwrap()
+});
+
+pub static CODE_CACHE_HITS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_code_cache_hits",
+        "Forked accounts loaded from the code cache instead of the provider"
+    )
+    .unwrap()
+});
+
+pub static CODE_CACHE_MISSES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_code_cache_misses",
+        "Forked accounts the code cache didn't have, fetched from the provider"
+    )
+    .unwrap()
+});
+
+pub static CODE_CACHE_CONTRACTS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "sando_code_cache_contracts",
+        "Contracts whose code simulations keep between blocks"
+    )
+    .unwrap()
+});
+
+pub static POOL_UPDATES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_pool_updates",
+        "Monitored pools that moved more than the pool update threshold in a block"
+    )
+    .unwrap()
+});
+
+pub static POOLS_REFRESHED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_pools_refreshed",
+        "Hot pools whose state was re-read with a multicall at the end of a block"
+    )
+    .unwrap()
+});
+
+pub static POOL_STATE_DRIFTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_pool_state_drifts",
+        "Refreshed pools whose cached state had drifted from the chain"
+    )
+    .unwrap()
+});
+
+pub static LOSS_GUARD_TRIPS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_loss_guard_trips",
+        "Times submissions were halted for losing more than the max window loss"
+    )
+    .unwrap()
+});
+
+pub static DEADLINES_MISSED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_deadlines_missed",
+        "Victims dropped for having less than the min time budget left before their target block"
+    )
+    .unwrap()
+});
+
+pub static STALE_STATE_ABORTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_stale_state_aborts",
+        "Submissions dropped because the tip passed the block their state was sourced from"
+    )
+    .unwrap()
+});
+
+pub static BUNDLES_MERGED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_bundles_merged",
+        "Bundles replaced by one sandwich around their victims and a later victim on the pool"
+    )
+    .unwrap()
+});
+
+pub static BUNDLES_REPLACED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_bundles_replaced",
+        "Bundles superseded by a more profitable bundle on the same pool"
+    )
+    .unwrap()
+});
+
+pub static BUNDLES_LANDED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("sando_bundles_landed", "Bundles included onchain").unwrap()
+});
+
+pub static BUNDLES_LANDED_FAILED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_bundles_landed_failed",
+        "Bundles included onchain with one of our txs reverted"
+    )
+    .unwrap()
+});
+
+pub static INCLUSION_LATENCY: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "sando_inclusion_latency_seconds",
+        "Time from submitting a bundle until the block it landed in was processed",
+        vec![1.0, 2.0, 4.0, 6.0, 8.0, 10.0, 12.0, 15.0, 20.0, 30.0]
+    )
+    .unwrap()
+});
+
+pub static BUNDLES_REORGED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "sando_bundles_reorged",
+        "Landed bundles whose block was reorged out"
+    )
+    .unwrap()
+});
+
+pub static GROSS_PROFIT: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "sando_gross_profit_eth",
+        "Cumulative revenue of landed bundles (eth)"
+    )
+    .unwrap()
+});
+
+pub static NET_PROFIT: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "sando_net_profit_eth",
+        "Cumulative profit of landed bundles after gas and bribes (eth)"
+    )
+    .unwrap()
+});
+
+pub static ONCHAIN_PROFIT: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "sando_onchain_profit_eth",
+        "Cumulative change of our balances across blocks that bundles landed in (eth)"
+    )
+    .unwrap()
+});
+
+pub static ONCHAIN_PROFIT_ESTIMATE: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "sando_onchain_profit_estimate_eth",
+        "Cumulative estimated net profit of the blocks in sando_onchain_profit_eth (eth)"
+    )
+    .unwrap()
+});
+
+pub static SANDO_WETH_INVENTORY: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "sando_weth_inventory",
+        "Weth held by each sando contract when last checked (in eth)",
+        &["sando"]
+    )
+    .unwrap()
+});
+
+pub static SEARCHER_ETH_BALANCE: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "sando_searcher_eth_balance",
+        "Eth the searcher has left for gas when last checked"
+    )
+    .unwrap()
+});
+
+pub static SYNCED_POOLS: Lazy<IntGauge> =
+    Lazy::new(|| register_int_gauge!("sando_synced_pools", "Pools currently monitored").unwrap());
+
+pub static MONITORED_POOLS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "sando_monitored_pools",
+        "Monitored pools by the factory of the dex they were discovered from",
+        &["factory", "kind"]
+    )
+    .unwrap()
+});
+
+pub static MONITORED_POOLS_BY_ACTIVITY: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "sando_monitored_pools_by_activity",
+        "Monitored pools that are hot, active or cold as of the last synced block",
+        &["activity"]
+    )
+    .unwrap()
+});
+
+pub static POOL_MANAGER_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "sando_pool_manager_bytes",
+        "Estimated bytes held by the monitored pools and the maps kept per pool"
+    )
+    .unwrap()
+});
+
+pub static DECISION_MS_BEFORE_BLOCK: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "sando_decision_ms_before_block",
+        "Milliseconds left before the target block's timestamp when the last bundle was decided"
+    )
+    .unwrap()
+});
+
+pub static BLOCK_FUNNEL: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "sando_block_funnel",
+        "Pending txs (bundles once submitted) reaching each pipeline stage in the last block",
+        &["stage"]
+    )
+    .unwrap()
+});
+
+/// Set once `sync_state` completes (cleared while resyncing)
+static SYNC_COMPLETE: AtomicBool = AtomicBool::new(false);
+
+/// Unix time (seconds) that the last block was processed at, zero if none has been seen
+static LAST_BLOCK_AT: AtomicU64 = AtomicU64::new(0);
+
+/// True while the rpc circuit breaker is open and events are skipped
+static RPC_BREAKER_OPEN: AtomicBool = AtomicBool::new(false);
+
+/// Makes sure the server is only started once (state is resynced after a reconnect)
+static METRICS_SERVER: Once = Once::new();
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Record whether the bot's state is synced
+pub fn set_sync_complete(complete: bool) {
+    SYNC_COMPLETE.store(complete, Ordering::Relaxed);
+}
+
+/// Record whether the rpc circuit breaker is pausing the bot (reported by `/health`)
+pub fn set_rpc_breaker_open(open: bool) {
+    RPC_BREAKER_OPEN.store(open, Ordering::Relaxed);
+}
+
+/// Count a victim (or one of the pools it touches) that wasn't sandwiched, also logged at debug
+/// level under the `strategy::rejections` target
+pub fn record_rejection(victim: TxHash, reason: RejectReason) {
+    REJECTIONS.with_label_values(&[reason.label()]).inc();
+    debug!(target: "strategy::rejections", "{:?} rejected: {}", victim, reason.label());
+}
+
+/// Count a simulated sandwich on `pool`, `diverged` if it disagreed with our math
+pub fn record_pool_simulation(pool: Address, diverged: bool) {
+    let label = format!("{:?}", pool);
+    POOL_SIMULATIONS.with_label_values(&[&label]).inc();
+    if diverged {
+        POOL_DIVERGENCES.with_label_values(&[&label]).inc();
+    }
+}
+
+/// Count a block's code cache lookups and publish how many contracts it holds
+pub fn record_code_cache(hits: u64, misses: u64, contracts: usize) {
+    CODE_CACHE_HITS.inc_by(hits);
+    CODE_CACHE_MISSES.inc_by(misses);
+    CODE_CACHE_CONTRACTS.set(contracts as i64);
+}
+
+/// Publish the size of the monitored pool set
+pub fn record_pool_manager(snapshot: &PoolManagerMetrics) {
+    SYNCED_POOLS.set(snapshot.total_pools() as i64);
+    for (dex, count) in &snapshot.pools_by_dex {
+        let factory = format!("{:?}", dex.factory);
+        let kind = format!("{:?}", dex.kind);
+        MONITORED_POOLS
+            .with_label_values(&[&factory, &kind])
+            .set(*count as i64);
+    }
+    MONITORED_POOLS
+        .with_label_values(&["unknown", "unknown"])
+        .set(snapshot.unattributed_pools as i64);
+    if let Some(pools_by_activity) = snapshot.pools_by_activity {
+        for (activity, count) in pools_by_activity {
+            MONITORED_POOLS_BY_ACTIVITY
+                .with_label_values(&[activity.label()])
+                .set(count as i64);
+        }
+    }
+    POOL_MANAGER_BYTES.set(snapshot.estimated_bytes as i64);
+}
+
+/// Publish the funnel of the block that was just closed out
+pub fn record_block_funnel(funnel: &BlockFunnel) {
+    for stage in FunnelStage::ALL {
+        BLOCK_FUNNEL
+            .with_label_values(&[stage.label()])
+            .set(funnel.get(stage) as i64);
+    }
+}
+
+/// Record that a block was just processed
+pub fn record_block_processed() {
+    LAST_BLOCK_AT.store(unix_now(), Ordering::Relaxed);
+}
+
+/// Serve all registered metrics at `http://0.0.0.0:<port>/metrics` and liveness at `/health` on
+/// a background task, `/health` returns 503 once no block was processed for `stale_after_secs`
+pub fn spawn_metrics_server(port: u16, stale_after_secs: u64) {
+    METRICS_SERVER.call_once(|| {
+        tokio::spawn(async move {
+            let addr = SocketAddr::from(([0, 0, 0, 0], port));
+            let make_service = make_service_fn(move |_| async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    serve_request(request, stale_after_secs)
+                }))
+            });
+
+            startup_info_log!("metrics served on port {}", port);
+
+            if let Err(e) = Server::bind(&addr).serve(make_service).await {
+                log_error!("Metrics server stopped: {}", e);
+            }
+        });
+    });
+}
+
+/// Write the current value of every registered metric to `path` (prometheus text format)
+pub fn write_snapshot(path: &str) -> anyhow::Result<()> {
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    std::fs::write(path, buffer)?;
+    Ok(())
+}
+
+async fn serve_request(
+    request: Request<Body>,
+    stale_after_secs: u64,
+) -> Result<Response<Body>, Infallible> {
+    match request.uri().path() {
+        "/metrics" => Ok(serve_metrics()),
+        "/health" => Ok(serve_health(stale_after_secs)),
+        _ => {
+            let mut not_found = Response::new(Body::empty());
+            *not_found.status_mut() = StatusCode::NOT_FOUND;
+            Ok(not_found)
+        }
+    }
+}
+
+/// Report sync status, seconds since the last processed block, synced pool count and whether
+/// the rpc circuit breaker is open
+fn serve_health(stale_after_secs: u64) -> Response<Body> {
+    let synced = SYNC_COMPLETE.load(Ordering::Relaxed);
+    let last_block_at = LAST_BLOCK_AT.load(Ordering::Relaxed);
+
+    // no block yet means the subscription never delivered one
+    let secs_since_last_block =
+        (last_block_at != 0).then(|| unix_now().saturating_sub(last_block_at));
+    let stalled = match secs_since_last_block {
+        Some(secs) => secs > stale_after_secs,
+        None => true,
+    };
+
+    let body = serde_json::json!({
+        "synced": synced,
+        "secs_since_last_block": secs_since_last_block,
+        "synced_pools": SYNCED_POOLS.get(),
+        "rpc_breaker_open": RPC_BREAKER_OPEN.load(Ordering::Relaxed),
+    });
+
+    let mut response = Response::new(Body::from(body.to_string()));
+    if !synced || stalled {
+        *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    }
+    if let Ok(content_type) = "application/json".parse() {
+        response.headers_mut().insert(CONTENT_TYPE, content_type);
+    }
+    response
+}
+
+fn serve_metrics() -> Response<Body> {
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        log_error!("Failed to encode metrics: {}", e);
+    }
+
+    let mut response = Response::new(Body::from(buffer));
+    if let Ok(content_type) = encoder.format_type().parse() {
+        response.headers_mut().insert(CONTENT_TYPE, content_type);
+    }
+    response
+}
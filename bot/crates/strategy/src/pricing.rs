@@ -0,0 +1,134 @@
+use cfmms::pool::Pool;
+use ethers::types::{Address, U256};
+use uniswap_v3_math::full_math::mul_div;
+
+use crate::helpers::u256_to_f64;
+
+/// Depth of a pool measured in weth (or the chain's wrapped native token)
+pub trait WethLiquidity {
+    /// Weth backing the pool's current price (v2: weth reserve, v3: virtual weth reserve of the
+    /// liquidity active at the current tick)
+    fn weth_liquidity(&self, weth: Address) -> U256;
+}
+
+impl WethLiquidity for Pool {
+    fn weth_liquidity(&self, weth: Address) -> U256 {
+        match self {
+            // token_a is the pool's token0
+            Pool::UniswapV2(p) if p.token_a == weth => p.reserve_0.into(),
+            Pool::UniswapV2(p) => p.reserve_1.into(),
+            Pool::UniswapV3(p) => {
+                if p.sqrt_price.is_zero() {
+                    return U256::zero();
+                }
+
+                // virtual reserves: token0 = L / sqrtP, token1 = L * sqrtP (sqrtP is Q64.96)
+                let liquidity = U256::from(p.liquidity);
+                let q96 = U256::one() << 96;
+                let weth_reserve = if p.token_a == weth {
+                    mul_div(liquidity, q96, p.sqrt_price)
+                } else {
+                    mul_div(liquidity, p.sqrt_price, q96)
+                };
+                weth_reserve.unwrap_or(U256::MAX)
+            }
+        }
+    }
+}
+
+// Spot price of a pool, what one unit of token0 trades for in token1 before fees and slippage
+// note: in raw units (not adjusted for decimals), so it can scale raw amounts directly
+//
+// Arguments:
+// * `pool`: pool to price (token_a is the pool's token0)
+//
+// Returns:
+// Some(f64): token1 per token0
+// None: if the pool is empty (v2 reserve of zero), uninitialized (v3 price of zero) or its
+// price is out of f64 range
+pub fn spot_price(pool: &Pool) -> Option<f64> {
+    match pool {
+        Pool::UniswapV2(p) => {
+            if p.reserve_0 == 0 || p.reserve_1 == 0 {
+                return None;
+            }
+            Some(p.reserve_1 as f64 / p.reserve_0 as f64)
+        }
+        Pool::UniswapV3(p) => {
+            if p.sqrt_price.is_zero() {
+                return None;
+            }
+            // sqrtP is Q64.96
+            let sqrt_price = u256_to_f64(p.sqrt_price) / 2f64.powi(96);
+            let price = sqrt_price * sqrt_price;
+            if price == 0.0 || !price.is_finite() {
+                return None;
+            }
+            Some(price)
+        }
+    }
+}
+
+// Value a token amount in weth using the most liquid weth pool for that token
+// note: uses the pool's spot price, fine for comparing profit against gas but not for sizing
+//
+// Arguments:
+// * `token`: token that `amount` is denominated in
+// * `amount`: amount of `token`
+// * `pools`: pools to pick the pricing pool from
+// * `weth`: address of weth (or the chain's wrapped native token)
+//
+// Returns:
+// Some(U256): value of `amount` in weth
+// None: if no pool pairs `token` with weth
+pub fn quote_in_weth<I: IntoIterator<Item = Pool>>(
+    token: Address,
+    amount: U256,
+    pools: I,
+    weth: Address,
+) -> Option<U256> {
+    if token == weth {
+        return Some(amount);
+    }
+
+    let pool = pools
+        .into_iter()
+        .filter(|pool| {
+            let (token_a, token_b) = match pool {
+                Pool::UniswapV2(p) => (p.token_a, p.token_b),
+                Pool::UniswapV3(p) => (p.token_a, p.token_b),
+            };
+            (token_a == token && token_b == weth) || (token_a == weth && token_b == token)
+        })
+        .max_by_key(|pool| pool.weth_liquidity(weth))?;
+
+    match pool {
+        Pool::UniswapV2(p) => {
+            let (reserve_weth, reserve_token) = if p.token_a == weth {
+                (p.reserve_0, p.reserve_1)
+            } else {
+                (p.reserve_1, p.reserve_0)
+            };
+
+            if reserve_token == 0 {
+                return None;
+            }
+            mul_div(amount, reserve_weth.into(), reserve_token.into()).ok()
+        }
+        Pool::UniswapV3(p) => {
+            if p.sqrt_price.is_zero() {
+                return None;
+            }
+
+            // price of token0 in token1 is sqrtP^2 / 2^192
+            let q96 = U256::one() << 96;
+            if p.token_a == weth {
+                let scaled = mul_div(amount, q96, p.sqrt_price).ok()?;
+                mul_div(scaled, q96, p.sqrt_price).ok()
+            } else {
+                let scaled = mul_div(amount, p.sqrt_price, q96).ok()?;
+                mul_div(scaled, p.sqrt_price, q96).ok()
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::Mutex;
+
+use ethers::{
+    core::rand::{rngs::StdRng, Rng, SeedableRng},
+    types::Address,
+};
+
+/// Source of every random choice the bot makes, seeded so a run's choices can be replayed
+/// (pin `rng_seed` in tests and audits, the seed is logged at startup either way)
+pub struct DecisionRng {
+    seed: u64,
+    rng: Mutex<StdRng>,
+}
+
+impl DecisionRng {
+    /// Seed with `seed`, or with a random one if `None`
+    pub fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| StdRng::from_entropy().gen());
+        Self {
+            seed,
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    /// Seed that reproduces this run's choices
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Uniformly pick an index below `len` (0 if `len` is 0)
+    pub fn pick(&self, len: usize) -> usize {
+        if len == 0 {
+            return 0;
+        }
+        self.rng.lock().unwrap().gen_range(0..len)
+    }
+
+    /// A fresh address nobody holds (e.g. a recipient whose balance starts at zero)
+    pub fn address(&self) -> Address {
+        Address::from(self.rng.lock().unwrap().gen::<[u8; 20]>())
+    }
+}
@@ -0,0 +1,236 @@
+use std::{env, path::PathBuf, str::FromStr};
+
+#[cfg(any(feature = "ledger", feature = "aws"))]
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+#[cfg(feature = "aws")]
+use ethers::signers::{AwsSigner, AwsSignerError};
+#[cfg(feature = "ledger")]
+use ethers::signers::{HDPath, Ledger, LedgerError};
+use ethers::{
+    signers::{LocalWallet, Signer, WalletError},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Address, Signature,
+    },
+};
+
+/// Where the searcher's tx signing key is loaded from (`SEARCHER_SIGNER`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignerKind {
+    /// Hex private key in `SEARCHER_PRIVATE_KEY`
+    PrivateKey,
+    /// Encrypted json keystore at `SEARCHER_KEYSTORE`, unlocked with
+    /// `SEARCHER_KEYSTORE_PASSWORD`
+    Keystore,
+    /// Ledger account `SEARCHER_LEDGER_INDEX` (ledger live derivation path)
+    Ledger,
+    /// AWS KMS key `SEARCHER_KMS_KEY_ID`, region and credentials come from the usual AWS env
+    AwsKms,
+}
+
+impl FromStr for SignerKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "key" | "private_key" => Ok(SignerKind::PrivateKey),
+            "keystore" => Ok(SignerKind::Keystore),
+            "ledger" => Ok(SignerKind::Ledger),
+            "aws" | "kms" => Ok(SignerKind::AwsKms),
+            _ => Err(anyhow!("Unknown signer kind: {}", s)),
+        }
+    }
+}
+
+/// How to build the searcher's tx signer, read from env so keys stay out of config files
+#[derive(Debug, Clone)]
+pub enum SignerConfig {
+    PrivateKey(String),
+    Keystore { path: PathBuf, password: String },
+    Ledger { index: usize },
+    AwsKms { key_id: String },
+}
+
+impl SignerConfig {
+    /// Read the env vars of the given signer kind
+    pub fn from_env(kind: SignerKind) -> Result<Self> {
+        let get_env = |var| {
+            env::var(var).map_err(|_| anyhow!("Required environment variable \"{}\" not set", var))
+        };
+
+        match kind {
+            SignerKind::PrivateKey => {
+                Ok(SignerConfig::PrivateKey(get_env("SEARCHER_PRIVATE_KEY")?))
+            }
+            SignerKind::Keystore => Ok(SignerConfig::Keystore {
+                path: get_env("SEARCHER_KEYSTORE")?.into(),
+                password: get_env("SEARCHER_KEYSTORE_PASSWORD")?,
+            }),
+            SignerKind::Ledger => {
+                let index = match env::var("SEARCHER_LEDGER_INDEX") {
+                    Ok(index) => index.trim().parse().map_err(|_| {
+                        anyhow!("Failed to parse \"SEARCHER_LEDGER_INDEX\" into usize")
+                    })?,
+                    Err(_) => 0,
+                };
+                Ok(SignerConfig::Ledger { index })
+            }
+            SignerKind::AwsKms => Ok(SignerConfig::AwsKms {
+                key_id: get_env("SEARCHER_KMS_KEY_ID")?,
+            }),
+        }
+    }
+
+    /// Load or connect to the signer, hardware and kms signers sign for `chain_id` only
+    pub async fn build(self, chain_id: u64) -> Result<SearcherSigner> {
+        match self {
+            SignerConfig::PrivateKey(key) => Ok(SearcherSigner::Local(
+                key.trim()
+                    .parse::<LocalWallet>()
+                    .map_err(|_| anyhow!("Failed to parse searcher private key"))?
+                    .with_chain_id(chain_id),
+            )),
+            SignerConfig::Keystore { path, password } => {
+                let wallet = LocalWallet::decrypt_keystore(&path, password)
+                    .map_err(|e| anyhow!("Failed to decrypt keystore {}: {}", path.display(), e))?;
+                Ok(SearcherSigner::Local(wallet.with_chain_id(chain_id)))
+            }
+            #[cfg(feature = "ledger")]
+            SignerConfig::Ledger { index } => {
+                let ledger = Ledger::new(HDPath::LedgerLive(index), chain_id)
+                    .await
+                    .map_err(|e| anyhow!("Failed to connect to ledger: {}", e))?;
+                Ok(SearcherSigner::Ledger(Arc::new(ledger)))
+            }
+            #[cfg(not(feature = "ledger"))]
+            SignerConfig::Ledger { .. } => Err(anyhow!(
+                "Ledger signer needs the bot built with the `ledger` feature"
+            )),
+            #[cfg(feature = "aws")]
+            SignerConfig::AwsKms { key_id } => {
+                // the signer borrows its client, which lives as long as the bot anyway
+                let kms = Box::leak(Box::new(rusoto_kms::KmsClient::new(
+                    rusoto_core::Region::default(),
+                )));
+                let signer = AwsSigner::new(kms, key_id, chain_id)
+                    .await
+                    .map_err(|e| anyhow!("Failed to load kms key: {}", e))?;
+                Ok(SearcherSigner::AwsKms(Arc::new(signer)))
+            }
+            #[cfg(not(feature = "aws"))]
+            SignerConfig::AwsKms { .. } => Err(anyhow!(
+                "AWS KMS signer needs the bot built with the `aws` feature"
+            )),
+        }
+    }
+}
+
+/// Signs the searcher's frontrun and backrun txs (the flashbots auth key is kept separate)
+#[derive(Debug, Clone)]
+pub enum SearcherSigner {
+    Local(LocalWallet),
+    #[cfg(feature = "ledger")]
+    Ledger(Arc<Ledger>),
+    #[cfg(feature = "aws")]
+    AwsKms(Arc<AwsSigner<'static>>),
+}
+
+/// Parses a hex private key into a local signer
+impl FromStr for SearcherSigner {
+    type Err = WalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(SearcherSigner::Local(s.parse()?))
+    }
+}
+
+impl From<LocalWallet> for SearcherSigner {
+    fn from(wallet: LocalWallet) -> Self {
+        SearcherSigner::Local(wallet)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearcherSignerError {
+    #[error(transparent)]
+    Local(#[from] WalletError),
+    #[cfg(feature = "ledger")]
+    #[error(transparent)]
+    Ledger(#[from] LedgerError),
+    #[cfg(feature = "aws")]
+    #[error(transparent)]
+    AwsKms(#[from] AwsSignerError),
+}
+
+#[async_trait]
+impl Signer for SearcherSigner {
+    type Error = SearcherSignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            SearcherSigner::Local(wallet) => Ok(wallet.sign_message(message).await?),
+            #[cfg(feature = "ledger")]
+            SearcherSigner::Ledger(ledger) => Ok(ledger.sign_message(message).await?),
+            #[cfg(feature = "aws")]
+            SearcherSigner::AwsKms(signer) => Ok(signer.sign_message(message).await?),
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        match self {
+            SearcherSigner::Local(wallet) => Ok(wallet.sign_transaction(tx).await?),
+            #[cfg(feature = "ledger")]
+            SearcherSigner::Ledger(ledger) => Ok(ledger.sign_transaction(tx).await?),
+            #[cfg(feature = "aws")]
+            SearcherSigner::AwsKms(signer) => Ok(signer.sign_transaction(tx).await?),
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            SearcherSigner::Local(wallet) => Ok(wallet.sign_typed_data(payload).await?),
+            #[cfg(feature = "ledger")]
+            SearcherSigner::Ledger(ledger) => Ok(ledger.sign_typed_data(payload).await?),
+            #[cfg(feature = "aws")]
+            SearcherSigner::AwsKms(signer) => Ok(signer.sign_typed_data(payload).await?),
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            SearcherSigner::Local(wallet) => wallet.address(),
+            #[cfg(feature = "ledger")]
+            SearcherSigner::Ledger(ledger) => ledger.address(),
+            #[cfg(feature = "aws")]
+            SearcherSigner::AwsKms(signer) => signer.address(),
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            SearcherSigner::Local(wallet) => wallet.chain_id(),
+            #[cfg(feature = "ledger")]
+            SearcherSigner::Ledger(ledger) => ledger.chain_id(),
+            #[cfg(feature = "aws")]
+            SearcherSigner::AwsKms(signer) => signer.chain_id(),
+        }
+    }
+
+    /// note: ledger and kms signers keep the chain id they were built with
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            SearcherSigner::Local(wallet) => SearcherSigner::Local(wallet.with_chain_id(chain_id)),
+            #[allow(unreachable_patterns)]
+            signer => signer,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use dashmap::DashMap;
+use foundry_evm::{
+    executor::{fork::SharedBackend, Bytecode},
+    revm::{
+        db::DatabaseRef,
+        primitives::{AccountInfo, B160, B256, U256 as rU256},
+    },
+};
+
+use crate::constants::CODE_CACHE_MAX_CONTRACTS;
+
+/// Contracts loaded by earlier simulations, kept across evaluations and blocks so their
+/// bytecode isn't fetched over rpc again (only their storage is)
+///
+/// Only contracts without an ether balance are kept (pools, routers and tokens), their balance
+/// and nonce are reused from the block they were first loaded at along with their code
+#[derive(Debug, Default)]
+pub struct CodeCache {
+    accounts: DashMap<B160, AccountInfo>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CodeCache {
+    /// Number of contracts cached
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Account lookups served from the cache and sent to the provider since the last call
+    pub fn take_stats(&self) -> (u64, u64) {
+        (
+            self.hits.swap(0, Ordering::Relaxed),
+            self.misses.swap(0, Ordering::Relaxed),
+        )
+    }
+
+    fn get(&self, address: B160) -> Option<AccountInfo> {
+        let info = self.accounts.get(&address).map(|info| info.clone());
+        match info {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        info
+    }
+
+    fn insert(&self, address: B160, info: &AccountInfo) {
+        let has_code = info.code.as_ref().map_or(false, |code| !code.is_empty());
+        if !has_code || info.balance != rU256::ZERO {
+            return;
+        }
+        if self.accounts.len() >= CODE_CACHE_MAX_CONTRACTS {
+            return;
+        }
+        self.accounts.insert(address, info.clone());
+    }
+}
+
+/// Forked state for a simulation, accounts come from `CodeCache` when it has them and from
+/// the `SharedBackend` (which fetches them at the fork block) otherwise
+#[derive(Clone)]
+pub struct CachedCodeBackend {
+    backend: SharedBackend,
+    code_cache: Arc<CodeCache>,
+}
+
+impl CachedCodeBackend {
+    pub fn new(backend: SharedBackend, code_cache: Arc<CodeCache>) -> Self {
+        Self {
+            backend,
+            code_cache,
+        }
+    }
+}
+
+impl DatabaseRef for CachedCodeBackend {
+    type Error = <SharedBackend as DatabaseRef>::Error;
+
+    fn basic(&self, address: B160) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(info) = self.code_cache.get(address) {
+            return Ok(Some(info));
+        }
+
+        let info = self.backend.basic(address)?;
+        if let Some(info) = &info {
+            self.code_cache.insert(address, info);
+        }
+        Ok(info)
+    }
+
+    fn code_by_hash(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.backend.code_by_hash(code_hash)
+    }
+
+    fn storage(&self, address: B160, index: rU256) -> Result<rU256, Self::Error> {
+        self.backend.storage(address, index)
+    }
+
+    fn block_hash(&self, number: rU256) -> Result<B256, Self::Error> {
+        self.backend.block_hash(number)
+    }
+}
@@ -4,39 +4,49 @@ use cfmms::pool::Pool::{UniswapV2, UniswapV3};
 use cfmms::pool::UniswapV2Pool;
 use ethers::abi::{self, parse_abi, Address, ParamType};
 use ethers::prelude::BaseContract;
-use ethers::types::{Bytes, U256};
+use ethers::types::{Bytes, Transaction, U256};
 use foundry_evm::executor::TxEnv;
-use foundry_evm::executor::{
-    fork::SharedBackend, inspector::AccessListTracer, ExecutionResult, Output, TransactTo,
-};
-use foundry_evm::revm::{
-    db::CacheDB,
-    primitives::{Address as rAddress, U256 as rU256},
-    EVM,
-};
+use foundry_evm::executor::{inspector::AccessListTracer, ExecutionResult, Output, TransactTo};
+use foundry_evm::revm::{db::CacheDB, primitives::U256 as rU256, EVM};
 
 use crate::constants::{GET_RESERVES_SIG, SUGAR_DADDY, WETH_ADDRESS};
 use crate::helpers::access_list_to_revm;
-use crate::simulator::setup_block_state;
-use crate::tx_utils::huff_sando_interface::common::five_byte_encoder::FiveByteMetaData;
-use crate::tx_utils::huff_sando_interface::{
-    common::weth_encoder::WethEncoder,
-    v2::{v2_create_backrun_payload, v2_create_frontrun_payload},
-    v3::{v3_create_backrun_payload, v3_create_frontrun_payload},
+use crate::math::v2_sandwich::get_amount_out;
+use crate::simulator::{
+    check_sim_gas_cap, code_cache::CachedCodeBackend, set_pending_tx_env, setup_block_state,
 };
-use crate::types::{BlockInfo, RawIngredients, SandoRecipe};
+use crate::tx_utils::huff_sando_interface::common::five_byte_encoder::FiveByteMetaData;
+use crate::tx_utils::huff_sando_interface::common::weth_encoder::WethEncoder;
+use crate::tx_utils::sando_encoder::{backrun_min_out, SandoEncoder};
+use crate::types::{AccessListMode, BlockInfo, RawIngredients, SandoRecipe};
 
 use super::salmonella_inspector::{IsSandoSafu, SalmonellaInspectoooor};
 
 /// finds if sandwich is profitable + salmonella free
+/// note: the backrun asks for `backrun_min_out_buffer_bps` less than it simulates to get (v2),
+/// calldata is built by `encoder` so it must match the contract at `sando_address`
+///
+/// `preceding_txs` are pending txs the builder runs before our bundle (in that order), they are
+/// applied to the fork first and left out of the recipe
+///
+/// `access_list_mode` decides if our frontrun and backrun carry the slots they touch as an
+/// access list, their gas used (and so the recipe's profit) is with whatever list they carry
+///
+/// Fails with `SimGasCapExceeded` if a preceding tx or meat burns all of `max_sim_gas`
+#[allow(clippy::too_many_arguments)]
 pub fn create_recipe(
+    encoder: &dyn SandoEncoder,
     ingredients: &RawIngredients,
     next_block: &BlockInfo,
     optimal_in: U256,
     sando_start_bal: U256,
     searcher: Address,
     sando_address: Address,
-    shared_backend: SharedBackend,
+    shared_backend: CachedCodeBackend,
+    backrun_min_out_buffer_bps: u64,
+    preceding_txs: &[Transaction],
+    max_sim_gas: Option<u64>,
+    access_list_mode: AccessListMode,
 ) -> Result<SandoRecipe> {
     #[allow(unused_mut)]
     let mut fork_db = CacheDB::new(shared_backend);
@@ -54,11 +64,45 @@ pub fn create_recipe(
     evm.database(fork_db);
     setup_block_state(&mut evm, &next_block);
 
+    // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
+    // *                    PRECEDING TRANSACTION/s                 */
+    // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
+    // a tx that reverts, or can't run at all on this state, leaves it as it was
+    for tx in preceding_txs {
+        set_pending_tx_env(&mut evm, tx, max_sim_gas);
+        if let Ok(result) = evm.transact_commit() {
+            check_sim_gas_cap(tx, &result, max_sim_gas)?;
+        }
+    }
+
     // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
     // *                    FRONTRUN TRANSACTION                    */
     // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
+    // victim selling the token is frontrun by selling the contract's token inventory, the
+    // contract's backrun call does that swap and its frontrun call buys the token back
+    let sells_token = ingredients.get_start_end_token() != *WETH_ADDRESS;
+    let sando_start_bal = if sells_token {
+        if let UniswapV3(_) = ingredients.get_target_pool() {
+            return Err(anyhow!(
+                "[huffsando] v3 pools can only be sandwiched weth->token"
+            ));
+        }
+        get_erc20_balance(
+            ingredients.get_start_end_token(),
+            sando_address,
+            next_block,
+            &mut evm,
+        )?
+    } else {
+        sando_start_bal
+    };
+
     // encode frontrun_in before passing to sandwich contract
-    let frontrun_in = WethEncoder::decode(WethEncoder::encode(optimal_in));
+    let frontrun_in = if sells_token {
+        FiveByteMetaData::encode(optimal_in, 1).decode()
+    } else {
+        WethEncoder::decode(WethEncoder::encode(optimal_in))
+    };
 
     // caluclate frontrun_out using encoded frontrun_in
     let frontrun_out = match ingredients.get_target_pool() {
@@ -66,24 +110,26 @@ pub fn create_recipe(
             evm.env.tx.gas_price = next_block.base_fee_per_gas.into();
             evm.env.tx.gas_limit = 700000;
             evm.env.tx.value = rU256::ZERO;
-            v2_get_amount_out(frontrun_in, p, true, &mut evm)?
+            v2_get_amount_out(frontrun_in, p, !sells_token, &mut evm)?
         }
         UniswapV3(_) => U256::zero(),
     };
 
     // create tx.data and tx.value for frontrun_in
-    let (frontrun_data, frontrun_value) = match ingredients.get_target_pool() {
-        UniswapV2(p) => v2_create_frontrun_payload(
-            p,
-            ingredients.get_intermediary_token(),
+    let frontrun_call = if sells_token {
+        encoder.encode_backrun(
+            ingredients.get_target_pool(),
+            ingredients.get_start_end_token(),
             frontrun_in,
             frontrun_out,
-        ),
-        UniswapV3(p) => v3_create_frontrun_payload(
-            p,
+        )
+    } else {
+        encoder.encode_frontrun(
+            ingredients.get_target_pool(),
             ingredients.get_intermediary_token(),
-            frontrun_in.as_u128().into(),
-        ),
+            frontrun_in,
+            frontrun_out,
+        )
     };
 
     // setup evm for frontrun transaction
@@ -93,27 +139,20 @@ pub fn create_recipe(
         gas_price: next_block.base_fee_per_gas.into(),
         gas_priority_fee: None,
         transact_to: TransactTo::Call(sando_address.0.into()),
-        value: frontrun_value.into(),
-        data: frontrun_data.clone().into(),
+        value: frontrun_call.value.into(),
+        data: frontrun_call.calldata.0.clone(),
         chain_id: None,
         nonce: None,
         access_list: Default::default(),
     };
-    evm.env.tx = frontrun_tx_env.clone();
-
-    // get access list
-    let mut access_list_inspector = AccessListTracer::new(
-        Default::default(),
+    let frontrun_gas_saved = attach_access_list(
+        &mut evm,
+        &mut frontrun_tx_env,
         searcher,
         sando_address,
-        get_precompiles_for(evm.env.cfg.spec_id),
-    );
-    evm.inspect_ref(&mut access_list_inspector)
-        .map_err(|e| anyhow!("[EVM ERROR] frontrun: {:?}", (e)))?;
-    let frontrun_access_list = access_list_inspector.access_list();
-
-    frontrun_tx_env.access_list = access_list_to_revm(frontrun_access_list);
-    evm.env.tx = frontrun_tx_env.clone();
+        access_list_mode,
+    )
+    .map_err(|e| anyhow!("[huffsando: EVM ERROR] frontrun: {:?}", e))?;
 
     // run again but now with access list (so that we get accurate gas used)
     // run with a salmonella inspector to flag `suspicious` opcodes
@@ -148,29 +187,7 @@ pub fn create_recipe(
     // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
     let mut is_meat_good = Vec::new();
     for meat in ingredients.get_meats_ref().iter() {
-        evm.env.tx.caller = rAddress::from_slice(&meat.from.0);
-        evm.env.tx.transact_to =
-            TransactTo::Call(rAddress::from_slice(&meat.to.unwrap_or_default().0));
-        evm.env.tx.data = meat.input.0.clone();
-        evm.env.tx.value = meat.value.into();
-        evm.env.tx.chain_id = meat.chain_id.map(|id| id.as_u64());
-        //evm.env.tx.nonce = Some(meat.nonce.as_u64());
-        evm.env.tx.gas_limit = meat.gas.as_u64();
-        match meat.transaction_type {
-            Some(ethers::types::U64([0])) => {
-                // legacy tx
-                evm.env.tx.gas_price = meat.gas_price.unwrap_or_default().into();
-            }
-            Some(_) => {
-                // type 2 tx
-                evm.env.tx.gas_priority_fee = meat.max_priority_fee_per_gas.map(|mpf| mpf.into());
-                evm.env.tx.gas_price = meat.max_fee_per_gas.unwrap_or_default().into();
-            }
-            None => {
-                // legacy tx
-                evm.env.tx.gas_price = meat.gas_price.unwrap().into();
-            }
-        }
+        set_pending_tx_env(&mut evm, meat, max_sim_gas);
 
         // keep track of which meat transactions are successful to filter reverted meats at end
         // remove reverted meats because mempool tx/s gas costs are accounted for by fb
@@ -178,6 +195,7 @@ pub fn create_recipe(
             Ok(result) => result,
             Err(e) => return Err(anyhow!("[huffsando: EVM ERROR] meat: {:?}", e)),
         };
+        check_sim_gas_cap(meat, &res, max_sim_gas)?;
         match res.is_success() {
             true => is_meat_good.push(true),
             false => is_meat_good.push(false),
@@ -191,36 +209,49 @@ pub fn create_recipe(
     let backrun_token_in = ingredients.get_intermediary_token();
     let backrun_token_out = ingredients.get_start_end_token();
 
-    // keep some dust
-    let backrun_in = get_erc20_balance(backrun_token_in, sando_address, next_block, &mut evm)?;
-    let backrun_in = match ingredients.get_target_pool() {
-        UniswapV2(_) => {
-            let mut backrun_in_encoded = FiveByteMetaData::encode(backrun_in, 1);
-            backrun_in_encoded.decrement_four_bytes();
-            backrun_in_encoded.decode()
-        }
-        UniswapV3(_) => {
-            let backrun_in_encoded = FiveByteMetaData::encode(backrun_in, 1);
-            backrun_in_encoded.decode()
+    let backrun_in = if sells_token {
+        // only spend the weth the frontrun made, not the contract's weth inventory
+        WethEncoder::decode(WethEncoder::encode(frontrun_out))
+    } else {
+        // keep some dust
+        let backrun_in = get_erc20_balance(backrun_token_in, sando_address, next_block, &mut evm)?;
+        match ingredients.get_target_pool() {
+            UniswapV2(_) => {
+                let mut backrun_in_encoded = FiveByteMetaData::encode(backrun_in, 1);
+                backrun_in_encoded.decrement_four_bytes();
+                backrun_in_encoded.decode()
+            }
+            UniswapV3(_) => {
+                let backrun_in_encoded = FiveByteMetaData::encode(backrun_in, 1);
+                backrun_in_encoded.decode()
+            }
         }
     };
 
     // caluclate backrun_out using encoded backrun_in
     let backrun_out = match ingredients.get_target_pool() {
         UniswapV2(p) => {
-            let out = v2_get_amount_out(backrun_in, p, false, &mut evm)?;
-            out
+            let out = v2_get_amount_out(backrun_in, p, sells_token, &mut evm)?;
+            backrun_min_out(out, backrun_min_out_buffer_bps)
         }
         UniswapV3(_p) => U256::zero(), // we don't need to know backrun out for v3
     };
 
     // create tx.data and tx.value for backrun_in
-    let (backrun_data, backrun_value) = match ingredients.get_target_pool() {
-        UniswapV2(p) => v2_create_backrun_payload(p, backrun_token_in, backrun_in, backrun_out),
-        UniswapV3(p) => (
-            v3_create_backrun_payload(p, backrun_token_in, backrun_in),
-            U256::zero(),
-        ),
+    let backrun_call = if sells_token {
+        encoder.encode_frontrun(
+            ingredients.get_target_pool(),
+            backrun_token_out,
+            backrun_in,
+            backrun_out,
+        )
+    } else {
+        encoder.encode_backrun(
+            ingredients.get_target_pool(),
+            backrun_token_in,
+            backrun_in,
+            backrun_out,
+        )
     };
 
     // setup evm for backrun transaction
@@ -230,27 +261,20 @@ pub fn create_recipe(
         gas_price: next_block.base_fee_per_gas.into(),
         gas_priority_fee: None,
         transact_to: TransactTo::Call(sando_address.0.into()),
-        value: backrun_value.into(),
-        data: backrun_data.clone().into(),
+        value: backrun_call.value.into(),
+        data: backrun_call.calldata.0.clone(),
         chain_id: None,
         nonce: None,
         access_list: Default::default(),
     };
-    evm.env.tx = backrun_tx_env.clone();
-
-    // create access list
-    let mut access_list_inspector = AccessListTracer::new(
-        Default::default(),
+    let backrun_gas_saved = attach_access_list(
+        &mut evm,
+        &mut backrun_tx_env,
         searcher,
         sando_address,
-        get_precompiles_for(evm.env.cfg.spec_id),
-    );
-    evm.inspect_ref(&mut access_list_inspector)
-        .map_err(|e| anyhow!("[huffsando: EVM ERROR] frontrun: {:?}", e))
-        .unwrap();
-    let backrun_access_list = access_list_inspector.access_list();
-    backrun_tx_env.access_list = access_list_to_revm(backrun_access_list);
-    evm.env.tx = backrun_tx_env.clone();
+        access_list_mode,
+    )
+    .map_err(|e| anyhow!("[huffsando: EVM ERROR] backrun: {:?}", e))?;
 
     // run again but now with access list (so that we get accurate gas used)
     // run with a salmonella inspector to flag `suspicious` opcodes
@@ -307,7 +331,59 @@ pub fn create_recipe(
         backrun_gas_used,
         revenue,
         *next_block,
-    ))
+    )
+    .with_access_list_gas_saved(frontrun_gas_saved + backrun_gas_saved))
+}
+
+/// Attach the accounts and slots `tx_env` touches as its access list (if `mode` wants one) and
+/// leave the evm's tx env pointing at it, tx isn't committed
+///
+/// Returns the gas the access list saves the tx (zero when it goes without one)
+fn attach_access_list(
+    evm: &mut EVM<CacheDB<CachedCodeBackend>>,
+    tx_env: &mut TxEnv,
+    searcher: Address,
+    sando_address: Address,
+    mode: AccessListMode,
+) -> Result<u64> {
+    tx_env.access_list = Default::default();
+    evm.env.tx = tx_env.clone();
+    if mode == AccessListMode::Never {
+        return Ok(0);
+    }
+
+    // trace the slots touched, this run also gives the gas used without a list
+    let mut access_list_inspector = AccessListTracer::new(
+        Default::default(),
+        searcher,
+        sando_address,
+        get_precompiles_for(evm.env.cfg.spec_id),
+    );
+    let gas_without = evm
+        .inspect_ref(&mut access_list_inspector)
+        .map_err(|e| anyhow!("{:?}", e))?
+        .result
+        .gas_used();
+    let access_list = access_list_inspector.access_list();
+    if access_list.0.is_empty() {
+        return Ok(0);
+    }
+
+    tx_env.access_list = access_list_to_revm(access_list);
+    evm.env.tx = tx_env.clone();
+    let gas_with = evm
+        .transact_ref()
+        .map_err(|e| anyhow!("{:?}", e))?
+        .result
+        .gas_used();
+
+    // listed accounts and slots are paid for up front, that only pays off for ones touched cold
+    if mode == AccessListMode::WhenCheaper && gas_with >= gas_without {
+        tx_env.access_list = Default::default();
+        evm.env.tx = tx_env.clone();
+        return Ok(0);
+    }
+    Ok(gas_without.saturating_sub(gas_with))
 }
 
 /// Get the balance of a token in an evm (account for tax)
@@ -315,7 +391,7 @@ pub fn get_erc20_balance(
     token: Address,
     owner: Address,
     block: &BlockInfo,
-    evm: &mut EVM<CacheDB<SharedBackend>>,
+    evm: &mut EVM<CacheDB<CachedCodeBackend>>,
 ) -> Result<U256> {
     let erc20 = BaseContract::from(
         parse_abi(&["function balanceOf(address) external returns (uint)"]).unwrap(),
@@ -357,7 +433,7 @@ pub fn get_erc20_balance(
 
 // Find amount out from an amount in using the k=xy formula
 // note: reserve values taken from evm
-// note: assuming fee is set to 3% for all pools (not case irl)
+// note: uses the swap fee the pool was synced with (from its dex config)
 //
 // Arguments:
 // * `amount_in`: amount of token in
@@ -373,7 +449,7 @@ pub fn v2_get_amount_out(
     amount_in: U256,
     target_pool: UniswapV2Pool,
     is_frontrun: bool,
-    evm: &mut EVM<CacheDB<SharedBackend>>,
+    evm: &mut EVM<CacheDB<CachedCodeBackend>>,
 ) -> Result<U256> {
     // get reserves
     evm.env.tx.transact_to = TransactTo::Call(target_pool.address().0.into());
@@ -429,17 +505,15 @@ pub fn v2_get_amount_out(
         false => (reserves_1, reserves_0),
     };
 
-    let a_in_with_fee: U256 = amount_in * 997;
-    let numerator: U256 = a_in_with_fee * reserve_out;
-    let denominator: U256 = reserve_in * 1000 + a_in_with_fee;
-    let amount_out: U256 = numerator.checked_div(denominator).unwrap_or(U256::zero());
+    // pool fee is tagged from its dex config (cfmms stores 0.3% as 300)
+    let fee_bps = target_pool.fee / 10;
 
-    Ok(amount_out)
+    Ok(get_amount_out(amount_in, reserve_in, reserve_out, fee_bps))
 }
 
 #[cfg(feature = "debug")]
 fn inject_huff_sando(
-    db: &mut CacheDB<SharedBackend>,
+    db: &mut CacheDB<CachedCodeBackend>,
     huff_sando_addy: foundry_evm::executor::B160,
     searcher: foundry_evm::executor::B160,
     sando_start_bal: U256,
@@ -2,10 +2,10 @@ use anyhow::{anyhow, Result};
 use cfmms::pool::Pool::{UniswapV2, UniswapV3};
 use ethers::{abi, types::U256};
 use foundry_evm::{
-    executor::{fork::SharedBackend, Bytecode, ExecutionResult, Output, TransactTo},
+    executor::{Bytecode, ExecutionResult, Output, TransactTo},
     revm::{
         db::CacheDB,
-        primitives::{keccak256, AccountInfo, Address as rAddress, U256 as rU256},
+        primitives::{keccak256, AccountInfo, U256 as rU256},
         EVM,
     },
 };
@@ -17,18 +17,23 @@ use crate::{
     tx_utils::lil_router_interface::{
         build_swap_v2_data, build_swap_v3_data, decode_swap_v2_result, decode_swap_v3_result,
     },
-    types::{BlockInfo, RawIngredients},
+    types::{BlockInfo, RawIngredients, SimGasCapExceeded},
 };
 
-use super::{eth_to_wei, setup_block_state};
+use super::{
+    check_sim_gas_cap, code_cache::CachedCodeBackend, eth_to_wei, set_pending_tx_env,
+    setup_block_state,
+};
 
 // Juiced implementation of https://research.ijcaonline.org/volume65/number14/pxc3886165.pdf
 // splits range in more intervals, search intervals concurrently, compare, repeat till termination
+// note: fails with `SimGasCapExceeded` if a meat burns all of `max_sim_gas`
 pub async fn find_optimal_input(
     ingredients: &RawIngredients,
     target_block: &BlockInfo,
     weth_inventory: U256,
-    shared_backend: SharedBackend,
+    shared_backend: CachedCodeBackend,
+    max_sim_gas: Option<u64>,
 ) -> Result<U256> {
     //
     //            [EXAMPLE WITH 10 BOUND INTERVALS]
@@ -111,16 +116,21 @@ pub async fn find_optimal_input(
                 target_block.clone(),
                 shared_backend.clone(),
                 ingredients.clone(),
+                max_sim_gas,
             ));
             revenues.push(sim);
         }
 
-        let revenues = futures::future::join_all(revenues).await;
-
-        let revenues = revenues
-            .into_iter()
-            .map(|r| r.unwrap().unwrap_or_default())
-            .collect::<Vec<_>>();
+        let mut revenues_found = Vec::new();
+        for revenue in futures::future::join_all(revenues).await {
+            match revenue.unwrap() {
+                Ok(revenue) => revenues_found.push(revenue),
+                // the meats run the same for every input, searching further won't finish either
+                Err(e) if e.is::<SimGasCapExceeded>() => return Err(e),
+                Err(_) => revenues_found.push(U256::zero()),
+            }
+        }
+        let revenues = revenues_found;
 
         // find interval that produces highest revenue
         let (highest_revenue_index, _highest_revenue) = revenues
@@ -167,8 +177,9 @@ pub async fn find_optimal_input(
 async fn evaluate_sandwich_revenue(
     frontrun_in: U256,
     next_block: BlockInfo,
-    shared_backend: SharedBackend,
+    shared_backend: CachedCodeBackend,
     ingredients: RawIngredients,
+    max_sim_gas: Option<u64>,
 ) -> Result<U256> {
     let mut fork_db = CacheDB::new(shared_backend);
     inject_lil_router_code(&mut fork_db);
@@ -228,31 +239,11 @@ async fn evaluate_sandwich_revenue(
     /*                     MEAT TRANSACTION/s                     */
     /*.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
     for meat in ingredients.get_meats_ref().iter() {
-        evm.env.tx.caller = rAddress::from_slice(&meat.from.0);
-        evm.env.tx.transact_to =
-            TransactTo::Call(rAddress::from_slice(&meat.to.unwrap_or_default().0));
-        evm.env.tx.data = meat.input.0.clone();
-        evm.env.tx.value = meat.value.into();
-        evm.env.tx.chain_id = meat.chain_id.map(|id| id.as_u64());
-        // evm.env.tx.nonce = Some(meat.nonce.as_u64()); /** ignore nonce check for now **/
-        evm.env.tx.gas_limit = meat.gas.as_u64();
-        match meat.transaction_type {
-            Some(ethers::types::U64([0])) => {
-                // legacy tx
-                evm.env.tx.gas_price = meat.gas_price.unwrap_or_default().into();
-            }
-            Some(_) => {
-                // type 2 tx
-                evm.env.tx.gas_priority_fee = meat.max_priority_fee_per_gas.map(|mpf| mpf.into());
-                evm.env.tx.gas_price = meat.max_fee_per_gas.unwrap_or_default().into();
-            }
-            None => {
-                // legacy tx
-                evm.env.tx.gas_price = meat.gas_price.unwrap_or_default().into();
-            }
-        }
+        set_pending_tx_env(&mut evm, meat, max_sim_gas);
 
-        let _res = evm.transact_commit();
+        if let Ok(result) = evm.transact_commit() {
+            check_sim_gas_cap(meat, &result, max_sim_gas)?;
+        }
     }
 
     /*´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
@@ -305,7 +296,7 @@ async fn evaluate_sandwich_revenue(
 }
 
 /// Inserts custom minimal router contract into evm instance for simulations
-fn inject_lil_router_code(db: &mut CacheDB<SharedBackend>) {
+fn inject_lil_router_code(db: &mut CacheDB<CachedCodeBackend>) {
     // insert lilRouter bytecode
     let lil_router_info = AccountInfo::new(
         rU256::ZERO,
@@ -1,18 +1,27 @@
+pub mod code_cache;
 pub mod huff_sando;
 pub(crate) mod lil_router;
 pub(crate) mod salmonella_inspector;
 
+use anyhow::Result;
+use ethers::types::{Transaction, U256};
 use foundry_evm::{
-    executor::fork::SharedBackend,
-    revm::{db::CacheDB, primitives::U256 as rU256, EVM},
+    executor::{ExecutionResult, TransactTo},
+    revm::{
+        db::CacheDB,
+        primitives::{Address as rAddress, U256 as rU256},
+        EVM,
+    },
 };
 
+use self::code_cache::CachedCodeBackend;
 use crate::{
     constants::{COINBASE, ONE_ETHER_IN_WEI},
-    types::BlockInfo,
+    helpers::access_list_to_revm,
+    types::{BlockInfo, SandoRecipe, SimGasCapExceeded},
 };
 
-fn setup_block_state(evm: &mut EVM<CacheDB<SharedBackend>>, next_block: &BlockInfo) {
+fn setup_block_state(evm: &mut EVM<CacheDB<CachedCodeBackend>>, next_block: &BlockInfo) {
     evm.env.block.number = rU256::from(next_block.number.as_u64());
     evm.env.block.timestamp = next_block.timestamp.into();
     evm.env.block.basefee = next_block.base_fee_per_gas.into();
@@ -20,6 +29,75 @@ fn setup_block_state(evm: &mut EVM<CacheDB<SharedBackend>>, next_block: &BlockIn
     evm.env.block.coinbase = *COINBASE;
 }
 
+/// Gas limit that a pending tx is simulated with, its own capped at `max_sim_gas`
+fn sim_gas_limit(tx: &Transaction, max_sim_gas: Option<u64>) -> u64 {
+    let gas_limit = tx.gas.min(U256::from(u64::MAX)).as_u64();
+    max_sim_gas.map_or(gas_limit, |gas_cap| gas_limit.min(gas_cap))
+}
+
+/// Point the evm's tx env at a pending tx (a victim or one that runs before our bundle), with
+/// its gas limit capped at `max_sim_gas`
+/// note: typed txs run with their own access list (it changes the gas they use), nothing is
+/// left over from the tx simulated before
+fn set_pending_tx_env(
+    evm: &mut EVM<CacheDB<CachedCodeBackend>>,
+    tx: &Transaction,
+    max_sim_gas: Option<u64>,
+) {
+    evm.env.tx.caller = rAddress::from_slice(&tx.from.0);
+    evm.env.tx.transact_to = TransactTo::Call(rAddress::from_slice(&tx.to.unwrap_or_default().0));
+    evm.env.tx.data = tx.input.0.clone();
+    evm.env.tx.value = tx.value.into();
+    evm.env.tx.chain_id = tx.chain_id.map(|id| id.as_u64());
+    // evm.env.tx.nonce = Some(tx.nonce.as_u64()); /** ignore nonce check for now **/
+    evm.env.tx.gas_limit = sim_gas_limit(tx, max_sim_gas);
+    evm.env.tx.access_list = access_list_to_revm(tx.access_list.clone().unwrap_or_default());
+    match tx.transaction_type.map(|kind| kind.as_u64()) {
+        Some(2) => {
+            // type 2 tx
+            evm.env.tx.gas_priority_fee = tx.max_priority_fee_per_gas.map(|mpf| mpf.into());
+            evm.env.tx.gas_price = tx.max_fee_per_gas.unwrap_or_default().into();
+        }
+        _ => {
+            // legacy and type 1 (access list) txs pay a flat gas price
+            evm.env.tx.gas_priority_fee = None;
+            evm.env.tx.gas_price = tx.gas_price.unwrap_or_default().into();
+        }
+    }
+}
+
+/// Fails if a pending tx failed after burning all of the gas it was capped at, while its own
+/// limit would have let it run further (we can't tell how it ends without running it)
+fn check_sim_gas_cap(
+    tx: &Transaction,
+    result: &ExecutionResult,
+    max_sim_gas: Option<u64>,
+) -> Result<()> {
+    let gas_cap = match max_sim_gas {
+        Some(gas_cap) if tx.gas > U256::from(gas_cap) => gas_cap,
+        _ => return Ok(()),
+    };
+
+    if !result.is_success() && result.gas_used() >= gas_cap {
+        return Err(SimGasCapExceeded {
+            tx_hash: tx.hash,
+            gas_cap,
+        }
+        .into());
+    }
+    Ok(())
+}
+
 pub fn eth_to_wei(amt: u128) -> rU256 {
     rU256::from(amt).checked_mul(*ONE_ETHER_IN_WEI).unwrap()
 }
+
+/// Gas used by a bundle's `(frontrun, backrun)` when it was executed in our local evm fork
+/// note: depends on ticks crossed and how the sando contract routes, so prefer this over the
+/// `*_GAS_ESTIMATE` constants once a bundle has been simulated
+pub fn estimate_gas(bundle: &SandoRecipe) -> (U256, U256) {
+    (
+        bundle.get_frontrun_gas_used().into(),
+        bundle.get_backrun_gas_used().into(),
+    )
+}
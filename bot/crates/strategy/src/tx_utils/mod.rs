@@ -1,2 +1,5 @@
 pub(crate) mod huff_sando_interface;
 pub(crate) mod lil_router_interface;
+
+/// Calldata builders for the sando contract
+pub mod sando_encoder;
@@ -0,0 +1,229 @@
+//! Calldata for the huff sando contract
+//!
+//! The contract has no abi (so no `abigen!` binding), it jumps straight to the first calldata
+//! byte and reads tightly packed arguments:
+//!
+//! - frontrun v2: `jump_dest (1) | pool (20) | five_bytes(amount_out) (5)`
+//! - frontrun v3: `jump_dest (1) | pool (20) | pool_key_hash (32)`
+//! - backrun v2: `jump_dest (1) | pool (20) | token_in (20) | five_bytes(amount_in) (5)`
+//! - backrun v3: `jump_dest (1) | pool (20) | token_in (20) | pool_key_hash (32) | five_bytes(amount_in) (5)`
+//!
+//! Backruns can append a `uint256` bribe (32 bytes) after their packed args, the contract
+//! transfers it to `block.coinbase` from its eth balance (no bribe = paid via priority fee).
+//!
+//! Weth amounts are passed through `tx.value` divided by `WETH_ENCODING_MULTIPLE`, five byte
+//! amounts are the memory offset to store at (1 byte) followed by the amount squashed to 4 bytes.
+//!
+//! The contract calls the pool's `swap` itself (no router), on v2 pools with the output amount
+//! worked out off chain. `v2_direct_frontrun` and `v2_direct_backrun` give the amounts such a
+//! swap moves once both encodings rounded them down.
+//!
+//! Contracts with a different layout plug in their own `SandoEncoder` (see
+//! `SandoBot::with_sando_encoder`), `HuffSandoEncoder` encodes the layout above.
+use cfmms::pool::Pool;
+use ethers::types::{Address, Bytes, U256};
+
+use super::huff_sando_interface::{
+    common::{five_byte_encoder::FiveByteMetaData, weth_encoder::WethEncoder},
+    v2::{v2_create_backrun_payload, v2_create_frontrun_payload},
+    v3::{v3_create_backrun_payload, v3_create_frontrun_payload},
+};
+use crate::math::v2_sandwich::get_amount_out;
+
+/// Calldata and `tx.value` for a call to the sando contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandoCall {
+    pub calldata: Bytes,
+    /// Encoded weth amount (zero if the call doesn't spend weth)
+    pub value: U256,
+}
+
+impl SandoCall {
+    /// Append a bribe that the contract transfers to `block.coinbase` (only valid for backruns)
+    pub fn with_coinbase_payment(self, bribe: U256) -> Self {
+        let mut bribe_word = [0u8; 32];
+        bribe.to_big_endian(&mut bribe_word);
+
+        let mut calldata = self.calldata.to_vec();
+        calldata.extend_from_slice(&bribe_word);
+
+        Self {
+            calldata: calldata.into(),
+            value: self.value,
+        }
+    }
+}
+
+/// What a v2 swap that the sando contract sends straight to the pool moves, both amounts are
+/// exactly representable in its calldata
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirectSwap {
+    pub amount_in: U256,
+    /// Asked from the pool, never more than the pool pays for `amount_in`
+    pub amount_out: U256,
+}
+
+// Find the amounts of a `weth->token` v2 frontrun sent to the pool directly
+// note: weth in is rounded down to what `tx.value` carries, the token out to what five bytes hold
+//
+// Arguments:
+// * `amount_in`: weth we'd like to swap
+// * `reserve_weth`: pool's weth reserve when the frontrun runs
+// * `reserve_token`: pool's token reserve when the frontrun runs
+// * `fee_bps`: pool swap fee in basis points (30 for uniswap v2)
+//
+// Returns:
+// DirectSwap: weth in and token out to encode (pass both to `encode_frontrun`)
+pub fn v2_direct_frontrun(
+    amount_in: U256,
+    reserve_weth: U256,
+    reserve_token: U256,
+    fee_bps: u32,
+) -> DirectSwap {
+    let amount_in = WethEncoder::decode(WethEncoder::encode(amount_in));
+    let amount_out = get_amount_out(amount_in, reserve_weth, reserve_token, fee_bps);
+
+    DirectSwap {
+        amount_in,
+        amount_out: FiveByteMetaData::encode(amount_out, 0).decode(),
+    }
+}
+
+// Find the amounts of a `token->weth` v2 backrun sent to the pool directly
+// note: token in is rounded down to what five bytes hold (one step further so dust stays on the
+// contract), weth out to what `tx.value` carries
+//
+// Arguments:
+// * `amount_in`: token we'd like to sell (e.g. the contract's whole balance)
+// * `reserve_token`: pool's token reserve when the backrun runs
+// * `reserve_weth`: pool's weth reserve when the backrun runs
+// * `fee_bps`: pool swap fee in basis points (30 for uniswap v2)
+//
+// Returns:
+// Some(DirectSwap): token in and weth out to encode (pass both to `encode_backrun`)
+// None: if `amount_in` is too small to leave dust behind
+pub fn v2_direct_backrun(
+    amount_in: U256,
+    reserve_token: U256,
+    reserve_weth: U256,
+    fee_bps: u32,
+) -> Option<DirectSwap> {
+    let mut encoded_in = FiveByteMetaData::encode(amount_in, 1);
+    if encoded_in.decode() <= U256::one() {
+        return None;
+    }
+    encoded_in.decrement_four_bytes();
+    let amount_in = encoded_in.decode();
+    let amount_out = get_amount_out(amount_in, reserve_token, reserve_weth, fee_bps);
+
+    Some(DirectSwap {
+        amount_in,
+        amount_out: WethEncoder::decode(WethEncoder::encode(amount_out)),
+    })
+}
+
+/// Lower a backrun's `amount_out` by `buffer_bps` so a little interference between our frontrun
+/// and backrun doesn't revert the bundle
+/// note: v2 pools send exactly the amount asked for, whatever the backrun could have taken on top
+/// of it stays in the pool
+pub fn backrun_min_out(amount_out: U256, buffer_bps: u64) -> U256 {
+    amount_out - amount_out * U256::from(buffer_bps.min(10_000)) / 10_000
+}
+
+/// Builds the calldata of a deployed sando contract, it must match the contract's decoder byte
+/// for byte or every frontrun and backrun reverts
+///
+/// Amounts are passed as sized, an encoder that can't represent them exactly has to round them
+/// down (never up, the contract only holds what was sized)
+pub trait SandoEncoder: Send + Sync {
+    /// Encode a `weth->output_token` frontrun
+    ///
+    /// `amount_out` is the least the pool must pay out (v2 pools are asked for exactly this)
+    fn encode_frontrun(
+        &self,
+        pool: Pool,
+        output_token: Address,
+        amount_in: U256,
+        amount_out: U256,
+    ) -> SandoCall;
+
+    /// Encode an `input_token->weth` backrun
+    ///
+    /// `amount_out` is the least the pool must pay out (v2 pools are asked for exactly this)
+    fn encode_backrun(
+        &self,
+        pool: Pool,
+        input_token: Address,
+        amount_in: U256,
+        amount_out: U256,
+    ) -> SandoCall;
+}
+
+/// Encoder for the huff sando contract in this repo (layout in the module docs)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HuffSandoEncoder;
+
+impl SandoEncoder for HuffSandoEncoder {
+    fn encode_frontrun(
+        &self,
+        pool: Pool,
+        output_token: Address,
+        amount_in: U256,
+        amount_out: U256,
+    ) -> SandoCall {
+        encode_frontrun(pool, output_token, amount_in, amount_out)
+    }
+
+    fn encode_backrun(
+        &self,
+        pool: Pool,
+        input_token: Address,
+        amount_in: U256,
+        amount_out: U256,
+    ) -> SandoCall {
+        encode_backrun(pool, input_token, amount_in, amount_out)
+    }
+}
+
+/// Encode a `weth->output_token` frontrun
+///
+/// `amount_out` is only used by v2 pools (it must be passed for taxed tokens)
+pub fn encode_frontrun(
+    pool: Pool,
+    output_token: Address,
+    amount_in: U256,
+    amount_out: U256,
+) -> SandoCall {
+    let (calldata, value) = match pool {
+        Pool::UniswapV2(p) => v2_create_frontrun_payload(p, output_token, amount_in, amount_out),
+        Pool::UniswapV3(p) => v3_create_frontrun_payload(p, output_token, amount_in),
+    };
+
+    SandoCall {
+        calldata: calldata.into(),
+        value,
+    }
+}
+
+/// Encode an `input_token->weth` backrun
+///
+/// `amount_out` is only used by v2 pools (it must be passed for taxed tokens)
+pub fn encode_backrun(
+    pool: Pool,
+    input_token: Address,
+    amount_in: U256,
+    amount_out: U256,
+) -> SandoCall {
+    let (calldata, value) = match pool {
+        Pool::UniswapV2(p) => v2_create_backrun_payload(p, input_token, amount_in, amount_out),
+        Pool::UniswapV3(p) => (
+            v3_create_backrun_payload(p, input_token, amount_in),
+            U256::zero(),
+        ),
+    };
+
+    SandoCall {
+        calldata: calldata.into(),
+        value,
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::ensure;
@@ -5,39 +7,2239 @@ use anyhow::{anyhow, Result};
 use artemis_core::{
     collectors::block_collector::NewBlock, executors::flashbots_executor::FlashbotsBundle,
 };
-use cfmms::pool::Pool;
-use ethers::providers::Middleware;
-use ethers::signers::LocalWallet;
-use ethers::signers::Signer;
+use cfmms::{
+    dex::DexVariant,
+    pool::{Pool, UniswapV2Pool},
+};
+use ethers::abi::{self, AbiDecode};
+use ethers::providers::{Middleware, ProviderError};
 use ethers::types::{
-    Address, Block, Bytes, Eip1559TransactionRequest, Transaction, H256, U256, U64,
+    transaction::eip2718::TypedTransaction, AccountDiff, Address, Block, BlockId, BlockNumber,
+    Bytes, ChangedType, Diff, DiffMode, Eip1559TransactionRequest, GethDebugBuiltInTracerConfig,
+    GethDebugBuiltInTracerType, GethDebugTracerConfig, GethDebugTracerType,
+    GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, GethTraceFrame,
+    PreStateConfig, PreStateFrame, TraceType, Transaction, H256, I256, U256, U64,
 };
 use ethers_flashbots::BundleRequest;
 use foundry_evm::executor::TxEnv;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use crate::constants::DUST_OVERPAY;
-use crate::helpers::access_list_to_ethers;
+use crate::abi::{UniswapV2RouterCalls as V2, UniswapV3RouterCalls as V3};
+use crate::constants::{
+    AGGREGATOR_ROUTERS, COINBASE_PAYMENT_GAS_ESTIMATE, DUST_OVERPAY, HOT_POOL_WINDOW_BLOCKS,
+    NATIVE_ETH_ADDRESSES, POOL_SYNC_CONCURRENCY, REBASING_TOKENS, STABLE_TOKENS,
+    V2_BACKRUN_GAS_ESTIMATE, V2_FRONTRUN_GAS_ESTIMATE, V3_BACKRUN_GAS_ESTIMATE, V3_FEE_TIERS,
+    V3_FRONTRUN_GAS_ESTIMATE, WETH_ADDRESS,
+};
 use crate::helpers::sign_eip1559;
+use crate::helpers::{access_list_to_ethers, padded_gas_limit};
+use crate::managers::block_manager::{
+    calculate_next_block_base_fee, max_base_fee_after, with_base_fee_margin,
+};
+use crate::managers::opportunity_log::OpportunityRecord;
+use crate::math::{
+    ordering::OrderingStrategy,
+    profit::SandwichProfit,
+    v2_sandwich::{get_amount_in, get_amount_out},
+};
+use crate::signer::SearcherSigner;
+use crate::simulator::estimate_gas;
+use crate::tx_utils::sando_encoder::SandoCall;
 
 /// Core Event enum for current strategy
-#[derive(Debug, Clone)]
+/// note: (de)serializable so that recorded events can be replayed by the backtester
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Event {
     NewBlock(NewBlock),
     NewTransaction(Transaction),
+    /// Pending txs that arrived within the batching window (see `TX_BATCH_WINDOW_MS`)
+    NewTransactions(Vec<Transaction>),
 }
 
 /// Core Action enum for current strategy
 #[derive(Debug, Clone)]
 pub enum Action {
-    SubmitToFlashbots(FlashbotsBundle),
+    /// Sandwich bundles (frontrun, meats, backrun) simulated for `target_block`
+    SubmitBundle {
+        bundles: FlashbotsBundle,
+        target_block: U64,
+        /// Expected net profit of each bundle (same order as `bundles`)
+        net_profits: Vec<I256>,
+        /// Blocks each bundle is sent for (same order as `bundles`, see `target_block_offsets`)
+        target_blocks: Vec<Vec<U64>>,
+    },
+    /// Backrun or arb (no frontrun) sent with `eth_sendPrivateTransaction`, only valid up to
+    /// `target_block`
+    SubmitPrivateTx {
+        /// Raw signed txs, in nonce order
+        txs: Vec<Bytes>,
+        target_block: U64,
+        /// Expected net profit of the txs
+        net_profit: I256,
+    },
+}
+
+/// Whether bundles are sent to relays or only recorded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BotMode {
+    /// Submit bundles to relays
+    #[default]
+    Live,
+    /// Run the full pipeline but write bundles to a file instead of submitting them
+    DryRun,
+}
+
+impl FromStr for BotMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "live" => Ok(BotMode::Live),
+            "dry_run" | "dryrun" => Ok(BotMode::DryRun),
+            _ => Err(anyhow!("Unknown bot mode {}", s)),
+        }
+    }
+}
+
+/// How the logging macros format their output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable colored lines
+    #[default]
+    Text,
+    /// One json object per line (with fields such as `tx_hash`, `pool`, `block`, `profit`)
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(anyhow!("Unknown log format {}", s)),
+        }
+    }
+}
+
+/// How the backrun pays the builder for landing our bundle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PaymentStrategy {
+    /// Bribe is paid as the backrun's priority fee
+    #[default]
+    PriorityFee,
+    /// Backrun pays base fee only and transfers the bribe to `block.coinbase` (sando contract
+    /// must hold enough eth)
+    CoinbaseTransfer,
+}
+
+impl FromStr for PaymentStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "priority_fee" => Ok(PaymentStrategy::PriorityFee),
+            "coinbase_transfer" => Ok(PaymentStrategy::CoinbaseTransfer),
+            _ => Err(anyhow!("Unknown payment strategy {}", s)),
+        }
+    }
+}
+
+/// How the sando contract used for each bundle is picked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SandoRotation {
+    /// Each bundle uses the contract after the previous one's
+    #[default]
+    RoundRobin,
+    /// Each bundle uses a contract drawn from the bot's seeded rng
+    Random,
+}
+
+impl FromStr for SandoRotation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "round_robin" => Ok(SandoRotation::RoundRobin),
+            "random" => Ok(SandoRotation::Random),
+            _ => Err(anyhow!("Unknown sando rotation {}", s)),
+        }
+    }
+}
+
+/// Which chain state victims are traced and sandwiches are simulated on top of
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SimStateSource {
+    /// Latest mined block (the target block's parent)
+    #[default]
+    Latest,
+    /// Node's pending block (includes txs the node expects to land before ours)
+    Pending,
+    /// Fixed block, so backtests see the same state on every run
+    Pinned(U64),
+}
+
+impl SimStateSource {
+    /// Block to trace and fork at when targeting `target_block`
+    pub fn block_for(&self, target_block: U64) -> BlockNumber {
+        match self {
+            SimStateSource::Latest => BlockNumber::Number(target_block - 1),
+            SimStateSource::Pending => BlockNumber::Pending,
+            SimStateSource::Pinned(block) => BlockNumber::Number(*block),
+        }
+    }
+}
+
+/// Whether our frontruns and backruns carry an EIP-2930 access list, built from the accounts
+/// and slots their simulation touched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccessListMode {
+    /// Only when simulating with it uses less gas (prepaying an account or slot costs gas that
+    /// is lost if it would have been warm anyway)
+    #[default]
+    WhenCheaper,
+    /// Always attach the traced access list
+    Always,
+    /// Never attach one
+    Never,
+}
+
+impl FromStr for AccessListMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "when_cheaper" => Ok(AccessListMode::WhenCheaper),
+            "always" => Ok(AccessListMode::Always),
+            "never" => Ok(AccessListMode::Never),
+            _ => Err(anyhow!("Unknown access list mode {}", s)),
+        }
+    }
+}
+
+/// Where a victim is assumed to land in the block when sizing and simulating its sandwich
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VictimPosition {
+    /// Straight on the block state, as if nothing ran before it (optimistic, nothing extra to
+    /// simulate)
+    #[default]
+    TopOfBlock,
+    /// After the pending txs on its pool that outbid it, in the order a builder runs them (safer,
+    /// every sandwich is sized on their reserves and simulated after them)
+    AfterPending,
+}
+
+impl FromStr for VictimPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "top_of_block" => Ok(VictimPosition::TopOfBlock),
+            "after_pending" => Ok(VictimPosition::AfterPending),
+            _ => Err(anyhow!("Unknown victim position assumption {}", s)),
+        }
+    }
+}
+
+/// What to do with a victim trading a v2 pool against the victims of a bundle already out on
+/// it for the same block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpposingVictims {
+    /// Size the victim's sandwich on its own, it replaces the outstanding bundle if it makes more
+    #[default]
+    Independent,
+    /// Net every victim's swap into one bundle that frontruns whichever way they move the pool
+    /// together, dropped if nothing profitable is left after netting
+    Net,
+    /// Leave the victim alone, the outstanding bundle keeps the pool
+    Skip,
+}
+
+impl FromStr for OpposingVictims {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "independent" => Ok(OpposingVictims::Independent),
+            "net" => Ok(OpposingVictims::Net),
+            "skip" => Ok(OpposingVictims::Skip),
+            _ => Err(anyhow!("Unknown opposing victims policy {}", s)),
+        }
+    }
+}
+
+/// Rpc method used to get a tx's state diffs (providers often only support one of them)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StateDiffMethod {
+    /// `trace_call` with a `stateDiff` trace (erigon, reth, nethermind)
+    #[default]
+    TraceCall,
+    /// `trace_callMany` with a single call, for providers that don't expose `trace_call`
+    TraceCallMany,
+    /// `debug_traceCall` with the `prestateTracer` in diff mode (geth and most hosted rpcs)
+    DebugTraceCall,
+}
+
+impl FromStr for StateDiffMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "trace_call" => Ok(StateDiffMethod::TraceCall),
+            "trace_callmany" | "trace_call_many" => Ok(StateDiffMethod::TraceCallMany),
+            "debug_tracecall" | "debug_trace_call" | "prestate_tracer" => {
+                Ok(StateDiffMethod::DebugTraceCall)
+            }
+            _ => Err(anyhow!("Unknown state diff method {}", s)),
+        }
+    }
+}
+
+impl StateDiffMethod {
+    /// Trace `tx` on top of `block` (`None` = latest) and return the state it changes
+    pub async fn trace_state_diffs<M, T>(
+        &self,
+        provider: &M,
+        tx: T,
+        block: Option<BlockNumber>,
+    ) -> std::result::Result<BTreeMap<Address, AccountDiff>, StateDiffError>
+    where
+        M: Middleware,
+        T: Into<TypedTransaction> + Send + Sync,
+    {
+        let transient = |e: M::Error| StateDiffError::Transient(format!("{:?}", e));
+        let no_state_diffs = || StateDiffError::Permanent("no state diffs produced".to_string());
+
+        match self {
+            StateDiffMethod::TraceCall => Ok(provider
+                .trace_call(tx, vec![TraceType::StateDiff], block)
+                .await
+                .map_err(transient)?
+                .state_diff
+                .ok_or_else(no_state_diffs)?
+                .0),
+            StateDiffMethod::TraceCallMany => Ok(provider
+                .trace_call_many(vec![(tx, vec![TraceType::StateDiff])], block)
+                .await
+                .map_err(transient)?
+                .pop()
+                .and_then(|trace| trace.state_diff)
+                .ok_or_else(no_state_diffs)?
+                .0),
+            StateDiffMethod::DebugTraceCall => {
+                let tracer_config = GethDebugBuiltInTracerConfig::PreStateTracer(PreStateConfig {
+                    diff_mode: Some(true),
+                });
+                let options = GethDebugTracingCallOptions {
+                    tracing_options: GethDebugTracingOptions {
+                        tracer: Some(GethDebugTracerType::BuiltInTracer(
+                            GethDebugBuiltInTracerType::PreStateTracer,
+                        )),
+                        tracer_config: Some(GethDebugTracerConfig::BuiltInTracer(tracer_config)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+
+                let trace = provider
+                    .debug_trace_call(tx, block.map(BlockId::from), options)
+                    .await
+                    .map_err(transient)?;
+                match trace {
+                    GethTrace::Known(GethTraceFrame::PreStateTracer(PreStateFrame::Diff(diff))) => {
+                        Ok(prestate_diff_to_state_diffs(diff))
+                    }
+                    _ => Err(no_state_diffs()),
+                }
+            }
+        }
+    }
+}
+
+/// True if a v2 pair's storage `value` packs `getReserves()`'s reserves the way uniswap v2 does
+/// (`blockTimestampLast|reserve1|reserve0`)
+/// note: empty pairs would match any zeroed slot, so zero reserves never match
+pub fn packs_v2_reserves(value: U256, reserve_0: U256, reserve_1: U256) -> bool {
+    if reserve_0.is_zero() || reserve_1.is_zero() {
+        return false;
+    }
+    let reserves_mask = (U256::one() << 224) - 1;
+    value & reserves_mask == (reserve_1 << 112) | reserve_0
+}
+
+/// Rebuild the reserve diffs of a v2 router swap from the packed reserves slot of each pair it
+/// trades through (`pairs` in hop order, slot values read with `eth_getStorageAt`), for when the
+/// provider can't trace
+/// note: only the pairs' reserves slot (8) is filled in, which is all v2 sandwich sizing reads
+///
+/// `None` if `pairs` don't match the swap's path or the swap would revert on their reserves
+pub fn v2_swap_state_diffs(
+    swap: &DecodedSwap,
+    pairs: &[(UniswapV2Pool, U256)],
+) -> Option<BTreeMap<Address, AccountDiff>> {
+    if !swap.fees.is_empty() || swap.path.len() != pairs.len() + 1 {
+        return None;
+    }
+
+    let mask = (U256::one() << 112) - 1;
+    // `(zero_for_one, reserve_in, reserve_out, fee_bps)` of each hop
+    let hops = swap
+        .path
+        .windows(2)
+        .zip(pairs)
+        .map(|(hop, (pair, packed))| {
+            let (reserve_0, reserve_1) = (*packed & mask, (*packed >> 112) & mask);
+            // cfmms stores 0.3% as 300
+            let fee_bps = pair.fee / 10;
+            if hop[0] == pair.token_a && hop[1] == pair.token_b {
+                Some((true, reserve_0, reserve_1, fee_bps))
+            } else if hop[0] == pair.token_b && hop[1] == pair.token_a {
+                Some((false, reserve_1, reserve_0, fee_bps))
+            } else {
+                None
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    // `(amount_in, amount_out)` of each hop
+    let amounts = if swap.exact_output {
+        let mut amount_out = swap.amount_out_min;
+        let mut amounts = vec![];
+        for (_, reserve_in, reserve_out, fee_bps) in hops.iter().rev() {
+            let amount_in = get_amount_in(amount_out, *reserve_in, *reserve_out, *fee_bps)?;
+            amounts.push((amount_in, amount_out));
+            amount_out = amount_in;
+        }
+        amounts.reverse();
+        if amount_out > swap.amount_in {
+            return None;
+        }
+        amounts
+    } else {
+        let mut amount_in = swap.amount_in;
+        let mut amounts = vec![];
+        for (_, reserve_in, reserve_out, fee_bps) in &hops {
+            let amount_out = get_amount_out(amount_in, *reserve_in, *reserve_out, *fee_bps);
+            amounts.push((amount_in, amount_out));
+            amount_in = amount_out;
+        }
+        if amount_in.is_zero() || amount_in < swap.amount_out_min {
+            return None;
+        }
+        amounts
+    };
+
+    let to_slot = |value: U256| {
+        let mut bytes = [0u8; 32];
+        value.to_big_endian(&mut bytes);
+        H256::from(bytes)
+    };
+
+    let mut state_diffs = BTreeMap::new();
+    for (((pair, packed), hop), (amount_in, amount_out)) in pairs.iter().zip(hops).zip(amounts) {
+        let (zero_for_one, reserve_in, reserve_out, _) = hop;
+        let reserve_in = reserve_in.checked_add(amount_in).filter(|r| *r <= mask)?;
+        let reserve_out = reserve_out - amount_out;
+        let (reserve_0, reserve_1) = match zero_for_one {
+            true => (reserve_in, reserve_out),
+            false => (reserve_out, reserve_in),
+        };
+        // keep `blockTimestampLast`, nothing reads it
+        let packed_after = (*packed >> 224 << 224) | (reserve_1 << 112) | reserve_0;
+
+        let storage = [(
+            H256::from_low_u64_be(8),
+            Diff::Changed(ChangedType {
+                from: to_slot(*packed),
+                to: to_slot(packed_after),
+            }),
+        )]
+        .into_iter()
+        .collect();
+        let diff = AccountDiff {
+            balance: Diff::Same,
+            nonce: Diff::Same,
+            code: Diff::Same,
+            storage,
+        };
+
+        // a path through the same pair twice can't be rebuilt hop by hop
+        if state_diffs.insert(pair.address, diff).is_some() {
+            return None;
+        }
+    }
+
+    Some(state_diffs)
+}
+
+/// Convert a `prestateTracer` diff mode trace to the `stateDiff` trace format
+/// note: geth leaves fields and storage slots that didn't change (or were zeroed) out of `post`
+pub fn prestate_diff_to_state_diffs(diff: DiffMode) -> BTreeMap<Address, AccountDiff> {
+    let DiffMode { pre, post } = diff;
+    let accounts: BTreeSet<Address> = pre.keys().chain(post.keys()).copied().collect();
+
+    accounts
+        .into_iter()
+        .map(|address| {
+            let before = pre.get(&address);
+            let after = post.get(&address);
+
+            let field_diff = |from: Option<U256>, to: Option<U256>| match (from, to) {
+                (Some(from), Some(to)) if from != to => Diff::Changed(ChangedType { from, to }),
+                (None, Some(to)) => Diff::Born(to),
+                _ => Diff::Same,
+            };
+            let balance = field_diff(
+                before.and_then(|a| a.balance),
+                after.and_then(|a| a.balance),
+            );
+            let nonce = field_diff(
+                before.and_then(|a| a.nonce.map(U256::from)),
+                after.and_then(|a| a.nonce.map(U256::from)),
+            );
+            let code = match (before, after.and_then(|a| a.code.as_ref())) {
+                (None, Some(code)) => code.parse().map(Diff::Born).unwrap_or(Diff::Same),
+                _ => Diff::Same,
+            };
+
+            let empty = BTreeMap::new();
+            let slots_before = before.and_then(|a| a.storage.as_ref()).unwrap_or(&empty);
+            let slots_after = after.and_then(|a| a.storage.as_ref()).unwrap_or(&empty);
+            let storage = slots_before
+                .keys()
+                .chain(slots_after.keys())
+                .filter_map(|slot| {
+                    let from = slots_before.get(slot).copied().unwrap_or_default();
+                    let to = slots_after.get(slot).copied().unwrap_or_default();
+                    (from != to).then_some((*slot, Diff::Changed(ChangedType { from, to })))
+                })
+                .collect();
+
+            (
+                address,
+                AccountDiff {
+                    balance,
+                    nonce,
+                    code,
+                    storage,
+                },
+            )
+        })
+        .collect()
+}
+
+impl FromStr for SimStateSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "latest" => Ok(SimStateSource::Latest),
+            "pending" => Ok(SimStateSource::Pending),
+            block => block
+                .parse::<u64>()
+                .map(|block| SimStateSource::Pinned(block.into()))
+                .map_err(|_| anyhow!("Unknown sim state source {}", s)),
+        }
+    }
+}
+
+/// What we would have submitted for a target block (written by the dry run executor)
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunRecord {
+    pub target_block: U64,
+    pub bundles: Vec<DryRunBundle>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub private_txs: Vec<DryRunPrivateTx>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunBundle {
+    /// Expected net profit after gas and priority fee (in wei)
+    pub expected_profit: String,
+    /// Blocks the bundle would have been sent for
+    pub target_blocks: Vec<U64>,
+    pub bundle: BundleRequest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunPrivateTx {
+    /// Expected net profit after gas and priority fee (in wei)
+    pub expected_profit: String,
+    pub txs: Vec<Bytes>,
+}
+
+impl From<Action> for DryRunRecord {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::SubmitBundle {
+                bundles,
+                target_block,
+                net_profits,
+                target_blocks,
+            } => Self {
+                target_block,
+                bundles: bundles
+                    .into_iter()
+                    .zip(net_profits)
+                    .zip(target_blocks)
+                    .map(|((bundle, net_profit), target_blocks)| DryRunBundle {
+                        expected_profit: net_profit.to_string(),
+                        target_blocks,
+                        bundle: bundle.set_block(target_block),
+                    })
+                    .collect(),
+                private_txs: vec![],
+            },
+            Action::SubmitPrivateTx {
+                txs,
+                target_block,
+                net_profit,
+            } => Self {
+                target_block,
+                bundles: vec![],
+                private_txs: vec![DryRunPrivateTx {
+                    expected_profit: net_profit.to_string(),
+                    txs,
+                }],
+            },
+        }
+    }
 }
 
 /// Configuration for variables needed for sandwiches
 #[derive(Debug, Clone)]
 pub struct StratConfig {
-    pub sando_address: Address,
+    /// Sando contracts to rotate between (all owned by `searcher_signer`)
+    pub sando_addresses: Vec<Address>,
+    /// How the sando contract for each bundle is picked from `sando_addresses`
+    pub sando_rotation: SandoRotation,
+    /// Seed for every random choice (sando rotation, probe recipients), pin it to replay a
+    /// run's decisions (`None` = random seed, logged at startup)
+    pub rng_seed: Option<u64>,
+    /// Gas used by sando contracts that differ from the default estimates (e.g. one built for
+    /// v3 swaps), when set every contract sizes each sandwich and the one netting most is used
+    pub sando_gas_profiles: HashMap<Address, SandoGasProfile>,
     pub sando_inception_block: U64,
-    pub searcher_signer: LocalWallet,
+    pub searcher_signer: SearcherSigner,
+    /// Stop optimal input search once revenue improves by less than this (in wei)
+    pub optimizer_epsilon: U256,
+    /// Only sandwich if net profit after gas is greater than this (in wei)
+    pub min_profit_threshold: U256,
+    /// Max difference between simulated and analytic revenue before rejecting (in bps)
+    pub simulation_tolerance_bps: u64,
+    /// Pools whose simulations disagree with our math beyond `simulation_tolerance_bps` at least
+    /// this often (0.0 to 1.0) are no longer sandwiched until reviewed (`None` = never)
+    pub max_pool_divergence_rate: Option<f64>,
+    /// Skip v3 pools with a protocol fee switched on or an onchain fee that isn't the tier we
+    /// price swaps with (forks can charge protocol fees on top of the lp fee)
+    pub exclude_fee_switch_pools: bool,
+    /// Sandwiches netting at least this much (in wei) are simulated after the pending txs on
+    /// their pool that outbid the victim, in the order a builder runs them (`None` = only after
+    /// the victim's block state, every extra tx makes the simulation slower)
+    pub preceding_txs_min_profit: Option<U256>,
+    /// Where victims are assumed to land, `AfterPending` sizes and simulates every sandwich
+    /// after the pending txs that outbid its victim (whatever `preceding_txs_min_profit` is)
+    pub victim_position_assumption: VictimPosition,
+    /// Tokens to never sandwich (e.g. known fee-on-transfer or honeypot tokens)
+    pub blacklist: HashSet<Address>,
+    /// Dexes to sync pools from and monitor for swaps
+    pub dexes: Vec<DexConfig>,
+    /// Fraction of net profit paid as priority fee when uncontested (0.0 to 1.0)
+    pub bid_fraction: f64,
+    /// How contested our flow is (0.0 to 1.0), higher bids more of our profit
+    pub competition_level: f64,
+    /// Smallest priority fee to bid per bundle (in wei)
+    pub min_tip: U256,
+    /// Largest priority fee to bid per bundle (in wei)
+    pub max_tip: U256,
+    /// Added to the bid fraction every time another searcher is seen competing for a victim
+    /// (a bundle missing its block while the victim stays pending, or a searcher tx on the
+    /// bundle's pool), 0.0 never escalates
+    pub bid_escalation_step: f64,
+    /// Escalated bids never pay more than this fraction of net profit
+    pub max_bid_fraction: f64,
+    /// Skip victims swapping in less than this much weth/eth before tracing them (in wei)
+    pub min_victim_swap_value: U256,
+    /// Times to try tracing a victim tx before giving up on a transient rpc error
+    pub state_diff_attempts: u32,
+    /// Delay before the first trace retry (doubles after every attempt)
+    pub state_diff_backoff_ms: u64,
+    /// Rebuild v2 router swaps' reserve diffs from `eth_getStorageAt` reads when tracing a
+    /// victim keeps failing (v3 and unknown swaps are still dropped)
+    pub state_diff_storage_fallback: bool,
+    /// Port that prometheus metrics are served on
+    pub metrics_port: u16,
+    /// Where to write a final metrics snapshot on shutdown (`None` = don't write one)
+    pub metrics_snapshot_path: Option<String>,
+    /// Append a json line for every sized opportunity and its outcome here (`None` = off)
+    pub opportunity_log_path: Option<String>,
+    /// Webhook (e.g. a discord channel's) that every landed sandwich is posted to (`None` = off)
+    pub alert_webhook: Option<String>,
+    /// Alert when a sando contract's weth inventory drops below this (in wei, `None` = never)
+    pub min_weth_inventory: Option<U256>,
+    /// Alert when the searcher's eth for gas drops below this (in wei, `None` = never)
+    pub min_searcher_balance: Option<U256>,
+    /// Stop submitting once landed bundles lose more than this (in wei) over
+    /// `loss_window_blocks` (`None` = never stop)
+    pub max_window_loss: Option<U256>,
+    /// Blocks that realized profit is summed over for `max_window_loss`
+    pub loss_window_blocks: u64,
+    /// Submit again this many blocks after the loss guard trips (`None` = only after a restart)
+    pub loss_cooldown_blocks: Option<u64>,
+    /// Stop evaluating a sender's txs once this many bundles around them in a row didn't land
+    /// (`None` = never)
+    pub victim_max_misses: Option<u32>,
+    /// Blocks that a sender's txs aren't evaluated for after `victim_max_misses`
+    pub victim_cooldown_blocks: u64,
+    /// `/health` reports unhealthy once no block was processed for this many seconds
+    pub health_stale_after_secs: u64,
+    /// Respect slippage of victims that route through two pools (otherwise only the pool we
+    /// sandwich is considered)
+    pub enable_multihop: bool,
+    /// If no sandwich is found, sell the sando contract's token inventory into the victim's
+    /// price impact with a private backrun
+    pub enable_private_backruns: bool,
+    /// If no sandwich or private backrun is found, look for a v2 pool whose price the victim
+    /// leaves behind, buy the token there and sell it back into the victim's pool
+    pub enable_backrun_arb: bool,
+    /// Orderings of our txs around a v2 victim that the evaluator may build, it picks the most
+    /// profitable one per pool (`BackrunOnly` also needs `enable_private_backruns`)
+    pub ordering_strategies: Vec<OrderingStrategy>,
+    /// Discount (in bps) on the tokens a `FrontrunOnly` bundle keeps when pricing it, they're
+    /// only sold later at whatever the price is by then
+    pub frontrun_only_haircut_bps: u64,
+    /// When a victim touches several pools, evaluate the ones that made the most per sized
+    /// opportunity first (they get the first simulation permits)
+    pub prioritize_profitable_pools: bool,
+    /// Merge a victim into our bundle on a v2 pool when they swap the same way for the same
+    /// block (one frontrun before both victims and one backrun after)
+    pub enable_bundle_merging: bool,
+    /// How a victim trading a v2 pool against the victims of a bundle already out on it for the
+    /// same block is handled (two sandwiches pushing the pool opposite ways undercut each other)
+    pub opposing_victims: OpposingVictims,
+    /// Drop a victim once less than this many ms are left before the target block is expected
+    /// (state diffs and simulation would finish too late to land), 0 still drops victims whose
+    /// target block's slot has already started
+    pub min_time_budget_ms: u64,
+    /// Gas limit of our txs as a multiple of their simulated gas (profit math still uses the
+    /// simulated gas)
+    pub gas_limit_multiplier: f64,
+    /// Backruns ask for this much less than their simulated output (in bps) so txs landing between
+    /// our frontrun and backrun don't revert the sandwich, the difference stays in the pool
+    pub backrun_min_out_buffer_bps: u64,
+    /// Predicted base fees are raised by this much (in bps, up to one block's 12.5% rise) before
+    /// checking that our bundle and its victim can pay it, and our txs' max fee covers it
+    pub base_fee_safety_margin_bps: u64,
+    /// Report monitored pools whose reserves/price move more than this in a block (in bps), `None`
+    /// disables it (costs an `eth_getLogs` call per block)
+    pub pool_update_threshold_bps: Option<u64>,
+    /// Apply every block's pool logs so cached pool spot prices track the chain (costs the same
+    /// `eth_getLogs` call, shared with pool updates and activity tracking), otherwise prices are
+    /// as of the pools' last sync
+    pub track_pool_prices: bool,
+    /// Pools moving more than this in a block (in bps) are hot for a few blocks, victims on hot
+    /// pools are evaluated first (`None` = no pool is hot)
+    pub hot_pool_change_bps: Option<u64>,
+    /// Pools without a swap or sync for this many blocks are cold, victims only touching cold
+    /// pools are evaluated last (`None` = no pool goes cold)
+    pub cold_pool_after_blocks: Option<u64>,
+    /// Re-read every hot pool's state with a multicall at the end of each block, so state
+    /// drifted from missed logs is caught (costs an `eth_call` per 100 hot pools a block)
+    pub refresh_hot_pools: bool,
+    /// Submit bundles (`Live`) or only record them (`DryRun`)
+    pub mode: BotMode,
+    /// Consecutive failed resyncs (after a block fails to process) before the bot gives up
+    pub max_resync_failures: u32,
+    /// Skip pools with less weth liquidity than this (v3 counts liquidity at the current tick)
+    pub min_pool_liquidity: U256,
+    /// Pools where both tokens are in this set are never sandwiched (too little price impact)
+    pub stable_tokens: HashSet<Address>,
+    /// Rebasing/elastic supply tokens that are never sandwiched (detected ones are added)
+    pub rebasing_tokens: HashSet<Address>,
+    /// Standalone `Sync`s (reserves caught up without a swap) on a token's v2 pools before it
+    /// is treated as rebasing, 0 turns detection off
+    pub rebase_sync_strikes: u32,
+    /// Searcher EOAs and contracts whose txs are never sandwiched (usually other bots' frontruns)
+    pub known_searchers: HashSet<Address>,
+    /// Aggregator routers whose swaps are inferred from the pools they touch instead of decoded
+    pub aggregator_routers: HashSet<Address>,
+    /// Only load and sandwich these pools, factories aren't scanned for others (`None` = every
+    /// pool of `dexes`)
+    pub pool_whitelist: Option<HashSet<Address>>,
+    /// Skip victims paying a higher priority fee than this, only searchers bid this much (in wei)
+    pub max_victim_priority_fee: U256,
+    /// Skip victims that could tip more than this share of the weth/eth they swap (in bps),
+    /// `None` disables it
+    pub max_victim_tip_bps: Option<u64>,
+    /// Most weth a single frontrun spends (in wei), sandwiches are sized to the smaller of this
+    /// and the sando contract's weth balance (`None` = whole balance)
+    pub max_position_weth: Option<U256>,
+    /// Furthest a frontrun may move its pool's price (in bps), sandwiches are sized to the best
+    /// frontrun within the cap (`None` = uncapped)
+    pub max_frontrun_price_impact_bps: Option<u64>,
+    /// Blocks to keep re-evaluating a tx that couldn't pay the base fee when we first saw it
+    pub parked_tx_ttl_blocks: u64,
+    /// Weth (or the chain's wrapped native token, e.g. wmatic), profit is valued in this token
+    /// and only pools pairing it are sandwiched
+    pub weth_address: Address,
+    /// Most victim traces and sandwich simulations in flight at once (protects against rpc rate
+    /// limits, extra calls wait for a slot)
+    pub max_concurrent_sims: usize,
+    /// A bundle on the same pool and block as an outstanding bundle replaces it only if its net
+    /// profit is higher by at least this much (in basis points)
+    pub min_replacement_gain_bps: u64,
+    /// Most bundles sent for the same target block (each uses the next pair of searcher nonces)
+    pub max_bundles_per_block: u32,
+    /// Hold sandwiches until this many ms before their target block, then only send the most
+    /// profitable ones that fit the block's budget (`None` = send each as soon as it is found)
+    pub rank_bundles_ms_before_block: Option<u64>,
+    /// Most gas our frontruns and backruns may use summed over a block's ranked bundles
+    pub max_bundle_gas_per_block: Option<u64>,
+    /// Most of the block's gas limit (0 to 1) that a block's ranked bundles may use between them
+    pub max_block_gas_fraction: Option<f64>,
+    /// Abandon a victim whose tracing or evaluation takes longer than this (in ms), so a slow
+    /// rpc can't stall the events behind it (`None` = wait as long as it takes)
+    pub victim_timeout_ms: Option<u64>,
+    /// Most gas a victim (or tx ahead of our bundle) may burn in our local simulation, a tx that
+    /// runs out of it with a higher gas limit isn't simulated further (`None` = its own limit)
+    pub max_sim_gas: Option<u64>,
+    /// Blocks of factory logs queried at once while discovering pools, full discovery then
+    /// scans factories in such chunks too (`None` = cfmms syncs every pool)
+    pub sync_chunk_blocks: Option<u64>,
+    /// Pools whose tokens and reserves are fetched at once while discovering pools (raise it
+    /// on rpcs that allow many concurrent requests)
+    pub sync_concurrency: usize,
+    /// Blocks after the next one that each bundle is sent for (0 = the next block), our txs are
+    /// priced to cover the base fee of the furthest one
+    pub target_block_offsets: Vec<u64>,
+    /// Blocks after a bundle's last target block that its receipts are still checked for
+    /// before it counts as not landed (covers nodes that index receipts late)
+    pub inclusion_check_blocks: u64,
+    /// Colored text or structured json output from the logging macros
+    pub log_format: LogFormat,
+    /// State that victims are traced and sandwiches are simulated on
+    pub sim_state_source: SimStateSource,
+    /// Check the chain's tip before submitting and drop bundles (and private txs) if it already
+    /// passed the block their state was sourced from (we fell behind under load)
+    pub stale_state_guard: bool,
+    /// Rpc method victims (and token transfer probes) are traced with
+    pub state_diff_method: StateDiffMethod,
+    /// When our frontruns and backruns carry the access list their simulation touched
+    pub access_list_mode: AccessListMode,
+    /// Pay the builder through the backrun's priority fee or a direct coinbase transfer
+    pub payment_strategy: PaymentStrategy,
+}
+
+impl StratConfig {
+    /// Create config with default values for all tunable parameters
+    pub fn new(
+        sando_address: Address,
+        sando_inception_block: U64,
+        searcher_signer: SearcherSigner,
+    ) -> Self {
+        Self {
+            sando_addresses: vec![sando_address],
+            sando_rotation: SandoRotation::default(),
+            rng_seed: None,
+            sando_gas_profiles: HashMap::new(),
+            sando_inception_block,
+            searcher_signer,
+            optimizer_epsilon: U256::from(10_000_000_000u64), // 10 gwei
+            min_profit_threshold: U256::zero(),
+            simulation_tolerance_bps: 500, // 5%
+            max_pool_divergence_rate: None,
+            exclude_fee_switch_pools: false,
+            preceding_txs_min_profit: None,
+            victim_position_assumption: VictimPosition::TopOfBlock,
+            blacklist: HashSet::new(),
+            dexes: DexConfig::mainnet_defaults(),
+            bid_fraction: 0.9,
+            competition_level: 0.0,
+            min_tip: U256::zero(),
+            max_tip: U256::MAX,
+            bid_escalation_step: 0.0,
+            max_bid_fraction: 0.95,
+            min_victim_swap_value: U256::zero(),
+            state_diff_attempts: 3,
+            state_diff_backoff_ms: 50,
+            state_diff_storage_fallback: false,
+            metrics_port: 9090,
+            health_stale_after_secs: 30,
+            metrics_snapshot_path: None,
+            opportunity_log_path: None,
+            alert_webhook: None,
+            min_weth_inventory: None,
+            min_searcher_balance: None,
+            max_window_loss: None,
+            loss_window_blocks: 300,
+            loss_cooldown_blocks: None,
+            victim_max_misses: None,
+            victim_cooldown_blocks: 50,
+            enable_multihop: false,
+            enable_private_backruns: false,
+            enable_backrun_arb: false,
+            ordering_strategies: vec![OrderingStrategy::Sandwich, OrderingStrategy::BackrunOnly],
+            frontrun_only_haircut_bps: 100,
+            prioritize_profitable_pools: false,
+            enable_bundle_merging: false,
+            opposing_victims: OpposingVictims::Independent,
+            min_time_budget_ms: 0,
+            gas_limit_multiplier: 10.0 / 7.0,
+            backrun_min_out_buffer_bps: 0,
+            base_fee_safety_margin_bps: 0,
+            pool_update_threshold_bps: None,
+            track_pool_prices: false,
+            hot_pool_change_bps: None,
+            cold_pool_after_blocks: None,
+            refresh_hot_pools: false,
+            mode: BotMode::Live,
+            max_resync_failures: 3,
+            min_pool_liquidity: U256::zero(),
+            stable_tokens: STABLE_TOKENS.iter().copied().collect(),
+            rebasing_tokens: REBASING_TOKENS.iter().copied().collect(),
+            rebase_sync_strikes: 2,
+            known_searchers: HashSet::new(),
+            aggregator_routers: AGGREGATOR_ROUTERS.iter().copied().collect(),
+            pool_whitelist: None,
+            max_victim_priority_fee: U256::MAX,
+            max_victim_tip_bps: None,
+            max_position_weth: None,
+            max_frontrun_price_impact_bps: None,
+            weth_address: *WETH_ADDRESS,
+            max_concurrent_sims: 8,
+            min_replacement_gain_bps: 1_000,
+            max_bundles_per_block: 1,
+            rank_bundles_ms_before_block: None,
+            max_bundle_gas_per_block: None,
+            max_block_gas_fraction: None,
+            victim_timeout_ms: None,
+            max_sim_gas: None,
+            sync_chunk_blocks: None,
+            sync_concurrency: POOL_SYNC_CONCURRENCY,
+            target_block_offsets: vec![0],
+            inclusion_check_blocks: 2,
+            parked_tx_ttl_blocks: 5,
+            log_format: LogFormat::Text,
+            sim_state_source: SimStateSource::Latest,
+            stale_state_guard: false,
+            state_diff_method: StateDiffMethod::TraceCall,
+            access_list_mode: AccessListMode::WhenCheaper,
+            payment_strategy: PaymentStrategy::PriorityFee,
+        }
+    }
+}
+
+/// Gas a sando contract uses per swap (used for sizing before simulating), contracts built
+/// for one pool kind can be cheaper on it than on the other (v3 ticks crossed add the same gas
+/// on every contract)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandoGasProfile {
+    pub v2_frontrun: u64,
+    pub v2_backrun: u64,
+    pub v3_frontrun: u64,
+    pub v3_backrun: u64,
+}
+
+impl Default for SandoGasProfile {
+    fn default() -> Self {
+        Self {
+            v2_frontrun: V2_FRONTRUN_GAS_ESTIMATE,
+            v2_backrun: V2_BACKRUN_GAS_ESTIMATE,
+            v3_frontrun: V3_FRONTRUN_GAS_ESTIMATE,
+            v3_backrun: V3_BACKRUN_GAS_ESTIMATE,
+        }
+    }
+}
+
+/// A uniswap v2/v3 style dex (or fork) to monitor
+#[derive(Debug, Clone)]
+pub struct DexConfig {
+    pub factory: Address,
+    /// Swap fee in basis points (e.g. 30 for 0.3%), ignored for v3 where the fee is per pool
+    pub fee_bps: u32,
+    pub kind: DexVariant,
+    /// Block that the factory was deployed (pools are synced from here)
+    pub creation_block: u64,
+    /// Routers that users swap through (only txs sent to these or directly to a pool are traced)
+    pub routers: Vec<Address>,
+    /// V3 fee tiers to monitor in hundredths of a bip (500 = 0.05%), empty monitors every tier
+    pub fee_tiers: Vec<u32>,
+}
+
+impl DexConfig {
+    pub fn new(
+        factory: Address,
+        fee_bps: u32,
+        kind: DexVariant,
+        creation_block: u64,
+        routers: Vec<Address>,
+    ) -> Self {
+        Self {
+            factory,
+            fee_bps,
+            kind,
+            creation_block,
+            routers,
+            fee_tiers: vec![],
+        }
+    }
+
+    /// Only monitor v3 pools with one of these fee tiers (each tier of a pair is its own pool)
+    pub fn with_fee_tiers(mut self, fee_tiers: Vec<u32>) -> Self {
+        self.fee_tiers = fee_tiers;
+        self
+    }
+
+    /// True if this dex's config monitors `pool` (v2 pools share the dex's single fee)
+    pub fn monitors_pool(&self, pool: &Pool) -> bool {
+        match pool {
+            Pool::UniswapV2(_) => true,
+            Pool::UniswapV3(p) => self.fee_tiers.is_empty() || self.fee_tiers.contains(&p.fee),
+        }
+    }
+
+    /// Uniswap and the v2 forks on mainnet that we sandwich by default
+    pub fn mainnet_defaults() -> Vec<Self> {
+        [
+            // Uniswap v2
+            (
+                "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f",
+                30,
+                DexVariant::UniswapV2,
+                10000835,
+                vec![
+                    "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D", // router02
+                    "0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD", // universal router
+                ],
+            ),
+            // Sushiswap
+            (
+                "0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac",
+                30,
+                DexVariant::UniswapV2,
+                10794229,
+                vec!["0xd9e1cE17f2641f24aE83637ab66a2cca9C378B9F"],
+            ),
+            // Crypto.com swap
+            (
+                "0x9DEB29c9a4c7A88a3C0257393b7f3335338D9A9D",
+                30,
+                DexVariant::UniswapV2,
+                10828414,
+                vec!["0xCeB90E4C17d626BE0fACd78b79c9c87d7ca181b3"],
+            ),
+            // Convergence swap (no router tracked, only direct pool calls are traced)
+            (
+                "0x4eef5746ED22A2fD368629C1852365bf5dcb79f1",
+                30,
+                DexVariant::UniswapV2,
+                12385067,
+                vec![],
+            ),
+            // Pancakeswap
+            (
+                "0x1097053Fd2ea711dad45caCcc45EfF7548fCB362",
+                25,
+                DexVariant::UniswapV2,
+                15614590,
+                vec!["0xEfF92A263d31888d860bD50809A8D171709b7b1c"],
+            ),
+            // ShibaSwap
+            (
+                "0x115934131916C8b277DD010Ee02de363c09d037c",
+                30,
+                DexVariant::UniswapV2,
+                12771526,
+                vec!["0x03f7724180AA6b939894B5Ca4314783B0b36b329"],
+            ),
+            // Saitaswap (no router tracked, only direct pool calls are traced)
+            (
+                "0x35113a300ca0D7621374890ABFEAC30E88f214b1",
+                30,
+                DexVariant::UniswapV2,
+                15210780,
+                vec![],
+            ),
+            // Uniswap v3
+            (
+                "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+                30,
+                DexVariant::UniswapV3,
+                12369621,
+                vec![
+                    "0xE592427A0AEce92De3Edee1F18E0157C05861564", // swap router
+                    "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45", // swap router02
+                    "0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD", // universal router
+                ],
+            ),
+        ]
+        .into_iter()
+        .map(|(factory, fee_bps, kind, creation_block, routers)| {
+            let dex = Self::new(
+                factory.parse().unwrap(),
+                fee_bps,
+                kind,
+                creation_block,
+                routers.into_iter().map(|r| r.parse().unwrap()).collect(),
+            );
+
+            match dex.kind {
+                DexVariant::UniswapV2 => dex,
+                DexVariant::UniswapV3 => dex.with_fee_tiers(V3_FEE_TIERS.to_vec()),
+            }
+        })
+        .collect()
+    }
+}
+
+/// A pending tx used up the gas its simulation was capped at (`max_sim_gas`) while its own gas
+/// limit allows more, the rest of it isn't run so a pathological tx can't stall the bot
+#[derive(Error, Debug)]
+#[error("tx {tx_hash:?} ran out of the {gas_cap} gas simulations are capped at")]
+pub struct SimGasCapExceeded {
+    pub tx_hash: H256,
+    pub gas_cap: u64,
+}
+
+/// Reasons that filling a victim's state diffs can fail
+#[derive(Error, Debug)]
+pub enum StateDiffError {
+    /// Provider/transport failed, retrying may succeed
+    #[error("failed to trace victim tx: {0}")]
+    Transient(String),
+    /// Trace succeeded but can't be used, retrying won't help
+    #[error("not sandwichable: {0}")]
+    Permanent(String),
+}
+
+/// Why a pending tx (or one of the pools it touches) wasn't sandwiched, counted per reason in
+/// `sando_rejections`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RejectReason {
+    /// Max fee is below the next block's base fee (parked until it drops)
+    CantInclude,
+    /// Swap deadline passes before the next block
+    SwapExpired,
+    /// Looks like another searcher's tx
+    SearcherTx,
+    /// Swap is worth less than `min_victim_swap_value`
+    SwapTooSmall,
+    /// Tips more than `max_victim_tip_bps` of its swap
+    TipTooHigh,
+    /// Less than `min_time_budget_ms` left before the target block
+    DeadlineMissed,
+    /// Tracing or evaluating took longer than `victim_timeout_ms`
+    Timeout,
+    /// State diffs couldn't be traced
+    TraceFailed,
+    /// Victim touches no pool we monitor
+    NoTouchedPools,
+    /// Pool trades a blacklisted or rebasing token
+    Blacklisted,
+    /// Pool trades two stables
+    StablePair,
+    /// Pool's token taxes or blocks transfers
+    TransferTax,
+    /// Pool holds less than `min_pool_liquidity`
+    LowLiquidity,
+    /// Victim moves a v3 pool's price less than our round trip fees
+    SmallPriceMove,
+    /// Victim sells the token for weth, only `weth->token` swaps are sandwiched
+    WrongDirection,
+    /// Can't tell which way the victim swaps
+    UnknownDirection,
+    /// Sandwich can't clear `min_profit_threshold` even taking all of the victim's slippage
+    BelowProfitScreen,
+    /// No frontrun leaves the victim's swap above their slippage floor at a profit
+    SlippageBound,
+    /// Optimal sandwich is below `min_profit_threshold`
+    BelowMinProfit,
+    /// Sandwich failed simulation
+    SimulationFailed,
+    /// Simulated revenue disagrees with our math
+    SimulationMismatch,
+    /// No weth pool to value the revenue with
+    NoWethQuote,
+    /// Below `min_profit_threshold` after simulated gas
+    UnprofitableAfterGas,
+    /// Our txs or the victim can't pay a target block's base fee
+    BaseFeeTooHigh,
+    /// A more profitable bundle on the pool is already out for the block
+    ConflictingBundle,
+    /// Victim trades against a bundle already out on the pool for the block, and netting them
+    /// leaves no profitable sandwich (or `opposing_victims` skips it)
+    OpposingVictims,
+    /// Held sandwich didn't make its block's top bundles
+    Outranked,
+    /// Held sandwich's target block passed before it was ranked
+    HeldPastBlock,
+    /// No searcher nonces left in the block's bundle budget
+    BundleBudget,
+    /// Bundle couldn't be signed or encoded
+    BuildFailed,
+    /// Victim was mined while we were evaluating it
+    AlreadyMined,
+    /// Pool's simulations disagree with our math more often than `max_pool_divergence_rate`
+    DivergentPool,
+    /// Pool's fee settings may make swaps pay something else than its fee tier
+    FeeSwitch,
+    /// Too many bundles around the sender's txs missed in a row (see `victim_max_misses`)
+    VictimCooldown,
+}
+
+impl RejectReason {
+    /// Label of the reason in metrics and logs
+    pub fn label(&self) -> &'static str {
+        match self {
+            RejectReason::CantInclude => "cant_include",
+            RejectReason::SwapExpired => "swap_expired",
+            RejectReason::SearcherTx => "searcher_tx",
+            RejectReason::SwapTooSmall => "swap_too_small",
+            RejectReason::TipTooHigh => "tip_too_high",
+            RejectReason::DeadlineMissed => "deadline_missed",
+            RejectReason::Timeout => "timeout",
+            RejectReason::TraceFailed => "trace_failed",
+            RejectReason::NoTouchedPools => "no_touched_pools",
+            RejectReason::Blacklisted => "blacklisted",
+            RejectReason::StablePair => "stable_pair",
+            RejectReason::TransferTax => "transfer_tax",
+            RejectReason::LowLiquidity => "low_liquidity",
+            RejectReason::SmallPriceMove => "small_price_move",
+            RejectReason::WrongDirection => "wrong_direction",
+            RejectReason::UnknownDirection => "unknown_direction",
+            RejectReason::BelowProfitScreen => "below_profit_screen",
+            RejectReason::SlippageBound => "slippage_bound",
+            RejectReason::BelowMinProfit => "below_min_profit",
+            RejectReason::SimulationFailed => "simulation_failed",
+            RejectReason::SimulationMismatch => "simulation_mismatch",
+            RejectReason::NoWethQuote => "no_weth_quote",
+            RejectReason::UnprofitableAfterGas => "unprofitable_after_gas",
+            RejectReason::BaseFeeTooHigh => "base_fee_too_high",
+            RejectReason::ConflictingBundle => "conflicting_bundle",
+            RejectReason::OpposingVictims => "opposing_victims",
+            RejectReason::Outranked => "outranked",
+            RejectReason::HeldPastBlock => "held_past_block",
+            RejectReason::BundleBudget => "bundle_budget",
+            RejectReason::BuildFailed => "build_failed",
+            RejectReason::AlreadyMined => "already_mined",
+            RejectReason::DivergentPool => "divergent_pool",
+            RejectReason::FeeSwitch => "fee_switch",
+            RejectReason::VictimCooldown => "victim_cooldown",
+        }
+    }
+}
+
+/// Strategy's view of the chain can no longer be trusted
+#[derive(Error, Debug)]
+pub enum SyncError {
+    /// Processing a block failed, resyncing state may recover
+    #[error("failed to process block {block}: {reason}")]
+    BlockProcessing { block: U64, reason: String },
+    /// Resyncing failed too many times in a row, bot can't recover on its own
+    #[error("strategy is out of sync after {attempts} failed resyncs: {reason}")]
+    Fatal { attempts: u32, reason: String },
+}
+
+/// Why one of the strategy's public calls failed, so callers can tell failures worth retrying
+/// from ones that aren't
+#[derive(Error, Debug)]
+pub enum SandoError {
+    /// Rpc request failed (transport, timeout or node error), retrying may succeed
+    #[error("provider error: {0}")]
+    ProviderError(String),
+    /// Node response, tx or cached file couldn't be decoded, retrying won't help
+    #[error("decode error: {0}")]
+    DecodeError(String),
+    /// Sandwich couldn't be executed in revm (reverted, halted or not salmonella free)
+    #[error("simulation failed: {0}")]
+    SimulationFailed(String),
+    /// Our copy of the chain (pools, blocks, nonces) is behind, resyncing may recover
+    #[error("stale state: {0}")]
+    StaleState(String),
+    /// Victim can't be sandwiched at a profit
+    #[error("no opportunity: {0}")]
+    NoOpportunity(String),
+    /// Anything that doesn't fit the other variants
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl SandoError {
+    /// True if making the same call again may succeed, unknown failures count as retryable
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SandoError::ProviderError(_) | SandoError::StaleState(_) | SandoError::Other(_) => true,
+            SandoError::DecodeError(_)
+            | SandoError::SimulationFailed(_)
+            | SandoError::NoOpportunity(_) => false,
+        }
+    }
+}
+
+impl From<anyhow::Error> for SandoError {
+    /// Unwraps a `SandoError` raised below an anyhow call, otherwise classified by the first
+    /// provider or decode error in the error's chain (`Other` if neither)
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<SandoError>() {
+            Ok(e) => return e,
+            Err(e) => e,
+        };
+        for cause in e.chain() {
+            if cause.is::<ProviderError>() {
+                return SandoError::ProviderError(format!("{:#}", e));
+            }
+            if cause.is::<serde_json::Error>() || cause.is::<abi::Error>() {
+                return SandoError::DecodeError(format!("{:#}", e));
+            }
+        }
+        SandoError::Other(e)
+    }
+}
+
+/// A pool's reserves or price, e.g. after the victim tx (parsed from its storage diffs) or after a
+/// block (from its `Sync`/`Swap` logs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolState {
+    /// Uniswap v2 style pool reserves
+    V2 { reserve_0: U256, reserve_1: U256 },
+    /// Uniswap v3 style pool price, `liquidity` is `None` if the victim didn't change it (no
+    /// initialized tick was crossed)
+    V3 {
+        sqrt_price_x96: U256,
+        tick: i32,
+        liquidity: Option<u128>,
+    },
+}
+
+impl From<&Pool> for PoolState {
+    fn from(pool: &Pool) -> Self {
+        match pool {
+            Pool::UniswapV2(p) => PoolState::V2 {
+                reserve_0: p.reserve_0.into(),
+                reserve_1: p.reserve_1.into(),
+            },
+            Pool::UniswapV3(p) => PoolState::V3 {
+                sqrt_price_x96: p.sqrt_price,
+                tick: p.tick,
+                liquidity: Some(p.liquidity),
+            },
+        }
+    }
+}
+
+impl PoolState {
+    /// Largest relative move of any reserve (v2) or of price/liquidity (v3) since `previous`, in
+    /// basis points
+    ///
+    /// Returns `None` if the states are of different pool types
+    pub fn change_bps(&self, previous: &PoolState) -> Option<u64> {
+        // a value appearing from zero counts as a full move
+        let change = |before: U256, after: U256| -> u64 {
+            if before.is_zero() {
+                return if after.is_zero() { 0 } else { 10_000 };
+            }
+            let delta = if after > before {
+                after - before
+            } else {
+                before - after
+            };
+            (delta.saturating_mul(U256::from(10_000)) / before)
+                .min(U256::from(u64::MAX))
+                .as_u64()
+        };
+
+        match (previous, self) {
+            (
+                PoolState::V2 {
+                    reserve_0: before_0,
+                    reserve_1: before_1,
+                },
+                PoolState::V2 {
+                    reserve_0: after_0,
+                    reserve_1: after_1,
+                },
+            ) => Some(change(*before_0, *after_0).max(change(*before_1, *after_1))),
+            (
+                PoolState::V3 {
+                    sqrt_price_x96: before_price,
+                    liquidity: before_liquidity,
+                    ..
+                },
+                PoolState::V3 {
+                    sqrt_price_x96: after_price,
+                    liquidity: after_liquidity,
+                    ..
+                },
+            ) => {
+                let liquidity_change = match (before_liquidity, after_liquidity) {
+                    (Some(before), Some(after)) => change((*before).into(), (*after).into()),
+                    _ => 0,
+                };
+                Some(change(*before_price, *after_price).max(liquidity_change))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A monitored pool that moved more than `pool_update_threshold_bps` in a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolUpdate {
+    pub block: U64,
+    pub pool: Address,
+    /// State at the end of the previous block
+    pub before: PoolState,
+    pub after: PoolState,
+    /// See `PoolState::change_bps`
+    pub change_bps: u64,
+}
+
+/// Where an opportunity that hasn't played out yet is in the pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingStage {
+    /// Simulated and waiting for its block's ranking (see `rank_bundles_ms_before_block`)
+    Held,
+    /// Sent and waiting for its target block to be mined
+    Submitted,
+}
+
+/// Snapshot of an opportunity that hasn't played out yet (see
+/// `SandoBot::pending_opportunities`)
+#[derive(Debug, Clone)]
+pub struct PendingOpportunity {
+    pub stage: PendingStage,
+    pub pool: Address,
+    /// Block the opportunity is held for or was first sent for
+    pub target_block: U64,
+    /// Net profit after gas and the builder's bid
+    pub net_profit: I256,
+    pub priority_fee: U256,
+    /// What was recorded about the opportunity so far
+    pub record: OpportunityRecord,
+}
+
+/// How active a monitored pool has been lately, victims are evaluated in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PoolActivity {
+    /// Moved at least `hot_pool_change_bps` within the last `HOT_POOL_WINDOW_BLOCKS` blocks
+    Hot,
+    Active,
+    /// No swap or sync for `cold_pool_after_blocks` blocks
+    Cold,
+}
+
+impl PoolActivity {
+    /// Metric label
+    pub fn label(&self) -> &'static str {
+        match self {
+            PoolActivity::Hot => "hot",
+            PoolActivity::Active => "active",
+            PoolActivity::Cold => "cold",
+        }
+    }
+
+    /// Classify a pool as of `block` from the last block it swapped or synced in (or when
+    /// tracking started) and the last block it moved at least `hot_pool_change_bps` in
+    /// note: a pool that is both hot and quiet for `cold_after_blocks` counts as hot
+    pub fn classify(
+        block: U64,
+        last_active: U64,
+        last_hot: Option<U64>,
+        cold_after_blocks: Option<u64>,
+    ) -> Self {
+        if let Some(last_hot) = last_hot {
+            if block.as_u64().saturating_sub(last_hot.as_u64()) < HOT_POOL_WINDOW_BLOCKS {
+                return PoolActivity::Hot;
+            }
+        }
+        match cold_after_blocks {
+            Some(cold_after_blocks)
+                if block.as_u64().saturating_sub(last_active.as_u64()) >= cold_after_blocks =>
+            {
+                PoolActivity::Cold
+            }
+            _ => PoolActivity::Active,
+        }
+    }
+}
+
+/// Size of the monitored pool set (see `PoolManager::metrics_snapshot`), published as gauges
+#[derive(Debug, Clone)]
+pub struct PoolManagerMetrics {
+    /// Monitored pools discovered from each dex's factory
+    pub pools_by_dex: Vec<(DexConfig, usize)>,
+    /// Monitored pools that weren't discovered from a factory (e.g. added with `with_pools`)
+    pub unattributed_pools: usize,
+    /// Hot, active and cold pools as of the last synced block, `None` until activity is tracked
+    pub pools_by_activity: Option<[(PoolActivity, usize); 3]>,
+    /// Estimated bytes held by the pools and the maps kept per pool (allocator overhead aside)
+    pub estimated_bytes: usize,
+}
+
+impl PoolManagerMetrics {
+    /// Every monitored pool
+    pub fn total_pools(&self) -> usize {
+        self.pools_by_dex
+            .iter()
+            .map(|(_, count)| count)
+            .sum::<usize>()
+            + self.unattributed_pools
+    }
+}
+
+/// Pending transaction that we are looking to sandwich
+#[derive(Debug, Clone)]
+pub struct VictimInfo {
+    /// Victim tx
+    pub tx: Transaction,
+    /// State changes caused by victim tx (filled using `fill_state_diffs`)
+    state_diffs: Option<BTreeMap<Address, AccountDiff>>,
+    /// Sent to an aggregator router, its calldata isn't decoded (see `via_aggregator`)
+    aggregator: bool,
+}
+
+impl VictimInfo {
+    pub fn new(tx: Transaction) -> Self {
+        Self {
+            tx,
+            state_diffs: None,
+            aggregator: false,
+        }
+    }
+
+    /// Victim with state diffs that were already traced (e.g. loaded by a backtest)
+    pub fn with_state_diffs(tx: Transaction, state_diffs: BTreeMap<Address, AccountDiff>) -> Self {
+        Self {
+            tx,
+            state_diffs: Some(state_diffs),
+            aggregator: false,
+        }
+    }
+
+    /// Victim swapping through an aggregator router, its swap is read from the state diffs of
+    /// the pools it touches alone (an aggregator's calldata could match a router selector by
+    /// chance and decode into a swap it isn't)
+    pub fn via_aggregator(mut self) -> Self {
+        self.aggregator = true;
+        self
+    }
+
+    /// True if the victim swaps through an aggregator router (see `via_aggregator`)
+    pub fn is_aggregator_swap(&self) -> bool {
+        self.aggregator
+    }
+
+    /// Get the victim tx's state diffs by tracing it on top of `block` with `method`
+    pub async fn fill_state_diffs<M: Middleware>(
+        &mut self,
+        block: BlockNumber,
+        provider: Arc<M>,
+        method: StateDiffMethod,
+    ) -> std::result::Result<(), StateDiffError> {
+        let state_diffs = method
+            .trace_state_diffs(provider.as_ref(), &self.tx, Some(block))
+            .await?;
+
+        self.state_diffs = Some(state_diffs);
+        Ok(())
+    }
+
+    pub fn get_state_diffs(&self) -> Option<&BTreeMap<Address, AccountDiff>> {
+        self.state_diffs.as_ref()
+    }
+
+    /// Returns an address's weth balance before and after the victim tx
+    pub fn get_weth_balance_change(&self, owner: Address) -> Option<(U256, U256)> {
+        // find owner's mapping location on WETH contract
+        let storage_key = H256::from(ethers::utils::keccak256(abi::encode(&[
+            abi::Token::Address(owner),
+            abi::Token::Uint(U256::from(3)), // WETH balanceOf mapping is at index 3
+        ])));
+
+        let diff = self
+            .state_diffs
+            .as_ref()?
+            .get(&WETH_ADDRESS)?
+            .storage
+            .get(&storage_key)?;
+
+        match diff {
+            Diff::Changed(c) => Some((
+                U256::from(c.from.to_fixed_bytes()),
+                U256::from(c.to.to_fixed_bytes()),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns a storage slot's value before and after the victim tx (`None` if unchanged)
+    fn get_storage_change(&self, address: Address, slot: u64) -> Option<(U256, U256)> {
+        let diff = self
+            .state_diffs
+            .as_ref()?
+            .get(&address)?
+            .storage
+            .get(&H256::from_low_u64_be(slot))?;
+
+        match diff {
+            Diff::Changed(c) => Some((
+                U256::from(c.from.to_fixed_bytes()),
+                U256::from(c.to.to_fixed_bytes()),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns a v2 pool's `(reserve0, reserve1)` before and after the victim tx
+    /// note: reserves are packed into storage slot 8 as `blockTimestampLast|reserve1|reserve0`
+    pub fn get_v2_reserves(&self, pool: Address) -> Option<((U256, U256), (U256, U256))> {
+        let (from, to) = self.get_storage_change(pool, 8)?;
+
+        let unpack = |packed: U256| {
+            let mask = (U256::one() << 112) - 1;
+            (packed & mask, (packed >> 112) & mask)
+        };
+
+        Some((unpack(from), unpack(to)))
+    }
+
+    /// Returns a pool's state after the victim tx, `None` if the victim didn't swap on it
+    /// note: v2 reserves live in slot 8, v3 keeps `tick|sqrtPriceX96` in slot 0 and liquidity in
+    /// slot 4 (a v2 swap never writes slot 0, so the layouts can't be confused)
+    pub fn pool_state_after(&self, pool: Address) -> Option<PoolState> {
+        if let Some((_, (reserve_0, reserve_1))) = self.get_v2_reserves(pool) {
+            return Some(PoolState::V2 {
+                reserve_0,
+                reserve_1,
+            });
+        }
+
+        let (_, slot0) = self.get_storage_change(pool, 0)?;
+        let sqrt_price_x96 = slot0 & ((U256::one() << 160) - 1);
+        let tick = slot0_tick(slot0);
+        let liquidity = self
+            .get_storage_change(pool, 4)
+            .map(|(_, liquidity)| liquidity.low_u128());
+
+        Some(PoolState::V3 {
+            sqrt_price_x96,
+            tick,
+            liquidity,
+        })
+    }
+
+    /// Returns a v3 pool's `(tick before, tick after)` the victim tx, `None` if the victim didn't
+    /// move its price
+    pub fn get_v3_ticks(&self, pool: Address) -> Option<(i32, i32)> {
+        let (from, to) = self.get_storage_change(pool, 0)?;
+        Some((slot0_tick(from), slot0_tick(to)))
+    }
+
+    /// Which way the victim trades `token` against `weth`, from the hop through the pair in their
+    /// decoded swap (falls back to which of `pool`'s v2 reserves grew if no hop trades the pair)
+    pub fn get_swap_direction(
+        &self,
+        pool: Address,
+        token: Address,
+        weth: Address,
+    ) -> Option<SwapDirection> {
+        let (token_0, token_1) = if weth < token {
+            (weth, token)
+        } else {
+            (token, weth)
+        };
+
+        let zero_for_one = match self
+            .decode_swap()
+            .and_then(|swap| swap.zero_for_one(token_0, token_1))
+        {
+            Some(zero_for_one) => zero_for_one,
+            None => {
+                // tokens flow into the pool, so the reserve that grew is the victim's input
+                let ((reserve_0, _), (reserve_0_after, _)) = self.get_v2_reserves(pool)?;
+                reserve_0_after > reserve_0
+            }
+        };
+
+        Some(SwapDirection::new(zero_for_one, weth == token_0))
+    }
+
+    /// Returns `(victim_in, reserve_in, reserve_out)` of a v2 pool oriented for a sandwich in
+    /// `direction` (reserves of the frontrun's input token first)
+    ///
+    /// `None` if the victim's reserve diffs don't move the pool in `direction` (a decoded swap
+    /// that disagrees with the state diffs must not be sandwiched the wrong way round)
+    pub fn get_v2_sandwich_reserves(
+        &self,
+        pool: Address,
+        token: Address,
+        weth: Address,
+        direction: SwapDirection,
+    ) -> Option<(U256, U256, U256)> {
+        let (reserves_before, reserves_after) = self.get_v2_reserves(pool)?;
+
+        // orient reserves around weth (token0 is the lower address)
+        let orient = |(reserve_0, reserve_1)| {
+            if weth < token {
+                (reserve_0, reserve_1)
+            } else {
+                (reserve_1, reserve_0)
+            }
+        };
+        let (reserve_weth, reserve_token) = orient(reserves_before);
+        let (weth_after, token_after) = orient(reserves_after);
+
+        match direction {
+            SwapDirection::WethToToken => Some((
+                weth_after
+                    .checked_sub(reserve_weth)
+                    .filter(|x| !x.is_zero())?,
+                reserve_weth,
+                reserve_token,
+            )),
+            SwapDirection::TokenToWeth => Some((
+                token_after
+                    .checked_sub(reserve_token)
+                    .filter(|x| !x.is_zero())?,
+                reserve_token,
+                reserve_weth,
+            )),
+        }
+    }
+
+    /// Weth (or eth) the victim is swapping in, `None` if it can't be found from the tx alone
+    pub fn get_swap_value(&self) -> Option<U256> {
+        match self.decode_swap() {
+            Some(swap) if swap.path.first() == Some(&WETH_ADDRESS) => Some(swap.amount_in),
+            // token amounts aren't comparable to weth without pool state
+            Some(_) => None,
+            // unknown router, eth sent in is the best we can do
+            None if !self.tx.value.is_zero() => Some(self.tx.value),
+            None => None,
+        }
+    }
+
+    /// Returns `(amount_in, amount_out_min)` of a single hop `weth->token_out` router swap
+    /// note: for exact output swaps this is `(amount_in_max, amount_out)`, the victim's tx clears
+    /// slippage iff swapping `amount_in` yields at least `amount_out_min`
+    ///
+    /// `fee_tier` is the fee of the v3 pool being checked, a v3 router swap through another fee
+    /// tier's pool for the same pair has no limits on this pool
+    pub fn get_swap_limits(
+        &self,
+        token_out: Address,
+        fee_tier: Option<u32>,
+    ) -> Option<(U256, U256)> {
+        let swap = self.decode_swap()?;
+
+        if swap.path != vec![*WETH_ADDRESS, token_out] {
+            return None;
+        }
+
+        if let (Some(fee_tier), [swap_fee]) = (fee_tier, swap.fees.as_slice()) {
+            if *swap_fee != fee_tier {
+                return None;
+            }
+        }
+
+        Some((swap.amount_in, swap.amount_out_min))
+    }
+
+    /// Returns `(amount_in_max, amount_out)` if the victim makes a single hop exact output
+    /// `weth->token_out` router swap (see `get_swap_limits` for `fee_tier`)
+    pub fn get_exact_output_limits(
+        &self,
+        token_out: Address,
+        fee_tier: Option<u32>,
+    ) -> Option<(U256, U256)> {
+        match self.decode_swap()?.exact_output {
+            true => self.get_swap_limits(token_out, fee_tier),
+            false => None,
+        }
+    }
+
+    /// True if routers will reject the victim's swap in a block with `timestamp` (their
+    /// deadline has passed), false if the deadline is still ahead or the swap can't be decoded
+    pub fn is_expired_at(&self, timestamp: U256) -> bool {
+        // routers check `deadline >= block.timestamp`
+        self.decode_swap()
+            .map_or(false, |swap| swap.deadline < timestamp)
+    }
+
+    /// Decode the victim's calldata if it is a call to a uniswap v2/v3 style router swap, swaps
+    /// that routers batch with a permit are unwrapped (a `multicall` with `selfPermit`, or a
+    /// universal router `execute` with a Permit2 permit)
+    /// note: for exact output swaps `amount_in` is the max input and `amount_out_min` is the
+    /// exact output, only the first swap of a batch is decoded, native eth in the path is
+    /// reported as weth (routers wrap and unwrap it around the weth pools)
+    pub fn decode_swap(&self) -> Option<DecodedSwap> {
+        if self.aggregator {
+            return None;
+        }
+        decode_swap_call(&self.tx.input, self.tx.value, U256::MAX).map(DecodedSwap::eth_as_weth)
+    }
+}
+
+/// Tick packed into a v3 pool's slot 0 (`tick|sqrtPriceX96`)
+fn slot0_tick(slot0: U256) -> i32 {
+    // tick is a signed 24 bit int, shift it to the top of an i32 to sign extend it
+    (((slot0 >> 160).low_u32() << 8) as i32) >> 8
+}
+
+/// Decode router calldata into the swap it makes, paying `value` in eth
+/// `deadline` is used for calls that don't carry their own (swap router02 calls get theirs from
+/// the `multicall` wrapping them)
+fn decode_swap_call(input: &[u8], value: U256, deadline: U256) -> Option<DecodedSwap> {
+    if let Ok(call) = V2::decode(input) {
+        let exact_output = matches!(
+            call,
+            V2::SwapTokensForExactTokens(_)
+                | V2::SwapTokensForExactETH(_)
+                | V2::SwapETHForExactTokens(_)
+        );
+        let (path, amount_in, amount_out_min, deadline) = match call {
+            V2::SwapExactTokensForTokens(c) => (c.path, c.amount_in, c.amount_out_min, c.deadline),
+            V2::SwapExactTokensForTokensSupportingFeeOnTransferTokens(c) => {
+                (c.path, c.amount_in, c.amount_out_min, c.deadline)
+            }
+            V2::SwapExactETHForTokens(c) => (c.path, value, c.amount_out_min, c.deadline),
+            V2::SwapExactETHForTokensSupportingFeeOnTransferTokens(c) => {
+                (c.path, value, c.amount_out_min, c.deadline)
+            }
+            V2::SwapExactTokensForETH(c) => (c.path, c.amount_in, c.amount_out_min, c.deadline),
+            V2::SwapExactTokensForETHSupportingFeeOnTransferTokens(c) => {
+                (c.path, c.amount_in, c.amount_out_min, c.deadline)
+            }
+            V2::SwapTokensForExactTokens(c) => (c.path, c.amount_in_max, c.amount_out, c.deadline),
+            V2::SwapTokensForExactETH(c) => (c.path, c.amount_in_max, c.amount_out, c.deadline),
+            V2::SwapETHForExactTokens(c) => (c.path, value, c.amount_out, c.deadline),
+            _ => return None,
+        };
+
+        return Some(DecodedSwap {
+            path,
+            fees: vec![],
+            amount_in,
+            amount_out_min,
+            deadline,
+            exact_output,
+        });
+    }
+
+    if let Ok(call) = V3::decode(input) {
+        return match call {
+            V3::ExactInputSingle(c) => Some(DecodedSwap {
+                path: vec![c.params.token_in, c.params.token_out],
+                fees: vec![c.params.fee],
+                amount_in: c.params.amount_in,
+                amount_out_min: c.params.amount_out_minimum,
+                deadline: c.params.deadline,
+                exact_output: false,
+            }),
+            V3::ExactInput(c) => {
+                let (path, fees) = decode_v3_tokens_and_fees(&c.params.path)?;
+                Some(DecodedSwap {
+                    path,
+                    fees,
+                    amount_in: c.params.amount_in,
+                    amount_out_min: c.params.amount_out_minimum,
+                    deadline: c.params.deadline,
+                    exact_output: false,
+                })
+            }
+            V3::ExactOutputSingle(c) => Some(DecodedSwap {
+                path: vec![c.params.token_in, c.params.token_out],
+                fees: vec![c.params.fee],
+                amount_in: c.params.amount_in_maximum,
+                amount_out_min: c.params.amount_out,
+                deadline: c.params.deadline,
+                exact_output: true,
+            }),
+            V3::ExactOutput(c) => {
+                // exact output paths are encoded backwards (`token_out` first)
+                let (mut path, mut fees) = decode_v3_tokens_and_fees(&c.params.path)?;
+                path.reverse();
+                fees.reverse();
+                Some(DecodedSwap {
+                    path,
+                    fees,
+                    amount_in: c.params.amount_in_maximum,
+                    amount_out_min: c.params.amount_out,
+                    deadline: c.params.deadline,
+                    exact_output: true,
+                })
+            }
+        };
+    }
+
+    let selector = input.get(..4)?;
+    let args = &input[4..];
+    let is = |signature: &str| selector == ethers::utils::id(signature);
+    let calls = abi::ParamType::Array(Box::new(abi::ParamType::Bytes));
+
+    if is("multicall(bytes[])") {
+        let calls = abi::decode(&[calls], args).ok()?.pop()?;
+        return decode_multicall_swap(calls, value, deadline);
+    }
+    if is("multicall(uint256,bytes[])") || is("multicall(bytes32,bytes[])") {
+        let first = match is("multicall(uint256,bytes[])") {
+            true => abi::ParamType::Uint(256),
+            // previous blockhash variant doesn't bound the block
+            false => abi::ParamType::FixedBytes(32),
+        };
+        let mut params = abi::decode(&[first, calls], args).ok()?.into_iter();
+        let deadline = params.next()?.into_uint().unwrap_or(deadline);
+        return decode_multicall_swap(params.next()?, value, deadline);
+    }
+
+    if is("execute(bytes,bytes[])") || is("execute(bytes,bytes[],uint256)") {
+        let mut params = vec![abi::ParamType::Bytes, calls];
+        if is("execute(bytes,bytes[],uint256)") {
+            params.push(abi::ParamType::Uint(256));
+        }
+        let mut params = abi::decode(&params, args).ok()?.into_iter();
+        let commands = params.next()?.into_bytes()?;
+        let inputs = params.next()?;
+        let deadline = params
+            .next()
+            .and_then(abi::Token::into_uint)
+            .unwrap_or(deadline);
+        return decode_universal_router_swap(&commands, inputs, value, deadline);
+    }
+
+    decode_router02_swap(&is, args, deadline)
+}
+
+/// Decode the first swap batched in a router `multicall`, other calls (permits, refunds, weth
+/// unwrapping) don't change the swap's limits
+fn decode_multicall_swap(calls: abi::Token, value: U256, deadline: U256) -> Option<DecodedSwap> {
+    calls
+        .into_array()?
+        .into_iter()
+        .find_map(|call| decode_swap_call(&call.into_bytes()?, value, deadline))
+}
+
+/// Decode the first swap command of a universal router `execute` (one command byte per input),
+/// other commands (Permit2 permits, wrapping eth, sweeps) are skipped
+fn decode_universal_router_swap(
+    commands: &[u8],
+    inputs: abi::Token,
+    value: U256,
+    deadline: U256,
+) -> Option<DecodedSwap> {
+    // amount that tells the router to swap its whole balance (e.g. eth it just wrapped)
+    let contract_balance = U256::one() << 255;
+
+    commands
+        .iter()
+        .zip(inputs.into_array()?)
+        .find_map(|(command, input)| {
+            // top bits are flags (e.g. allow revert)
+            let (is_v2, exact_output) = match command & 0x3f {
+                0x00 => (false, false),
+                0x01 => (false, true),
+                0x08 => (true, false),
+                0x09 => (true, true),
+                _ => return None,
+            };
+
+            // (recipient, amount, amount limit, path, payer is user)
+            let path_type = match is_v2 {
+                true => abi::ParamType::Array(Box::new(abi::ParamType::Address)),
+                false => abi::ParamType::Bytes,
+            };
+            let mut params = abi::decode(
+                &[
+                    abi::ParamType::Address,
+                    abi::ParamType::Uint(256),
+                    abi::ParamType::Uint(256),
+                    path_type,
+                    abi::ParamType::Bool,
+                ],
+                &input.into_bytes()?,
+            )
+            .ok()?
+            .into_iter()
+            .skip(1);
+            let amount = params.next()?.into_uint()?;
+            let amount_limit = params.next()?.into_uint()?;
+            let path = params.next()?;
+
+            let (path, fees) = match is_v2 {
+                true => (
+                    path.into_array()?
+                        .into_iter()
+                        .map(abi::Token::into_address)
+                        .collect::<Option<Vec<_>>>()?,
+                    vec![],
+                ),
+                false => {
+                    let (mut path, mut fees) = decode_v3_tokens_and_fees(&path.into_bytes()?)?;
+                    // v3 exact output paths are encoded backwards (`token_out` first)
+                    if exact_output {
+                        path.reverse();
+                        fees.reverse();
+                    }
+                    (path, fees)
+                }
+            };
+
+            let (amount_in, amount_out_min) = match exact_output {
+                true => (amount_limit, amount),
+                false if amount == contract_balance => (value, amount_limit),
+                false => (amount, amount_limit),
+            };
+
+            Some(DecodedSwap {
+                path,
+                fees,
+                amount_in,
+                amount_out_min,
+                deadline,
+                exact_output,
+            })
+        })
+}
+
+/// Decode a swap router02 swap, unlike the first swap router its calls take no deadline (it
+/// comes from the wrapping `multicall`)
+fn decode_router02_swap(
+    is: &dyn Fn(&str) -> bool,
+    args: &[u8],
+    deadline: U256,
+) -> Option<DecodedSwap> {
+    use abi::ParamType;
+
+    let single = ParamType::Tuple(vec![
+        ParamType::Address,
+        ParamType::Address,
+        ParamType::Uint(24),
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(160),
+    ]);
+    let multi = ParamType::Tuple(vec![
+        ParamType::Bytes,
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+    ]);
+    let v2_path = ParamType::Array(Box::new(ParamType::Address));
+    let decode_params = |params: Vec<ParamType>| abi::decode(&params, args).ok();
+
+    let exact_input_single =
+        "exactInputSingle((address,address,uint24,address,uint256,uint256,uint160))";
+    let exact_output_single =
+        "exactOutputSingle((address,address,uint24,address,uint256,uint256,uint160))";
+    if is(exact_input_single) || is(exact_output_single) {
+        // (token in, token out, fee, recipient, amount, amount limit, price limit)
+        let params = decode_params(vec![single])?.pop()?.into_tuple()?;
+        let exact_output = is(exact_output_single);
+        let fee = params[2].clone().into_uint()?.as_u32();
+        let amount = params[4].clone().into_uint()?;
+        let amount_limit = params[5].clone().into_uint()?;
+        let (amount_in, amount_out_min) = match exact_output {
+            true => (amount_limit, amount),
+            false => (amount, amount_limit),
+        };
+
+        return Some(DecodedSwap {
+            path: vec![
+                params[0].clone().into_address()?,
+                params[1].clone().into_address()?,
+            ],
+            fees: vec![fee],
+            amount_in,
+            amount_out_min,
+            deadline,
+            exact_output,
+        });
+    }
+
+    if is("exactInput((bytes,address,uint256,uint256))")
+        || is("exactOutput((bytes,address,uint256,uint256))")
+    {
+        // (path, recipient, amount, amount limit)
+        let params = decode_params(vec![multi])?.pop()?.into_tuple()?;
+        let exact_output = is("exactOutput((bytes,address,uint256,uint256))");
+        let (mut path, mut fees) = decode_v3_tokens_and_fees(&params[0].clone().into_bytes()?)?;
+        let amount = params[2].clone().into_uint()?;
+        let amount_limit = params[3].clone().into_uint()?;
+        let (amount_in, amount_out_min) = match exact_output {
+            // exact output paths are encoded backwards (`token_out` first)
+            true => {
+                path.reverse();
+                fees.reverse();
+                (amount_limit, amount)
+            }
+            false => (amount, amount_limit),
+        };
+
+        return Some(DecodedSwap {
+            path,
+            fees,
+            amount_in,
+            amount_out_min,
+            deadline,
+            exact_output,
+        });
+    }
+
+    if is("swapExactTokensForTokens(uint256,uint256,address[],address)")
+        || is("swapTokensForExactTokens(uint256,uint256,address[],address)")
+    {
+        // (amount, amount limit, path, recipient)
+        let mut params = decode_params(vec![
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            v2_path,
+            ParamType::Address,
+        ])?
+        .into_iter();
+        let exact_output = is("swapTokensForExactTokens(uint256,uint256,address[],address)");
+        let amount = params.next()?.into_uint()?;
+        let amount_limit = params.next()?.into_uint()?;
+        let path = params
+            .next()?
+            .into_array()?
+            .into_iter()
+            .map(abi::Token::into_address)
+            .collect::<Option<Vec<_>>>()?;
+        let (amount_in, amount_out_min) = match exact_output {
+            true => (amount_limit, amount),
+            false => (amount, amount_limit),
+        };
+
+        return Some(DecodedSwap {
+            path,
+            fees: vec![],
+            amount_in,
+            amount_out_min,
+            deadline,
+            exact_output,
+        });
+    }
+
+    None
+}
+
+/// Which way a victim trades through the pool we sandwich, our frontrun trades the same way and
+/// the backrun undoes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwapDirection {
+    /// Victim buys the token, we frontrun buy it with weth and sell it back for weth
+    WethToToken,
+    /// Victim sells the token, we frontrun sell the contract's token inventory and buy it back
+    /// with the weth (revenue is in the token)
+    TokenToWeth,
+}
+
+impl SwapDirection {
+    /// Direction of a swap that sells the pool's token0 (`zero_for_one`) or token1
+    pub fn new(zero_for_one: bool, weth_is_token_0: bool) -> Self {
+        if zero_for_one == weth_is_token_0 {
+            SwapDirection::WethToToken
+        } else {
+            SwapDirection::TokenToWeth
+        }
+    }
+
+    /// The other way through the pool
+    pub fn opposite(&self) -> Self {
+        match self {
+            SwapDirection::WethToToken => SwapDirection::TokenToWeth,
+            SwapDirection::TokenToWeth => SwapDirection::WethToToken,
+        }
+    }
+}
+
+/// Swap parameters decoded from a victim's router calldata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedSwap {
+    /// Tokens swapped through (first is token in, last is token out)
+    pub path: Vec<Address>,
+    /// Fee tier of each v3 hop (`path[i]->path[i + 1]`), empty for v2 routers
+    pub fees: Vec<u32>,
+    pub amount_in: U256,
+    /// Minimum output the victim accepts (their slippage floor)
+    pub amount_out_min: U256,
+    pub deadline: U256,
+    /// Victim fixed their output (`amount_out_min`) and pays at most `amount_in`, a frontrun
+    /// raises what they pay instead of lowering what they get
+    pub exact_output: bool,
+}
+
+impl DecodedSwap {
+    /// Replace native eth placeholders in the path with weth, so an eth in (or out) swap
+    /// matches the weth pools it trades on
+    pub fn eth_as_weth(mut self) -> Self {
+        for token in self.path.iter_mut() {
+            if NATIVE_ETH_ADDRESSES.contains(token) {
+                *token = *WETH_ADDRESS;
+            }
+        }
+        self
+    }
+
+    /// True if the swap sells `token_0` for `token_1` on the hop between them, false if it sells
+    /// `token_1`, `None` if the path never trades the pair
+    pub fn zero_for_one(&self, token_0: Address, token_1: Address) -> Option<bool> {
+        self.path.windows(2).find_map(|hop| match (hop[0], hop[1]) {
+            (token_in, token_out) if token_in == token_0 && token_out == token_1 => Some(true),
+            (token_in, token_out) if token_in == token_1 && token_out == token_0 => Some(false),
+            _ => None,
+        })
+    }
+
+    /// Every v3 hop of the swap as `(token_in, fee, token_out)` in trade order, empty for v2
+    /// routers
+    pub fn v3_hops(&self) -> Vec<(Address, u32, Address)> {
+        self.path
+            .windows(2)
+            .zip(&self.fees)
+            .map(|(hop, fee)| (hop[0], *fee, hop[1]))
+            .collect()
+    }
+}
+
+/// Decode a packed v3 path (`token|fee|token|fee|token...`) into its `(token, fee, token)` hops
+/// in encoded order (exact output paths start from `token_out`)
+///
+/// Returns an empty vec if the path is malformed
+pub fn decode_v3_path(path: &[u8]) -> Vec<(Address, u32, Address)> {
+    // each hop is a 20 byte token followed by a 3 byte fee
+    if path.len() < 43 || (path.len() - 20) % 23 != 0 {
+        return vec![];
+    }
+
+    path.chunks_exact(23)
+        .enumerate()
+        .map(|(i, hop)| {
+            let next_token = &path[(i + 1) * 23..(i + 1) * 23 + 20];
+            let fee = u32::from_be_bytes([0, hop[20], hop[21], hop[22]]);
+            (
+                Address::from_slice(&hop[..20]),
+                fee,
+                Address::from_slice(next_token),
+            )
+        })
+        .collect()
+}
+
+/// Split a packed v3 path into its tokens and hop fees, `None` if it is malformed
+fn decode_v3_tokens_and_fees(path: &[u8]) -> Option<(Vec<Address>, Vec<u32>)> {
+    let hops = decode_v3_path(path);
+    let (first_token, _, _) = hops.first()?;
+
+    let mut tokens = vec![*first_token];
+    tokens.extend(hops.iter().map(|(_, _, token_out)| *token_out));
+    let fees = hops.iter().map(|(_, fee, _)| *fee).collect();
+
+    Some((tokens, fees))
 }
 
 /// Information on potential sandwichable opportunity
@@ -107,6 +2309,8 @@ pub struct BlockInfo {
     // These are optional because we don't know these values for `next_block`
     pub gas_used: Option<U256>,
     pub gas_limit: Option<U256>,
+    pub hash: Option<H256>,
+    pub parent_hash: Option<H256>,
 }
 
 impl BlockInfo {
@@ -114,10 +2318,12 @@ impl BlockInfo {
     pub fn get_next_block(&self) -> BlockInfo {
         BlockInfo {
             number: self.number + 1,
-            base_fee_per_gas: calculate_next_block_base_fee(&self),
+            base_fee_per_gas: calculate_next_block_base_fee(self),
             timestamp: self.timestamp + 12,
             gas_used: None,
             gas_limit: None,
+            hash: None,
+            parent_hash: self.hash,
         }
     }
 }
@@ -136,6 +2342,8 @@ impl TryFrom<Block<H256>> for BlockInfo {
                 "could not parse base fee when setting up `block_manager`"
             ))?,
             timestamp: value.timestamp,
+            hash: value.hash,
+            parent_hash: Some(value.parent_hash),
         })
     }
 }
@@ -148,43 +2356,14 @@ impl From<NewBlock> for BlockInfo {
             timestamp: value.timestamp,
             gas_used: Some(value.gas_used),
             gas_limit: Some(value.gas_limit),
+            hash: Some(value.hash),
+            parent_hash: Some(value.parent_hash),
         }
     }
 }
 
-/// Calculate the next block base fee
-// based on math provided here: https://ethereum.stackexchange.com/questions/107173/how-is-the-base-fee-per-gas-computed-for-a-new-block
-fn calculate_next_block_base_fee(block: &BlockInfo) -> U256 {
-    // Get the block base fee per gas
-    let current_base_fee_per_gas = block.base_fee_per_gas;
-
-    let current_gas_used = block
-        .gas_used
-        .expect("can't calculate base fee from unmined block \"next_block\"");
-
-    let current_gas_target = block
-        .gas_limit
-        .expect("can't calculate base fee from unmined block \"next_block\"")
-        / 2;
-
-    if current_gas_used == current_gas_target {
-        current_base_fee_per_gas
-    } else if current_gas_used > current_gas_target {
-        let gas_used_delta = current_gas_used - current_gas_target;
-        let base_fee_per_gas_delta =
-            current_base_fee_per_gas * gas_used_delta / current_gas_target / 8;
-
-        return current_base_fee_per_gas + base_fee_per_gas_delta;
-    } else {
-        let gas_used_delta = current_gas_target - current_gas_used;
-        let base_fee_per_gas_delta =
-            current_base_fee_per_gas * gas_used_delta / current_gas_target / 8;
-
-        return current_base_fee_per_gas - base_fee_per_gas_delta;
-    }
-}
-
 /// All details for capturing a sando opp
+#[derive(Clone)]
 pub struct SandoRecipe {
     frontrun: TxEnv,
     frontrun_gas_used: u64,
@@ -193,6 +2372,10 @@ pub struct SandoRecipe {
     backrun_gas_used: u64,
     revenue: U256,
     target_block: BlockInfo,
+    /// Base fee our txs' max fee covers, the target block's unless also sent for later blocks
+    base_fee_ceiling: U256,
+    /// Gas our frontrun and backrun saved by carrying an access list (already in their gas used)
+    access_list_gas_saved: u64,
 }
 
 impl SandoRecipe {
@@ -212,79 +2395,165 @@ impl SandoRecipe {
             backrun,
             backrun_gas_used,
             revenue,
+            base_fee_ceiling: target_block.base_fee_per_gas,
             target_block,
+            access_list_gas_saved: 0,
         }
     }
 
+    /// Record the gas our txs' access lists saved them
+    pub fn with_access_list_gas_saved(mut self, gas: u64) -> Self {
+        self.access_list_gas_saved = gas;
+        self
+    }
+
+    /// Price our txs to stay valid up to `blocks` blocks after the target block, however much
+    /// the base fee rises in between (they still only pay the base fee of the block they land in)
+    pub fn covering_later_blocks(mut self, blocks: u64) -> Self {
+        self.base_fee_ceiling = max_base_fee_after(self.target_block.base_fee_per_gas, blocks);
+        self
+    }
+
+    /// Raise the max fee of our txs `margin_bps` above the base fee ceiling, so they still land
+    /// if the base fee comes in that much higher than predicted
+    pub fn covering_base_fee_margin(mut self, margin_bps: u64) -> Self {
+        self.base_fee_ceiling = with_base_fee_margin(self.base_fee_ceiling, margin_bps);
+        self
+    }
+
+    /// Revenue after paying base fee for the frontrun and backrun
+    pub fn get_net_profit(&self, payment: PaymentStrategy) -> I256 {
+        self.get_profit(payment).net_profit
+    }
+
+    /// Profit breakdown using the gas our txs used while simulating
+    pub fn get_profit(&self, payment: PaymentStrategy) -> SandwichProfit {
+        let (frontrun_gas, backrun_gas) = estimate_gas(self);
+        SandwichProfit::new(
+            self.revenue,
+            frontrun_gas,
+            backrun_gas,
+            self.target_block.base_fee_per_gas,
+            U256::zero(),
+            payment,
+        )
+    }
+
+    /// Same sandwich around different victim txs (that leave the pool in the same state)
+    pub fn with_meats(mut self, meats: Vec<Transaction>) -> Self {
+        self.meats = meats;
+        self
+    }
+
     pub fn get_revenue(&self) -> U256 {
         self.revenue
     }
 
+    pub fn get_frontrun_gas_used(&self) -> u64 {
+        self.frontrun_gas_used
+    }
+
+    pub fn get_backrun_gas_used(&self) -> u64 {
+        self.backrun_gas_used
+    }
+
+    pub fn get_access_list_gas_saved(&self) -> u64 {
+        self.access_list_gas_saved
+    }
+
+    /// Lowest max fee per gas among our frontrun and backrun when bidding `priority_fee`
+    /// note: builders reject the bundle if any of its txs can't pay the target block's base fee
+    pub fn max_fee_per_gas(&self, priority_fee: U256, payment: PaymentStrategy) -> U256 {
+        let (_, backrun_max_fee) = self.backrun_fee_caps(priority_fee, payment);
+        // frontrun pays no tip
+        self.base_fee_ceiling.min(backrun_max_fee)
+    }
+
+    /// Gas the backrun uses, a coinbase transfer makes it a bit more expensive
+    fn backrun_gas(&self, payment: PaymentStrategy) -> u64 {
+        match payment {
+            PaymentStrategy::PriorityFee => self.backrun_gas_used,
+            PaymentStrategy::CoinbaseTransfer => {
+                self.backrun_gas_used + COINBASE_PAYMENT_GAS_ESTIMATE
+            }
+        }
+    }
+
+    /// `(max priority fee, max fee)` per gas of a backrun that bribes `bribe_amount`
+    fn backrun_fee_caps(&self, bribe_amount: U256, payment: PaymentStrategy) -> (U256, U256) {
+        let max_priority_fee = match payment {
+            PaymentStrategy::PriorityFee => bribe_amount / self.backrun_gas(payment),
+            PaymentStrategy::CoinbaseTransfer => U256::zero(),
+        };
+        (max_priority_fee, self.base_fee_ceiling + max_priority_fee)
+    }
+
     /// turn recipe into a signed bundle that can be sumbitted to flashbots
-    pub async fn to_fb_bundle<M: Middleware>(
+    #[allow(clippy::too_many_arguments)]
+    /// note: nonces come from the `NonceManager` so bundles within a block don't collide
+    pub async fn to_fb_bundle(
         self,
         sando_address: Address,
-        searcher: &LocalWallet,
+        searcher: &SearcherSigner,
         has_dust: bool,
-        provider: Arc<M>,
+        priority_fee: U256,
+        payment: PaymentStrategy,
+        gas_limit_multiplier: f64,
+        frontrun_nonce: U256,
+        backrun_nonce: U256,
     ) -> Result<BundleRequest> {
-        let nonce = provider
-            .get_transaction_count(searcher.address(), Some(self.target_block.number.into()))
-            .await
-            .map_err(|e| anyhow!("FAILED TO CREATE BUNDLE: Failed to get nonce {:?}", e))?;
-
         let frontrun_tx = Eip1559TransactionRequest {
             to: Some(sando_address.into()),
-            gas: Some((U256::from(self.frontrun_gas_used) * 10) / 7),
+            gas: Some(padded_gas_limit(
+                self.frontrun_gas_used,
+                gas_limit_multiplier,
+            )),
             value: Some(self.frontrun.value.into()),
             data: Some(self.frontrun.data.into()),
-            nonce: Some(nonce),
+            nonce: Some(frontrun_nonce),
             access_list: access_list_to_ethers(self.frontrun.access_list),
-            max_fee_per_gas: Some(self.target_block.base_fee_per_gas.into()),
+            max_fee_per_gas: Some(self.base_fee_ceiling),
             ..Default::default()
         };
         let signed_frontrun = sign_eip1559(frontrun_tx, &searcher).await?;
 
         let signed_meat_txs: Vec<Bytes> = self.meats.into_iter().map(|meat| meat.rlp()).collect();
 
-        // calc bribe (bribes paid in backrun)
-        let revenue_minus_frontrun_tx_fee = self
-            .revenue
-            .checked_sub(U256::from(self.frontrun_gas_used) * self.target_block.base_fee_per_gas)
-            .ok_or_else(|| {
-                anyhow!("[FAILED TO CREATE BUNDLE] revenue doesn't cover frontrun basefee")
-            })?;
+        let backrun_gas_used = self.backrun_gas(payment);
+
+        // bribes paid in backrun (tip comes out of net profit)
+        let backrun_fee = U256::from(backrun_gas_used) * self.target_block.base_fee_per_gas;
+        let frontrun_fee = U256::from(self.frontrun_gas_used) * self.target_block.base_fee_per_gas;
+        ensure!(
+            self.revenue >= frontrun_fee + backrun_fee + priority_fee,
+            "[FAILED TO CREATE BUNDLE] revenue doesn't cover gas and priority fee"
+        );
 
         // eat a loss (overpay) to get dust onto the sando contract (more: https://twitter.com/libevm/status/1474870661373779969)
         let bribe_amount = if !has_dust {
-            revenue_minus_frontrun_tx_fee + *DUST_OVERPAY
+            priority_fee + *DUST_OVERPAY
         } else {
-            // bribe away 99.9999999% of revenue lmeow
-            revenue_minus_frontrun_tx_fee * 999999999 / 1000000000
+            priority_fee
         };
 
-        let max_fee = bribe_amount / self.backrun_gas_used;
-
-        ensure!(
-            max_fee >= self.target_block.base_fee_per_gas,
-            "[FAILED TO CREATE BUNDLE] backrun maxfee less than basefee"
-        );
-
-        let effective_miner_tip = max_fee.checked_sub(self.target_block.base_fee_per_gas);
-
-        ensure!(
-            effective_miner_tip.is_none(),
-            "[FAILED TO CREATE BUNDLE] negative miner tip"
-        );
+        let backrun_call = SandoCall {
+            calldata: self.backrun.data.into(),
+            value: self.backrun.value.into(),
+        };
+        let backrun_call = match payment {
+            PaymentStrategy::PriorityFee => backrun_call,
+            PaymentStrategy::CoinbaseTransfer => backrun_call.with_coinbase_payment(bribe_amount),
+        };
+        let (max_priority_fee, max_fee) = self.backrun_fee_caps(bribe_amount, payment);
 
         let backrun_tx = Eip1559TransactionRequest {
             to: Some(sando_address.into()),
-            gas: Some((U256::from(self.backrun_gas_used) * 10) / 7),
-            value: Some(self.backrun.value.into()),
-            data: Some(self.backrun.data.into()),
-            nonce: Some(nonce+1),
+            gas: Some(padded_gas_limit(backrun_gas_used, gas_limit_multiplier)),
+            value: Some(backrun_call.value),
+            data: Some(backrun_call.calldata),
+            nonce: Some(backrun_nonce),
             access_list: access_list_to_ethers(self.backrun.access_list),
-            max_priority_fee_per_gas: Some(max_fee),
+            max_priority_fee_per_gas: Some(max_priority_fee),
             max_fee_per_gas: Some(max_fee),
             ..Default::default()
         };
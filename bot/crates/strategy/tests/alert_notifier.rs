@@ -0,0 +1,104 @@
+use ethers::types::{Address, TxHash, I256, U256, U64};
+use strategy::managers::{
+    alert_notifier::{Alert, AlertNotifier, LandedAlert, LowBalanceAlert},
+    balance_monitor::BalanceMonitor,
+    bundle_audit::{BundleStatus, LandedBundle},
+    opportunity_log::OpportunityRecord,
+};
+
+fn landed() -> LandedBundle {
+    let pool = Address::repeat_byte(0x11);
+    let net_profit = I256::from(25_000_000_000_000_000u64);
+    let record = OpportunityRecord::new(
+        U64::from(17_700_000),
+        TxHash::repeat_byte(0xaa),
+        pool,
+        U256::exp10(18),
+        net_profit,
+    );
+
+    LandedBundle::new(
+        U64::from(17_700_000),
+        TxHash::repeat_byte(0xbb),
+        pool,
+        U256::exp10(17),
+        net_profit,
+        record,
+    )
+}
+
+#[test]
+fn posts_landed_sandwiches_as_json() {
+    let alert = LandedAlert::new(&landed());
+    assert_eq!(alert.victim_hash, TxHash::repeat_byte(0xaa));
+    assert_eq!(alert.net_profit_eth, 0.025);
+
+    let notifier = AlertNotifier::new("https://alerts.example.com/hook".to_string());
+    let payload = notifier.payload(&alert);
+    assert_eq!(payload["block"], "0x10e14a0");
+    assert_eq!(payload["pool"], format!("{:?}", Address::repeat_byte(0x11)));
+    assert_eq!(payload["net_profit"], "25000000000000000");
+}
+
+#[test]
+fn posts_a_chat_message_to_discord() {
+    let alert = LandedAlert::new(&landed());
+    let notifier = AlertNotifier::new("https://discord.com/api/webhooks/123/token".to_string());
+
+    let payload = notifier.payload(&alert);
+    let content = payload["content"].as_str().unwrap();
+    assert!(content.starts_with("Sandwich landed in block 17700000: 0.025000 eth"));
+    assert!(payload.get("victim_hash").is_none());
+}
+
+#[test]
+fn flags_bundles_that_landed_with_a_reverted_tx() {
+    let failed = landed().with_status(BundleStatus::Failed);
+    let alert = LandedAlert::new(&failed);
+    assert!(alert.failed);
+    assert!(alert
+        .message()
+        .starts_with("Sandwich landed but reverted in block 17700000"));
+    assert!(!LandedAlert::new(&landed()).failed);
+}
+
+#[test]
+fn alerts_once_per_drop_below_the_floor() {
+    let sando = Address::repeat_byte(0x22);
+    let floor = U256::exp10(18);
+    let mut monitor = BalanceMonitor::default();
+
+    assert!(!monitor.check(sando, floor * 2, floor));
+    assert!(monitor.check(sando, floor / 2, floor));
+    // still low, already alerted
+    assert!(!monitor.check(sando, floor / 4, floor));
+    assert!(monitor.is_low(sando));
+
+    // topped up, the next drop alerts again
+    assert!(!monitor.check(sando, floor, floor));
+    assert!(!monitor.is_low(sando));
+    assert!(monitor.check(sando, floor - 1, floor));
+}
+
+#[test]
+fn posts_low_balances() {
+    let sando = Address::repeat_byte(0x22);
+    let alert = LowBalanceAlert::new(
+        U64::from(17_700_000),
+        sando,
+        "weth",
+        U256::exp10(17),
+        U256::exp10(18),
+    );
+
+    let notifier = AlertNotifier::new("https://alerts.example.com/hook".to_string());
+    let payload = notifier.payload(&alert);
+    assert_eq!(payload["asset"], "weth");
+    assert_eq!(payload["balance"], "100000000000000000");
+    assert_eq!(payload["floor_eth"], 1.0);
+
+    let notifier = AlertNotifier::new("https://discord.com/api/webhooks/123/token".to_string());
+    let payload = notifier.payload(&alert);
+    let content = payload["content"].as_str().unwrap();
+    assert!(content.starts_with(&format!("Low weth balance on {:?}", sando)));
+}
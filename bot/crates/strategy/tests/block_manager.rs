@@ -0,0 +1,154 @@
+use ethers::types::{Transaction, H256, U256, U64};
+use strategy::{
+    managers::{
+        block_manager::{
+            max_base_fee_after, with_base_fee_margin, BlockManager, MAX_BASE_FEE_SAFETY_MARGIN_BPS,
+        },
+        mempool_manager::can_include_in_target_block,
+    },
+    types::BlockInfo,
+};
+
+fn block(number: u64, hash: u64, parent_hash: u64) -> BlockInfo {
+    BlockInfo {
+        number: U64::from(number),
+        base_fee_per_gas: U256::from(30_000_000_000u64),
+        timestamp: U256::from(number * 12),
+        gas_used: Some(U256::from(15_000_000)),
+        gas_limit: Some(U256::from(30_000_000)),
+        hash: Some(H256::from_low_u64_be(hash)),
+        parent_hash: Some(H256::from_low_u64_be(parent_hash)),
+    }
+}
+
+#[test]
+fn recovers_tip_after_reorg() {
+    let mut block_manager = BlockManager::new();
+
+    // canonical chain: 100 -> 101 -> 102
+    assert!(block_manager
+        .update_block_info(block(100, 100, 99))
+        .is_none());
+    assert!(block_manager
+        .update_block_info(block(101, 101, 100))
+        .is_none());
+    assert!(block_manager
+        .update_block_info(block(102, 102, 101))
+        .is_none());
+
+    // fork off block 100: 101' replaces 101 and 102
+    let next_block = block_manager
+        .update_block_info(block(101, 1101, 100))
+        .expect("reorg should be detected");
+
+    assert_eq!(next_block.number, U64::from(102));
+    assert_eq!(next_block.parent_hash, Some(H256::from_low_u64_be(1101)));
+    assert_eq!(block_manager.get_latest_block().number, U64::from(101));
+    assert_eq!(
+        block_manager.get_latest_block().hash,
+        Some(H256::from_low_u64_be(1101))
+    );
+
+    // new fork keeps extending without reporting a reorg
+    assert!(block_manager
+        .update_block_info(block(102, 1102, 1101))
+        .is_none());
+    assert_eq!(block_manager.get_next_block().number, U64::from(103));
+
+    // duplicate notifications are ignored
+    assert!(block_manager
+        .update_block_info(block(102, 1102, 1101))
+        .is_none());
+    assert_eq!(
+        block_manager.get_latest_block().hash,
+        Some(H256::from_low_u64_be(1102))
+    );
+}
+
+#[test]
+fn predicts_exact_next_base_fee() {
+    let mut block_manager = BlockManager::new();
+
+    // full block raises base fee by exactly 12.5%
+    let mut full_block = block(100, 100, 99);
+    full_block.gas_used = full_block.gas_limit;
+    block_manager.update_block_info(full_block);
+    assert_eq!(
+        block_manager.predict_next_base_fee(),
+        U256::from(33_750_000_000u64)
+    );
+    assert_eq!(
+        block_manager.get_next_block().base_fee_per_gas,
+        block_manager.predict_next_base_fee()
+    );
+
+    // barely over target still raises base fee by the minimum of 1 wei
+    let mut over_target = block(101, 101, 100);
+    over_target.base_fee_per_gas = U256::from(7);
+    over_target.gas_used = Some(U256::from(15_000_001));
+    block_manager.update_block_info(over_target);
+    assert_eq!(block_manager.predict_next_base_fee(), U256::from(8));
+
+    // empty block lowers base fee by exactly 12.5%
+    let mut empty_block = block(102, 102, 101);
+    empty_block.gas_used = Some(U256::zero());
+    block_manager.update_block_info(empty_block);
+    assert_eq!(
+        block_manager.predict_next_base_fee(),
+        U256::from(26_250_000_000u64)
+    );
+}
+
+#[test]
+fn bounds_base_fee_of_later_blocks() {
+    let base_fee = U256::from(32_000_000_000u64);
+
+    assert_eq!(max_base_fee_after(base_fee, 0), base_fee);
+    // every block in between full
+    assert_eq!(
+        max_base_fee_after(base_fee, 1),
+        U256::from(36_000_000_000u64)
+    );
+    assert_eq!(
+        max_base_fee_after(base_fee, 2),
+        U256::from(40_500_000_000u64)
+    );
+    // tiny base fees still rise by at least 1 wei a block
+    assert_eq!(max_base_fee_after(U256::from(7), 3), U256::from(10));
+}
+
+#[test]
+fn safety_margin_covers_a_base_fee_spike() {
+    let mut block_manager = BlockManager::new();
+    let mut full_block = block(100, 100, 99);
+    full_block.gas_used = full_block.gas_limit;
+    block_manager.update_block_info(full_block);
+    let predicted = block_manager.predict_next_base_fee();
+
+    // 5% margin on the predicted 33.75 gwei
+    let margined = with_base_fee_margin(predicted, 500);
+    assert_eq!(margined, U256::from(35_437_500_000u64));
+    assert_eq!(with_base_fee_margin(predicted, 0), predicted);
+    // the largest margin covers one more full block
+    assert_eq!(
+        with_base_fee_margin(predicted, MAX_BASE_FEE_SAFETY_MARGIN_BPS),
+        max_base_fee_after(predicted, 1)
+    );
+
+    // base fee spikes 4% past the prediction: a bundle priced at the margined base fee still
+    // clears it, one priced at the bare prediction doesn't
+    let spiked = predicted * 104 / 100;
+    let victim = Transaction {
+        max_fee_per_gas: Some(U256::from(40_000_000_000u64)),
+        ..Default::default()
+    };
+    assert!(can_include_in_target_block(&victim, margined, spiked));
+    assert!(!can_include_in_target_block(&victim, predicted, spiked));
+
+    // a victim that can't pay the margined base fee is skipped up front
+    let tight = Transaction {
+        max_fee_per_gas: Some(predicted),
+        ..Default::default()
+    };
+    assert!(!can_include_in_target_block(&tight, margined, margined));
+}
@@ -0,0 +1,131 @@
+use ethers::types::{Address, TxHash, H256, I256, U256, U64};
+use strategy::{
+    managers::{
+        block_manager::BlockManager,
+        bundle_audit::{
+            balance_delta, BlockReconciliation, BundleAudit, BundleStatus, LandedBundle,
+        },
+        loss_guard::LossGuard,
+        opportunity_log::OpportunityRecord,
+    },
+    types::BlockInfo,
+};
+
+fn block(number: u64, hash: u64, parent_hash: u64) -> BlockInfo {
+    BlockInfo {
+        number: U64::from(number),
+        base_fee_per_gas: U256::from(30_000_000_000u64),
+        timestamp: U256::from(number * 12),
+        gas_used: Some(U256::from(15_000_000)),
+        gas_limit: Some(U256::from(30_000_000)),
+        hash: Some(H256::from_low_u64_be(hash)),
+        parent_hash: Some(H256::from_low_u64_be(parent_hash)),
+    }
+}
+
+fn landed(block: u64, backrun: u64, net_profit: i64) -> LandedBundle {
+    let pool = Address::repeat_byte(0x11);
+    let record = OpportunityRecord::new(
+        U64::from(block),
+        TxHash::from_low_u64_be(backrun + 1_000),
+        pool,
+        U256::from(1_000),
+        I256::from(net_profit),
+    );
+
+    LandedBundle::new(
+        U64::from(block),
+        TxHash::from_low_u64_be(backrun),
+        pool,
+        U256::from(net_profit.unsigned_abs() * 2),
+        I256::from(net_profit),
+        record,
+    )
+    .with_realized_profit(I256::from(net_profit))
+}
+
+#[test]
+fn reorg_reverts_bundles_that_landed_in_dropped_blocks() {
+    let mut block_manager = BlockManager::new();
+    let mut audit = BundleAudit::new(64);
+    let mut loss_guard = LossGuard::new(U256::from(1_000), 10);
+
+    // canonical chain 100 -> 101 -> 102 with bundles landing in 100 and 101
+    block_manager.update_block_info(block(100, 100, 99));
+    audit.record_landed(landed(100, 1, 300));
+    loss_guard.record(U64::from(100), I256::from(300));
+    block_manager.update_block_info(block(101, 101, 100));
+    audit.record_landed(landed(101, 2, -200));
+    loss_guard.record(U64::from(101), I256::from(-200));
+    block_manager.update_block_info(block(102, 102, 101));
+    assert!(block_manager.take_reorged_blocks().is_empty());
+    assert_eq!(audit.landed_profit(), I256::from(100));
+
+    // fork off block 100: 101' replaces 101 and 102
+    block_manager.update_block_info(block(101, 1101, 100));
+    let reorged_blocks = block_manager.take_reorged_blocks();
+    assert_eq!(reorged_blocks, vec![U64::from(101), U64::from(102)]);
+
+    let reverted = audit.revert_blocks(&reorged_blocks);
+    assert_eq!(reverted.len(), 1);
+    assert_eq!(reverted[0].backrun_hash, TxHash::from_low_u64_be(2));
+    for bundle in &reverted {
+        loss_guard.forget(bundle.block, bundle.realized_profit.unwrap());
+    }
+
+    // only the bundle in block 100 still counts
+    assert_eq!(audit.landed_profit(), I256::from(300));
+    assert_eq!(loss_guard.window_profit(), I256::from(300));
+    let statuses: Vec<_> = audit.bundles().map(|b| b.status).collect();
+    assert_eq!(statuses, vec![BundleStatus::Landed, BundleStatus::Reverted]);
+
+    // reorgs are reported once and bundles are only reverted once
+    assert!(block_manager.take_reorged_blocks().is_empty());
+    assert!(audit.revert_blocks(&reorged_blocks).is_empty());
+    assert_eq!(audit.landed_profit(), I256::from(300));
+}
+
+#[test]
+fn forgets_bundles_deeper_than_its_depth() {
+    let mut audit = BundleAudit::new(2);
+
+    audit.record_landed(landed(100, 1, 300));
+    audit.record_landed(landed(101, 2, 300));
+    audit.record_landed(landed(102, 3, 300));
+
+    assert_eq!(audit.bundles().count(), 2);
+    assert!(audit.revert_blocks(&[U64::from(100)]).is_empty());
+    assert_eq!(audit.landed_profit(), I256::from(900));
+}
+
+#[test]
+fn reconciles_onchain_profit_and_takes_back_reorged_blocks() {
+    let mut audit = BundleAudit::new(64);
+
+    // weth up 500, searcher paid 150 for gas
+    let realized = balance_delta(U256::from(10_000), U256::from(10_500))
+        + balance_delta(U256::from(1_000), U256::from(850));
+    assert_eq!(realized, I256::from(350));
+
+    let reconciliation = BlockReconciliation::new(U64::from(100), 2, I256::from(400), realized);
+    assert_eq!(reconciliation.error(), I256::from(-50));
+    audit.record_reconciliation(reconciliation);
+    audit.record_reconciliation(BlockReconciliation::new(
+        U64::from(101),
+        1,
+        I256::from(100),
+        I256::from(-20),
+    ));
+    assert_eq!(
+        audit.reconciled_profit(),
+        (I256::from(330), I256::from(500))
+    );
+
+    audit.revert_blocks(&[U64::from(101)]);
+    assert_eq!(
+        audit.reconciled_profit(),
+        (I256::from(350), I256::from(400))
+    );
+    let statuses: Vec<_> = audit.reconciliations().map(|r| r.status).collect();
+    assert_eq!(statuses, vec![BundleStatus::Landed, BundleStatus::Reverted]);
+}
@@ -0,0 +1,97 @@
+use ethers::types::{Address, I256, U64};
+use strategy::managers::bundle_ranking::{
+    select_top_opportunities, BlockBudget, BundleRanking, HeldOpportunity,
+};
+
+fn held(id: u8, net_profit: i64, gas_used: u64) -> HeldOpportunity<u8> {
+    HeldOpportunity {
+        pool: Address::repeat_byte(id),
+        net_profit: I256::from(net_profit),
+        gas_used,
+        opportunity: id,
+    }
+}
+
+fn ids(opportunities: &[HeldOpportunity<u8>]) -> Vec<u8> {
+    opportunities.iter().map(|o| o.opportunity).collect()
+}
+
+#[test]
+fn submits_only_the_top_two_of_five() {
+    let mut ranking = BundleRanking::new();
+    assert!(ranking.expire_before(U64::from(100)).is_empty());
+    for (id, net_profit) in [(1, 300), (2, 900), (3, 100), (4, 700), (5, 500)] {
+        ranking.hold(held(id, net_profit, 200_000));
+    }
+    assert_eq!(ranking.len(), 5);
+
+    let budget = BlockBudget {
+        max_bundles: 2,
+        max_gas: u64::MAX,
+    };
+    let (selected, dropped) = select_top_opportunities(ranking.take(U64::from(100)), budget);
+
+    assert_eq!(ids(&selected), vec![2, 4]);
+    assert_eq!(ids(&dropped), vec![5, 1, 3]);
+    assert!(ranking.is_empty());
+}
+
+#[test]
+fn skips_sandwiches_that_overrun_the_block_budget() {
+    // same pool as a more profitable sandwich
+    let mut same_pool = held(9, 800, 200_000);
+    same_pool.pool = Address::repeat_byte(1);
+    let held = vec![
+        held(1, 900, 200_000),
+        same_pool,
+        // too much gas left after the first
+        held(2, 700, 350_000),
+        held(3, 100, 150_000),
+    ];
+
+    let budget = BlockBudget {
+        max_bundles: 3,
+        max_gas: 500_000,
+    };
+    let (selected, dropped) = select_top_opportunities(held, budget);
+
+    assert_eq!(ids(&selected), vec![1, 3]);
+    assert_eq!(ids(&dropped), vec![9, 2]);
+}
+
+#[test]
+fn drops_the_third_sandwich_once_the_block_gas_share_is_used() {
+    let held = vec![
+        held(1, 900, 400_000),
+        held(2, 700, 400_000),
+        held(3, 500, 400_000),
+    ];
+
+    // 3% of a 30m gas block leaves room for two
+    let budget = BlockBudget {
+        max_bundles: 3,
+        max_gas: u64::MAX,
+    }
+    .with_block_gas_fraction(30_000_000, 0.03);
+    assert_eq!(budget.max_gas, 900_000);
+
+    let (selected, dropped) = select_top_opportunities(held, budget);
+
+    assert_eq!(ids(&selected), vec![1, 2]);
+    assert_eq!(ids(&dropped), vec![3]);
+}
+
+#[test]
+fn expires_sandwiches_held_for_an_earlier_block() {
+    let mut ranking = BundleRanking::new();
+    ranking.expire_before(U64::from(100));
+    ranking.hold(held(1, 300, 200_000));
+
+    // still holding for block 100
+    assert!(ranking.expire_before(U64::from(100)).is_empty());
+    assert!(ranking.take(U64::from(101)).is_empty());
+
+    let expired = ranking.expire_before(U64::from(101));
+    assert_eq!(ids(&expired), vec![1]);
+    assert!(ranking.take(U64::from(101)).is_empty());
+}
@@ -0,0 +1,211 @@
+//! Mock rpc for driving the bot end to end without a node, every request is answered from the
+//! canned responses of a scenario in `tests/fixtures`
+use std::{
+    fmt::Debug,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use cfmms::pool::{Pool, UniswapV2Pool, UniswapV3Pool};
+use ethers::{
+    providers::{JsonRpcClient, JsonRpcError, Provider, ProviderError, RpcError},
+    types::{Address, Transaction, U256},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+/// Canned result for requests to `method` whose serialized params contain every string in
+/// `params_contain` (compared lowercase, so addresses can be checksummed)
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureResponse {
+    pub method: String,
+    #[serde(default)]
+    pub params_contain: Vec<String>,
+    pub result: Value,
+}
+
+/// Monitored pool as written in a fixture
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum FixturePool {
+    V2 {
+        address: Address,
+        token_a: Address,
+        token_b: Address,
+        fee: u32,
+        reserve_0: U256,
+        reserve_1: U256,
+    },
+    V3 {
+        address: Address,
+        token_a: Address,
+        token_b: Address,
+        fee: u32,
+        tick_spacing: i32,
+        sqrt_price: U256,
+        tick: i32,
+        liquidity: U256,
+    },
+}
+
+impl From<FixturePool> for Pool {
+    fn from(pool: FixturePool) -> Self {
+        match pool {
+            FixturePool::V2 {
+                address,
+                token_a,
+                token_b,
+                fee,
+                reserve_0,
+                reserve_1,
+            } => Pool::UniswapV2(UniswapV2Pool {
+                address,
+                token_a,
+                token_b,
+                reserve_0: reserve_0.as_u128(),
+                reserve_1: reserve_1.as_u128(),
+                fee,
+                ..Default::default()
+            }),
+            FixturePool::V3 {
+                address,
+                token_a,
+                token_b,
+                fee,
+                tick_spacing,
+                sqrt_price,
+                tick,
+                liquidity,
+            } => Pool::UniswapV3(UniswapV3Pool {
+                address,
+                token_a,
+                token_b,
+                fee,
+                tick_spacing,
+                sqrt_price,
+                tick,
+                liquidity: liquidity.as_u128(),
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// A pending victim with the chain state it is evaluated against
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    /// What the scenario sets up and what the bot should make of it
+    pub description: String,
+    pub victim: Transaction,
+    pub pools: Vec<FixturePool>,
+    pub responses: Vec<FixtureResponse>,
+}
+
+impl Scenario {
+    /// Load `tests/fixtures/<name>.json`
+    pub fn load(name: &str) -> Self {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(format!("{}.json", name));
+        let fixture = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read fixture {:?}: {}", path, e));
+        serde_json::from_str(&fixture)
+            .unwrap_or_else(|e| panic!("Failed to parse fixture {:?}: {}", path, e))
+    }
+
+    pub fn pools(&self) -> Vec<Pool> {
+        self.pools.iter().cloned().map(Pool::from).collect()
+    }
+
+    pub fn client(&self) -> FixtureClient {
+        FixtureClient::new(self.responses.clone())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FixtureError {
+    #[error("no fixture response for {method} with params {params}")]
+    Unexpected { method: String, params: String },
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+}
+
+impl RpcError for FixtureError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        None
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            FixtureError::Serde(e) => Some(e),
+            FixtureError::Unexpected { .. } => None,
+        }
+    }
+}
+
+impl From<FixtureError> for ProviderError {
+    fn from(e: FixtureError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+/// Json rpc client that answers every request with the first matching fixture response, requests
+/// without one fail (so a test shows which response its fixture is missing)
+#[derive(Debug, Clone)]
+pub struct FixtureClient {
+    responses: Vec<FixtureResponse>,
+    /// Methods served so far, shared between clones
+    served: Arc<Mutex<Vec<String>>>,
+}
+
+impl FixtureClient {
+    pub fn new(responses: Vec<FixtureResponse>) -> Self {
+        Self {
+            responses,
+            served: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Provider backed by a clone of this client
+    pub fn provider(&self) -> Arc<Provider<FixtureClient>> {
+        Arc::new(Provider::new(self.clone()))
+    }
+
+    /// Methods of every request answered so far, in order
+    pub fn served(&self) -> Vec<String> {
+        self.served.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for FixtureClient {
+    type Error = FixtureError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, FixtureError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_string(&params)?.to_lowercase();
+        let response = self
+            .responses
+            .iter()
+            .find(|response| {
+                response.method == method
+                    && response
+                        .params_contain
+                        .iter()
+                        .all(|needle| params.contains(&needle.to_lowercase()))
+            })
+            .ok_or_else(|| FixtureError::Unexpected {
+                method: method.to_string(),
+                params: params.clone(),
+            })?;
+
+        self.served.lock().unwrap().push(method.to_string());
+        Ok(serde_json::from_value(response.result.clone())?)
+    }
+}
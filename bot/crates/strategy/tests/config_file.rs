@@ -0,0 +1,124 @@
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Address, U256},
+};
+use strategy::types::{AccessListMode, BotMode, SandoGasProfile, SandoRotation, StratConfig};
+
+// config files normally leave the searcher key to the `SEARCHER_PRIVATE_KEY` env var
+const CONFIG: &str = r#"
+sando_addresses = [
+    "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+    "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+]
+sando_inception_block = 17700000
+searcher_private_key = "0x0000000000000000000000000000000000000000000000000000000000000001"
+min_profit_threshold = "5000000000000000"
+max_tip = "0x2386f26fc10000"
+bid_fraction = 0.8
+mode = "dry_run"
+pool_whitelist = ["0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc"]
+
+[[dexes]]
+factory = "0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f"
+fee_bps = 30
+kind = "v2"
+creation_block = 10000835
+
+[[sando_gas_profiles]]
+address = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"
+v3_frontrun_gas = 110000
+v3_backrun_gas = 105000
+"#;
+
+#[test]
+fn loads_config_file_over_defaults() {
+    let config = StratConfig::from_toml(CONFIG).unwrap();
+
+    assert_eq!(
+        config.sando_addresses,
+        vec![Address::repeat_byte(0xaa), Address::repeat_byte(0xbb)]
+    );
+    assert_eq!(config.sando_inception_block, 17_700_000.into());
+    let searcher: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+    assert_eq!(config.searcher_signer.address(), searcher.address());
+    assert_eq!(
+        config.min_profit_threshold,
+        U256::from(5_000_000_000_000_000u64)
+    );
+    assert_eq!(config.max_tip, U256::exp10(16));
+    assert_eq!(config.bid_fraction, 0.8);
+    assert_eq!(config.mode, BotMode::DryRun);
+    assert_eq!(config.dexes.len(), 1);
+    assert_eq!(config.dexes[0].fee_bps, 30);
+    let pool: Address = "0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc"
+        .parse()
+        .unwrap();
+    assert_eq!(config.pool_whitelist, Some([pool].into_iter().collect()));
+    let profile = config.sando_gas_profiles[&Address::repeat_byte(0xbb)];
+    assert_eq!(profile.v3_frontrun, 110_000);
+    assert_eq!(profile.v2_frontrun, SandoGasProfile::default().v2_frontrun);
+    assert!(!config
+        .sando_gas_profiles
+        .contains_key(&Address::repeat_byte(0xaa)));
+
+    // left out, keeps its default
+    assert_eq!(config.max_concurrent_sims, 8);
+    assert_eq!(config.target_block_offsets, vec![0]);
+    assert_eq!(config.access_list_mode, AccessListMode::WhenCheaper);
+
+    let never = format!("access_list_mode = \"never\"\n{}", CONFIG);
+    let config = StratConfig::from_toml(&never).unwrap();
+    assert_eq!(config.access_list_mode, AccessListMode::Never);
+
+    assert_eq!(config.rng_seed, None);
+    let seeded = format!("rng_seed = 42\nsando_rotation = \"random\"\n{}", CONFIG);
+    let config = StratConfig::from_toml(&seeded).unwrap();
+    assert_eq!(config.rng_seed, Some(42));
+    assert_eq!(config.sando_rotation, SandoRotation::Random);
+}
+
+#[test]
+fn rejects_malformed_config_files() {
+    // required field missing
+    assert!(StratConfig::from_toml("sando_inception_block = 1").is_err());
+    // typo'd field
+    let typo = CONFIG.replace("bid_fraction", "bid_fractoin");
+    assert!(StratConfig::from_toml(&typo).is_err());
+    // out of range
+    let bid = CONFIG.replace("bid_fraction = 0.8", "bid_fraction = 1.5");
+    assert!(StratConfig::from_toml(&bid).is_err());
+    let cap = format!("max_bid_fraction = 1.2\n{}", CONFIG);
+    assert!(StratConfig::from_toml(&cap).is_err());
+    // gas limit below the simulated gas
+    let gas = format!("gas_limit_multiplier = 0.9\n{}", CONFIG);
+    assert!(StratConfig::from_toml(&gas).is_err());
+    // empty pool sync chunks
+    let chunks = format!("sync_chunk_blocks = 0\n{}", CONFIG);
+    assert!(StratConfig::from_toml(&chunks).is_err());
+    let concurrency = format!("sync_concurrency = 0\n{}", CONFIG);
+    assert!(StratConfig::from_toml(&concurrency).is_err());
+    // simulations that can't run a single opcode
+    let sim_gas = format!("max_sim_gas = 0\n{}", CONFIG);
+    assert!(StratConfig::from_toml(&sim_gas).is_err());
+    // no block to target
+    let offsets = format!("target_block_offsets = []\n{}", CONFIG);
+    assert!(StratConfig::from_toml(&offsets).is_err());
+    // unknown dex kind
+    let dex = CONFIG.replace("kind = \"v2\"", "kind = \"v4\"");
+    assert!(StratConfig::from_toml(&dex).is_err());
+    // dex fee that takes the whole swap
+    let fee = CONFIG.replace("fee_bps = 30", "fee_bps = 10000");
+    assert!(StratConfig::from_toml(&fee).is_err());
+    // unknown access list mode
+    let access_list = format!("access_list_mode = \"sometimes\"\n{}", CONFIG);
+    assert!(StratConfig::from_toml(&access_list).is_err());
+    // gas profile for a contract we don't use
+    let profile = CONFIG.replace(
+        "address = \"0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\"",
+        "address = \"0xcccccccccccccccccccccccccccccccccccccccc\"",
+    );
+    assert!(StratConfig::from_toml(&profile).is_err());
+}
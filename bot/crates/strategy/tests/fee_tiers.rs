@@ -0,0 +1,124 @@
+use cfmms::{
+    dex::DexVariant,
+    pool::{Pool, UniswapV3Pool},
+};
+use ethers::{
+    abi::{encode, short_signature, ParamType, Token},
+    types::{Address, Transaction, U256},
+    utils::parse_ether,
+};
+use strategy::{
+    math::v3_sandwich::{protocol_fee_denominators, simulate_swap, TickMap},
+    types::{DexConfig, VictimInfo},
+};
+
+fn weth() -> Address {
+    "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap()
+}
+
+fn token() -> Address {
+    Address::repeat_byte(0x11)
+}
+
+/// Same pair at `fee` (every fee tier of a pair is a separate pool)
+fn pool_with_fee(address: Address, fee: u32) -> UniswapV3Pool {
+    UniswapV3Pool {
+        address,
+        token_a: weth().min(token()),
+        token_b: weth().max(token()),
+        fee,
+        liquidity: 10u128.pow(24),
+        sqrt_price: U256::one() << 96,
+        tick: 0,
+        tick_spacing: 10,
+        ..Default::default()
+    }
+}
+
+/// Victim calls `exactInputSingle` on the v3 swap router for a `weth->token` swap
+fn victim_swapping_through(fee: u32) -> VictimInfo {
+    let params = ParamType::Tuple(vec![
+        ParamType::Address,
+        ParamType::Address,
+        ParamType::Uint(24),
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(160),
+    ]);
+    let mut input = short_signature("exactInputSingle", &[params]).to_vec();
+    input.extend(encode(&[Token::Tuple(vec![
+        Token::Address(weth()),
+        Token::Address(token()),
+        Token::Uint(fee.into()),
+        Token::Address(Address::repeat_byte(0x22)),
+        Token::Uint(U256::MAX),
+        Token::Uint(parse_ether(1).unwrap()),
+        Token::Uint(U256::from(1_000)),
+        Token::Uint(U256::zero()),
+    ])]));
+
+    VictimInfo::new(Transaction {
+        input: input.into(),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn dex_only_monitors_configured_fee_tiers() {
+    let dex = DexConfig::new(Address::zero(), 30, DexVariant::UniswapV3, 0, vec![])
+        .with_fee_tiers(vec![500]);
+
+    let low_fee = Pool::UniswapV3(pool_with_fee(Address::repeat_byte(0xaa), 500));
+    let high_fee = Pool::UniswapV3(pool_with_fee(Address::repeat_byte(0xbb), 3_000));
+
+    assert!(dex.monitors_pool(&low_fee));
+    assert!(!dex.monitors_pool(&high_fee));
+}
+
+#[test]
+fn swap_limits_only_apply_to_the_fee_tier_swapped_through() {
+    let victim = victim_swapping_through(500);
+
+    assert_eq!(
+        victim.get_swap_limits(token(), Some(500)),
+        Some((parse_ether(1).unwrap(), U256::from(1_000)))
+    );
+    // 0.3% pool for the same pair exists but the victim didn't swap on it
+    assert_eq!(victim.get_swap_limits(token(), Some(3_000)), None);
+}
+
+#[test]
+fn swap_math_uses_the_matched_pool_fee() {
+    let low_fee = pool_with_fee(Address::repeat_byte(0xaa), 500);
+    let high_fee = pool_with_fee(Address::repeat_byte(0xbb), 3_000);
+    let amount_in = parse_ether(1).unwrap();
+    let tick_map = TickMap {
+        lowest_tick: -887_270,
+        highest_tick: 887_270,
+        ..Default::default()
+    };
+
+    let low_fee_out = simulate_swap(&low_fee, &tick_map, true, amount_in)
+        .unwrap()
+        .amount_out;
+    let high_fee_out = simulate_swap(&high_fee, &tick_map, true, amount_in)
+        .unwrap()
+        .amount_out;
+
+    // price and liquidity are identical, only the fee tier differs
+    assert!(low_fee_out > high_fee_out);
+}
+
+#[test]
+fn splits_the_packed_protocol_fee() {
+    assert_eq!(protocol_fee_denominators(0), (None, None));
+    // 1/4 of the fee on token0 inputs, 1/5 on token1 inputs
+    assert_eq!(protocol_fee_denominators(0x54), (Some(4), Some(5)));
+    // switched on for one side only
+    assert_eq!(protocol_fee_denominators(0x60), (None, Some(6)));
+    assert_eq!(protocol_fee_denominators(0x0a), (Some(10), None));
+}
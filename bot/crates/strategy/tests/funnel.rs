@@ -0,0 +1,53 @@
+use strategy::managers::funnel::{FunnelStage, FunnelTracker};
+
+#[test]
+fn counters_start_over_every_block() {
+    let mut tracker = FunnelTracker::new(2);
+    tracker.record(FunnelStage::Seen, 200);
+    tracker.record(FunnelStage::Prefiltered, 40);
+    tracker.record(FunnelStage::TouchedPools, 10);
+    tracker.record(FunnelStage::Profitable, 4);
+    tracker.record(FunnelStage::Submitted, 2);
+    tracker.record(FunnelStage::Landed, 1);
+
+    let closed = tracker.close_block();
+    assert_eq!(closed.blocks, 1);
+    assert_eq!(closed.get(FunnelStage::Seen), 200);
+    assert_eq!(closed.conversion(FunnelStage::Seen), None);
+    assert_eq!(closed.conversion(FunnelStage::Prefiltered), Some(20.0));
+    assert_eq!(closed.conversion(FunnelStage::Landed), Some(50.0));
+    assert_eq!(
+        closed.summary(),
+        "seen 200 > prefiltered 40 (20.0%) > touched_pools 10 (25.0%) > profitable 4 (40.0%) > \
+         submitted 2 (50.0%) > landed 1 (50.0%)"
+    );
+
+    // next block's funnel doesn't carry anything over
+    let empty = tracker.close_block();
+    assert_eq!(empty.get(FunnelStage::Seen), 0);
+    assert_eq!(empty.conversion(FunnelStage::Prefiltered), None);
+}
+
+#[test]
+fn summary_sums_the_window_of_closed_blocks() {
+    let mut tracker = FunnelTracker::new(2);
+
+    tracker.record(FunnelStage::Seen, 100);
+    tracker.record(FunnelStage::Prefiltered, 10);
+    tracker.close_block();
+    assert_eq!(tracker.poll_summary(), None);
+
+    tracker.record(FunnelStage::Seen, 50);
+    tracker.record(FunnelStage::Prefiltered, 20);
+    tracker.close_block();
+    let window = tracker.poll_summary().unwrap();
+    assert_eq!(window.blocks, 2);
+    assert_eq!(window.get(FunnelStage::Seen), 150);
+    assert_eq!(window.get(FunnelStage::Prefiltered), 30);
+    assert_eq!(window.conversion(FunnelStage::Prefiltered), Some(20.0));
+
+    // window starts over once summarized
+    assert_eq!(tracker.poll_summary(), None);
+    tracker.close_block();
+    assert_eq!(tracker.poll_summary(), None);
+}
@@ -0,0 +1,96 @@
+use ethers::{
+    types::{Address, Log, TransactionReceipt, H256, I256, U256, U64},
+    utils::keccak256,
+};
+use strategy::managers::loss_guard::{realized_profit, LossGuard};
+
+fn block(number: u64) -> U64 {
+    U64::from(number)
+}
+
+#[test]
+fn trips_once_window_loss_passes_max() {
+    let mut guard = LossGuard::new(U256::from(100), 10);
+
+    assert!(!guard.record(block(1), I256::from(-60)));
+    assert!(!guard.record(block(2), I256::from(20)));
+    assert!(!guard.is_halted());
+
+    assert!(guard.record(block(3), I256::from(-70)));
+    assert!(guard.is_halted());
+    assert_eq!(guard.window_profit(), I256::from(-110));
+
+    // already halted, doesn't trip again
+    assert!(!guard.record(block(4), I256::from(-50)));
+    // no cooldown, only a restart re-enables
+    assert!(!guard.poll_cooldown(block(1_000)));
+    assert!(guard.is_halted());
+}
+
+#[test]
+fn old_losses_fall_out_of_the_window() {
+    let mut guard = LossGuard::new(U256::from(100), 10);
+
+    guard.record(block(1), I256::from(-90));
+    // block 1 is outside the last 10 blocks at block 11
+    assert!(!guard.record(block(11), I256::from(-90)));
+    assert_eq!(guard.window_profit(), I256::from(-90));
+}
+
+#[test]
+fn reenables_after_cooldown() {
+    let mut guard = LossGuard::new(U256::from(100), 10).with_cooldown_blocks(5);
+
+    assert!(guard.record(block(1), I256::from(-150)));
+    assert!(!guard.poll_cooldown(block(5)));
+    assert!(guard.is_halted());
+
+    assert!(guard.poll_cooldown(block(6)));
+    assert!(!guard.is_halted());
+    assert_eq!(guard.window_profit(), I256::zero());
+}
+
+fn weth_transfer(weth: Address, from: Address, to: Address, amount: u64) -> Log {
+    let mut data = [0u8; 32];
+    U256::from(amount).to_big_endian(&mut data);
+    Log {
+        address: weth,
+        topics: vec![
+            H256::from(keccak256("Transfer(address,address,uint256)")),
+            H256::from(from),
+            H256::from(to),
+        ],
+        data: data.to_vec().into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn realized_profit_counts_weth_flows_and_gas() {
+    let weth = Address::repeat_byte(0xee);
+    let sando = Address::repeat_byte(0x5a);
+    let pool = Address::repeat_byte(0xaa);
+
+    let receipt = |logs: Vec<Log>, gas_used: u64| TransactionReceipt {
+        to: Some(sando),
+        logs,
+        gas_used: Some(gas_used.into()),
+        effective_gas_price: Some(U256::from(2)),
+        ..Default::default()
+    };
+    let frontrun = receipt(vec![weth_transfer(weth, sando, pool, 1_000)], 100);
+    let backrun = receipt(
+        vec![
+            weth_transfer(weth, pool, sando, 1_500),
+            // not weth, ignored
+            weth_transfer(Address::repeat_byte(0xbb), pool, sando, 99_999),
+        ],
+        150,
+    );
+
+    // 1500 in - 1000 out - (100 + 150) * 2 gas - 50 coinbase payment
+    assert_eq!(
+        realized_profit(&[backrun, frontrun], weth, U256::from(50)),
+        I256::from(-50)
+    );
+}
@@ -4,11 +4,12 @@ use cfmms::pool::{Pool, UniswapV2Pool, UniswapV3Pool};
 use ethers::{
     prelude::Lazy,
     providers::{Middleware, Provider, Ws},
-    types::{Address, Transaction, TxHash, U64},
+    types::{Address, BlockNumber, Transaction, TxHash, U256, U64},
 };
 use strategy::{
     bot::SandoBot,
-    types::{BlockInfo, RawIngredients, StratConfig},
+    math::is_within_tolerance,
+    types::{BlockInfo, RawIngredients, StateDiffMethod, StratConfig, SwapDirection, VictimInfo},
 };
 
 // -- consts --
@@ -31,15 +32,15 @@ fn setup_logger() {
 async fn setup_bot(provider: Arc<Provider<Ws>>) -> SandoBot<Provider<Ws>> {
     setup_logger();
 
-    let strat_config = StratConfig {
-        sando_address: "0xaAaAaAaaAaAaAaaAaAAAAAAAAaaaAaAaAaaAaaAa"
+    let strat_config = StratConfig::new(
+        "0xaAaAaAaaAaAaAaaAaAAAAAAAAaaaAaAaAaaAaaAa"
             .parse()
             .unwrap(),
-        sando_inception_block: U64::from(17700000),
-        searcher_signer: "0x0000000000000000000000000000000000000000000000000000000000000001"
+        U64::from(17700000),
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
             .parse()
             .unwrap(),
-    };
+    );
 
     SandoBot::new(provider, strat_config)
 }
@@ -190,3 +191,115 @@ async fn can_sandwich_uni_v3() {
         .await
         .unwrap();
 }
+
+/// analytic round trip (frontrun, victim on the moved reserves, backrun) should match revm up to
+/// the contract's amount encoding, testing against the same victim as `can_sandwich_uni_v2`
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn analytic_v2_round_trip_matches_simulation() {
+    let client = Arc::new(Provider::new(Ws::connect(WSS_RPC).await.unwrap()));
+
+    let bot = setup_bot(client.clone()).await;
+    let sando_address = hex_to_address("0xaAaAaAaaAaAaAaaAaAAAAAAAAaaaAaAaAaaAaaAa");
+
+    let victim_tx = victim_tx_hash(
+        "0xfecf2c78d1418e6905c18a6a6301c9d39b14e5320e345adce52baaecf805580d",
+        client.clone(),
+    )
+    .await;
+    let pool =
+        hex_to_univ2_pool("0x5d1dd0661E1D22697943C1F50Cc726eA3143329b", client.clone()).await;
+    let target_block = block_num_to_info(17754167, client.clone()).await;
+
+    let mut victim = VictimInfo::new(victim_tx.clone());
+    victim
+        .fill_state_diffs(
+            BlockNumber::Number(17754166.into()),
+            client.clone(),
+            StateDiffMethod::TraceCall,
+        )
+        .await
+        .unwrap();
+
+    let v2_pool = match pool {
+        Pool::UniswapV2(p) => p,
+        Pool::UniswapV3(_) => unreachable!(),
+    };
+    let (optimal_input, profit) = bot
+        .find_optimal_v2_input(
+            &v2_pool,
+            &victim,
+            SwapDirection::WethToToken,
+            U256::zero(),
+            sando_address,
+        )
+        .unwrap();
+
+    let ingredients = RawIngredients::new(
+        vec![victim_tx],
+        *WETH_ADDRESS,
+        hex_to_address("0x3642Cf76c5894B4aB51c1080B2c4F5B9eA734106"),
+        pool,
+    );
+    let recipe = bot
+        .is_sandwichable_with_input(ingredients, target_block, optimal_input, sando_address)
+        .unwrap();
+
+    // backrun keeps a little token dust, so simulated revenue is a hair lower
+    assert!(is_within_tolerance(
+        profit.gross_revenue,
+        recipe.get_revenue(),
+        1
+    ));
+}
+
+/// v3 round trip walks the backrun back down from the price the victim left (re-crossing the
+/// ticks the frontrun and victim crossed), it should match revm, testing against the same victim
+/// as `can_sandwich_uni_v3`
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn analytic_v3_round_trip_matches_simulation() {
+    let client = Arc::new(Provider::new(Ws::connect(WSS_RPC).await.unwrap()));
+
+    let bot = setup_bot(client.clone()).await;
+    let sando_address = hex_to_address("0xaAaAaAaaAaAaAaaAaAAAAAAAAaaaAaAaAaaAaaAa");
+
+    let victim_tx = victim_tx_hash(
+        "0x90dfe56814821e7f76f2e4970a7b35948670a968abffebb7be69fe528283e6d8",
+        client.clone(),
+    )
+    .await;
+    let pool =
+        hex_to_univ3_pool("0x62CBac19051b130746Ec4CF96113aF5618F3A212", client.clone()).await;
+    let target_block = block_num_to_info(16863225, client.clone()).await;
+    let state_block = BlockNumber::Number(16863224.into());
+
+    let mut victim = VictimInfo::new(victim_tx.clone());
+    victim
+        .fill_state_diffs(state_block, client.clone(), StateDiffMethod::TraceCall)
+        .await
+        .unwrap();
+
+    let v3_pool = match pool {
+        Pool::UniswapV3(p) => p,
+        Pool::UniswapV2(_) => unreachable!(),
+    };
+    let (optimal_input, profit) = bot
+        .find_optimal_v3_input(&v3_pool, &victim, state_block, sando_address)
+        .await
+        .unwrap();
+
+    let ingredients = RawIngredients::new(
+        vec![victim_tx],
+        *WETH_ADDRESS,
+        hex_to_address("0x24C19F7101c1731b85F1127EaA0407732E36EcDD"),
+        pool,
+    );
+    let recipe = bot
+        .is_sandwichable_with_input(ingredients, target_block, optimal_input, sando_address)
+        .unwrap();
+
+    assert!(is_within_tolerance(
+        profit.gross_revenue,
+        recipe.get_revenue(),
+        1
+    ));
+}
@@ -0,0 +1,462 @@
+use ethers::{
+    types::{I256, U256},
+    utils::parse_ether,
+};
+use strategy::{
+    math::{
+        profit::{rank_by_net_profit, SandwichProfit},
+        v2_sandwich::{
+            find_optimal_exact_output_input, find_optimal_input, find_optimal_merged_input,
+            get_amount_in, get_amount_out, get_backrun_revenue, get_merged_sandwich_revenue,
+            get_sandwich_revenue, max_frontrun_for_exact_output, max_frontrun_for_merged_slippage,
+            max_frontrun_for_slippage,
+        },
+    },
+    types::PaymentStrategy,
+};
+
+// uniswap v2 swap fee
+const FEE_BPS: u32 = 30;
+
+#[test]
+fn tight_slippage_caps_frontrun() {
+    let reserve_weth = parse_ether(1000).unwrap();
+    let reserve_token = parse_ether(1_000_000).unwrap();
+    let victim_in = parse_ether(10).unwrap();
+    let inventory = parse_ether(1000).unwrap();
+    let epsilon = U256::from(10_000_000_000u64);
+
+    // victim accepts 0.5% slippage
+    let expected_out = get_amount_out(victim_in, reserve_weth, reserve_token, FEE_BPS);
+    let victim_out_min = expected_out * 995 / 1000;
+
+    let (greedy_input, _) = find_optimal_input(
+        victim_in,
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        inventory,
+        epsilon,
+    );
+
+    let max_frontrun = max_frontrun_for_slippage(
+        victim_in,
+        victim_out_min,
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        inventory,
+    )
+    .unwrap();
+
+    let (capped_input, _) = find_optimal_input(
+        victim_in,
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        max_frontrun,
+        epsilon,
+    );
+
+    assert!(capped_input < greedy_input);
+    assert!(capped_input <= max_frontrun);
+
+    // victim still clears their slippage floor after our frontrun
+    let frontrun_out = get_amount_out(capped_input, reserve_weth, reserve_token, FEE_BPS);
+    let victim_out = get_amount_out(
+        victim_in,
+        reserve_weth + capped_input,
+        reserve_token - frontrun_out,
+        FEE_BPS,
+    );
+    assert!(victim_out >= victim_out_min);
+}
+
+#[test]
+fn victim_at_slippage_limit_is_not_sandwichable() {
+    let reserve_weth = parse_ether(1000).unwrap();
+    let reserve_token = parse_ether(1_000_000).unwrap();
+    let victim_in = parse_ether(10).unwrap();
+
+    // victim expects more than the pool can give them
+    let victim_out_min = get_amount_out(victim_in, reserve_weth, reserve_token, FEE_BPS) + 1;
+
+    assert!(max_frontrun_for_slippage(
+        victim_in,
+        victim_out_min,
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        parse_ether(1000).unwrap(),
+    )
+    .is_none());
+}
+
+// Frontrun input (in whole ether) with the highest revenue, checking every ether up to
+// `inventory`, against a 1000 weth / 1M token pool
+fn brute_force_best_input(victim_in: U256, fee_bps: u32, inventory: u64) -> (U256, U256) {
+    let reserve_weth = parse_ether(1000).unwrap();
+    let reserve_token = parse_ether(1_000_000).unwrap();
+
+    (0..=inventory)
+        .map(|ether| parse_ether(ether).unwrap())
+        .map(|input| {
+            let revenue =
+                get_sandwich_revenue(input, victim_in, reserve_weth, reserve_token, fee_bps);
+            (input, revenue)
+        })
+        .max_by_key(|&(_, revenue)| revenue)
+        .unwrap()
+}
+
+fn assert_optimizer_converges(victim_in: U256, fee_bps: u32) {
+    let inventory = 10_000;
+    let (input, revenue) = find_optimal_input(
+        victim_in,
+        parse_ether(1000).unwrap(),
+        parse_ether(1_000_000).unwrap(),
+        fee_bps,
+        parse_ether(inventory).unwrap(),
+        U256::from(10_000_000_000u64),
+    );
+    let (best_input, best_revenue) = brute_force_best_input(victim_in, fee_bps, inventory);
+
+    assert!(!best_revenue.is_zero());
+    // within 0.1% of the best revenue and 5% of the best input
+    assert!(revenue * 1000 >= best_revenue * 999);
+    assert!(input * 100 >= best_input * 95 && input * 100 <= best_input * 105);
+}
+
+#[test]
+fn optimizer_converges_on_quarter_percent_fee_pool() {
+    assert_optimizer_converges(parse_ether(10).unwrap(), 25);
+}
+
+#[test]
+fn optimizer_converges_on_one_percent_fee_pool() {
+    assert_optimizer_converges(parse_ether(50).unwrap(), 100);
+}
+
+#[test]
+fn higher_pool_fee_lowers_sandwich_revenue() {
+    let reserve_weth = parse_ether(1000).unwrap();
+    let reserve_token = parse_ether(1_000_000).unwrap();
+    let victim_in = parse_ether(10).unwrap();
+    let inventory = parse_ether(10_000).unwrap();
+    let epsilon = U256::from(10_000_000_000u64);
+
+    let revenue = |fee_bps| {
+        find_optimal_input(
+            victim_in,
+            reserve_weth,
+            reserve_token,
+            fee_bps,
+            inventory,
+            epsilon,
+        )
+        .1
+    };
+
+    assert!(revenue(25) > revenue(FEE_BPS));
+    // 10 weth doesn't move the price enough to pay for two 1% swaps
+    assert!(revenue(100).is_zero());
+}
+
+#[test]
+fn frontrun_keeps_exact_output_victim_under_max_input() {
+    let reserve_weth = parse_ether(1000).unwrap();
+    let reserve_token = parse_ether(1_000_000).unwrap();
+    let inventory = parse_ether(1000).unwrap();
+    let epsilon = U256::from(10_000_000_000u64);
+
+    // victim buys exactly 9_000 tokens and accepts paying 0.5% more than the current price
+    let victim_out = parse_ether(9_000).unwrap();
+    let expected_in = get_amount_in(victim_out, reserve_weth, reserve_token, FEE_BPS).unwrap();
+    assert!(get_amount_out(expected_in, reserve_weth, reserve_token, FEE_BPS) >= victim_out);
+    let victim_in_max = expected_in * 1005 / 1000;
+
+    let max_frontrun = max_frontrun_for_exact_output(
+        victim_out,
+        victim_in_max,
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        inventory,
+    )
+    .unwrap();
+    assert!(max_frontrun < inventory);
+
+    let (input, revenue) = find_optimal_exact_output_input(
+        victim_out,
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        max_frontrun,
+        epsilon,
+    );
+    assert!(!revenue.is_zero());
+    assert!(input <= max_frontrun);
+
+    // our frontrun raised what the victim pays, but not past their max input
+    let frontrun_out = get_amount_out(input, reserve_weth, reserve_token, FEE_BPS);
+    let victim_in = get_amount_in(
+        victim_out,
+        reserve_weth + input,
+        reserve_token - frontrun_out,
+        FEE_BPS,
+    )
+    .unwrap();
+    assert!(victim_in > expected_in);
+    assert!(victim_in <= victim_in_max);
+
+    // victim already at their max input
+    assert!(max_frontrun_for_exact_output(
+        victim_out,
+        expected_in - 1,
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        inventory,
+    )
+    .is_none());
+}
+
+#[test]
+fn backrun_sells_inventory_into_victim_price_impact() {
+    let reserve_weth = parse_ether(1000).unwrap();
+    let reserve_token = parse_ether(1_000_000).unwrap();
+    let backrun_in = parse_ether(5_000).unwrap();
+
+    let revenue = |victim_in| {
+        get_backrun_revenue(backrun_in, victim_in, reserve_weth, reserve_token, FEE_BPS)
+    };
+
+    // nothing to capture without a victim, a bigger victim moves price further
+    assert!(revenue(U256::zero()).is_zero());
+    assert!(!revenue(parse_ether(1).unwrap()).is_zero());
+    assert!(revenue(parse_ether(10).unwrap()) > revenue(parse_ether(1).unwrap()));
+}
+
+#[test]
+fn picks_profitable_pool_when_victim_touches_two() {
+    let inventory = parse_ether(1000).unwrap();
+    let epsilon = U256::from(10_000_000_000u64);
+    let base_fee = U256::from(30_000_000_000u64);
+
+    // victim swaps through two pools, big weth leg on a deep pool and dust on another
+    let pools = [
+        (
+            "deep",
+            parse_ether(1000).unwrap(),
+            parse_ether(1_000_000).unwrap(),
+            parse_ether(50).unwrap(),
+        ),
+        (
+            "dust",
+            parse_ether(100).unwrap(),
+            parse_ether(100_000).unwrap(),
+            parse_ether("0.01").unwrap(),
+        ),
+    ];
+
+    let candidates = pools
+        .into_iter()
+        .map(|(name, reserve_weth, reserve_token, victim_in)| {
+            let (_, revenue) = find_optimal_input(
+                victim_in,
+                reserve_weth,
+                reserve_token,
+                FEE_BPS,
+                inventory,
+                epsilon,
+            );
+            let profit = SandwichProfit::new(
+                revenue,
+                U256::from(150_000),
+                U256::from(130_000),
+                base_fee,
+                U256::zero(),
+                PaymentStrategy::PriorityFee,
+            );
+            (name, profit)
+        })
+        .collect();
+
+    let ranked = rank_by_net_profit(candidates, U256::zero());
+
+    assert_eq!(ranked.len(), 1);
+    assert_eq!(ranked[0].0, "deep");
+}
+
+#[test]
+fn coinbase_payment_costs_extra_backrun_gas() {
+    let revenue = parse_ether("0.1").unwrap();
+    let base_fee = U256::from(20_000_000_000u64);
+    let priority_fee = U256::from(1_000_000_000u64);
+
+    let by_priority_fee = SandwichProfit::new(
+        revenue,
+        U256::from(100_000),
+        U256::from(100_000),
+        base_fee,
+        priority_fee,
+        PaymentStrategy::PriorityFee,
+    );
+    let by_coinbase = SandwichProfit::new(
+        revenue,
+        U256::from(100_000),
+        U256::from(100_000),
+        base_fee,
+        priority_fee,
+        PaymentStrategy::CoinbaseTransfer,
+    );
+
+    // builder is paid the same, the transfer itself is paid at base fee
+    let transfer_cost = I256::from_raw(by_coinbase.gas_cost - by_priority_fee.gas_cost);
+    assert!(transfer_cost > I256::zero());
+    assert_eq!(
+        by_priority_fee.net_profit - by_coinbase.net_profit,
+        transfer_cost
+    );
+}
+
+#[test]
+fn backrun_is_priced_on_post_victim_reserves() {
+    let (reserve_weth, reserve_token) = (parse_ether(1_000).unwrap(), parse_ether(2_000).unwrap());
+    let frontrun_in = parse_ether(5).unwrap();
+    let victim_in = parse_ether(20).unwrap();
+
+    // walk the round trip one swap at a time
+    let frontrun_out = get_amount_out(frontrun_in, reserve_weth, reserve_token, FEE_BPS);
+    let (reserve_weth, reserve_token) = (reserve_weth + frontrun_in, reserve_token - frontrun_out);
+    let victim_out = get_amount_out(victim_in, reserve_weth, reserve_token, FEE_BPS);
+    let (reserve_weth, reserve_token) = (reserve_weth + victim_in, reserve_token - victim_out);
+    let backrun_out = get_amount_out(frontrun_out, reserve_token, reserve_weth, FEE_BPS);
+
+    let (reserve_weth, reserve_token) = (parse_ether(1_000).unwrap(), parse_ether(2_000).unwrap());
+    let revenue =
+        get_sandwich_revenue(frontrun_in, victim_in, reserve_weth, reserve_token, FEE_BPS);
+    assert_eq!(revenue, backrun_out - frontrun_in);
+
+    // selling back before the victim moves the price only pays fees
+    assert_eq!(
+        get_sandwich_revenue(
+            frontrun_in,
+            U256::zero(),
+            reserve_weth,
+            reserve_token,
+            FEE_BPS
+        ),
+        U256::zero()
+    );
+}
+
+#[test]
+fn merged_sandwich_beats_separate_bundles_for_same_direction_victims() {
+    let reserve_weth = parse_ether(1000).unwrap();
+    let reserve_token = parse_ether(1_000_000).unwrap();
+    let inventory = parse_ether(1000).unwrap();
+    let epsilon = U256::from(1_000_000);
+    let first_in = parse_ether(10).unwrap();
+    let second_in = parse_ether(15).unwrap();
+
+    // one victim merged is a plain sandwich
+    let frontrun_in = parse_ether(3).unwrap();
+    assert_eq!(
+        get_merged_sandwich_revenue(
+            frontrun_in,
+            &[first_in],
+            reserve_weth,
+            reserve_token,
+            FEE_BPS
+        ),
+        get_sandwich_revenue(frontrun_in, first_in, reserve_weth, reserve_token, FEE_BPS)
+    );
+
+    let solo = |victim_in| {
+        find_optimal_input(
+            victim_in,
+            reserve_weth,
+            reserve_token,
+            FEE_BPS,
+            inventory,
+            epsilon,
+        )
+        .1
+    };
+    let (merged_input, merged_revenue) = find_optimal_merged_input(
+        &[first_in, second_in],
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        inventory,
+        epsilon,
+    );
+
+    // both victims push the price the same way, one round trip around both captures more than
+    // two sandwiches priced on the untouched pool
+    assert!(merged_revenue > solo(first_in) + solo(second_in));
+    assert_eq!(
+        merged_revenue,
+        get_merged_sandwich_revenue(
+            merged_input,
+            &[first_in, second_in],
+            reserve_weth,
+            reserve_token,
+            FEE_BPS
+        )
+    );
+}
+
+#[test]
+fn merged_slippage_prices_each_victim_after_the_previous_one() {
+    let reserve_weth = parse_ether(1000).unwrap();
+    let reserve_token = parse_ether(1_000_000).unwrap();
+    let inventory = parse_ether(1000).unwrap();
+    let first_in = parse_ether(10).unwrap();
+    let second_in = parse_ether(15).unwrap();
+
+    // second victim's floor is exactly what they get swapping after the first one
+    let first_out = get_amount_out(first_in, reserve_weth, reserve_token, FEE_BPS);
+    let second_out = get_amount_out(
+        second_in,
+        reserve_weth + first_in,
+        reserve_token - first_out,
+        FEE_BPS,
+    );
+
+    // alone on the pool that floor leaves plenty of room to frontrun
+    assert!(
+        max_frontrun_for_slippage(
+            second_in,
+            second_out,
+            reserve_weth,
+            reserve_token,
+            FEE_BPS,
+            inventory
+        )
+        .unwrap()
+            > parse_ether(1).unwrap()
+    );
+    // behind the first victim it leaves none
+    assert_eq!(
+        max_frontrun_for_merged_slippage(
+            &[(first_in, U256::zero()), (second_in, second_out)],
+            reserve_weth,
+            reserve_token,
+            FEE_BPS,
+            inventory,
+        ),
+        Some(U256::zero())
+    );
+    assert_eq!(
+        max_frontrun_for_merged_slippage(
+            &[(first_in, U256::zero()), (second_in, second_out + 1)],
+            reserve_weth,
+            reserve_token,
+            FEE_BPS,
+            inventory,
+        ),
+        None
+    );
+}
@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+
+use ethers::types::{Address, Transaction, TxHash, U256, U64};
+use strategy::managers::mempool_manager::{
+    can_include_in_target_block, preceding_txs, priority_fee_per_gas, tip_exceeds_swap_share,
+    MempoolManager,
+};
+
+fn pending_tx(hash: u64, from: u64, nonce: u64) -> Transaction {
+    Transaction {
+        hash: TxHash::from_low_u64_be(hash),
+        from: Address::from_low_u64_be(from),
+        nonce: U256::from(nonce),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn skips_rebroadcast_for_same_block() {
+    let mut mempool_manager = MempoolManager::default();
+    let tx = pending_tx(1, 1, 0);
+
+    assert!(mempool_manager.mark_seen(tx.hash, U64::from(100)));
+    assert!(!mempool_manager.mark_seen(tx.hash, U64::from(100)));
+}
+
+#[test]
+fn evaluates_again_after_stale_eviction() {
+    let mut mempool_manager = MempoolManager::default();
+    let tx = pending_tx(1, 1, 0);
+
+    mempool_manager.mark_seen(tx.hash, U64::from(100));
+    mempool_manager.evict_stale(U64::from(101));
+
+    assert!(mempool_manager.mark_seen(tx.hash, U64::from(101)));
+}
+
+#[test]
+fn evicts_oldest_once_full() {
+    let mut mempool_manager = MempoolManager::new(2);
+
+    mempool_manager.mark_seen(TxHash::from_low_u64_be(1), U64::from(100));
+    mempool_manager.mark_seen(TxHash::from_low_u64_be(2), U64::from(100));
+    mempool_manager.mark_seen(TxHash::from_low_u64_be(3), U64::from(100));
+
+    // oldest hash was forgotten, newer ones are still deduped
+    assert!(mempool_manager.mark_seen(TxHash::from_low_u64_be(1), U64::from(100)));
+    assert!(!mempool_manager.mark_seen(TxHash::from_low_u64_be(3), U64::from(100)));
+}
+
+#[test]
+fn detects_same_nonce_replacement() {
+    let mut mempool_manager = MempoolManager::default();
+    let original = pending_tx(1, 1, 5);
+    let replacement = pending_tx(2, 1, 5);
+
+    assert_eq!(mempool_manager.track_replacement(&original), None);
+    mempool_manager.mark_seen(original.hash, U64::from(100));
+
+    assert_eq!(
+        mempool_manager.track_replacement(&replacement),
+        Some(original.hash)
+    );
+
+    // replacement is evaluated, and the old version is no longer treated as seen
+    assert!(mempool_manager.mark_seen(replacement.hash, U64::from(100)));
+    assert!(mempool_manager.mark_seen(original.hash, U64::from(100)));
+}
+
+#[test]
+fn rebroadcast_and_other_nonces_are_not_replacements() {
+    let mut mempool_manager = MempoolManager::default();
+    let tx = pending_tx(1, 1, 5);
+
+    mempool_manager.track_replacement(&tx);
+
+    assert_eq!(mempool_manager.track_replacement(&tx), None);
+    assert_eq!(
+        mempool_manager.track_replacement(&pending_tx(2, 1, 6)),
+        None
+    );
+    assert_eq!(
+        mempool_manager.track_replacement(&pending_tx(3, 2, 5)),
+        None
+    );
+}
+
+#[test]
+fn unparks_tx_once_base_fee_drops() {
+    let mut mempool_manager = MempoolManager::default();
+    let mut tx = pending_tx(1, 1, 0);
+    tx.max_fee_per_gas = Some(U256::from(20));
+
+    mempool_manager.park(tx.clone(), U64::from(100));
+
+    assert!(mempool_manager.take_includable(U256::from(25)).is_empty());
+    assert_eq!(
+        mempool_manager.take_includable(U256::from(20)),
+        vec![tx.clone()]
+    );
+    assert_eq!(mempool_manager.parked_count(), 0);
+}
+
+#[test]
+fn drops_parked_txs_once_mined_or_expired() {
+    let mut mempool_manager = MempoolManager::default();
+    let mined = pending_tx(1, 1, 0);
+    let expired = pending_tx(2, 2, 0);
+    let waiting = pending_tx(3, 3, 0);
+
+    mempool_manager.park(mined.clone(), U64::from(100));
+    mempool_manager.park(expired, U64::from(95));
+    mempool_manager.park(waiting.clone(), U64::from(100));
+
+    mempool_manager.prune_parked(&HashSet::from([mined.hash]), U64::from(101), 5);
+
+    assert_eq!(mempool_manager.take_includable(U256::zero()), vec![waiting]);
+}
+
+#[test]
+fn bundle_needs_to_clear_base_fee_along_with_victim() {
+    let mut victim = pending_tx(1, 1, 0);
+    victim.max_fee_per_gas = Some(U256::from(30));
+
+    // recipe priced for a base fee of 20 while the next block's base fee rose to 25
+    assert!(!can_include_in_target_block(
+        &victim,
+        U256::from(20),
+        U256::from(25)
+    ));
+    assert!(can_include_in_target_block(
+        &victim,
+        U256::from(25),
+        U256::from(25)
+    ));
+    assert!(!can_include_in_target_block(
+        &victim,
+        U256::from(40),
+        U256::from(35)
+    ));
+}
+
+#[test]
+fn flags_victims_tipping_too_much_for_their_swap() {
+    let gwei = U256::from(10).pow(9.into());
+    let mut victim = pending_tx(1, 1, 0);
+    victim.gas = U256::from(200_000);
+    victim.max_fee_per_gas = Some(gwei * 300);
+    victim.max_priority_fee_per_gas = Some(gwei * 250);
+    // 0.1 eth swap
+    let swap_value = gwei * 100_000_000;
+
+    // tip is capped by the max fee, 0.04 eth over its gas limit is 4000 bps of the swap
+    assert_eq!(priority_fee_per_gas(&victim, gwei * 100), gwei * 200);
+    assert!(tip_exceeds_swap_share(
+        &victim,
+        gwei * 100,
+        swap_value,
+        1_000
+    ));
+    assert!(!tip_exceeds_swap_share(
+        &victim,
+        gwei * 100,
+        swap_value,
+        4_000
+    ));
+
+    // legacy txs tip everything above the base fee
+    let mut legacy = pending_tx(2, 2, 0);
+    legacy.gas = U256::from(200_000);
+    legacy.gas_price = Some(gwei * 30);
+    assert_eq!(priority_fee_per_gas(&legacy, gwei * 25), gwei * 5);
+    assert!(!tip_exceeds_swap_share(
+        &legacy,
+        gwei * 25,
+        swap_value,
+        1_000
+    ));
+}
+
+#[test]
+fn orders_txs_outbidding_the_victim_ahead_of_it() {
+    let base_fee = U256::from(10);
+    let tipping = |hash: u64, from: u64, tip: u64| {
+        let mut tx = pending_tx(hash, from, 0);
+        tx.max_fee_per_gas = Some(U256::from(100));
+        tx.max_priority_fee_per_gas = Some(U256::from(tip));
+        tx
+    };
+    let victim = tipping(1, 1, 5);
+    // victim's own next tx keeps its nonce order, tying and lower tips land after it
+    let victim_next = tipping(2, 1, 9);
+    let pending = vec![
+        victim.clone(),
+        tipping(3, 3, 7),
+        victim_next,
+        tipping(4, 4, 5),
+        tipping(5, 5, 2),
+        tipping(6, 6, 20),
+    ];
+
+    let preceding: Vec<TxHash> = preceding_txs(&victim, &pending, base_fee)
+        .iter()
+        .map(|tx| tx.hash)
+        .collect();
+    assert_eq!(
+        preceding,
+        vec![TxHash::from_low_u64_be(6), TxHash::from_low_u64_be(3)]
+    );
+
+    // a tip above the tx's fee headroom only pays what is left over the base fee
+    let mut capped = tipping(7, 7, 50);
+    capped.max_fee_per_gas = Some(U256::from(14));
+    assert!(preceding_txs(&victim, &[capped], base_fee).is_empty());
+}
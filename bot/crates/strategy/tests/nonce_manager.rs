@@ -0,0 +1,139 @@
+use ethers::types::{Address, U256, U64};
+use strategy::managers::nonce_manager::NonceManager;
+
+fn nonce_manager(onchain_nonce: u64) -> NonceManager {
+    let nonce_manager = NonceManager::new(Address::zero());
+    nonce_manager.sync_onchain_nonce(U256::from(onchain_nonce));
+    nonce_manager
+}
+
+#[test]
+fn hands_out_consecutive_nonces_per_bundle() {
+    let nonce_manager = nonce_manager(7);
+
+    assert_eq!(
+        nonce_manager.reserve_bundle_nonces(),
+        (U256::from(7), U256::from(8))
+    );
+    assert_eq!(
+        nonce_manager.reserve_bundle_nonces(),
+        (U256::from(9), U256::from(10))
+    );
+    assert_eq!(nonce_manager.get_next_nonce(), U256::from(11));
+}
+
+#[test]
+fn resets_after_bundle_fails_to_land() {
+    let nonce_manager = nonce_manager(7);
+
+    let (frontrun_nonce, _) = nonce_manager.reserve_bundle_nonces();
+    nonce_manager.release_bundle_nonces(frontrun_nonce);
+
+    // next bundle reuses the nonces of the failed one
+    assert_eq!(
+        nonce_manager.reserve_bundle_nonces(),
+        (U256::from(7), U256::from(8))
+    );
+}
+
+#[test]
+fn resets_to_earliest_failed_bundle() {
+    let nonce_manager = nonce_manager(7);
+
+    let (first, _) = nonce_manager.reserve_bundle_nonces();
+    let (second, _) = nonce_manager.reserve_bundle_nonces();
+
+    // both bundles miss their block, order of releasing should not matter
+    nonce_manager.release_bundle_nonces(second);
+    nonce_manager.release_bundle_nonces(first);
+
+    assert_eq!(nonce_manager.get_next_nonce(), U256::from(7));
+}
+
+#[test]
+fn keeps_nonces_of_landed_bundle_when_later_bundle_fails() {
+    let nonce_manager = nonce_manager(7);
+
+    let _landed = nonce_manager.reserve_bundle_nonces();
+    let (failed, _) = nonce_manager.reserve_bundle_nonces();
+    nonce_manager.release_bundle_nonces(failed);
+
+    assert_eq!(
+        nonce_manager.reserve_bundle_nonces(),
+        (U256::from(9), U256::from(10))
+    );
+}
+
+#[test]
+fn catches_up_with_onchain_nonce() {
+    let nonce_manager = nonce_manager(7);
+    nonce_manager.reserve_bundle_nonces();
+
+    // searcher sent txs outside of the bot
+    nonce_manager.sync_onchain_nonce(U256::from(20));
+    assert_eq!(nonce_manager.get_next_nonce(), U256::from(20));
+
+    // a stale onchain nonce never moves us backwards
+    nonce_manager.sync_onchain_nonce(U256::from(5));
+    assert_eq!(nonce_manager.get_next_nonce(), U256::from(20));
+}
+
+#[test]
+fn two_victims_in_same_block_do_not_share_nonces() {
+    let mut nonce_manager = nonce_manager(7).with_max_bundles_per_block(2);
+    let target_block = U64::from(100);
+
+    let first = nonce_manager.reserve_nonces_for_block(target_block);
+    let second = nonce_manager.reserve_nonces_for_block(target_block);
+
+    assert_eq!(first, Some((U256::from(7), U256::from(8))));
+    assert_eq!(second, Some((U256::from(9), U256::from(10))));
+}
+
+#[test]
+fn caps_bundles_per_block() {
+    let mut nonce_manager = nonce_manager(7).with_max_bundles_per_block(1);
+
+    assert!(nonce_manager
+        .reserve_nonces_for_block(U64::from(100))
+        .is_some());
+    assert_eq!(nonce_manager.reserve_nonces_for_block(U64::from(100)), None);
+
+    // budget resets once we target the next block
+    assert_eq!(
+        nonce_manager.reserve_nonces_for_block(U64::from(101)),
+        Some((U256::from(9), U256::from(10)))
+    );
+}
+
+#[test]
+fn cancelled_bundle_frees_block_budget() {
+    let mut nonce_manager = nonce_manager(7).with_max_bundles_per_block(1);
+    let target_block = U64::from(100);
+
+    let (frontrun_nonce, _) = nonce_manager
+        .reserve_nonces_for_block(target_block)
+        .unwrap();
+    nonce_manager.cancel_bundle(frontrun_nonce);
+
+    assert_eq!(
+        nonce_manager.reserve_nonces_for_block(target_block),
+        Some((U256::from(7), U256::from(8)))
+    );
+}
+
+#[test]
+fn private_backrun_uses_one_nonce_from_block_budget() {
+    let mut nonce_manager = nonce_manager(7).with_max_bundles_per_block(2);
+    let target_block = U64::from(100);
+
+    assert_eq!(
+        nonce_manager.reserve_nonce_for_block(target_block),
+        Some(U256::from(7))
+    );
+    assert_eq!(
+        nonce_manager.reserve_nonces_for_block(target_block),
+        Some((U256::from(8), U256::from(9)))
+    );
+    assert_eq!(nonce_manager.reserve_nonce_for_block(target_block), None);
+}
@@ -0,0 +1,138 @@
+use ethers::types::{Address, TxHash, I256, U256, U64};
+use serde_json::Value;
+use strategy::managers::opportunity_log::{
+    OpportunityLog, OpportunityOutcome, OpportunityRecord, PoolStats,
+};
+
+fn record() -> OpportunityRecord {
+    OpportunityRecord::new(
+        U64::from(100),
+        TxHash::repeat_byte(0x11),
+        Address::repeat_byte(0xaa),
+        U256::from(1_000),
+        I256::from(50),
+    )
+}
+
+#[test]
+fn appends_one_json_line_per_record() {
+    let path = std::env::temp_dir().join(format!("opportunity-log-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let log = OpportunityLog::new(&path);
+
+    log.record_opportunity(
+        &record()
+            .with_simulation(I256::from(-5), 210_000)
+            .rejected("not profitable after simulated gas"),
+    )
+    .unwrap();
+    log.record_opportunity(
+        &record()
+            .with_simulation(I256::from(45), 200_000)
+            .with_bid(U256::from(20))
+            .with_outcome(OpportunityOutcome::Landed),
+    )
+    .unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 2);
+
+    assert_eq!(lines[0]["analytic_profit"], "50");
+    assert_eq!(lines[0]["simulated_profit"], "-5");
+    assert_eq!(lines[0]["bid"], Value::Null);
+    assert_eq!(lines[0]["outcome"]["status"], "rejected");
+    assert_eq!(
+        lines[0]["outcome"]["reason"],
+        "not profitable after simulated gas"
+    );
+
+    assert_eq!(lines[1]["gas"], 200_000);
+    assert_eq!(lines[1]["outcome"]["status"], "landed");
+}
+
+#[test]
+fn records_the_input_in_whole_tokens() {
+    let usdc = Address::repeat_byte(0x01);
+    let input_amount = |input: u64, decimals| {
+        OpportunityRecord::new(
+            U64::from(100),
+            TxHash::repeat_byte(0x11),
+            Address::repeat_byte(0xaa),
+            U256::from(input),
+            I256::from(50),
+        )
+        .with_input_token(usdc, decimals)
+        .input_amount
+    };
+
+    assert_eq!(input_amount(1_500_000, Some(6)).as_deref(), Some("1.5"));
+    assert_eq!(input_amount(42_000_000, Some(6)).as_deref(), Some("42"));
+    assert_eq!(input_amount(5, Some(6)).as_deref(), Some("0.000005"));
+    assert_eq!(input_amount(7, Some(0)).as_deref(), Some("7"));
+    // decimals that couldn't be read leave only the raw input
+    assert_eq!(input_amount(1_000, None), None);
+
+    let with_token = record().with_input_token(usdc, Some(18));
+    assert_eq!(with_token.input_token, Some(usdc));
+    assert_eq!(
+        with_token.input_amount.as_deref(),
+        Some("0.000000000000001")
+    );
+}
+
+#[test]
+fn pool_stats_rank_by_profit_per_opportunity() {
+    let mut consistent = PoolStats::default();
+    for _ in 0..2 {
+        consistent.record_opportunity();
+        consistent.record_landed(I256::from(30));
+    }
+
+    let mut lucky = PoolStats::default();
+    for _ in 0..10 {
+        lucky.record_opportunity();
+    }
+    lucky.record_landed(I256::from(100));
+
+    assert_eq!(consistent.landed, 2);
+    assert_eq!(consistent.cumulative_profit, I256::from(60));
+    assert_eq!(consistent.profit_per_opportunity(), I256::from(30));
+    assert_eq!(lucky.profit_per_opportunity(), I256::from(10));
+    // never sized ranks neutral
+    assert_eq!(PoolStats::default().profit_per_opportunity(), I256::zero());
+
+    // survives the round trip through the pool cache
+    let json = serde_json::to_string(&consistent).unwrap();
+    assert_eq!(
+        serde_json::from_str::<PoolStats>(&json).unwrap(),
+        consistent
+    );
+}
+
+#[test]
+fn pool_stats_track_the_share_of_diverging_simulations() {
+    let mut stats = PoolStats::default();
+    assert_eq!(stats.divergence_rate(), None);
+
+    stats.record_simulation(true);
+    for _ in 0..3 {
+        stats.record_simulation(false);
+    }
+    assert_eq!(stats.simulated, 4);
+    assert_eq!(stats.diverged, 1);
+    assert_eq!(stats.divergence_rate(), Some(0.25));
+
+    // caches written before divergence was tracked still load
+    let mut cached = serde_json::to_value(stats).unwrap();
+    let fields = cached.as_object_mut().unwrap();
+    fields.remove("simulated");
+    fields.remove("diverged");
+    let cached: PoolStats = serde_json::from_value(cached).unwrap();
+    assert_eq!(cached.divergence_rate(), None);
+}
@@ -0,0 +1,587 @@
+use cfmms::pool::UniswapV3Pool;
+use ethers::types::{Address, U256};
+use strategy::math::{
+    is_within_tolerance,
+    optimizer::{
+        optimize_v2_arb, optimize_v2_netted_sandwich, optimize_v2_sandwich,
+        optimize_v2_sandwich_from, optimize_v3_sandwich, position_budget, sandwich_revenue_ceiling,
+        v2_arb_revenue, v2_frontrun_bound, v2_netted_sandwich_revenue, v2_price_impact_budget,
+        v2_sandwich_revenue, v2_state_after_pending, v2_victims_net_along, v3_frontrun_bound,
+        v3_price_impact_budget, v3_round_trip_ticks, v3_virtual_reserves, OptimalSandwich,
+        PendingV2Swap, V2PoolState, V3PoolState, VictimSwap,
+    },
+    v2_sandwich::{get_amount_in, get_amount_out},
+    v3_sandwich::{get_sandwich_revenue, simulate_round_trip, TickMap},
+};
+
+// 1:2 pool with a 0.3% fee, victims below swap 1% of its input reserves
+fn v2_pool() -> V2PoolState {
+    V2PoolState::new(u(1_000_000_000), u(2_000_000_000), 30)
+}
+
+const VICTIM_IN: u64 = 10_000_000;
+// what the victim receives on the untouched pool
+const VICTIM_FAIR_OUT: u64 = 19_743_160;
+const INVENTORY: u64 = 200_000_000;
+
+fn u(value: u64) -> U256 {
+    U256::from(value)
+}
+
+struct Case {
+    name: &'static str,
+    victim: VictimSwap,
+    bound: Option<u64>,
+    optimal: Option<(u64, u64)>,
+}
+
+fn v2_cases() -> Vec<Case> {
+    vec![
+        Case {
+            name: "unchecked victim is only capped by inventory",
+            victim: VictimSwap::Unchecked {
+                amount_in: u(VICTIM_IN),
+            },
+            bound: Some(INVENTORY),
+            optimal: Some((199_999_768, 2_047_596)),
+        },
+        Case {
+            name: "exact input with 1% slippage",
+            victim: VictimSwap::ExactInput {
+                amount_in: u(VICTIM_IN),
+                amount_out_min: u(VICTIM_FAIR_OUT * 99 / 100),
+            },
+            bound: Some(5_070_475),
+            optimal: Some((5_070_375, 70_161)),
+        },
+        Case {
+            name: "exact input already past its floor",
+            victim: VictimSwap::ExactInput {
+                amount_in: u(VICTIM_IN),
+                amount_out_min: u(VICTIM_FAIR_OUT + 1),
+            },
+            bound: None,
+            optimal: None,
+        },
+        Case {
+            name: "exact output paying at most 1% over the fair price",
+            victim: VictimSwap::ExactOutput {
+                amount_out: u(VICTIM_FAIR_OUT),
+                amount_in_max: u(VICTIM_IN * 101 / 100),
+            },
+            bound: Some(4_970_209),
+            optimal: Some((4_970_143, 69_775)),
+        },
+        Case {
+            name: "first hop of a two hop swap with 1% slippage on the final output",
+            victim: VictimSwap::ExactInputTwoHop {
+                amount_in: u(VICTIM_IN),
+                amount_out_min: u(4_824_291),
+                next_hop: V2PoolState::new(u(2_000_000_000), u(500_000_000), 30),
+            },
+            bound: Some(5_120_369),
+            optimal: Some((5_120_301, 70_846)),
+        },
+    ]
+}
+
+#[test]
+fn v2_optimizer_matches_known_sandwiches() {
+    let pool = v2_pool();
+
+    for case in v2_cases() {
+        assert_eq!(
+            v2_frontrun_bound(&pool, &case.victim, u(INVENTORY)),
+            case.bound.map(u),
+            "bound: {}",
+            case.name
+        );
+        assert_eq!(
+            optimize_v2_sandwich(&pool, &case.victim, u(INVENTORY), U256::one()),
+            case.optimal.map(|(frontrun_in, revenue)| OptimalSandwich {
+                frontrun_in: u(frontrun_in),
+                revenue: u(revenue),
+            }),
+            "optimal: {}",
+            case.name
+        );
+    }
+}
+
+#[test]
+fn warm_started_search_converges_to_the_cold_optimum() {
+    let pool = v2_pool();
+
+    for case in v2_cases() {
+        let cold = match optimize_v2_sandwich(&pool, &case.victim, u(INVENTORY), U256::one()) {
+            Some(cold) if !cold.revenue.is_zero() => cold,
+            _ => continue,
+        };
+        assert_eq!(
+            optimize_v2_sandwich_from(&pool, &case.victim, u(INVENTORY), U256::one(), None),
+            Some(cold),
+            "no hint: {}",
+            case.name
+        );
+
+        // from the exact optimum, stale hints either side of it and one past the inventory
+        for hint in [
+            U256::one(),
+            cold.frontrun_in / 3,
+            cold.frontrun_in,
+            cold.frontrun_in * 3,
+            u(INVENTORY) * 10,
+        ] {
+            let warm = optimize_v2_sandwich_from(
+                &pool,
+                &case.victim,
+                u(INVENTORY),
+                U256::one(),
+                Some(hint),
+            )
+            .unwrap();
+
+            assert!(
+                warm.frontrun_in <= v2_frontrun_bound(&pool, &case.victim, u(INVENTORY)).unwrap(),
+                "bound: {} from {}",
+                case.name,
+                hint
+            );
+            assert!(
+                is_within_tolerance(cold.revenue, warm.revenue, 1),
+                "{} from {}: warm {:?} cold {:?}",
+                case.name,
+                hint,
+                warm,
+                cold
+            );
+        }
+    }
+}
+
+#[test]
+fn v2_revenue_reprices_a_given_frontrun() {
+    let pool = v2_pool();
+    let exact_input = VictimSwap::Unchecked {
+        amount_in: u(VICTIM_IN),
+    };
+    let exact_output = VictimSwap::ExactOutput {
+        amount_out: u(VICTIM_FAIR_OUT),
+        amount_in_max: U256::MAX,
+    };
+
+    for (victim, frontrun_in, revenue) in [
+        (exact_input, 0, 0),
+        (exact_input, 5_000_000, 69_195),
+        (exact_output, 5_000_000, 70_196),
+    ] {
+        assert_eq!(
+            v2_sandwich_revenue(&pool, &victim, u(frontrun_in)),
+            u(revenue)
+        );
+    }
+}
+
+#[test]
+fn capital_cap_clamps_the_greedy_optimum() {
+    let pool = v2_pool();
+    let victim = VictimSwap::Unchecked {
+        amount_in: u(VICTIM_IN),
+    };
+    let greedy = optimize_v2_sandwich(&pool, &victim, u(INVENTORY), U256::one()).unwrap();
+
+    // uncapped budget is the whole inventory, a cap above it changes nothing
+    assert_eq!(position_budget(u(INVENTORY), None), u(INVENTORY));
+    assert_eq!(
+        position_budget(u(INVENTORY), Some(u(INVENTORY * 2))),
+        u(INVENTORY)
+    );
+
+    // capital only funds a quarter of the greedy frontrun
+    let budget = position_budget(u(INVENTORY), Some(u(50_000_000)));
+    assert_eq!(budget, u(50_000_000));
+    assert!(greedy.frontrun_in > budget);
+
+    let capped = optimize_v2_sandwich(&pool, &victim, budget, U256::one()).unwrap();
+    assert!(capped.frontrun_in <= budget);
+    assert!(capped.revenue < greedy.revenue);
+    // revenue still grows up to the greedy optimum, so the best fundable frontrun spends
+    // (almost) all of it
+    assert!(budget - capped.frontrun_in < budget / 1_000);
+    assert_eq!(
+        capped.revenue,
+        v2_sandwich_revenue(&pool, &victim, capped.frontrun_in)
+    );
+}
+
+#[test]
+fn price_impact_cap_clamps_the_greedy_optimum() {
+    let pool = v2_pool();
+    let victim = VictimSwap::Unchecked {
+        amount_in: u(VICTIM_IN),
+    };
+    let greedy = optimize_v2_sandwich(&pool, &victim, u(INVENTORY), U256::one()).unwrap();
+
+    // uncapped (or a cap of 100%) leaves the inventory alone
+    assert_eq!(
+        v2_price_impact_budget(&pool, u(INVENTORY), None),
+        u(INVENTORY)
+    );
+    assert_eq!(
+        v2_price_impact_budget(&pool, u(INVENTORY), Some(10_000)),
+        u(INVENTORY)
+    );
+
+    // a 5% cap allows about 2.6% of the input reserves, well short of the greedy frontrun
+    let budget = v2_price_impact_budget(&pool, u(INVENTORY), Some(500));
+    assert!(greedy.frontrun_in > budget);
+    assert!(budget > u(25_900_000) && budget < u(26_000_000));
+
+    let capped = optimize_v2_sandwich(&pool, &victim, budget, U256::one()).unwrap();
+    assert!(capped.frontrun_in <= budget);
+    assert!(capped.revenue < greedy.revenue);
+    assert!(budget - capped.frontrun_in < budget / 1_000);
+
+    // the capped frontrun really stays within 5%: price after / price before >= 95%
+    let out = get_amount_out(capped.frontrun_in, pool.reserve_in, pool.reserve_out, 30);
+    let price_after = (pool.reserve_out - out) * pool.reserve_in;
+    let price_before = pool.reserve_out * (pool.reserve_in + capped.frontrun_in);
+    assert!(price_after * 10_000 >= price_before * 9_500);
+
+    // v3 caps the same way, its 1:1 pool is as deep as the v2 one around the current price
+    let v3_budget = v3_price_impact_budget(&v3_pool(), u(INVENTORY), Some(500));
+    assert!(v3_budget > u(25_000_000) && v3_budget < u(27_000_000));
+    assert_eq!(
+        v3_price_impact_budget(&v3_pool(), u(INVENTORY), None),
+        u(INVENTORY)
+    );
+}
+
+#[test]
+fn optimizers_need_inventory() {
+    let victim = VictimSwap::Unchecked {
+        amount_in: u(VICTIM_IN),
+    };
+
+    assert_eq!(v2_frontrun_bound(&v2_pool(), &victim, U256::zero()), None);
+    assert_eq!(v3_frontrun_bound(&v3_pool(), &victim, U256::zero()), None);
+}
+
+fn v3_pool() -> V3PoolState {
+    let weth: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap();
+    let token = Address::repeat_byte(0x11);
+
+    let pool = UniswapV3Pool {
+        token_a: weth.min(token),
+        token_b: weth.max(token),
+        fee: 3_000,
+        liquidity: 10u128.pow(9),
+        sqrt_price: U256::one() << 96,
+        tick: 0,
+        tick_spacing: 60,
+        ..Default::default()
+    };
+    let tick_map = TickMap {
+        lowest_tick: -887_220,
+        highest_tick: 887_220,
+        ..Default::default()
+    };
+
+    V3PoolState::new(pool, tick_map, weth < token)
+}
+
+#[test]
+fn v3_optimizer_stays_under_the_victims_floor() {
+    let pool = v3_pool();
+    let unchecked = VictimSwap::Unchecked {
+        amount_in: u(VICTIM_IN),
+    };
+    assert_eq!(
+        v3_frontrun_bound(&pool, &unchecked, u(INVENTORY)),
+        Some(u(INVENTORY))
+    );
+    let unbounded = optimize_v3_sandwich(&pool, &unchecked, u(INVENTORY), U256::one()).unwrap();
+
+    // victim accepts about 2% below the 1:1 price, their own price impact takes half of that
+    let bounded_victim = VictimSwap::ExactInput {
+        amount_in: u(VICTIM_IN),
+        amount_out_min: u(VICTIM_IN * 98 / 100 * 997 / 1000),
+    };
+    let bound = v3_frontrun_bound(&pool, &bounded_victim, u(INVENTORY)).unwrap();
+    let bounded = optimize_v3_sandwich(&pool, &bounded_victim, u(INVENTORY), U256::one()).unwrap();
+
+    assert!(bound < u(INVENTORY));
+    assert!(bounded.frontrun_in <= bound);
+    assert!(bounded.revenue <= unbounded.revenue);
+
+    // two hop victims aren't bounded on v3
+    let two_hop = VictimSwap::ExactInputTwoHop {
+        amount_in: u(VICTIM_IN),
+        amount_out_min: U256::MAX,
+        next_hop: v2_pool(),
+    };
+    assert_eq!(
+        optimize_v3_sandwich(&pool, &two_hop, u(INVENTORY), U256::one()),
+        Some(unbounded)
+    );
+}
+
+#[test]
+fn v3_backrun_walks_back_over_the_victims_ticks() {
+    // weth is token1 here, buying the token pushes the price (and tick) up
+    let mut pool = v3_pool();
+    assert!(!pool.weth_is_token0);
+    pool.tick_map.ticks.insert(60, 5 * 10i128.pow(8));
+    pool.tick_map.ticks.insert(120, -2 * 10i128.pow(8));
+
+    // frontrun crosses tick 60, the victim crosses tick 120 on top
+    let frontrun_in = u(7_000_000);
+    let victim = VictimSwap::Unchecked {
+        amount_in: u(2_000_000),
+    };
+    let round_trip = simulate_round_trip(
+        frontrun_in,
+        u(2_000_000),
+        &pool.pool,
+        &pool.tick_map,
+        pool.weth_is_token0,
+    )
+    .unwrap();
+    assert_eq!(round_trip.frontrun.ticks_crossed, 1);
+    assert_eq!(round_trip.victim.ticks_crossed, 1);
+    assert!(round_trip.victim.tick >= 120);
+
+    // selling back re-crosses both ticks, with the liquidity each range had on the way up
+    assert_eq!(round_trip.backrun.ticks_crossed, 2);
+    assert!(round_trip.backrun.tick < 60);
+    assert_eq!(round_trip.backrun.liquidity, pool.pool.liquidity);
+    assert_eq!(v3_round_trip_ticks(&pool, &victim, frontrun_in), (1, 2));
+
+    assert_eq!(
+        get_sandwich_revenue(
+            frontrun_in,
+            u(2_000_000),
+            &pool.pool,
+            &pool.tick_map,
+            pool.weth_is_token0
+        )
+        .unwrap(),
+        round_trip.backrun.amount_out.saturating_sub(frontrun_in)
+    );
+}
+
+#[test]
+fn revenue_ceiling_screen_has_no_false_negatives() {
+    let mut cases = 0;
+    let mut false_negatives = 0;
+
+    // victims of 0.05% to 5% of the pool's reserves leaving 0% to 10% slippage, the ceiling
+    // must never price a sandwich below what the optimizer finds
+    for (reserve_in, reserve_out) in [
+        (u(1_000_000_000), u(2_000_000_000)),
+        (U256::exp10(12), U256::exp10(9)),
+        (U256::exp10(18) * 5, U256::exp10(22)),
+    ] {
+        let pool = V2PoolState::new(reserve_in, reserve_out, 30);
+        for victim_bps in [5u64, 20, 100, 500] {
+            let amount_in = reserve_in * victim_bps / 10_000;
+            let fair_out = get_amount_out(amount_in, reserve_in, reserve_out, 30);
+            let amount_out = reserve_out * victim_bps / 10_000;
+            let fair_in = get_amount_in(amount_out, reserve_in, reserve_out, 30).unwrap();
+
+            for slippage_bps in [0u64, 10, 50, 100, 300, 1_000] {
+                let victims = [
+                    VictimSwap::ExactInput {
+                        amount_in,
+                        amount_out_min: fair_out * (10_000 - slippage_bps) / 10_000,
+                    },
+                    VictimSwap::ExactOutput {
+                        amount_out,
+                        amount_in_max: fair_in * (10_000 + slippage_bps) / 10_000,
+                    },
+                    VictimSwap::Unchecked { amount_in },
+                ];
+                for victim in victims {
+                    cases += 1;
+                    let ceiling = sandwich_revenue_ceiling(&victim, reserve_in, reserve_out);
+                    let revenue =
+                        optimize_v2_sandwich(&pool, &victim, reserve_in * 10, U256::one())
+                            .map(|optimal| optimal.revenue)
+                            .unwrap_or_default();
+
+                    if revenue > ceiling {
+                        false_negatives += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    assert_eq!(cases, 216);
+    assert_eq!(false_negatives, 0, "{} of {} cases", false_negatives, cases);
+}
+
+#[test]
+fn revenue_ceiling_of_a_tight_victim_is_its_price_impact() {
+    let pool = v2_pool();
+    let victim = VictimSwap::ExactInput {
+        amount_in: u(VICTIM_IN),
+        amount_out_min: u(VICTIM_FAIR_OUT),
+    };
+
+    // 10m in for 19.74m out at a 1:2 spot price leaves 128_420 of fee and impact to take
+    let ceiling = sandwich_revenue_ceiling(&victim, pool.reserve_in, pool.reserve_out);
+    assert_eq!(ceiling, u(128_420));
+    let optimal = optimize_v2_sandwich(&pool, &victim, u(INVENTORY), U256::one());
+    assert!(optimal.map_or(true, |optimal| optimal.revenue <= ceiling));
+}
+
+#[test]
+fn v3_virtual_reserves_follow_the_spot_price() {
+    let pool = v3_pool().pool;
+
+    // 1:1 price, both sides hold the pool's liquidity
+    assert_eq!(
+        v3_virtual_reserves(&pool, true),
+        (u(1_000_000_000), u(1_000_000_000))
+    );
+
+    // token1 at four times token0 (sqrt price doubled)
+    let pool = UniswapV3Pool {
+        sqrt_price: U256::one() << 97,
+        ..pool
+    };
+    assert_eq!(
+        v3_virtual_reserves(&pool, true),
+        (u(500_000_000), u(2_000_000_000))
+    );
+    assert_eq!(
+        v3_virtual_reserves(&pool, false),
+        (u(2_000_000_000), u(500_000_000))
+    );
+}
+
+#[test]
+fn v2_arb_buys_where_the_token_is_cheap() {
+    let buy = v2_pool();
+    // same pool with its token 10% dearer, reserves oriented token first for the sell
+    let sell = V2PoolState::new(u(1_800_000_000), u(1_000_000_000), 30);
+
+    let optimal = optimize_v2_arb(&buy, &sell, u(INVENTORY), U256::one()).unwrap();
+    assert!(!optimal.revenue.is_zero());
+    assert_eq!(
+        optimal.revenue,
+        v2_arb_revenue(&buy, &sell, optimal.frontrun_in)
+    );
+    for input in [optimal.frontrun_in / 2, optimal.frontrun_in * 3 / 2] {
+        assert!(v2_arb_revenue(&buy, &sell, input) <= optimal.revenue);
+    }
+
+    // pools at the same price only lose their fees
+    let aligned = V2PoolState::new(u(2_000_000_000), u(1_000_000_000), 30);
+    assert_eq!(
+        optimize_v2_arb(&buy, &aligned, u(INVENTORY), U256::one()),
+        None
+    );
+    assert_eq!(
+        optimize_v2_arb(&buy, &sell, U256::zero(), U256::one()),
+        None
+    );
+}
+
+#[test]
+fn victims_after_pending_swaps_are_sized_on_the_moved_reserves() {
+    let pool = v2_pool();
+    // 2% slippage on what the victim gets from the untouched pool
+    let victim = VictimSwap::ExactInput {
+        amount_in: u(VICTIM_IN),
+        amount_out_min: u(VICTIM_FAIR_OUT * 98 / 100),
+    };
+    assert_eq!(v2_state_after_pending(&pool, &[]), pool);
+
+    // a pending buy ahead of the victim eats into its slippage, leaving us less room
+    let pending = [PendingV2Swap::SameDirection(u(VICTIM_IN / 2))];
+    let moved = v2_state_after_pending(&pool, &pending);
+    assert_eq!(moved.reserve_in, pool.reserve_in + u(VICTIM_IN / 2));
+    assert!(moved.reserve_out < pool.reserve_out);
+
+    let top_of_block = optimize_v2_sandwich(&pool, &victim, u(INVENTORY), U256::one()).unwrap();
+    let after_pending = optimize_v2_sandwich(&moved, &victim, u(INVENTORY), U256::one()).unwrap();
+    assert!(after_pending.frontrun_in < top_of_block.frontrun_in);
+    assert!(after_pending.revenue < top_of_block.revenue);
+
+    // a pending sell the other way gives some of it back
+    let reverted = v2_state_after_pending(
+        &pool,
+        &[
+            pending[0],
+            PendingV2Swap::OppositeDirection(u(VICTIM_FAIR_OUT / 2)),
+        ],
+    );
+    let optimal = optimize_v2_sandwich(&reverted, &victim, u(INVENTORY), U256::one()).unwrap();
+    assert!(optimal.frontrun_in > after_pending.frontrun_in);
+}
+
+#[test]
+fn opposing_victims_are_netted_into_one_sandwich() {
+    let pool = v2_pool();
+    let reversed = V2PoolState::new(pool.reserve_out, pool.reserve_in, pool.fee_bps);
+    let optimize = |pool: &V2PoolState, victims: &[(PendingV2Swap, U256)]| {
+        optimize_v2_netted_sandwich(pool, victims, u(INVENTORY), U256::one())
+    };
+    let flip = |victims: &[(PendingV2Swap, U256)]| -> Vec<(PendingV2Swap, U256)> {
+        victims
+            .iter()
+            .map(|(swap, amount_out_min)| (swap.reversed(), *amount_out_min))
+            .collect()
+    };
+
+    // a buyer and a seller half its size, both taking 2% slippage on the untouched pool
+    let buy = (
+        PendingV2Swap::SameDirection(u(VICTIM_IN)),
+        u(VICTIM_FAIR_OUT * 98 / 100),
+    );
+    let sell_in = u(VICTIM_FAIR_OUT / 2);
+    let sell = (
+        PendingV2Swap::OppositeDirection(sell_in),
+        get_amount_out(sell_in, pool.reserve_out, pool.reserve_in, pool.fee_bps) * 98 / 100,
+    );
+    assert!(v2_victims_net_along(&pool, &[sell.0, buy.0]));
+    assert!(!v2_victims_net_along(
+        &reversed,
+        &[sell.0.reversed(), buy.0.reversed()]
+    ));
+
+    // the buyer's sandwich sized on its own makes far less once the seller lands inside it
+    let victim = VictimSwap::ExactInput {
+        amount_in: u(VICTIM_IN),
+        amount_out_min: buy.1,
+    };
+    let solo = optimize_v2_sandwich(&pool, &victim, u(INVENTORY), U256::one()).unwrap();
+    let undercut = v2_netted_sandwich_revenue(&pool, &[sell, buy], solo.frontrun_in);
+    assert!(undercut < solo.revenue / 2);
+
+    // netting resizes the frontrun for the smaller price move, and nothing is left frontrunning
+    // the seller's way
+    let netted = optimize(&pool, &[sell, buy]).unwrap();
+    assert!(netted.revenue > undercut);
+    assert!(netted.revenue < solo.revenue);
+    assert_eq!(
+        netted.revenue,
+        v2_netted_sandwich_revenue(&pool, &[sell, buy], netted.frontrun_in)
+    );
+    assert_eq!(optimize(&reversed, &flip(&[buy, sell])), None);
+
+    // a seller outweighing the buyer flips the net move, the frontrun sells the token instead
+    let big_sell_in = u(VICTIM_FAIR_OUT * 2);
+    let big_sell = (
+        PendingV2Swap::OppositeDirection(big_sell_in),
+        get_amount_out(big_sell_in, pool.reserve_out, pool.reserve_in, pool.fee_bps) * 98 / 100,
+    );
+    assert!(!v2_victims_net_along(&pool, &[big_sell.0, buy.0]));
+    assert_eq!(optimize(&pool, &[big_sell, buy]), None);
+    let flipped = optimize(&reversed, &flip(&[buy, big_sell])).unwrap();
+    assert!(!flipped.revenue.is_zero());
+}
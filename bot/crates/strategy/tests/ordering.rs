@@ -0,0 +1,164 @@
+use ethers::{types::U256, utils::parse_ether};
+use strategy::{
+    math::{
+        ordering::{
+            best_ordering, get_frontrun_only_revenue, get_v2_ordering_revenue, OrderingStrategy,
+        },
+        profit::SandwichProfit,
+        v2_sandwich::{get_backrun_revenue, get_sandwich_revenue},
+    },
+    types::PaymentStrategy,
+};
+
+// uniswap v2 swap fee
+const FEE_BPS: u32 = 30;
+
+const GAS: u64 = 100_000;
+
+fn gwei(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(9)
+}
+
+fn reserves() -> (U256, U256) {
+    (parse_ether(1000).unwrap(), parse_ether(1_000_000).unwrap())
+}
+
+/// Net profit of an ordering around a 10 weth victim, frontrunning it with 5 weth
+fn profit_at(ordering: OrderingStrategy, base_fee: U256, haircut_bps: u64) -> SandwichProfit {
+    let (reserve_weth, reserve_token) = reserves();
+    let revenue = get_v2_ordering_revenue(
+        ordering,
+        parse_ether(5).unwrap(),
+        parse_ether(10).unwrap(),
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        haircut_bps,
+    );
+    ordering.profit(
+        revenue,
+        U256::from(GAS),
+        U256::from(GAS),
+        base_fee,
+        U256::zero(),
+        PaymentStrategy::PriorityFee,
+    )
+}
+
+#[test]
+fn sandwich_ordering_pays_for_both_legs() {
+    let (reserve_weth, reserve_token) = reserves();
+    let (frontrun_in, victim_in) = (parse_ether(5).unwrap(), parse_ether(10).unwrap());
+    let base_fee = gwei(10);
+
+    let profit = profit_at(OrderingStrategy::Sandwich, base_fee, 0);
+
+    let revenue =
+        get_sandwich_revenue(frontrun_in, victim_in, reserve_weth, reserve_token, FEE_BPS);
+    assert_eq!(profit.gross_revenue, revenue);
+    assert_eq!(profit.gas_cost, U256::from(2 * GAS) * base_fee);
+}
+
+#[test]
+fn frontrun_only_ordering_marks_kept_tokens_and_skips_backrun_gas() {
+    let (reserve_weth, reserve_token) = reserves();
+    let (frontrun_in, victim_in) = (parse_ether(5).unwrap(), parse_ether(10).unwrap());
+    let base_fee = gwei(10);
+
+    // without a haircut the kept tokens are worth what the backrun would have sold them for
+    let unmarked = profit_at(OrderingStrategy::FrontrunOnly, base_fee, 0);
+    let sandwich_revenue =
+        get_sandwich_revenue(frontrun_in, victim_in, reserve_weth, reserve_token, FEE_BPS);
+    assert_eq!(unmarked.gross_revenue, sandwich_revenue);
+    assert_eq!(unmarked.gas_cost, U256::from(GAS) * base_fee);
+
+    // a haircut comes out of the marked value, not the revenue
+    let marked = profit_at(OrderingStrategy::FrontrunOnly, base_fee, 10);
+    let marked_value = sandwich_revenue + frontrun_in;
+    assert_eq!(
+        marked.gross_revenue,
+        marked_value * 9_990 / 10_000 - frontrun_in
+    );
+
+    // a haircut bigger than the gain leaves nothing
+    let revenue = get_frontrun_only_revenue(
+        frontrun_in,
+        victim_in,
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        1_000,
+    );
+    assert!(revenue.is_zero());
+}
+
+#[test]
+fn backrun_only_ordering_sells_inventory_without_frontrun_gas() {
+    let (reserve_weth, reserve_token) = reserves();
+    let (inventory, victim_in) = (parse_ether(5_000).unwrap(), parse_ether(10).unwrap());
+    let base_fee = gwei(10);
+
+    let revenue = get_v2_ordering_revenue(
+        OrderingStrategy::BackrunOnly,
+        inventory,
+        victim_in,
+        reserve_weth,
+        reserve_token,
+        FEE_BPS,
+        0,
+    );
+    assert_eq!(
+        revenue,
+        get_backrun_revenue(inventory, victim_in, reserve_weth, reserve_token, FEE_BPS)
+    );
+
+    let profit = OrderingStrategy::BackrunOnly.profit(
+        revenue,
+        U256::from(GAS),
+        U256::from(GAS),
+        base_fee,
+        U256::zero(),
+        PaymentStrategy::PriorityFee,
+    );
+    assert_eq!(profit.gas_cost, U256::from(GAS) * base_fee);
+}
+
+#[test]
+fn frontrun_only_wins_once_backrun_gas_outweighs_the_haircut() {
+    let orderings = [OrderingStrategy::Sandwich, OrderingStrategy::FrontrunOnly];
+    let candidates_at = |base_fee| {
+        orderings
+            .iter()
+            .map(|ordering| (*ordering, profit_at(*ordering, base_fee, 10)))
+            .collect::<Vec<_>>()
+    };
+
+    // cheap gas, selling the tokens back is worth the backrun
+    let (ordering, _) = best_ordering(&candidates_at(gwei(10)), U256::zero()).unwrap();
+    assert_eq!(ordering, OrderingStrategy::Sandwich);
+
+    // at 100 gwei the backrun costs more than the tokens' haircut
+    let (ordering, profit) = best_ordering(&candidates_at(gwei(100)), U256::zero()).unwrap();
+    assert_eq!(ordering, OrderingStrategy::FrontrunOnly);
+    assert_eq!(
+        profit.net_profit,
+        profit_at(OrderingStrategy::FrontrunOnly, gwei(100), 10).net_profit
+    );
+
+    // nothing beats a threshold above every candidate
+    assert!(best_ordering(&candidates_at(gwei(10)), parse_ether(1).unwrap()).is_none());
+}
+
+#[test]
+fn orderings_parse_from_config_names() {
+    for ordering in OrderingStrategy::ALL {
+        assert_eq!(
+            ordering.label().parse::<OrderingStrategy>().unwrap(),
+            ordering
+        );
+    }
+    assert!("victim_first".parse::<OrderingStrategy>().is_err());
+    assert!(OrderingStrategy::Sandwich.has_frontrun() && OrderingStrategy::Sandwich.has_backrun());
+    assert!(!OrderingStrategy::FrontrunOnly.has_backrun());
+    assert!(!OrderingStrategy::BackrunOnly.has_frontrun());
+}
@@ -0,0 +1,563 @@
+//! Drives the bot from a pending victim to its action against the fixture rpc in `common`
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use artemis_core::types::Strategy;
+use cfmms::pool::{Pool, UniswapV2Pool};
+use ethers::{
+    abi::{encode, Token},
+    providers::Middleware,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, BlockTrace, Bytes,
+        Transaction, H256, I256, U256, U64,
+    },
+    utils::rlp::Rlp,
+};
+use ethers_flashbots::BundleTransaction;
+use strategy::{
+    bot::SandoBot,
+    managers::{
+        block_manager::with_base_fee_margin,
+        opportunity_log::{OpportunityOutcome, OpportunityRecord},
+    },
+    math::ordering::OrderingStrategy,
+    types::{
+        Action, BlockInfo, Event, PendingStage, PoolState, SandoError, SandoGasProfile,
+        StratConfig, VictimInfo,
+    },
+};
+
+use common::{FixtureClient, FixtureResponse, Scenario};
+
+fn config() -> StratConfig {
+    StratConfig::new(
+        "0x5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a"
+            .parse()
+            .unwrap(),
+        U64::from(18_000_000),
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap(),
+    )
+}
+
+/// Evaluate the scenario's victim against its pools, pinned to the fixture's latest block
+///
+/// Returns the bot's action, the opportunities it sized and the rpc methods it called
+async fn run(
+    scenario: &Scenario,
+    config: StratConfig,
+) -> (Option<Action>, Vec<OpportunityRecord>, Vec<String>) {
+    let client = scenario.client();
+    let records = Arc::new(Mutex::new(vec![]));
+    let bot_records = records.clone();
+    let mut bot = SandoBot::new(client.provider(), config)
+        .with_pools(scenario.pools())
+        .on_opportunity(move |record| {
+            bot_records.lock().unwrap().push(record.clone());
+        });
+    bot.sync_state().await.unwrap();
+
+    let block: BlockInfo = client
+        .provider()
+        .get_block(BlockNumber::Latest)
+        .await
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    bot.pin_to_block(block);
+
+    let action = bot
+        .process_event(Event::NewTransaction(scenario.victim.clone()))
+        .await;
+    let records = records.lock().unwrap().clone();
+    (action, records, client.served())
+}
+
+#[tokio::test]
+async fn backruns_unsandwichable_v2_victim_with_an_arb() {
+    let scenario = Scenario::load("v2_backrun_arb");
+    let mut config = config();
+    config.enable_backrun_arb = true;
+
+    let (action, records, _) = run(&scenario, config).await;
+
+    match action {
+        Some(Action::SubmitPrivateTx {
+            txs,
+            target_block,
+            net_profit,
+        }) => {
+            assert_eq!(target_block, U64::from(18_000_001));
+            assert!(net_profit > I256::zero(), "{}", scenario.description);
+
+            // only our buy and sell, the victim isn't sent along
+            assert_eq!(txs.len(), 2);
+            assert!(txs.iter().all(|tx| *tx != scenario.victim.rlp()));
+        }
+        other => panic!(
+            "{}: expected arb private txs, got {:?}",
+            scenario.description, other
+        ),
+    }
+
+    let pool_a: Address = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+        .parse()
+        .unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].pool, pool_a);
+}
+
+#[tokio::test]
+async fn drops_the_arb_once_its_victim_is_mined() {
+    let mut scenario = Scenario::load("v2_backrun_arb");
+    let mut config = config();
+    config.enable_backrun_arb = true;
+
+    // mined into the block the pinned state is from, before the block the arb targets
+    let mut mined = scenario.victim.clone();
+    mined.block_number = Some(U64::from(18_000_000));
+    scenario.responses.insert(
+        0,
+        FixtureResponse {
+            method: "eth_getTransactionByHash".to_string(),
+            params_contain: vec![],
+            result: serde_json::to_value(&mined).unwrap(),
+        },
+    );
+
+    let (action, records, served) = run(&scenario, config).await;
+
+    assert!(action.is_none(), "{}", scenario.description);
+    assert!(served
+        .iter()
+        .any(|method| method == "eth_getTransactionByHash"));
+    // sized, then dropped
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        records[1].outcome,
+        OpportunityOutcome::Rejected("victim tx was already mined".to_string())
+    );
+}
+
+/// The scenario's buy from another sender without a slippage floor, which leaves room to
+/// frontrun it (still pending when looked up again)
+fn loose_victim(scenario: &mut Scenario) -> Transaction {
+    let mut loose = scenario.victim.clone();
+    loose.hash = H256::repeat_byte(0xcd);
+    loose.from = Address::repeat_byte(0x88);
+    let mut input = loose.input.to_vec();
+    input[4..36].fill(0);
+    loose.input = Bytes::from(input);
+    scenario.responses.insert(
+        0,
+        FixtureResponse {
+            method: "eth_getTransactionByHash".to_string(),
+            params_contain: vec![format!("{:?}", loose.hash)],
+            result: serde_json::to_value(&loose).unwrap(),
+        },
+    );
+    loose
+}
+
+#[tokio::test]
+async fn holds_the_arb_back_while_bundles_are_collected_for_the_event() {
+    let mut scenario = Scenario::load("v2_backrun_arb");
+    let mut config = config();
+    config.enable_backrun_arb = true;
+    config.ordering_strategies = vec![OrderingStrategy::FrontrunOnly];
+    let loose = loose_victim(&mut scenario);
+
+    let client = scenario.client();
+    let mut bot = SandoBot::new(client.provider(), config).with_pools(scenario.pools());
+    bot.sync_state().await.unwrap();
+    let block: BlockInfo = client
+        .provider()
+        .get_block(BlockNumber::Latest)
+        .await
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    bot.pin_to_block(block);
+
+    let action = bot
+        .process_event(Event::NewTransactions(vec![loose, scenario.victim.clone()]))
+        .await;
+
+    // the frontrun only bundle goes out, the arb victim waits for the next event
+    match action {
+        Some(Action::SubmitBundle { bundles, .. }) => assert_eq!(bundles.len(), 1),
+        other => panic!(
+            "{}: expected the frontrun only bundle, got {:?}",
+            scenario.description, other
+        ),
+    }
+    assert_eq!(bot.requeued_victims(), vec![scenario.victim.hash]);
+}
+
+#[tokio::test]
+async fn prices_frontrun_only_bundles_for_a_base_fee_spike_within_the_margin() {
+    let mut scenario = Scenario::load("v2_backrun_arb");
+    scenario.victim = loose_victim(&mut scenario);
+
+    // base fee part of the frontrun's max fee
+    let frontrun_base_fee = |margin_bps: u64| {
+        let scenario = &scenario;
+        async move {
+            let mut config = config();
+            config.ordering_strategies = vec![OrderingStrategy::FrontrunOnly];
+            config.base_fee_safety_margin_bps = margin_bps;
+            let frontrun = match run(scenario, config).await.0 {
+                Some(Action::SubmitBundle { bundles, .. }) => {
+                    match bundles[0].transactions()[0].clone() {
+                        BundleTransaction::Raw(raw) => raw,
+                        other => panic!("Frontrun should be sent raw, got {:?}", other),
+                    }
+                }
+                other => panic!(
+                    "{}: expected a frontrun only bundle, got {:?}",
+                    scenario.description, other
+                ),
+            };
+            let (frontrun, _) = TypedTransaction::decode_signed(&Rlp::new(&frontrun)).unwrap();
+            match frontrun {
+                TypedTransaction::Eip1559(tx) => {
+                    tx.max_fee_per_gas.unwrap() - tx.max_priority_fee_per_gas.unwrap()
+                }
+                other => panic!("Frontrun should be eip1559, got {:?}", other),
+            }
+        }
+    };
+
+    // base fee comes in 4% above the next block's prediction: priced at the bare prediction the
+    // frontrun can't pay it, with a 5% margin it still can
+    let base_fee = frontrun_base_fee(0).await;
+    let spiked = base_fee * 104 / 100;
+    let margined = frontrun_base_fee(500).await;
+    assert_eq!(margined, with_base_fee_margin(base_fee, 500));
+    assert!(base_fee < spiked);
+    assert!(margined >= spiked);
+}
+
+#[tokio::test]
+async fn leaves_unsandwichable_v2_victim_without_backrun_arb() {
+    let scenario = Scenario::load("v2_backrun_arb");
+
+    let (action, _, _) = run(&scenario, config()).await;
+
+    assert!(action.is_none(), "{}", scenario.description);
+}
+
+#[tokio::test]
+async fn skips_v3_victim_with_small_price_move() {
+    let scenario = Scenario::load("v3_small_price_move");
+
+    let (action, records, served) = run(&scenario, config()).await;
+
+    assert!(action.is_none(), "{}", scenario.description);
+    assert!(records.is_empty());
+    // rejected after tracing the victim, not before
+    assert!(served.iter().any(|method| method == "trace_call"));
+}
+
+#[tokio::test]
+async fn sizes_v3_victims_with_the_v3_optimized_sando_contract() {
+    let mut scenario = Scenario::load("v3_small_price_move");
+    let default_sando: Address = "0x5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a"
+        .parse()
+        .unwrap();
+    // cheaper v3 swaps, dearer v2 ones
+    let v3_sando = Address::repeat_byte(0x6b);
+    let mut config = config();
+    config.sando_addresses.push(v3_sando);
+    config.sando_gas_profiles.insert(
+        v3_sando,
+        SandoGasProfile {
+            v2_frontrun: 130_000,
+            v2_backrun: 130_000,
+            v3_frontrun: 95_000,
+            v3_backrun: 90_000,
+        },
+    );
+
+    // pool syncs with a thousandth of the fixture's liquidity (and no initialized ticks around
+    // its price), so the victim's 10 weth leave room for a sandwich
+    let mut pool = match scenario.pools()[0] {
+        Pool::UniswapV3(pool) => pool,
+        _ => unreachable!(),
+    };
+    pool.liquidity = 10u128.pow(21);
+    let pool_call = |selector: &str, result: Vec<Token>| FixtureResponse {
+        method: "eth_call".to_string(),
+        params_contain: vec![format!("{:?}", pool.address), selector.to_string()],
+        result: serde_json::to_value(Bytes::from(encode(&result))).unwrap(),
+    };
+    let slot_0 = vec![
+        Token::Uint(pool.sqrt_price),
+        Token::Int(U256::zero()),
+        Token::Uint(U256::zero()),
+        Token::Uint(U256::one()),
+        Token::Uint(U256::one()),
+        Token::Uint(U256::zero()),
+        Token::Bool(true),
+    ];
+    let pool_calls = [
+        pool_call("0x3850c7bd", slot_0),
+        pool_call("0x1a686502", vec![Token::Uint(pool.liquidity.into())]),
+        pool_call("0x5339c296", vec![Token::Uint(U256::zero())]),
+    ];
+    for response in pool_calls {
+        scenario.responses.insert(0, response);
+    }
+
+    let client = scenario.client();
+    let mut bot = SandoBot::new(client.provider(), config).with_pools(scenario.pools());
+    bot.sync_state().await.unwrap();
+    let block: BlockInfo = client
+        .provider()
+        .get_block(BlockNumber::Latest)
+        .await
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    bot.pin_to_block(block);
+
+    let trace = scenario
+        .responses
+        .iter()
+        .find(|response| response.method == "trace_call")
+        .unwrap();
+    let trace: BlockTrace = serde_json::from_value(trace.result.clone()).unwrap();
+    let victim = VictimInfo::with_state_diffs(scenario.victim.clone(), trace.state_diff.unwrap().0);
+    let sim_block = BlockNumber::Number(U64::from(18_000_000));
+
+    let (sando, profit) = bot
+        .cheapest_sando(Pool::UniswapV3(pool), &victim, sim_block, default_sando)
+        .await
+        .expect(&scenario.description);
+    assert_eq!(sando, v3_sando);
+
+    // same sandwich, only the gas it pays differs
+    let (_, default_profit) = bot
+        .find_optimal_v3_input(&pool, &victim, sim_block, default_sando)
+        .await
+        .unwrap();
+    assert_eq!(profit.gross_revenue, default_profit.gross_revenue);
+    assert!(profit.net_profit > default_profit.net_profit);
+}
+
+#[tokio::test]
+async fn exposes_pending_bundles_for_the_next_block() {
+    let scenario = Scenario::load("v2_backrun_arb");
+    let mut config = config();
+    config.enable_backrun_arb = true;
+
+    let client = scenario.client();
+    let mut bot = SandoBot::new(client.provider(), config).with_pools(scenario.pools());
+    assert_eq!(bot.synced_pool_count(), scenario.pools.len());
+    bot.sync_state().await.unwrap();
+
+    let block: BlockInfo = client
+        .provider()
+        .get_block(BlockNumber::Latest)
+        .await
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    bot.pin_to_block(block);
+    assert_eq!(bot.current_next_block().number, U64::from(18_000_001));
+    assert!(bot.pending_opportunities().is_empty());
+
+    bot.process_event(Event::NewTransaction(scenario.victim.clone()))
+        .await
+        .unwrap();
+
+    let pending = bot.pending_opportunities();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].stage, PendingStage::Submitted);
+    assert_eq!(pending[0].target_block, U64::from(18_000_001));
+    assert_eq!(pending[0].record.victim_hash, scenario.victim.hash);
+}
+
+#[tokio::test]
+async fn snapshots_the_monitored_pool_set() {
+    let scenario = Scenario::load("v2_backrun_arb");
+    let client = scenario.client();
+    let bot = SandoBot::new(client.provider(), config()).with_pools(scenario.pools());
+
+    let snapshot = bot.pool_metrics();
+    assert_eq!(snapshot.total_pools(), scenario.pools.len());
+    // handed in rather than discovered from a factory
+    assert_eq!(snapshot.unattributed_pools, scenario.pools.len());
+    assert!(snapshot.pools_by_dex.iter().all(|(_, count)| *count == 0));
+    // hot and cold aren't classified before any block's logs were seen
+    assert!(snapshot.pools_by_activity.is_none());
+    assert!(snapshot.estimated_bytes > 0);
+}
+
+/// v2 pool holding `reserve_x` of `token_x` and `reserve_y` of `token_y`
+fn v2_pool(
+    address: Address,
+    (token_x, reserve_x): (Address, u128),
+    (token_y, reserve_y): (Address, u128),
+) -> Pool {
+    // token_a is the pool's token0
+    let ((token_a, reserve_0), (token_b, reserve_1)) = if token_x < token_y {
+        ((token_x, reserve_x), (token_y, reserve_y))
+    } else {
+        ((token_y, reserve_y), (token_x, reserve_x))
+    };
+    Pool::UniswapV2(UniswapV2Pool {
+        address,
+        token_a,
+        token_b,
+        reserve_0,
+        reserve_1,
+        fee: 300,
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn routes_weth_prices_through_cached_pool_prices() {
+    let config = config();
+    let weth = config.weth_address;
+    let (token, usdc) = (Address::repeat_byte(0x01), Address::repeat_byte(0x02));
+    let (token_pool, usdc_pool) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+
+    let client = FixtureClient::new(vec![]);
+    let bot = SandoBot::new(client.provider(), config).with_pools(vec![
+        v2_pool(token_pool, (token, 1_000), (usdc, 2_000)),
+        v2_pool(usdc_pool, (usdc, 4_000), (weth, 1_000)),
+    ]);
+
+    assert_eq!(bot.pool_price(token_pool), Some(2.0));
+    assert_eq!(bot.weth_price(usdc), Some(0.25));
+    // no weth pool trades the token, it's priced through usdc
+    assert_eq!(bot.weth_price(token), Some(0.5));
+    assert_eq!(bot.pool_price(Address::repeat_byte(0xcc)), None);
+
+    // the token pool's reserves moved, so do the prices derived from it
+    let bot = bot.with_pools(vec![v2_pool(token_pool, (token, 1_000), (usdc, 4_000))]);
+    assert_eq!(bot.pool_price(token_pool), Some(4.0));
+    assert_eq!(bot.weth_price(token), Some(1.0));
+}
+
+#[tokio::test]
+async fn caches_token_decimals_of_monitored_pools() {
+    let config = config();
+    let weth = config.weth_address;
+    let (usdc, unknown) = (Address::repeat_byte(0x02), Address::repeat_byte(0x03));
+
+    let mut usdc_pool = v2_pool(Address::repeat_byte(0xbb), (usdc, 4_000), (weth, 1_000));
+    if let Pool::UniswapV2(pool) = &mut usdc_pool {
+        // synced with decimals, like pools cfmms loads
+        (pool.token_a_decimals, pool.token_b_decimals) = if pool.token_a == usdc {
+            (6, 18)
+        } else {
+            (18, 6)
+        };
+    }
+    let client = FixtureClient::new(vec![]);
+    let bot = SandoBot::new(client.provider(), config).with_pools(vec![
+        usdc_pool,
+        v2_pool(Address::repeat_byte(0xcc), (unknown, 1_000), (weth, 1_000)),
+    ]);
+
+    assert_eq!(bot.token_decimals(usdc), Some(6));
+    assert_eq!(bot.token_decimals(weth), Some(18));
+    assert_eq!(bot.token_decimals(unknown), None);
+    assert_eq!(bot.display_amount(usdc, U256::from(2_500_000)), "2.5");
+    assert_eq!(
+        bot.display_amount(unknown, U256::from(2_500_000)),
+        "2500000 (raw)"
+    );
+}
+
+#[tokio::test]
+async fn refreshes_drifted_pool_state_with_a_multicall() {
+    let config = config();
+    let weth = config.weth_address;
+    let token = Address::repeat_byte(0x01);
+    let (drifted, current, unreadable) = (
+        Address::repeat_byte(0xaa),
+        Address::repeat_byte(0xbb),
+        Address::repeat_byte(0xcc),
+    );
+
+    // `aggregate3` results: the first pool's reserves moved without us seeing the log, the
+    // second's match the cache and the third's call reverted
+    let reserves = |reserve_0: u64, reserve_1: u64| {
+        Token::Tuple(vec![
+            Token::Bool(true),
+            Token::Bytes(encode(&[
+                Token::Uint(reserve_0.into()),
+                Token::Uint(reserve_1.into()),
+                Token::Uint(U256::zero()),
+            ])),
+        ])
+    };
+    let results = encode(&[Token::Array(vec![
+        reserves(1_500, 2_000),
+        reserves(1_000, 2_000),
+        Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+    ])]);
+    let client = FixtureClient::new(vec![FixtureResponse {
+        method: "eth_call".to_string(),
+        params_contain: vec!["0xca11bde05977b3631167028862be2a173976ca11".to_string()],
+        result: serde_json::to_value(Bytes::from(results)).unwrap(),
+    }]);
+    let bot = SandoBot::new(client.provider(), config).with_pools(vec![
+        v2_pool(drifted, (token, 1_000), (weth, 2_000)),
+        v2_pool(current, (token, 1_000), (weth, 2_000)),
+        v2_pool(unreadable, (token, 1_000), (weth, 2_000)),
+    ]);
+
+    let refreshed = bot
+        .refresh_pools(&[drifted, current, unreadable], U64::from(18_000_000))
+        .await
+        .unwrap();
+
+    let state = |reserve_0: u64, reserve_1: u64| PoolState::V2 {
+        reserve_0: reserve_0.into(),
+        reserve_1: reserve_1.into(),
+    };
+    assert_eq!(
+        refreshed,
+        vec![(drifted, state(1_000, 2_000), state(1_500, 2_000))]
+    );
+    // the cached state (and the prices derived from it) follow the chain
+    assert_eq!(bot.pool_price(drifted), Some(2_000.0 / 1_500.0));
+    assert_eq!(bot.pool_price(current), Some(2.0));
+    assert_eq!(bot.pool_price(unreadable), Some(2.0));
+}
+
+#[tokio::test]
+async fn tells_rpc_failures_apart_from_decode_errors() {
+    // no canned responses, so every rpc call fails
+    let client = FixtureClient::new(vec![]);
+    let mut bot = SandoBot::new(client.provider(), config());
+    let path = std::env::temp_dir().join("sando-pipeline-pools.json");
+    match bot.sync_pools_to(path.to_str().unwrap()).await {
+        Err(e @ SandoError::ProviderError(_)) => assert!(e.is_retryable()),
+        Err(e) => panic!("expected a provider error, got {}", e),
+        Ok(_) => panic!("expected a provider error, pools synced"),
+    }
+
+    let decode = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+    let decode = SandoError::from(anyhow::Error::new(decode).context("Failed to read pool cache"));
+    assert!(matches!(decode, SandoError::DecodeError(_)));
+    assert!(!decode.is_retryable());
+
+    // typed errors raised under anyhow come back out as they were
+    let stale = anyhow::Error::new(SandoError::StaleState("nonce behind".to_string()));
+    assert!(matches!(
+        SandoError::from(stale),
+        SandoError::StaleState(reason) if reason == "nonce behind"
+    ));
+}
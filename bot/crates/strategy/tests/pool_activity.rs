@@ -0,0 +1,40 @@
+use ethers::types::U64;
+use strategy::types::PoolActivity;
+
+#[test]
+fn classifies_pools_by_their_latest_logs() {
+    let block = U64::from(1_000);
+
+    // moved enough within the hot window (`HOT_POOL_WINDOW_BLOCKS` = 10)
+    assert_eq!(
+        PoolActivity::classify(block, block, Some(U64::from(991)), Some(50)),
+        PoolActivity::Hot
+    );
+    assert_eq!(
+        PoolActivity::classify(block, block, Some(U64::from(990)), Some(50)),
+        PoolActivity::Active
+    );
+
+    // quiet for too long, unless going cold is off
+    assert_eq!(
+        PoolActivity::classify(block, U64::from(950), None, Some(50)),
+        PoolActivity::Cold
+    );
+    assert_eq!(
+        PoolActivity::classify(block, U64::from(950), None, None),
+        PoolActivity::Active
+    );
+    assert_eq!(
+        PoolActivity::classify(block, U64::from(951), None, Some(50)),
+        PoolActivity::Active
+    );
+    // a hot pool never counts as cold
+    assert_eq!(
+        PoolActivity::classify(block, U64::from(900), Some(U64::from(995)), Some(50)),
+        PoolActivity::Hot
+    );
+
+    // victims are evaluated in this order
+    assert!(PoolActivity::Hot < PoolActivity::Active);
+    assert!(PoolActivity::Active < PoolActivity::Cold);
+}
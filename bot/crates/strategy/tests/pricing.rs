@@ -0,0 +1,97 @@
+use cfmms::pool::{Pool, UniswapV2Pool, UniswapV3Pool};
+use ethers::types::{Address, U256};
+use strategy::pricing::{quote_in_weth, spot_price, WethLiquidity};
+
+fn weth() -> Address {
+    "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap()
+}
+
+fn wmatic() -> Address {
+    "0x0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270"
+        .parse()
+        .unwrap()
+}
+
+/// v2 pool pricing 1 token at 2 `wrapped_native`
+fn pool(token: Address, wrapped_native: Address) -> Pool {
+    let (token_a, token_b, reserve_0, reserve_1) = if token < wrapped_native {
+        (token, wrapped_native, 1_000u128, 2_000u128)
+    } else {
+        (wrapped_native, token, 2_000u128, 1_000u128)
+    };
+
+    Pool::UniswapV2(UniswapV2Pool {
+        address: Address::repeat_byte(0xaa),
+        token_a,
+        token_b,
+        reserve_0,
+        reserve_1,
+        fee: 300,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn normalizes_profit_with_configured_wrapped_native() {
+    let token = Address::repeat_byte(0x01);
+    let pools = vec![pool(token, wmatic())];
+
+    assert_eq!(
+        quote_in_weth(token, U256::from(100), pools.clone(), wmatic()),
+        Some(U256::from(200))
+    );
+
+    // same pools can't price the token when weth is expected
+    assert_eq!(quote_in_weth(token, U256::from(100), pools, weth()), None);
+}
+
+#[test]
+fn configured_wrapped_native_is_already_normalized() {
+    assert_eq!(
+        quote_in_weth(wmatic(), U256::from(100), vec![], wmatic()),
+        Some(U256::from(100))
+    );
+}
+
+#[test]
+fn liquidity_is_measured_in_configured_wrapped_native() {
+    let token = Address::repeat_byte(0x01);
+
+    assert_eq!(
+        pool(token, wmatic()).weth_liquidity(wmatic()),
+        U256::from(2_000)
+    );
+}
+
+#[test]
+fn spot_price_tracks_reserve_changes() {
+    let token = Address::repeat_byte(0x01);
+    let mut pool = match pool(token, wmatic()) {
+        Pool::UniswapV2(p) => p,
+        Pool::UniswapV3(_) => unreachable!(),
+    };
+    // token0 trades for 2 token1
+    assert_eq!(spot_price(&Pool::UniswapV2(pool)), Some(2.0));
+
+    pool.reserve_1 = 500;
+    assert_eq!(spot_price(&Pool::UniswapV2(pool)), Some(0.5));
+
+    // nothing to price against
+    pool.reserve_0 = 0;
+    assert_eq!(spot_price(&Pool::UniswapV2(pool)), None);
+}
+
+#[test]
+fn spot_price_of_v3_pools_squares_their_sqrt_price() {
+    let mut pool = UniswapV3Pool {
+        sqrt_price: U256::one() << 97,
+        ..Default::default()
+    };
+    assert_eq!(spot_price(&Pool::UniswapV3(pool)), Some(4.0));
+
+    // uninitialized
+    pool.sqrt_price = U256::zero();
+    assert_eq!(spot_price(&Pool::UniswapV3(pool)), None);
+}
@@ -0,0 +1,56 @@
+use ethers::{
+    types::{Address, Log, TxHash, H256},
+    utils::keccak256,
+};
+use strategy::managers::rebase_detector::{standalone_sync_pairs, RebaseDetector};
+
+fn pair_log(event: &str, pair: u8, tx: u64) -> Log {
+    Log {
+        address: Address::repeat_byte(pair),
+        topics: vec![H256::from(keccak256(event))],
+        transaction_hash: Some(TxHash::from_low_u64_be(tx)),
+        ..Default::default()
+    }
+}
+
+const SYNC: &str = "Sync(uint112,uint112)";
+const SWAP: &str = "Swap(address,uint256,uint256,uint256,uint256,address)";
+const MINT: &str = "Mint(address,uint256,uint256)";
+
+#[test]
+fn finds_pairs_syncing_without_a_swap() {
+    let logs = vec![
+        // regular swap
+        pair_log(SWAP, 1, 1),
+        pair_log(SYNC, 1, 1),
+        // liquidity added
+        pair_log(MINT, 2, 2),
+        pair_log(SYNC, 2, 2),
+        // `sync()` called after the token rebased, twice in the block
+        pair_log(SYNC, 3, 3),
+        pair_log(SWAP, 1, 4),
+        pair_log(SYNC, 1, 4),
+        pair_log(SYNC, 3, 5),
+        // swap in one tx doesn't cover a sync in another
+        pair_log(SWAP, 4, 6),
+        pair_log(SYNC, 4, 7),
+    ];
+
+    assert_eq!(
+        standalone_sync_pairs(&logs),
+        vec![Address::repeat_byte(3), Address::repeat_byte(4)]
+    );
+}
+
+#[test]
+fn flags_a_token_once_it_reaches_its_strikes() {
+    let mut detector = RebaseDetector::new(2);
+    let ampl = Address::repeat_byte(0xaa);
+    let other = Address::repeat_byte(0xbb);
+
+    assert!(!detector.record_standalone_sync(ampl));
+    assert!(!detector.record_standalone_sync(other));
+    assert!(detector.record_standalone_sync(ampl));
+    // already reported
+    assert!(!detector.record_standalone_sync(ampl));
+}
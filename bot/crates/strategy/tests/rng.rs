@@ -0,0 +1,28 @@
+use std::collections::HashSet;
+
+use strategy::rng::DecisionRng;
+
+#[test]
+fn same_seed_replays_the_same_choices() {
+    let (a, b) = (DecisionRng::new(Some(7)), DecisionRng::new(Some(7)));
+    assert_eq!(a.seed(), 7);
+
+    let picks_a = (0..200).map(|_| a.pick(5)).collect::<Vec<_>>();
+    let picks_b = (0..200).map(|_| b.pick(5)).collect::<Vec<_>>();
+    assert_eq!(picks_a, picks_b);
+    assert_eq!(a.address(), b.address());
+
+    // every index in range comes up, none past it
+    let seen = picks_a.into_iter().collect::<HashSet<_>>();
+    assert_eq!(seen, (0..5).collect());
+    assert_eq!(a.pick(0), 0);
+}
+
+#[test]
+fn unseeded_rng_reports_a_replayable_seed() {
+    let rng = DecisionRng::new(None);
+    let replay = DecisionRng::new(Some(rng.seed()));
+
+    assert_eq!(rng.address(), replay.address());
+    assert_eq!(rng.pick(1_000), replay.pick(1_000));
+}
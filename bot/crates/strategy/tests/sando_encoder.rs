@@ -0,0 +1,333 @@
+use cfmms::pool::{Pool, UniswapV2Pool, UniswapV3Pool};
+use ethers::{
+    abi::{encode, Token},
+    types::{Address, Bytes, U256},
+    utils::keccak256,
+};
+use strategy::tx_utils::sando_encoder::{
+    backrun_min_out, encode_backrun, encode_frontrun, v2_direct_backrun, v2_direct_frontrun,
+    HuffSandoEncoder, SandoCall, SandoEncoder,
+};
+
+fn weth() -> Address {
+    "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap()
+}
+
+fn v2_pool(token: Address) -> Pool {
+    Pool::UniswapV2(UniswapV2Pool {
+        address: Address::repeat_byte(0xaa),
+        token_a: weth().min(token),
+        token_b: weth().max(token),
+        fee: 300,
+        ..Default::default()
+    })
+}
+
+fn v3_pool(token: Address) -> Pool {
+    Pool::UniswapV3(UniswapV3Pool {
+        address: Address::repeat_byte(0xbb),
+        token_a: weth().min(token),
+        token_b: weth().max(token),
+        fee: 3000,
+        ..Default::default()
+    })
+}
+
+/// keccak256(abi.encode(token0, token1, fee)) used by the contract to verify v3 callbacks
+fn pool_key_hash(pool: &Pool) -> [u8; 32] {
+    match pool {
+        Pool::UniswapV3(p) => keccak256(encode(&[
+            Token::Address(p.token_a),
+            Token::Address(p.token_b),
+            Token::Uint(p.fee.into()),
+        ])),
+        Pool::UniswapV2(_) => unreachable!(),
+    }
+}
+
+#[test]
+fn v2_frontrun_weth_token0() {
+    let token = Address::repeat_byte(0xff);
+    let call = encode_frontrun(
+        v2_pool(token),
+        token,
+        U256::from(10u64.pow(18)),
+        U256::from(1000),
+    );
+    let calldata = call.calldata.to_vec();
+
+    // jump_dest (1) | pool (20) | five_bytes (5)
+    assert_eq!(calldata.len(), 26);
+    assert_eq!(calldata[0], 0x0a); // v2_frontrun0
+    assert_eq!(&calldata[1..21], Address::repeat_byte(0xaa).as_bytes());
+    // amount_out stored so it lands in param 1 (offset 4 + 32 + 32 - 4)
+    assert_eq!(&calldata[21..], &[0x40, 0x00, 0x00, 0x03, 0xe8]);
+
+    // weth is sent as tx.value / 100000
+    assert_eq!(call.value, U256::from(10_000_000_000_000u64));
+}
+
+#[test]
+fn v2_backrun_weth_token1() {
+    let token = Address::repeat_byte(0x01);
+    let call = encode_backrun(
+        v2_pool(token),
+        token,
+        U256::from(1000),
+        U256::from(10u64.pow(18)),
+    );
+    let calldata = call.calldata.to_vec();
+
+    // jump_dest (1) | pool (20) | token_in (20) | five_bytes (5)
+    assert_eq!(calldata.len(), 46);
+    assert_eq!(calldata[0], 0x0f); // v2_backrun1
+    assert_eq!(&calldata[1..21], Address::repeat_byte(0xaa).as_bytes());
+    assert_eq!(&calldata[21..41], token.as_bytes());
+    assert_eq!(&calldata[41..], &[0x40, 0x00, 0x00, 0x03, 0xe8]);
+    assert_eq!(call.value, U256::from(10_000_000_000_000u64));
+}
+
+#[test]
+fn backrun_min_out_buffer_lowers_encoded_weth_out() {
+    let token = Address::repeat_byte(0x01);
+    let simulated_out = U256::from(10u64.pow(18));
+
+    // 50 bps below the simulated 1 weth out
+    let min_out = backrun_min_out(simulated_out, 50);
+    assert_eq!(min_out, U256::from(995_000_000_000_000_000u64));
+
+    let call = encode_backrun(v2_pool(token), token, U256::from(1000), min_out);
+    assert_eq!(call.value, U256::from(9_950_000_000_000u64));
+
+    // no buffer keeps the simulated output, buffers are capped at the whole output
+    assert_eq!(backrun_min_out(simulated_out, 0), simulated_out);
+    assert!(backrun_min_out(simulated_out, 20_000).is_zero());
+}
+
+#[test]
+fn five_bytes_shift_amounts_that_do_not_fit_in_four_bytes() {
+    let token = Address::repeat_byte(0x01);
+    let call = encode_backrun(
+        v2_pool(token),
+        token,
+        U256::from(0x1234567890u64),
+        U256::zero(),
+    );
+    let calldata = call.calldata.to_vec();
+
+    // lowest byte is dropped and the store offset moves one byte left to compensate
+    assert_eq!(&calldata[41..], &[0x3f, 0x12, 0x34, 0x56, 0x78]);
+}
+
+#[test]
+fn v3_frontrun_weth_token1() {
+    let token = Address::repeat_byte(0x01);
+    let pool = v3_pool(token);
+    let call = encode_frontrun(pool, token, U256::from(10u64.pow(18)), U256::zero());
+    let calldata = call.calldata.to_vec();
+
+    // jump_dest (1) | pool (20) | pool_key_hash (32)
+    assert_eq!(calldata.len(), 53);
+    assert_eq!(calldata[0], 0x28); // v3_frontrun1
+    assert_eq!(&calldata[1..21], Address::repeat_byte(0xbb).as_bytes());
+    assert_eq!(&calldata[21..], &pool_key_hash(&pool));
+    assert_eq!(call.value, U256::from(10_000_000_000_000u64));
+}
+
+#[test]
+fn v3_backrun_weth_token0() {
+    let token = Address::repeat_byte(0xff);
+    let pool = v3_pool(token);
+    let call = encode_backrun(pool, token, U256::from(1000), U256::zero());
+    let calldata = call.calldata.to_vec();
+
+    // jump_dest (1) | pool (20) | token_in (20) | pool_key_hash (32) | five_bytes (5)
+    assert_eq!(calldata.len(), 78);
+    assert_eq!(calldata[0], 0x19); // v3_backrun0
+    assert_eq!(&calldata[1..21], Address::repeat_byte(0xbb).as_bytes());
+    assert_eq!(&calldata[21..41], token.as_bytes());
+    assert_eq!(&calldata[41..73], &pool_key_hash(&pool));
+    // amount_in stored so it lands in param 2 (offset 4 + 32 + 64 - 4)
+    assert_eq!(&calldata[73..], &[0x60, 0x00, 0x00, 0x03, 0xe8]);
+
+    // v3 backruns don't spend weth
+    assert!(call.value.is_zero());
+}
+
+#[test]
+fn coinbase_payment_is_appended_after_backrun_args() {
+    let token = Address::repeat_byte(0xff);
+    let pool = v2_pool(token);
+    let call = encode_backrun(pool, token, U256::from(1000), U256::from(1000));
+    let bribe = U256::from(123_456_789u64);
+
+    let paid = call.clone().with_coinbase_payment(bribe);
+    let calldata = paid.calldata.to_vec();
+
+    // packed args are untouched, bribe is a full word at the end
+    assert_eq!(calldata.len(), 46 + 32);
+    assert_eq!(&calldata[..46], &call.calldata.to_vec()[..]);
+    assert_eq!(U256::from_big_endian(&calldata[46..]), bribe);
+    assert_eq!(paid.value, call.value);
+}
+
+/// Amount in the trailing five bytes of `calldata`, stored for abi param `param_index`
+fn five_byte_amount(calldata: &[u8], param_index: u8) -> U256 {
+    let five_bytes = &calldata[calldata.len() - 5..];
+    let byte_shift = 4 + 32 + param_index * 32 - 4 - five_bytes[0];
+    let four_bytes = u32::from_be_bytes(five_bytes[1..].try_into().unwrap());
+    U256::from(four_bytes) << (8 * byte_shift as usize)
+}
+
+/// Mirrors the k check in `UniswapV2Pair.swap` (fee taken from the input)
+fn pool_accepts(
+    amount_in: U256,
+    amount_out: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee_bps: u32,
+) -> bool {
+    let balance_in = (reserve_in + amount_in) * 10_000 - amount_in * fee_bps;
+    let balance_out = (reserve_out - amount_out) * 10_000;
+    balance_in * balance_out >= reserve_in * reserve_out * 100_000_000
+}
+
+#[test]
+fn direct_v2_frontrun_amounts_are_what_the_pool_accepts() {
+    // token0 is weth, the output lands in param 1
+    let token = Address::repeat_byte(0xff);
+    let reserve_weth = U256::from(10u64).pow(21.into());
+    let reserve_token = U256::from(7u64).pow(30.into());
+
+    for weth_in in [
+        U256::from(123_456_789_012_345_678u64),
+        U256::from(10u64).pow(18.into()),
+        U256::from(99_999u64) * U256::from(10u64).pow(15.into()),
+    ] {
+        let swap = v2_direct_frontrun(weth_in, reserve_weth, reserve_token, 30);
+        let call = encode_frontrun(v2_pool(token), token, swap.amount_in, swap.amount_out);
+
+        // what the contract sends and asks for is exactly what we priced
+        assert_eq!(call.value * 100_000, swap.amount_in);
+        assert_eq!(five_byte_amount(&call.calldata, 1), swap.amount_out);
+        assert!(swap.amount_in <= weth_in);
+        assert!(pool_accepts(
+            swap.amount_in,
+            swap.amount_out,
+            reserve_weth,
+            reserve_token,
+            30
+        ));
+    }
+}
+
+#[test]
+fn direct_v2_backrun_amounts_are_what_the_pool_accepts() {
+    // token1 is weth, the contract sells its token balance
+    let token = Address::repeat_byte(0x01);
+    let reserve_token = U256::from(3u64).pow(40.into());
+    let reserve_weth = U256::from(10u64).pow(21.into());
+
+    for balance in [
+        U256::from(987_654_321_987_654_321u64) * 1_000_000u64,
+        U256::from(u32::MAX),
+        U256::from(12_345u64),
+    ] {
+        let swap = v2_direct_backrun(balance, reserve_token, reserve_weth, 30).unwrap();
+        let call = encode_backrun(v2_pool(token), token, swap.amount_in, swap.amount_out);
+
+        assert_eq!(five_byte_amount(&call.calldata, 1), swap.amount_in);
+        assert_eq!(call.value * 100_000, swap.amount_out);
+        // dust stays on the contract
+        assert!(swap.amount_in < balance);
+        assert!(pool_accepts(
+            swap.amount_in,
+            swap.amount_out,
+            reserve_token,
+            reserve_weth,
+            30
+        ));
+    }
+
+    // nothing left to sell after keeping dust
+    assert!(v2_direct_backrun(U256::one(), reserve_token, reserve_weth, 30).is_none());
+}
+
+/// Contract taking abi encoded `(pool, token, amount_in, amount_out)` behind a 4 byte selector
+struct AbiSandoEncoder;
+
+impl AbiSandoEncoder {
+    fn encode(
+        selector: [u8; 4],
+        pool: Pool,
+        token: Address,
+        amount_in: U256,
+        amount_out: U256,
+    ) -> SandoCall {
+        let mut calldata = selector.to_vec();
+        calldata.extend(encode(&[
+            Token::Address(pool.address()),
+            Token::Address(token),
+            Token::Uint(amount_in),
+            Token::Uint(amount_out),
+        ]));
+        SandoCall {
+            calldata: Bytes::from(calldata),
+            value: U256::zero(),
+        }
+    }
+}
+
+impl SandoEncoder for AbiSandoEncoder {
+    fn encode_frontrun(
+        &self,
+        pool: Pool,
+        output_token: Address,
+        amount_in: U256,
+        amount_out: U256,
+    ) -> SandoCall {
+        Self::encode([0, 0, 0, 1], pool, output_token, amount_in, amount_out)
+    }
+
+    fn encode_backrun(
+        &self,
+        pool: Pool,
+        input_token: Address,
+        amount_in: U256,
+        amount_out: U256,
+    ) -> SandoCall {
+        Self::encode([0, 0, 0, 2], pool, input_token, amount_in, amount_out)
+    }
+}
+
+#[test]
+fn encoders_are_interchangeable() {
+    let token = Address::repeat_byte(0xff);
+    let amount_in = U256::from(10u64.pow(18));
+    let amount_out = U256::from(1000);
+
+    // reference encoder is the huff layout
+    let huff: Box<dyn SandoEncoder> = Box::new(HuffSandoEncoder);
+    assert_eq!(
+        huff.encode_frontrun(v2_pool(token), token, amount_in, amount_out),
+        encode_frontrun(v2_pool(token), token, amount_in, amount_out)
+    );
+    assert_eq!(
+        huff.encode_backrun(v3_pool(token), token, amount_in, amount_out),
+        encode_backrun(v3_pool(token), token, amount_in, amount_out)
+    );
+
+    // a custom layout gets the same sized amounts
+    let custom: Box<dyn SandoEncoder> = Box::new(AbiSandoEncoder);
+    let call = custom.encode_backrun(v2_pool(token), token, amount_in, amount_out);
+    assert_eq!(call.calldata.len(), 4 + 4 * 32);
+    assert_eq!(&call.calldata[..4], &[0, 0, 0, 2]);
+    assert_eq!(
+        &call.calldata[16..36],
+        Address::repeat_byte(0xaa).as_bytes()
+    );
+    assert_eq!(U256::from_big_endian(&call.calldata[68..100]), amount_in);
+    assert_eq!(U256::from_big_endian(&call.calldata[100..]), amount_out);
+}
@@ -0,0 +1,67 @@
+use ethers::{
+    core::rand::thread_rng,
+    signers::{LocalWallet, Signer},
+};
+use strategy::signer::{SearcherSigner, SignerConfig, SignerKind};
+
+const KEY: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+
+#[tokio::test]
+async fn keystore_and_private_key_load_the_same_signer() {
+    let dir = std::env::temp_dir().join("sando-signer-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let key: LocalWallet = KEY.parse().unwrap();
+    let (_, name) = LocalWallet::encrypt_keystore(
+        &dir,
+        &mut thread_rng(),
+        key.signer().to_bytes(),
+        "hunter2",
+        Some("searcher.json"),
+    )
+    .unwrap();
+
+    let keystore = SignerConfig::Keystore {
+        path: dir.join(name),
+        password: "hunter2".to_string(),
+    };
+    let from_keystore = keystore.clone().build(1).await.unwrap();
+    let from_key = SignerConfig::PrivateKey(KEY.to_string())
+        .build(1)
+        .await
+        .unwrap();
+    assert_eq!(from_keystore.address(), key.address());
+    assert_eq!(from_key.address(), key.address());
+    assert_eq!(from_keystore.chain_id(), 1);
+
+    // wrong password
+    let SignerConfig::Keystore { path, .. } = keystore else {
+        unreachable!()
+    };
+    let wrong = SignerConfig::Keystore {
+        path,
+        password: "hunter3".to_string(),
+    };
+    assert!(wrong.build(1).await.is_err());
+
+    // config files and tests parse a hex key straight into a local signer
+    let parsed: SearcherSigner = KEY.parse().unwrap();
+    assert_eq!(parsed.address(), key.address());
+}
+
+#[test]
+fn parses_signer_kinds() {
+    assert_eq!("key".parse::<SignerKind>().unwrap(), SignerKind::PrivateKey);
+    assert_eq!(
+        "Keystore".parse::<SignerKind>().unwrap(),
+        SignerKind::Keystore
+    );
+    assert_eq!("ledger".parse::<SignerKind>().unwrap(), SignerKind::Ledger);
+    assert_eq!("kms".parse::<SignerKind>().unwrap(), SignerKind::AwsKms);
+    assert!("trezor".parse::<SignerKind>().is_err());
+}
+
+#[cfg(not(feature = "ledger"))]
+#[tokio::test]
+async fn hardware_signers_need_their_feature() {
+    assert!(SignerConfig::Ledger { index: 0 }.build(1).await.is_err());
+}
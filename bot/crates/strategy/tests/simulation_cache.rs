@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use ethers::types::{AccountDiff, Address, ChangedType, Diff, Transaction, H256, U256, U64};
+use strategy::{
+    managers::simulation_cache::{SimulationCache, SimulationKey},
+    types::VictimInfo,
+};
+
+fn pool() -> Address {
+    Address::repeat_byte(0xaa)
+}
+
+/// Victim tx that moves the pool's reserve slot from `from` to `to`
+fn victim(hash: u64, from: u64, to: u64) -> VictimInfo {
+    let account_diff = AccountDiff {
+        balance: Diff::Same,
+        nonce: Diff::Same,
+        code: Diff::Same,
+        storage: BTreeMap::from([(
+            H256::from_low_u64_be(8),
+            Diff::Changed(ChangedType {
+                from: H256::from_low_u64_be(from),
+                to: H256::from_low_u64_be(to),
+            }),
+        )]),
+    };
+
+    VictimInfo::with_state_diffs(
+        Transaction {
+            hash: H256::from_low_u64_be(hash),
+            ..Default::default()
+        },
+        BTreeMap::from([(pool(), account_diff)]),
+    )
+}
+
+fn key(victim: &VictimInfo, pool: Address) -> Option<SimulationKey> {
+    SimulationKey::new(
+        U64::from(100),
+        pool,
+        Address::repeat_byte(0xcc),
+        U256::exp10(18),
+        victim,
+    )
+}
+
+#[test]
+fn victims_leaving_pool_in_same_state_share_a_key() {
+    let first = key(&victim(1, 10, 20), pool()).unwrap();
+    let same_swap = key(&victim(2, 10, 20), pool()).unwrap();
+    let other_swap = key(&victim(3, 10, 25), pool()).unwrap();
+
+    assert_eq!(first, same_swap);
+    assert_ne!(first, other_swap);
+    assert!(key(&victim(1, 10, 20), Address::repeat_byte(0xbb)).is_none());
+}
+
+#[test]
+fn new_block_clears_cached_results() {
+    let mut cache = SimulationCache::default();
+    let key = key(&victim(1, 10, 20), pool()).unwrap();
+
+    cache.insert(key, Err("backrun reverted".to_string()));
+    assert!(matches!(cache.get(&key), Some(Err(e)) if e == "backrun reverted"));
+
+    cache.clear();
+    assert!(cache.get(&key).is_none());
+    assert!(cache.is_empty());
+}
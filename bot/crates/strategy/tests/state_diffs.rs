@@ -0,0 +1,219 @@
+use cfmms::pool::UniswapV2Pool;
+use ethers::types::{Address, ChangedType, Diff, DiffMode, Transaction, H256, U256};
+use std::time::{Duration, Instant};
+use strategy::{
+    bot::evaluate_within,
+    math::v2_sandwich::get_amount_out,
+    types::{
+        packs_v2_reserves, prestate_diff_to_state_diffs, v2_swap_state_diffs, DecodedSwap,
+        StateDiffError, StateDiffMethod, VictimInfo,
+    },
+};
+
+fn slot(value: u64) -> H256 {
+    H256::from_low_u64_be(value)
+}
+
+#[test]
+fn parses_state_diff_methods() {
+    for (name, method) in [
+        ("trace_call", StateDiffMethod::TraceCall),
+        ("trace_callMany", StateDiffMethod::TraceCallMany),
+        ("debug_traceCall", StateDiffMethod::DebugTraceCall),
+        ("prestate_tracer", StateDiffMethod::DebugTraceCall),
+    ] {
+        assert_eq!(name.parse::<StateDiffMethod>().unwrap(), method);
+    }
+    assert!("eth_call".parse::<StateDiffMethod>().is_err());
+}
+
+#[test]
+fn converts_prestate_tracer_diffs_to_state_diffs() {
+    // `debug_traceCall` prestate tracer output (diff mode) of a swap paying a fresh recipient
+    let diff: DiffMode = serde_json::from_str(
+        r#"{
+            "pre": {
+                "0x1111111111111111111111111111111111111111": {
+                    "balance": "0x100",
+                    "nonce": 7,
+                    "storage": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000064",
+                        "0x0000000000000000000000000000000000000000000000000000000000000002": "0x0000000000000000000000000000000000000000000000000000000000000005"
+                    }
+                }
+            },
+            "post": {
+                "0x1111111111111111111111111111111111111111": {
+                    "balance": "0x80",
+                    "nonce": 8,
+                    "storage": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000001": "0x00000000000000000000000000000000000000000000000000000000000000c8",
+                        "0x0000000000000000000000000000000000000000000000000000000000000003": "0x0000000000000000000000000000000000000000000000000000000000000009"
+                    }
+                },
+                "0x2222222222222222222222222222222222222222": {
+                    "balance": "0x80"
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let state_diffs = prestate_diff_to_state_diffs(diff);
+    assert_eq!(state_diffs.len(), 2);
+
+    let pool = &state_diffs[&Address::repeat_byte(0x11)];
+    assert_eq!(
+        pool.balance,
+        Diff::Changed(ChangedType {
+            from: U256::from(0x100),
+            to: U256::from(0x80)
+        })
+    );
+    assert_eq!(
+        pool.nonce,
+        Diff::Changed(ChangedType {
+            from: U256::from(7),
+            to: U256::from(8)
+        })
+    );
+    // changed, zeroed (left out of `post`) and set slots
+    assert_eq!(
+        pool.storage[&slot(1)],
+        Diff::Changed(ChangedType {
+            from: slot(100),
+            to: slot(200)
+        })
+    );
+    assert_eq!(
+        pool.storage[&slot(2)],
+        Diff::Changed(ChangedType {
+            from: slot(5),
+            to: H256::zero()
+        })
+    );
+    assert_eq!(
+        pool.storage[&slot(3)],
+        Diff::Changed(ChangedType {
+            from: H256::zero(),
+            to: slot(9)
+        })
+    );
+
+    // account only in `post` is new
+    let recipient = &state_diffs[&Address::repeat_byte(0x22)];
+    assert_eq!(recipient.balance, Diff::Born(U256::from(0x80)));
+    assert_eq!(recipient.nonce, Diff::Same);
+    assert!(recipient.storage.is_empty());
+}
+
+#[test]
+fn rebuilds_v2_swap_reserves_from_storage() {
+    let (token_0, token_1) = (Address::repeat_byte(0x11), Address::repeat_byte(0x22));
+    let pair = UniswapV2Pool {
+        address: Address::repeat_byte(0xaa),
+        token_a: token_0,
+        token_b: token_1,
+        fee: 300,
+        ..Default::default()
+    };
+    // `blockTimestampLast|reserve1|reserve0` as read from slot 8
+    let packed = (U256::from(1_234) << 224)
+        | (U256::from(2_000_000_000u64) << 112)
+        | U256::from(1_000_000_000u64);
+    let decoded =
+        |path: Vec<Address>, amount_in: u64, amount_out_min: u64, exact_output| DecodedSwap {
+            path,
+            fees: vec![],
+            amount_in: U256::from(amount_in),
+            amount_out_min: U256::from(amount_out_min),
+            deadline: U256::MAX,
+            exact_output,
+        };
+    let reserves_after = |swap: &DecodedSwap| {
+        let state_diffs = v2_swap_state_diffs(swap, &[(pair.clone(), packed)])?;
+        VictimInfo::with_state_diffs(Transaction::default(), state_diffs)
+            .get_v2_reserves(pair.address)
+    };
+    let before = (U256::from(1_000_000_000u64), U256::from(2_000_000_000u64));
+
+    // exact input and the exact output swap that costs the same leave the same reserves
+    let sold_0 = (
+        U256::from(1_010_000_000u64),
+        U256::from(2_000_000_000u64 - 19_743_160),
+    );
+    for swap in [
+        decoded(vec![token_0, token_1], 10_000_000, 19_000_000, false),
+        decoded(vec![token_0, token_1], 10_000_000, 19_743_160, true),
+    ] {
+        assert_eq!(reserves_after(&swap), Some((before, sold_0)));
+    }
+
+    // selling token1 grows reserve1
+    let out = get_amount_out(
+        U256::from(20_000_000),
+        U256::from(2_000_000_000u64),
+        U256::from(1_000_000_000u64),
+        30,
+    );
+    assert_eq!(
+        reserves_after(&decoded(vec![token_1, token_0], 20_000_000, 0, false)),
+        Some((
+            before,
+            (
+                U256::from(1_000_000_000u64) - out,
+                U256::from(2_020_000_000u64)
+            )
+        ))
+    );
+
+    // slippage reverts the swap, and the path has to trade through the given pairs
+    for swap in [
+        decoded(vec![token_0, token_1], 10_000_000, 19_743_161, false),
+        decoded(vec![token_0, token_1], 9_999_999, 19_743_160, true),
+        decoded(
+            vec![token_0, Address::repeat_byte(0x33)],
+            10_000_000,
+            0,
+            false,
+        ),
+        decoded(vec![token_0, token_1, token_0], 10_000_000, 0, false),
+    ] {
+        assert_eq!(reserves_after(&swap), None, "{:?}", swap.path);
+    }
+}
+
+#[test]
+fn matches_packed_reserves_to_get_reserves() {
+    let reserve_0 = U256::from(1_000_000u64);
+    let reserve_1 = U256::from(2_000_000u64);
+    let timestamp = U256::from(1_700_000_000u64) << 224;
+    let packed = timestamp | (reserve_1 << 112) | reserve_0;
+
+    assert!(packs_v2_reserves(packed, reserve_0, reserve_1));
+    // reserves swapped, or a slot holding something else
+    assert!(!packs_v2_reserves(packed, reserve_1, reserve_0));
+    assert!(!packs_v2_reserves(U256::from(42), reserve_0, reserve_1));
+    // empty pairs can't tell which slot is theirs
+    assert!(!packs_v2_reserves(U256::zero(), U256::zero(), U256::zero()));
+}
+
+#[tokio::test]
+async fn abandons_a_slow_state_diff_trace_after_the_timeout() {
+    let slow_trace = async {
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        Ok::<(), StateDiffError>(())
+    };
+
+    let started = Instant::now();
+    let result = evaluate_within(Some(Duration::from_millis(20)), slow_trace).await;
+    assert!(result.is_none());
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // without a timeout the trace is awaited to the end
+    let fast_trace = async { Ok::<(), StateDiffError>(()) };
+    assert!(matches!(
+        evaluate_within(None, fast_trace).await,
+        Some(Ok(()))
+    ));
+}
@@ -0,0 +1,421 @@
+use ethers::{
+    abi::{encode, short_signature, ParamType, Token},
+    types::{Address, Transaction, U256},
+};
+use strategy::types::{decode_v3_path, DecodedSwap, VictimInfo};
+
+fn weth() -> Address {
+    "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap()
+}
+
+fn token() -> Address {
+    Address::repeat_byte(0x11)
+}
+
+fn user() -> Address {
+    Address::repeat_byte(0x22)
+}
+
+fn calldata(name: &str, params: &[ParamType], args: &[Token]) -> Vec<u8> {
+    let mut input = short_signature(name, params).to_vec();
+    input.extend(encode(args));
+    input
+}
+
+fn victim(input: Vec<u8>, value: U256) -> VictimInfo {
+    VictimInfo::new(Transaction {
+        input: input.into(),
+        value,
+        ..Default::default()
+    })
+}
+
+fn bytes_array(calls: Vec<Vec<u8>>) -> Token {
+    Token::Array(calls.into_iter().map(Token::Bytes).collect())
+}
+
+fn bytes_array_param() -> ParamType {
+    ParamType::Array(Box::new(ParamType::Bytes))
+}
+
+/// `selfPermit(token, value, deadline, v, r, s)` on the v3 swap routers
+fn self_permit() -> Vec<u8> {
+    calldata(
+        "selfPermit",
+        &[
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(8),
+            ParamType::FixedBytes(32),
+            ParamType::FixedBytes(32),
+        ],
+        &[
+            Token::Address(weth()),
+            Token::Uint(U256::from(10).pow(18.into())),
+            Token::Uint(U256::from(1_700_000_000u64)),
+            Token::Uint(U256::from(27)),
+            Token::FixedBytes(vec![0xab; 32]),
+            Token::FixedBytes(vec![0xcd; 32]),
+        ],
+    )
+}
+
+/// v3 path `token_in|fee|token_out`
+fn v3_path(token_in: Address, fee: u32, token_out: Address) -> Vec<u8> {
+    let mut path = token_in.as_bytes().to_vec();
+    path.extend(&fee.to_be_bytes()[1..]);
+    path.extend(token_out.as_bytes());
+    path
+}
+
+#[test]
+fn unwraps_self_permit_multicall_on_the_swap_router() {
+    // first swap router's `exactInputSingle` carries its own deadline
+    let exact_input_single = calldata(
+        "exactInputSingle",
+        &[ParamType::Tuple(vec![
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Uint(24),
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(160),
+        ])],
+        &[Token::Tuple(vec![
+            Token::Address(weth()),
+            Token::Address(token()),
+            Token::Uint(500.into()),
+            Token::Address(user()),
+            Token::Uint(U256::from(1_700_000_000u64)),
+            Token::Uint(U256::from(10).pow(18.into())),
+            Token::Uint(U256::from(1_000)),
+            Token::Uint(U256::zero()),
+        ])],
+    );
+    let multicall = calldata(
+        "multicall",
+        &[bytes_array_param()],
+        &[bytes_array(vec![self_permit(), exact_input_single])],
+    );
+
+    assert_eq!(
+        victim(multicall, U256::zero()).decode_swap(),
+        Some(DecodedSwap {
+            path: vec![weth(), token()],
+            fees: vec![500],
+            amount_in: U256::from(10).pow(18.into()),
+            amount_out_min: U256::from(1_000),
+            deadline: U256::from(1_700_000_000u64),
+            exact_output: false,
+        })
+    );
+}
+
+#[test]
+fn router02_swaps_take_the_multicall_deadline() {
+    // swap router02's `exactOutput` has no deadline of its own
+    let exact_output = calldata(
+        "exactOutput",
+        &[ParamType::Tuple(vec![
+            ParamType::Bytes,
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ])],
+        &[Token::Tuple(vec![
+            // exact output paths are encoded backwards
+            Token::Bytes(v3_path(token(), 3_000, weth())),
+            Token::Address(user()),
+            Token::Uint(U256::from(5_000)),
+            Token::Uint(U256::from(2_000_000)),
+        ])],
+    );
+    let multicall = calldata(
+        "multicall",
+        &[ParamType::Uint(256), bytes_array_param()],
+        &[
+            Token::Uint(U256::from(1_700_000_012u64)),
+            bytes_array(vec![self_permit(), exact_output]),
+        ],
+    );
+
+    let swap = victim(multicall, U256::zero()).decode_swap().unwrap();
+    assert_eq!(swap.path, vec![weth(), token()]);
+    assert_eq!(swap.fees, vec![3_000]);
+    assert_eq!(swap.amount_in, U256::from(2_000_000));
+    assert_eq!(swap.amount_out_min, U256::from(5_000));
+    assert_eq!(swap.deadline, U256::from(1_700_000_012u64));
+    assert!(swap.exact_output);
+}
+
+/// Universal router `execute(commands, inputs, deadline)`
+fn execute(commands: Vec<u8>, inputs: Vec<Vec<u8>>) -> Vec<u8> {
+    calldata(
+        "execute",
+        &[ParamType::Bytes, bytes_array_param(), ParamType::Uint(256)],
+        &[
+            Token::Bytes(commands),
+            bytes_array(inputs),
+            Token::Uint(U256::from(1_700_000_024u64)),
+        ],
+    )
+}
+
+#[test]
+fn skips_permit2_permit_before_a_universal_router_swap() {
+    // PERMIT2_PERMIT: (((token, amount, expiration, nonce), spender, sig deadline), signature)
+    let permit = encode(&[
+        Token::Tuple(vec![
+            Token::Tuple(vec![
+                Token::Address(token()),
+                Token::Uint(U256::MAX >> 96),
+                Token::Uint(U256::from(1_702_592_000u64)),
+                Token::Uint(U256::zero()),
+            ]),
+            Token::Address(Address::repeat_byte(0x3f)),
+            Token::Uint(U256::from(1_700_001_800u64)),
+        ]),
+        Token::Bytes(vec![0xee; 65]),
+    ]);
+    // V2_SWAP_EXACT_IN: (recipient, amount in, amount out min, path, payer is user)
+    let v2_swap = encode(&[
+        Token::Address(user()),
+        Token::Uint(U256::from(7_000)),
+        Token::Uint(U256::from(3_000)),
+        Token::Array(vec![Token::Address(token()), Token::Address(weth())]),
+        Token::Bool(true),
+    ]);
+
+    assert_eq!(
+        victim(
+            execute(vec![0x0a, 0x08], vec![permit, v2_swap]),
+            U256::zero()
+        )
+        .decode_swap(),
+        Some(DecodedSwap {
+            path: vec![token(), weth()],
+            fees: vec![],
+            amount_in: U256::from(7_000),
+            amount_out_min: U256::from(3_000),
+            deadline: U256::from(1_700_000_024u64),
+            exact_output: false,
+        })
+    );
+}
+
+#[test]
+fn universal_router_swap_of_wrapped_eth_uses_tx_value() {
+    let value = U256::from(10).pow(18.into());
+    // WRAP_ETH: (recipient, amount min), recipient 2 is the router itself
+    let wrap = encode(&[
+        Token::Address(Address::from_low_u64_be(2)),
+        Token::Uint(value),
+    ]);
+    // V3_SWAP_EXACT_IN swapping the router's whole (just wrapped) balance
+    let v3_swap = encode(&[
+        Token::Address(user()),
+        Token::Uint(U256::one() << 255),
+        Token::Uint(U256::from(1_000)),
+        Token::Bytes(v3_path(weth(), 500, token())),
+        Token::Bool(false),
+    ]);
+
+    let swap = victim(execute(vec![0x0b, 0x00], vec![wrap, v3_swap]), value)
+        .decode_swap()
+        .unwrap();
+    assert_eq!(swap.path, vec![weth(), token()]);
+    assert_eq!(swap.fees, vec![500]);
+    assert_eq!(swap.amount_in, value);
+    assert_eq!(swap.amount_out_min, U256::from(1_000));
+}
+
+#[test]
+fn multicall_without_a_swap_is_not_decoded() {
+    let multicall = calldata(
+        "multicall",
+        &[bytes_array_param()],
+        &[bytes_array(vec![self_permit()])],
+    );
+
+    assert_eq!(victim(multicall, U256::zero()).decode_swap(), None);
+}
+
+fn usdc() -> Address {
+    "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+        .parse()
+        .unwrap()
+}
+
+fn dai() -> Address {
+    "0x6B175474E89094C44Da98b954EedeAC495271d0F"
+        .parse()
+        .unwrap()
+}
+
+/// v3 path through every `(token, fee)` hop, ending in `token_out`
+fn v3_multi_hop_path(hops: &[(Address, u32)], token_out: Address) -> Vec<u8> {
+    let mut path = vec![];
+    for (token, fee) in hops {
+        path.extend(token.as_bytes());
+        path.extend(&fee.to_be_bytes()[1..]);
+    }
+    path.extend(token_out.as_bytes());
+    path
+}
+
+#[test]
+fn decodes_packed_v3_paths_hop_by_hop() {
+    // weth -(0.05%)-> usdc -(0.01%)-> dai -(0.3%)-> token
+    let path = v3_multi_hop_path(&[(weth(), 500), (usdc(), 100), (dai(), 3_000)], token());
+    assert_eq!(path.len(), 20 + 3 * 23);
+
+    assert_eq!(
+        decode_v3_path(&path),
+        vec![
+            (weth(), 500, usdc()),
+            (usdc(), 100, dai()),
+            (dai(), 3_000, token())
+        ]
+    );
+
+    // a token without its fee or a dangling fee isn't a path
+    assert!(decode_v3_path(&path[..20]).is_empty());
+    assert!(decode_v3_path(&path[..path.len() - 20]).is_empty());
+}
+
+#[test]
+fn exact_input_multi_hop_swaps_expose_every_hop() {
+    // first swap router's `exactInput((path, recipient, deadline, amount in, amount out min))`
+    let exact_input = calldata(
+        "exactInput",
+        &[ParamType::Tuple(vec![
+            ParamType::Bytes,
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ])],
+        &[Token::Tuple(vec![
+            Token::Bytes(v3_multi_hop_path(
+                &[(weth(), 500), (usdc(), 100), (dai(), 3_000)],
+                token(),
+            )),
+            Token::Address(user()),
+            Token::Uint(U256::from(1_700_000_000u64)),
+            Token::Uint(U256::from(10).pow(18.into())),
+            Token::Uint(U256::from(1_000)),
+        ])],
+    );
+
+    let swap = victim(exact_input, U256::zero()).decode_swap().unwrap();
+    assert_eq!(swap.path, vec![weth(), usdc(), dai(), token()]);
+    assert_eq!(swap.fees, vec![500, 100, 3_000]);
+    assert_eq!(
+        swap.v3_hops(),
+        vec![
+            (weth(), 500, usdc()),
+            (usdc(), 100, dai()),
+            (dai(), 3_000, token())
+        ]
+    );
+    assert_eq!(swap.zero_for_one(usdc(), dai()), Some(true));
+}
+
+#[test]
+fn exact_output_multi_hop_hops_are_in_trade_order() {
+    // swap router02's `exactOutput((path, recipient, amount out, amount in max))`, path is
+    // encoded backwards: token <-(0.3%)- dai <-(0.01%)- usdc <-(0.05%)- weth
+    let exact_output = calldata(
+        "exactOutput",
+        &[ParamType::Tuple(vec![
+            ParamType::Bytes,
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ])],
+        &[Token::Tuple(vec![
+            Token::Bytes(v3_multi_hop_path(
+                &[(token(), 3_000), (dai(), 100), (usdc(), 500)],
+                weth(),
+            )),
+            Token::Address(user()),
+            Token::Uint(U256::from(5_000)),
+            Token::Uint(U256::from(2_000_000)),
+        ])],
+    );
+    let multicall = calldata(
+        "multicall",
+        &[ParamType::Uint(256), bytes_array_param()],
+        &[
+            Token::Uint(U256::from(1_700_000_012u64)),
+            bytes_array(vec![exact_output]),
+        ],
+    );
+
+    let swap = victim(multicall, U256::zero()).decode_swap().unwrap();
+    assert!(swap.exact_output);
+    assert_eq!(
+        swap.v3_hops(),
+        vec![
+            (weth(), 500, usdc()),
+            (usdc(), 100, dai()),
+            (dai(), 3_000, token())
+        ]
+    );
+}
+
+#[test]
+fn v2_swaps_have_no_v3_hops() {
+    let swap = DecodedSwap {
+        path: vec![weth(), token()],
+        fees: vec![],
+        amount_in: U256::from(7_000),
+        amount_out_min: U256::from(3_000),
+        deadline: U256::from(1_700_000_024u64),
+        exact_output: false,
+    };
+    assert!(swap.v3_hops().is_empty());
+}
+
+/// `swapExactETHForTokens(amountOutMin, path, to, deadline)` on a v2 router
+fn swap_exact_eth_for_tokens(path: Vec<Address>) -> Vec<u8> {
+    calldata(
+        "swapExactETHForTokens",
+        &[
+            ParamType::Uint(256),
+            ParamType::Array(Box::new(ParamType::Address)),
+            ParamType::Address,
+            ParamType::Uint(256),
+        ],
+        &[
+            Token::Uint(U256::from(3_000)),
+            Token::Array(path.into_iter().map(Token::Address).collect()),
+            Token::Address(user()),
+            Token::Uint(U256::from(1_700_000_024u64)),
+        ],
+    )
+}
+
+#[test]
+fn eth_in_swaps_map_to_the_weth_pool() {
+    let value = U256::from(10).pow(18.into());
+    let native_eth: Address = "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE"
+        .parse()
+        .unwrap();
+
+    for path in [vec![weth(), token()], vec![native_eth, token()]] {
+        let swap = victim(swap_exact_eth_for_tokens(path), value)
+            .decode_swap()
+            .unwrap();
+        assert_eq!(swap.path, vec![weth(), token()]);
+        assert_eq!(swap.amount_in, value);
+        // victim buys the token on the weth pool, whichever way the pool orders its tokens
+        assert_eq!(swap.zero_for_one(weth(), token()), Some(true));
+        assert_eq!(swap.zero_for_one(token(), weth()), Some(false));
+    }
+}
@@ -0,0 +1,44 @@
+use ethers::types::{Address, U64};
+use strategy::managers::victim_cooldown::VictimCooldown;
+
+fn block(number: u64) -> U64 {
+    U64::from(number)
+}
+
+#[test]
+fn cools_down_after_consecutive_misses_and_lifts_after_cooldown() {
+    let sender = Address::repeat_byte(1);
+    let mut cooldown = VictimCooldown::new(3, 10);
+
+    assert!(!cooldown.record_missed(sender, block(1)));
+    assert!(!cooldown.record_missed(sender, block(2)));
+    assert!(!cooldown.is_cooling(sender));
+
+    assert!(cooldown.record_missed(sender, block(3)));
+    assert!(cooldown.is_cooling(sender));
+    // already cooling, doesn't engage again
+    assert!(!cooldown.record_missed(sender, block(4)));
+
+    assert!(cooldown.poll(block(12)).is_empty());
+    assert_eq!(cooldown.poll(block(13)), vec![sender]);
+    assert!(!cooldown.is_cooling(sender));
+
+    // misses start over once lifted
+    assert!(!cooldown.record_missed(sender, block(14)));
+}
+
+#[test]
+fn landed_bundle_resets_misses() {
+    let sender = Address::repeat_byte(1);
+    let other = Address::repeat_byte(2);
+    let mut cooldown = VictimCooldown::new(2, 10);
+
+    assert!(!cooldown.record_missed(sender, block(1)));
+    assert!(!cooldown.record_missed(other, block(1)));
+    cooldown.record_landed(sender);
+
+    assert!(!cooldown.record_missed(sender, block(2)));
+    assert!(cooldown.record_missed(other, block(2)));
+    assert!(!cooldown.is_cooling(sender));
+    assert!(cooldown.is_cooling(other));
+}
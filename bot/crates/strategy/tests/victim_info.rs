@@ -0,0 +1,439 @@
+use std::collections::BTreeMap;
+
+use ethers::{
+    abi::{encode, short_signature, ParamType, Token},
+    types::{AccountDiff, Address, ChangedType, Diff, Transaction, H256, U256},
+};
+use strategy::{
+    math::{v2_sandwich::get_sandwich_revenue, v3_sandwich::moves_past_round_trip_fee},
+    types::{PoolState, SwapDirection, VictimInfo},
+};
+
+fn slot(slot: u64) -> H256 {
+    H256::from_low_u64_be(slot)
+}
+
+fn word(value: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    H256::from(bytes)
+}
+
+fn changed(from: U256, to: U256) -> Diff<H256> {
+    Diff::Changed(ChangedType {
+        from: word(from),
+        to: word(to),
+    })
+}
+
+fn victim_touching(pool: Address, storage: BTreeMap<H256, Diff<H256>>) -> VictimInfo {
+    let account_diff = AccountDiff {
+        balance: Diff::Same,
+        nonce: Diff::Same,
+        code: Diff::Same,
+        storage,
+    };
+
+    VictimInfo::with_state_diffs(
+        Transaction::default(),
+        BTreeMap::from([(pool, account_diff)]),
+    )
+}
+
+#[test]
+fn parses_v2_reserves_after_victim() {
+    let pool = Address::repeat_byte(0xaa);
+    let pack = |reserve_0: u64, reserve_1: u64| {
+        (U256::from(1_700_000_000u64) << 224) | (U256::from(reserve_1) << 112) | reserve_0.into()
+    };
+    let victim = victim_touching(
+        pool,
+        BTreeMap::from([(slot(8), changed(pack(10, 20), pack(15, 14)))]),
+    );
+
+    assert_eq!(
+        victim.pool_state_after(pool),
+        Some(PoolState::V2 {
+            reserve_0: U256::from(15),
+            reserve_1: U256::from(14),
+        })
+    );
+}
+
+#[test]
+fn parses_v3_slot0_with_negative_tick() {
+    let pool = Address::repeat_byte(0xbb);
+    let sqrt_price_x96 = U256::from(79_228_162_514_264_337_593_543_950_336u128);
+    // tick -10 as a 24 bit two's complement int, followed by `observationIndex`
+    let tick_bits = U256::from(0x00ff_fff6u64);
+    let slot0 = (U256::one() << 184) | (tick_bits << 160) | sqrt_price_x96;
+
+    let victim = victim_touching(
+        pool,
+        BTreeMap::from([(slot(0), changed(U256::zero(), slot0))]),
+    );
+
+    assert_eq!(
+        victim.pool_state_after(pool),
+        Some(PoolState::V3 {
+            sqrt_price_x96,
+            tick: -10,
+            liquidity: None,
+        })
+    );
+}
+
+#[test]
+fn reports_v3_liquidity_when_a_tick_is_crossed() {
+    let pool = Address::repeat_byte(0xbb);
+    let sqrt_price_x96 = U256::from(1) << 96;
+    let victim = victim_touching(
+        pool,
+        BTreeMap::from([
+            (
+                slot(0),
+                changed(U256::zero(), (U256::from(42) << 160) | sqrt_price_x96),
+            ),
+            (slot(4), changed(U256::from(1_000), U256::from(400))),
+        ]),
+    );
+
+    assert_eq!(
+        victim.pool_state_after(pool),
+        Some(PoolState::V3 {
+            sqrt_price_x96,
+            tick: 42,
+            liquidity: Some(400),
+        })
+    );
+}
+
+#[test]
+fn small_v3_moves_dont_cover_round_trip_fees() {
+    let pool = Address::repeat_byte(0xbb);
+    let sqrt_price_x96 = U256::from(1) << 96;
+    // tick -10 (24 bit two's complement) to tick 40
+    let before = (U256::from(0x00ff_fff6u64) << 160) | sqrt_price_x96;
+    let after = (U256::from(40) << 160) | sqrt_price_x96;
+    let victim = victim_touching(pool, BTreeMap::from([(slot(0), changed(before, after))]));
+
+    assert_eq!(victim.get_v3_ticks(pool), Some((-10, 40)));
+    // 50 ticks covers 2 x 0.05% but not 2 x 0.3%
+    assert!(moves_past_round_trip_fee(-10, 40, 500));
+    assert!(!moves_past_round_trip_fee(-10, 40, 3_000));
+    assert_eq!(victim.get_v3_ticks(Address::repeat_byte(0xcc)), None);
+}
+
+#[test]
+fn untouched_pool_has_no_state() {
+    let victim = victim_touching(Address::repeat_byte(0xaa), BTreeMap::new());
+
+    assert_eq!(victim.pool_state_after(Address::repeat_byte(0xaa)), None);
+    assert_eq!(victim.pool_state_after(Address::repeat_byte(0xcc)), None);
+}
+
+#[test]
+fn pool_change_is_largest_relative_move() {
+    let before = PoolState::V2 {
+        reserve_0: U256::from(1_000),
+        reserve_1: U256::from(2_000),
+    };
+    let after = PoolState::V2 {
+        reserve_0: U256::from(1_050),
+        reserve_1: U256::from(1_900),
+    };
+    // reserve_0 moved 5%, reserve_1 moved 5%
+    assert_eq!(after.change_bps(&before), Some(500));
+
+    let v3 = PoolState::V3 {
+        sqrt_price_x96: U256::one() << 96,
+        tick: 0,
+        liquidity: None,
+    };
+    assert_eq!(v3.change_bps(&before), None);
+    assert_eq!(v3.change_bps(&v3), Some(0));
+}
+
+/// Victim calls `swapExactETHForTokens` on a v2 router with `deadline`
+fn victim_with_deadline(deadline: u64) -> VictimInfo {
+    let params = [
+        ParamType::Uint(256),
+        ParamType::Array(Box::new(ParamType::Address)),
+        ParamType::Address,
+        ParamType::Uint(256),
+    ];
+    let mut input = short_signature("swapExactETHForTokens", &params).to_vec();
+    input.extend(encode(&[
+        Token::Uint(U256::from(1_000)),
+        Token::Array(vec![
+            Token::Address(Address::repeat_byte(0xaa)),
+            Token::Address(Address::repeat_byte(0xbb)),
+        ]),
+        Token::Address(Address::repeat_byte(0xcc)),
+        Token::Uint(deadline.into()),
+    ]));
+
+    VictimInfo::new(Transaction {
+        input: input.into(),
+        value: U256::from(10).pow(18.into()),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn victim_past_deadline_is_expired() {
+    let victim = victim_with_deadline(1_700_000_000);
+
+    // routers accept a swap in the block at its deadline
+    assert!(!victim.is_expired_at(U256::from(1_699_999_988u64)));
+    assert!(!victim.is_expired_at(U256::from(1_700_000_000u64)));
+    assert!(victim.is_expired_at(U256::from(1_700_000_012u64)));
+
+    // no decodable deadline, let the simulation decide
+    assert!(!VictimInfo::new(Transaction::default()).is_expired_at(U256::MAX));
+}
+
+#[test]
+fn decodes_v3_exact_output_single_limits() {
+    let weth: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap();
+    let token = Address::repeat_byte(0xbb);
+
+    let params = ParamType::Tuple(vec![
+        ParamType::Address,
+        ParamType::Address,
+        ParamType::Uint(24),
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(160),
+    ]);
+    let mut input = short_signature("exactOutputSingle", &[params]).to_vec();
+    input.extend(encode(&[Token::Tuple(vec![
+        Token::Address(weth),
+        Token::Address(token),
+        Token::Uint(3_000.into()),
+        Token::Address(Address::repeat_byte(0xcc)),
+        Token::Uint(U256::MAX),
+        // amount out, amount in max
+        Token::Uint(U256::from(5_000)),
+        Token::Uint(U256::from(7)),
+        Token::Uint(U256::zero()),
+    ])]));
+    let victim = VictimInfo::new(Transaction {
+        input: input.into(),
+        ..Default::default()
+    });
+
+    assert_eq!(
+        victim.get_exact_output_limits(token, Some(3_000)),
+        Some((U256::from(7), U256::from(5_000)))
+    );
+    // swapped through another fee tier's pool
+    assert_eq!(victim.get_exact_output_limits(token, Some(500)), None);
+    // exact input victims have no exact output limits
+    assert_eq!(
+        victim_with_deadline(0).get_exact_output_limits(token, None),
+        None
+    );
+}
+
+fn pack_reserves(reserve_0: u64, reserve_1: u64) -> U256 {
+    (U256::from(reserve_1) << 112) | reserve_0.into()
+}
+
+/// Victim swapping on a weth (token0) / token (token1) pool, `path` decides which way
+fn victim_on_pool(
+    pool: Address,
+    path: [Address; 2],
+    reserves_before: (u64, u64),
+    reserves_after: (u64, u64),
+) -> VictimInfo {
+    let path = Token::Array(path.into_iter().map(Token::Address).collect());
+    let to = Token::Address(Address::repeat_byte(0xcc));
+    let deadline = Token::Uint(U256::MAX);
+    let input = if reserves_after.0 > reserves_before.0 {
+        let params = [
+            ParamType::Uint(256),
+            ParamType::Array(Box::new(ParamType::Address)),
+            ParamType::Address,
+            ParamType::Uint(256),
+        ];
+        let mut input = short_signature("swapExactETHForTokens", &params).to_vec();
+        input.extend(encode(&[Token::Uint(U256::zero()), path, to, deadline]));
+        input
+    } else {
+        let params = [
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Array(Box::new(ParamType::Address)),
+            ParamType::Address,
+            ParamType::Uint(256),
+        ];
+        let mut input = short_signature("swapExactTokensForETH", &params).to_vec();
+        let amount_in = Token::Uint((reserves_after.1 - reserves_before.1).into());
+        input.extend(encode(&[
+            amount_in,
+            Token::Uint(U256::zero()),
+            path,
+            to,
+            deadline,
+        ]));
+        input
+    };
+
+    let mut victim = victim_touching(
+        pool,
+        BTreeMap::from([(
+            slot(8),
+            changed(
+                pack_reserves(reserves_before.0, reserves_before.1),
+                pack_reserves(reserves_after.0, reserves_after.1),
+            ),
+        )]),
+    );
+    victim.tx.input = input.into();
+    victim
+}
+
+#[test]
+fn frontrun_follows_the_victims_direction_on_the_same_pool() {
+    let pool = Address::repeat_byte(0xaa);
+    let weth = Address::repeat_byte(0x11);
+    let token = Address::repeat_byte(0xbb);
+    let (reserve_weth, reserve_token) = (1_000_000_000_000u64, 2_000_000_000_000u64);
+
+    let buyer = victim_on_pool(
+        pool,
+        [weth, token],
+        (reserve_weth, reserve_token),
+        (
+            reserve_weth + 10_000_000_000,
+            reserve_token - 19_743_160_687,
+        ),
+    );
+    let seller = victim_on_pool(
+        pool,
+        [token, weth],
+        (reserve_weth, reserve_token),
+        (reserve_weth - 4_960_273_038, reserve_token + 10_000_000_000),
+    );
+
+    assert_eq!(
+        buyer.get_swap_direction(pool, token, weth),
+        Some(SwapDirection::WethToToken)
+    );
+    assert_eq!(
+        seller.get_swap_direction(pool, token, weth),
+        Some(SwapDirection::TokenToWeth)
+    );
+
+    // frontrun input token's reserve comes first
+    let buy = buyer
+        .get_v2_sandwich_reserves(pool, token, weth, SwapDirection::WethToToken)
+        .unwrap();
+    assert_eq!(
+        buy,
+        (
+            U256::from(10_000_000_000u64),
+            U256::from(reserve_weth),
+            U256::from(reserve_token)
+        )
+    );
+    let sell = seller
+        .get_v2_sandwich_reserves(pool, token, weth, SwapDirection::TokenToWeth)
+        .unwrap();
+    assert_eq!(
+        sell,
+        (
+            U256::from(10_000_000_000u64),
+            U256::from(reserve_token),
+            U256::from(reserve_weth)
+        )
+    );
+
+    // frontrunning against the victim's trade would guarantee a loss, never oriented that way
+    assert_eq!(
+        buyer.get_v2_sandwich_reserves(pool, token, weth, SwapDirection::TokenToWeth),
+        None
+    );
+    assert_eq!(
+        seller.get_v2_sandwich_reserves(pool, token, weth, SwapDirection::WethToToken),
+        None
+    );
+
+    // both orientations profit from the victim's price impact
+    for (victim_in, reserve_in, reserve_out) in [buy, sell] {
+        let frontrun_in = victim_in / 2;
+        assert!(
+            get_sandwich_revenue(frontrun_in, victim_in, reserve_in, reserve_out, 30)
+                > U256::zero()
+        );
+    }
+}
+
+#[test]
+fn aggregator_swaps_are_read_from_state_diffs_alone() {
+    let pool = Address::repeat_byte(0xaa);
+    let weth = Address::repeat_byte(0x11);
+    let token = Address::repeat_byte(0xbb);
+    let (reserve_weth, reserve_token) = (1_000_000_000_000u64, 2_000_000_000_000u64);
+
+    // calldata that happens to decode as a token sell, but the pool's weth reserve grew
+    let seller = victim_on_pool(
+        pool,
+        [token, weth],
+        (reserve_weth, reserve_token),
+        (reserve_weth - 4_960_273_038, reserve_token + 10_000_000_000),
+    );
+    let mut victim = victim_on_pool(
+        pool,
+        [weth, token],
+        (reserve_weth, reserve_token),
+        (
+            reserve_weth + 10_000_000_000,
+            reserve_token - 19_743_160_687,
+        ),
+    )
+    .via_aggregator();
+    victim.tx.input = seller.tx.input;
+
+    assert!(victim.is_aggregator_swap());
+    assert!(victim.decode_swap().is_none());
+    assert_eq!(
+        victim.get_swap_direction(pool, token, weth),
+        Some(SwapDirection::WethToToken)
+    );
+    assert_eq!(
+        victim
+            .get_v2_sandwich_reserves(pool, token, weth, SwapDirection::WethToToken)
+            .map(|(victim_in, _, _)| victim_in),
+        Some(U256::from(10_000_000_000u64))
+    );
+}
+
+#[test]
+fn swap_direction_falls_back_to_reserve_diffs() {
+    let pool = Address::repeat_byte(0xaa);
+    let pack = |reserve_0: u64, reserve_1: u64| pack_reserves(reserve_0, reserve_1);
+    // token (0x01..) is token0 here, weth is token1
+    let token = Address::repeat_byte(0x01);
+    let weth = Address::repeat_byte(0x11);
+
+    // undecodable calldata, token0 reserve grew so the victim sold the token
+    let victim = victim_touching(
+        pool,
+        BTreeMap::from([(slot(8), changed(pack(100, 200), pack(110, 182)))]),
+    );
+    assert_eq!(
+        victim.get_swap_direction(pool, token, weth),
+        Some(SwapDirection::TokenToWeth)
+    );
+
+    assert_eq!(SwapDirection::new(true, true), SwapDirection::WethToToken);
+    assert_eq!(SwapDirection::new(false, false), SwapDirection::WethToToken);
+    assert_eq!(SwapDirection::new(false, true), SwapDirection::TokenToWeth);
+    assert_eq!(SwapDirection::new(true, false), SwapDirection::TokenToWeth);
+}
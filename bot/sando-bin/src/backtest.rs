@@ -0,0 +1,104 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use artemis_core::types::Strategy;
+use ethers::{providers::Middleware, types::I256};
+use log::info;
+use strategy::{
+    bot::SandoBot,
+    types::{Action, Event},
+};
+
+/// Tally of what the strategy found while replaying recorded events
+#[derive(Debug, Default)]
+pub struct BacktestReport {
+    pub blocks_replayed: u64,
+    pub txs_replayed: u64,
+    pub sandwiches_found: u64,
+    pub backruns_found: u64,
+    /// Summed expected net profit of every sandwich and backrun found (in wei)
+    pub total_net_profit: I256,
+    /// Most profitable sandwich or backrun found (in wei)
+    pub best_net_profit: I256,
+}
+
+impl BacktestReport {
+    fn record_action(&mut self, action: Action) {
+        match action {
+            Action::SubmitBundle { net_profits, .. } => {
+                for net_profit in net_profits {
+                    self.sandwiches_found += 1;
+                    self.total_net_profit += net_profit;
+                    self.best_net_profit = self.best_net_profit.max(net_profit);
+                }
+            }
+            Action::SubmitPrivateTx { net_profit, .. } => {
+                self.backruns_found += 1;
+                self.total_net_profit += net_profit;
+                self.best_net_profit = self.best_net_profit.max(net_profit);
+            }
+        }
+    }
+
+    /// Log a summary of the backtest
+    pub fn log_summary(&self) {
+        info!("==================== backtest summary ====================");
+        info!("blocks replayed  : {}", self.blocks_replayed);
+        info!("txs replayed     : {}", self.txs_replayed);
+        info!("sandwiches found : {}", self.sandwiches_found);
+        info!("backruns found   : {}", self.backruns_found);
+        info!(
+            "total net profit : {} eth",
+            self.total_net_profit.as_i128() as f64 / 1e18
+        );
+        info!(
+            "best net profit  : {} eth",
+            self.best_net_profit.as_i128() as f64 / 1e18
+        );
+    }
+}
+
+/// Read recorded events from a json lines file (one serialized `Event` per line)
+pub fn load_events<P: AsRef<Path>>(path: P) -> Result<Vec<Event>> {
+    let file = File::open(path.as_ref())
+        .map_err(|e| anyhow!("Failed to open {}: {}", path.as_ref().display(), e))?;
+
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(l) if l.trim().is_empty()))
+        .map(|(i, line)| {
+            let line = line?;
+            serde_json::from_str(&line)
+                .map_err(|e| anyhow!("Failed to parse event on line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Feed recorded events through the bot in order
+/// note: every `NewBlock` moves the bot's block manager, so state diffs and simulations for the
+/// txs that follow are pinned to that historical block (the provider must be an archive node)
+pub async fn run_backtest<M: Middleware + 'static>(
+    bot: &mut SandoBot<M>,
+    events: Vec<Event>,
+) -> BacktestReport {
+    let mut report = BacktestReport::default();
+
+    for event in events {
+        match &event {
+            Event::NewBlock(_) => report.blocks_replayed += 1,
+            Event::NewTransaction(_) => report.txs_replayed += 1,
+            Event::NewTransactions(txs) => report.txs_replayed += txs.len() as u64,
+        }
+
+        if let Some(action) = bot.process_event(event).await {
+            report.record_action(action);
+        }
+    }
+
+    report
+}
@@ -0,0 +1,41 @@
+use std::{env, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use artemis_core::types::Strategy;
+use ethers::providers::{Provider, Ws};
+use rusty_sando::{
+    backtest::{load_events, run_backtest},
+    config::Config,
+    initialization::setup_logger,
+};
+use strategy::{bot::SandoBot, types::BotMode};
+
+/// Replay recorded events through the strategy and report the sandwiches it would have found
+///
+/// Usage: `cargo run --release --bin backtest -- <events.jsonl>`
+#[tokio::main]
+async fn main() -> Result<()> {
+    let config = Config::read_from_dotenv().await?;
+    setup_logger(config.log_format, config.log_rejections)?;
+
+    let events_path = env::args()
+        .nth(1)
+        .ok_or(anyhow!("Usage: backtest <events.jsonl>"))?;
+    let events = load_events(&events_path)?;
+
+    // Setup archive node provider (backtests are replayed against the primary endpoint only)
+    let ws = Ws::connect(config.rpc_endpoints[0].clone()).await?;
+    let provider = Arc::new(Provider::new(ws));
+
+    // Never submit anything while backtesting
+    let mut configs = config.strat_config();
+    configs.mode = BotMode::DryRun;
+
+    let mut bot = SandoBot::new(provider, configs);
+    bot.sync_state().await?;
+
+    let report = run_backtest(&mut bot, events).await;
+    report.log_summary();
+
+    Ok(())
+}
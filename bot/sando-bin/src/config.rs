@@ -1,20 +1,139 @@
 use dotenv::dotenv;
 use reqwest::Url;
-use std::{env, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    str::FromStr,
+    time::Duration,
+};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
+use artemis_core::{
+    executors::public_mempool::PublicFallback, utilities::circuit_breaker::CircuitBreaker,
+};
 use ethers::{
-    signers::LocalWallet,
-    types::{Address, U64},
+    signers::{LocalWallet, Signer},
+    types::{Address, U256, U64},
 };
+use strategy::{
+    managers::block_manager::MAX_BASE_FEE_SAFETY_MARGIN_BPS,
+    math::ordering::OrderingStrategy,
+    signer::{SearcherSigner, SignerConfig, SignerKind},
+    types::{
+        AccessListMode, BotMode, LogFormat, OpposingVictims, PaymentStrategy, SandoGasProfile,
+        SandoRotation, SimStateSource, StateDiffMethod, StratConfig, VictimPosition,
+    },
+};
+
+/// Chain the searcher's txs are signed for (sando contracts and pools are mainnet only)
+const CHAIN_ID: u64 = 1;
 
 pub struct Config {
-    pub searcher_signer: LocalWallet,
+    pub searcher_signer: SearcherSigner,
     pub sando_inception_block: U64,
-    pub sando_address: Address,
+    pub sando_addresses: Vec<Address>,
+    pub sando_gas_profiles: HashMap<Address, SandoGasProfile>,
     pub bundle_signer: LocalWallet,
-    pub wss_rpc: Url,
+    pub rpc_endpoints: Vec<Url>,
+    /// Pending txs come from here instead of `rpc_endpoints` if set
+    pub mempool_endpoints: Option<Vec<Url>>,
+    pub rpc_timeout_ms: u64,
+    /// Share of rpc requests (0.0 to 1.0) that may fail over `rpc_breaker_window_secs` before
+    /// processing pauses for `rpc_breaker_cooldown_secs` (1.0 = never)
+    pub rpc_breaker_error_rate: f64,
+    pub rpc_breaker_window_secs: u64,
+    pub rpc_breaker_cooldown_secs: u64,
+    /// Requests in the window before its error rate can trip the breaker
+    pub rpc_breaker_min_requests: usize,
+    /// The bots' calls and storage reads arriving within this many ms go out as one batch
+    pub rpc_batch_window_ms: Option<u64>,
+    pub relay_urls: Vec<Url>,
+    pub bloxroute_auth_header: Option<String>,
+    pub bloxroute_url: Url,
+    pub bid_fraction: f64,
+    pub competition_level: f64,
+    pub min_tip: U256,
+    pub max_tip: U256,
+    pub bid_escalation_step: f64,
+    pub max_bid_fraction: f64,
+    pub metrics_port: u16,
+    pub health_stale_after_secs: u64,
+    pub metrics_snapshot_path: Option<String>,
+    pub opportunity_log_path: Option<String>,
+    pub alert_webhook: Option<String>,
+    pub max_window_loss: Option<U256>,
+    pub min_weth_inventory: Option<U256>,
+    pub min_searcher_balance: Option<U256>,
+    pub loss_window_blocks: u64,
+    pub loss_cooldown_blocks: Option<u64>,
+    pub victim_max_misses: Option<u32>,
+    pub victim_cooldown_blocks: u64,
+    pub min_victim_swap_value: U256,
+    pub min_pool_liquidity: U256,
+    pub known_searchers: HashSet<Address>,
+    pub pool_whitelist: Option<HashSet<Address>>,
+    pub stable_tokens: Option<HashSet<Address>>,
+    pub rebasing_tokens: Option<HashSet<Address>>,
+    pub aggregator_routers: Option<HashSet<Address>>,
+    pub rebase_sync_strikes: u32,
+    pub max_victim_priority_fee: U256,
+    pub max_victim_tip_bps: Option<u64>,
+    pub max_position_weth: Option<U256>,
+    pub max_frontrun_price_impact_bps: Option<u64>,
+    pub tx_batch_window_ms: Option<u64>,
+    pub max_bundles_per_block: u32,
+    pub rank_bundles_ms_before_block: Option<u64>,
+    pub max_bundle_gas_per_block: Option<u64>,
+    pub max_block_gas_fraction: Option<f64>,
+    pub victim_timeout_ms: Option<u64>,
+    pub max_sim_gas: Option<u64>,
+    pub sync_chunk_blocks: Option<u64>,
+    pub sync_concurrency: usize,
+    pub target_block_offsets: Vec<u64>,
+    pub inclusion_check_blocks: u64,
+    pub min_replacement_gain_bps: u64,
+    pub parked_tx_ttl_blocks: u64,
+    pub max_concurrent_sims: usize,
+    pub weth_address: Option<Address>,
+    pub mode: BotMode,
+    pub dry_run_output: String,
+    pub simulate_bundles: bool,
+    pub relay_stats_interval_secs: u64,
+    pub public_fallback: Option<PublicFallback>,
+    pub log_format: LogFormat,
+    pub log_rejections: bool,
+    pub sim_state_source: SimStateSource,
+    pub stale_state_guard: bool,
+    pub state_diff_method: StateDiffMethod,
+    pub access_list_mode: AccessListMode,
+    pub state_diff_storage_fallback: bool,
+    pub payment_strategy: PaymentStrategy,
+    pub enable_private_backruns: bool,
+    pub enable_backrun_arb: bool,
+    pub ordering_strategies: Vec<OrderingStrategy>,
+    pub frontrun_only_haircut_bps: u64,
+    pub prioritize_profitable_pools: bool,
+    pub enable_bundle_merging: bool,
+    pub opposing_victims: OpposingVictims,
+    pub min_time_budget_ms: u64,
+    pub gas_limit_multiplier: f64,
+    pub backrun_min_out_buffer_bps: u64,
+    pub base_fee_safety_margin_bps: u64,
+    pub pool_update_threshold_bps: Option<u64>,
+    pub track_pool_prices: bool,
+    pub hot_pool_change_bps: Option<u64>,
+    pub cold_pool_after_blocks: Option<u64>,
+    pub refresh_hot_pools: bool,
+    pub max_pool_divergence_rate: Option<f64>,
+    pub exclude_fee_switch_pools: bool,
+    pub preceding_txs_min_profit: Option<U256>,
+    pub victim_position_assumption: VictimPosition,
+    pub sando_rotation: SandoRotation,
+    pub rng_seed: Option<u64>,
     pub discord_webhook: String,
+    pub file_config: Option<StratConfig>,
+    /// Strategy variants that run alongside the main strategy on its synced pools
+    pub variant_configs: Vec<StratConfig>,
 }
 
 impl Config {
@@ -25,35 +144,873 @@ impl Config {
             env::var(var).map_err(|_| anyhow!("Required environment variable \"{}\" not set", var))
         };
 
-        let searcher_signer = get_env("SEARCHER_PRIVATE_KEY")?
-            .parse::<LocalWallet>()
-            .map_err(|_| anyhow!("Failed to parse \"SEARCHER_PRIVATE_KEY\""))?;
+        // strategy parameters can come from a toml file instead of env (see
+        // `StratConfig::from_file`), env only configures the binary then
+        //
+        // the searcher's tx signing key is a `SEARCHER_PRIVATE_KEY` by default, `SEARCHER_SIGNER`
+        // loads it from a keystore, a ledger or aws kms instead (see `SignerKind`), relays are
+        // still authenticated with the separate `FLASHBOTS_AUTH_KEY`
+        let signer_kind = get_optional_env("SEARCHER_SIGNER", SignerKind::PrivateKey)?;
+        let external_signer = match signer_kind {
+            SignerKind::PrivateKey => None,
+            kind => Some(SignerConfig::from_env(kind)?.build(CHAIN_ID).await?),
+        };
+        let file_config = match (env::var("CONFIG_FILE").ok(), external_signer.clone()) {
+            (Some(path), Some(signer)) => Some(StratConfig::from_file_with_signer(path, signer)?),
+            (Some(path), None) => Some(StratConfig::from_file(path)?),
+            (None, _) => None,
+        };
 
-        let sando_inception_block = get_env("SANDWICH_INCEPTION_BLOCK")?
-            .parse::<u64>()
-            .map(U64::from)
-            .map_err(|_| anyhow!("Failed to parse \"SANDWICH_INCEPTION_BLOCK\" into u64"))?;
+        let (searcher_signer, sando_inception_block, sando_addresses) = match &file_config {
+            Some(file_config) => (
+                file_config.searcher_signer.clone(),
+                file_config.sando_inception_block,
+                file_config.sando_addresses.clone(),
+            ),
+            None => {
+                let searcher_signer = match external_signer {
+                    Some(signer) => signer,
+                    None => {
+                        SignerConfig::from_env(SignerKind::PrivateKey)?
+                            .build(CHAIN_ID)
+                            .await?
+                    }
+                };
+                let (sando_inception_block, sando_addresses) = read_sando_env()?;
+                (searcher_signer, sando_inception_block, sando_addresses)
+            }
+        };
 
-        let sando_address = Address::from_str(&get_env("SANDWICH_CONTRACT")?)
-            .map_err(|_| anyhow!("Failed to parse \"SANDWICH_CONTRACT\""))?;
+        // comma separated toml config files, each runs another strategy on the same pools (every
+        // variant needs its own searcher key, they would otherwise race for the same nonces)
+        let variant_configs = env::var("VARIANT_CONFIG_FILES")
+            .ok()
+            .map(|paths| {
+                paths
+                    .split(',')
+                    .map(|path| StratConfig::from_file(path.trim()))
+                    .collect::<Result<Vec<StratConfig>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let mut searchers = HashSet::from([searcher_signer.address()]);
+        for variant in &variant_configs {
+            ensure!(
+                searchers.insert(variant.searcher_signer.address()),
+                "Variant configs need a searcher key of their own, {:?} is already used",
+                variant.searcher_signer.address()
+            );
+        }
 
         let bundle_signer = get_env("FLASHBOTS_AUTH_KEY")?
             .parse::<LocalWallet>()
             .map_err(|_| anyhow!("Failed to parse \"FLASHBOTS_AUTH_KEY\""))?;
 
-        let wss_rpc = get_env("WSS_RPC")?
-            .parse()
-            .map_err(|_| anyhow!("Failed to parse \"WSS_RPC\""))?;
+        // comma separated list of websocket endpoints, later ones are backups that requests
+        // fail over to (in order) when the active endpoint errors or takes longer than
+        // `rpc_timeout_ms` to answer
+        let rpc_endpoints = get_env("WSS_RPC")?
+            .split(',')
+            .map(|url| {
+                url.trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Failed to parse \"WSS_RPC\" entry {}", url))
+            })
+            .collect::<Result<Vec<Url>>>()?;
+        let rpc_timeout_ms = get_optional_env("RPC_TIMEOUT_MS", 10_000)?;
+
+        // pause processing once most rpc requests fail (outage or rate limit ban) instead of
+        // hammering the provider
+        let rpc_breaker_error_rate = get_optional_env("RPC_BREAKER_ERROR_RATE", 0.5)?;
+        ensure!(
+            (0.0..=1.0).contains(&rpc_breaker_error_rate),
+            "\"RPC_BREAKER_ERROR_RATE\" must be between 0 and 1"
+        );
+        let rpc_breaker_window_secs = get_optional_env("RPC_BREAKER_WINDOW_SECS", 30)?;
+        let rpc_breaker_cooldown_secs = get_optional_env("RPC_BREAKER_COOLDOWN_SECS", 30)?;
+        let rpc_breaker_min_requests = get_optional_env("RPC_BREAKER_MIN_REQUESTS", 20)?;
+
+        // coalesce the bots' state reads arriving within this many ms (unset = one request each)
+        let rpc_batch_window_ms = env::var("RPC_BATCH_WINDOW_MS")
+            .ok()
+            .map(|ms| {
+                ms.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"RPC_BATCH_WINDOW_MS\""))
+            })
+            .transpose()?;
+
+        // optional websocket endpoints (same failover list format) that only the mempool
+        // subscription uses, so a fast mempool feed can sit in front of a slower archive node
+        let mempool_endpoints = env::var("MEMPOOL_WSS_RPC")
+            .ok()
+            .map(|urls| {
+                urls.split(',')
+                    .map(|url| {
+                        url.trim().parse().map_err(|_| {
+                            anyhow!("Failed to parse \"MEMPOOL_WSS_RPC\" entry {}", url)
+                        })
+                    })
+                    .collect::<Result<Vec<Url>>>()
+            })
+            .transpose()?;
+
+        // optional comma separated list of relays, defaults to flashbots
+        let relay_urls = env::var("RELAY_URLS")
+            .unwrap_or("https://relay.flashbots.net".to_string())
+            .split(',')
+            .map(|url| {
+                url.trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Failed to parse \"RELAY_URLS\" entry {}", url))
+            })
+            .collect::<Result<Vec<Url>>>()?;
+
+        // bundles are also sent to bloXroute when an auth header is set
+        let bloxroute_auth_header = env::var("BLOXROUTE_AUTH_HEADER").ok();
+        let bloxroute_url = env::var("BLOXROUTE_URL")
+            .unwrap_or("https://mev.api.blxrbdn.com".to_string())
+            .trim()
+            .parse::<Url>()
+            .map_err(|_| anyhow!("Failed to parse \"BLOXROUTE_URL\""))?;
+
+        // optional bidding knobs, tips are in wei
+        let bid_fraction = get_optional_env("BID_FRACTION", 0.9)?;
+        let competition_level = get_optional_env("COMPETITION_LEVEL", 0.0)?;
+        let min_tip = U256::from(get_optional_env("MIN_TIP", 0u128)?);
+        let max_tip = U256::from(get_optional_env("MAX_TIP", u128::MAX)?);
+        // bids around contested victims are raised step by step (off by default)
+        let bid_escalation_step = get_optional_env("BID_ESCALATION_STEP", 0.0)?;
+        let max_bid_fraction = get_optional_env("MAX_BID_FRACTION", 0.95)?;
+
+        let metrics_port = get_optional_env("METRICS_PORT", 9090)?;
+        let health_stale_after_secs = get_optional_env("HEALTH_STALE_AFTER_SECS", 30)?;
+        // metrics are written here on shutdown (counters live in memory otherwise)
+        let metrics_snapshot_path = env::var("METRICS_SNAPSHOT_PATH").ok();
+        // every sized opportunity (and whether it landed) is appended here as json lines
+        let opportunity_log_path = env::var("OPPORTUNITY_LOG_PATH").ok();
+        // every landed sandwich is posted here as json (a discord webhook gets a chat message)
+        let alert_webhook = env::var("ALERT_WEBHOOK")
+            .ok()
+            .map(|url| {
+                Url::parse(url.trim())
+                    .map(String::from)
+                    .map_err(|_| anyhow!("Failed to parse \"ALERT_WEBHOOK\""))
+            })
+            .transpose()?;
+
+        // stop submitting once landed bundles lose more than this (in wei) over the window
+        let max_window_loss = env::var("MAX_WINDOW_LOSS")
+            .ok()
+            .map(|loss| {
+                loss.trim()
+                    .parse::<u128>()
+                    .map(U256::from)
+                    .map_err(|_| anyhow!("Failed to parse \"MAX_WINDOW_LOSS\""))
+            })
+            .transpose()?;
+
+        // alert once a sando contract's weth or the searcher's eth drops below these (in wei)
+        let min_weth_inventory = env::var("MIN_WETH_INVENTORY")
+            .ok()
+            .map(|floor| {
+                floor
+                    .trim()
+                    .parse::<u128>()
+                    .map(U256::from)
+                    .map_err(|_| anyhow!("Failed to parse \"MIN_WETH_INVENTORY\""))
+            })
+            .transpose()?;
+        let min_searcher_balance = env::var("MIN_SEARCHER_BALANCE")
+            .ok()
+            .map(|floor| {
+                floor
+                    .trim()
+                    .parse::<u128>()
+                    .map(U256::from)
+                    .map_err(|_| anyhow!("Failed to parse \"MIN_SEARCHER_BALANCE\""))
+            })
+            .transpose()?;
+        let loss_window_blocks = get_optional_env("LOSS_WINDOW_BLOCKS", 300)?;
+        // halted until restarted if unset
+        let loss_cooldown_blocks = env::var("LOSS_COOLDOWN_BLOCKS")
+            .ok()
+            .map(|blocks| {
+                blocks
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"LOSS_COOLDOWN_BLOCKS\""))
+            })
+            .transpose()?;
+
+        // stop evaluating a sender's txs for a while once this many bundles around them missed
+        let victim_max_misses = env::var("VICTIM_MAX_MISSES")
+            .ok()
+            .map(|misses| {
+                misses
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| anyhow!("Failed to parse \"VICTIM_MAX_MISSES\""))
+            })
+            .transpose()?;
+        let victim_cooldown_blocks = get_optional_env("VICTIM_COOLDOWN_BLOCKS", 50)?;
+
+        // victims swapping in less than this (in wei) are ignored
+        let min_victim_swap_value = U256::from(get_optional_env("MIN_VICTIM_SWAP_VALUE", 0u128)?);
+
+        // pools with less weth liquidity than this (in wei) are never sandwiched
+        let min_pool_liquidity = U256::from(get_optional_env("MIN_POOL_LIQUIDITY", 0u128)?);
+
+        // txs from or to these addresses (comma separated) are assumed to be other searchers
+        let known_searchers = get_optional_address_set_env("KNOWN_SEARCHERS")?.unwrap_or_default();
+
+        // only these pools (comma separated) are loaded and sandwiched, unset = every pool
+        let pool_whitelist = get_optional_address_set_env("POOL_WHITELIST")?;
+
+        // pools between two of these tokens (comma separated) are skipped, default USDC/USDT/DAI
+        let stable_tokens = get_optional_address_set_env("STABLE_TOKENS")?;
+
+        // pools of these tokens (comma separated) are skipped, default AMPL/stETH
+        let rebasing_tokens = get_optional_address_set_env("REBASING_TOKENS")?;
+
+        // swaps through these routers (comma separated) are read from the pools they touch,
+        // default 1inch/0x/CoW
+        let aggregator_routers = get_optional_address_set_env("AGGREGATOR_ROUTERS")?;
+        // tokens whose v2 pools sync reserves without a swap this often are skipped too (0 = off)
+        let rebase_sync_strikes = get_optional_env("REBASE_SYNC_STRIKES", 2)?;
+
+        // victims paying a higher priority fee than this (in wei) are assumed to be searchers
+        let max_victim_priority_fee =
+            U256::from(get_optional_env("MAX_VICTIM_PRIORITY_FEE", u128::MAX)?);
+
+        // victims that could tip more than this many bps of their swap are bait (unset = off)
+        let max_victim_tip_bps = env::var("MAX_VICTIM_TIP_BPS")
+            .ok()
+            .map(|bps| {
+                bps.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"MAX_VICTIM_TIP_BPS\""))
+            })
+            .transpose()?;
+
+        // most weth (in wei) a single frontrun spends (unset = the sando contract's whole balance)
+        let max_position_weth = env::var("MAX_POSITION_WETH")
+            .ok()
+            .map(|wei| {
+                wei.trim()
+                    .parse::<u128>()
+                    .map(U256::from)
+                    .map_err(|_| anyhow!("Failed to parse \"MAX_POSITION_WETH\""))
+            })
+            .transpose()?;
+
+        // furthest (in bps) a frontrun may move its pool's price (unset = uncapped)
+        let max_frontrun_price_impact_bps = env::var("MAX_FRONTRUN_PRICE_IMPACT_BPS")
+            .ok()
+            .map(|bps| {
+                bps.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"MAX_FRONTRUN_PRICE_IMPACT_BPS\""))
+            })
+            .transpose()?;
+
+        // group pending txs arriving within this many ms into one event (unset = one tx per event)
+        let tx_batch_window_ms = env::var("TX_BATCH_WINDOW_MS")
+            .ok()
+            .map(|ms| {
+                ms.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"TX_BATCH_WINDOW_MS\""))
+            })
+            .transpose()?;
+
+        // bundles after the first for a block only land if every earlier one does
+        let max_bundles_per_block = get_optional_env("MAX_BUNDLES_PER_BLOCK", 1)?;
+
+        // optionally hold sandwiches until this many ms before the block and only send the most
+        // profitable ones that fit the block's bundle and gas budget
+        let rank_bundles_ms_before_block = env::var("RANK_BUNDLES_MS_BEFORE_BLOCK")
+            .ok()
+            .map(|ms| {
+                ms.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"RANK_BUNDLES_MS_BEFORE_BLOCK\""))
+            })
+            .transpose()?;
+        let max_bundle_gas_per_block = env::var("MAX_BUNDLE_GAS_PER_BLOCK")
+            .ok()
+            .map(|gas| {
+                gas.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"MAX_BUNDLE_GAS_PER_BLOCK\""))
+            })
+            .transpose()?;
+        let max_block_gas_fraction = env::var("MAX_BLOCK_GAS_FRACTION")
+            .ok()
+            .map(|fraction| {
+                fraction
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| anyhow!("Failed to parse \"MAX_BLOCK_GAS_FRACTION\""))
+            })
+            .transpose()?;
+        if let Some(fraction) = max_block_gas_fraction {
+            ensure!(
+                (0.0..=1.0).contains(&fraction),
+                "\"MAX_BLOCK_GAS_FRACTION\" must be between 0 and 1"
+            );
+        }
+        // optionally give up on a single victim after this many ms of tracing or evaluating it
+        let victim_timeout_ms = env::var("VICTIM_TIMEOUT_MS")
+            .ok()
+            .map(|ms| {
+                ms.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"VICTIM_TIMEOUT_MS\""))
+            })
+            .transpose()?;
+        // optionally stop simulating a pending tx once it burned this much gas
+        let max_sim_gas = env::var("MAX_SIM_GAS")
+            .ok()
+            .map(|gas| {
+                gas.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"MAX_SIM_GAS\""))
+            })
+            .transpose()?;
+        // optionally discover pools from factory logs this many blocks at a time
+        let sync_chunk_blocks = env::var("SYNC_CHUNK_BLOCKS")
+            .ok()
+            .map(|blocks| {
+                blocks
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"SYNC_CHUNK_BLOCKS\""))
+            })
+            .transpose()?;
+        // pools whose tokens and reserves are fetched at once while discovering pools
+        let sync_concurrency = get_optional_env("SYNC_CONCURRENCY", 10)?;
+        ensure!(
+            sync_concurrency > 0,
+            "\"SYNC_CONCURRENCY\" must be at least 1"
+        );
+
+        // comma separated blocks past the next one to also send each bundle for (e.g. "0,1")
+        let mut target_block_offsets = env::var("TARGET_BLOCK_OFFSETS")
+            .unwrap_or("0".to_string())
+            .split(',')
+            .map(|offset| {
+                offset.trim().parse::<u64>().map_err(|_| {
+                    anyhow!("Failed to parse \"TARGET_BLOCK_OFFSETS\" entry {}", offset)
+                })
+            })
+            .collect::<Result<Vec<u64>>>()?;
+        target_block_offsets.sort();
+        target_block_offsets.dedup();
+        // blocks past a bundle's last target that its receipts are still looked up for
+        let inclusion_check_blocks = get_optional_env("INCLUSION_CHECK_BLOCKS", 2)?;
+
+        // a bundle on an already bundled pool must net this much more (in bps) to replace it
+        let min_replacement_gain_bps = get_optional_env("MIN_REPLACEMENT_GAIN_BPS", 1_000)?;
+
+        // txs that can't pay the next base fee are re-evaluated for this many blocks
+        let parked_tx_ttl_blocks = get_optional_env("PARKED_TX_TTL_BLOCKS", 5)?;
+
+        // traces and simulations beyond this many wait instead of hitting the rpc at once
+        let max_concurrent_sims = get_optional_env("MAX_CONCURRENT_SIMS", 8)?;
+
+        // wrapped native token of the chain we run on, defaults to mainnet weth
+        let weth_address = env::var("WETH_ADDRESS")
+            .ok()
+            .map(|address| {
+                Address::from_str(address.trim())
+                    .map_err(|_| anyhow!("Failed to parse \"WETH_ADDRESS\""))
+            })
+            .transpose()?;
+
+        // dry run mode writes bundles to `dry_run_output` instead of sending them to relays
+        let mode = match &file_config {
+            Some(file_config) => file_config.mode,
+            None => get_optional_env("BOT_MODE", BotMode::Live)?,
+        };
+        let dry_run_output =
+            get_optional_env("DRY_RUN_OUTPUT", "dry-run-bundles.jsonl".to_string())?;
+
+        // bundles are simulated with `eth_callBundle` and dropped if a tx fails or tracing them
+        // shows they no longer beat `min_profit_threshold`
+        let simulate_bundles = get_optional_env("SIMULATE_BUNDLES", true)?;
+
+        // how often each relay's submission stats are logged (0 = never)
+        let relay_stats_interval_secs = get_optional_env("RELAY_STATS_INTERVAL_SECS", 300)?;
+
+        // bundles no relay accepted are broadcast as public txs, `backrun` only sends bundles
+        // without a frontrun and `sandwich` every bundle (both can be frontrun, off by default)
+        let public_fallback = env::var("PUBLIC_FALLBACK")
+            .ok()
+            .filter(|fallback| fallback.trim().to_lowercase() != "off")
+            .map(|fallback| fallback.trim().parse::<PublicFallback>())
+            .transpose()?;
+
+        // `json` emits one object per log line (for log aggregators), defaults to colored text
+        let log_format = match &file_config {
+            Some(file_config) => file_config.log_format,
+            None => get_optional_env("LOG_FORMAT", LogFormat::Text)?,
+        };
+        // log every rejected victim with its reason (they're always counted in `sando_rejections`)
+        let log_rejections = get_optional_env("LOG_REJECTIONS", false)?;
+
+        // `latest`, `pending` or a block number to trace and simulate on (pin for backtests)
+        let sim_state_source = get_optional_env("SIM_STATE_SOURCE", SimStateSource::Latest)?;
+
+        // drop bundles if the tip passed their state's block before they're submitted
+        let stale_state_guard = get_optional_env("STALE_STATE_GUARD", false)?;
+
+        // `trace_call`, `trace_callMany` or `debug_traceCall` (prestate tracer), whichever the rpc
+        // supports
+        let state_diff_method = get_optional_env("STATE_DIFF_METHOD", StateDiffMethod::TraceCall)?;
+
+        // `when_cheaper`, `always` or `never` attach the slots our txs touch as an access list
+        let access_list_mode = get_optional_env("ACCESS_LIST_MODE", AccessListMode::WhenCheaper)?;
+
+        // rebuild v2 swaps from storage reads of their pairs' reserves when tracing fails
+        let state_diff_storage_fallback = get_optional_env("STATE_DIFF_STORAGE_FALLBACK", false)?;
+
+        // `priority_fee` or `coinbase_transfer` (builder is paid straight from the sando contract)
+        let payment_strategy = get_optional_env("PAYMENT_STRATEGY", PaymentStrategy::PriorityFee)?;
+
+        // sell the sando contract's token dust into victims we can't sandwich (private txs)
+        let enable_private_backruns = get_optional_env("ENABLE_PRIVATE_BACKRUNS", false)?;
+
+        // arb victims' price impact against the token's other v2 pools (private txs)
+        let enable_backrun_arb = get_optional_env("ENABLE_BACKRUN_ARB", false)?;
+
+        // orderings of our txs around a victim to price (`sandwich`, `frontrun_only`,
+        // `backrun_only`), the most profitable one per pool is built
+        let ordering_strategies = env::var("ORDERING_STRATEGIES")
+            .unwrap_or("sandwich,backrun_only".to_string())
+            .split(',')
+            .map(|ordering| ordering.trim().parse::<OrderingStrategy>())
+            .collect::<Result<Vec<_>>>()?;
+
+        // tokens a frontrun only bundle keeps are priced this many bps below their exit quote
+        let frontrun_only_haircut_bps = get_optional_env("FRONTRUN_ONLY_HAIRCUT_BPS", 100)?;
+        ensure!(
+            frontrun_only_haircut_bps <= 10_000,
+            "\"FRONTRUN_ONLY_HAIRCUT_BPS\" must be at most 10000"
+        );
+
+        // evaluate pools with the best track record first when a victim touches several
+        let prioritize_profitable_pools = get_optional_env("PRIORITIZE_PROFITABLE_POOLS", false)?;
+
+        // sandwich same direction victims on a pool with one frontrun and backrun
+        let enable_bundle_merging = get_optional_env("ENABLE_BUNDLE_MERGING", false)?;
+
+        // `independent`, `net` (one bundle frontrunning the way victims trading a pool both ways
+        // move it together) or `skip` victims trading against a bundle already out on their pool
+        let opposing_victims = get_optional_env("OPPOSING_VICTIMS", OpposingVictims::Independent)?;
+
+        // drop victims with less than this many ms left before their target block
+        let min_time_budget_ms = get_optional_env("MIN_TIME_BUDGET_MS", 0)?;
+
+        // our txs' gas limit as a multiple of their simulated gas
+        let gas_limit_multiplier = get_optional_env("GAS_LIMIT_MULTIPLIER", 10.0 / 7.0)?;
+
+        // backruns ask for this many bps less than they simulate to get
+        let backrun_min_out_buffer_bps = get_optional_env("BACKRUN_MIN_OUT_BUFFER_BPS", 0)?;
+
+        // our bundle and its victim have to pay the predicted base fee plus this many bps
+        let base_fee_safety_margin_bps = get_optional_env("BASE_FEE_SAFETY_MARGIN_BPS", 0)?;
+        ensure!(
+            base_fee_safety_margin_bps <= MAX_BASE_FEE_SAFETY_MARGIN_BPS,
+            "\"BASE_FEE_SAFETY_MARGIN_BPS\" can't be above {}",
+            MAX_BASE_FEE_SAFETY_MARGIN_BPS
+        );
+
+        // log pools that move more than this many bps in a block (unset = off)
+        let pool_update_threshold_bps = env::var("POOL_UPDATE_THRESHOLD_BPS")
+            .ok()
+            .map(|bps| {
+                bps.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"POOL_UPDATE_THRESHOLD_BPS\""))
+            })
+            .transpose()?;
+
+        // keep cached pool spot prices current by applying every block's pool logs
+        let track_pool_prices = get_optional_env("TRACK_POOL_PRICES", false)?;
+
+        // victims on pools that moved more than this many bps lately are evaluated first
+        let hot_pool_change_bps = env::var("HOT_POOL_CHANGE_BPS")
+            .ok()
+            .map(|bps| {
+                bps.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"HOT_POOL_CHANGE_BPS\""))
+            })
+            .transpose()?;
+
+        // victims only on pools without logs for this many blocks are evaluated last
+        let cold_pool_after_blocks = env::var("COLD_POOL_AFTER_BLOCKS")
+            .ok()
+            .map(|blocks| {
+                blocks
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"COLD_POOL_AFTER_BLOCKS\""))
+            })
+            .transpose()?;
+
+        // re-read hot pools with a multicall every block to catch state drifted from missed logs
+        let refresh_hot_pools = get_optional_env("REFRESH_HOT_POOLS", false)?;
+
+        // stop sandwiching pools whose simulations disagree with our math this often (unset = off)
+        let max_pool_divergence_rate = env::var("MAX_POOL_DIVERGENCE_RATE")
+            .ok()
+            .map(|rate| {
+                rate.trim()
+                    .parse::<f64>()
+                    .map_err(|_| anyhow!("Failed to parse \"MAX_POOL_DIVERGENCE_RATE\""))
+            })
+            .transpose()?;
+        if let Some(rate) = max_pool_divergence_rate {
+            ensure!(
+                (0.0..=1.0).contains(&rate),
+                "\"MAX_POOL_DIVERGENCE_RATE\" must be between 0 and 1"
+            );
+        }
+
+        // skip v3 pools whose protocol fee is on or whose fee isn't their tier
+        let exclude_fee_switch_pools = get_optional_env("EXCLUDE_FEE_SWITCH_POOLS", false)?;
+
+        // simulate sandwiches netting at least this much (in wei) after the pending txs that
+        // outbid their victim (unset = off)
+        let preceding_txs_min_profit = env::var("PRECEDING_TXS_MIN_PROFIT")
+            .ok()
+            .map(|profit| {
+                profit
+                    .trim()
+                    .parse::<u128>()
+                    .map(U256::from)
+                    .map_err(|_| anyhow!("Failed to parse \"PRECEDING_TXS_MIN_PROFIT\""))
+            })
+            .transpose()?;
+
+        // `top_of_block` or `after_pending` (size and simulate every sandwich after the pending
+        // txs that outbid its victim)
+        let victim_position_assumption =
+            get_optional_env("VICTIM_POSITION_ASSUMPTION", VictimPosition::TopOfBlock)?;
+
+        // `round_robin` or `random` (drawn from the seeded rng)
+        let sando_rotation = get_optional_env("SANDO_ROTATION", SandoRotation::RoundRobin)?;
+        // optionally pin the seed of every random choice to replay a run's decisions
+        let rng_seed = env::var("RNG_SEED")
+            .ok()
+            .map(|seed| {
+                seed.trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"RNG_SEED\""))
+            })
+            .transpose()?;
+
+        let sando_gas_profiles = read_gas_profiles_env(&sando_addresses)?;
 
         let discord_webhook = get_env("DISCORD_WEBHOOK")?;
 
         Ok(Self {
             searcher_signer,
             sando_inception_block,
-            sando_address,
+            sando_addresses,
+            sando_gas_profiles,
             bundle_signer,
-            wss_rpc,
+            rpc_endpoints,
+            mempool_endpoints,
+            rpc_timeout_ms,
+            rpc_breaker_error_rate,
+            rpc_breaker_window_secs,
+            rpc_breaker_cooldown_secs,
+            rpc_breaker_min_requests,
+            rpc_batch_window_ms,
+            relay_urls,
+            bloxroute_auth_header,
+            bloxroute_url,
+            bid_fraction,
+            competition_level,
+            min_tip,
+            max_tip,
+            bid_escalation_step,
+            max_bid_fraction,
+            metrics_port,
+            health_stale_after_secs,
+            metrics_snapshot_path,
+            opportunity_log_path,
+            alert_webhook,
+            max_window_loss,
+            min_weth_inventory,
+            min_searcher_balance,
+            loss_window_blocks,
+            loss_cooldown_blocks,
+            victim_max_misses,
+            victim_cooldown_blocks,
+            min_victim_swap_value,
+            min_pool_liquidity,
+            known_searchers,
+            pool_whitelist,
+            stable_tokens,
+            rebasing_tokens,
+            aggregator_routers,
+            rebase_sync_strikes,
+            max_victim_priority_fee,
+            max_victim_tip_bps,
+            max_position_weth,
+            max_frontrun_price_impact_bps,
+            tx_batch_window_ms,
+            max_bundles_per_block,
+            rank_bundles_ms_before_block,
+            max_bundle_gas_per_block,
+            max_block_gas_fraction,
+            victim_timeout_ms,
+            max_sim_gas,
+            sync_chunk_blocks,
+            sync_concurrency,
+            target_block_offsets,
+            inclusion_check_blocks,
+            min_replacement_gain_bps,
+            parked_tx_ttl_blocks,
+            max_concurrent_sims,
+            weth_address,
+            mode,
+            dry_run_output,
+            simulate_bundles,
+            relay_stats_interval_secs,
+            public_fallback,
+            log_format,
+            log_rejections,
+            sim_state_source,
+            stale_state_guard,
+            state_diff_method,
+            access_list_mode,
+            state_diff_storage_fallback,
+            payment_strategy,
+            enable_private_backruns,
+            enable_backrun_arb,
+            ordering_strategies,
+            frontrun_only_haircut_bps,
+            prioritize_profitable_pools,
+            enable_bundle_merging,
+            opposing_victims,
+            min_time_budget_ms,
+            gas_limit_multiplier,
+            backrun_min_out_buffer_bps,
+            base_fee_safety_margin_bps,
+            pool_update_threshold_bps,
+            track_pool_prices,
+            hot_pool_change_bps,
+            cold_pool_after_blocks,
+            refresh_hot_pools,
+            max_pool_divergence_rate,
+            exclude_fee_switch_pools,
+            preceding_txs_min_profit,
+            victim_position_assumption,
+            sando_rotation,
+            rng_seed,
             discord_webhook,
+            file_config,
+            variant_configs,
         })
     }
 }
+
+impl Config {
+    /// Circuit breaker on the rpc error rate, shared by the transport and the bots
+    pub fn rpc_circuit_breaker(&self) -> CircuitBreaker {
+        CircuitBreaker::new(
+            Duration::from_secs(self.rpc_breaker_window_secs),
+            self.rpc_breaker_error_rate,
+            Duration::from_secs(self.rpc_breaker_cooldown_secs),
+        )
+        .with_min_requests(self.rpc_breaker_min_requests)
+    }
+
+    /// Strategy config with the tunables read from env (shared by the bot and the backtester)
+    pub fn strat_config(&self) -> StratConfig {
+        if let Some(file_config) = &self.file_config {
+            return file_config.clone();
+        }
+
+        let mut configs = StratConfig::new(
+            self.sando_addresses[0],
+            self.sando_inception_block,
+            self.searcher_signer.clone(),
+        );
+        configs.sando_addresses = self.sando_addresses.clone();
+        configs.sando_gas_profiles = self.sando_gas_profiles.clone();
+        configs.bid_fraction = self.bid_fraction;
+        configs.competition_level = self.competition_level;
+        configs.min_tip = self.min_tip;
+        configs.max_tip = self.max_tip;
+        configs.bid_escalation_step = self.bid_escalation_step;
+        configs.max_bid_fraction = self.max_bid_fraction;
+        configs.metrics_port = self.metrics_port;
+        configs.health_stale_after_secs = self.health_stale_after_secs;
+        configs.metrics_snapshot_path = self.metrics_snapshot_path.clone();
+        configs.opportunity_log_path = self.opportunity_log_path.clone();
+        configs.alert_webhook = self.alert_webhook.clone();
+        configs.max_window_loss = self.max_window_loss;
+        configs.min_weth_inventory = self.min_weth_inventory;
+        configs.min_searcher_balance = self.min_searcher_balance;
+        configs.loss_window_blocks = self.loss_window_blocks;
+        configs.loss_cooldown_blocks = self.loss_cooldown_blocks;
+        configs.victim_max_misses = self.victim_max_misses;
+        configs.victim_cooldown_blocks = self.victim_cooldown_blocks;
+        configs.min_victim_swap_value = self.min_victim_swap_value;
+        configs.min_pool_liquidity = self.min_pool_liquidity;
+        configs.known_searchers = self.known_searchers.clone();
+        configs.pool_whitelist = self.pool_whitelist.clone();
+        if let Some(stable_tokens) = &self.stable_tokens {
+            configs.stable_tokens = stable_tokens.clone();
+        }
+        if let Some(rebasing_tokens) = &self.rebasing_tokens {
+            configs.rebasing_tokens = rebasing_tokens.clone();
+        }
+        if let Some(aggregator_routers) = &self.aggregator_routers {
+            configs.aggregator_routers = aggregator_routers.clone();
+        }
+        configs.rebase_sync_strikes = self.rebase_sync_strikes;
+        configs.max_victim_priority_fee = self.max_victim_priority_fee;
+        configs.max_victim_tip_bps = self.max_victim_tip_bps;
+        configs.max_position_weth = self.max_position_weth;
+        configs.max_frontrun_price_impact_bps = self.max_frontrun_price_impact_bps;
+        configs.max_bundles_per_block = self.max_bundles_per_block;
+        configs.rank_bundles_ms_before_block = self.rank_bundles_ms_before_block;
+        configs.max_bundle_gas_per_block = self.max_bundle_gas_per_block;
+        configs.max_block_gas_fraction = self.max_block_gas_fraction;
+        configs.victim_timeout_ms = self.victim_timeout_ms;
+        configs.max_sim_gas = self.max_sim_gas;
+        configs.sync_chunk_blocks = self.sync_chunk_blocks;
+        configs.sync_concurrency = self.sync_concurrency;
+        configs.target_block_offsets = self.target_block_offsets.clone();
+        configs.inclusion_check_blocks = self.inclusion_check_blocks;
+        configs.min_replacement_gain_bps = self.min_replacement_gain_bps;
+        configs.parked_tx_ttl_blocks = self.parked_tx_ttl_blocks;
+        configs.max_concurrent_sims = self.max_concurrent_sims;
+        if let Some(weth_address) = self.weth_address {
+            configs.weth_address = weth_address;
+        }
+        configs.mode = self.mode;
+        configs.log_format = self.log_format;
+        configs.sim_state_source = self.sim_state_source;
+        configs.stale_state_guard = self.stale_state_guard;
+        configs.state_diff_method = self.state_diff_method;
+        configs.access_list_mode = self.access_list_mode;
+        configs.state_diff_storage_fallback = self.state_diff_storage_fallback;
+        configs.payment_strategy = self.payment_strategy;
+        configs.enable_private_backruns = self.enable_private_backruns;
+        configs.enable_backrun_arb = self.enable_backrun_arb;
+        configs.ordering_strategies = self.ordering_strategies.clone();
+        configs.frontrun_only_haircut_bps = self.frontrun_only_haircut_bps;
+        configs.prioritize_profitable_pools = self.prioritize_profitable_pools;
+        configs.enable_bundle_merging = self.enable_bundle_merging;
+        configs.opposing_victims = self.opposing_victims;
+        configs.min_time_budget_ms = self.min_time_budget_ms;
+        configs.gas_limit_multiplier = self.gas_limit_multiplier;
+        configs.backrun_min_out_buffer_bps = self.backrun_min_out_buffer_bps;
+        configs.base_fee_safety_margin_bps = self.base_fee_safety_margin_bps;
+        configs.pool_update_threshold_bps = self.pool_update_threshold_bps;
+        configs.track_pool_prices = self.track_pool_prices;
+        configs.hot_pool_change_bps = self.hot_pool_change_bps;
+        configs.cold_pool_after_blocks = self.cold_pool_after_blocks;
+        configs.refresh_hot_pools = self.refresh_hot_pools;
+        configs.max_pool_divergence_rate = self.max_pool_divergence_rate;
+        configs.exclude_fee_switch_pools = self.exclude_fee_switch_pools;
+        configs.preceding_txs_min_profit = self.preceding_txs_min_profit;
+        configs.victim_position_assumption = self.victim_position_assumption;
+        configs.sando_rotation = self.sando_rotation;
+        configs.rng_seed = self.rng_seed;
+        configs
+    }
+}
+
+/// Sando contracts from env (when no config file is given)
+fn read_sando_env() -> Result<(U64, Vec<Address>)> {
+    let get_env = |var| {
+        env::var(var).map_err(|_| anyhow!("Required environment variable \"{}\" not set", var))
+    };
+
+    let sando_inception_block = get_env("SANDWICH_INCEPTION_BLOCK")?
+        .parse::<u64>()
+        .map(U64::from)
+        .map_err(|_| anyhow!("Failed to parse \"SANDWICH_INCEPTION_BLOCK\" into u64"))?;
+
+    // comma separated list of sando contracts to rotate between (a single address also works)
+    let sando_addresses = get_env("SANDWICH_CONTRACT")?
+        .split(',')
+        .map(|address| {
+            Address::from_str(address.trim())
+                .map_err(|_| anyhow!("Failed to parse \"SANDWICH_CONTRACT\" entry {}", address))
+        })
+        .collect::<Result<Vec<Address>>>()?;
+
+    Ok((sando_inception_block, sando_addresses))
+}
+
+/// Gas profiles of sando contracts that differ from the default estimates (unset = none), as
+/// comma separated `address:v2_frontrun:v2_backrun:v3_frontrun:v3_backrun` entries
+fn read_gas_profiles_env(sando_addresses: &[Address]) -> Result<HashMap<Address, SandoGasProfile>> {
+    let profiles = match env::var("SANDO_GAS_PROFILES") {
+        Ok(profiles) => profiles,
+        Err(_) => return Ok(HashMap::new()),
+    };
+
+    let mut sando_gas_profiles = HashMap::new();
+    for entry in profiles.split(',') {
+        let parse_err = || anyhow!("Failed to parse \"SANDO_GAS_PROFILES\" entry {}", entry);
+        let fields: Vec<&str> = entry.trim().split(':').collect();
+        ensure!(fields.len() == 5, parse_err());
+        let address = Address::from_str(fields[0]).map_err(|_| parse_err())?;
+        let gas = fields[1..]
+            .iter()
+            .map(|gas| gas.parse::<u64>().map_err(|_| parse_err()))
+            .collect::<Result<Vec<u64>>>()?;
+        ensure!(
+            sando_addresses.contains(&address),
+            "\"SANDO_GAS_PROFILES\" has a profile for {:?} which isn't a sando contract",
+            address
+        );
+        sando_gas_profiles.insert(
+            address,
+            SandoGasProfile {
+                v2_frontrun: gas[0],
+                v2_backrun: gas[1],
+                v3_frontrun: gas[2],
+                v3_backrun: gas[3],
+            },
+        );
+    }
+
+    Ok(sando_gas_profiles)
+}
+
+/// Read an optional environment variable, falling back to `default` if it is not set
+fn get_optional_env<T: FromStr>(var: &str, default: T) -> Result<T> {
+    match env::var(var) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| anyhow!("Failed to parse \"{}\"", var)),
+        Err(_) => Ok(default),
+    }
+}
+
+fn get_optional_address_set_env(var: &str) -> Result<Option<HashSet<Address>>> {
+    match env::var(var) {
+        Ok(addresses) => addresses
+            .split(',')
+            .filter(|address| !address.trim().is_empty())
+            .map(|address| {
+                Address::from_str(address.trim())
+                    .map_err(|_| anyhow!("Failed to parse \"{}\" entry {}", var, address))
+            })
+            .collect::<Result<HashSet<Address>>>()
+            .map(Some),
+        Err(_) => Ok(None),
+    }
+}
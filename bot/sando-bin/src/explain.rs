@@ -0,0 +1,141 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use artemis_core::types::Strategy;
+use ethers::{
+    providers::Middleware,
+    types::{TxHash, U64},
+};
+use log::info;
+use strategy::{
+    bot::SandoBot,
+    managers::opportunity_log::OpportunityRecord,
+    types::{BlockInfo, BotMode, DryRunRecord, Event, StratConfig, VictimInfo},
+};
+
+/// Tx (and optionally the block it is evaluated for) given to the `explain` subcommand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainArgs {
+    pub tx_hash: TxHash,
+    /// Block the sandwich would have targeted, defaults to the block the tx was mined in
+    pub block: Option<u64>,
+}
+
+/// Parse the `explain` subcommand's args (`--tx <hash> [--block <number>]`)
+pub fn parse_explain_args(args: &[String]) -> Result<ExplainArgs> {
+    let usage = || anyhow!("Usage: explain --tx <hash> [--block <number>]");
+
+    let mut tx_hash = None;
+    let mut block = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tx" => {
+                let value = args.next().ok_or_else(usage)?;
+                tx_hash = Some(
+                    value
+                        .parse()
+                        .map_err(|_| anyhow!("Failed to parse tx hash {}", value))?,
+                );
+            }
+            "--block" => {
+                let value = args.next().ok_or_else(usage)?;
+                block = Some(
+                    value
+                        .parse()
+                        .map_err(|_| anyhow!("Failed to parse block number {}", value))?,
+                );
+            }
+            _ => return Err(anyhow!("Unknown explain argument {}", arg)),
+        }
+    }
+
+    Ok(ExplainArgs {
+        tx_hash: tx_hash.ok_or_else(usage)?,
+        block,
+    })
+}
+
+/// Re-evaluate a single historical tx as if it was pending right before `args.block`, logging
+/// every decision the bot makes along the way (nothing is submitted)
+/// note: state is pinned to the block before, so the provider must be an archive node
+pub async fn run_explain<M: Middleware + 'static>(
+    provider: Arc<M>,
+    mut configs: StratConfig,
+    args: ExplainArgs,
+) -> Result<()> {
+    let tx = provider
+        .get_transaction(args.tx_hash)
+        .await
+        .map_err(|e| anyhow!("Failed to get tx {:?}: {}", args.tx_hash, e))?
+        .ok_or(anyhow!("Tx {:?} not found", args.tx_hash))?;
+
+    let target_block = match (args.block, tx.block_number) {
+        (Some(block), _) => U64::from(block),
+        (None, Some(block)) => block,
+        (None, None) => return Err(anyhow!("Tx is still pending, pass --block")),
+    };
+    let parent_block: BlockInfo = provider
+        .get_block(target_block - 1)
+        .await
+        .map_err(|e| anyhow!("Failed to get block {}: {}", target_block - 1, e))?
+        .ok_or(anyhow!("Block {} not found", target_block - 1))?
+        .try_into()?;
+
+    info!("==================== explain ====================");
+    info!("tx               : {:?}", tx.hash);
+    info!("target block     : {}", target_block);
+    info!("state pinned at  : {}", parent_block.number);
+
+    // Never submit anything while explaining
+    configs.mode = BotMode::DryRun;
+
+    let records = Arc::new(Mutex::new(vec![]));
+    let bot_records = records.clone();
+    let mut bot = SandoBot::new(provider, configs).on_opportunity(move |record| {
+        bot_records.lock().unwrap().push(record.clone());
+    });
+    bot.sync_state().await?;
+    bot.pin_to_block(parent_block);
+
+    // logged once the pools are synced, so amounts can be shown in whole tokens
+    match VictimInfo::new(tx.clone()).decode_swap() {
+        Some(swap) => {
+            let token_in = swap.path.first().copied().unwrap_or_default();
+            let token_out = swap.path.last().copied().unwrap_or_default();
+            info!("swap path        : {:?}", swap.path);
+            info!("v3 fees          : {:?}", swap.fees);
+            info!(
+                "amount in        : {}",
+                bot.display_amount(token_in, swap.amount_in)
+            );
+            info!(
+                "amount out min   : {}",
+                bot.display_amount(token_out, swap.amount_out_min)
+            );
+            info!("exact output     : {}", swap.exact_output);
+        }
+        None => info!("swap             : calldata isn't a router swap we decode"),
+    }
+
+    let action = bot.process_event(Event::NewTransaction(tx)).await;
+
+    info!("==================== decisions ====================");
+    let records: Vec<OpportunityRecord> = records.lock().unwrap().drain(..).collect();
+    if records.is_empty() {
+        info!("no opportunity was sized (see the logs above for why)");
+    }
+    for record in records {
+        info!("{}", serde_json::to_string(&record)?);
+    }
+    match action {
+        Some(action) => info!(
+            "would submit     : {}",
+            serde_json::to_string_pretty(&DryRunRecord::from(action))?
+        ),
+        None => info!("would submit     : nothing"),
+    }
+
+    Ok(())
+}
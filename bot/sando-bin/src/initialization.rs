@@ -1,8 +1,12 @@
+use std::fmt;
+
 use anyhow::Result;
 use colored::Colorize;
 use fern::colors::{Color, ColoredLevelConfig};
 use indoc::indoc;
 use log::LevelFilter;
+use serde_json::{Map, Value};
+use strategy::types::LogFormat;
 
 pub fn print_banner() {
     let banner = indoc! {
@@ -26,7 +30,14 @@ ______ __   __     _____       ___  ___ _____  _   _  _____  _____  _      _____
     log::info!("{}", format!("{}", banner.green().bold()));
 }
 
-pub fn setup_logger() -> Result<()> {
+/// Log at info level (rejected victims at debug level under the `strategy::rejections` target
+/// if `log_rejections` is set)
+pub fn setup_logger(log_format: LogFormat, log_rejections: bool) -> Result<()> {
+    let rejections_level = match log_rejections {
+        true => LevelFilter::Debug,
+        false => LevelFilter::Info,
+    };
+
     let colors = ColoredLevelConfig {
         trace: Color::Cyan,
         debug: Color::Magenta,
@@ -37,21 +48,46 @@ pub fn setup_logger() -> Result<()> {
     };
 
     fern::Dispatch::new()
-        .format(move |out, message, record| {
-            out.finish(format_args!(
+        .format(move |out, message, record| match log_format {
+            LogFormat::Text => out.finish(format_args!(
                 "{}[{}] {}",
                 chrono::Local::now().format("[%H:%M:%S]"),
                 colors.color(record.level()),
                 message
-            ))
+            )),
+            LogFormat::Json => out.finish(format_args!("{}", json_line(message, record))),
         })
         .chain(std::io::stdout())
         .chain(fern::log_file("output.log")?)
         .level(log::LevelFilter::Error)
         .level_for("rusty_sando", LevelFilter::Info)
         .level_for("strategy", LevelFilter::Info)
+        .level_for("strategy::rejections", rejections_level)
         .level_for("artemis_core", LevelFilter::Info)
         .apply()?;
 
     Ok(())
 }
+
+/// Wrap a log record as a json object, messages that are already json (from the strategy's
+/// logging macros) keep their fields
+fn json_line(message: &fmt::Arguments, record: &log::Record) -> String {
+    let message = message.to_string();
+    let mut line = match serde_json::from_str::<Value>(&message) {
+        Ok(Value::Object(fields)) => fields,
+        _ => {
+            let mut fields = Map::new();
+            fields.insert("message".to_string(), message.into());
+            fields
+        }
+    };
+
+    line.insert(
+        "timestamp".to_string(),
+        chrono::Utc::now().to_rfc3339().into(),
+    );
+    line.insert("level".to_string(), record.level().as_str().into());
+    line.insert("target".to_string(), record.target().into());
+
+    Value::Object(line).to_string()
+}
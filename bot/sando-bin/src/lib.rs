@@ -1,2 +1,5 @@
+pub mod backtest;
 pub mod config;
+pub mod explain;
 pub mod initialization;
+pub mod pool_sync;
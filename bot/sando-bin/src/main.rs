@@ -1,38 +1,106 @@
-use std::sync::Arc;
+use std::{collections::HashSet, env, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use artemis_core::{
-    collectors::{block_collector::BlockCollector, mempool_collector::MempoolCollector},
+    collectors::{
+        batching_collector::BatchingCollector, block_collector::BlockCollector,
+        mempool_collector::MempoolCollector,
+    },
     engine::Engine,
-    executors::flashbots_executor::FlashbotsExecutor,
-    types::{CollectorMap, ExecutorMap},
+    executors::{
+        bundle_profit::TracedBundleProfit,
+        bundle_relay::BloxrouteRelay,
+        dry_run_executor::DryRunExecutor,
+        flashbots_executor::FlashbotsExecutor,
+        private_tx_executor::{PrivateTx, PrivateTxExecutor},
+        public_mempool::PublicMempool,
+    },
+    types::{CollectorMap, Executor, ExecutorMap},
+    utilities::{batching_middleware::BatchingMiddleware, failover_ws::FailoverWs},
 };
-use ethers::providers::{Provider, Ws};
+use ethers::{providers::Provider, signers::Signer};
 use log::info;
-use reqwest::Url;
 use rusty_sando::{
     config::Config,
+    explain::{parse_explain_args, run_explain},
     initialization::{print_banner, setup_logger},
+    pool_sync::{parse_sync_args, run_pool_sync},
 };
 use strategy::{
     bot::SandoBot,
-    types::{Action, Event, StratConfig},
+    types::{Action, BotMode, DryRunRecord, Event, LogFormat, StratConfig},
 };
+use tokio::signal::unix::{signal, SignalKind};
+
+/// Times the websocket transport tries to reconnect before giving up
+const WS_RECONNECTS: usize = 5;
+
+/// Most pending txs grouped into one event when batching is on
+const MAX_TX_BATCH_SIZE: usize = 64;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Setup
-    setup_logger()?;
-    print_banner();
     let config = Config::read_from_dotenv().await?;
+    setup_logger(config.log_format, config.log_rejections)?;
+    if config.log_format == LogFormat::Text {
+        print_banner();
+    }
+
+    // Setup ethers provider (transports reconnect on their own and fail over to the backup
+    // endpoints, collectors resubscribe on top), bots pause while most requests to it fail
+    let rpc_breaker = Arc::new(config.rpc_circuit_breaker());
+    let ws = FailoverWs::connect(
+        &config.rpc_endpoints,
+        WS_RECONNECTS,
+        Duration::from_millis(config.rpc_timeout_ms),
+    )
+    .await?
+    .with_circuit_breaker(rpc_breaker.clone());
 
-    // Setup ethers provider
-    let ws = Ws::connect(config.wss_rpc).await?;
+    // the bots' state reads go through a middleware that batches them if a window is set,
+    // subscriptions and submissions stay on `provider`
+    let mut state_provider = BatchingMiddleware::new(Provider::new(ws.clone()));
+    if let Some(window_ms) = config.rpc_batch_window_ms {
+        state_provider = state_provider.with_window(Duration::from_millis(window_ms));
+    }
+    let state_provider = Arc::new(state_provider);
     let provider = Arc::new(Provider::new(ws));
 
-    // Setup signers
-    let flashbots_signer = config.bundle_signer;
-    let searcher_signer = config.searcher_signer;
+    // pending txs can come from a separate (faster) mempool feed, state reads stay on `WSS_RPC`
+    let mempool_provider = match &config.mempool_endpoints {
+        Some(mempool_endpoints) => {
+            let ws = FailoverWs::connect(
+                mempool_endpoints,
+                WS_RECONNECTS,
+                Duration::from_millis(config.rpc_timeout_ms),
+            )
+            .await?;
+            info!(
+                "Reading pending txs from {} mempool endpoints",
+                mempool_endpoints.len()
+            );
+            Arc::new(Provider::new(ws))
+        }
+        None => provider.clone(),
+    };
+
+    // `sync [--out <pools.json>]` only discovers pools of the configured dexes and writes them
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("sync") {
+        let out = parse_sync_args(&args[1..])?;
+        let mut bot = SandoBot::new(state_provider, config.strat_config());
+        return run_pool_sync(&mut bot, &out).await;
+    }
+
+    // `explain --tx <hash> [--block <number>]` replays one historical tx and logs every decision
+    if args.first().map(String::as_str) == Some("explain") {
+        let explain_args = parse_explain_args(&args[1..])?;
+        return run_explain(state_provider, config.strat_config(), explain_args).await;
+    }
+
+    // Setup signer used to authenticate with relays
+    let flashbots_signer = config.bundle_signer.clone();
 
     // Create engine
     let mut engine: Engine<Event, Action> = Engine::default();
@@ -42,37 +110,189 @@ async fn main() -> Result<()> {
     let block_collector = CollectorMap::new(block_collector, Event::NewBlock);
     engine.add_collector(Box::new(block_collector));
 
-    // Setup mempool collector
-    let mempool_collector = Box::new(MempoolCollector::new(provider.clone()));
-    let mempool_collector = CollectorMap::new(mempool_collector, Event::NewTransaction);
-    engine.add_collector(Box::new(mempool_collector));
+    // Setup mempool collector (batching pending txs on busy mempools if configured)
+    let mempool_collector = Box::new(MempoolCollector::new(mempool_provider));
+    match config.tx_batch_window_ms {
+        Some(window_ms) => {
+            let mempool_collector = BatchingCollector::new(
+                mempool_collector,
+                Duration::from_millis(window_ms),
+                MAX_TX_BATCH_SIZE,
+            );
+            let mempool_collector =
+                CollectorMap::new(Box::new(mempool_collector), Event::NewTransactions);
+            engine.add_collector(Box::new(mempool_collector));
+        }
+        None => {
+            let mempool_collector = CollectorMap::new(mempool_collector, Event::NewTransaction);
+            engine.add_collector(Box::new(mempool_collector));
+        }
+    }
 
     // Setup strategy
-    let configs = StratConfig {
-        sando_address: config.sando_address,
-        sando_inception_block: config.sando_inception_block,
-        searcher_signer,
-    };
-    let strategy = SandoBot::new(provider.clone(), configs);
+    let strategy = SandoBot::new(state_provider.clone(), config.strat_config())
+        .with_circuit_breaker(rpc_breaker.clone());
+    // variants are added after the main strategy, which syncs the pools they share
+    let variants: Vec<SandoBot<_>> = config
+        .variant_configs
+        .iter()
+        .map(|variant| {
+            SandoBot::new(state_provider.clone(), variant.clone())
+                .sharing_pools_of(&strategy)
+                .with_circuit_breaker(rpc_breaker.clone())
+        })
+        .collect();
     engine.add_strategy(Box::new(strategy));
+    for variant in variants {
+        engine.add_strategy(Box::new(variant));
+    }
+
+    match config.mode {
+        BotMode::Live => {
+            // Setup flashbots executor
+            let mut executor = FlashbotsExecutor::new(
+                provider.clone(),
+                flashbots_signer.clone(),
+                config.relay_urls.clone(),
+            );
+            if let Some(auth_header) = &config.bloxroute_auth_header {
+                executor = executor.with_relay(BloxrouteRelay::new(
+                    config.bloxroute_url.clone(),
+                    auth_header.clone(),
+                ));
+            }
+            if config.simulate_bundles {
+                // our weth and eth are spread over every strategy's searcher and sando contracts,
+                // a bundle is kept while it beats the lowest min profit of the strategies
+                let strat_configs: Vec<StratConfig> = std::iter::once(config.strat_config())
+                    .chain(config.variant_configs.iter().cloned())
+                    .collect();
+                let min_profit = strat_configs
+                    .iter()
+                    .map(|strat_config| strat_config.min_profit_threshold)
+                    .min()
+                    .unwrap_or_default();
+                let accounts: HashSet<_> = strat_configs
+                    .iter()
+                    .flat_map(|strat_config| {
+                        let searcher = strat_config.searcher_signer.address();
+                        strat_config
+                            .sando_addresses
+                            .iter()
+                            .copied()
+                            .chain(std::iter::once(searcher))
+                    })
+                    .collect();
+                executor = executor.with_simulation(
+                    min_profit,
+                    TracedBundleProfit::new(
+                        provider.clone(),
+                        strat_configs[0].weth_address,
+                        accounts.into_iter().collect(),
+                    ),
+                );
+            }
+            if let Some(public_fallback) = config.public_fallback {
+                // only our own txs are broadcast (re-signed), a bundle's other txs are its victims
+                let searchers = std::iter::once(&config.searcher_signer)
+                    .chain(
+                        config
+                            .variant_configs
+                            .iter()
+                            .map(|variant| &variant.searcher_signer),
+                    )
+                    .cloned()
+                    .collect();
+                executor = executor.with_public_fallback(PublicMempool::new(
+                    provider.clone(),
+                    public_fallback,
+                    searchers,
+                ));
+            }
+            if config.relay_stats_interval_secs > 0 {
+                executor = executor
+                    .with_stats_summary(Duration::from_secs(config.relay_stats_interval_secs));
+            }
+            let executor = Box::new(executor);
+            let executor = ExecutorMap::new(executor, |action| match action {
+                // each bundle goes out once per block it targets
+                Action::SubmitBundle {
+                    bundles,
+                    target_blocks,
+                    ..
+                } => Some(
+                    bundles
+                        .into_iter()
+                        .zip(target_blocks)
+                        .flat_map(|(bundle, target_blocks)| {
+                            target_blocks
+                                .into_iter()
+                                .map(move |block| bundle.clone().set_block(block))
+                        })
+                        .collect(),
+                ),
+                Action::SubmitPrivateTx { .. } => None,
+            });
+            engine.add_executor(Box::new(executor));
+
+            // Setup private tx executor (backruns and arbs without a frontrun)
+            let executor = Box::new(PrivateTxExecutor::new(flashbots_signer, config.relay_urls));
+            let executor = ExecutorMap::new(executor, |action| match action {
+                Action::SubmitPrivateTx {
+                    txs, target_block, ..
+                } => Some(PrivateTx {
+                    txs,
+                    max_block_number: target_block,
+                }),
+                Action::SubmitBundle { .. } => None,
+            });
+            engine.add_executor(Box::new(executor));
+        }
+        BotMode::DryRun => {
+            // Record bundles instead of sending them to relays
+            let executor: Box<dyn Executor<DryRunRecord>> =
+                Box::new(DryRunExecutor::new(config.dry_run_output));
+            let executor = ExecutorMap::new(executor, |action| Some(DryRunRecord::from(action)));
+            engine.add_executor(Box::new(executor));
+        }
+    }
 
-    // Setup flashbots executor
-    let executor = Box::new(FlashbotsExecutor::new(
-        provider.clone(),
-        flashbots_signer,
-        Url::parse("https://relay.flashbots.net")?,
-    ));
-    let executor = ExecutorMap::new(executor, |action| match action {
-        Action::SubmitToFlashbots(bundle) => Some(bundle),
-    });
-    engine.add_executor(Box::new(executor));
-
-    // Start engine
+    // Start engine (on SIGINT/SIGTERM stop taking events and let the strategy persist its state)
+    let shutdown_sender = engine.shutdown_sender();
     if let Ok(mut set) = engine.run().await {
-        while let Some(res) = set.join_next().await {
-            info!("res: {:?}", res)
+        let mut shutting_down = false;
+        loop {
+            tokio::select! {
+                res = set.join_next() => match res {
+                    Some(res) => info!("res: {:?}", res),
+                    None => break,
+                },
+                _ = shutdown_signal(), if !shutting_down => {
+                    info!("shutdown signal received, stopping engine...");
+                    shutting_down = true;
+                    let _ = shutdown_sender.send(());
+                }
+            }
         }
     }
 
+    info!("clean exit");
     Ok(())
 }
+
+/// Resolves once the process receives SIGINT (ctrl-c) or SIGTERM
+async fn shutdown_signal() {
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(_) => {
+            // only listen for ctrl-c if the SIGTERM handler can't be installed
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = sigterm.recv() => {}
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use log::info;
+use strategy::bot::SandoBot;
+
+/// Where `sync` writes pools if `--out` isn't given
+const DEFAULT_SYNC_OUT: &str = "pools.json";
+
+/// Parse the `sync` subcommand's args (`[--out <path>]`), returns the path to write pools to
+pub fn parse_sync_args(args: &[String]) -> Result<String> {
+    let mut out = DEFAULT_SYNC_OUT.to_string();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => {
+                out = args
+                    .next()
+                    .ok_or(anyhow!("Usage: sync --out <pools.json>"))?
+                    .clone()
+            }
+            _ => return Err(anyhow!("Unknown sync argument {}", arg)),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Discover every pool of the configured dexes and write them to `out`, logging how many pools
+/// each dex has (the bot itself isn't started)
+pub async fn run_pool_sync<M: Middleware + 'static>(
+    bot: &mut SandoBot<M>,
+    out: &str,
+) -> Result<()> {
+    let synced = bot.sync_pools_to(out).await?;
+
+    info!("==================== pool sync summary ===================");
+    for (dex, pool_count) in &synced {
+        info!("{:?} {:?}: {} pools", dex.kind, dex.factory, pool_count);
+    }
+    info!(
+        "total: {} pools written to {}",
+        synced
+            .iter()
+            .map(|(_, pool_count)| pool_count)
+            .sum::<usize>(),
+        out
+    );
+
+    Ok(())
+}